@@ -0,0 +1,222 @@
+/*!
+ * Adaptive Concurrency (AIMD)
+ *
+ * More workers only help until the backend is saturated; past that point
+ * every extra request just waits in its queue, and latency climbs with no
+ * gain in throughput. Where that point lies changes while the service
+ * runs - a slow disk, a neighbour hogging the CPU - so no fixed worker
+ * count is right for long. An Aimd controller finds it from the outside,
+ * the way TCP congestion control finds a link's capacity:
+ *
+ *   latency at or under target   one more worker (additive increase)
+ *   latency over target          halve the workers (multiplicative decrease)
+ *
+ * The result is a sawtooth just under the knee, which backs off within a
+ * window when the backend slows down and climbs back when it recovers.
+ *
+ * The backend is a model, like MockClock: its latency is computed from
+ * the requests in flight, so every run shows the same plot. The workers
+ * are real threads, started through spawn_policy on the serve stage of a
+ * phase::Pipeline.
+ */
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::phase::Pipeline;
+use crate::spawn_policy;
+use crate::static_check;
+
+pub const WINDOWS: usize = static_check::nonzero(60);
+pub const MAX_WORKERS: usize = static_check::nonzero(16);
+pub const TARGET: Duration = Duration::from_millis(25);
+// Windows [from, until) in which the backend is degraded
+pub const SLOWDOWNS: [(usize, usize); 2] = [(18, 28), (42, 48)];
+
+const WINDOW: Duration = Duration::from_millis(100);  // how long each window lasts
+const START_WORKERS: usize = static_check::nonzero(4);
+const PLOT_ROWS: usize = static_check::nonzero(8);
+const LATENCY_SCALE: f64 = 4.0;  // latency plot height, in targets
+
+#[derive(Debug, Clone)]
+pub struct Aimd {
+    limit: f64,
+    min: usize,
+    max: usize,
+    target: Duration,
+    increase: f64,
+    decrease: f64,
+}
+
+impl Aimd {
+    // Start at `start` workers; +1 per good window, x0.5 per bad one
+    pub fn new(start: usize, min: usize, max: usize, target: Duration) -> Self {
+        Aimd {
+            limit: start.clamp(min, max) as f64,
+            min,
+            max,
+            target,
+            increase: 1.0,
+            decrease: 0.5,
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit as usize
+    }
+
+    // Adjust to one window's latency and return the new limit
+    pub fn observe(&mut self, latency: Duration) -> usize {
+        self.limit = if latency > self.target {
+            (self.limit * self.decrease).max(self.min as f64)
+        } else {
+            (self.limit + self.increase).min(self.max as f64)
+        };
+        self.limit()
+    }
+}
+
+// A service with `capacity` requests in progress at once; the rest queue
+#[derive(Debug, Clone, Copy)]
+pub struct Backend {
+    pub capacity: usize,
+    pub service: Duration,  // one request, without queueing
+}
+
+pub const HEALTHY: Backend = Backend { capacity: 6, service: Duration::from_millis(10) };
+pub const DEGRADED: Backend = Backend { capacity: 2, service: Duration::from_millis(20) };
+
+impl Backend {
+    // Latency of each request while `in_flight` are outstanding
+    pub fn latency(&self, in_flight: usize) -> Duration {
+        let queued = (in_flight as f64 / self.capacity as f64).max(1.0);
+        self.service.mul_f64(queued)
+    }
+}
+
+pub fn slowed_down(window: usize) -> bool {
+    SLOWDOWNS.iter().any(|&(from, until)| (from..until).contains(&window))
+}
+
+// One window of traffic, filled in as it moves down the pipeline
+#[derive(Debug, Clone)]
+pub struct Window {
+    pub index: usize,
+    pub backend: Backend,
+    pub workers: usize,
+    pub latency: Duration,
+    pub served: u64,
+}
+
+// How the serve stage picks its worker count
+pub enum Sizing {
+    Fixed(usize),
+    Adaptive(Aimd),
+}
+
+// Each worker keeps one request in flight for the whole window
+fn serve(window: &mut Window, workers: usize) {
+    let latency = window.backend.latency(workers);
+    let per_worker = (WINDOW.as_micros() / latency.as_micros().max(1)) as u64;
+    let served: u64 = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers).map(|_| spawn_policy::spawn_scoped(scope, move || per_worker)).collect();
+        handles.into_iter().filter_map(|handle| handle.join().ok()).sum()
+    });
+    window.workers = workers;
+    window.latency = latency;
+    window.served = served;
+}
+
+// Push WINDOWS windows through arrive -> serve and return them in order
+pub fn run(sizing: Sizing) -> Vec<Window> {
+    let sizing = Mutex::new(sizing);
+    let pipeline = Pipeline::new()
+        .stage(|mut window: Window| {
+            window.backend = if slowed_down(window.index) { DEGRADED } else { HEALTHY };
+            window
+        })
+        .stage(move |mut window: Window| {
+            // Windows reach this stage one at a time, in order, so each
+            // sees the limit its predecessor left behind
+            let mut sizing = sizing.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            match &mut *sizing {
+                Sizing::Fixed(workers) => serve(&mut window, *workers),
+                Sizing::Adaptive(controller) => {
+                    serve(&mut window, controller.limit());
+                    controller.observe(window.latency);
+                }
+            }
+            window
+        })
+        .start();
+    for index in 0..WINDOWS {
+        pipeline.send(Window { index, backend: HEALTHY, workers: 0, latency: Duration::ZERO, served: 0 });
+    }
+    pipeline.drain().finish().outputs().to_vec()
+}
+
+pub fn adaptive() -> Aimd {
+    Aimd::new(START_WORKERS, 1, MAX_WORKERS, TARGET)
+}
+
+pub fn over_target(windows: &[Window]) -> usize {
+    windows.iter().filter(|window| window.latency > TARGET).count()
+}
+
+// One column per value, PLOT_ROWS high; '-' marks `line` where no bar reaches
+fn columns(values: &[f64], top: f64, line: Option<f64>, label: impl Fn(f64) -> String) -> Vec<String> {
+    let step = top / PLOT_ROWS as f64;
+    let line_row = line.map(|line| (line / step).round() as usize);
+    (1..=PLOT_ROWS)
+        .rev()
+        .map(|row| {
+            let cells: String = values
+                .iter()
+                .map(|&value| {
+                    if value >= row as f64 * step {
+                        '#'
+                    } else if line_row == Some(row) {
+                        '-'
+                    } else {
+                        ' '
+                    }
+                })
+                .collect();
+            format!("{:>6} |{}", label(row as f64 * step), cells)
+        })
+        .collect()
+}
+
+// Workers and latency per window, one above the other on the same time axis
+pub fn plot(windows: &[Window]) -> Vec<String> {
+    let workers: Vec<f64> = windows.iter().map(|window| window.workers as f64).collect();
+    let latency: Vec<f64> = windows.iter().map(|window| window.latency.as_secs_f64() * 1000.0).collect();
+    // Scaled so the target line shows; taller columns are cut off
+    let latency_top = TARGET.as_secs_f64() * 1000.0 * LATENCY_SCALE;
+    let axis = format!("{:>6} +{}", "", "-".repeat(windows.len()));
+    let slow: String = windows.iter().map(|window| if slowed_down(window.index) { '~' } else { ' ' }).collect();
+
+    let mut lines = vec!["workers".to_string()];
+    lines.extend(columns(&workers, MAX_WORKERS as f64, None, |value| format!("{:.0}", value)));
+    lines.push(axis.clone());
+    lines.push(format!("latency, ms (target {}ms: -, cut off at {:.0}ms)", TARGET.as_millis(), latency_top));
+    lines.extend(columns(&latency, latency_top, Some(TARGET.as_secs_f64() * 1000.0), |value| format!("{:.0}", value)));
+    lines.push(axis);
+    lines.push(format!("{:>6}  {}  (~ backend slowed down, {} windows of {}ms)", "", slow, windows.len(), WINDOW.as_millis()));
+    lines
+}
+
+// The window after each slowdown starts runs with fewer workers
+pub fn backs_off(windows: &[Window]) -> bool {
+    SLOWDOWNS.iter().all(|&(from, _)| windows[from + 1].workers < windows[from].workers)
+}
+
+// Between each slowdown and the next, the workers climb back past the
+// healthy backend's capacity
+pub fn recovers(windows: &[Window]) -> bool {
+    SLOWDOWNS.iter().enumerate().all(|(slowdown, &(_, until))| {
+        let next = SLOWDOWNS.get(slowdown + 1).map_or(windows.len(), |&(from, _)| from);
+        windows[until..next].iter().any(|window| window.workers >= HEALTHY.capacity)
+    })
+}
@@ -0,0 +1,318 @@
+/*!
+ * Async Stream Processing
+ *
+ * The threaded pipeline (phase::Pipeline) gives every stage its own
+ * thread, and a stage that waits on slow I/O holds its thread while it
+ * waits. Here the same workload runs as a futures Stream on a tokio
+ * runtime: `buffered` / `buffer_unordered` decide how many items are in
+ * flight at once, and each item gets its own timeout, so one stalled item
+ * is dropped instead of holding up the rest. The workload is identical in
+ * both models, so the timings can be compared directly.
+ *
+ * The flip side: a task that computes without ever awaiting keeps its
+ * runtime worker to itself, and every other task on that worker stalls.
+ * CPU-heavy work belongs on the blocking pool (spawn_blocking).
+ *
+ * To see that from the numbers, spawned tasks are wrapped so every poll
+ * is counted and timed, and tokio's own runtime metrics are added to the
+ * report (budget-forced yields only with RUSTFLAGS="--cfg tokio_unstable").
+ */
+
+use std::future::Future;
+use std::hint;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use futures::stream::{self, StreamExt};
+use tokio::runtime::{Builder, Runtime};
+use tokio::sync::mpsc;
+use tokio::task::{self, JoinHandle};
+use tokio::time;
+
+use crate::atomic64::AtomicU64;
+use crate::event_log;
+use crate::histogram::{Histogram, Snapshot};
+use crate::phase::Pipeline;
+use crate::units::{Count, Millis, Quantity};
+
+// One item of work: a simulated I/O wait, then a small computation
+#[derive(Debug, Clone, Copy)]
+pub struct Item {
+    pub id: u64,
+    pub latency: Duration,
+}
+
+// Every seventh item stalls far longer than the others
+pub fn workload(count: u64, tick: Duration) -> Vec<Item> {
+    (0..count)
+        .map(|id| {
+            let ticks = if id % 7 == 6 { 20 } else { 1 + id % 4 };
+            Item { id, latency: tick * ticks as u32 }
+        })
+        .collect()
+}
+
+fn transform(id: u64) -> u64 {
+    id * id
+}
+
+#[derive(Debug)]
+pub struct PipelineRun {
+    pub outputs: Vec<u64>,
+    pub timed_out: usize,
+    pub elapsed: Duration,
+}
+
+impl PipelineRun {
+    // Outputs arrived in the same order as the input items
+    pub fn in_order(&self) -> bool {
+        self.outputs.windows(2).all(|pair| pair[0] < pair[1])
+    }
+}
+
+pub fn runtime(workers: usize) -> io::Result<Runtime> {
+    Builder::new_multi_thread().worker_threads(workers.max(1)).enable_time().build()
+}
+
+// A single-threaded runtime whose clock is paused: whenever every task is
+// waiting on a timer, time jumps straight to the next one
+pub fn paused_runtime() -> io::Result<Runtime> {
+    Builder::new_current_thread().enable_time().start_paused(true).build()
+}
+
+// The threaded version: a fetch stage and a transform stage, one thread
+// each. A thread cannot be timed out, so stalled items are waited for.
+pub fn threaded(items: &[Item]) -> PipelineRun {
+    let latencies: Vec<Duration> = items.iter().map(|item| item.latency).collect();
+    let start = Instant::now();
+    let pipeline = Pipeline::new()
+        .stage(move |id: u64| {
+            thread::sleep(latencies[id as usize]);
+            id
+        })
+        .stage(transform)
+        .start();
+    for item in items {
+        pipeline.send(item.id);
+    }
+    let pipeline = pipeline.drain().finish();
+    PipelineRun {
+        outputs: pipeline.outputs().to_vec(),
+        timed_out: 0,
+        elapsed: start.elapsed(),
+    }
+}
+
+// The async version: up to `concurrency` fetches in flight, each one
+// cancelled after `timeout`. `ordered` keeps the input order (buffered);
+// otherwise results come out as they finish (buffer_unordered).
+pub fn streamed(runtime: &Runtime, items: &[Item], concurrency: usize, ordered: bool, timeout: Duration) -> PipelineRun {
+    runtime.block_on(async {
+        let start = Instant::now();
+        let fetches = stream::iter(items.to_vec()).map(|item| async move {
+            time::timeout(timeout, time::sleep(item.latency)).await.ok().map(|()| item.id)
+        });
+        let fetched: Vec<Option<u64>> = if ordered {
+            fetches.buffered(concurrency).collect().await
+        } else {
+            fetches.buffer_unordered(concurrency).collect().await
+        };
+        PipelineRun {
+            outputs: fetched.iter().flatten().map(|id| transform(*id)).collect(),
+            timed_out: fetched.iter().filter(|id| id.is_none()).count(),
+            elapsed: start.elapsed(),
+        }
+    })
+}
+
+// Where the CPU-bound task runs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CpuPlacement {
+    OnRuntime,     // tokio::spawn: occupies a runtime worker
+    BlockingPool,  // spawn_blocking: a separate thread for blocking work
+}
+
+// How late a periodic heartbeat task woke up while the CPU work ran
+#[derive(Debug)]
+pub struct HeartbeatLatency {
+    pub lateness: Snapshot,
+    pub runtime: RuntimeReport,
+}
+
+// Poll counts and times for the tasks spawned through it
+#[derive(Debug, Default)]
+pub struct TaskMetrics {
+    spawned: AtomicU64,
+    completed: AtomicU64,
+    polls: AtomicU64,
+    poll_nanos: AtomicU64,
+    slowest_poll_nanos: AtomicU64,
+}
+
+impl TaskMetrics {
+    pub fn spawn<F>(self: &Arc<Self>, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.spawned.fetch_add(1, Ordering::Relaxed);
+        tokio::spawn(Instrumented { inner: Box::pin(future), metrics: Arc::clone(self) })
+    }
+}
+
+// Times every poll of the wrapped future
+struct Instrumented<F> {
+    inner: Pin<Box<F>>,
+    metrics: Arc<TaskMetrics>,
+}
+
+impl<F: Future> Future for Instrumented<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let start = Instant::now();
+        let result = self.inner.as_mut().poll(cx);
+        let took = start.elapsed().as_nanos() as u64;
+
+        let metrics = &self.metrics;
+        metrics.polls.fetch_add(1, Ordering::Relaxed);
+        metrics.poll_nanos.fetch_add(took, Ordering::Relaxed);
+        metrics.slowest_poll_nanos.fetch_max(took, Ordering::Relaxed);
+        if result.is_ready() {
+            metrics.completed.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+// Runtime and task metrics after a run
+#[derive(Debug)]
+pub struct RuntimeReport {
+    pub workers: usize,
+    pub busy: Duration,  // summed over the workers
+    pub spawned: u64,
+    pub completed: u64,
+    pub polls: u64,
+    pub mean_poll: Duration,
+    pub slowest_poll: Duration,
+    pub forced_yields: Option<u64>,  // tokio's coop budget ran out
+}
+
+impl RuntimeReport {
+    fn collect(runtime: &Runtime, tasks: &TaskMetrics) -> Self {
+        let metrics = runtime.metrics();
+        let polls = tasks.polls.load(Ordering::Relaxed);
+        #[cfg(tokio_unstable)]
+        let forced_yields = Some(metrics.budget_forced_yield_count());
+        #[cfg(not(tokio_unstable))]
+        let forced_yields = None;
+        // tokio counts busy time only where it has 64-bit atomics
+        #[cfg(target_has_atomic = "64")]
+        let busy = (0..metrics.num_workers()).map(|worker| metrics.worker_total_busy_duration(worker)).sum();
+        #[cfg(not(target_has_atomic = "64"))]
+        let busy = Duration::ZERO;
+
+        RuntimeReport {
+            workers: metrics.num_workers(),
+            busy,
+            spawned: tasks.spawned.load(Ordering::Relaxed),
+            completed: tasks.completed.load(Ordering::Relaxed),
+            polls,
+            mean_poll: Duration::from_nanos(tasks.poll_nanos.load(Ordering::Relaxed) / polls.max(1)),
+            slowest_poll: Duration::from_nanos(tasks.slowest_poll_nanos.load(Ordering::Relaxed)),
+            forced_yields,
+        }
+    }
+
+    // Add every value to the event log as `<prefix>.<metric>`
+    pub fn record(&self, prefix: &str) {
+        let mut counts = vec![
+            ("workers", self.workers as u64),
+            ("tasks_spawned", self.spawned),
+            ("tasks_completed", self.completed),
+            ("polls", self.polls),
+        ];
+        if let Some(forced_yields) = self.forced_yields {
+            counts.push(("budget_forced_yields", forced_yields));
+        }
+        for (name, value) in counts {
+            event_log::metric::<Count>(&format!("{}.{}", prefix, name), Quantity::new(value as f64));
+        }
+        for (name, duration) in [("busy", self.busy), ("mean_poll", self.mean_poll), ("slowest_poll", self.slowest_poll)] {
+            event_log::metric(&format!("{}.{}", prefix, name), Quantity::<f64, Millis>::from_duration(duration));
+        }
+    }
+}
+
+// Busy computation that never awaits
+fn spin(work: Duration) -> u64 {
+    let start = Instant::now();
+    let mut state = 1u64;
+    while start.elapsed() < work {
+        state = hint::black_box(state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1));
+    }
+    state
+}
+
+// A one-worker runtime runs a heartbeat every `interval` while `work` of
+// CPU time is spent in the given placement
+pub fn heartbeat_under_cpu_load(placement: CpuPlacement, work: Duration, interval: Duration) -> io::Result<HeartbeatLatency> {
+    let runtime = runtime(1)?;
+    let tasks = Arc::new(TaskMetrics::default());
+    let beats = (work.as_nanos() / interval.as_nanos().max(1)) as u32 + 5;
+    let spawner = Arc::clone(&tasks);
+    let lateness = runtime.block_on(async move {
+        let heartbeat = spawner.spawn(async move {
+            let start = time::Instant::now();
+            let lateness = Histogram::new();
+            for beat in 1..=beats {
+                let due = start + interval * beat;
+                time::sleep_until(due).await;
+                lateness.record(time::Instant::now() - due);
+            }
+            lateness.snapshot()
+        });
+        time::sleep(interval).await;  // The heartbeat is running before the work starts
+        let cpu = match placement {
+            CpuPlacement::OnRuntime => spawner.spawn(async move { spin(work) }),
+            CpuPlacement::BlockingPool => task::spawn_blocking(move || spin(work)),
+        };
+        let _ = cpu.await;
+        heartbeat.await.unwrap_or_default()
+    });
+
+    Ok(HeartbeatLatency {
+        lateness,
+        runtime: RuntimeReport::collect(&runtime, &tasks),
+    })
+}
+
+// One task drains a channel whose messages are all ready, so it never has
+// to wait. tokio's coop budget still makes it yield every so often, which
+// shows up as extra polls (and as forced yields with tokio_unstable).
+pub fn drain_ready_channel(messages: usize) -> io::Result<RuntimeReport> {
+    let runtime = runtime(1)?;
+    let tasks = Arc::new(TaskMetrics::default());
+    let spawner = Arc::clone(&tasks);
+    runtime.block_on(async move {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        for message in 0..messages {
+            let _ = sender.send(message);
+        }
+        drop(sender);
+        let consumer = spawner.spawn(async move {
+            let mut received = 0;
+            while receiver.recv().await.is_some() {
+                received += 1;
+            }
+            received
+        });
+        let _ = consumer.await;
+    });
+    Ok(RuntimeReport::collect(&runtime, &tasks))
+}
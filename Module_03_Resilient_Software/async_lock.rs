@@ -0,0 +1,295 @@
+/*!
+ * Locks Across .await
+ *
+ * A std Mutex guard held across an .await stays held while the task is
+ * suspended. Another task on the same executor thread that then calls
+ * lock() blocks that thread inside a poll: the holder, which needs the
+ * thread to be polled again and unlock, cannot run, and neither can any
+ * other task on it. With a timeout the executor stalls until it expires;
+ * without one, it deadlocks for good.
+ *
+ * On a multi-threaded runtime tokio::spawn refuses such a task at compile
+ * time, since a std MutexGuard is not Send ("future cannot be sent
+ * between threads safely"). A LocalSet, spawn_local or a current-thread
+ * runtime accepts it, and that is where the bug turns up at run time.
+ * Clippy's `await_holding_lock` lint finds the pattern in the source;
+ * AwaitCheck here finds it while the program runs: every WatchedMutex
+ * guard is recorded per thread, and a watched task that returns Pending
+ * with a guard it took still held is reported.
+ *
+ * The two fixes:
+ *
+ *   async mutex    tokio::sync::Mutex: lock().await suspends the waiting
+ *                  task instead of blocking the thread, and its guard may
+ *                  be held across an .await
+ *   narrow scope   keep the std Mutex, but do the awaiting first and hold
+ *                  the guard only for the update, which never awaits
+ */
+
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tokio::runtime::Builder;
+use tokio::sync::Mutex as AsyncMutex;
+use tokio::task::{self, LocalSet};
+use tokio::time;
+
+thread_local! {
+    // Names of the WatchedMutex locks this thread holds, oldest first
+    static HELD: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+}
+
+// A std Mutex whose guards AwaitCheck can see
+pub struct WatchedMutex<T> {
+    name: &'static str,
+    inner: Mutex<T>,
+}
+
+impl<T> WatchedMutex<T> {
+    pub fn new(name: &'static str, value: T) -> Self {
+        WatchedMutex { name, inner: Mutex::new(value) }
+    }
+
+    // Blocks the thread, and with it every task the thread runs
+    pub fn lock(&self) -> WatchedGuard<'_, T> {
+        self.held(self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+
+    // Like lock(), giving up after `limit`: the thread is blocked all the
+    // same until then
+    pub fn lock_for(&self, limit: Duration) -> Option<WatchedGuard<'_, T>> {
+        let start = Instant::now();
+        loop {
+            match self.inner.try_lock() {
+                Ok(guard) => return Some(self.held(guard)),
+                Err(TryLockError::Poisoned(poisoned)) => return Some(self.held(poisoned.into_inner())),
+                Err(TryLockError::WouldBlock) if start.elapsed() >= limit => return None,
+                Err(TryLockError::WouldBlock) => thread::yield_now(),
+            }
+        }
+    }
+
+    fn held<'a>(&'a self, guard: MutexGuard<'a, T>) -> WatchedGuard<'a, T> {
+        HELD.with(|held| held.borrow_mut().push(self.name));
+        WatchedGuard { name: self.name, guard }
+    }
+}
+
+pub struct WatchedGuard<'a, T> {
+    name: &'static str,
+    guard: MutexGuard<'a, T>,
+}
+
+impl<T> Deref for WatchedGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for WatchedGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for WatchedGuard<'_, T> {
+    fn drop(&mut self) {
+        HELD.with(|held| {
+            let mut held = held.borrow_mut();
+            if let Some(position) = held.iter().rposition(|&name| name == self.name) {
+                held.remove(position);
+            }
+        });
+    }
+}
+
+// Tasks that suspended while holding a WatchedMutex guard: clippy's
+// await_holding_lock, checked at run time
+#[derive(Debug, Default)]
+pub struct AwaitCheck {
+    findings: Mutex<Vec<String>>,
+}
+
+impl AwaitCheck {
+    pub fn watch<F: Future>(self: &Arc<Self>, task: &'static str, future: F) -> Watched<F> {
+        Watched { task, inner: Box::pin(future), check: Arc::clone(self) }
+    }
+
+    // One line per task and lock, in the order first seen
+    pub fn findings(&self) -> Vec<String> {
+        self.findings.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    fn report(&self, task: &str, lock: &str) {
+        let finding = format!("task `{}` awaited while holding `{}`", task, lock);
+        let mut findings = self.findings.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !findings.contains(&finding) {
+            findings.push(finding);
+        }
+    }
+}
+
+// Looks at the guards held after each poll of the wrapped future
+pub struct Watched<F> {
+    task: &'static str,
+    inner: Pin<Box<F>>,
+    check: Arc<AwaitCheck>,
+}
+
+impl<F: Future> Future for Watched<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        // Guards from before this poll belong to other suspended tasks
+        let before = HELD.with(|held| held.borrow().len());
+        let result = self.inner.as_mut().poll(cx);
+        if result.is_pending() {
+            let kept: Vec<_> = HELD.with(|held| held.borrow().iter().skip(before).copied().collect());
+            for lock in kept {
+                self.check.report(self.task, lock);
+            }
+        }
+        result
+    }
+}
+
+// How the holder task waits and updates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fix {
+    None,         // std guard held across the .await
+    AsyncMutex,   // tokio::sync::Mutex guard held across the .await
+    NarrowScope,  // std guard taken after the .await
+}
+
+impl Fix {
+    pub const ALL: [Fix; 3] = [Fix::None, Fix::AsyncMutex, Fix::NarrowScope];
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Fix::None => "std Mutex held across .await",
+            Fix::AsyncMutex => "tokio::sync::Mutex",
+            Fix::NarrowScope => "std Mutex, narrowed scope",
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct LockRun {
+    pub fix: Fix,
+    pub count: u64,     // the shared counter at the end
+    pub expected: u64,  // updates made: every round of both tasks, less give-ups
+    pub gave_up: u32,   // contender lock_for calls that timed out
+    pub worst_heartbeat: Duration,  // latest a 1ms heartbeat woke
+    pub findings: Vec<String>,
+    pub elapsed: Duration,
+}
+
+const TICK: Duration = Duration::from_millis(1);
+
+// Two tasks on one current-thread runtime add `rounds` each to a shared
+// counter. The holder waits `hold` per round (with the guard held, for
+// Fix::None and Fix::AsyncMutex); the contender starts a quarter of the
+// way into each hold, and with Fix::None gives up after `give_up`, or
+// never if that is None. A heartbeat ticks beside them throughout
+pub fn run(fix: Fix, rounds: u32, hold: Duration, give_up: Option<Duration>) -> io::Result<LockRun> {
+    let runtime = Builder::new_current_thread().enable_time().build()?;
+    let check = Arc::new(AwaitCheck::default());
+    let std_counter = Rc::new(WatchedMutex::new("counter", 0u64));
+    let async_counter = Rc::new(AsyncMutex::new(0u64));
+    let finished = Rc::new(Cell::new(false));
+    let start = Instant::now();
+
+    let local = LocalSet::new();
+    let (gave_up, worst_heartbeat) = local.block_on(&runtime, {
+        let (check, std_counter, async_counter) = (Arc::clone(&check), Rc::clone(&std_counter), Rc::clone(&async_counter));
+        async move {
+            let heartbeat = {
+                let finished = Rc::clone(&finished);
+                task::spawn_local(async move {
+                    let mut worst = Duration::ZERO;
+                    while !finished.get() {
+                        let due = Instant::now() + TICK;
+                        time::sleep(TICK).await;
+                        worst = worst.max(Instant::now().saturating_duration_since(due));
+                    }
+                    worst
+                })
+            };
+            let holder = {
+                let (std_counter, async_counter) = (Rc::clone(&std_counter), Rc::clone(&async_counter));
+                task::spawn_local(check.watch("holder", async move {
+                    for _ in 0..rounds {
+                        match fix {
+                            // The bug: the guard lives across the sleep
+                            Fix::None => {
+                                let mut count = std_counter.lock();
+                                let seen = *count;
+                                time::sleep(hold).await;
+                                *count = seen + 1;
+                            }
+                            Fix::AsyncMutex => {
+                                let mut count = async_counter.lock().await;
+                                let seen = *count;
+                                time::sleep(hold).await;
+                                *count = seen + 1;
+                            }
+                            Fix::NarrowScope => {
+                                time::sleep(hold).await;
+                                *std_counter.lock() += 1;
+                            }
+                        }
+                    }
+                }))
+            };
+            let contender = task::spawn_local(check.watch("contender", async move {
+                let mut gave_up = 0;
+                for _ in 0..rounds {
+                    time::sleep(hold / 4).await;
+                    match fix {
+                        Fix::None => match give_up {
+                            Some(limit) => match std_counter.lock_for(limit) {
+                                Some(mut count) => *count += 1,
+                                None => gave_up += 1,
+                            },
+                            None => *std_counter.lock() += 1,
+                        },
+                        Fix::AsyncMutex => *async_counter.lock().await += 1,
+                        Fix::NarrowScope => *std_counter.lock() += 1,
+                    }
+                }
+                gave_up
+            }));
+            let _ = holder.await;
+            let gave_up = contender.await.unwrap_or_default();
+            finished.set(true);
+            (gave_up, heartbeat.await.unwrap_or_default())
+        }
+    });
+
+    let count = match fix {
+        Fix::AsyncMutex => *async_counter.try_lock().expect("both tasks have finished"),
+        Fix::None | Fix::NarrowScope => *std_counter.lock(),
+    };
+    Ok(LockRun {
+        fix,
+        count,
+        expected: 2 * u64::from(rounds) - u64::from(gave_up),
+        gave_up,
+        worst_heartbeat,
+        findings: check.findings(),
+        elapsed: start.elapsed(),
+    })
+}
@@ -0,0 +1,108 @@
+/*!
+ * 64-bit Atomics on Every Target
+ *
+ * AtomicU64 is not everywhere: 32-bit PowerPC and MIPS and ARMv5 only
+ * have 32-bit atomics, and std leaves the type out there. Modules take
+ * AtomicU64 from here instead of std::sync::atomic. On a target with
+ * native 64-bit atomics it is the std type; elsewhere it is LockedU64, a
+ * u64 behind a Mutex with the same methods - still atomic, only not
+ * lock-free. LOCK_FREE says which one a build got, so the demos that
+ * are about lock-freedom can say what they really measured.
+ *
+ * LockedU64 is compiled on every target, so tests/atomic64.rs checks it
+ * against the native type on the machine it runs on.
+ */
+
+use std::sync::atomic::Ordering;
+use std::sync::{Mutex, MutexGuard};
+
+#[cfg(target_has_atomic = "64")]
+pub use std::sync::atomic::AtomicU64;
+#[cfg(not(target_has_atomic = "64"))]
+pub use LockedU64 as AtomicU64;
+
+// Whether AtomicU64 is the native, lock-free type on this target
+pub const LOCK_FREE: bool = cfg!(target_has_atomic = "64");
+
+// A u64 whose every operation holds a lock: SeqCst whatever ordering is
+// asked for, which is never weaker than what the caller needs
+#[derive(Debug, Default)]
+pub struct LockedU64(Mutex<u64>);
+
+impl LockedU64 {
+    pub const fn new(value: u64) -> Self {
+        LockedU64(Mutex::new(value))
+    }
+
+    // Every operation completes under the lock, so a poisoned one still
+    // holds a whole value
+    fn lock(&self) -> MutexGuard<'_, u64> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn load(&self, _order: Ordering) -> u64 {
+        *self.lock()
+    }
+
+    pub fn store(&self, value: u64, _order: Ordering) {
+        *self.lock() = value;
+    }
+
+    pub fn swap(&self, value: u64, _order: Ordering) -> u64 {
+        std::mem::replace(&mut *self.lock(), value)
+    }
+
+    // Wraps around on overflow, as AtomicU64::fetch_add does
+    pub fn fetch_add(&self, value: u64, _order: Ordering) -> u64 {
+        let mut current = self.lock();
+        let previous = *current;
+        *current = previous.wrapping_add(value);
+        previous
+    }
+
+    pub fn fetch_or(&self, value: u64, _order: Ordering) -> u64 {
+        let mut current = self.lock();
+        let previous = *current;
+        *current = previous | value;
+        previous
+    }
+
+    pub fn fetch_max(&self, value: u64, _order: Ordering) -> u64 {
+        let mut current = self.lock();
+        let previous = *current;
+        *current = previous.max(value);
+        previous
+    }
+
+    pub fn compare_exchange(&self, current: u64, new: u64, _success: Ordering, _failure: Ordering) -> Result<u64, u64> {
+        let mut value = self.lock();
+        if *value == current {
+            *value = new;
+            Ok(current)
+        } else {
+            Err(*value)
+        }
+    }
+
+    // Never fails spuriously: there is no race to lose under the lock
+    pub fn compare_exchange_weak(&self, current: u64, new: u64, success: Ordering, failure: Ordering) -> Result<u64, u64> {
+        self.compare_exchange(current, new, success, failure)
+    }
+
+    pub fn fetch_update(
+        &self,
+        _set_order: Ordering,
+        _fetch_order: Ordering,
+        mut update: impl FnMut(u64) -> Option<u64>,
+    ) -> Result<u64, u64> {
+        let mut value = self.lock();
+        let previous = *value;
+        match update(previous) {
+            Some(next) => {
+                *value = next;
+                Ok(previous)
+            }
+            None => Err(previous),
+        }
+    }
+}
@@ -0,0 +1,238 @@
+/*!
+ * AtomicStore - a generic atomic cell
+ *
+ * `AtomicStore<T>` holds any small Copy value and reads, writes, swaps,
+ * and updates it atomically - like crossbeam's AtomicCell. Values that
+ * fit in 64 bits live in an AtomicU64 (lock-free where the target has
+ * 64-bit atomics, see atomic64.rs); anything larger falls back to a Mutex.
+ *
+ * Packing goes through integer arithmetic - widening casts and shifts -
+ * never through memory bytes, so the bits a value packs to are the same
+ * on little- and big-endian targets and on 32- and 64-bit ones.
+ *
+ * AtomicCell decides this with size checks and unsafe byte copies. This
+ * version stays in safe Rust, and the choice is made per type, at compile
+ * time. A type that packs implements PackedValue, saying how it packs
+ * into 64 bits, and picks `type Storage = Native` in AtomicValue. Any
+ * other Copy type picks `type Storage = Locked<Self>`. Storage has no
+ * default, so an impl that picks nothing does not compile. Native only
+ * stores PackedValue types, so picking it without a packing does not
+ * compile either (tests/compile_fail/atomic_value_*.rs).
+ *
+ * compare_exchange compares with PartialEq in both storages, never
+ * bit patterns: for floats, a stored -0.0 matches 0.0, and NaN matches
+ * nothing, exactly as `==` says.
+ */
+
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::atomic64::{self, AtomicU64};
+
+// A value that packs into 64 bits and back. AtomicStore keeps these in an
+// AtomicU64, and the lock-free queues only take these
+pub trait PackedValue: Copy {
+    fn to_bits(self) -> u64;
+    fn from_bits(bits: u64) -> Self;
+}
+
+// A value AtomicStore can hold, and where it keeps it
+pub trait AtomicValue: Copy {
+    type Storage: Storage<Self>;
+}
+
+// Where an AtomicStore keeps its value, and the operations on it there
+pub trait Storage<T>: Sized {
+    const LOCK_FREE: bool;
+    fn new(value: T) -> Self;
+    fn load(&self) -> T;
+    fn swap(&self, value: T) -> T;
+    fn fetch_update(&self, update: impl FnMut(T) -> T) -> T;
+    fn compare_exchange(&self, current: T, new: T) -> Result<T, T>
+    where
+        T: PartialEq;
+}
+
+// Packed into an AtomicU64: lock-free where the target has 64-bit atomics
+pub struct Native(AtomicU64);
+
+// Behind a Mutex, for anything that does not pack
+pub struct Locked<T>(Mutex<T>);
+
+impl<T: PackedValue> Storage<T> for Native {
+    const LOCK_FREE: bool = atomic64::LOCK_FREE;
+
+    fn new(value: T) -> Self {
+        Native(AtomicU64::new(value.to_bits()))
+    }
+
+    fn load(&self) -> T {
+        T::from_bits(self.0.load(Ordering::SeqCst))
+    }
+
+    fn swap(&self, value: T) -> T {
+        T::from_bits(self.0.swap(value.to_bits(), Ordering::SeqCst))
+    }
+
+    fn fetch_update(&self, mut update: impl FnMut(T) -> T) -> T {
+        let previous = self
+            .0
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| Some(update(T::from_bits(current)).to_bits()))
+            .unwrap_or_else(|current| current);  // Never fails: the closure always returns Some
+        T::from_bits(previous)
+    }
+
+    // Compares values, not bits: when the bits differ but the values are
+    // equal (-0.0 and 0.0), swap the bits that are actually there
+    fn compare_exchange(&self, current: T, new: T) -> Result<T, T>
+    where
+        T: PartialEq,
+    {
+        let mut bits = self.0.load(Ordering::SeqCst);
+        loop {
+            let actual = T::from_bits(bits);
+            if actual != current {
+                return Err(actual);
+            }
+            match self.0.compare_exchange(bits, new.to_bits(), Ordering::SeqCst, Ordering::SeqCst) {
+                Ok(_) => return Ok(actual),
+                Err(changed) => bits = changed,
+            }
+        }
+    }
+}
+
+impl<T: Copy> Locked<T> {
+    // A Copy value cannot be left half-written, so poisoning is harmless
+    fn lock(&self) -> MutexGuard<'_, T> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<T: Copy> Storage<T> for Locked<T> {
+    const LOCK_FREE: bool = false;
+
+    fn new(value: T) -> Self {
+        Locked(Mutex::new(value))
+    }
+
+    fn load(&self) -> T {
+        *self.lock()
+    }
+
+    fn swap(&self, value: T) -> T {
+        std::mem::replace(&mut *self.lock(), value)
+    }
+
+    fn fetch_update(&self, mut update: impl FnMut(T) -> T) -> T {
+        let mut guard = self.lock();
+        let previous = *guard;
+        *guard = update(previous);
+        previous
+    }
+
+    fn compare_exchange(&self, current: T, new: T) -> Result<T, T>
+    where
+        T: PartialEq,
+    {
+        let mut guard = self.lock();
+        if *guard == current {
+            Ok(std::mem::replace(&mut *guard, new))
+        } else {
+            Err(*guard)
+        }
+    }
+}
+
+// Primitives pack by widening to u64
+macro_rules! packed_primitive {
+    ($($ty:ty),*) => {$(
+        impl PackedValue for $ty {
+            fn to_bits(self) -> u64 { self as u64 }
+            fn from_bits(bits: u64) -> Self { bits as $ty }
+        }
+
+        impl AtomicValue for $ty {
+            type Storage = Native;
+        }
+    )*};
+}
+
+packed_primitive!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl PackedValue for bool {
+    fn to_bits(self) -> u64 { self as u64 }
+    fn from_bits(bits: u64) -> Self { bits != 0 }
+}
+
+impl AtomicValue for bool {
+    type Storage = Native;
+}
+
+impl PackedValue for f32 {
+    fn to_bits(self) -> u64 { f32::to_bits(self) as u64 }
+    fn from_bits(bits: u64) -> Self { f32::from_bits(bits as u32) }
+}
+
+impl AtomicValue for f32 {
+    type Storage = Native;
+}
+
+impl PackedValue for f64 {
+    fn to_bits(self) -> u64 { f64::to_bits(self) }
+    fn from_bits(bits: u64) -> Self { f64::from_bits(bits) }
+}
+
+impl AtomicValue for f64 {
+    type Storage = Native;
+}
+
+pub struct AtomicStore<T: AtomicValue> {
+    storage: T::Storage,
+}
+
+impl<T: AtomicValue> AtomicStore<T> {
+    pub fn new(value: T) -> Self {
+        AtomicStore { storage: T::Storage::new(value) }
+    }
+
+    pub fn is_lock_free(&self) -> bool {
+        T::Storage::LOCK_FREE
+    }
+
+    pub fn load(&self) -> T {
+        self.storage.load()
+    }
+
+    pub fn store(&self, value: T) {
+        self.swap(value);
+    }
+
+    pub fn swap(&self, value: T) -> T {
+        self.storage.swap(value)
+    }
+
+    // Apply `update` atomically, retrying if another thread got in first;
+    // returns the previous value
+    pub fn fetch_update(&self, update: impl FnMut(T) -> T) -> T {
+        self.storage.fetch_update(update)
+    }
+}
+
+impl<T: AtomicValue + PartialEq> AtomicStore<T> {
+    // Replace `current` with `new`; Err(actual value) if it was not
+    // `current`, as PartialEq compares them
+    pub fn compare_exchange(&self, current: T, new: T) -> Result<T, T> {
+        self.storage.compare_exchange(current, new)
+    }
+}
+
+impl<T: AtomicValue + fmt::Debug> fmt::Debug for AtomicStore<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomicStore")
+            .field("value", &self.load())
+            .field("lock_free", &self.is_lock_free())
+            .finish()
+    }
+}
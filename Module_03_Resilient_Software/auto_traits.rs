@@ -0,0 +1,141 @@
+/*!
+ * Send and Sync, Type by Type
+ *
+ * Send (a value may move to another thread) and Sync (a & to it may be
+ * shared with another thread, i.e. &T is Send) are auto traits: nobody
+ * writes `impl Send`, the compiler derives them from the fields. A struct
+ * is Send when every field is Send, and Sync when every field is Sync. So
+ * one field decides for the whole type, and the types below each hold
+ * one field to show which:
+ *
+ *   raw pointers     opt out of both; the compiler cannot know who else
+ *                    reaches what they point to
+ *   Rc               neither: its reference count is not atomic
+ *   Cell, RefCell    Send but not Sync: they mutate through &self with
+ *                    no lock, which is fine for one owner at a time
+ *   MutexGuard       Sync but not Send: it has to be unlocked on the
+ *                    thread that locked it
+ *   PhantomData<T>   counts as a T, though it takes no space; with
+ *                    fn() -> T the marker keeps Send and Sync whatever T is
+ *   unsafe impl      the author's promise, for what the compiler cannot
+ *                    see: here, that the pointer is the only one
+ *
+ * Every claim in the table is checked while the program compiles, with
+ * the two assertions of the `static_assertions` crate written out:
+ * assert_impl! fails with E0277 when the type lacks the trait, and
+ * assert_not_impl! fails with E0283 when it has it. The auto_traits_*
+ * snippets in tests/compile_fail/ show both failing.
+ */
+
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::atomic::AtomicI32;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// Fails to compile (E0277) unless `$ty` implements `$trait`
+#[macro_export]
+macro_rules! assert_impl {
+    ($ty:ty: $trait:path) => {
+        const _: fn() = || {
+            fn implemented<T: ?Sized + $trait>() {}
+            implemented::<$ty>();
+        };
+    };
+}
+
+// Fails to compile (E0283) if `$ty` implements `$trait`: with the trait,
+// two impls of AmbiguousIfImpl apply and the call cannot pick one
+#[macro_export]
+macro_rules! assert_not_impl {
+    ($ty:ty: $trait:path) => {
+        const _: fn() = || {
+            trait AmbiguousIfImpl<A> {
+                fn some_item() {}
+            }
+            impl<T: ?Sized> AmbiguousIfImpl<()> for T {}
+            struct Invalid;
+            impl<T: ?Sized + $trait> AmbiguousIfImpl<Invalid> for T {}
+            let _ = <$ty as AmbiguousIfImpl<_>>::some_item;
+        };
+    };
+}
+
+macro_rules! assert_auto_trait {
+    ($ty:ty: $trait:path, yes) => {
+        assert_impl!($ty: $trait);
+    };
+    ($ty:ty: $trait:path, no) => {
+        assert_not_impl!($ty: $trait);
+    };
+}
+
+macro_rules! yes {
+    (yes) => {
+        true
+    };
+    (no) => {
+        false
+    };
+}
+
+// One row of the table
+#[derive(Debug, Clone, Copy)]
+pub struct AutoTraits {
+    pub ty: &'static str,  // the struct, as declared
+    pub send: bool,
+    pub sync: bool,
+    pub why: &'static str,
+}
+
+// Declares each one-field struct, asserts what it claims about Send and
+// Sync, and lists the claims in TABLE
+macro_rules! auto_traits {
+    ($($name:ident($field:ty): Send $send:ident, Sync $sync:ident, $why:literal;)*) => {
+        $(
+            #[allow(dead_code)]  // it exists for its traits; none is ever built
+            pub struct $name(pub $field);
+            assert_auto_trait!($name: Send, $send);
+            assert_auto_trait!($name: Sync, $sync);
+        )*
+        pub const TABLE: &[AutoTraits] = &[$(AutoTraits {
+            ty: concat!(stringify!($name), "(", stringify!($field), ")"),
+            send: yes!($send),
+            sync: yes!($sync),
+            why: $why,
+        }),*];
+    };
+}
+
+auto_traits! {
+    Plain(i32): Send yes, Sync yes,
+        "every field is Send and Sync, so the struct is";
+    AtomicField(AtomicI32): Send yes, Sync yes,
+        "atomics are built to be changed through & from many threads";
+    RawPointer(*const i32): Send no, Sync no,
+        "raw pointers opt out: who else reaches the target is unknown";
+    OwnedPointer(*mut i32): Send yes, Sync no,
+        "unsafe impl Send: the struct promises it holds the only pointer";
+    RcField(Rc<i32>): Send no, Sync no,
+        "two threads cloning at once would race on the plain count";
+    CellField(Cell<i32>): Send yes, Sync no,
+        "one owner may write it through &; two sharing & would race";
+    MutexField(Mutex<Cell<i32>>): Send yes, Sync yes,
+        "a Mutex makes Send contents Sync: one thread reaches them at a time";
+    ArcField(Arc<Mutex<i32>>): Send yes, Sync yes,
+        "Arc is Send and Sync when what it shares is both";
+    ArcCell(Arc<Cell<i32>>): Send no, Sync no,
+        "a clone on each thread would share the Cell, so Arc refuses it";
+    GuardField(MutexGuard<'static, i32>): Send no, Sync yes,
+        "a lock must be released on the thread that took it";
+    PhantomRaw(PhantomData<*const i32>): Send no, Sync no,
+        "PhantomData<T> counts as a T, though it takes no space";
+    PhantomCell(PhantomData<Cell<i32>>): Send yes, Sync no,
+        "the marker gives up Sync as a real Cell would";
+    PhantomFn(PhantomData<fn() -> *const i32>): Send yes, Sync yes,
+        "fn pointers are Send and Sync whatever they return";
+}
+
+// SAFETY: an OwnedPointer is the only pointer to its target, so moving
+// it moves sole access. Sharing it is another matter, and Sync stays off
+unsafe impl Send for OwnedPointer {}
@@ -0,0 +1,79 @@
+/*!
+ * Batched Channel Sends
+ *
+ * Every mpsc send synchronizes with the receiver. A BatchingSender
+ * collects items in a local buffer and sends them as one Vec once the
+ * batch is full, so a thousand items cost a handful of channel
+ * operations. Whatever is still buffered is flushed when the sender is
+ * dropped, so the tail of a stream is never lost.
+ */
+
+use std::mem;
+use std::sync::mpsc::{self, Receiver, SendError, Sender};
+
+#[derive(Debug)]
+pub struct BatchingSender<T> {
+    sender: Sender<Vec<T>>,
+    buffer: Vec<T>,
+    batch_size: usize,
+}
+
+#[derive(Debug)]
+pub struct BatchReceiver<T> {
+    receiver: Receiver<Vec<T>>,
+}
+
+pub fn batch_channel<T>(batch_size: usize) -> (BatchingSender<T>, BatchReceiver<T>) {
+    let (sender, receiver) = mpsc::channel();
+    let batch_size = batch_size.max(1);
+    (
+        BatchingSender { sender, buffer: Vec::with_capacity(batch_size), batch_size },
+        BatchReceiver { receiver },
+    )
+}
+
+impl<T> BatchingSender<T> {
+    // Buffer the item; the whole batch goes out once it is full.
+    // On error the returned batch holds every item that was not delivered.
+    pub fn send(&mut self, item: T) -> Result<(), SendError<Vec<T>>> {
+        self.buffer.push(item);
+        if self.buffer.len() >= self.batch_size {
+            self.flush()
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn flush(&mut self) -> Result<(), SendError<Vec<T>>> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let batch = mem::replace(&mut self.buffer, Vec::with_capacity(self.batch_size));
+        self.sender.send(batch)
+    }
+}
+
+// Each clone has its own buffer; all of them feed the same receiver
+impl<T> Clone for BatchingSender<T> {
+    fn clone(&self) -> Self {
+        BatchingSender {
+            sender: self.sender.clone(),
+            buffer: Vec::with_capacity(self.batch_size),
+            batch_size: self.batch_size,
+        }
+    }
+}
+
+impl<T> Drop for BatchingSender<T> {
+    fn drop(&mut self) {
+        // Nowhere to report a failure from drop: the receiver is gone anyway
+        let _ = self.flush();
+    }
+}
+
+impl<T> BatchReceiver<T> {
+    // Items one at a time, in the order each sender sent them
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        self.receiver.iter().flatten()
+    }
+}
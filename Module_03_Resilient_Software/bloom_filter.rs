@@ -0,0 +1,89 @@
+/*!
+ * Concurrent Bloom Filter
+ *
+ * A set that answers "definitely not seen" or "probably seen" in a fixed
+ * amount of memory. The bits live in AtomicU64 words, so any number of
+ * threads can insert and query at once without a lock: setting a bit is
+ * a fetch_or and bits are never cleared. There are no false negatives;
+ * the false-positive rate is chosen up front from the expected item count.
+ */
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::Ordering;
+
+use crate::atomic64::AtomicU64;
+use crate::units::{Bytes, Quantity};
+
+#[derive(Debug)]
+pub struct BloomFilter {
+    words: Vec<AtomicU64>,
+    bits: u64,
+    hashes: u32,
+}
+
+impl BloomFilter {
+    // Size the filter for `expected_items` at the target false-positive rate
+    pub fn with_rate(expected_items: usize, false_positive_rate: f64) -> Self {
+        let items = expected_items.max(1) as f64;
+        let rate = false_positive_rate.clamp(1e-9, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-(items * rate.ln()) / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let hashes = ((bits as f64 / items) * ln2).round().clamp(1.0, 32.0) as u32;
+        BloomFilter {
+            words: (0..bits.div_ceil(64)).map(|_| AtomicU64::new(0)).collect(),
+            bits,
+            hashes,
+        }
+    }
+
+    pub fn bit_count(&self) -> u64 {
+        self.bits
+    }
+
+    pub fn hash_count(&self) -> u32 {
+        self.hashes
+    }
+
+    // Memory held by the bit words
+    pub fn size(&self) -> Quantity<u64, Bytes> {
+        Quantity::new(self.words.len() as u64 * 8)
+    }
+
+    // Double hashing: position i is h1 + i * h2, from two seeded hashes
+    fn positions<T: Hash + ?Sized>(&self, item: &T) -> impl Iterator<Item = u64> {
+        let mut first = DefaultHasher::new();
+        item.hash(&mut first);
+        let h1 = first.finish();
+        let mut second = DefaultHasher::new();
+        0x9E37_79B9_7F4A_7C15u64.hash(&mut second);
+        item.hash(&mut second);
+        let h2 = second.finish() | 1;
+        let bits = self.bits;
+        (0..self.hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % bits)
+    }
+
+    // Add the item; true if it was (probably) already present
+    pub fn insert<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        let mut already_set = true;
+        for position in self.positions(item) {
+            let mask = 1u64 << (position % 64);
+            let previous = self.words[(position / 64) as usize].fetch_or(mask, Ordering::AcqRel);
+            already_set &= previous & mask != 0;
+        }
+        already_set
+    }
+
+    pub fn contains<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        self.positions(item).all(|position| {
+            let mask = 1u64 << (position % 64);
+            self.words[(position / 64) as usize].load(Ordering::Acquire) & mask != 0
+        })
+    }
+
+    // Theoretical false-positive rate after `items` distinct inserts
+    pub fn expected_false_positive_rate(&self, items: usize) -> f64 {
+        let k = self.hashes as f64;
+        (1.0 - (-k * items as f64 / self.bits as f64).exp()).powf(k)
+    }
+}
@@ -0,0 +1,140 @@
+/*!
+ * Bounded Buffer - producers and consumers on a Mutex and two Condvars
+ *
+ * The channel demos hand values over mpsc, which hides the waiting inside
+ * the channel. BoundedBuffer does that waiting in the open, the way a
+ * textbook monitor does: one Mutex guards a VecDeque with a fixed bound,
+ * and two condition variables say what a waiting thread is waiting for.
+ *
+ *   push on a full buffer    waits on `not_full`, woken by a pop
+ *   pop on an empty buffer   waits on `not_empty`, woken by a push
+ *
+ * A Condvar wait releases the lock while the thread sleeps and takes it
+ * back before returning, and a wakeup is only a hint - another thread may
+ * have got there first - so every wait rechecks its condition in a loop
+ * (`wait_while`). close() ends the exchange: waiting producers get their
+ * value back and consumers drain what is left, then get None.
+ */
+
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+struct BufferState<T> {
+    items: VecDeque<T>,
+    closed: bool,
+    stats: BufferStats,
+}
+
+// What the buffer is doing right now, and how often threads had to wait
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BufferStats {
+    pub len: usize,
+    pub waiting_producers: usize,  // blocked in push right now
+    pub waiting_consumers: usize,  // blocked in pop right now
+    pub blocked_pushes: u64,       // pushes that found the buffer full
+    pub blocked_pops: u64,         // pops that found the buffer empty
+}
+
+#[derive(Debug)]
+pub struct BoundedBuffer<T> {
+    state: Mutex<BufferState<T>>,
+    not_full: Condvar,   // signalled by pop and close
+    not_empty: Condvar,  // signalled by push and close
+    capacity: usize,
+}
+
+impl<T> BoundedBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "a buffer needs room for at least one value");
+        BoundedBuffer {
+            state: Mutex::new(BufferState {
+                items: VecDeque::with_capacity(capacity),
+                closed: false,
+                stats: BufferStats::default(),
+            }),
+            not_full: Condvar::new(),
+            not_empty: Condvar::new(),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // A push or pop either happens whole or not at all, so a poisoned
+    // lock still guards a consistent buffer
+    fn lock(&self) -> MutexGuard<'_, BufferState<T>> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Blocks while the buffer is full. Err(value) once it is closed
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let mut state = self.lock();
+        let capacity = self.capacity;
+        if state.items.len() >= capacity && !state.closed {
+            state.stats.blocked_pushes += 1;
+            state.stats.waiting_producers += 1;
+            state = self.not_full
+                .wait_while(state, |state| state.items.len() >= capacity && !state.closed)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            state.stats.waiting_producers -= 1;
+        }
+        if state.closed {
+            return Err(value);
+        }
+        state.items.push_back(value);
+        drop(state);
+        self.not_empty.notify_one();
+        Ok(())
+    }
+
+    // Blocks while the buffer is empty. None once it is closed and drained
+    pub fn pop(&self) -> Option<T> {
+        let mut state = self.lock();
+        if state.items.is_empty() && !state.closed {
+            state.stats.blocked_pops += 1;
+            state.stats.waiting_consumers += 1;
+            state = self.not_empty
+                .wait_while(state, |state| state.items.is_empty() && !state.closed)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            state.stats.waiting_consumers -= 1;
+        }
+        let value = state.items.pop_front();
+        drop(state);
+        if value.is_some() {
+            self.not_full.notify_one();
+        }
+        value
+    }
+
+    // No more pushes: wakes every waiting thread so none sleeps forever
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.not_full.notify_all();
+        self.not_empty.notify_all();
+    }
+
+    pub fn stats(&self) -> BufferStats {
+        let state = self.lock();
+        BufferStats { len: state.items.len(), ..state.stats }
+    }
+
+    // Polls until the buffer reaches a state another thread is expected
+    // to put it in (a producer blocked, say). False if it never did
+    pub fn wait_until(&self, timeout: Duration, condition: impl Fn(&BufferStats) -> bool) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if condition(&self.stats()) {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
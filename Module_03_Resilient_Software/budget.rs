@@ -0,0 +1,119 @@
+/*!
+ * Demo Time Budgets
+ *
+ * The runner gives every demonstration a time budget so a classroom run
+ * cannot hang indefinitely. The demo runs on its own thread while the
+ * runner watches the clock: past the soft budget it prints a warning and
+ * lets the demo continue; past the hard budget the run is cancelled with
+ * exit code 3. A thread cannot be stopped from outside, so cancelling
+ * means ending the process - after saying which demo overran.
+ *
+ * If the OS refuses the demo its thread, the demo runs on the caller's
+ * thread instead, without a budget - late is better than not at all.
+ *
+ * Time a demo spends waiting at an interactive step (step.rs) is not
+ * counted: the budget is for the demo, not for the lecturer.
+ *
+ * The runner also watches for Ctrl+C (platform.rs) and says that the
+ * demo will be allowed to finish, so the first press is not mistaken
+ * for one that went unnoticed.
+ *
+ * The watchdog sleeps on an Event (resilient_core) that the demo's
+ * thread sets when it is done - returned or panicked - so between its
+ * checks the runner is parked by the OS, not polling.
+ */
+
+use std::panic;
+use std::process;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use resilient_core::Event;
+
+use crate::console;
+use crate::event_log;
+use crate::platform;
+use crate::step;
+use crate::trace;
+
+pub const CANCELLED_EXIT_CODE: i32 = 3;
+const INTERRUPT_POLL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Budget {
+    pub soft: Duration,  // warn once past this
+    pub hard: Duration,  // cancel the run past this
+}
+
+// Run `work` under `budget`; returns its value, or re-raises its panic
+pub fn run_budgeted<T: Send>(name: &str, budget: Budget, work: impl FnOnce() -> T + Send) -> T {
+    let (start, paused_before) = (Instant::now(), step::paused_time());
+    let elapsed = || start.elapsed().saturating_sub(step::paused_time().saturating_sub(paused_before));
+    let _output = console::section();
+    // Spawning consumes its closure even when it fails; the work waits
+    // here so the fallback can still take it
+    let work = Mutex::new(Some(work));
+    let take_work = || work.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take().expect("work runs once");
+    let context = trace::current_context();
+    let finished = Event::new();
+    thread::scope(|scope| {
+        let (thread_context, finished) = (context.clone(), &finished);
+        let spawned = thread::Builder::new()
+            .name(name.to_string())
+            .spawn_scoped(scope, move || {
+                let _done = SetOnDrop(finished);  // a panic sets it too; join() below re-raises it
+                thread_context.enter(|| take_work()())
+            });
+        let worker = match spawned {
+            Ok(worker) => worker,
+            Err(error) => {
+                narrate!("budget.cannot_start", name, error);
+                return context.enter(|| take_work()());
+            }
+        };
+
+        let (mut warned, mut announced) = (false, false);
+        loop {
+            let deadline = if warned { budget.hard } else { budget.soft.min(budget.hard) };
+            match finished.wait_timeout(deadline.saturating_sub(elapsed()).min(INTERRUPT_POLL)) {
+                true => break,
+                // Not due yet, or paused at a step meanwhile: the deadline moved
+                false if elapsed() < deadline => {
+                    if platform::interrupted() && !std::mem::replace(&mut announced, true) {
+                        narrate!("budget.interrupted", name);
+                    }
+                }
+                false if !warned && elapsed() < budget.hard => {
+                    warned = true;
+                    overran(name, budget.soft, false);
+                    narrate!("budget.past_soft", name, soft = budget.soft.as_millis(), hard = budget.hard.as_millis());
+                }
+                false => {
+                    overran(name, budget.hard, true);
+                    narrate!("budget.past_hard", name, hard = budget.hard.as_millis());
+                    console::drain();
+                    process::exit(CANCELLED_EXIT_CODE);
+                }
+            }
+        }
+        worker.join().unwrap_or_else(|payload| panic::resume_unwind(payload))
+    })
+}
+
+// Tells the watchdog the demo is done, however it ended
+struct SetOnDrop<'a>(&'a Event);
+
+impl Drop for SetOnDrop<'_> {
+    fn drop(&mut self) {
+        self.0.set();
+    }
+}
+
+fn overran(name: &str, budget: Duration, hard: bool) {
+    event_log::record(event_log::Event::BudgetExceeded {
+        demo: name.to_string(),
+        budget_ms: budget.as_millis() as u64,
+        hard,
+    });
+}
@@ -1,4 +1,4 @@
-/**
+/*!
  * Rust Buffer Safety Example - TYPE SAFE
  * 
  * This program demonstrates how Rust prevents buffer overflows
@@ -6,15 +6,17 @@
  * ensuring memory safety without performance overhead.
  */
 
+use safety_demo::{narrate, narration, standalone};
+
 fn demonstrate_buffer_safety() {
     // Rust arrays know their size and are bounds-checked
     let mut buffer: [u8; 10] = [0; 10];
     
-    println!("Buffer size: {} bytes", buffer.len());
+    narrate!("buffer_safe.buffer_size", size = buffer.len());
     
     // Safe string handling with automatic bounds checking
     let input = "This string is much longer than 10 characters and would overflow in C++!";
-    println!("Input size: {} characters", input.len());
+    narrate!("buffer_safe.input_size", size = input.len());
     
     // Rust prevents buffer overflow by using safe methods
     // Option 1: Take only what fits safely
@@ -23,20 +25,20 @@ fn demonstrate_buffer_safety() {
     
     buffer[..copy_len].copy_from_slice(&safe_bytes[..copy_len]);
     
-    println!("Safely copied {} bytes", copy_len);
-    println!("Buffer contents: {:?}", &buffer);
+    narrate!("buffer_safe.copied", copied = copy_len);
+    narrate!("buffer_safe.contents", buffer = ?&buffer);
     
     // Option 2: Use Vec<u8> for dynamic sizing
     let mut dynamic_buffer = Vec::new();
     dynamic_buffer.extend_from_slice(input.as_bytes());
-    println!("Dynamic buffer size: {} bytes", dynamic_buffer.len());
+    narrate!("buffer_safe.dynamic_size", size = dynamic_buffer.len());
 }
 
 fn array_bounds_safety() {
     let arr = [1, 2, 3, 4, 5];
     
     // Safe access using indexing
-    println!("Valid access: arr[4] = {}", arr[4]);
+    narrate!("buffer_safe.valid_index", value = arr[4]);
     
     // Rust prevents bounds violations with runtime checks
     // These would panic with clear error messages:
@@ -45,32 +47,32 @@ fn array_bounds_safety() {
     
     // Safe alternatives using get() method
     match arr.get(10) {
-        Some(value) => println!("arr[10] = {}", value),
-        None => println!("Index 10 is out of bounds - safely handled!"),
+        Some(value) => narrate!("buffer_safe.get_out_of_bounds", value),
+        None => narrate!("buffer_safe.get_out_of_bounds_handled"),
     }
     
     match arr.get(4) {
-        Some(value) => println!("arr[4] = {} (safe access)", value),
-        None => println!("Index 4 is out of bounds"),
+        Some(value) => narrate!("buffer_safe.get_in_bounds", value),
+        None => narrate!("buffer_safe.get_in_bounds_missing"),
     }
     
     // Iterators provide safe access to all elements
-    println!("Safe iteration through array:");
+    narrate!("buffer_safe.iteration_heading");
     for (index, value) in arr.iter().enumerate() {
-        println!("  arr[{}] = {}", index, value);
+        narrate!("buffer_safe.iteration_item", index, value);
     }
 }
 
 fn slice_safety() {
-    let data = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+    let data: Vec<i32> = (1..=10).collect();
     
     // Safe slicing with bounds checking
     let safe_slice = &data[2..5];  // This is checked at runtime
-    println!("Safe slice [2..5]: {:?}", safe_slice);
+    narrate!("buffer_safe.safe_slice", slice = ?safe_slice);
     
     // Using get() for optional slicing
     if let Some(slice) = data.get(2..5) {
-        println!("Optional slice [2..5]: {:?}", slice);
+        narrate!("buffer_safe.optional_slice", slice = ?slice);
     }
     
     // This would panic if uncommented (bounds checked):
@@ -79,7 +81,48 @@ fn slice_safety() {
     // Safe alternative:
     let end_index = std::cmp::min(20, data.len());
     let safe_slice2 = &data[2..end_index];
-    println!("Safe slice with clamped bounds [2..{}]: {:?}", end_index, safe_slice2);
+    narrate!("buffer_safe.clamped_slice", end = end_index, slice = ?safe_slice2);
+}
+
+// Sizes and byte order that hold on every target. C code that reads a
+// length with memcpy into an int, or stores a file size in a size_t,
+// works on the machine it was written on and misreads on the next one.
+fn portable_sizes_and_byte_order() {
+    if cfg!(target_endian = "big") {
+        narrate!("buffer_safe.target_big", bits = usize::BITS);
+    } else {
+        narrate!("buffer_safe.target_little", bits = usize::BITS);
+    }
+    
+    // A frame as it arrives from the network: a 4-byte big-endian length,
+    // then the payload
+    let frame = [0, 0, 0, 5, b'h', b'e', b'l', b'l', b'o', b'!', b'!'];
+    let Some((header, rest)) = frame.split_first_chunk::<4>() else {
+        narrate!("buffer_safe.frame_too_short");
+        return;
+    };
+    let length = u32::from_be_bytes(*header);
+    narrate!("buffer_safe.length_big_endian", length);
+    narrate!("buffer_safe.length_native", length = u32::from_ne_bytes(*header));
+    
+    // The length comes from outside: convert it checked, then slice checked
+    let payload = usize::try_from(length).ok().and_then(|length| rest.get(..length));
+    match payload {
+        Some(payload) => narrate!("buffer_safe.frame_payload", payload = ?String::from_utf8_lossy(payload)),
+        None => narrate!("buffer_safe.frame_length_too_long"),
+    }
+    
+    // A size from a 64-bit file format, on a target whose usize may be 32 bits
+    let declared: u64 = 5_000_000_000;
+    match usize::try_from(declared) {
+        Ok(_) => narrate!("buffer_safe.size_fits", declared),
+        Err(_) => narrate!("buffer_safe.size_too_big", declared),
+    }
+    
+    // Bit patterns with an explicit byte order round-trip on any target
+    let value: u64 = 0x0102_0304_0506_0708;
+    narrate!("buffer_safe.le_bytes", hex = format!("{:#018x}", value), bytes = ?value.to_le_bytes());
+    narrate!("buffer_safe.be_round_trip", round_trips = u64::from_be_bytes(value.to_be_bytes()) == value);
 }
 
 // Demonstrate compile-time safety
@@ -96,29 +139,26 @@ fn compile_time_safety() {
     
     // Safe iteration instead
     for item in &arr {
-        println!("Safe access: {}", item);
+        narrate!("buffer_safe.iterator_item", item);
     }
 }
 
 fn main() {
-    println!("=== Rust Type Safe Buffer Operations ===");
-    
-    println!("\n1. Buffer Safety Demonstration:");
-    demonstrate_buffer_safety();
-    
-    println!("\n2. Array Bounds Safety:");
-    array_bounds_safety();
-    
-    println!("\n3. Slice Safety:");
-    slice_safety();
-    
-    println!("\n4. Compile-time Safety:");
-    compile_time_safety();
-    
-    println!("\nKey Points:");
-    println!("- Rust prevents buffer overflows at compile time and runtime");
-    println!("- Array bounds are always checked");
-    println!("- Safe alternatives (get(), iterators) are provided");
-    println!("- Performance is maintained through zero-cost abstractions");
-    println!("- Unsafe operations require explicit 'unsafe' blocks");
+    standalone::start();
+    narrate!("buffer_safe.heading");
+    
+    standalone::demonstrate("buffer_safety", &narration!("buffer_safe.buffer_safety_heading"), demonstrate_buffer_safety);
+    standalone::demonstrate("array_bounds_safety", &narration!("buffer_safe.array_bounds_safety_heading"), array_bounds_safety);
+    standalone::demonstrate("slice_safety", &narration!("buffer_safe.slice_safety_heading"), slice_safety);
+    standalone::demonstrate("compile_time_safety", &narration!("buffer_safe.compile_time_safety_heading"), compile_time_safety);
+    standalone::demonstrate("portable_sizes_and_byte_order", &narration!("buffer_safe.portable_sizes_and_byte_order_heading"),
+                            portable_sizes_and_byte_order);
+    
+    narrate!("buffer_safe.points_heading");
+    narrate!("buffer_safe.point_overflows");
+    narrate!("buffer_safe.point_bounds");
+    narrate!("buffer_safe.point_alternatives");
+    narrate!("buffer_safe.point_zero_cost");
+    narrate!("buffer_safe.point_unsafe");
+    narrate!("buffer_safe.point_byte_order");
 }
@@ -0,0 +1,190 @@
+/*!
+ * Circuit Breaker
+ *
+ * Stops calling a failing dependency instead of piling more work onto
+ * it. Closed: calls pass and failures are counted. Open: calls are
+ * rejected immediately until a cooldown passes. Half-open: a single
+ * trial call decides whether to close again or re-open.
+ *
+ * The cooldown is measured on a Clock, so with a MockClock the breaker
+ * walks through its states without any real waiting.
+ */
+
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::clock::SharedClock;
+
+/// Where a [`CircuitBreaker`] stands; scenario files name it in snake_case.
+///
+/// # Examples
+///
+/// ```
+/// use safety_demo::circuit_breaker::BreakerState;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Expect {
+///     state: BreakerState,
+/// }
+///
+/// let expect: Expect = toml::from_str(r#"state = "half_open""#).unwrap();
+/// assert_eq!(expect.state, BreakerState::HalfOpen);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct BreakerInner {
+    state: BreakerState,
+    failures: u32,
+    opened_at: Option<Duration>,  // clock time
+    trial_in_flight: bool,
+}
+
+/// Rejects calls to a dependency after `failure_threshold` failures in a
+/// row, until `cooldown` has passed on its clock; then lets one trial
+/// call through to decide whether to close again.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// use safety_demo::circuit_breaker::{BreakerState, CircuitBreaker};
+/// use safety_demo::clock::MockClock;
+///
+/// let clock = Arc::new(MockClock::new());
+/// let breaker = CircuitBreaker::new(2, Duration::from_secs(5), clock.clone());
+///
+/// // Two failures in a row trip it, and it rejects calls while open
+/// assert!(breaker.allow());
+/// breaker.record_failure();
+/// breaker.record_failure();
+/// assert_eq!(breaker.state(), BreakerState::Open);
+/// assert!(!breaker.allow());
+///
+/// // After the cooldown, one trial call at a time; its success closes it
+/// clock.advance(Duration::from_secs(5));
+/// assert_eq!(breaker.state(), BreakerState::HalfOpen);
+/// assert!(breaker.allow());
+/// assert!(!breaker.allow());
+/// breaker.record_success();
+/// assert_eq!(breaker.state(), BreakerState::Closed);
+///
+/// // An operator can trip it by hand
+/// breaker.force_open();
+/// assert_eq!(breaker.state(), BreakerState::Open);
+/// ```
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    clock: SharedClock,
+    inner: Mutex<BreakerInner>,
+}
+
+// The clock is a trait object, so Debug shows the breaker's own state
+impl fmt::Debug for CircuitBreaker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CircuitBreaker")
+            .field("failure_threshold", &self.failure_threshold)
+            .field("cooldown", &self.cooldown)
+            .field("inner", &*self.lock())
+            .finish()
+    }
+}
+
+impl CircuitBreaker {
+    /// A closed breaker; a threshold of 0 counts as 1.
+    pub fn new(failure_threshold: u32, cooldown: Duration, clock: SharedClock) -> Self {
+        CircuitBreaker {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            clock,
+            inner: Mutex::new(BreakerInner {
+                state: BreakerState::Closed,
+                failures: 0,
+                opened_at: None,
+                trial_in_flight: false,
+            }),
+        }
+    }
+
+    // The breaker's own state stays valid even if a caller panicked
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerInner> {
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The current state, half-open once an open breaker's cooldown has passed.
+    pub fn state(&self) -> BreakerState {
+        let mut inner = self.lock();
+        self.refresh(&mut inner);
+        inner.state
+    }
+
+    // Open -> HalfOpen once the cooldown has passed
+    fn refresh(&self, inner: &mut BreakerInner) {
+        if inner.state == BreakerState::Open {
+            if let Some(opened_at) = inner.opened_at {
+                if self.clock.now().saturating_sub(opened_at) >= self.cooldown {
+                    inner.state = BreakerState::HalfOpen;
+                    inner.trial_in_flight = false;
+                }
+            }
+        }
+    }
+
+    /// May a call go through right now? Half-open, only the first caller
+    /// gets the trial.
+    pub fn allow(&self) -> bool {
+        let mut inner = self.lock();
+        self.refresh(&mut inner);
+        match inner.state {
+            BreakerState::Closed => true,
+            BreakerState::Open => false,
+            BreakerState::HalfOpen if inner.trial_in_flight => false,
+            BreakerState::HalfOpen => {
+                inner.trial_in_flight = true;
+                true
+            }
+        }
+    }
+
+    /// A call succeeded: the failure count restarts, and a trial closes it.
+    pub fn record_success(&self) {
+        let mut inner = self.lock();
+        inner.failures = 0;
+        if inner.state == BreakerState::HalfOpen {
+            inner.state = BreakerState::Closed;
+            inner.trial_in_flight = false;
+        }
+    }
+
+    /// A call failed: the threshold reached, or a failed trial, opens it.
+    pub fn record_failure(&self) {
+        let mut inner = self.lock();
+        inner.failures += 1;
+        let trip = inner.state == BreakerState::HalfOpen || inner.failures >= self.failure_threshold;
+        if trip && inner.state != BreakerState::Open {
+            inner.state = BreakerState::Open;
+            inner.opened_at = Some(self.clock.now());
+            inner.trial_in_flight = false;
+        }
+    }
+
+    /// Trip the breaker by hand (operator action or scripted scenario).
+    pub fn force_open(&self) {
+        let mut inner = self.lock();
+        inner.state = BreakerState::Open;
+        inner.opened_at = Some(self.clock.now());
+        inner.trial_in_flight = false;
+    }
+}
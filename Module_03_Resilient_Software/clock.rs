@@ -0,0 +1,93 @@
+/*!
+ * Clocks
+ *
+ * Code that depends on elapsed time asks a Clock instead of calling
+ * Instant::now() and thread::sleep() itself. SystemClock is real time.
+ * MockClock only moves when told to - and a sleep on it just moves it -
+ * so expiry, cooldowns and timeouts can be shown (and checked) step by
+ * step, instantly and with the same result every run.
+ */
+
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::atomic64::AtomicU64;
+
+pub trait Clock {
+    // Time since the clock's own starting point
+    fn now(&self) -> Duration;
+
+    fn sleep(&self, duration: Duration);
+}
+
+// A clock shared between threads and components
+pub type SharedClock = Arc<dyn Clock + Send + Sync>;
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        (**self).sleep(duration)
+    }
+}
+
+#[derive(Debug)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        SystemClock { start: Instant::now() }
+    }
+
+    pub fn shared() -> SharedClock {
+        Arc::new(SystemClock::new())
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MockClock {
+    nanos: AtomicU64,
+}
+
+impl MockClock {
+    pub fn new() -> Self {
+        MockClock::default()
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.nanos.fetch_add(by.as_nanos() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::SeqCst))
+    }
+
+    // Returns at once, with the time moved on
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
@@ -0,0 +1,191 @@
+/*!
+ * Console Output
+ *
+ * println! holds the stdout lock for one call, so two threads never mix
+ * within a line - but the worker threads of a demo still shuffle their
+ * lines together, and the reader loses track of whose story is whose.
+ * While a section runs, every console line goes through one writer
+ * thread instead:
+ *
+ *   - a worker thread started through spawn_policy collects its lines
+ *     and hands them over as one batch when it finishes (or every
+ *     BATCH_LINES lines), so they appear together
+ *   - any other thread - the section's own, the budget runner - hands
+ *     over each line as it is said, in order
+ *
+ * The queue to the writer is bounded: a chatty thread waits for the
+ * writer rather than piling up memory. When the section ends, the runner
+ * waits until the writer has written everything. Outside sections, lines
+ * are printed directly.
+ *
+ * SAFETY_DEMO_OUTPUT=interleaved (or --interleaved) prints every line the
+ * moment it is said, for when the interleaving is the point. The event
+ * log always records lines in the order they were said.
+ */
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::OnceLock;
+use std::thread;
+
+use crate::static_check;
+
+const BATCH_LINES: usize = static_check::nonzero(32);   // a worker's lines per batch, at most
+const QUEUE_BATCHES: usize = static_check::nonzero(64); // batches waiting for the writer
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    Batched,      // worker lines grouped per thread
+    Interleaved,  // every line printed the moment it is said
+}
+
+impl std::str::FromStr for OutputMode {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, ()> {
+        match value.trim().to_lowercase().as_str() {
+            "batched" => Ok(OutputMode::Batched),
+            "interleaved" => Ok(OutputMode::Interleaved),
+            _ => Err(()),
+        }
+    }
+}
+
+pub const OUTPUT_CHOICES: &str = "batched, interleaved";
+
+enum Job {
+    Lines(Vec<String>),
+    Drained(mpsc::Sender<()>),
+}
+
+static INTERLEAVED: AtomicBool = AtomicBool::new(false);
+static SECTIONS_RUNNING: AtomicUsize = AtomicUsize::new(0);
+static WRITER: OnceLock<Option<SyncSender<Job>>> = OnceLock::new();
+
+thread_local! {
+    // Some while this thread is a spawn_policy worker
+    static BATCH: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+pub fn set_mode(mode: OutputMode) {
+    INTERLEAVED.store(mode == OutputMode::Interleaved, Ordering::Relaxed);
+}
+
+fn print_now(lines: &[String]) {
+    let mut stdout = io::stdout().lock();
+    for line in lines {
+        // The console going away must not take the demo with it
+        let _ = writeln!(stdout, "{}", line);
+    }
+    let _ = stdout.flush();
+}
+
+// The writer thread, started on first use; None if it could not be
+fn writer() -> Option<&'static SyncSender<Job>> {
+    WRITER
+        .get_or_init(|| {
+            let (sender, jobs) = mpsc::sync_channel(QUEUE_BATCHES);
+            let started = thread::Builder::new().name("console".to_string()).spawn(move || {
+                for job in jobs {
+                    match job {
+                        Job::Lines(lines) => print_now(&lines),
+                        Job::Drained(done) => {
+                            let _ = done.send(());
+                        }
+                    }
+                }
+            });
+            started.ok().map(|_| sender)
+        })
+        .as_ref()
+}
+
+fn emit(lines: Vec<String>) {
+    if INTERLEAVED.load(Ordering::Relaxed) || SECTIONS_RUNNING.load(Ordering::SeqCst) == 0 {
+        return print_now(&lines);
+    }
+    match writer() {
+        Some(writer) => {
+            if let Err(mpsc::SendError(Job::Lines(lines))) = writer.send(Job::Lines(lines)) {
+                print_now(&lines);
+            }
+        }
+        None => print_now(&lines),
+    }
+}
+
+// Print one line: now, or through the writer while a section runs
+pub fn line(text: String) {
+    if INTERLEAVED.load(Ordering::Relaxed) || SECTIONS_RUNNING.load(Ordering::SeqCst) == 0 {
+        return print_now(&[text]);
+    }
+    let mut text = Some(text);
+    // try_with: a thread's own destructors may still print after its
+    // batch is gone
+    let full = BATCH
+        .try_with(|batch| match batch.borrow_mut().as_mut() {
+            Some(lines) => {
+                lines.extend(text.take());
+                (lines.len() >= BATCH_LINES).then(|| mem::take(lines))
+            }
+            None => None,
+        })
+        .ok()
+        .flatten();
+    if let Some(lines) = full {
+        emit(lines);
+    }
+    if let Some(text) = text {
+        emit(vec![text]);
+    }
+}
+
+// Hands the worker's last lines over, even if it panicked
+struct BatchFlush;
+
+impl Drop for BatchFlush {
+    fn drop(&mut self) {
+        let lines = BATCH.try_with(|batch| batch.borrow_mut().take()).ok().flatten();
+        if let Some(lines) = lines.filter(|lines| !lines.is_empty()) {
+            emit(lines);
+        }
+    }
+}
+
+// Run `work` as a worker whose lines are batched (spawn_policy calls this
+// on every thread it starts)
+pub fn worker<T>(work: impl FnOnce() -> T) -> T {
+    BATCH.with(|batch| *batch.borrow_mut() = Some(Vec::new()));
+    let _flush = BatchFlush;
+    work()
+}
+
+// Wait until the writer has written everything queued so far
+pub fn drain() {
+    if let Some(writer) = WRITER.get().and_then(Option::as_ref) {
+        let (done, drained) = mpsc::channel();
+        if writer.send(Job::Drained(done)).is_ok() {
+            let _ = drained.recv();
+        }
+    }
+}
+
+// Lines go through the writer until the guard is dropped, which drains it
+pub fn section() -> SectionOutput {
+    SECTIONS_RUNNING.fetch_add(1, Ordering::SeqCst);
+    SectionOutput
+}
+
+pub struct SectionOutput;
+
+impl Drop for SectionOutput {
+    fn drop(&mut self) {
+        drain();
+        SECTIONS_RUNNING.fetch_sub(1, Ordering::SeqCst);
+        // A worker that outlived the section may have queued more meanwhile
+        drain();
+    }
+}
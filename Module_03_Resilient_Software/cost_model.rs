@@ -0,0 +1,413 @@
+/*!
+ * Cost Models
+ *
+ * A benchmark that prints "12.3ms" says little on its own: on another
+ * machine, or for another input size, the number is different. What
+ * carries over is how the time grows with the input. Every benchmark here
+ * is annotated with the complexity its algorithm should have, and `bench`
+ * checks that annotation against the clock. It times each operation at
+ * several input sizes and fits every candidate model
+ *
+ *   time = overhead + c * f(n)    f = 1, log n, n, n log n, n^2
+ *
+ * by weighted least squares (relative error, so small sizes count as much
+ * as large ones). The overhead term absorbs fixed costs such as starting
+ * threads. The simplest model that fits about as well as the best one
+ * wins.
+ *
+ * Memory-bound code often fits one class too high: once the input
+ * outgrows a cache, every element costs a little more. So the verdict
+ * compares growth rates instead. The slope of log(time) over log(n) must
+ * stay within MARGIN of the annotated model's slope over the same sizes.
+ * A benchmark that grows faster than that is flagged as a regression:
+ * the code has become slower in a way more hardware will not fix.
+ *
+ * `--regress <name>` swaps a quadratic mistake into one benchmark, to
+ * show what a flagged regression looks like.
+ */
+
+use std::fmt;
+use std::hint::black_box;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use resilient_core::InlineString;
+
+use crate::event_log;
+use crate::progress::{Goal, Progress};
+use crate::rng::SeededRng;
+use crate::spawn_policy;
+use crate::static_check;
+use crate::term::{self, Severity};
+
+const SIZES: usize = static_check::nonzero(6);       // each twice the previous
+const THREADS: usize = static_check::nonzero(4);     // for the parallel algorithms
+const REGRESSED_BASE: usize = static_check::nonzero(1 << 9);  // quadratic code needs small inputs
+// A simpler model wins if its error is within this of the best one's
+const TIE_FACTOR: f64 = 1.25;
+const TIE_SLACK: f64 = 0.02;
+// Growth exponent allowed above the annotation: caches and allocation
+// noise stay under it, a slip to the next power of n does not
+const MARGIN: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Complexity {
+    Constant,
+    Log,
+    Linear,
+    NLogN,
+    Quadratic,
+}
+
+impl Complexity {
+    pub const ALL: [Complexity; 5] =
+        [Complexity::Constant, Complexity::Log, Complexity::Linear, Complexity::NLogN, Complexity::Quadratic];
+
+    fn cost(self, n: f64) -> f64 {
+        match self {
+            Complexity::Constant => 0.0,  // all overhead
+            Complexity::Log => n.log2(),
+            Complexity::Linear => n,
+            Complexity::NLogN => n * n.log2(),
+            Complexity::Quadratic => n * n,
+        }
+    }
+
+    // The slope of log(cost) over log(n) between two sizes
+    pub fn exponent(self, smallest: usize, largest: usize) -> f64 {
+        let (low, high) = (self.cost(smallest as f64), self.cost(largest as f64));
+        if low <= 0.0 || largest <= smallest {
+            return 0.0;
+        }
+        (high / low).ln() / (largest as f64 / smallest as f64).ln()
+    }
+}
+
+impl fmt::Display for Complexity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Complexity::Constant => "O(1)",
+            Complexity::Log => "O(log n)",
+            Complexity::Linear => "O(n)",
+            Complexity::NLogN => "O(n log n)",
+            Complexity::Quadratic => "O(n^2)",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Fit {
+    pub model: Complexity,
+    pub error: f64,     // relative RMS error of the fit
+    pub exponent: f64,  // slope of log(time) over log(n)
+}
+
+// time ~ overhead + c * f(n), weighted by 1/time^2; both terms >= 0
+fn fit_model(samples: &[(usize, Duration)], model: Complexity) -> (f64, f64, f64) {
+    let points: Vec<(f64, f64)> = samples
+        .iter()
+        .map(|&(n, time)| (model.cost(n as f64), time.as_secs_f64().max(1e-9)))
+        .collect();
+    let sum = |term: &dyn Fn(f64, f64) -> f64| points.iter().map(|&(f, t)| term(f, t) / (t * t)).sum::<f64>();
+    let (w, wf, wff, wt, wft) = (sum(&|_, _| 1.0), sum(&|f, _| f), sum(&|f, _| f * f), sum(&|_, t| t), sum(&|f, t| f * t));
+
+    let det = w * wff - wf * wf;
+    let (mut a, mut c) = if det.abs() > f64::EPSILON * w * wff { ((wt * wff - wf * wft) / det, (w * wft - wf * wt) / det) } else { (wt / w, 0.0) };
+    if a < 0.0 {
+        (a, c) = (0.0, if wff > 0.0 { wft / wff } else { 0.0 });
+    }
+    if c < 0.0 {
+        (a, c) = (wt / w, 0.0);
+    }
+    let error = (points.iter().map(|&(f, t)| ((t - a - c * f) / t).powi(2)).sum::<f64>() / points.len() as f64).sqrt();
+    (a, c, error)
+}
+
+// The simplest model that fits about as well as the best
+pub fn fit(samples: &[(usize, Duration)]) -> Fit {
+    let fits: Vec<(Complexity, f64)> = Complexity::ALL
+        .iter()
+        .map(|&model| {
+            let (_, _, error) = fit_model(samples, model);
+            (model, error)
+        })
+        .collect();
+    let best = fits.iter().map(|&(_, error)| error).fold(f64::INFINITY, f64::min);
+    let (model, error) = fits
+        .into_iter()
+        .find(|&(_, error)| error <= best * TIE_FACTOR + TIE_SLACK)
+        .expect("the best model is within its own tolerance");
+
+    let logs: Vec<(f64, f64)> =
+        samples.iter().map(|&(n, time)| ((n as f64).ln(), time.as_secs_f64().max(1e-9).ln())).collect();
+    let count = logs.len() as f64;
+    let (mean_x, mean_y) = (logs.iter().map(|p| p.0).sum::<f64>() / count, logs.iter().map(|p| p.1).sum::<f64>() / count);
+    let sxx: f64 = logs.iter().map(|&(x, _)| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = logs.iter().map(|&(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let exponent = if sxx > 0.0 { sxy / sxx } else { 0.0 };
+
+    Fit { model, error, exponent }
+}
+
+// Whether `fit` grows no faster than `expected` allows over `samples`
+pub fn within(expected: Complexity, fit: &Fit, samples: &[(usize, Duration)]) -> bool {
+    let smallest = samples.first().map_or(0, |&(n, _)| n);
+    let largest = samples.last().map_or(0, |&(n, _)| n);
+    fit.exponent <= expected.exponent(smallest, largest) + MARGIN
+}
+
+type Operation = fn(&[u64]) -> u64;
+
+// A benchmarked operation and the cost model it is annotated with
+pub struct Benchmark {
+    pub name: &'static str,
+    pub expected: Complexity,
+    base: usize,                   // smallest input, in u64 values
+    run: Operation,
+    regressed: Option<Operation>,  // the same result, quadratically
+}
+
+pub const BENCHMARKS: [Benchmark; 8] = [
+    Benchmark { name: "parallel_sum", expected: Complexity::Linear, base: 1 << 14, run: parallel_sum, regressed: None },
+    Benchmark { name: "parallel_sort", expected: Complexity::NLogN, base: 1 << 13, run: parallel_sort, regressed: None },
+    Benchmark { name: "buffer_copy", expected: Complexity::Linear, base: 1 << 15, run: buffer_copy, regressed: None },
+    Benchmark { name: "buffer_extend", expected: Complexity::Linear, base: 1 << 14, run: buffer_extend, regressed: Some(buffer_prepend) },
+    Benchmark { name: "checked_scan", expected: Complexity::Linear, base: 1 << 15, run: checked_scan, regressed: None },
+    Benchmark { name: "sorted_lookup", expected: Complexity::NLogN, base: 1 << 12, run: sorted_lookup, regressed: Some(linear_lookup) },
+    Benchmark { name: "string_registry", expected: Complexity::NLogN, base: 1 << 11, run: string_registry, regressed: None },
+    Benchmark { name: "inline_registry", expected: Complexity::NLogN, base: 1 << 11, run: inline_registry, regressed: None },
+];
+
+// Chunks summed on scoped threads
+fn parallel_sum(input: &[u64]) -> u64 {
+    let chunk = input.len().div_ceil(THREADS).max(1);
+    thread::scope(|scope| {
+        let workers: Vec<_> = input
+            .chunks(chunk)
+            .map(|part| spawn_policy::spawn_scoped(scope, move || part.iter().fold(0u64, |sum, &x| sum.wrapping_add(x))))
+            .collect();
+        workers.into_iter().filter_map(|worker| worker.join().ok()).fold(0, u64::wrapping_add)
+    })
+}
+
+fn merge(left: &[u64], right: &[u64]) -> Vec<u64> {
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() && j < right.len() {
+        if left[i] <= right[j] {
+            merged.push(left[i]);
+            i += 1;
+        } else {
+            merged.push(right[j]);
+            j += 1;
+        }
+    }
+    merged.extend_from_slice(&left[i..]);
+    merged.extend_from_slice(&right[j..]);
+    merged
+}
+
+// Chunks sorted on scoped threads, then merged pairwise
+fn parallel_sort(input: &[u64]) -> u64 {
+    let mut data = input.to_vec();
+    let chunk = data.len().div_ceil(THREADS).max(1);
+    thread::scope(|scope| {
+        let workers: Vec<_> = data.chunks_mut(chunk).map(|part| spawn_policy::spawn_scoped(scope, move || part.sort_unstable())).collect();
+        for worker in workers {
+            let _ = worker.join();
+        }
+    });
+    let mut runs: Vec<Vec<u64>> = data.chunks(chunk).map(<[u64]>::to_vec).collect();
+    while runs.len() > 1 {
+        runs = runs.chunks(2).map(|pair| if pair.len() == 2 { merge(&pair[0], &pair[1]) } else { pair[0].clone() }).collect();
+    }
+    let sorted = runs.pop().unwrap_or_default();
+    sorted.first().copied().unwrap_or(0) ^ sorted.last().copied().unwrap_or(0)
+}
+
+// The clamped copy from buffer_safe: only what fits is copied
+fn buffer_copy(input: &[u64]) -> u64 {
+    let mut buffer = vec![0u64; input.len() / 2];
+    let len = buffer.len().min(input.len());
+    buffer[..len].copy_from_slice(&input[..len]);
+    buffer.last().copied().unwrap_or(0)
+}
+
+// A growable buffer filled 64 values at a time
+fn buffer_extend(input: &[u64]) -> u64 {
+    let mut buffer = Vec::new();
+    for chunk in input.chunks(64) {
+        buffer.extend_from_slice(chunk);
+    }
+    buffer.len() as u64
+}
+
+// The same buffer, built by inserting at the front: every insert moves
+// everything already there
+fn buffer_prepend(input: &[u64]) -> u64 {
+    let mut buffer = Vec::new();
+    for &value in input.iter().rev() {
+        buffer.insert(0, value);
+    }
+    buffer.len() as u64
+}
+
+// Every index read through get(), bounds-checked
+fn checked_scan(input: &[u64]) -> u64 {
+    (0..input.len()).filter_map(|index| input.get(index)).fold(0u64, |sum, &x| sum.wrapping_add(x))
+}
+
+// Sort once, then binary-search every value
+fn sorted_lookup(input: &[u64]) -> u64 {
+    let mut sorted = input.to_vec();
+    sorted.sort_unstable();
+    input.iter().filter(|value| sorted.binary_search(value).is_ok()).count() as u64
+}
+
+// Every value searched for from the start
+fn linear_lookup(input: &[u64]) -> u64 {
+    input.iter().filter(|value| input.iter().any(|x| x == *value)).count() as u64
+}
+
+// A resource name such as "res-1f3a", at most 12 bytes long
+fn resource_name<Name: Default + fmt::Write>(value: u64) -> Name {
+    let mut name = Name::default();
+    let _ = write!(name, "res-{:x}", value as u32);
+    name
+}
+
+// A registry of resource names: one per value, sorted, then every name
+// looked up again
+fn registry<Name: Default + fmt::Write + Ord>(input: &[u64]) -> u64 {
+    let mut names: Vec<Name> = input.iter().map(|&value| resource_name(value)).collect();
+    names.sort_unstable();
+    input.iter().filter(|&&value| names.binary_search(&resource_name(value)).is_ok()).count() as u64
+}
+
+// The registry with a heap allocation per name, lookup keys included
+fn string_registry(input: &[u64]) -> u64 {
+    registry::<String>(input)
+}
+
+// The same registry with every name inline
+fn inline_registry(input: &[u64]) -> u64 {
+    registry::<InlineString<16>>(input)
+}
+
+impl Benchmark {
+    pub fn sizes(&self, scale: usize, regressed: bool) -> Vec<usize> {
+        let base = if regressed { REGRESSED_BASE } else { self.base } * scale;
+        (0..SIZES).map(|step| base << step).collect()
+    }
+
+    // The fastest of `repeats` runs at each size
+    pub fn measure(&self, sizes: &[usize], repeats: usize, regressed: bool, progress: &Progress) -> Vec<(usize, Duration)> {
+        let operation = if regressed { self.regressed.unwrap_or(self.run) } else { self.run };
+        let mut rng = SeededRng::new(sizes.len() as u64);
+        sizes
+            .iter()
+            .map(|&n| {
+                let input: Vec<u64> = (0..n).map(|_| rng.next_u64()).collect();
+                let fastest = (0..repeats)
+                    .map(|_| {
+                        let start = Instant::now();
+                        black_box(operation(black_box(&input)));
+                        start.elapsed()
+                    })
+                    .min()
+                    .unwrap_or_default();
+                progress.tick();
+                (n, fastest)
+            })
+            .collect()
+    }
+}
+
+struct Options {
+    scale: usize,
+    repeats: usize,
+    only: Option<String>,
+    regress: Option<String>,
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options { scale: 1, repeats: 5, only: None, regress: None };
+    let known = |value: &str| BENCHMARKS.iter().any(|bench| bench.name == value);
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{} needs a value", flag))?;
+        let invalid = || format!("invalid value for {}: {}", flag, value);
+        match flag.as_str() {
+            "--scale" => options.scale = value.parse().ok().filter(|&scale| scale > 0).ok_or_else(invalid)?,
+            "--repeat" => options.repeats = value.parse().ok().filter(|&repeats| repeats > 0).ok_or_else(invalid)?,
+            "--only" if known(value) => options.only = Some(value.clone()),
+            "--regress" if BENCHMARKS.iter().any(|bench| bench.name == value && bench.regressed.is_some()) => {
+                options.regress = Some(value.clone())
+            }
+            "--only" | "--regress" => return Err(invalid()),
+            other => return Err(format!("unknown option {}", other)),
+        }
+    }
+    Ok(options)
+}
+
+// `bench [--scale N] [--repeat N] [--only name] [--regress name]` - returns the exit code
+pub fn bench_command(args: &[String]) -> i32 {
+    let options = match parse_options(args) {
+        Ok(options) => options,
+        Err(error) => {
+            let regressable: Vec<&str> =
+                BENCHMARKS.iter().filter(|bench| bench.regressed.is_some()).map(|bench| bench.name).collect();
+            eprintln!("{}", error);
+            eprintln!("{}", narration!("cost_model.usage", regressable = regressable.join("|")));
+            return 2;
+        }
+    };
+    let selected: Vec<&Benchmark> =
+        BENCHMARKS.iter().filter(|bench| options.only.as_deref().is_none_or(|only| only == bench.name)).collect();
+
+    println!("{}", narration!("cost_model.heading"));
+    println!("{}", narration!("cost_model.sizes", sizes = SIZES, repeats = options.repeats));
+    if cfg!(debug_assertions) {
+        println!("{}", narration!("cost_model.debug_build"));
+    }
+    if let Some(name) = &options.regress {
+        println!("{}", narration!("cost_model.injected", name));
+    }
+
+    let progress = Progress::start("bench", Goal::Steps((selected.len() * SIZES) as u64));
+    let mut results = Vec::new();
+    for bench in &selected {
+        let regressed = options.regress.as_deref() == Some(bench.name);
+        let sizes = bench.sizes(options.scale, regressed);
+        let samples = bench.measure(&sizes, options.repeats, regressed, &progress);
+        results.push((bench, fit(&samples), samples));
+    }
+    progress.finish();
+
+    println!("{}", narration!("cost_model.header"));
+    let mut regressions = Vec::new();
+    for (bench, fit, samples) in &results {
+        let within = within(bench.expected, fit, samples);
+        let (largest, time) = samples.last().copied().unwrap_or_default();
+        let verdict = if within { narration!("cost_model.ok") } else { narration!("cost_model.regression") };
+        let line = narration!("cost_model.row", name = bench.name, expected = bench.expected, model = fit.model,
+                              exponent = fit.exponent, error = fit.error * 100.0, verdict, largest, time = ?time);
+        println!("{}", if within { line } else { term::paint(Severity::Violation, &line) });
+        event_log::check_invariant(&format!("{} grows no faster than {}", bench.name, bench.expected), within);
+        if !within {
+            regressions.push(bench.name);
+        }
+    }
+
+    if regressions.is_empty() {
+        println!("{}", narration!("cost_model.all_within"));
+        return 0;
+    }
+    println!("{}", narration!("cost_model.regressions", regressions = regressions.join(", ")));
+    for name in regressions {
+        let regress = options.regress.as_deref().filter(|regress| *regress == name).map(|name| format!(" --regress {}", name)).unwrap_or_default();
+        println!("{}", narration!("cost_model.reproduce", name, repeats = options.repeats, regress));
+    }
+    1
+}
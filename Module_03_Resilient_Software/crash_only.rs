@@ -0,0 +1,568 @@
+/*!
+ * Crash-Only Recovery
+ *
+ * Crash-only software has no clean shutdown path: the only way to stop is
+ * to crash, and the only way to start is to recover. If recovery is
+ * correct after a crash at *any* point, it is correct after a clean stop
+ * too, and there is only one startup path to test.
+ *
+ * `crash-only` tests exactly that. A child process keeps a small bank
+ * (ACCOUNTS balances) and applies a seeded, deterministic sequence of
+ * transfers. Every transfer is appended to a write-ahead log (WAL) as a
+ * checksummed record and synced before the child acknowledges it on
+ * stdout. Every
+ * CHECKPOINT_EVERY transfers, the balances are written to a temporary
+ * snapshot, synced, renamed over the old snapshot, and the log is
+ * truncated. The parent kills the child at a random point in that cycle,
+ * including halfway through a log record or a snapshot. It then recovers
+ * the state exactly as the next child would and checks two things:
+ *
+ *   consistent  the balances are exactly those after transfers 1..=seq,
+ *               for the seq recovered (no half-applied transfer)
+ *   durable     seq is at least the last transfer the child acknowledged
+ *
+ * Each trial crashes GENERATIONS children in a row in the same directory,
+ * so recovery also has to recover from a recovery.
+ *
+ * `--naive` runs the same transfers with the state file rewritten in
+ * place and no log; a crash mid-write leaves a torn file that nothing can
+ * recover.
+ *
+ * Killing a process is not a power cut: data written but not yet synced
+ * is still in the OS page cache and survives. Torn writes are simulated
+ * by writing part of a record and then dying.
+ */
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::event_log;
+use crate::extensions::{Checksum, Fletcher32};
+use crate::progress::{Goal, Progress};
+use crate::rng::SeededRng;
+use crate::static_check;
+use crate::term::{self, Severity};
+
+const ACCOUNTS: usize = static_check::nonzero(4);
+const OPENING_BALANCE: u64 = 100;
+const CHECKPOINT_EVERY: u64 = static_check::nonzero(8) as u64;
+const GENERATIONS: usize = 3;   // children crashed per trial
+const LAST_CRASH_OP: usize = 24;  // crashes land within this many transfers
+const RUNAWAY: u64 = 10_000;    // a child that never reaches its crash point
+
+const WAL: &str = "wal.log";
+const SNAPSHOT: &str = "snapshot";
+const SNAPSHOT_TMP: &str = "snapshot.tmp";
+const NAIVE_STATE: &str = "state";
+
+// Where in the write cycle the child dies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum CrashPoint {
+    BeforeAppend,    // the transfer never reached the log
+    TornAppend,      // half a log record written
+    BeforeSync,      // record written, not yet synced or acknowledged
+    AfterSync,       // record synced and acknowledged
+    TornSnapshot,    // half a temporary snapshot written
+    BeforeRename,    // temporary snapshot complete, not yet renamed
+    BeforeTruncate,  // snapshot renamed, log not yet truncated
+}
+
+const CRASH_POINTS: [CrashPoint; 7] = [
+    CrashPoint::BeforeAppend,
+    CrashPoint::TornAppend,
+    CrashPoint::BeforeSync,
+    CrashPoint::AfterSync,
+    CrashPoint::TornSnapshot,
+    CrashPoint::BeforeRename,
+    CrashPoint::BeforeTruncate,
+];
+
+impl CrashPoint {
+    fn name(self) -> &'static str {
+        match self {
+            CrashPoint::BeforeAppend => "before-append",
+            CrashPoint::TornAppend => "torn-append",
+            CrashPoint::BeforeSync => "before-sync",
+            CrashPoint::AfterSync => "after-sync",
+            CrashPoint::TornSnapshot => "torn-snapshot",
+            CrashPoint::BeforeRename => "before-rename",
+            CrashPoint::BeforeTruncate => "before-truncate",
+        }
+    }
+
+    fn parse(name: &str) -> Option<CrashPoint> {
+        CRASH_POINTS.iter().copied().find(|point| point.name() == name)
+    }
+
+    // Reached during a checkpoint rather than an append
+    fn in_checkpoint(self) -> bool {
+        self >= CrashPoint::TornSnapshot
+    }
+}
+
+// The points a design passes through; the naive one never checkpoints
+fn crash_points(naive: bool) -> &'static [CrashPoint] {
+    if naive {
+        &CRASH_POINTS[..4]
+    } else {
+        &CRASH_POINTS
+    }
+}
+
+// Die at once: no destructors, no flushing, no clean shutdown
+fn crash() -> ! {
+    #[cfg(target_os = "linux")]
+    // SAFETY: kill only sends a signal to this process
+    unsafe {
+        libc::kill(libc::getpid(), libc::SIGKILL);
+    }
+    std::process::abort()
+}
+
+// --- The state and its transfers ---------------------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+struct Bank {
+    seq: u64,  // transfers applied
+    balances: [u64; ACCOUNTS],
+}
+
+impl Bank {
+    fn opening() -> Bank {
+        Bank { seq: 0, balances: [OPENING_BALANCE; ACCOUNTS] }
+    }
+
+    // Transfer `seq` of the run seeded with `seed`; the same every time
+    fn transfer(seed: u64, seq: u64) -> (usize, usize, u64) {
+        let mut rng = SeededRng::new(seed ^ seq.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        let from = rng.range(0, ACCOUNTS - 1);
+        let to = (from + rng.range(1, ACCOUNTS - 1)) % ACCOUNTS;
+        (from, to, rng.range(1, 50) as u64)
+    }
+
+    // Apply a transfer; one that would overdraw is skipped but still counts
+    fn apply(&mut self, seq: u64, (from, to, amount): (usize, usize, u64)) {
+        if self.balances[from] >= amount {
+            self.balances[from] -= amount;
+            self.balances[to] += amount;
+        }
+        self.seq = seq;
+    }
+
+    // The state after transfers 1..=seq, computed without any files
+    fn expected(seed: u64, seq: u64) -> Bank {
+        let mut bank = Bank::opening();
+        for next in 1..=seq {
+            bank.apply(next, Bank::transfer(seed, next));
+        }
+        bank
+    }
+
+    fn encode(&self) -> String {
+        let balances: Vec<String> = self.balances.iter().map(u64::to_string).collect();
+        format!("{} {}", self.seq, balances.join(" "))
+    }
+
+    fn decode(text: &str) -> Option<Bank> {
+        let mut numbers = text.split_whitespace().map(|field| field.parse::<u64>().ok());
+        let seq = numbers.next()??;
+        let mut balances = [0; ACCOUNTS];
+        for balance in balances.iter_mut() {
+            *balance = numbers.next()??;
+        }
+        numbers.next().is_none().then_some(Bank { seq, balances })
+    }
+}
+
+// `payload checksum`, so a torn or corrupted line is recognized
+fn seal(payload: &str) -> String {
+    format!("{} {:08x}\n", payload, Fletcher32.digest(payload.as_bytes()))
+}
+
+// A log record: `seq from to amount`
+fn encode_record(seq: u64, (from, to, amount): (usize, usize, u64)) -> String {
+    format!("{} {} {} {}", seq, from, to, amount)
+}
+
+fn decode_record(payload: &str) -> Option<(u64, (usize, usize, u64))> {
+    let fields: Vec<u64> = payload.split_whitespace().map(|field| field.parse().ok()).collect::<Option<_>>()?;
+    match fields[..] {
+        [seq, from, to, amount] if (from as usize) < ACCOUNTS && (to as usize) < ACCOUNTS => {
+            Some((seq, (from as usize, to as usize, amount)))
+        }
+        _ => None,
+    }
+}
+
+fn unseal(line: &str) -> Option<&str> {
+    let (payload, digest) = line.rsplit_once(' ')?;
+    (u64::from_str_radix(digest, 16).ok()? == Fletcher32.digest(payload.as_bytes())).then_some(payload)
+}
+
+// --- Recovery -------------------------------------------------------------
+
+// The last complete snapshot plus every intact log record after it, and
+// the length of the log's intact prefix. A torn tail ends the replay.
+fn recover(dir: &Path) -> Result<(Bank, u64), String> {
+    let mut bank = match fs::read_to_string(dir.join(SNAPSHOT)) {
+        Ok(text) => unseal(text.trim_end_matches('\n'))
+            .and_then(Bank::decode)
+            .ok_or("the snapshot is corrupt")?,
+        Err(_) => Bank::opening(),
+    };
+
+    let log = fs::read_to_string(dir.join(WAL)).unwrap_or_default();
+    let mut intact = 0;
+    for line in log.split_inclusive('\n') {
+        let Some(payload) = line.strip_suffix('\n').and_then(unseal) else { break };
+        let (seq, transfer) = decode_record(payload).ok_or_else(|| format!("log record {:?} is malformed", payload))?;
+        if seq > bank.seq + 1 {
+            return Err(format!("the log skips from {} to {}", bank.seq, seq));
+        }
+        // Records the snapshot already covers are left from before a truncate
+        if seq == bank.seq + 1 {
+            bank.apply(seq, transfer);
+        }
+        intact += line.len() as u64;
+    }
+    Ok((bank, intact))
+}
+
+fn recover_naive(dir: &Path) -> Result<Bank, String> {
+    match fs::read_to_string(dir.join(NAIVE_STATE)) {
+        Ok(text) => Bank::decode(&text).ok_or_else(|| format!("the state file is torn: {:?}", text)),
+        Err(_) => Ok(Bank::opening()),
+    }
+}
+
+// --- The child ------------------------------------------------------------
+
+fn sync_dir(dir: &Path) {
+    // Makes a rename durable; not every platform can open a directory
+    if let Ok(handle) = File::open(dir) {
+        let _ = handle.sync_all();
+    }
+}
+
+// Write half of `text`, then die
+fn tear(file: &mut File, text: &str) -> ! {
+    let _ = file.write_all(&text.as_bytes()[..text.len() / 2]);
+    crash()
+}
+
+fn run_child(dir: &Path, seed: u64, crash_op: u64, point: CrashPoint) -> Result<(), String> {
+    let io = |error: std::io::Error| error.to_string();
+    let (mut bank, intact) = recover(dir)?;
+    let mut log = OpenOptions::new().create(true).append(true).open(dir.join(WAL)).map_err(io)?;
+    log.set_len(intact).map_err(io)?;  // drop a torn tail before appending
+    println!("recovered {}", bank.seq);
+
+    for op in 1..RUNAWAY {
+        let seq = bank.seq + 1;
+        let due = op >= crash_op;
+        let transfer = Bank::transfer(seed, seq);
+        let record = seal(&encode_record(seq, transfer));
+        if due && point == CrashPoint::BeforeAppend {
+            crash();
+        }
+        if due && point == CrashPoint::TornAppend {
+            tear(&mut log, &record);
+        }
+        log.write_all(record.as_bytes()).map_err(io)?;
+        if due && point == CrashPoint::BeforeSync {
+            crash();
+        }
+        log.sync_data().map_err(io)?;
+        println!("acked {}", seq);
+        if due && point == CrashPoint::AfterSync {
+            crash();
+        }
+        bank.apply(seq, transfer);
+
+        if seq % CHECKPOINT_EVERY == 0 {
+            let snapshot = seal(&bank.encode());
+            let mut tmp = File::create(dir.join(SNAPSHOT_TMP)).map_err(io)?;
+            if due && point == CrashPoint::TornSnapshot {
+                tear(&mut tmp, &snapshot);
+            }
+            tmp.write_all(snapshot.as_bytes()).map_err(io)?;
+            tmp.sync_all().map_err(io)?;
+            if due && point == CrashPoint::BeforeRename {
+                crash();
+            }
+            fs::rename(dir.join(SNAPSHOT_TMP), dir.join(SNAPSHOT)).map_err(io)?;
+            sync_dir(dir);
+            if due && point == CrashPoint::BeforeTruncate {
+                crash();
+            }
+            log.set_len(0).map_err(io)?;
+            log.sync_all().map_err(io)?;
+        }
+    }
+    Err(format!("never reached {} after op {}", point.name(), crash_op))
+}
+
+// Rewrite the whole state in place after every transfer; a crash before
+// the rewrite is complete tears it
+fn run_naive_child(dir: &Path, seed: u64, crash_op: u64, point: CrashPoint) -> Result<(), String> {
+    let io = |error: std::io::Error| error.to_string();
+    if point.in_checkpoint() {
+        return Err(format!("the naive design never reaches {}", point.name()));
+    }
+    let mut bank = recover_naive(dir)?;
+    println!("recovered {}", bank.seq);
+
+    for op in 1..RUNAWAY {
+        let seq = bank.seq + 1;
+        let due = op >= crash_op;
+        let mut next = bank.clone();
+        next.apply(seq, Bank::transfer(seed, seq));
+        let text = next.encode();
+        let mut file = File::create(dir.join(NAIVE_STATE)).map_err(io)?;  // truncates
+        if due && point == CrashPoint::BeforeAppend {
+            crash();
+        }
+        if due && point == CrashPoint::TornAppend {
+            tear(&mut file, &text);
+        }
+        file.write_all(text.as_bytes()).map_err(io)?;
+        if due && point == CrashPoint::BeforeSync {
+            crash();
+        }
+        file.sync_data().map_err(io)?;
+        println!("acked {}", seq);
+        if due && point == CrashPoint::AfterSync {
+            crash();
+        }
+        bank = next;
+    }
+    Err(format!("never reached {} after op {}", point.name(), crash_op))
+}
+
+// --- The parent -----------------------------------------------------------
+
+#[derive(Debug)]
+struct Generation {
+    acked: u64,      // last transfer the child acknowledged
+    recovered: u64,  // seq recovery came back with (if it did)
+    problems: Vec<String>,
+}
+
+// Start a child that crashes at `point` on or after op `crash_op`, then
+// recover and check what it left behind
+fn crash_generation(dir: &Path, seed: u64, crash_op: u64, point: CrashPoint, naive: bool) -> Generation {
+    let mut generation = Generation { acked: 0, recovered: 0, problems: Vec::new() };
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(error) => {
+            generation.problems.push(format!("cannot locate executable: {}", error));
+            return generation;
+        }
+    };
+    let mut command = Command::new(exe);
+    command
+        .args(["crash-only", "--child"])
+        .arg(dir)
+        .args(["--seed", &seed.to_string(), "--crash", &format!("{}:{}", crash_op, point.name())])
+        .env_remove("SAFETY_DEMO_EVENT_LOG")  // a killed child would tear the parent's log
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if naive {
+        command.arg("--naive");
+    }
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(error) => {
+            generation.problems.push(format!("cannot start child: {}", error));
+            return generation;
+        }
+    };
+
+    // Every ack is flushed before the next write, so the last one read is
+    // the last one made
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if let Some(seq) = line.strip_prefix("acked ").and_then(|seq| seq.parse().ok()) {
+                generation.acked = seq;
+            }
+        }
+    }
+    let output = child.wait_with_output();
+    match output {
+        Ok(output) if output.status.code().is_some() => generation.problems.push(format!(
+            "child exited instead of crashing ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+        Ok(_) => {}
+        Err(error) => generation.problems.push(format!("lost the child: {}", error)),
+    }
+
+    let recovered = if naive { recover_naive(dir) } else { recover(dir).map(|(bank, _)| bank) };
+    let consistent = match &recovered {
+        Ok(bank) => {
+            generation.recovered = bank.seq;
+            *bank == Bank::expected(seed, bank.seq)
+        }
+        Err(_) => false,
+    };
+    if !event_log::check_invariant("recovered state is consistent", consistent) {
+        generation.problems.push(match recovered {
+            Ok(bank) => format!("recovered {:?}, expected {:?}", bank.balances, Bank::expected(seed, bank.seq).balances),
+            Err(error) => format!("recovery failed: {}", error),
+        });
+    }
+    let durable = generation.recovered >= generation.acked;
+    if consistent && !event_log::check_invariant("acknowledged transfers survive a crash", durable) {
+        generation.problems.push(format!("acked {} but recovered only {}", generation.acked, generation.recovered));
+    }
+    generation
+}
+
+// One fresh child crashed at `point` (by name) on op `crash_op`, then
+// recovered; the problems found, if any
+pub fn crash_once(point: &str, crash_op: u64, seed: u64, naive: bool) -> Result<(), String> {
+    let point = CrashPoint::parse(point).ok_or_else(|| format!("no crash point named {}", point))?;
+    let dir = std::env::temp_dir().join(format!("safety-demo-crash-once-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).map_err(|error| format!("cannot create {}: {}", dir.display(), error))?;
+    let generation = crash_generation(&dir, seed, crash_op, point, naive);
+    let _ = fs::remove_dir_all(&dir);
+    if generation.problems.is_empty() {
+        Ok(())
+    } else {
+        Err(generation.problems.join("; "))
+    }
+}
+
+struct Options {
+    trials: usize,
+    seed: u64,
+    naive: bool,
+    child: Option<(PathBuf, u64, CrashPoint)>,  // run as the crashing child
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options { trials: 20, seed: SeededRng::time_seed(), naive: false, child: None };
+    let mut child_dir = None;
+    let mut crash_at = None;
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        if flag == "--naive" {
+            options.naive = true;
+            continue;
+        }
+        let value = args.next().ok_or_else(|| format!("{} needs a value", flag))?;
+        let invalid = || format!("invalid value for {}: {}", flag, value);
+        match flag.as_str() {
+            "--trials" => options.trials = value.parse().map_err(|_| invalid())?,
+            "--seed" => options.seed = value.parse().map_err(|_| invalid())?,
+            "--child" => child_dir = Some(PathBuf::from(value)),
+            "--crash" => {
+                let (op, point) = value.split_once(':').ok_or_else(invalid)?;
+                crash_at = Some((op.parse().map_err(|_| invalid())?, CrashPoint::parse(point).ok_or_else(invalid)?));
+            }
+            other => return Err(format!("unknown option {}", other)),
+        }
+    }
+    match (child_dir, crash_at) {
+        (Some(dir), Some((op, point))) => options.child = Some((dir, op, point)),
+        (None, None) => {}
+        _ => return Err("--child and --crash go together".to_string()),
+    }
+    Ok(options)
+}
+
+// `crash-only [--trials <n>] [--seed <n>] [--naive]` - returns the exit code
+pub fn crash_only_command(args: &[String]) -> i32 {
+    let options = match parse_options(args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            eprintln!("{}", narration!("crash_only.usage"));
+            return 2;
+        }
+    };
+    if let Some((dir, crash_op, point)) = &options.child {
+        let result = if options.naive {
+            run_naive_child(dir, options.seed, *crash_op, *point)
+        } else {
+            run_child(dir, options.seed, *crash_op, *point)
+        };
+        if let Err(error) = result {
+            eprintln!("{}", error);
+        }
+        return 3;  // reaching here at all means the child did not crash
+    }
+
+    let design = if options.naive { narration!("crash_only.naive_design") } else { narration!("crash_only.wal_design") };
+    println!("{}", narration!("crash_only.heading", trials = options.trials, generations = GENERATIONS, design,
+                              seed = options.seed));
+
+    let base = std::env::temp_dir().join(format!("safety-demo-crash-{}", std::process::id()));
+    let mut rng = SeededRng::new(options.seed);
+    let mut tally: BTreeMap<CrashPoint, (usize, usize)> = BTreeMap::new();  // (crashes, failed)
+    let mut failures = Vec::new();
+    let progress = Progress::start("crash-only", Goal::Steps((options.trials * GENERATIONS) as u64));
+
+    for trial in 0..options.trials {
+        let dir = base.join(format!("trial-{}", trial));
+        let _ = fs::remove_dir_all(&dir);
+        if let Err(error) = fs::create_dir_all(&dir) {
+            eprintln!("{}", narration!("crash_only.cannot_create", path = dir.display(), error));
+            return 2;
+        }
+        let seed = rng.next_u64();
+        let mut trial_failed = false;
+
+        for generation in 0..GENERATIONS {
+            let crash_op = rng.range(1, LAST_CRASH_OP) as u64;
+            let points = crash_points(options.naive);
+            let point = points[rng.range(0, points.len() - 1)];
+            progress.note(point.name());
+            let outcome = crash_generation(&dir, seed, crash_op, point, options.naive);
+            progress.tick();
+
+            let entry = tally.entry(point).or_default();
+            entry.0 += 1;
+            if !outcome.problems.is_empty() {
+                entry.1 += 1;
+                trial_failed = true;
+                for problem in &outcome.problems {
+                    failures.push(narration!("crash_only.failure", trial, generation = generation + 1,
+                                             point = point.name(), op = crash_op, acked = outcome.acked, problem));
+                }
+                break;  // later generations would only compound it
+            }
+        }
+        // Keep a failing trial's files for inspection
+        if !trial_failed {
+            let _ = fs::remove_dir_all(&dir);
+        }
+    }
+    progress.finish();
+
+    println!("{}", narration!("crash_only.header"));
+    for (point, (crashes, failed)) in &tally {
+        println!("{:<16} {:>8} {:>8}", point.name(), crashes, failed);
+    }
+
+    if failures.is_empty() {
+        let _ = fs::remove_dir_all(&base);
+        println!("\n{}", term::paint(Severity::Safe, &narration!("crash_only.all_consistent")));
+        0
+    } else {
+        println!("\n{}", term::paint(Severity::Violation, &narration!("crash_only.failures", count = failures.len())));
+        for failure in &failures {
+            println!("{}", term::paint(Severity::Violation, &format!("  {}", failure)));
+        }
+        println!("{}", narration!("crash_only.files_kept", path = base.display()));
+        println!("{}", narration!("crash_only.reproduce", seed = options.seed, trials = options.trials,
+                                  naive = if options.naive { " --naive" } else { "" }));
+        1
+    }
+}
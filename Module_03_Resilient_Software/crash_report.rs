@@ -0,0 +1,139 @@
+/*!
+ * Crash Reports
+ *
+ * The default panic hook prints one line to stderr, and that line is gone
+ * once the terminal scrolls or the lab machine is reset. Production
+ * services install their own hook and send a crash report instead. This
+ * module does the same for the harness: a panic on any thread writes one
+ * JSON file to SAFETY_DEMO_CRASH_DIR with
+ *
+ *   message, location and thread  what panicked, and where
+ *   demo                          the demonstration that was running
+ *   args and config               the command line and every setting,
+ *                                 enough to rerun it
+ *   recent_events                 the last event-log records, kept in
+ *                                 memory even when no log file is open
+ *   backtrace                     always captured, RUST_BACKTRACE or not
+ *
+ * then hands over to the previous hook, so the usual message still
+ * appears. Injected faults (fault::InjectedPanic) are expected and are
+ * not reported. Only the newest MAX_REPORTS files are kept.
+ */
+
+use std::backtrace::Backtrace;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::panic::{self, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::atomic64::AtomicU64;
+use crate::env_config::{EnvConfig, ENV_PREFIX};
+use crate::event_log::{self, Record};
+use crate::fault::InjectedPanic;
+use crate::static_check;
+
+const MAX_REPORTS: usize = static_check::nonzero(50);
+
+// Tells apart reports from the same process in the same millisecond
+static REPORTS_WRITTEN: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Serialize)]
+pub struct CrashReport {
+    pub time: u64,  // unix millis
+    pub pid: u32,
+    pub thread: String,
+    pub message: String,
+    pub location: String,  // file:line:column, or "" if unknown
+    pub demo: String,
+    pub args: Vec<String>,
+    pub config: BTreeMap<String, String>,  // SAFETY_DEMO_* name -> effective value
+    pub recent_events: Vec<Record>,
+    pub backtrace: Vec<String>,
+}
+
+impl CrashReport {
+    // Gather everything about the panicking thread except the panic itself
+    pub fn capture(message: String, location: String, config: &BTreeMap<String, String>) -> Self {
+        CrashReport {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis() as u64)
+                .unwrap_or(0),
+            pid: std::process::id(),
+            thread: thread::current()
+                .name()
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("{:?}", thread::current().id())),
+            message,
+            location,
+            demo: event_log::active_demo(),
+            args: std::env::args().collect(),
+            config: config.clone(),
+            recent_events: event_log::recent(),
+            backtrace: Backtrace::force_capture().to_string().lines().map(str::to_string).collect(),
+        }
+    }
+
+    // Write the report into `dir` (created if missing) and return its path
+    pub fn write(&self, dir: &Path) -> io::Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let number = REPORTS_WRITTEN.fetch_add(1, Ordering::Relaxed);
+        let path = dir.join(format!("crash-{}-{}-{}.json", self.time, self.pid, number));
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(&path, json)?;
+        prune(dir, MAX_REPORTS)?;
+        Ok(path)
+    }
+}
+
+// Delete all but the newest `keep` reports in `dir`
+pub fn prune(dir: &Path, keep: usize) -> io::Result<()> {
+    let mut reports: Vec<(u64, PathBuf)> = fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let name = path.file_name()?.to_str()?;
+            // crash-<time>-<pid>-<n>.json
+            let time = name.strip_prefix("crash-")?.strip_suffix(".json")?.split('-').next()?.parse().ok()?;
+            Some((time, path))
+        })
+        .collect();
+    reports.sort();
+    let excess = reports.len().saturating_sub(keep);
+    for (_, path) in reports.into_iter().take(excess) {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+// Report every real panic from now on; call once, after the config is loaded
+pub fn install(config: &EnvConfig) {
+    let dir = config.crash_dir.clone();
+    let settings: BTreeMap<String, String> = config
+        .settings()
+        .into_iter()
+        .map(|(key, value)| (format!("{}{}", ENV_PREFIX, key), value))
+        .collect();
+
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info: &PanicHookInfo| {
+        if !info.payload().is::<InjectedPanic>() {
+            let location = info
+                .location()
+                .map(|location| format!("{}:{}:{}", location.file(), location.line(), location.column()))
+                .unwrap_or_default();
+            let report = CrashReport::capture(event_log::panic_message(info.payload()), location, &settings);
+            // A failed report must not hide the panic itself
+            match report.write(&dir) {
+                Ok(path) => eprintln!("{}", narration!("crash_report.written", path = path.display())),
+                Err(error) => eprintln!("{}", narration!("crash_report.write_failed", dir = dir.display(), error)),
+            }
+        }
+        previous_hook(info);
+    }));
+}
@@ -0,0 +1,184 @@
+/*!
+ * Replicated Data Types (CRDTs)
+ *
+ * SafeCounter keeps one counter correct under many threads of one
+ * process. Across machines there is no shared memory to make atomic:
+ * every node keeps its own replica, updates it locally, and ships its
+ * state to the others. A CRDT's merge is commutative, associative and
+ * idempotent, so replicas that have seen the same updates end up equal -
+ * no matter how often, late or out of order the states arrive.
+ *
+ *   GCounter  - one count per node; merge takes the maximum of each
+ *   PNCounter - a GCounter of increments and one of decrements
+ *   OrSet     - every add gets a unique tag; a remove deletes only the
+ *               tags it has seen, so a concurrent add wins
+ */
+
+use std::collections::BTreeSet;
+
+use crate::network_sim::{LinkFaults, NetStats, NetworkSim, NodeId};
+
+pub trait Crdt: Clone {
+    fn merge(&mut self, other: &Self);
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GCounter {
+    counts: Vec<u64>,  // per node
+}
+
+impl GCounter {
+    pub fn increment(&mut self, node: NodeId, by: u64) {
+        if node >= self.counts.len() {
+            self.counts.resize(node + 1, 0);
+        }
+        self.counts[node] += by;
+    }
+
+    pub fn value(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+impl Crdt for GCounter {
+    fn merge(&mut self, other: &Self) {
+        if other.counts.len() > self.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (mine, theirs) in self.counts.iter_mut().zip(&other.counts) {
+            *mine = (*mine).max(*theirs);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PNCounter {
+    increments: GCounter,
+    decrements: GCounter,
+}
+
+impl PNCounter {
+    pub fn increment(&mut self, node: NodeId, by: u64) {
+        self.increments.increment(node, by);
+    }
+
+    pub fn decrement(&mut self, node: NodeId, by: u64) {
+        self.decrements.increment(node, by);
+    }
+
+    pub fn value(&self) -> i64 {
+        self.increments.value() as i64 - self.decrements.value() as i64
+    }
+}
+
+impl Crdt for PNCounter {
+    fn merge(&mut self, other: &Self) {
+        self.increments.merge(&other.increments);
+        self.decrements.merge(&other.decrements);
+    }
+}
+
+// (node, sequence number): unique per add
+type Tag = (NodeId, u64);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrSet<T: Ord + Clone> {
+    node: NodeId,
+    next_tag: u64,
+    added: BTreeSet<(T, Tag)>,
+    removed: BTreeSet<Tag>,
+}
+
+impl<T: Ord + Clone> OrSet<T> {
+    pub fn new(node: NodeId) -> Self {
+        OrSet { node, next_tag: 0, added: BTreeSet::new(), removed: BTreeSet::new() }
+    }
+
+    pub fn add(&mut self, value: T) {
+        self.next_tag += 1;
+        self.added.insert((value, (self.node, self.next_tag)));
+    }
+
+    // Removes the adds this replica has seen - not ones made concurrently
+    pub fn remove(&mut self, value: &T) {
+        let seen: Vec<Tag> = self.added.iter().filter(|(added, _)| added == value).map(|(_, tag)| *tag).collect();
+        self.removed.extend(seen);
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        self.added.iter().any(|(added, tag)| added == value && !self.removed.contains(tag))
+    }
+
+    pub fn elements(&self) -> Vec<T> {
+        let mut elements: Vec<T> = self
+            .added
+            .iter()
+            .filter(|(_, tag)| !self.removed.contains(tag))
+            .map(|(value, _)| value.clone())
+            .collect();
+        elements.dedup();
+        elements
+    }
+
+    // Same adds and removes seen (the node id and tag counter are each
+    // replica's own)
+    pub fn same_state(&self, other: &Self) -> bool {
+        self.added == other.added && self.removed == other.removed
+    }
+}
+
+impl<T: Ord + Clone> Crdt for OrSet<T> {
+    fn merge(&mut self, other: &Self) {
+        self.added.extend(other.added.iter().cloned());
+        self.removed.extend(other.removed.iter().copied());
+    }
+}
+
+#[derive(Debug)]
+pub struct Replication<C> {
+    pub replicas: Vec<C>,
+    pub diverged_rounds: usize,  // update rounds after which replicas disagreed
+    pub settle_rounds: usize,    // fault-free gossip rounds until they agreed
+    pub stats: NetStats,
+}
+
+// Each round every node applies `update` to its replica and broadcasts its
+// whole state over a network with `faults`; received states are merged.
+// After `rounds` the faults stop and gossip continues until `agree` holds
+// (or 20 more rounds pass).
+pub fn replicate<C, U, A>(mut replicas: Vec<C>, rounds: u64, faults: LinkFaults, seed: u64, mut update: U, agree: A) -> Replication<C>
+where
+    C: Crdt,
+    U: FnMut(NodeId, u64, &mut C),
+    A: Fn(&[C]) -> bool,
+{
+    let nodes = replicas.len();
+    let mut network = NetworkSim::new(nodes, faults, seed);
+    let mut diverged_rounds = 0;
+    let gossip = |network: &mut NetworkSim<C>, replicas: &mut Vec<C>| {
+        for (node, replica) in replicas.iter().enumerate() {
+            network.broadcast(node, replica.clone());
+        }
+        for envelope in network.tick() {
+            replicas[envelope.to].merge(&envelope.message);
+        }
+    };
+
+    for round in 0..rounds {
+        for (node, replica) in replicas.iter_mut().enumerate() {
+            update(node, round, replica);
+        }
+        gossip(&mut network, &mut replicas);
+        if !agree(&replicas) {
+            diverged_rounds += 1;
+        }
+    }
+
+    network.set_faults(LinkFaults::default());
+    let mut settle_rounds = 0;
+    while !agree(&replicas) && settle_rounds < 20 {
+        gossip(&mut network, &mut replicas);
+        settle_rounds += 1;
+    }
+    Replication { replicas, diverged_rounds, settle_rounds, stats: network.stats() }
+}
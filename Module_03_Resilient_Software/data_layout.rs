@@ -0,0 +1,145 @@
+/*!
+ * Data Layout: Array of Structs vs Struct of Arrays
+ *
+ * One SharedData per sensor, kept in a Vec, is an array of structs (AoS):
+ * each sensor's data pointer, sum and processing flag sit together in
+ * memory. That suits code that works on one sensor at a time. The
+ * statistics workload does the opposite - it reads the sum and the flag
+ * of every sensor and never looks at the data - yet each cache line it
+ * loads is mostly data pointers it does not need.
+ *
+ * SharedColumns is the same table turned around into a struct of arrays
+ * (SoA): one Vec per field. The statistics pass then streams through two
+ * dense arrays, touching a third of the bytes, and the sum loop can
+ * vectorize. Both layouts implement SharedTable, so callers - and the
+ * invariants - do not change; only the memory layout does.
+ */
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use resilient_core::SharedData;
+use resilient_core::invariants::Invariants;
+
+use crate::static_check;
+
+// What the statistics workload computes over every sensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TableStats {
+    pub total: i64,
+    pub largest: i32,
+    pub processing: usize,  // sensors with an odd number of values
+}
+
+// A table of sensors, each a SharedData, in whatever layout
+pub trait SharedTable {
+    fn with_sensors(sensors: usize) -> Self;
+
+    fn sensors(&self) -> usize;
+
+    fn add_value(&mut self, sensor: usize, value: i32);
+
+    // Reads every sensor's sum and flag, and none of its data
+    fn stats(&self) -> TableStats;
+
+    // Whether every sensor's sum matches its data
+    fn consistent(&self) -> bool;
+}
+
+// Array of structs: the layout the demos already use
+impl SharedTable for Vec<SharedData> {
+    fn with_sensors(sensors: usize) -> Self {
+        (0..sensors).map(|_| SharedData::new()).collect()
+    }
+
+    fn sensors(&self) -> usize {
+        self.len()
+    }
+
+    fn add_value(&mut self, sensor: usize, value: i32) {
+        self[sensor].add_value(value);
+    }
+
+    fn stats(&self) -> TableStats {
+        TableStats {
+            total: self.iter().map(|sensor| sensor.sum() as i64).sum(),
+            largest: self.iter().map(|sensor| sensor.sum()).max().unwrap_or(0),
+            processing: self.iter().filter(|sensor| sensor.is_processing()).count(),
+        }
+    }
+
+    fn consistent(&self) -> bool {
+        self.iter().all(|sensor| sensor.sum() == sensor.values().iter().sum::<i32>())
+    }
+}
+
+// Struct of arrays: entry i of every column belongs to sensor i
+#[derive(Debug, Invariants)]
+pub struct SharedColumns {
+    data: Vec<Arc<Vec<i32>>>,
+    #[invariant(self.sums.len() == self.data.len(), "one sum per sensor")]
+    #[invariant(self.sums.iter().zip(&self.data).all(|(sum, data)| *sum == data.iter().sum::<i32>()), "sum matches data")]
+    sums: Vec<i32>,
+    #[invariant(self.processing.len() == self.data.len(), "one flag per sensor")]
+    processing: Vec<bool>,
+}
+
+impl SharedTable for SharedColumns {
+    fn with_sensors(sensors: usize) -> Self {
+        SharedColumns {
+            data: (0..sensors).map(|_| Arc::new(Vec::new())).collect(),
+            sums: vec![0; sensors],
+            processing: vec![false; sensors],
+        }
+    }
+
+    fn sensors(&self) -> usize {
+        self.data.len()
+    }
+
+    // The same update as SharedData::add_value, one column at a time
+    fn add_value(&mut self, sensor: usize, value: i32) {
+        Arc::make_mut(&mut self.data[sensor]).push(value);
+        self.sums[sensor] += value;
+        self.processing[sensor] = !self.processing[sensor];
+    }
+
+    fn stats(&self) -> TableStats {
+        TableStats {
+            total: self.sums.iter().map(|&sum| sum as i64).sum(),
+            largest: self.sums.iter().copied().max().unwrap_or(0),
+            processing: self.processing.iter().filter(|&&flag| flag).count(),
+        }
+    }
+
+    fn consistent(&self) -> bool {
+        self.check_invariants().is_ok()
+    }
+}
+
+// Fill a table the same way whatever its layout: `values` per sensor
+pub fn filled<T: SharedTable>(sensors: usize, values: usize) -> T {
+    let mut table = T::with_sensors(sensors);
+    for round in 0..values {
+        for sensor in 0..sensors {
+            table.add_value(sensor, ((sensor * 7 + round * 13) % 100) as i32);
+        }
+    }
+    table
+}
+
+pub const SENSORS: usize = static_check::nonzero(1 << 18);
+pub const VALUES: usize = static_check::nonzero(3);  // per sensor
+const PASSES: usize = static_check::nonzero(20);
+
+// The statistics pass, timed: the fastest of PASSES runs
+pub fn time_stats<T: SharedTable>(table: &T) -> (TableStats, Duration) {
+    let mut best = Duration::MAX;
+    let mut stats = TableStats::default();
+    for _ in 0..PASSES {
+        let start = Instant::now();
+        stats = std::hint::black_box(table.stats());
+        best = best.min(start.elapsed());
+    }
+    (stats, best)
+}
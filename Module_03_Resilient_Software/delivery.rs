@@ -0,0 +1,121 @@
+/*!
+ * Delivery Semantics
+ *
+ * A producer sends numbered transfers to a consumer over a NetworkSim
+ * that loses and reorders messages - and acknowledgements. What the
+ * consumer ends up applying depends on the guarantee:
+ *
+ *   AtMostOnce  - send once, never retry: nothing is applied twice, but a
+ *                 lost message is gone for good
+ *   AtLeastOnce - retry until acknowledged: nothing is lost, but a lost
+ *                 ack makes the producer resend what already arrived
+ *   Idempotent  - at-least-once delivery to a consumer that remembers the
+ *                 ids it applied, so a redelivery is acknowledged and
+ *                 ignored: the effect of exactly once
+ */
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::network_sim::{LinkFaults, NetStats, NetworkSim};
+
+// Ticks the producer waits for an ack before resending
+pub const RETRY_AFTER: u64 = 8;
+
+// Ticks to keep delivering after the producer is done, so late and
+// duplicated messages still in flight arrive
+const DRAIN: u64 = 20;
+const _: () = assert!(DRAIN > RETRY_AFTER, "a resend must have time to arrive");
+
+const PRODUCER: usize = 0;
+const CONSUMER: usize = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Guarantee {
+    AtMostOnce,
+    AtLeastOnce,
+    Idempotent,
+}
+
+#[derive(Debug, Clone)]
+enum Packet {
+    Transfer { id: u64, amount: i64 },
+    Ack { id: u64 },
+}
+
+#[derive(Debug)]
+pub struct DeliveryRun {
+    pub attempts: u64,    // transfers put on the wire, resends included
+    pub lost: u64,        // transfers never applied
+    pub duplicates: u64,  // extra applications of a transfer
+    pub ignored: u64,     // redeliveries the consumer recognised and skipped
+    pub balance: i64,
+    pub expected: i64,
+    pub stats: NetStats,
+}
+
+// Transfer i (of `amount(i)`) is sent at tick i; the producer is done once
+// every transfer is acknowledged (at once for AtMostOnce), or 200 ticks
+// after the last first send
+pub fn run(guarantee: Guarantee, transfers: u64, amount: impl Fn(u64) -> i64, faults: LinkFaults, seed: u64) -> DeliveryRun {
+    let mut network = NetworkSim::new(2, faults, seed);
+    let mut unacked: BTreeMap<u64, u64> = BTreeMap::new();  // id -> tick last sent
+    let mut applied: BTreeMap<u64, u64> = BTreeMap::new();  // id -> times applied
+    let mut seen = HashSet::new();
+    let (mut attempts, mut ignored, mut balance) = (0, 0, 0);
+    let mut drain_until = None;
+
+    loop {
+        let now = network.now();
+        if now < transfers {
+            network.send(PRODUCER, CONSUMER, Packet::Transfer { id: now, amount: amount(now) });
+            attempts += 1;
+            if guarantee != Guarantee::AtMostOnce {
+                unacked.insert(now, now);
+            }
+        }
+        for (id, sent_at) in unacked.iter_mut() {
+            if now - *sent_at >= RETRY_AFTER {
+                network.send(PRODUCER, CONSUMER, Packet::Transfer { id: *id, amount: amount(*id) });
+                attempts += 1;
+                *sent_at = now;
+            }
+        }
+
+        for envelope in network.tick() {
+            match envelope.message {
+                Packet::Transfer { id, amount } => {
+                    if guarantee == Guarantee::Idempotent && !seen.insert(id) {
+                        ignored += 1;
+                    } else {
+                        balance += amount;
+                        *applied.entry(id).or_insert(0) += 1;
+                    }
+                    if guarantee != Guarantee::AtMostOnce {
+                        network.send(CONSUMER, PRODUCER, Packet::Ack { id });
+                    }
+                }
+                Packet::Ack { id } => {
+                    unacked.remove(&id);
+                }
+            }
+        }
+
+        let now = network.now();
+        if drain_until.is_none() && now >= transfers && (unacked.is_empty() || now >= transfers + 200) {
+            drain_until = Some(now + DRAIN);
+        }
+        if drain_until.is_some_and(|until| now >= until) {
+            break;
+        }
+    }
+
+    DeliveryRun {
+        attempts,
+        lost: transfers - applied.len() as u64,
+        duplicates: applied.values().map(|times| times - 1).sum(),
+        ignored,
+        balance,
+        expected: (0..transfers).map(&amount).sum(),
+        stats: network.stats(),
+    }
+}
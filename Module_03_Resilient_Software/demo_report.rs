@@ -0,0 +1,421 @@
+/*!
+ * Demo Registry and Reports
+ *
+ * What #[safety_demo] generates code against. A Section is one entry of
+ * the SECTIONS registry; a DemoRecorder brackets one run of a demo and,
+ * at the end, accounts for each invariant the demo declared: held,
+ * violated, or not checked at all. An invariant the demo declared but
+ * never checked is recorded as a failed check, so a refactor that drops a
+ * check cannot pass silently. The accounting comes back as the run's
+ * Outcome.
+ *
+ * Demo is what tooling sees of any demonstration: its name, what it
+ * shows, the safety properties it claims, and a way to run it for a
+ * report. Every Section is a Demo, and so is each of the other demo
+ * binaries (BinaryDemo), run as a child process whose properties hold
+ * when it runs to completion.
+ *
+ * A demo's prose claims about its run ("no lost increments!") go through
+ * claim(), which only says them when the checks behind them held.
+ */
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::panic::AssertUnwindSafe;
+use std::process::Command;
+
+use crate::budget;
+use crate::env_config::EnvConfig;
+use crate::event_log;
+use crate::messages::{self, Message};
+use crate::sections::registry;
+use crate::static_check;
+use crate::term::Severity;
+use crate::trace;
+use crate::verify;
+
+/// One entry of the SECTIONS registry; `#[safety_demo]` writes one for
+/// each demo function.
+///
+/// # Examples
+///
+/// ```
+/// use safety_demo::demo_report::{Demo, DemoRecorder, Outcome, Section};
+/// use safety_demo::env_config::EnvConfig;
+/// use safety_demo::event_log;
+///
+/// fn counting(_config: &EnvConfig) -> Outcome {
+///     let recorder = DemoRecorder::begin("counting", "Examples", &["no lost increments"]);
+///     event_log::check_invariant("no lost increments", 2 + 2 == 4);
+///     recorder.finish()
+/// }
+///
+/// let section = Section {
+///     name: "counting",
+///     module: "Examples",
+///     description: "Adds without losing an increment",
+///     invariants: &["no lost increments"],
+///     needs_unwind: false,
+///     run: counting,
+/// };
+/// assert_eq!(section.safety_properties(), ["no lost increments"]);
+/// assert!(section.run(&EnvConfig::default()).passed());
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Section {
+    pub name: &'static str,
+    pub module: &'static str,  // topic the demo belongs to
+    pub description: &'static str,
+    pub invariants: &'static [&'static str],
+    pub needs_unwind: bool,  // skipped where panics abort
+    pub run: fn(&EnvConfig) -> Outcome,
+}
+
+/// What tooling sees of any demonstration, section or binary.
+///
+/// # Examples
+///
+/// ```
+/// use safety_demo::demo_report::Demo;
+/// use safety_demo::sections;
+///
+/// let demos = sections::registry();
+/// let buffer_safe = demos.iter().find(|demo| demo.name() == "buffer_safe").unwrap();
+/// assert_eq!(buffer_safe.module(), "Demo Binaries");
+/// assert!(!buffer_safe.description().is_empty());
+/// assert_eq!(buffer_safe.safety_properties(), ["no out-of-bounds access"]);
+/// ```
+pub trait Demo: Sync {
+    fn name(&self) -> &'static str;
+    fn module(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    fn safety_properties(&self) -> &'static [&'static str];
+    fn run(&self, config: &EnvConfig) -> Outcome;
+}
+
+impl Demo for Section {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn module(&self) -> &'static str {
+        self.module
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn safety_properties(&self) -> &'static [&'static str] {
+        self.invariants
+    }
+
+    fn run(&self, config: &EnvConfig) -> Outcome {
+        (self.run)(config)
+    }
+}
+
+/// One of the other demo binaries, built next to this one. Running it
+/// runs the binary; its properties hold if it exits successfully.
+///
+/// # Examples
+///
+/// ```
+/// use safety_demo::demo_report::{BinaryDemo, Demo};
+///
+/// let demo = BinaryDemo {
+///     binary: "option_safe",
+///     description: "Option and Result replace null pointers and unchecked errors",
+///     properties: &["no null dereference"],
+/// };
+/// assert_eq!(demo.name(), "option_safe");
+/// assert_eq!(demo.module(), "Demo Binaries");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct BinaryDemo {
+    pub binary: &'static str,
+    pub description: &'static str,
+    pub properties: &'static [&'static str],
+}
+
+impl Demo for BinaryDemo {
+    fn name(&self) -> &'static str {
+        self.binary
+    }
+
+    fn module(&self) -> &'static str {
+        "Demo Binaries"
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn safety_properties(&self) -> &'static [&'static str] {
+        self.properties
+    }
+
+    // It runs with the same settings, flags included, as SAFETY_DEMO_*
+    // variables. Its output goes straight to stdout; every property holds
+    // if it exits successfully and is violated otherwise
+    fn run(&self, config: &EnvConfig) -> Outcome {
+        event_log::demo_started(self.binary);
+        let status = env::current_exe()
+            .map(|exe| exe.with_file_name(format!("{}{}", self.binary, env::consts::EXE_SUFFIX)))
+            .and_then(|path| {
+                let mut command = Command::new(path);
+                config.pass_to(&mut command);
+                command.status()
+            });
+        let completed = match status {
+            Ok(status) => status.success(),
+            Err(error) => {
+                narrate!("demo_report.cannot_run", binary = self.binary, error);
+                false
+            }
+        };
+        let verdict = if completed { Verdict::Held } else { Verdict::Violated };
+        for property in self.properties {
+            event_log::check_invariant(property, completed);
+        }
+        let verdicts = self.properties.iter().map(|property| (*property, verdict)).collect();
+        Outcome { name: self.binary, verdicts, skipped: false }
+    }
+}
+
+/// For a compile-time check on the registry.
+///
+/// # Examples
+///
+/// ```
+/// use safety_demo::demo_report::names_distinct;
+/// use safety_demo::sections::SECTIONS;
+///
+/// const _: () = assert!(names_distinct(&SECTIONS));
+/// assert!(!names_distinct(&[SECTIONS[0], SECTIONS[0]]));
+/// ```
+pub const fn names_distinct(sections: &[Section]) -> bool {
+    let mut i = 0;
+    while i < sections.len() {
+        let mut j = i + 1;
+        while j < sections.len() {
+            if static_check::same_str(sections[i].name, sections[j].name) {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// How one declared invariant fared in a run.
+///
+/// # Examples
+///
+/// ```
+/// use safety_demo::demo_report::{Outcome, Verdict};
+///
+/// let outcome = Outcome { name: "counting", verdicts: vec![("no lost increments", Verdict::NotChecked)], skipped: false };
+/// assert!(!outcome.passed());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verdict {
+    Held,
+    Violated,
+    NotChecked,  // declared, but the demo never checked it
+}
+
+// Padding applies, so verdicts line up in a column
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            Verdict::Held => Message::VerdictHeld {},
+            Verdict::Violated => Message::VerdictViolated {},
+            Verdict::NotChecked => Message::VerdictNotChecked {},
+        };
+        f.pad(&messages::text(&message))
+    }
+}
+
+/// How each declared invariant fared in one run. A skipped demo did not
+/// run, so it checked nothing and does not pass.
+///
+/// # Examples
+///
+/// ```
+/// use safety_demo::demo_report::{Outcome, Verdict};
+///
+/// let held = Outcome { name: "counting", verdicts: vec![("no lost increments", Verdict::Held)], skipped: false };
+/// assert!(held.passed());
+/// let skipped = Outcome { name: "counting", verdicts: Vec::new(), skipped: true };
+/// assert!(!skipped.passed());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub name: &'static str,
+    pub verdicts: Vec<(&'static str, Verdict)>,
+    pub skipped: bool,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        !self.skipped && self.verdicts.iter().all(|(_, verdict)| *verdict == Verdict::Held)
+    }
+}
+
+/// Brackets one run of a demo: begin() before it, finish() after, for
+/// its Outcome. An invariant never checked in between is a failure.
+///
+/// # Examples
+///
+/// ```
+/// use safety_demo::demo_report::{DemoRecorder, Verdict};
+/// use safety_demo::event_log;
+///
+/// let recorder = DemoRecorder::begin("forgetful", "Examples", &["checked", "forgotten"]);
+/// event_log::check_invariant("checked", true);
+/// let outcome = recorder.finish();
+/// assert_eq!(outcome.verdicts, [("checked", Verdict::Held), ("forgotten", Verdict::NotChecked)]);
+/// assert!(!outcome.passed());
+/// ```
+#[derive(Debug)]
+pub struct DemoRecorder {
+    name: &'static str,
+    module: &'static str,
+    declared: &'static [&'static str],
+    before: BTreeMap<String, (u64, u64)>,  // (passed, failed) when the demo started
+    _span: tracing::span::EnteredSpan,     // demo{name}, until the report is finished
+}
+
+impl DemoRecorder {
+    pub fn begin(name: &'static str, module: &'static str, declared: &'static [&'static str]) -> Self {
+        let span = trace::demo_span(name).entered();
+        event_log::demo_started(name);
+        DemoRecorder { name, module, declared, before: event_log::invariant_tally(), _span: span }
+    }
+
+    pub fn finish(self) -> Outcome {
+        let mut outcome = Outcome { name: self.name, verdicts: Vec::new(), skipped: false };
+        if self.declared.is_empty() {
+            return outcome;
+        }
+        let after = event_log::invariant_tally();
+        say!("{}", self);
+        for invariant in self.declared {
+            let count = |tally: &BTreeMap<String, (u64, u64)>| tally.get(*invariant).copied().unwrap_or((0, 0));
+            let ((passed_before, failed_before), (passed, failed)) = (count(&self.before), count(&after));
+            let verdict = match (passed - passed_before, failed - failed_before) {
+                (0, 0) => {
+                    event_log::check_invariant(invariant, false);
+                    Verdict::NotChecked
+                }
+                (_, 0) => Verdict::Held,
+                _ => Verdict::Violated,
+            };
+            let severity = if verdict == Verdict::Held { Severity::Safe } else { Severity::Violation };
+            say_as!(severity, "  {:<12} {}", verdict, invariant);
+            outcome.verdicts.push((invariant, verdict));
+        }
+        outcome
+    }
+}
+
+/// Say a demo's claim about its run if the checks behind it held, and
+/// that the run did not bear it out otherwise.
+///
+/// # Examples
+///
+/// ```
+/// use safety_demo::demo_report::claim;
+/// use safety_demo::event_log;
+///
+/// let total = 2 + 2;
+/// claim(event_log::check_invariant("no lost increments", total == 4), "No lost increments!");
+/// ```
+pub fn claim(held: bool, claim: &str) {
+    if held {
+        say!("{}", claim);
+    } else {
+        say_as!(Severity::Violation, "{}", messages::text(&Message::ClaimFailed { claim }));
+    }
+}
+
+/// `demos` lists the registry; `demos --json` lists it for tools;
+/// `demos run <name>` runs one demo and exits 0 only if its outcome passed
+/// (a skipped demo did not). Returns the exit code.
+///
+/// # Examples
+///
+/// ```
+/// use safety_demo::demo_report::demos_command;
+/// use safety_demo::env_config::EnvConfig;
+///
+/// let args = ["run".to_string(), "no_such_demo".to_string()];
+/// assert_eq!(demos_command(&args, &EnvConfig::default()), 2);
+/// ```
+pub fn demos_command(args: &[String], config: &EnvConfig) -> i32 {
+    let demos = registry();
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => {
+            println!("{}", narration!("demo_report.heading"));
+            for demo in &demos {
+                println!("\n{} ({})", demo.name(), demo.module());
+                println!("  {}", demo.description());
+                if !demo.safety_properties().is_empty() {
+                    println!("{}", narration!("demo_report.claims", claims = demo.safety_properties().join("; ")));
+                }
+            }
+            0
+        }
+        ["--json"] => {
+            let listing: Vec<serde_json::Value> = demos
+                .iter()
+                .map(|demo| {
+                    serde_json::json!({
+                        "name": demo.name(),
+                        "module": demo.module(),
+                        "description": demo.description(),
+                        "safety_properties": demo.safety_properties(),
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::Value::Array(listing));
+            0
+        }
+        ["run", name] => {
+            let Some(demo) = demos.iter().find(|demo| demo.name() == name) else {
+                eprintln!("{}", narration!("demo_report.no_such_demo", name));
+                return 2;
+            };
+            let run = AssertUnwindSafe(|| budget::run_budgeted(demo.name(), config.budget(), || demo.run(config)));
+            match verify::run_isolated(demo.name(), run) {
+                Ok(outcome) => {
+                    let verdict = match (outcome.skipped, outcome.passed()) {
+                        (true, _) => narration!("demo_report.skipped"),
+                        (false, true) => narration!("demo_report.passed"),
+                        (false, false) => narration!("demo_report.failed"),
+                    };
+                    println!("{}: {}", outcome.name, verdict);
+                    if outcome.passed() { 0 } else { verify::FAILED_EXIT_CODE }
+                }
+                Err(panicked) => {
+                    println!("{}", narration!("demo_report.panicked", demo = panicked.demo, message = panicked.message));
+                    verify::FAILED_EXIT_CODE
+                }
+            }
+        }
+        _ => {
+            eprintln!("{}", narration!("demo_report.usage"));
+            2
+        }
+    }
+}
+
+// The report's heading
+impl fmt::Display for DemoRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&messages::text(&Message::ReportHeading { demo: self.name, module: self.module }))
+    }
+}
@@ -0,0 +1,383 @@
+/*!
+ * Environment Configuration Layer
+ *
+ * Parses every demo setting from SAFETY_DEMO_* environment variables so
+ * lab machines can be configured without editing source files. Invalid
+ * input is reported through Result<T, ConfigError> instead of being
+ * silently replaced by a default - the same pattern as the
+ * TryFrom<RawResource> and TryFrom<&str> conversions in
+ * resilient_core/resource.rs.
+ */
+
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Command;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::budget::Budget;
+use crate::console::{self, OutputMode};
+use crate::messages;
+use crate::scheduler::Scheduler;
+use crate::static_check;
+use crate::trace;
+use crate::units::{Count, Millis, Quantity};
+
+pub const ENV_PREFIX: &str = "SAFETY_DEMO_";
+
+// Every key the loader understands (without the prefix)
+pub const KNOWN_KEYS: [&str; 26] = [
+    "THREADS",
+    "INCREMENTS",
+    "POOL",
+    "WRITES",
+    "READS",
+    "READERS",
+    "FAIRNESS_READERS",
+    "MESSAGES",
+    "ATOMIC_THREADS",
+    "TICK_MS",
+    "EVENT_LOG",
+    "LEVEL",
+    "STRESS_ROUNDS",
+    "BUDGET_MS",
+    "HARD_BUDGET_MS",
+    "PLUGIN_DIR",
+    "LANG",
+    "MESSAGES_DIR",
+    "CRASH_DIR",
+    "FAIL_SPAWNS",
+    "OUTPUT",
+    "INTERACTIVE",
+    "VERIFY",
+    "ALLOW_HANG",
+    "TRACE",
+    "SEED",
+];
+const _: () = assert!(static_check::distinct(&KNOWN_KEYS), "duplicate config key");
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    NotUnicode { key: String },
+    InvalidNumber { key: String, value: String },
+    OutOfRange { key: String, value: u64, min: u64, max: u64 },
+    UnknownKey { key: String },
+    InvalidChoice { key: String, value: String, choices: &'static str },
+    InvalidFilter { key: String, value: String, reason: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::NotUnicode { key } => {
+                write!(f, "{} is not valid unicode", key)
+            }
+            ConfigError::InvalidNumber { key, value } => {
+                write!(f, "{}={:?} is not a valid number", key, value)
+            }
+            ConfigError::OutOfRange { key, value, min, max } => {
+                write!(f, "{}={} is out of range ({}..={})", key, value, min, max)
+            }
+            ConfigError::UnknownKey { key } => {
+                write!(f, "{} is not a recognized setting (known: {})",
+                       key, KNOWN_KEYS.join(", "))
+            }
+            ConfigError::InvalidChoice { key, value, choices } => {
+                write!(f, "{}={:?} must be one of: {}", key, value, choices)
+            }
+            ConfigError::InvalidFilter { key, value, reason } => {
+                write!(f, "{}={:?} is not a valid trace filter: {}", key, value, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+// How deep each demonstration goes; every level shares the same sections
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Basic,         // the original demonstrations
+    Intermediate,  // adds repeated stress rounds and timing metrics
+    Advanced,      // adds the unsafe-counterpart variants
+}
+
+impl FromStr for Level {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, ()> {
+        match value.trim().to_lowercase().as_str() {
+            "basic" => Ok(Level::Basic),
+            "intermediate" => Ok(Level::Intermediate),
+            "advanced" => Ok(Level::Advanced),
+            _ => Err(()),
+        }
+    }
+}
+
+pub const LEVEL_CHOICES: &str = "basic, intermediate, advanced";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvConfig {
+    pub threads: Quantity<usize, Count>,         // counter demo worker threads
+    pub increments: Quantity<usize, Count>,      // increments per counter thread
+    pub pool: Quantity<usize, Count>,            // pool threads for the counter and mutex demos (0: a thread per task)
+    pub writes: Quantity<usize, Count>,          // values written by the mutex writer
+    pub reads: Quantity<usize, Count>,           // snapshots taken by the mutex reader
+    pub readers: Quantity<usize, Count>,         // concurrent RwLock readers
+    pub fairness_readers: Quantity<usize, Count>,  // most readers in the writer-starvation table (advanced)
+    pub messages: Quantity<usize, Count>,        // messages sent through the channel
+    pub atomic_threads: Quantity<usize, Count>,  // threads in the atomic operations demo
+    pub tick: Quantity<u64, Millis>,             // base unit for every simulated delay
+    pub event_log: Option<PathBuf>,              // JSONL event log to append to
+    pub level: Level,                            // demonstration depth
+    pub stress_rounds: Quantity<usize, Count>,   // repetitions at intermediate level and above
+    pub soft_budget: Quantity<u64, Millis>,      // per-demo soft time budget (warning)
+    pub hard_budget: Quantity<u64, Millis>,      // per-demo hard time budget (cancels the run)
+    pub plugin_dir: Option<PathBuf>,             // demo plugins to load (plugins feature)
+    pub locale: String,                          // message catalog, e.g. en-US
+    pub messages_dir: Option<PathBuf>,           // catalogs other than en-US (default: messages/ by the executable)
+    pub crash_dir: PathBuf,                      // where panic crash reports are written
+    pub fail_spawns: Quantity<usize, Count>,     // make every Nth demo spawn fail (0: never)
+    pub output: OutputMode,                      // worker lines batched or interleaved
+    pub interactive: bool,                       // pause at each demo phase for Enter
+    pub verify: bool,                            // exit nonzero unless every declared invariant held
+    pub allow_hang: bool,                        // let the deadlock demo block for real, watched by a timeout
+    pub trace: Option<String>,                   // tracing filter for stderr diagnostics
+    pub seed: Option<u64>,                       // reproducible scheduling with this seed
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        EnvConfig {
+            threads: Quantity::new(10),
+            increments: Quantity::new(1000),
+            pool: Quantity::new(0),
+            writes: Quantity::new(10),
+            reads: Quantity::new(5),
+            readers: Quantity::new(3),
+            fairness_readers: Quantity::new(16),
+            messages: Quantity::new(5),
+            atomic_threads: Quantity::new(5),
+            tick: Quantity::new(10),
+            event_log: None,
+            level: Level::Basic,
+            stress_rounds: Quantity::new(5),
+            soft_budget: Quantity::new(10_000),
+            hard_budget: Quantity::new(60_000),
+            plugin_dir: None,
+            locale: messages::DEFAULT_LOCALE.to_string(),
+            messages_dir: None,
+            crash_dir: default_crash_dir(),
+            fail_spawns: Quantity::new(0),
+            output: OutputMode::Batched,
+            interactive: false,
+            verify: false,
+            allow_hang: false,
+            trace: None,
+            seed: None,
+        }
+    }
+}
+
+impl EnvConfig {
+    // Load from the process environment
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let mut vars = Vec::new();
+        for (key, value) in env::vars_os() {
+            let key = key.to_string_lossy().into_owned();
+            if !key.starts_with(ENV_PREFIX) {
+                continue;
+            }
+            match value.into_string() {
+                Ok(value) => vars.push((key, value)),
+                Err(_) => return Err(ConfigError::NotUnicode { key }),
+            }
+        }
+        Self::from_vars(vars)
+    }
+
+    // Load from an explicit list of (key, value) pairs
+    pub fn from_vars<I>(vars: I) -> Result<Self, ConfigError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut config = EnvConfig::default();
+
+        for (key, value) in vars {
+            match key.strip_prefix(ENV_PREFIX) {
+                Some(name) => config.set(name, &key, value)?,
+                None => continue,  // Not ours - ignore
+            }
+        }
+
+        Ok(config)
+    }
+
+    // Apply one setting by name (without the prefix); `key` is how the
+    // caller spelled it - the variable or the flag - for error messages
+    pub fn set(&mut self, name: &str, key: &str, value: String) -> Result<(), ConfigError> {
+        match name {
+            "THREADS" => self.threads = parse_var(key, &value, 1, 256)?,
+            "INCREMENTS" => self.increments = parse_var(key, &value, 1, 1_000_000)?,
+            "POOL" => self.pool = parse_var(key, &value, 0, 256)?,
+            "WRITES" => self.writes = parse_var(key, &value, 1, 10_000)?,
+            "READS" => self.reads = parse_var(key, &value, 1, 10_000)?,
+            "READERS" => self.readers = parse_var(key, &value, 1, 256)?,
+            "FAIRNESS_READERS" => self.fairness_readers = parse_var(key, &value, 1, 256)?,
+            "MESSAGES" => self.messages = parse_var(key, &value, 0, 100_000)?,
+            "ATOMIC_THREADS" => self.atomic_threads = parse_var(key, &value, 1, 256)?,
+            "TICK_MS" => self.tick = parse_var(key, &value, 0, 10_000)?,
+            "EVENT_LOG" if value.trim().is_empty() => self.event_log = None,
+            "EVENT_LOG" => self.event_log = Some(PathBuf::from(value)),
+            "LEVEL" => self.level = parse_level(key, &value)?,
+            "STRESS_ROUNDS" => self.stress_rounds = parse_var(key, &value, 1, 10_000)?,
+            "BUDGET_MS" => self.soft_budget = parse_var(key, &value, 1, 86_400_000)?,
+            "HARD_BUDGET_MS" => self.hard_budget = parse_var(key, &value, 1, 86_400_000)?,
+            "PLUGIN_DIR" if value.trim().is_empty() => self.plugin_dir = None,
+            "PLUGIN_DIR" => self.plugin_dir = Some(PathBuf::from(value)),
+            "LANG" if value.trim().is_empty() => self.locale = messages::DEFAULT_LOCALE.to_string(),
+            "LANG" => self.locale = value.trim().to_string(),
+            "MESSAGES_DIR" if value.trim().is_empty() => self.messages_dir = None,
+            "MESSAGES_DIR" => self.messages_dir = Some(PathBuf::from(value)),
+            "CRASH_DIR" if value.trim().is_empty() => self.crash_dir = default_crash_dir(),
+            "CRASH_DIR" => self.crash_dir = PathBuf::from(value),
+            "FAIL_SPAWNS" => self.fail_spawns = parse_var(key, &value, 0, 1000)?,
+            "OUTPUT" => self.output = parse_output(key, &value)?,
+            "INTERACTIVE" => self.interactive = parse_switch(key, &value)?,
+            "VERIFY" => self.verify = parse_switch(key, &value)?,
+            "ALLOW_HANG" => self.allow_hang = parse_switch(key, &value)?,
+            "SEED" if value.trim().is_empty() => self.seed = None,
+            "SEED" => {
+                let seed = value.trim().parse().map_err(|_| ConfigError::InvalidNumber { key: key.to_string(), value: value.clone() })?;
+                self.seed = Some(seed);
+            }
+            "TRACE" if value.trim().is_empty() => self.trace = None,
+            "TRACE" => {
+                trace::check_filter(&value).map_err(|reason| ConfigError::InvalidFilter { key: key.to_string(), value: value.clone(), reason })?;
+                self.trace = Some(value);
+            }
+            _ => return Err(ConfigError::UnknownKey { key: key.to_string() }),
+        }
+        Ok(())
+    }
+
+    // Delay of `ticks` base units
+    pub fn ticks(&self, ticks: u64) -> Duration {
+        (self.tick * ticks).duration()
+    }
+
+    // Paces demo threads: seeded and reproducible if SEED is set
+    pub fn scheduler(&self) -> Scheduler {
+        match self.seed {
+            Some(seed) => Scheduler::seeded(seed),
+            None => Scheduler::real(self.ticks(1)),
+        }
+    }
+
+    // The effective value of every key, in KNOWN_KEYS order - enough to
+    // rerun with the same settings
+    pub fn settings(&self) -> Vec<(&'static str, String)> {
+        let path = |path: &Option<PathBuf>| path.as_ref().map(|path| path.display().to_string()).unwrap_or_default();
+        vec![
+            ("THREADS", self.threads.get().to_string()),
+            ("INCREMENTS", self.increments.get().to_string()),
+            ("POOL", self.pool.get().to_string()),
+            ("WRITES", self.writes.get().to_string()),
+            ("READS", self.reads.get().to_string()),
+            ("READERS", self.readers.get().to_string()),
+            ("FAIRNESS_READERS", self.fairness_readers.get().to_string()),
+            ("MESSAGES", self.messages.get().to_string()),
+            ("ATOMIC_THREADS", self.atomic_threads.get().to_string()),
+            ("TICK_MS", self.tick.get().to_string()),
+            ("EVENT_LOG", path(&self.event_log)),
+            ("LEVEL", format!("{:?}", self.level).to_lowercase()),
+            ("STRESS_ROUNDS", self.stress_rounds.get().to_string()),
+            ("BUDGET_MS", self.soft_budget.get().to_string()),
+            ("HARD_BUDGET_MS", self.hard_budget.get().to_string()),
+            ("PLUGIN_DIR", path(&self.plugin_dir)),
+            ("LANG", self.locale.clone()),
+            ("MESSAGES_DIR", path(&self.messages_dir)),
+            ("CRASH_DIR", self.crash_dir.display().to_string()),
+            ("FAIL_SPAWNS", self.fail_spawns.get().to_string()),
+            ("OUTPUT", format!("{:?}", self.output).to_lowercase()),
+            ("INTERACTIVE", if self.interactive { "on" } else { "off" }.to_string()),
+            ("VERIFY", if self.verify { "on" } else { "off" }.to_string()),
+            ("ALLOW_HANG", if self.allow_hang { "on" } else { "off" }.to_string()),
+            ("TRACE", self.trace.clone().unwrap_or_default()),
+            ("SEED", self.seed.map(|seed| seed.to_string()).unwrap_or_default()),
+        ]
+    }
+
+    // Hand these settings to a child process as SAFETY_DEMO_* variables,
+    // all but the event log, which only the parent writes
+    pub fn pass_to(&self, command: &mut Command) {
+        for (key, value) in self.settings() {
+            if !value.is_empty() && key != "EVENT_LOG" {
+                command.env(format!("{}{}", ENV_PREFIX, key), value);
+            }
+        }
+    }
+
+    pub fn budget(&self) -> Budget {
+        Budget {
+            soft: self.soft_budget.duration(),
+            hard: self.hard_budget.duration(),
+        }
+    }
+}
+
+fn default_crash_dir() -> PathBuf {
+    env::temp_dir().join("safety-demo-crashes")
+}
+
+pub fn parse_level(key: &str, value: &str) -> Result<Level, ConfigError> {
+    value.parse().map_err(|_| ConfigError::InvalidChoice {
+        key: key.to_string(),
+        value: value.to_string(),
+        choices: LEVEL_CHOICES,
+    })
+}
+
+pub fn parse_output(key: &str, value: &str) -> Result<OutputMode, ConfigError> {
+    value.parse().map_err(|_| ConfigError::InvalidChoice {
+        key: key.to_string(),
+        value: value.to_string(),
+        choices: console::OUTPUT_CHOICES,
+    })
+}
+
+// An on/off variable: on, true or 1; off, false, 0 or empty; any case
+fn parse_switch(key: &str, value: &str) -> Result<bool, ConfigError> {
+    match value.trim().to_lowercase().as_str() {
+        "on" | "true" | "1" => Ok(true),
+        "off" | "false" | "0" | "" => Ok(false),
+        _ => Err(ConfigError::InvalidChoice { key: key.to_string(), value: value.to_string(), choices: "on, off" }),
+    }
+}
+
+// Parse and range-check a single variable, in the unit of its field
+fn parse_var<T, U>(key: &str, value: &str, min: u64, max: u64) -> Result<Quantity<T, U>, ConfigError>
+where
+    T: TryFrom<u64>,
+{
+    let parsed: u64 = value.trim().parse().map_err(|_| ConfigError::InvalidNumber {
+        key: key.to_string(),
+        value: value.to_string(),
+    })?;
+
+    if parsed < min || parsed > max {
+        return Err(ConfigError::OutOfRange {
+            key: key.to_string(),
+            value: parsed,
+            min,
+            max,
+        });
+    }
+
+    T::try_from(parsed).map(Quantity::new).map_err(|_| ConfigError::InvalidNumber {
+        key: key.to_string(),
+        value: value.to_string(),
+    })
+}
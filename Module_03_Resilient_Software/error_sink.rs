@@ -0,0 +1,111 @@
+/*!
+ * Error Sink
+ *
+ * A panic inside a worker thread surfaces when the thread is joined, but
+ * a recoverable failure - a send to a channel nobody listens on, a lock
+ * poisoned by another thread - is usually swallowed or turned into a
+ * panic. Every worker gets a clone of an ErrorSink and reports such
+ * failures into one channel; the spawning code collects and summarizes
+ * them after joining its workers.
+ */
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{mpsc, LockResult};
+
+use crate::event_log::{self, Event};
+use crate::term::Severity;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ErrorKind {
+    ChannelClosed,  // the other end of a channel hung up
+    LockPoisoned,   // a thread panicked while holding the lock (recovered)
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::ChannelClosed => write!(f, "channel closed"),
+            ErrorKind::LockPoisoned => write!(f, "lock poisoned"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkerError {
+    pub worker: String,
+    pub kind: ErrorKind,
+    pub detail: String,
+}
+
+impl fmt::Display for WorkerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} ({})", self.worker, self.kind, self.detail)
+    }
+}
+
+// Cloned into every worker
+#[derive(Debug, Clone)]
+pub struct ErrorSink {
+    sender: mpsc::Sender<WorkerError>,
+}
+
+// Kept by the spawning code
+#[derive(Debug)]
+pub struct ErrorCollector {
+    receiver: mpsc::Receiver<WorkerError>,
+}
+
+pub fn channel() -> (ErrorSink, ErrorCollector) {
+    let (sender, receiver) = mpsc::channel();
+    (ErrorSink { sender }, ErrorCollector { receiver })
+}
+
+impl ErrorSink {
+    pub fn report(&self, worker: &str, kind: ErrorKind, detail: impl Into<String>) {
+        let error = WorkerError {
+            worker: worker.to_string(),
+            kind,
+            detail: detail.into(),
+        };
+        event_log::record(Event::WorkerError { worker: error.worker.clone(), error: error.to_string() });
+        // If the collector is gone nobody is left to tell - the event log has it
+        let _ = self.sender.send(error);
+    }
+
+    // Take the guard out of a lock result, reporting (not panicking on) poison
+    pub fn recover<G>(&self, result: LockResult<G>, worker: &str, lock: &str) -> G {
+        result.unwrap_or_else(|poisoned| {
+            self.report(worker, ErrorKind::LockPoisoned, narration!("error_sink.recovered", lock));
+            poisoned.into_inner()
+        })
+    }
+}
+
+impl ErrorCollector {
+    // Every error reported so far; call after the workers are joined
+    pub fn drain(&self) -> Vec<WorkerError> {
+        self.receiver.try_iter().collect()
+    }
+
+    // Print a per-kind summary of reported errors; returns how many there were
+    pub fn summarize(&self) -> usize {
+        let errors = self.drain();
+        if errors.is_empty() {
+            return 0;
+        }
+
+        let mut by_kind: BTreeMap<ErrorKind, Vec<&WorkerError>> = BTreeMap::new();
+        for error in &errors {
+            by_kind.entry(error.kind).or_default().push(error);
+        }
+        narrate_as!(Severity::Recovered, "error_sink.worker_errors", count = errors.len());
+        for (kind, reported) in by_kind {
+            let mut workers: Vec<&str> = reported.iter().map(|error| error.worker.as_str()).collect();
+            workers.sort_unstable();
+            workers.dedup();
+            narrate_as!(Severity::Recovered, "error_sink.by_kind", kind, count = reported.len(), workers = workers.join(", "));
+        }
+        errors.len()
+    }
+}
@@ -0,0 +1,483 @@
+/*!
+ * Structured Event Log
+ *
+ * Append-only JSONL record of a demo run: every console line plus the
+ * structured events behind it (demo started, invariant checked, fault
+ * injected, thread panicked, reference count changed, worker error,
+ * runtime metric, time budget exceeded, message sent or received). Each run appends to the same file under
+ * its own run id, and `replay` re-renders a past run's console output -
+ * so a student's nondeterministic result can be reproduced on screen
+ * exactly as they saw it.
+ */
+
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, LineWriter, Write};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::atomic64::AtomicU64;
+use crate::console;
+use crate::term::{self, Severity};
+use crate::trace;
+use crate::units::{Quantity, Unit};
+use crate::vector_clock::{self, MessageAction, MessageEvent, VectorClock};
+
+// Print a console line and record it in the event log (if one is open)
+#[macro_export]
+macro_rules! say {
+    () => {
+        $crate::event_log::say(String::new())
+    };
+    ($($arg:tt)*) => {
+        $crate::event_log::say(format!($($arg)*))
+    };
+}
+
+// The same, colored by severity on a terminal
+#[macro_export]
+macro_rules! say_as {
+    ($severity:expr, $($arg:tt)*) => {
+        $crate::event_log::say_as($severity, format!($($arg)*))
+    };
+}
+
+// A narration line from the message catalog, by key, with each named
+// argument filling the placeholder of that name - Display by default,
+// Debug with `?`, and a bare name passes the variable of that name:
+// narration!("shared_state.total", total = sum, seen = ?ids, elapsed)
+#[macro_export]
+macro_rules! narration {
+    ($key:literal $(, $($args:tt)*)?) => {
+        $crate::messages::narration($key, &$crate::narration_args!([] $($($args)*)?))
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! narration_args {
+    ([$($done:tt)*]) => {
+        [$($done)*]
+    };
+    ([$($done:tt)*] $name:ident = ? $value:expr $(, $($rest:tt)*)?) => {
+        $crate::narration_args!([$($done)* (stringify!($name), $crate::messages::Arg::Debug(&$value)),] $($($rest)*)?)
+    };
+    ([$($done:tt)*] $name:ident = $value:expr $(, $($rest:tt)*)?) => {
+        $crate::narration_args!([$($done)* (stringify!($name), $crate::messages::Arg::Display(&$value)),] $($($rest)*)?)
+    };
+    ([$($done:tt)*] $name:ident $(, $($rest:tt)*)?) => {
+        $crate::narration_args!([$($done)* (stringify!($name), $crate::messages::Arg::Display(&$name)),] $($($rest)*)?)
+    };
+}
+
+// say! and say_as! for a narration line
+#[macro_export]
+macro_rules! narrate {
+    ($($arg:tt)*) => {
+        $crate::event_log::say($crate::narration!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! narrate_as {
+    ($severity:expr, $($arg:tt)*) => {
+        $crate::event_log::say_as($severity, $crate::narration!($($arg)*))
+    };
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    DemoStarted { name: String },
+    DemoSkipped { name: String, reason: String },
+    Output { line: String },
+    InvariantChecked { invariant: String, passed: bool },
+    FaultInjected { fault: String },
+    ThreadPanicked { message: String },
+    RefCount { name: String, change: String, strong: usize },
+    WorkerError { worker: String, error: String },
+    Metric {
+        name: String,
+        value: f64,
+        #[serde(default)]  // logs written before metrics carried units
+        unit: String,
+    },
+    BudgetExceeded { demo: String, budget_ms: u64, hard: bool },
+    Message { process: String, action: MessageAction, peer: String, label: String, clock: VectorClock },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub run: u64,         // unix millis when the run started
+    pub seq: u64,         // position within the run
+    pub elapsed_ms: u64,  // time since the run started
+    pub thread: String,
+    pub demo: String,     // demo active when the event happened
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+struct EventLog {
+    run: u64,
+    start: Instant,
+    writer: Mutex<LineWriter<File>>,
+}
+
+static LOG: OnceLock<EventLog> = OnceLock::new();
+
+// Sequence, active demo and the last few records are kept even without a
+// log, so a crash report can say what the process was doing
+static SEQ: AtomicU64 = AtomicU64::new(0);
+static DEMO: Mutex<String> = Mutex::new(String::new());
+static FIRST_RECORD: OnceLock<Instant> = OnceLock::new();
+static RECENT: Mutex<VecDeque<Record>> = Mutex::new(VecDeque::new());
+const RECENT_LIMIT: usize = 32;
+
+// In-process (passed, failed) counts per invariant, kept even without a log
+static TALLY: Mutex<BTreeMap<String, (u64, u64)>> = Mutex::new(BTreeMap::new());
+
+// Open (or create) the log file for appending and start a new run
+pub fn open(path: &Path) -> io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let run = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0);
+
+    let log = EventLog {
+        run,
+        start: Instant::now(),
+        writer: Mutex::new(LineWriter::new(file)),
+    };
+    if LOG.set(log).is_err() {
+        return Err(io::Error::other("event log already open"));
+    }
+
+    // Record panics from any thread before the default hook reports them
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        record(Event::ThreadPanicked { message: panic_message(info.payload()) });
+        previous_hook(info);
+    }));
+
+    Ok(())
+}
+
+// The text of a panic payload (panic! with a literal or a format string)
+pub fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => match payload.downcast_ref::<String>() {
+            Some(message) => message.clone(),
+            None => "unknown panic".to_string(),
+        },
+    }
+}
+
+// Append one event to the log (if one is open) and the recent-events ring
+pub fn record(event: Event) {
+    trace::emit(&event);
+    let log = LOG.get();
+
+    // A poisoned lock only means another thread panicked mid-write;
+    // the log is still the best evidence of what happened, so keep going
+    let mut demo = DEMO.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Event::DemoStarted { name } | Event::DemoSkipped { name, .. } = &event {
+        *demo = name.clone();
+    }
+
+    let start = match log {
+        Some(log) => log.start,
+        None => *FIRST_RECORD.get_or_init(Instant::now),
+    };
+    let entry = Record {
+        run: log.map_or(0, |log| log.run),
+        seq: SEQ.fetch_add(1, Ordering::SeqCst),
+        elapsed_ms: start.elapsed().as_millis() as u64,
+        thread: thread::current()
+            .name()
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{:?}", thread::current().id())),
+        demo: demo.clone(),
+        event,
+    };
+    drop(demo);
+
+    {
+        let mut recent = RECENT.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if recent.len() == RECENT_LIMIT {
+            recent.pop_front();
+        }
+        recent.push_back(entry.clone());
+    }
+
+    let log = match log {
+        Some(log) => log,
+        None => return,
+    };
+    if let Ok(line) = serde_json::to_string(&entry) {
+        let mut writer = log.writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        // Logging must never take the demo down with it
+        let _ = writeln!(writer, "{}", line);
+    }
+}
+
+// Console output that is also captured for replay (used by say!)
+pub fn say(line: String) {
+    console::line(line.clone());
+    record(Event::Output { line });
+}
+
+pub fn say_as(severity: Severity, line: String) {
+    console::line(term::paint(severity, &line));
+    record(Event::Output { line });
+}
+
+pub fn metric<U: Unit>(name: &str, value: Quantity<f64, U>) {
+    record(Event::Metric { name: name.to_string(), value: value.get(), unit: U::SUFFIX.trim().to_string() });
+}
+
+pub fn message(event: &MessageEvent) {
+    record(Event::Message {
+        process: event.process.clone(),
+        action: event.action,
+        peer: event.peer.clone(),
+        label: event.label.clone(),
+        clock: event.clock.clone(),
+    });
+}
+
+// The demo most recently started, or "" before the first one
+pub fn active_demo() -> String {
+    // try_lock: this is called from the panic hook, possibly on a thread
+    // that already holds the lock
+    match DEMO.try_lock() {
+        Ok(demo) => demo.clone(),
+        Err(std::sync::TryLockError::Poisoned(poisoned)) => poisoned.into_inner().clone(),
+        Err(std::sync::TryLockError::WouldBlock) => String::new(),
+    }
+}
+
+// The last few records, oldest first (empty if the ring is busy)
+pub fn recent() -> Vec<Record> {
+    match RECENT.try_lock() {
+        Ok(recent) => recent.iter().cloned().collect(),
+        Err(std::sync::TryLockError::Poisoned(poisoned)) => poisoned.into_inner().iter().cloned().collect(),
+        Err(std::sync::TryLockError::WouldBlock) => Vec::new(),
+    }
+}
+
+pub fn demo_started(demo: &str) {
+    record(Event::DemoStarted { name: demo.to_string() });
+}
+
+// A demo that was not run, and why
+pub fn demo_skipped(demo: &str, reason: &str) {
+    record(Event::DemoSkipped { name: demo.to_string(), reason: reason.to_string() });
+}
+
+// Record an invariant check and hand the verdict back to the caller
+pub fn check_invariant(invariant: &str, passed: bool) -> bool {
+    {
+        let mut tally = TALLY.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let entry = tally.entry(invariant.to_string()).or_insert((0, 0));
+        if passed {
+            entry.0 += 1;
+        } else {
+            entry.1 += 1;
+        }
+    }
+    record(Event::InvariantChecked {
+        invariant: invariant.to_string(),
+        passed,
+    });
+    passed
+}
+
+// The (passed, failed) counts gathered so far
+pub fn invariant_tally() -> BTreeMap<String, (u64, u64)> {
+    TALLY.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+}
+
+// Take the (passed, failed) counts gathered so far and start over
+pub fn take_invariant_tally() -> BTreeMap<String, (u64, u64)> {
+    let mut tally = TALLY.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    std::mem::take(&mut *tally)
+}
+
+// Read every well-formed record from a log file
+pub fn load(path: &Path) -> io::Result<Vec<Record>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut records = Vec::new();
+
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Record>(&line) {
+            Ok(record) => records.push(record),
+            // A torn final line (e.g. the process was killed) is expected
+            Err(error) => eprintln!("{}", narration!("event_log.skipping_line", line = number + 1, error)),
+        }
+    }
+
+    Ok(records)
+}
+
+// One line per event, as `replay --events` prints it
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Output { line } => write!(f, "{}", line),
+            Event::DemoStarted { name } => f.write_str(&narration!("event_log.demo_started", name)),
+            Event::DemoSkipped { name, reason } => f.write_str(&narration!("event_log.demo_skipped", name, reason)),
+            Event::InvariantChecked { invariant, passed: true } => {
+                f.write_str(&narration!("event_log.invariant_held", invariant))
+            }
+            Event::InvariantChecked { invariant, passed: false } => {
+                f.write_str(&narration!("event_log.invariant_violated", invariant))
+            }
+            Event::FaultInjected { fault } => f.write_str(&narration!("event_log.fault_injected", fault)),
+            Event::ThreadPanicked { message } => f.write_str(&narration!("event_log.thread_panicked", message)),
+            Event::RefCount { name, change, strong } => {
+                f.write_str(&narration!("event_log.ref_count", name, change, strong))
+            }
+            Event::WorkerError { error, .. } => f.write_str(&narration!("event_log.worker_error", error)),
+            Event::Metric { name, value, unit } if unit.is_empty() => f.write_str(&narration!("event_log.metric", name, value)),
+            Event::Metric { name, value, unit } => f.write_str(&narration!("event_log.metric_with_unit", name, value, unit)),
+            Event::BudgetExceeded { demo, budget_ms, hard: true } => {
+                f.write_str(&narration!("event_log.hard_budget_exceeded", demo, budget_ms))
+            }
+            Event::BudgetExceeded { demo, budget_ms, hard: false } => {
+                f.write_str(&narration!("event_log.soft_budget_exceeded", demo, budget_ms))
+            }
+            Event::Message { process, action: MessageAction::Send, peer, label, clock } => {
+                f.write_str(&narration!("event_log.message_sent", process, label, peer, clock))
+            }
+            Event::Message { process, action: MessageAction::Receive, peer, label, clock } => {
+                f.write_str(&narration!("event_log.message_received", process, label, peer, clock))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[+{}ms {}] {}", self.elapsed_ms, self.thread, self.event)
+    }
+}
+
+// `replay <file> [--list] [--run <id>] [--events] [--sequence]` - returns the exit code
+pub fn replay_command(args: &[String]) -> i32 {
+    let usage = narration!("event_log.usage");
+
+    let mut path = None;
+    let mut run = None;
+    let mut list = false;
+    let mut show_events = false;
+    let mut sequence = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--list" => list = true,
+            "--events" => show_events = true,
+            "--sequence" => sequence = true,
+            "--run" => match args.next().map(|id| id.parse::<u64>()) {
+                Some(Ok(id)) => run = Some(id),
+                _ => {
+                    eprintln!("{}", narration!("event_log.run_needs_id", usage));
+                    return 2;
+                }
+            },
+            other if path.is_none() => path = Some(other.to_string()),
+            other => {
+                eprintln!("{}", narration!("event_log.unexpected_argument", argument = other, usage));
+                return 2;
+            }
+        }
+    }
+
+    let path = match path {
+        Some(path) => path,
+        None => {
+            eprintln!("{}", usage);
+            return 2;
+        }
+    };
+
+    let records = match load(Path::new(&path)) {
+        Ok(records) => records,
+        Err(error) => {
+            eprintln!("{}", narration!("event_log.cannot_read", path, error));
+            return 1;
+        }
+    };
+
+    let mut runs: Vec<u64> = records.iter().map(|record| record.run).collect();
+    runs.dedup();
+
+    if list {
+        for id in &runs {
+            let count = records.iter().filter(|record| record.run == *id).count();
+            println!("{}", narration!("event_log.run_listing", run = id, events = count));
+        }
+        return 0;
+    }
+
+    // Default to the most recent run in the file
+    let selected = match run.or_else(|| runs.last().copied()) {
+        Some(id) => id,
+        None => {
+            eprintln!("{}", narration!("event_log.no_runs", path));
+            return 1;
+        }
+    };
+
+    let mut events: Vec<&Record> = records.iter().filter(|record| record.run == selected).collect();
+    if events.is_empty() {
+        eprintln!("{}", narration!("event_log.run_not_found", run = selected, path));
+        return 1;
+    }
+    events.sort_by_key(|record| record.seq);
+
+    // Only the message events, as a diagram
+    if sequence {
+        let messages: Vec<MessageEvent> = events
+            .iter()
+            .filter_map(|record| match &record.event {
+                Event::Message { process, action, peer, label, clock } => Some(MessageEvent {
+                    process: process.clone(),
+                    action: *action,
+                    peer: peer.clone(),
+                    label: label.clone(),
+                    clock: clock.clone(),
+                }),
+                _ => None,
+            })
+            .collect();
+        if messages.is_empty() {
+            eprintln!("{}", narration!("event_log.no_messages", run = selected));
+            return 1;
+        }
+        println!("{}", vector_clock::sequence_diagram(&messages));
+        return 0;
+    }
+
+    for record in events {
+        match &record.event {
+            Event::Output { line } => println!("{}", line),
+            _ if show_events => println!("{}", record),
+            _ => {}
+        }
+    }
+
+    0
+}
@@ -0,0 +1,6 @@
+/* mps2-an386: 4 MiB of code memory and 4 MiB of SRAM */
+MEMORY
+{
+  FLASH : ORIGIN = 0x00000000, LENGTH = 4M
+  RAM : ORIGIN = 0x20000000, LENGTH = 4M
+}
@@ -0,0 +1,102 @@
+/*!
+ * Safety Abstractions Without an Operating System
+ *
+ * resilient_core built with `default-features = false` on a Cortex-M4:
+ * no threads, no heap, no std. The same checks the hosted demos rely on
+ * still hold here:
+ *
+ *   RingBuffer   a full buffer hands the value back; nothing is
+ *                overwritten, and values come out in the order they went in
+ *   CheckedTotal an addition that would overflow is an error, in debug and
+ *                release alike, and leaves the total as it was
+ *   Pipeline     submitting after draining does not compile (see the
+ *                compile_fail example on Pipeline); at run time the
+ *                machine sums exactly what it accepted
+ *   SafeCounter  atomic increments, here from the main loop and an
+ *                exception handler
+ *
+ * `cargo run` boots it in QEMU. Each check prints a line over
+ * semihosting, and the exit code is 0 only if all of them held. A panic
+ * also exits non-zero, so a failed assertion cannot look like a pass.
+ */
+
+#![no_std]
+#![no_main]
+
+use core::panic::PanicInfo;
+
+use cortex_m_rt::{entry, exception};
+use cortex_m_semihosting::{debug, hprintln};
+use resilient_core::{CheckedTotal, Overflow, Pipeline, RingBuffer, SafeCounter, Setup};
+
+// Runs one check and says whether it held
+type Check = fn() -> bool;
+
+// Incremented by the main loop and by the SysTick handler alike
+static TICKS: SafeCounter = SafeCounter::new();
+
+fn ring_buffer_holds() -> bool {
+    let mut ring = RingBuffer::<u8, 3>::new();
+    let accepted = (1..=5).filter(|&value| ring.push(value).is_ok()).count();
+    let order = [ring.pop(), ring.pop(), ring.pop(), ring.pop()];
+    accepted == 3 && order == [Some(1), Some(2), Some(3), None]
+}
+
+fn checked_total_holds() -> bool {
+    let mut total = CheckedTotal::new();
+    let fits = total.try_add(u32::MAX - 1) == Ok(u32::MAX - 1);
+    let refused = total.try_add(2) == Err(Overflow { total: u32::MAX - 1, amount: 2 });
+    fits && refused && total.get() == u32::MAX - 1
+}
+
+fn pipeline_holds() -> bool {
+    let mut pipeline = Pipeline::<Setup, 4>::new().start();
+    let refused = (1..=6).filter(|&value| pipeline.submit(value).is_err()).count();
+    let summed = matches!(pipeline.drain().finish(), Ok(finished) if finished.total() == 1 + 2 + 3 + 4);
+    let mut pipeline = Pipeline::<Setup, 4>::new().start();
+    let overflowed = pipeline.submit(u32::MAX).is_ok() && pipeline.submit(1).is_ok() && pipeline.drain().finish().is_err();
+    refused == 2 && summed && overflowed
+}
+
+fn counter_holds() -> bool {
+    for _ in 0..1000 {
+        TICKS.increment();
+    }
+    cortex_m::peripheral::SCB::set_pendst();  // one SysTick exception, which increments once more
+    cortex_m::asm::isb();
+    TICKS.get_count() == 1001
+}
+
+#[exception]
+fn SysTick() {
+    TICKS.increment();
+}
+
+#[entry]
+fn main() -> ! {
+    let checks: [(&str, Check); 4] = [
+        ("ring_buffer", ring_buffer_holds),
+        ("checked_total", checked_total_holds),
+        ("pipeline", pipeline_holds),
+        ("safe_counter", counter_holds),
+    ];
+    let mut all_held = true;
+    for (name, check) in checks {
+        let held = check();
+        hprintln!("{:<14} {}", name, if held { "held" } else { "VIOLATED" });
+        all_held &= held;
+    }
+    debug::exit(if all_held { debug::EXIT_SUCCESS } else { debug::EXIT_FAILURE });
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    hprintln!("panicked: {}", info);
+    debug::exit(debug::EXIT_FAILURE);
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
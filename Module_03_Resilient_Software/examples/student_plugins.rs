@@ -0,0 +1,146 @@
+/*!
+ * Student Plugins
+ *
+ * An assignment-style crate implementing all four extension points from
+ * outside the demo crate: a CRC-32 checksum, a fixed-window limiter, a
+ * bounded channel backend, and a running-maximum aggregator. They plug in
+ * by implementing the plugin traits only; the checks around them come from
+ * the sealed *Ext traits, which this file can call but not implement.
+ *
+ * Run with: cargo run --example student_plugins
+ */
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use safety_demo::extensions::{
+    Aggregator, AggregatorExt, ChannelBackend, ChannelBackendExt, Checksum, ChecksumExt, Limiter, LimiterExt,
+    Sequenced,
+};
+
+// CRC-32 (IEEE), bit by bit
+struct Crc32;
+
+impl Checksum for Crc32 {
+    fn name(&self) -> &str {
+        "crc32"
+    }
+
+    fn digest(&self, data: &[u8]) -> u64 {
+        let mut crc = 0xFFFF_FFFFu32;
+        for byte in data {
+            crc ^= *byte as u32;
+            for _ in 0..8 {
+                let mask = (crc & 1).wrapping_neg();
+                crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+            }
+        }
+        (!crc) as u64
+    }
+}
+
+// At most `limit` requests in each window of `window` ticks
+struct FixedWindow {
+    limit: u64,
+    window: u64,
+    current: u64,
+    used: u64,
+}
+
+impl Limiter for FixedWindow {
+    fn name(&self) -> &str {
+        "fixed window"
+    }
+
+    fn admit(&mut self, now: u64) -> bool {
+        if now / self.window != self.current {
+            self.current = now / self.window;
+            self.used = 0;
+        }
+        if self.used == self.limit {
+            return false;
+        }
+        self.used += 1;
+        true
+    }
+}
+
+// Keeps at most `capacity` items and overwrites the oldest when full - a
+// fine design for telemetry, a lossy one for a channel
+struct Ring {
+    capacity: usize,
+    items: Mutex<VecDeque<Sequenced<String>>>,
+}
+
+impl ChannelBackend<String> for Ring {
+    fn name(&self) -> &str {
+        "ring buffer"
+    }
+
+    fn push(&self, item: Sequenced<String>) {
+        let mut items = self.items.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if items.len() == self.capacity {
+            items.pop_front();
+        }
+        items.push_back(item);
+    }
+
+    fn pop(&self) -> Option<Sequenced<String>> {
+        self.items.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).pop_front()
+    }
+}
+
+struct Max(Option<i64>);
+
+impl Aggregator for Max {
+    type Output = Option<i64>;
+
+    fn empty() -> Self {
+        Max(None)
+    }
+
+    fn name(&self) -> &str {
+        "max"
+    }
+
+    fn add(&mut self, value: i64) {
+        self.0 = self.0.max(Some(value));
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.0 = self.0.max(other.0);
+    }
+
+    fn finish(self) -> Option<i64> {
+        self.0
+    }
+}
+
+fn main() {
+    let crc = Crc32;
+    let mut frame = crc.frame(b"123456789".to_vec());
+    println!("{}: check value {:08x} (expected cbf43926)", crc.name(), crc.digest(b"123456789"));
+    frame.corrupt(0);
+    println!("{}: corrupted frame -> {:?}", crc.name(), crc.open(&frame));
+
+    let mut limiter = FixedWindow { limit: 2, window: 5, current: 0, used: 0 };
+    let arrivals: Vec<u64> = (0..15).collect();
+    let (handled, stats) = limiter.drive(&arrivals, |tick| format!("t{}", tick));
+    println!("{}: {:?} handled, {} refused", limiter.name(), handled, stats.refused);
+
+    let ring = Ring { capacity: 1024, items: Mutex::new(VecDeque::new()) };
+    let messages: Vec<String> = (0..200).map(|n| format!("message {}", n)).collect();
+    match ring.transfer(messages) {
+        Ok(received) => println!("{} (capacity {}): all {} messages arrived in order", ring.name(), ring.capacity, received.len()),
+        Err(violation) => println!("{} (capacity {}): rejected - {:?}", ring.name(), ring.capacity, violation),
+    }
+    let tiny = Ring { capacity: 1, items: Mutex::new(VecDeque::new()) };
+    let messages: Vec<String> = (0..200).map(|n| format!("message {}", n)).collect();
+    match tiny.transfer(messages) {
+        Ok(received) => println!("{} (capacity 1): all {} arrived - the receiver kept up this time", tiny.name(), received.len()),
+        Err(violation) => println!("{} (capacity 1): rejected - {:?}", tiny.name(), violation),
+    }
+
+    let values: Vec<i64> = (0..10_000).map(|n| (n * 7919) % 10_007).collect();
+    println!("{} over 8 threads: {:?}", Max::empty().name(), Max::aggregate(&values, 8));
+}
@@ -0,0 +1,365 @@
+/*!
+ * Resource Exhaustion
+ *
+ * `thread::spawn` panics when the OS refuses a thread, and `File::open`
+ * fails once the process runs out of file descriptors. Most demo code
+ * treats both as impossible. A production service meets them under load
+ * and has to keep going with less.
+ *
+ * Each demo here lowers one real resource limit (setrlimit) just above
+ * what the process already uses, runs into it, and recovers:
+ *
+ *   threads  RLIMIT_AS leaves room for a few thread stacks; the work
+ *            runs on the workers that could be spawned, or inline
+ *   files    RLIMIT_NOFILE leaves a few descriptors; an LRU pool of
+ *            open files reads every file through them
+ *   sockets  the same descriptors; one connection at a time serves
+ *            every request
+ *
+ * The naive version of each stops at the first failure and reports it
+ * as an ExhaustionError - never a panic. The limit is restored as soon as
+ * the demo is done. Lowering limits is Linux-only; elsewhere the demos
+ * report that and skip.
+ */
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::atomic64::AtomicU64;
+use crate::static_check;
+
+pub const WANTED_WORKERS: usize = static_check::nonzero(8);
+pub const STACKS_THAT_FIT: usize = static_check::nonzero(3);
+pub const SPARE_FDS: u64 = 12;  // descriptors left under the lowered limit
+pub const FILES: usize = static_check::nonzero(40);
+pub const REQUESTS: usize = static_check::nonzero(30);
+
+const STACK_SIZE: usize = 64 << 20;
+// Room for everything but stacks; smaller than a stack, so the stack
+// after the last one that fits always fails
+const SLACK: u64 = 32 << 20;
+const CHUNKS: u64 = 64;
+const CHUNK_LEN: u64 = 10_000;
+
+#[derive(Debug)]
+pub enum ExhaustionError {
+    Threads { spawned: usize, source: io::Error },
+    Files { opened: usize, source: io::Error },
+    Sockets { connected: usize, source: io::Error },
+}
+
+impl fmt::Display for ExhaustionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExhaustionError::Threads { spawned, source } => {
+                write!(f, "could not spawn thread {}: {}", spawned + 1, source)
+            }
+            ExhaustionError::Files { opened, source } => {
+                write!(f, "could not open file {}: {}", opened + 1, source)
+            }
+            ExhaustionError::Sockets { connected, source } => {
+                write!(f, "could not open connection {}: {}", connected + 1, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExhaustionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExhaustionError::Threads { source, .. }
+            | ExhaustionError::Files { source, .. }
+            | ExhaustionError::Sockets { source, .. } => Some(source),
+        }
+    }
+}
+
+// Out of descriptors, for this process (EMFILE) or the whole system (ENFILE)
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+fn out_of_descriptors(error: &io::Error) -> bool {
+    #[cfg(target_os = "linux")]
+    return matches!(error.raw_os_error(), Some(libc::EMFILE | libc::ENFILE));
+    #[cfg(not(target_os = "linux"))]
+    return false;
+}
+
+// A lowered soft limit, restored on drop
+#[cfg(target_os = "linux")]
+struct LoweredLimit {
+    resource: libc::__rlimit_resource_t,
+    previous: libc::rlimit,
+}
+
+#[cfg(target_os = "linux")]
+impl LoweredLimit {
+    fn new(resource: libc::__rlimit_resource_t, soft: u64) -> io::Result<LoweredLimit> {
+        let mut previous = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+        // SAFETY: getrlimit/setrlimit only read and write the struct passed in
+        unsafe {
+            if libc::getrlimit(resource, &mut previous) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // rlim_t is 32 bits on 32-bit targets: a larger limit saturates
+            let soft = libc::rlim_t::try_from(soft).unwrap_or(libc::rlim_t::MAX);
+            let lowered = libc::rlimit { rlim_cur: soft.min(previous.rlim_cur), rlim_max: previous.rlim_max };
+            if libc::setrlimit(resource, &lowered) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(LoweredLimit { resource, previous })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for LoweredLimit {
+    fn drop(&mut self) {
+        // SAFETY: as above; raising the soft limit back up to the old
+        // value is always allowed, since the hard limit was not touched
+        unsafe {
+            libc::setrlimit(self.resource, &self.previous);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn lower_address_space(extra: u64) -> io::Result<LoweredLimit> {
+    let status = fs::read_to_string("/proc/self/status")?;
+    let kib: u64 = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmSize:")?.trim().strip_suffix("kB")?.trim().parse().ok())
+        .ok_or_else(|| io::Error::other("no VmSize in /proc/self/status"))?;
+    LoweredLimit::new(libc::RLIMIT_AS, kib * 1024 + extra)
+}
+
+#[cfg(target_os = "linux")]
+fn lower_descriptors(spare: u64) -> io::Result<LoweredLimit> {
+    // Less the descriptor read_dir itself holds while counting
+    let open = fs::read_dir("/proc/self/fd")?.count() as u64 - 1;
+    LoweredLimit::new(libc::RLIMIT_NOFILE, open + spare)
+}
+
+#[cfg(not(target_os = "linux"))]
+struct LoweredLimit;
+
+#[cfg(not(target_os = "linux"))]
+fn lower_address_space(_extra: u64) -> io::Result<LoweredLimit> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "lowering resource limits needs Linux"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn lower_descriptors(_spare: u64) -> io::Result<LoweredLimit> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "lowering resource limits needs Linux"))
+}
+
+// Spawn up to `wanted` threads running `work`, stopping at the first
+// refusal; the handles of those that did start come back either way
+pub fn spawn_up_to<F>(wanted: usize, stack: usize, work: F) -> (Vec<JoinHandle<()>>, Option<ExhaustionError>)
+where
+    F: Fn() + Send + Clone + 'static,
+{
+    // Sized up front: allocating is exactly what may fail next
+    let mut handles = Vec::with_capacity(wanted);
+    for _ in 0..wanted {
+        match thread::Builder::new().stack_size(stack).spawn(work.clone()) {
+            Ok(handle) => handles.push(handle),
+            Err(source) => {
+                let spawned = handles.len();
+                return (handles, Some(ExhaustionError::Threads { spawned, source }));
+            }
+        }
+    }
+    (handles, None)
+}
+
+pub struct ThreadRun {
+    pub spawned: usize,
+    pub error: Option<ExhaustionError>,
+    pub sum: u64,
+    pub expected: u64,
+}
+
+// Sum CHUNKS chunks of numbers on WANTED_WORKERS threads, with address
+// space for only STACKS_THAT_FIT of their stacks
+pub fn threads_demo() -> io::Result<ThreadRun> {
+    let next_chunk = Arc::new(AtomicU64::new(0));
+    let total = Arc::new(AtomicU64::new(0));
+    // Workers do not allocate: under the lowered limit, the heap may be
+    // as short of room as the stacks
+    let work = {
+        let (next_chunk, total) = (Arc::clone(&next_chunk), Arc::clone(&total));
+        move || loop {
+            let chunk = next_chunk.fetch_add(1, Ordering::Relaxed);
+            if chunk >= CHUNKS {
+                return;
+            }
+            let sum: u64 = (chunk * CHUNK_LEN..(chunk + 1) * CHUNK_LEN).sum();
+            total.fetch_add(sum, Ordering::Relaxed);
+        }
+    };
+
+    let (handles, error) = {
+        let _limit = lower_address_space(STACKS_THAT_FIT as u64 * STACK_SIZE as u64 + SLACK)?;
+        spawn_up_to(WANTED_WORKERS, STACK_SIZE, work.clone())
+    };
+    let spawned = handles.len();
+    for handle in handles {
+        let _ = handle.join();
+    }
+    // Degrade all the way: no thread at all still gets the work done
+    if spawned == 0 {
+        work();
+    }
+
+    let n = CHUNKS * CHUNK_LEN;
+    Ok(ThreadRun { spawned, error, sum: total.load(Ordering::Relaxed), expected: n * (n - 1) / 2 })
+}
+
+// Files kept open for reuse, closing the least recently used one when
+// the process runs out of descriptors
+pub struct FilePool {
+    open: VecDeque<(PathBuf, File)>,
+    pub evictions: usize,
+    pub peak_open: usize,
+}
+
+impl FilePool {
+    pub fn new() -> Self {
+        FilePool { open: VecDeque::new(), evictions: 0, peak_open: 0 }
+    }
+
+    pub fn read(&mut self, path: &Path) -> io::Result<String> {
+        let index = match self.open.iter().position(|(open, _)| open == path) {
+            Some(index) => index,
+            None => {
+                let file = loop {
+                    match File::open(path) {
+                        Ok(file) => break file,
+                        Err(error) if out_of_descriptors(&error) && !self.open.is_empty() => {
+                            self.open.pop_front();
+                            self.evictions += 1;
+                        }
+                        Err(error) => return Err(error),
+                    }
+                };
+                self.open.push_back((path.to_path_buf(), file));
+                self.peak_open = self.peak_open.max(self.open.len());
+                self.open.len() - 1
+            }
+        };
+        // Most recently used at the back
+        let entry = self.open.remove(index).expect("index came from position");
+        self.open.push_back(entry);
+        let file = &mut self.open.back_mut().expect("just pushed").1;
+        let mut contents = String::new();
+        io::Seek::rewind(file)?;
+        file.read_to_string(&mut contents)?;
+        Ok(contents)
+    }
+}
+
+impl Default for FilePool {
+    fn default() -> Self {
+        FilePool::new()
+    }
+}
+
+pub struct FileRun {
+    pub naive: Option<ExhaustionError>,  // opening every file and keeping it
+    pub read: usize,                     // files read correctly through the pool
+    pub evictions: usize,
+    pub peak_open: usize,
+}
+
+// Read FILES files twice over, with SPARE_FDS descriptors to do it with
+pub fn files_demo() -> io::Result<FileRun> {
+    let dir = std::env::temp_dir().join(format!("safety-demo-exhaustion-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let paths: Vec<PathBuf> = (0..FILES).map(|index| dir.join(format!("file-{}.txt", index))).collect();
+    let run = (|| {
+        for (index, path) in paths.iter().enumerate() {
+            fs::write(path, format!("contents of file {}", index))?;
+        }
+        let _limit = lower_descriptors(SPARE_FDS)?;
+
+        let mut held = Vec::with_capacity(FILES);
+        let mut naive = None;
+        for path in &paths {
+            match File::open(path) {
+                Ok(file) => held.push(file),
+                Err(source) => {
+                    naive = Some(ExhaustionError::Files { opened: held.len(), source });
+                    break;
+                }
+            }
+        }
+        drop(held);
+
+        let mut pool = FilePool::new();
+        let mut read = 0;
+        for (index, path) in paths.iter().enumerate().chain(paths.iter().enumerate().rev()) {
+            if pool.read(path)? == format!("contents of file {}", index) {
+                read += 1;
+            }
+        }
+        Ok(FileRun { naive, read, evictions: pool.evictions, peak_open: pool.peak_open })
+    })();
+    let _ = fs::remove_dir_all(&dir);
+    run
+}
+
+pub struct SocketRun {
+    pub naive: Option<ExhaustionError>,  // one connection per request, all at once
+    pub served: usize,                   // requests answered one connection at a time
+}
+
+// One request over a fresh loopback connection: send a byte, get it back + 1
+fn serve_one(listener: &TcpListener, request: u8) -> io::Result<u8> {
+    let mut client = TcpStream::connect(listener.local_addr()?)?;
+    let (mut server, _) = listener.accept()?;
+    client.write_all(&[request])?;
+    let mut byte = [0u8];
+    server.read_exact(&mut byte)?;
+    server.write_all(&[byte[0].wrapping_add(1)])?;
+    client.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+// Answer REQUESTS requests over loopback with SPARE_FDS descriptors; each
+// connection costs two, the client end and the accepted end
+pub fn sockets_demo() -> io::Result<SocketRun> {
+    let _limit = lower_descriptors(SPARE_FDS)?;
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let mut held = Vec::with_capacity(REQUESTS);
+    let mut naive = None;
+    for _ in 0..REQUESTS {
+        let pair = TcpStream::connect(listener.local_addr()?).and_then(|client| Ok((client, listener.accept()?)));
+        match pair {
+            Ok(pair) => held.push(pair),
+            Err(source) => {
+                naive = Some(ExhaustionError::Sockets { connected: held.len(), source });
+                break;
+            }
+        }
+    }
+    drop(held);
+    // Its backlog still holds the connection whose accept failed
+    drop(listener);
+
+    // Bounded: each connection is closed before the next one opens
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let mut served = 0;
+    for request in 0..REQUESTS as u8 {
+        if serve_one(&listener, request)? == request + 1 {
+            served += 1;
+        }
+    }
+    Ok(SocketRun { naive, served })
+}
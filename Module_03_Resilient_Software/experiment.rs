@@ -0,0 +1,220 @@
+/*!
+ * Sharded Counter Experiment
+ *
+ * `experiment` measures what the counter demos cannot show on a laptop:
+ * the cost of where memory lives relative to the core touching it. The
+ * counter is split into one cache-line-sized shard per physical core
+ * (found by probing the CPU topology), every thread is pinned to a CPU,
+ * and three placements are compared:
+ *
+ *   per-core      each thread increments its own core's shard
+ *   random        each increment goes to a random shard
+ *   cross-socket  each thread increments a shard of a core on another
+ *                 socket (only on multi-socket lab servers)
+ *
+ * The ratio between per-core and cross-socket is the cross-socket penalty.
+ */
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::Instant;
+
+use crate::atomic64::AtomicU64;
+use crate::event_log;
+use crate::progress::{Goal, Progress};
+use crate::rng::SeededRng;
+
+#[derive(Debug, Clone, Copy)]
+struct LogicalCpu {
+    id: usize,
+    core: usize,     // index of its physical core in the shard table
+    package: usize,  // socket
+}
+
+// Logical CPUs grouped into physical cores, from /sys on Linux. Elsewhere
+// (or if /sys is unreadable) every logical CPU counts as its own core.
+fn probe_topology() -> Vec<LogicalCpu> {
+    let read = |cpu: usize, file: &str| -> Option<usize> {
+        let path = format!("/sys/devices/system/cpu/cpu{}/topology/{}", cpu, file);
+        fs::read_to_string(path).ok()?.trim().parse().ok()
+    };
+
+    let logical = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let mut cores: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+    (0..logical)
+        .map(|id| {
+            let package = read(id, "physical_package_id").unwrap_or(0);
+            let core_id = read(id, "core_id").unwrap_or(id);
+            let next = cores.len();
+            let core = *cores.entry((package, core_id)).or_insert(next);
+            LogicalCpu { id, core, package }
+        })
+        .collect()
+}
+
+// Pin the calling thread to one logical CPU; false if that is not possible
+#[cfg(target_os = "linux")]
+fn pin_to(cpu: usize) -> bool {
+    // SAFETY: cpu_set_t is plain data, zeroed is a valid empty set, and
+    // sched_setaffinity(0, ..) only affects the calling thread
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == 0
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_to(_cpu: usize) -> bool {
+    false
+}
+
+// One shard per cache line, so shards never share a line
+#[repr(align(64))]
+#[derive(Debug, Default)]
+struct Shard(AtomicU64);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Placement {
+    PerCore,
+    Random,
+    CrossSocket,
+}
+
+impl Placement {
+    fn name(self) -> &'static str {
+        match self {
+            Placement::PerCore => "per-core",
+            Placement::Random => "random",
+            Placement::CrossSocket => "cross-socket",
+        }
+    }
+}
+
+// Shard (physical core) a thread on `cpu` increments for this placement
+fn home_shard(placement: Placement, cpu: &LogicalCpu, cpus: &[LogicalCpu]) -> usize {
+    match placement {
+        Placement::PerCore | Placement::Random => cpu.core,
+        Placement::CrossSocket => cpus
+            .iter()
+            .find(|other| other.package != cpu.package)
+            .map(|other| other.core)
+            .unwrap_or(cpu.core),
+    }
+}
+
+struct Outcome {
+    ops_per_sec: f64,
+    pinned: bool,
+}
+
+// Workers report progress in batches, off the shards' cache lines
+const PROGRESS_BATCH: u64 = 1 << 16;
+
+fn run_placement(placement: Placement, cpus: &[LogicalCpu], threads: usize, ops: u64, seed: u64) -> Outcome {
+    let shard_count = cpus.iter().map(|cpu| cpu.core).max().unwrap_or(0) + 1;
+    let shards: Arc<Vec<Shard>> = Arc::new((0..shard_count).map(|_| Shard::default()).collect());
+    let barrier = Arc::new(Barrier::new(threads + 1));
+    let progress = Progress::with_lanes(placement.name(), Goal::Steps(threads as u64 * ops), threads, ops);
+
+    let handles: Vec<_> = (0..threads)
+        .map(|worker| {
+            let cpu = cpus[worker % cpus.len()];
+            let home = home_shard(placement, &cpu, cpus);
+            let (shards, barrier) = (Arc::clone(&shards), Arc::clone(&barrier));
+            let lane = progress.lane(worker);
+            thread::spawn(move || {
+                let pinned = pin_to(cpu.id);
+                let mut rng = SeededRng::new(seed.wrapping_add(worker as u64));
+                let mut reported = 0;
+                barrier.wait();
+                for op in 1..=ops {
+                    let shard = match placement {
+                        Placement::Random => rng.range(0, shards.len() - 1),
+                        _ => home,
+                    };
+                    shards[shard].0.fetch_add(1, Ordering::Relaxed);
+                    if op % PROGRESS_BATCH == 0 || op == ops {
+                        lane.advance(op - reported);
+                        reported = op;
+                    }
+                }
+                pinned
+            })
+        })
+        .collect();
+
+    barrier.wait();  // Start the clock once every thread is pinned
+    let start = Instant::now();
+    let pinned: Vec<bool> = handles.into_iter().map(|handle| handle.join().unwrap_or(false)).collect();
+    let elapsed = start.elapsed();
+
+    let total: u64 = shards.iter().map(|shard| shard.0.load(Ordering::SeqCst)).sum();
+    event_log::check_invariant("sharded counter total is exact", total == threads as u64 * ops);
+    Outcome {
+        ops_per_sec: total as f64 / elapsed.as_secs_f64(),
+        pinned: pinned.iter().all(|&pinned| pinned),
+    }
+}
+
+// `experiment [--threads N] [--ops N] [--seed N]` - returns the exit code
+pub fn experiment_command(args: &[String]) -> i32 {
+    let cpus = probe_topology();
+    let (mut threads, mut ops, mut seed) = (cpus.len(), 2_000_000u64, SeededRng::time_seed());
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args.next();
+        let parsed = value.and_then(|value| value.parse::<u64>().ok());
+        match (flag.as_str(), parsed) {
+            ("--threads", Some(value)) if value > 0 => threads = value as usize,
+            ("--ops", Some(value)) => ops = value,
+            ("--seed", Some(value)) => seed = value,
+            _ => {
+                eprintln!("{}", narration!("experiment.usage"));
+                return 2;
+            }
+        }
+    }
+
+    let cores = cpus.iter().map(|cpu| cpu.core).max().unwrap_or(0) + 1;
+    let mut packages: Vec<usize> = cpus.iter().map(|cpu| cpu.package).collect();
+    packages.sort_unstable();
+    packages.dedup();
+    println!("{}", narration!("experiment.heading"));
+    println!("{}", narration!("experiment.topology", cpus = cpus.len(), cores, sockets = packages.len()));
+    println!("{}", narration!("experiment.workload", threads, ops, cores, seed));
+
+    let mut placements = vec![Placement::PerCore, Placement::Random];
+    if packages.len() > 1 {
+        placements.push(Placement::CrossSocket);
+    }
+
+    println!("{}", narration!("experiment.header"));
+    let mut results = Vec::new();
+    let mut all_pinned = true;
+    for placement in placements {
+        let outcome = run_placement(placement, &cpus, threads, ops, seed);
+        all_pinned &= outcome.pinned;
+        let baseline = results.first().map(|(_, rate)| *rate).unwrap_or(outcome.ops_per_sec);
+        println!("{:<14} {:>16.0} {:>9.2}x", placement.name(), outcome.ops_per_sec,
+                 outcome.ops_per_sec / baseline);
+        results.push((placement, outcome.ops_per_sec));
+    }
+
+    if !all_pinned {
+        println!("{}", narration!("experiment.not_pinned"));
+    }
+    let rate = |wanted: Placement| results.iter().find(|(placement, _)| *placement == wanted).map(|(_, rate)| *rate);
+    match (rate(Placement::PerCore), rate(Placement::CrossSocket)) {
+        (Some(local), Some(remote)) => println!("{}", narration!("experiment.cross_socket_penalty", slowdown = local / remote)),
+        _ => println!("{}", narration!("experiment.single_socket")),
+    }
+    if cores == 1 {
+        println!("{}", narration!("experiment.single_core"));
+    }
+    0
+}
@@ -0,0 +1,373 @@
+/*!
+ * Extension Points
+ *
+ * Four traits students implement for assignments: Checksum, Limiter,
+ * ChannelBackend and Aggregator. The plugin supplies the algorithm; the
+ * crate wraps it in a sealed extension trait (ChecksumExt, LimiterExt,
+ * ChannelBackendExt, AggregatorExt) that is implemented for every plugin
+ * and cannot be implemented or overridden anywhere else. The invariants
+ * live there:
+ *
+ *   - a framed payload is only handed out after its checksum is verified
+ *   - a request the limiter refuses never runs
+ *   - a backend that loses, repeats or reorders messages is reported,
+ *     not trusted (and it cannot forge a sequence number)
+ *   - every value reaches the aggregator exactly once, however it is split
+ *
+ * A plugin can be wrong, but it cannot switch the checks off.
+ * examples/student_plugins.rs implements all four from outside the crate.
+ */
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+// Public traits in a private module: nameable as a bound, impossible to
+// implement outside this file
+mod sealed {
+    pub trait ChecksumSeal {}
+    pub trait LimiterSeal {}
+    pub trait ChannelSeal<T> {}
+    pub trait AggregatorSeal {}
+}
+
+// --- Checksum ---------------------------------------------------------
+
+pub trait Checksum {
+    fn name(&self) -> &str;
+    fn digest(&self, data: &[u8]) -> u64;
+}
+
+// A payload and the digest it was framed with; only ChecksumExt::frame
+// builds one
+#[derive(Debug, Clone)]
+pub struct Frame {
+    payload: Vec<u8>,
+    digest: u64,
+}
+
+impl Frame {
+    // Flip one bit in transit, as a bad link would
+    pub fn corrupt(&mut self, index: usize) {
+        if let Some(byte) = self.payload.get_mut(index) {
+            *byte ^= 0x01;
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Corrupted {
+    pub expected: u64,
+    pub actual: u64,
+}
+
+impl fmt::Display for Corrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "checksum mismatch: frame says {:#x}, payload gives {:#x}", self.expected, self.actual)
+    }
+}
+
+pub trait ChecksumExt: sealed::ChecksumSeal {
+    fn frame(&self, payload: Vec<u8>) -> Frame;
+    fn open<'a>(&self, frame: &'a Frame) -> Result<&'a [u8], Corrupted>;
+}
+
+impl<C: Checksum + ?Sized> sealed::ChecksumSeal for C {}
+
+impl<C: Checksum + ?Sized> ChecksumExt for C {
+    fn frame(&self, payload: Vec<u8>) -> Frame {
+        let digest = self.digest(&payload);
+        Frame { payload, digest }
+    }
+
+    fn open<'a>(&self, frame: &'a Frame) -> Result<&'a [u8], Corrupted> {
+        let actual = self.digest(&frame.payload);
+        if actual == frame.digest {
+            Ok(&frame.payload)
+        } else {
+            Err(Corrupted { expected: frame.digest, actual })
+        }
+    }
+}
+
+// Fletcher-32 over bytes: two running sums, the second weighting position
+#[derive(Debug, Default)]
+pub struct Fletcher32;
+
+impl Checksum for Fletcher32 {
+    fn name(&self) -> &str {
+        "fletcher32"
+    }
+
+    fn digest(&self, data: &[u8]) -> u64 {
+        let (mut low, mut high) = (0u64, 0u64);
+        for byte in data {
+            low = (low + *byte as u64) % 65_535;
+            high = (high + low) % 65_535;
+        }
+        (high << 16) | low
+    }
+}
+
+// --- Limiter ----------------------------------------------------------
+
+pub trait Limiter {
+    fn name(&self) -> &str;
+    // May a request arriving at tick `now` go ahead? Ticks never go back.
+    fn admit(&mut self, now: u64) -> bool;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LimitStats {
+    pub admitted: u64,
+    pub refused: u64,
+}
+
+impl fmt::Display for LimitStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} admitted, {} refused", self.admitted, self.refused)
+    }
+}
+
+pub trait LimiterExt: sealed::LimiterSeal {
+    // Offer each request (by arrival tick) to the limiter in time order and
+    // run `work` only for the admitted ones
+    fn drive<R>(&mut self, arrivals: &[u64], work: impl FnMut(u64) -> R) -> (Vec<R>, LimitStats);
+}
+
+impl<L: Limiter + ?Sized> sealed::LimiterSeal for L {}
+
+impl<L: Limiter + ?Sized> LimiterExt for L {
+    fn drive<R>(&mut self, arrivals: &[u64], mut work: impl FnMut(u64) -> R) -> (Vec<R>, LimitStats) {
+        let mut arrivals = arrivals.to_vec();
+        arrivals.sort_unstable();
+        let mut stats = LimitStats::default();
+        let mut results = Vec::new();
+        for now in arrivals {
+            if self.admit(now) {
+                stats.admitted += 1;
+                results.push(work(now));
+            } else {
+                stats.refused += 1;
+            }
+        }
+        (results, stats)
+    }
+}
+
+// Holds up to `capacity` tokens and gains one every `refill_every` ticks
+#[derive(Debug)]
+pub struct TokenBucket {
+    capacity: u64,
+    refill_every: u64,
+    tokens: u64,
+    last_refill: u64,
+}
+
+impl TokenBucket {
+    pub fn new(capacity: u64, refill_every: u64) -> Self {
+        TokenBucket { capacity, refill_every: refill_every.max(1), tokens: capacity, last_refill: 0 }
+    }
+}
+
+impl Limiter for TokenBucket {
+    fn name(&self) -> &str {
+        "token bucket"
+    }
+
+    fn admit(&mut self, now: u64) -> bool {
+        let earned = (now - self.last_refill) / self.refill_every;
+        if earned > 0 {
+            self.tokens = (self.tokens + earned).min(self.capacity);
+            self.last_refill += earned * self.refill_every;
+        }
+        if self.tokens == 0 {
+            return false;
+        }
+        self.tokens -= 1;
+        true
+    }
+}
+
+// --- Channel backend --------------------------------------------------
+
+// An item and its place in the send order; a backend can store, drop or
+// copy one, but not create one or change its number
+#[derive(Debug, Clone)]
+pub struct Sequenced<T> {
+    seq: u64,
+    item: T,
+}
+
+pub trait ChannelBackend<T>: Sync {
+    fn name(&self) -> &str;
+    fn push(&self, item: Sequenced<T>);
+    fn pop(&self) -> Option<Sequenced<T>>;
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BackendViolation {
+    Lost { seq: u64 },
+    Duplicated { seq: u64 },
+    Reordered { expected: u64, got: u64 },
+}
+
+impl fmt::Display for BackendViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackendViolation::Lost { seq } => write!(f, "item {} was lost", seq),
+            BackendViolation::Duplicated { seq } => write!(f, "item {} arrived twice", seq),
+            BackendViolation::Reordered { expected, got } => write!(f, "expected item {}, got {}", expected, got),
+        }
+    }
+}
+
+pub trait ChannelBackendExt<T>: sealed::ChannelSeal<T> {
+    // Send `items` from a producer thread while this thread receives; the
+    // items come back only if every one arrived once, in order
+    fn transfer(&self, items: Vec<T>) -> Result<Vec<T>, BackendViolation>;
+}
+
+impl<T, B: ChannelBackend<T> + ?Sized> sealed::ChannelSeal<T> for B {}
+
+impl<T: Send, B: ChannelBackend<T> + ?Sized> ChannelBackendExt<T> for B {
+    fn transfer(&self, items: Vec<T>) -> Result<Vec<T>, BackendViolation> {
+        let total = items.len() as u64;
+        let sent = AtomicBool::new(false);
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                for (seq, item) in (0..).zip(items) {
+                    self.push(Sequenced { seq, item });
+                }
+                sent.store(true, Ordering::Release);
+            });
+
+            let mut received = Vec::new();
+            let mut expected = 0;
+            while expected < total {
+                // Read the flag first: empty after everything was sent means lost
+                let done = sent.load(Ordering::Acquire);
+                match self.pop() {
+                    Some(Sequenced { seq, item }) if seq == expected => {
+                        received.push(item);
+                        expected += 1;
+                    }
+                    Some(Sequenced { seq, .. }) if seq < expected => return Err(BackendViolation::Duplicated { seq }),
+                    Some(Sequenced { seq, .. }) => return Err(BackendViolation::Reordered { expected, got: seq }),
+                    None if done => return Err(BackendViolation::Lost { seq: expected }),
+                    None => thread::yield_now(),
+                }
+            }
+            Ok(received)
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct MutexQueue<T> {
+    items: Mutex<VecDeque<Sequenced<T>>>,
+}
+
+impl<T> Default for MutexQueue<T> {
+    fn default() -> Self {
+        MutexQueue { items: Mutex::new(VecDeque::new()) }
+    }
+}
+
+impl<T: Send> ChannelBackend<T> for MutexQueue<T> {
+    fn name(&self) -> &str {
+        "mutex queue"
+    }
+
+    fn push(&self, item: Sequenced<T>) {
+        self.items.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push_back(item);
+    }
+
+    fn pop(&self) -> Option<Sequenced<T>> {
+        self.items.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).pop_front()
+    }
+}
+
+// --- Aggregator -------------------------------------------------------
+
+pub trait Aggregator: Send + Sized {
+    type Output;
+    fn empty() -> Self;
+    fn name(&self) -> &str;
+    fn add(&mut self, value: i64);
+    // Combine with a partial result over other values
+    fn merge(&mut self, other: Self);
+    fn finish(self) -> Self::Output;
+}
+
+pub trait AggregatorExt: sealed::AggregatorSeal + Sized {
+    type Output;
+    // Split `values` across `threads` scoped threads, one partial result
+    // each, and merge the partials
+    fn aggregate(values: &[i64], threads: usize) -> Self::Output;
+}
+
+impl<A: Aggregator> sealed::AggregatorSeal for A {}
+
+impl<A: Aggregator> AggregatorExt for A {
+    type Output = A::Output;
+
+    fn aggregate(values: &[i64], threads: usize) -> A::Output {
+        let chunk = values.len().div_ceil(threads.max(1)).max(1);
+        let partials: Vec<A> = thread::scope(|scope| {
+            let handles: Vec<_> = values
+                .chunks(chunk)
+                .map(|part| {
+                    scope.spawn(move || {
+                        let mut partial = A::empty();
+                        for value in part {
+                            partial.add(*value);
+                        }
+                        partial
+                    })
+                })
+                .collect();
+            // A panicking plugin takes the run down rather than drop its values
+            handles.into_iter().map(|handle| handle.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))).collect()
+        });
+        let mut total = A::empty();
+        for partial in partials {
+            total.merge(partial);
+        }
+        total.finish()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Mean {
+    sum: i64,
+    count: u64,
+}
+
+impl Aggregator for Mean {
+    type Output = Option<f64>;
+
+    fn empty() -> Self {
+        Mean::default()
+    }
+
+    fn name(&self) -> &str {
+        "mean"
+    }
+
+    fn add(&mut self, value: i64) {
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.sum += other.sum;
+        self.count += other.count;
+    }
+
+    fn finish(self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum as f64 / self.count as f64)
+    }
+}
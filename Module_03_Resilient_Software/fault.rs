@@ -0,0 +1,175 @@
+/*!
+ * Fault Injection
+ *
+ * A FaultPlan names one worker and one step at which something goes
+ * wrong: the worker panics, stalls, yields its time slice, or sees its
+ * operation fail. Workers call `plan.at_step(worker, step)` inside their
+ * loops, so the same code path runs with and without faults - and every
+ * injected fault is recorded in the event log. A FaultSwitch does the
+ * same for faults triggered at a point in time rather than at a step.
+ */
+
+use std::fmt;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::event_log::{self, Event};
+use crate::rng::SeededRng;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FaultKind {
+    Panic,             // the worker panics
+    Delay(Duration),   // the worker stalls for a while
+    Yield,             // the worker gives up its time slice
+    Error,             // the worker's current operation fails
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fault {
+    pub worker: usize,
+    pub step: usize,
+    pub kind: FaultKind,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            FaultKind::Panic => write!(f, "panic in worker {} at step {}", self.worker, self.step),
+            FaultKind::Delay(delay) => write!(
+                f,
+                "{}ms delay in worker {} at step {}",
+                delay.as_millis(),
+                self.worker,
+                self.step
+            ),
+            FaultKind::Yield => write!(f, "yield in worker {} at step {}", self.worker, self.step),
+            FaultKind::Error => write!(f, "error in worker {} at step {}", self.worker, self.step),
+        }
+    }
+}
+
+// Panic payload that marks a panic as deliberate
+#[derive(Debug)]
+pub struct InjectedPanic;
+
+#[derive(Debug, Clone, Default)]
+pub struct FaultPlan {
+    faults: Vec<Fault>,
+}
+
+impl FaultPlan {
+    pub fn none() -> Self {
+        FaultPlan::default()
+    }
+
+    pub fn with(fault: Fault) -> Self {
+        FaultPlan { faults: vec![fault] }
+    }
+
+    // At most one random fault among `workers` workers running `steps` steps
+    pub fn random(rng: &mut SeededRng, workers: usize, steps: usize, probability: f64) -> Self {
+        if workers == 0 || steps == 0 || !rng.chance(probability) {
+            return FaultPlan::none();
+        }
+        let worker = rng.range(0, workers - 1);
+        let step = rng.range(0, steps - 1);
+        let kind = match rng.range(0, 2) {
+            0 => FaultKind::Panic,
+            1 => FaultKind::Delay(Duration::from_millis(rng.range(1, 20) as u64)),
+            _ => FaultKind::Yield,
+        };
+        FaultPlan::with(Fault { worker, step, kind })
+    }
+
+    pub fn faults(&self) -> &[Fault] {
+        &self.faults
+    }
+
+    // The step at which `worker` will panic, if it is planned to
+    pub fn panic_step(&self, worker: usize) -> Option<usize> {
+        self.faults
+            .iter()
+            .find(|fault| fault.worker == worker && fault.kind == FaultKind::Panic)
+            .map(|fault| fault.step)
+    }
+
+    // Called by worker `worker` before doing step `step`; true means the
+    // step's operation should be treated as failed
+    pub fn at_step(&self, worker: usize, step: usize) -> bool {
+        let mut failed = false;
+        for fault in &self.faults {
+            if fault.worker == worker && fault.step == step {
+                failed |= fire(fault);
+            }
+        }
+        failed
+    }
+}
+
+// Record and carry out one fault; true if it was an Error
+fn fire(fault: &Fault) -> bool {
+    event_log::record(Event::FaultInjected { fault: fault.to_string() });
+    match fault.kind {
+        FaultKind::Panic => std::panic::panic_any(InjectedPanic),
+        FaultKind::Delay(delay) => thread::sleep(delay),
+        FaultKind::Yield => thread::yield_now(),
+        FaultKind::Error => return true,
+    }
+    false
+}
+
+// Faults armed at runtime and fired at the target worker's next check
+#[derive(Debug, Default)]
+pub struct FaultSwitch {
+    pending: Mutex<Vec<(usize, FaultKind)>>,
+}
+
+impl FaultSwitch {
+    pub fn new() -> Self {
+        FaultSwitch::default()
+    }
+
+    pub fn trigger(&self, worker: usize, kind: FaultKind) {
+        let mut pending = self.pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        pending.push((worker, kind));
+    }
+
+    // Called by worker `worker` once per iteration; `step` is only used to
+    // describe the fault. True means this iteration's operation failed.
+    pub fn check(&self, worker: usize, step: usize) -> bool {
+        let armed: Vec<FaultKind> = {
+            let mut pending = self.pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            // Errors fire one per check; everything else fires at once
+            let mut armed = Vec::new();
+            let mut error_taken = false;
+            pending.retain(|(target, kind)| {
+                if *target != worker || (*kind == FaultKind::Error && error_taken) {
+                    return true;
+                }
+                error_taken |= *kind == FaultKind::Error;
+                armed.push(*kind);
+                false
+            });
+            armed
+        };
+
+        let mut failed = false;
+        for kind in armed {
+            failed |= fire(&Fault { worker, step, kind });
+        }
+        failed
+    }
+}
+
+// Keep deliberate panics off the console; real panics still report
+pub fn install_quiet_panic_hook() {
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if info.payload().downcast_ref::<InjectedPanic>().is_some() {
+            event_log::record(Event::ThreadPanicked { message: "injected fault".to_string() });
+        } else {
+            previous_hook(info);
+        }
+    }));
+}
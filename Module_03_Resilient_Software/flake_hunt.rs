@@ -0,0 +1,195 @@
+/*!
+ * Flake Hunter
+ *
+ * Runs the thread-safety stress workloads many times, each in a child
+ * process under a different kind of scheduler pressure (busy spinner
+ * threads competing for the cores, lowered `nice` priority). A failing
+ * seed is re-run without pressure: if it then passes, the failure was
+ * nondeterministic - a flake - and its event log is kept for inspection.
+ */
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use crate::rng::SeededRng;
+
+#[derive(Debug, Clone, Copy)]
+struct Pressure {
+    spinners: usize,  // busy threads competing with the workload
+    nice: i32,        // scheduling priority of the child (0 = normal)
+}
+
+impl Pressure {
+    const NONE: Pressure = Pressure { spinners: 0, nice: 0 };
+
+    fn describe(&self) -> String {
+        narration!("flake_hunt.pressure", spinners = self.spinners, nice = self.nice)
+    }
+}
+
+// Rotate through increasingly hostile scheduling conditions
+fn pressure_for_run(run: usize, cores: usize) -> Pressure {
+    let spinners = [0, cores, cores * 2][run % 3];
+    let nice = [0, 10, 19][(run / 3) % 3];
+    Pressure { spinners, nice }
+}
+
+// Occupy `count` cores with busy loops until the returned flag is set
+fn start_spinners(count: usize) -> (Arc<AtomicBool>, Vec<thread::JoinHandle<()>>) {
+    let stop = Arc::new(AtomicBool::new(false));
+    let handles = (0..count)
+        .map(|_| {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    std::hint::spin_loop();
+                }
+            })
+        })
+        .collect();
+    (stop, handles)
+}
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Passed,
+    Violation,        // stress reported an invariant violation
+    Crashed(String),  // anything else: signal, unexpected exit code
+}
+
+// Run `stress` in a child process under the given pressure
+fn run_child(seed: u64, iterations: usize, pressure: Pressure, log: &Path) -> Outcome {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(error) => return Outcome::Crashed(format!("cannot locate executable: {}", error)),
+    };
+    let stress_args = [
+        "stress".to_string(),
+        "--seed".to_string(),
+        seed.to_string(),
+        "--iterations".to_string(),
+        iterations.to_string(),
+    ];
+
+    let mut command = if pressure.nice > 0 && cfg!(unix) {
+        let mut command = Command::new("nice");
+        command.arg("-n").arg(pressure.nice.to_string()).arg(&exe);
+        command
+    } else {
+        Command::new(&exe)
+    };
+    command
+        .args(stress_args)
+        .env("SAFETY_DEMO_EVENT_LOG", log)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    let (stop, spinners) = start_spinners(pressure.spinners);
+    let status = command.status();
+    stop.store(true, Ordering::Relaxed);
+    for spinner in spinners {
+        let _ = spinner.join();
+    }
+
+    match status {
+        Ok(status) if status.success() => Outcome::Passed,
+        Ok(status) if status.code() == Some(1) => Outcome::Violation,
+        Ok(status) => Outcome::Crashed(status.to_string()),
+        Err(error) => Outcome::Crashed(format!("cannot start child: {}", error)),
+    }
+}
+
+struct Options {
+    runs: usize,
+    iterations: usize,
+    seed: u64,
+    out: PathBuf,
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        runs: 12,
+        iterations: 50,
+        seed: SeededRng::time_seed(),
+        out: PathBuf::from("flake-hunt"),
+    };
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{} needs a value", flag))?;
+        let invalid = || format!("invalid value for {}: {}", flag, value);
+        match flag.as_str() {
+            "--runs" => options.runs = value.parse().map_err(|_| invalid())?,
+            "--iterations" => options.iterations = value.parse().map_err(|_| invalid())?,
+            "--seed" => options.seed = value.parse().map_err(|_| invalid())?,
+            "--out" => options.out = PathBuf::from(value),
+            other => return Err(format!("unknown option {}", other)),
+        }
+    }
+    Ok(options)
+}
+
+// `flake-hunt [--runs N] [--iterations K] [--seed S] [--out dir]`
+pub fn flake_hunt_command(args: &[String]) -> i32 {
+    let options = match parse_options(args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            eprintln!("{}", narration!("flake_hunt.usage"));
+            return 2;
+        }
+    };
+    if let Err(error) = fs::create_dir_all(&options.out) {
+        eprintln!("{}", narration!("flake_hunt.cannot_create", path = options.out.display(), error));
+        return 2;
+    }
+
+    let cores = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    println!("{}", narration!("flake_hunt.heading", runs = options.runs, iterations = options.iterations, seed = options.seed));
+
+    let mut findings = Vec::new();
+    for run in 0..options.runs {
+        let seed = options.seed.wrapping_add(run as u64);
+        let pressure = pressure_for_run(run, cores);
+        let log = options.out.join(format!("run-{}-seed-{}.jsonl", run, seed));
+        let _ = fs::remove_file(&log);
+
+        let outcome = run_child(seed, options.iterations, pressure, &log);
+        println!("{}", narration!("flake_hunt.run", run, seed, pressure = pressure.describe(), outcome = ?outcome));
+
+        if outcome == Outcome::Passed {
+            let _ = fs::remove_file(&log);  // Only failures keep their evidence
+            continue;
+        }
+
+        // Same seed, no pressure: does it fail again?
+        let calm_log = options.out.join(format!("run-{}-seed-{}-calm.jsonl", run, seed));
+        let _ = fs::remove_file(&calm_log);
+        let calm = run_child(seed, options.iterations, Pressure::NONE, &calm_log);
+        let verdict = if calm == Outcome::Passed {
+            let _ = fs::remove_file(&calm_log);
+            narration!("flake_hunt.flaky")
+        } else {
+            narration!("flake_hunt.deterministic")
+        };
+        findings.push(narration!("flake_hunt.finding", seed, pressure = pressure.describe(), outcome = ?outcome,
+                                 verdict, log = log.display()));
+    }
+
+    if findings.is_empty() {
+        println!("{}", narration!("flake_hunt.no_failures", runs = options.runs));
+        let _ = fs::remove_dir(&options.out);  // Only removed when empty
+        0
+    } else {
+        println!("{}", narration!("flake_hunt.failures", count = findings.len()));
+        for finding in &findings {
+            println!("  {}", finding);
+        }
+        println!("{}", narration!("flake_hunt.replay"));
+        1
+    }
+}
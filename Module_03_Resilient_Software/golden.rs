@@ -0,0 +1,346 @@
+/*!
+ * Golden Output
+ *
+ * What the demos print is read by people, so its wording and layout
+ * should not drift by accident. Much of it still varies from run to run
+ * for good reasons: timings, finish-time bars, thread ids, which thread
+ * gets to print first. `golden` runs every registered demo in a child
+ * process, normalizes what it printed and compares the result with
+ * golden/<demo>.txt:
+ *
+ *   76ms, 0.131ms, 2.5s        [duration]
+ *   0.131 (a bare timing)      [time]
+ *   ThreadId(7)                ThreadId([id])
+ *   at 0x7ffd5e8c3a10          at [address]
+ *   ######## ending a line     [bar]
+ *
+ * A hex number is an address only where the line says so: after "at",
+ * "address", "pointer" or "ptr". Other hex, like a value printed with
+ * {:#x}, is the same in every run and is compared as it is.
+ *
+ * Padding around a placeholder shrinks to one space, since the width of
+ * a column of timings depends on the timings. Lines that threads print
+ * while the demo's own thread waits for them are grouped by thread, and
+ * the groups put in order of their text. Each thread's lines keep their
+ * order; only the race between threads is taken out. The event log says
+ * which thread printed each line. The other demo binaries do not write
+ * to it, so their stdout is used as it is.
+ *
+ * Anything else that legitimately varies is matched by writing [..] in
+ * the golden file; it stands for any text within one line. Children run
+ * with the SAFETY_DEMO_* variables cleared, so local settings do not leak
+ * in. The mutex and RwLock interleavings are pinned with a seed.
+ *
+ * `golden --bless` rewrites the files from the current output, for a
+ * change to the output that is intended. Review the diff before
+ * committing it, and put back any [..] by hand.
+ */
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::env_config::{ENV_PREFIX, KNOWN_KEYS};
+use crate::event_log::{self, Event};
+use crate::static_check;
+
+use crate::sections::{registry, BINARY_DEMOS};
+
+const WILDCARD: &str = "[..]";
+const UNITS: [&str; 5] = ["ns", "µs", "us", "ms", "s"];
+const ADDRESS_LABELS: [&str; 4] = ["address", "at", "pointer", "ptr"];
+const SHOWN: usize = static_check::nonzero(12);  // diff lines listed before "... and N more"
+
+// How a demo's output compared with its golden file
+#[derive(Debug, PartialEq)]
+pub enum Golden {
+    Matched,
+    Missing,
+    Differs(Vec<String>),  // "- expected" and "+ actual" lines
+}
+
+pub fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("golden")
+}
+
+fn golden_path(demo: &str) -> PathBuf {
+    golden_dir().join(format!("{}.txt", demo))
+}
+
+fn is_word(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+// Whether the text before a hex number labels it an address: "at 0x..",
+// "address: 0x..", "ptr=0x.."
+fn labels_address(before: &str) -> bool {
+    let before = before.trim_end_matches([' ', ':', '=']);
+    ADDRESS_LABELS.iter().any(|label| before.strip_suffix(label).is_some_and(|rest| !rest.ends_with(is_word)))
+}
+
+// Length of the number `text` starts with: digits, then maybe a fraction
+fn number_len(text: &str) -> usize {
+    let digits = |text: &str| text.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(text.len());
+    let whole = digits(text);
+    match text[whole..].strip_prefix('.') {
+        Some(fraction) if digits(fraction) > 0 => whole + 1 + digits(fraction),
+        _ => whole,
+    }
+}
+
+// Put a placeholder in for the text before `rest`, shrinking the padding
+// on either side of it to one space
+fn replace<'a>(normalized: &mut String, placeholder: &str, rest: &'a str) -> &'a str {
+    if normalized.ends_with("  ") {
+        normalized.truncate(normalized.trim_end_matches(' ').len() + 1);
+    }
+    normalized.push_str(placeholder);
+    let after = rest.trim_start_matches(' ');
+    if after.len() + 1 < rest.len() { &rest[rest.len() - after.len() - 1..] } else { rest }
+}
+
+fn normalize_line(line: &str) -> String {
+    let mut normalized = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(ch) = rest.chars().next() {
+        let follows_word = normalized.ends_with(is_word);
+        if let Some(hex) = rest.strip_prefix("0x").filter(|_| !follows_word && labels_address(&normalized)) {
+            let len = hex.find(|ch: char| !ch.is_ascii_hexdigit()).unwrap_or(hex.len());
+            if len > 0 {
+                rest = replace(&mut normalized, "[address]", &hex[len..]);
+                continue;
+            }
+        }
+        if let Some(id) = rest.strip_prefix("ThreadId(") {
+            if let Some(end) = id.find(')').filter(|&end| id[..end].chars().all(|ch| ch.is_ascii_digit())) {
+                normalized.push_str("ThreadId([id])");
+                rest = &id[end + 1..];
+                continue;
+            }
+        }
+        if ch.is_ascii_digit() && !follows_word {
+            let (number, after) = rest.split_at(number_len(rest));
+            let unit = UNITS.iter().find(|unit| after.strip_prefix(**unit).is_some_and(|after| !after.starts_with(is_word)));
+            if let Some(unit) = unit {
+                rest = replace(&mut normalized, "[duration]", &after[unit.len()..]);
+            } else if number.split_once('.').is_some_and(|(_, fraction)| fraction.len() >= 3) {
+                rest = replace(&mut normalized, "[time]", after);
+            } else {
+                normalized.push_str(number);
+                rest = after;
+            }
+            continue;
+        }
+        normalized.push(ch);
+        rest = &rest[ch.len_utf8()..];
+    }
+    // A bar whose length is a timing
+    let trimmed = normalized.trim_end();
+    if trimmed.ends_with('#') {
+        let mut bar = trimmed.trim_end_matches('#').to_string();
+        replace(&mut bar, "[bar]", "");
+        return bar;
+    }
+    trimmed.to_string()
+}
+
+// Output with the parts that vary between runs replaced by placeholders
+pub fn normalize(lines: &[String]) -> String {
+    lines.iter().map(|line| normalize_line(line) + "\n").collect()
+}
+
+// The lines as printed by `main` and the threads it waited for, with
+// each run of the other threads' lines grouped by thread and the groups
+// in order of their text
+pub fn canonical_order(main: &str, printed: Vec<(String, String)>) -> Vec<String> {
+    fn flush(lines: &mut Vec<String>, threads: &mut Vec<(String, Vec<String>)>) {
+        threads.sort_by(|(_, a), (_, b)| a.cmp(b));
+        lines.extend(threads.drain(..).flat_map(|(_, printed)| printed));
+    }
+    let (mut lines, mut threads) = (Vec::new(), Vec::new());
+    for (thread, line) in printed {
+        if thread == main {
+            flush(&mut lines, &mut threads);
+            lines.push(line);
+            continue;
+        }
+        match threads.iter_mut().find(|(printer, _)| *printer == thread) {
+            Some((_, printed)) => printed.push(line),
+            None => threads.push((thread, vec![line])),
+        }
+    }
+    flush(&mut lines, &mut threads);
+    lines
+}
+
+// Whether an output line matches a golden line, [..] matching any text
+pub fn line_matches(expected: &str, actual: &str) -> bool {
+    let mut pieces = expected.split(WILDCARD);
+    let first = pieces.next().unwrap_or_default();
+    let Some(mut rest) = actual.strip_prefix(first) else {
+        return false;
+    };
+    let mut pieces: Vec<&str> = pieces.collect();
+    let Some(last) = pieces.pop() else {
+        return rest.is_empty();  // no wildcard: the whole line
+    };
+    for piece in pieces {
+        match rest.find(piece) {
+            Some(at) => rest = &rest[at + piece.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+// The lines that differ, by a longest common subsequence of matching lines
+pub fn diff(expected: &str, actual: &str) -> Vec<String> {
+    let (expected, actual): (Vec<&str>, Vec<&str>) = (expected.lines().collect(), actual.lines().collect());
+    // common[i][j]: longest match of expected[i..] with actual[j..]
+    let mut common = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = if line_matches(expected[i], actual[j]) {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j, mut lines) = (0, 0, Vec::new());
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && line_matches(expected[i], actual[j]) {
+            (i, j) = (i + 1, j + 1);
+        } else if j == actual.len() || (i < expected.len() && common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(format!("- {}", expected[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", actual[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+// What one demo prints, run on its own by `exe` (thread_safe) with only
+// the pinned settings
+pub fn capture(exe: &Path, demo: &str) -> Result<Vec<String>, String> {
+    let log = env::temp_dir().join(format!("golden-{}-{}.jsonl", demo, std::process::id()));
+    let _ = fs::remove_file(&log);  // the log appends
+    let mut command = Command::new(exe);
+    command.args(["demos", "run", demo]);
+    for key in KNOWN_KEYS {
+        command.env_remove(format!("{}{}", ENV_PREFIX, key));
+    }
+    let output = command
+        .env("SAFETY_DEMO_SEED", "1")
+        .env("SAFETY_DEMO_TICK_MS", "0")
+        .env("SAFETY_DEMO_EVENT_LOG", &log)
+        .env("NO_COLOR", "1")
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|error| format!("cannot run {}: {}", demo, error));
+    let records = event_log::load(&log);
+    let _ = fs::remove_file(&log);
+    let stdout = String::from_utf8_lossy(&output?.stdout).into_owned();
+    if BINARY_DEMOS.iter().any(|binary| binary.binary == demo) {
+        return Ok(stdout.lines().map(str::to_string).collect());
+    }
+
+    let records = records.map_err(|error| format!("cannot read the event log of {}: {}", demo, error))?;
+    let main = records.iter().find(|record| matches!(record.event, Event::DemoStarted { .. })).map(|record| record.thread.clone());
+    let printed = records
+        .into_iter()
+        .filter_map(|record| match record.event {
+            Event::Output { line } => Some((record.thread, line)),
+            _ => None,
+        })
+        .flat_map(|(thread, line)| line.lines().map(|line| (thread.clone(), line.to_string())).collect::<Vec<_>>())
+        .collect();
+    let mut lines = canonical_order(&main.unwrap_or_default(), printed);
+    lines.extend(stdout.lines().last().map(str::to_string));  // "<demo>: passed"
+    Ok(lines)
+}
+
+pub fn compare(exe: &Path, demo: &str) -> Result<Golden, String> {
+    let actual = normalize(&capture(exe, demo)?);
+    let Ok(expected) = fs::read_to_string(golden_path(demo)) else {
+        return Ok(Golden::Missing);
+    };
+    let lines = diff(&expected, &actual);
+    Ok(if lines.is_empty() { Golden::Matched } else { Golden::Differs(lines) })
+}
+
+fn bless(exe: &Path, demo: &str) -> Result<(), String> {
+    let path = golden_path(demo);
+    let output = normalize(&capture(exe, demo)?);
+    fs::create_dir_all(golden_dir()).and_then(|()| fs::write(&path, output)).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+// `golden [--bless] [demo...]`: compare (or rewrite) the golden output of
+// the named demos, or of all of them
+pub fn golden_command(args: &[String]) -> i32 {
+    let bless_files = args.first().is_some_and(|arg| arg == "--bless");
+    let names: Vec<&str> = args[usize::from(bless_files)..].iter().map(String::as_str).collect();
+    let known: Vec<&'static str> = registry().iter().map(|demo| demo.name()).collect();
+    if let Some(unknown) = names.iter().find(|name| !known.contains(name)) {
+        eprintln!("{}", narration!("golden.no_such_demo", name = unknown));
+        eprintln!("{}", narration!("golden.usage"));
+        return 2;
+    }
+    let demos: Vec<&str> = if names.is_empty() { known } else { names };
+    let exe = match env::current_exe() {
+        Ok(exe) => exe,
+        Err(error) => {
+            eprintln!("{}", narration!("golden.cannot_locate", error));
+            return 1;
+        }
+    };
+
+    let mut failed = 0;
+    for demo in &demos {
+        if bless_files {
+            match bless(&exe, demo) {
+                Ok(()) => println!("{}", narration!("golden.blessed", path = golden_path(demo).display())),
+                Err(error) => {
+                    println!("{}", narration!("golden.error", demo, error));
+                    failed += 1;
+                }
+            }
+            continue;
+        }
+        match compare(&exe, demo) {
+            Ok(Golden::Matched) => println!("{}", narration!("golden.ok", demo)),
+            Ok(Golden::Missing) => {
+                println!("{}", narration!("golden.missing", demo));
+                failed += 1;
+            }
+            Ok(Golden::Differs(lines)) => {
+                println!("{}", narration!("golden.differs", demo));
+                for line in lines.iter().take(SHOWN) {
+                    println!("             {}", line);
+                }
+                if lines.len() > SHOWN {
+                    println!("{}", narration!("golden.more", count = lines.len() - SHOWN));
+                }
+                failed += 1;
+            }
+            Err(error) => {
+                println!("{}", narration!("golden.error", demo, error));
+                failed += 1;
+            }
+        }
+    }
+    if failed == 0 {
+        if bless_files {
+            println!("{}", narration!("golden.all_blessed", demos = demos.len()));
+        } else {
+            println!("{}", narration!("golden.all_match", demos = demos.len()));
+        }
+        0
+    } else {
+        println!("{}", narration!("golden.demos_failed", failed, demos = demos.len()));
+        1
+    }
+}
@@ -0,0 +1,356 @@
+/*!
+ * Rubric-Driven Auto-Grading
+ *
+ * `grade --rubric rubric.toml` scores a student's modified crate against
+ * a rubric: invariants that must be checked and must hold under the
+ * stress workloads, APIs that must not appear in the listed source files,
+ * minimum benchmark throughputs, and external commands (such as
+ * `cargo test`) that must succeed. The result is a JSON report the LMS
+ * can import directly.
+ */
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use resilient_core::{SafeCounter, SharedData};
+use serde::{Deserialize, Serialize};
+
+use crate::event_log;
+use crate::fault::FaultPlan;
+use crate::static_check;
+use crate::stress::{Params, WORKLOADS};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Rubric {
+    title: String,
+    #[serde(default = "default_iterations")]
+    iterations: usize,  // stress iterations per invariant check
+    #[serde(default, rename = "invariant")]
+    invariants: Vec<InvariantRule>,
+    #[serde(default, rename = "forbidden")]
+    forbidden: Vec<ForbiddenRule>,
+    #[serde(default, rename = "benchmark")]
+    benchmarks: Vec<BenchmarkRule>,
+    #[serde(default, rename = "command")]
+    commands: Vec<CommandRule>,
+}
+
+fn default_iterations() -> usize {
+    20
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InvariantRule {
+    name: String,      // invariant name as passed to check_invariant
+    workload: String,  // stress workload that exercises it
+    points: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ForbiddenRule {
+    pattern: String,        // plain text, e.g. ".unwrap()"
+    files: Vec<PathBuf>,    // relative to the rubric file
+    points: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BenchmarkRule {
+    name: String,  // one of BENCHMARKS
+    min_ops_per_sec: f64,
+    points: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CommandRule {
+    name: String,
+    run: Vec<String>,  // program followed by its arguments
+    points: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct GradedItem {
+    category: &'static str,
+    name: String,
+    passed: bool,
+    points_awarded: u32,
+    points_possible: u32,
+    detail: String,
+}
+
+#[derive(Debug, Serialize)]
+struct GradeReport {
+    rubric: String,
+    score: u32,
+    max_score: u32,
+    items: Vec<GradedItem>,
+}
+
+fn item(category: &'static str, name: &str, points: u32, outcome: Result<String, String>) -> GradedItem {
+    let passed = outcome.is_ok();
+    GradedItem {
+        category,
+        name: name.to_string(),
+        passed,
+        points_awarded: if passed { points } else { 0 },
+        points_possible: points,
+        detail: outcome.unwrap_or_else(|reason| reason),
+    }
+}
+
+// The invariant must be checked at least once and never fail
+fn grade_invariant(rule: &InvariantRule, iterations: usize) -> Result<String, String> {
+    let workload = WORKLOADS
+        .iter()
+        .find(|(name, _)| *name == rule.workload)
+        .map(|(_, workload)| *workload)
+        .ok_or_else(|| format!("unknown workload '{}'", rule.workload))?;
+
+    event_log::take_invariant_tally();
+    for iteration in 0..iterations {
+        let params = Params {
+            threads: 2 + iteration % 7,
+            size: 100 + 50 * iteration,
+            seed: iteration as u64,
+        };
+        workload(&params, &FaultPlan::none());
+    }
+    let tally = event_log::take_invariant_tally();
+
+    match tally.get(&rule.name) {
+        None => Err(format!("never checked by {}", rule.workload)),
+        Some((passed, 0)) => Ok(format!("held in {} checks", passed)),
+        Some((passed, failed)) => Err(format!("violated in {} of {} checks", failed, passed + failed)),
+    }
+}
+
+// Occurrences of the pattern outside comment lines, as "file:line"
+fn grade_forbidden(rule: &ForbiddenRule, base: &Path) -> Result<String, String> {
+    let mut hits = Vec::new();
+    for file in &rule.files {
+        let path = base.join(file);
+        let source = fs::read_to_string(&path)
+            .map_err(|error| format!("cannot read {}: {}", path.display(), error))?;
+        for (number, line) in source.lines().enumerate() {
+            let code = line.trim_start();
+            if code.starts_with("//") || code.starts_with('*') || code.starts_with("/*") {
+                continue;
+            }
+            if code.contains(&rule.pattern) {
+                hits.push(format!("{}:{}", file.display(), number + 1));
+            }
+        }
+    }
+
+    if hits.is_empty() {
+        Ok(format!("not used in {} file(s)", rule.files.len()))
+    } else {
+        let shown: Vec<&str> = hits.iter().take(10).map(String::as_str).collect();
+        Err(format!("{} use(s): {}", hits.len(), shown.join(", ")))
+    }
+}
+
+// Built-in benchmarks: each returns operations per second
+type Benchmark = fn() -> f64;
+
+const BENCHMARKS: [(&str, Benchmark); 3] = [
+    ("counter_increments", bench_counter),
+    ("mutex_updates", bench_mutex),
+    ("channel_messages", bench_channel),
+];
+
+const BENCH_THREADS: usize = static_check::nonzero(4);
+const BENCH_OPS: usize = static_check::nonzero(100_000);
+
+fn bench_counter() -> f64 {
+    let counter = Arc::new(SafeCounter::new());
+    let start = Instant::now();
+    let handles: Vec<_> = (0..BENCH_THREADS)
+        .map(|_| {
+            let counter = Arc::clone(&counter);
+            thread::spawn(move || {
+                for _ in 0..BENCH_OPS {
+                    counter.increment();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        let _ = handle.join();
+    }
+    (BENCH_THREADS * BENCH_OPS) as f64 / start.elapsed().as_secs_f64()
+}
+
+fn bench_mutex() -> f64 {
+    let shared = Arc::new(Mutex::new(SharedData::new()));
+    let start = Instant::now();
+    let handles: Vec<_> = (0..BENCH_THREADS)
+        .map(|_| {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || {
+                for i in 0..BENCH_OPS {
+                    let mut data = shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    data.add_value((i % 3) as i32);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        let _ = handle.join();
+    }
+    (BENCH_THREADS * BENCH_OPS) as f64 / start.elapsed().as_secs_f64()
+}
+
+fn bench_channel() -> f64 {
+    let (sender, receiver) = mpsc::channel();
+    let start = Instant::now();
+    let producer = thread::spawn(move || {
+        for i in 0..BENCH_OPS {
+            if sender.send(i).is_err() {
+                break;
+            }
+        }
+    });
+    let received = receiver.iter().count();
+    let _ = producer.join();
+    received as f64 / start.elapsed().as_secs_f64()
+}
+
+fn grade_benchmark(rule: &BenchmarkRule) -> Result<String, String> {
+    let bench = BENCHMARKS
+        .iter()
+        .find(|(name, _)| *name == rule.name)
+        .map(|(_, bench)| *bench)
+        .ok_or_else(|| format!("unknown benchmark '{}'", rule.name))?;
+
+    // Best of three to smooth out scheduler noise
+    let best = (0..3).map(|_| bench()).fold(0.0, f64::max);
+    let detail = format!("{:.0} ops/s (minimum {:.0})", best, rule.min_ops_per_sec);
+    if best >= rule.min_ops_per_sec {
+        Ok(detail)
+    } else {
+        Err(detail)
+    }
+}
+
+fn grade_external(rule: &CommandRule, base: &Path) -> Result<String, String> {
+    let (program, args) = rule.run.split_first().ok_or("empty command")?;
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(base)
+        .output()
+        .map_err(|error| format!("cannot run {}: {}", program, error))?;
+
+    if output.status.success() {
+        Ok("succeeded".to_string())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let last_line = stderr.lines().last().unwrap_or("").trim().to_string();
+        Err(format!("failed ({}): {}", output.status, last_line))
+    }
+}
+
+fn grade(rubric: &Rubric, base: &Path) -> GradeReport {
+    let mut items = Vec::new();
+
+    for rule in &rubric.invariants {
+        items.push(item("invariant", &rule.name, rule.points, grade_invariant(rule, rubric.iterations)));
+    }
+    for rule in &rubric.forbidden {
+        items.push(item("forbidden", &rule.pattern, rule.points, grade_forbidden(rule, base)));
+    }
+    for rule in &rubric.benchmarks {
+        items.push(item("benchmark", &rule.name, rule.points, grade_benchmark(rule)));
+    }
+    for rule in &rubric.commands {
+        items.push(item("command", &rule.name, rule.points, grade_external(rule, base)));
+    }
+
+    GradeReport {
+        rubric: rubric.title.clone(),
+        score: items.iter().map(|item| item.points_awarded).sum(),
+        max_score: items.iter().map(|item| item.points_possible).sum(),
+        items,
+    }
+}
+
+// `grade --rubric <file> [--out <file>]` - exit code 0 only for a full score
+pub fn grade_command(args: &[String]) -> i32 {
+    let usage = narration!("grade.usage");
+    let (mut rubric_path, mut out_path) = (None, None);
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        match (flag.as_str(), args.next()) {
+            ("--rubric", Some(path)) => rubric_path = Some(PathBuf::from(path)),
+            ("--out", Some(path)) => out_path = Some(PathBuf::from(path)),
+            _ => {
+                eprintln!("{}", usage);
+                return 2;
+            }
+        }
+    }
+    let rubric_path = match rubric_path {
+        Some(path) => path,
+        None => {
+            eprintln!("{}", usage);
+            return 2;
+        }
+    };
+
+    let rubric: Rubric = match fs::read_to_string(&rubric_path)
+        .map_err(|error| error.to_string())
+        .and_then(|text| toml::from_str(&text).map_err(|error| error.to_string()))
+    {
+        Ok(rubric) => rubric,
+        Err(error) => {
+            eprintln!("{}", narration!("grade.invalid_rubric", path = rubric_path.display(), error));
+            return 2;
+        }
+    };
+
+    // File paths in the rubric are relative to the rubric itself
+    let base = match rubric_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let report = grade(&rubric, &base);
+
+    for item in &report.items {
+        let mark = if item.passed { narration!("grade.pass") } else { narration!("grade.fail") };
+        eprintln!("[{}] {:<9} {} ({}/{}) - {}", mark, item.category, item.name,
+                  item.points_awarded, item.points_possible, item.detail);
+    }
+    eprintln!("{}", narration!("grade.score", score = report.score, total = report.max_score));
+
+    let json = match serde_json::to_string_pretty(&report) {
+        Ok(json) => json,
+        Err(error) => {
+            eprintln!("{}", narration!("grade.serialize_failed", error));
+            return 1;
+        }
+    };
+    match out_path {
+        Some(path) => {
+            if let Err(error) = fs::write(&path, json) {
+                eprintln!("{}", narration!("grade.write_failed", path = path.display(), error));
+                return 1;
+            }
+        }
+        None => println!("{}", json),
+    }
+
+    if report.score == report.max_score {
+        0
+    } else {
+        1
+    }
+}
@@ -0,0 +1,321 @@
+/*!
+ * Hazard Pointers
+ *
+ * LockFreeQueue packs its values into a fixed ring, so it never frees
+ * anything. A lock-free structure with nodes has to: a Treiber stack
+ * pushes by swapping a new node into `head` with a CAS, and pops by
+ * swapping `head` for `head.next`. The catch is the pop. A thread loads
+ * `head`, and before it reads `head.next` another thread may pop that
+ * node and free it; the first thread then reads freed memory. A Mutex
+ * rules that out by making the others wait, and a lock-free stack has to
+ * rule it out another way.
+ *
+ * Hazard pointers: each thread has a slot where it publishes the node it
+ * is about to follow. A popped node is not freed but retired to the
+ * popping thread's list, and once the list is long enough the thread
+ * scans every slot and frees only the retired nodes no slot names. A
+ * reader stores its hazard and then checks that `head` still is that
+ * node (both SeqCst, so a scan cannot miss a hazard published before the
+ * node was unlinked); from then on the node cannot be freed under it.
+ * The same rule stops ABA: a node that cannot be freed cannot have its
+ * address reused for a new node while a stale CAS still expects it.
+ *
+ * Epoch-based reclamation (crossbeam-epoch) is the other common scheme:
+ * threads announce an epoch instead of each pointer, which is cheaper
+ * per read but lets one stalled thread hold back every free.
+ *
+ * To show that no freed node is ever read, a checked stack does not give
+ * freed nodes back to the allocator: it marks them freed and keeps them
+ * until the stack is dropped, and every read of a node looks at the
+ * mark. A read of a marked node is a use-after-free that did no harm
+ * only because the memory was kept. Reclaim::Immediate, which frees a
+ * node as soon as it is popped, is always checked this way: freeing for
+ * real would make that read undefined behaviour.
+ */
+
+use std::mem::ManuallyDrop;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::spawn_policy;
+
+// When a popped node is freed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reclaim {
+    Immediate,       // as soon as it is popped: the bug
+    HazardPointers,  // once no hazard slot names it
+}
+
+struct Node<T> {
+    value: ManuallyDrop<T>,  // taken by the pop that unlinks the node
+    next: *mut Node<T>,
+    freed: AtomicBool,
+}
+
+// A node handed between threads: a retired node in the orphan list, or a
+// freed one kept for the check
+struct Retired<T>(*mut Node<T>);
+
+// SAFETY: a Retired node is unlinked, and only the list that holds it
+// frees it
+unsafe impl<T: Send> Send for Retired<T> {}
+
+// A lock-free LIFO stack whose pops go through a Handle
+pub struct TreiberStack<T> {
+    head: AtomicPtr<Node<T>>,
+    reclaim: Reclaim,
+    hazards: Box<[AtomicPtr<Node<T>>]>,  // one per handle
+    claimed: Box<[AtomicBool]>,
+    orphans: Mutex<Vec<Retired<T>>>,     // retired by a handle that was dropped first
+    graveyard: Option<Mutex<Vec<Retired<T>>>>,  // freed nodes, kept when checked
+    window: bool,                        // yield between loading head and following it
+    stale_reads: AtomicUsize,
+    reclaimed: AtomicUsize,
+}
+
+// SAFETY: the stack moves T values between threads, and otherwise only
+// shares atomics and locked lists; node pointers are only followed under
+// a hazard (or, for Reclaim::Immediate, into kept memory)
+unsafe impl<T: Send> Send for TreiberStack<T> {}
+unsafe impl<T: Send> Sync for TreiberStack<T> {}
+
+impl<T> TreiberStack<T> {
+    // A stack that `handles` threads can use at once
+    pub fn new(reclaim: Reclaim, handles: usize) -> Self {
+        TreiberStack {
+            head: AtomicPtr::new(ptr::null_mut()),
+            reclaim,
+            hazards: (0..handles).map(|_| AtomicPtr::new(ptr::null_mut())).collect(),
+            claimed: (0..handles).map(|_| AtomicBool::new(false)).collect(),
+            orphans: Mutex::new(Vec::new()),
+            graveyard: (reclaim == Reclaim::Immediate).then(|| Mutex::new(Vec::new())),
+            window: false,
+            stale_reads: AtomicUsize::new(0),
+            reclaimed: AtomicUsize::new(0),
+        }
+    }
+
+    // Keep freed nodes and count reads of them
+    pub fn checked(mut self) -> Self {
+        self.graveyard.get_or_insert_with(|| Mutex::new(Vec::new()));
+        self
+    }
+
+    // Yield between loading head and reading head.next, so on one core
+    // another thread gets to pop in between
+    pub fn widen_window(mut self) -> Self {
+        assert!(self.graveyard.is_some(), "a widened window needs a checked stack");
+        self.window = true;
+        self
+    }
+
+    // A free slot, or None when every one is taken
+    pub fn handle(&self) -> Option<Handle<'_, T>> {
+        let slot = self.claimed.iter().position(|claimed| {
+            claimed.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_ok()
+        })?;
+        Some(Handle { stack: self, slot, retired: Vec::new() })
+    }
+
+    // Reads of a node after it was freed; only counted on a checked stack
+    pub fn stale_reads(&self) -> usize {
+        self.stale_reads.load(Ordering::Relaxed)
+    }
+
+    // Nodes freed so far, not counting what drop frees
+    pub fn reclaimed(&self) -> usize {
+        self.reclaimed.load(Ordering::Relaxed)
+    }
+
+    // Only `freed` and `next` are read, through the raw pointer: the
+    // thread that wins the pop may be taking `value` meanwhile, so no
+    // reference to the whole node is ever formed here
+    fn next_of(&self, node: *mut Node<T>) -> *mut Node<T> {
+        // SAFETY: `node` is either protected by a hazard, so not yet freed,
+        // or (Reclaim::Immediate) freed into the graveyard, which keeps it.
+        // `freed` is atomic, and `next` is only written before the node is
+        // published
+        unsafe {
+            if (*ptr::addr_of!((*node).freed)).load(Ordering::Acquire) {
+                self.stale_reads.fetch_add(1, Ordering::Relaxed);
+            }
+            ptr::addr_of!((*node).next).read()
+        }
+    }
+
+    fn free(&self, node: *mut Node<T>) {
+        self.reclaimed.fetch_add(1, Ordering::Relaxed);
+        match &self.graveyard {
+            Some(graveyard) => {
+                // SAFETY: unlinked and retired, so still allocated
+                unsafe { (*node).freed.store(true, Ordering::Release) };
+                graveyard.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(Retired(node));
+            }
+            // SAFETY: no hazard names the node and it is unlinked, so no
+            // thread can reach it; its value was taken by the pop
+            None => drop(unsafe { Box::from_raw(node) }),
+        }
+    }
+}
+
+impl<T> Drop for TreiberStack<T> {
+    fn drop(&mut self) {
+        let mut node = *self.head.get_mut();
+        while !node.is_null() {
+            // SAFETY: &mut self: no handle is left, and these nodes are
+            // still linked, so each is owned by the stack alone
+            let mut owned = unsafe { Box::from_raw(node) };
+            node = owned.next;
+            // SAFETY: only a pop takes a value, and a pop unlinks its node
+            unsafe { ManuallyDrop::drop(&mut owned.value) };
+        }
+        let orphans = self.orphans.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let graveyard = self.graveyard.as_mut().map(|graveyard| graveyard.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner()));
+        for Retired(node) in orphans.drain(..).chain(graveyard.into_iter().flat_map(|graveyard| graveyard.drain(..))) {
+            // SAFETY: retired or freed nodes, each in one list once, and
+            // their values already taken
+            drop(unsafe { Box::from_raw(node) });
+        }
+    }
+}
+
+// One thread's way into a TreiberStack: its hazard slot and the nodes it
+// retired. Not Send: the slot belongs to the thread using it
+pub struct Handle<'a, T> {
+    stack: &'a TreiberStack<T>,
+    slot: usize,
+    retired: Vec<*mut Node<T>>,
+}
+
+impl<T> Handle<'_, T> {
+    pub fn push(&self, value: T) {
+        let node = Box::into_raw(Box::new(Node { value: ManuallyDrop::new(value), next: ptr::null_mut(), freed: AtomicBool::new(false) }));
+        let mut head = self.stack.head.load(Ordering::Relaxed);
+        loop {
+            // SAFETY: the node is not published yet; this thread owns it
+            unsafe { (*node).next = head };
+            match self.stack.head.compare_exchange_weak(head, node, Ordering::Release, Ordering::Relaxed) {
+                Ok(_) => return,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let stack = self.stack;
+        let hazard = &stack.hazards[self.slot];
+        loop {
+            let head = stack.head.load(Ordering::Acquire);
+            if head.is_null() {
+                hazard.store(ptr::null_mut(), Ordering::Release);
+                return None;
+            }
+            if stack.reclaim == Reclaim::HazardPointers {
+                hazard.store(head, Ordering::SeqCst);
+                if stack.head.load(Ordering::SeqCst) != head {
+                    continue;  // popped before the hazard was up: it may be gone
+                }
+            }
+            if stack.window {
+                thread::yield_now();
+            }
+            let next = stack.next_of(head);
+            if stack.head.compare_exchange(head, next, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+                hazard.store(ptr::null_mut(), Ordering::Release);
+                // SAFETY: the CAS unlinked the node, so this thread alone
+                // takes its value; others may still read its `next`
+                let value = unsafe { ManuallyDrop::take(&mut (*head).value) };
+                self.retire(head);
+                return Some(value);
+            }
+        }
+    }
+
+    fn retire(&mut self, node: *mut Node<T>) {
+        match self.stack.reclaim {
+            Reclaim::Immediate => self.stack.free(node),
+            Reclaim::HazardPointers => {
+                self.retired.push(node);
+                // Scanning costs a pass over every slot; wait until that
+                // frees a good share of the list
+                if self.retired.len() >= 2 * self.stack.hazards.len() {
+                    self.scan();
+                }
+            }
+        }
+    }
+
+    // Free every retired node that no hazard names
+    fn scan(&mut self) {
+        let stack = self.stack;
+        let orphans = std::mem::take(&mut *stack.orphans.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+        self.retired.extend(orphans.into_iter().map(|Retired(node)| node));
+        let hazards: Vec<_> = stack.hazards.iter().map(|hazard| hazard.load(Ordering::SeqCst)).collect();
+        self.retired.retain(|&node| {
+            let protected = hazards.contains(&node);
+            if !protected {
+                stack.free(node);
+            }
+            protected
+        });
+    }
+}
+
+impl<T> Drop for Handle<'_, T> {
+    fn drop(&mut self) {
+        self.scan();
+        let left = self.retired.drain(..).map(Retired);
+        self.stack.orphans.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).extend(left);
+        self.stack.hazards[self.slot].store(ptr::null_mut(), Ordering::Release);
+        self.stack.claimed[self.slot].store(false, Ordering::Release);
+    }
+}
+
+#[derive(Debug)]
+pub struct StackRun {
+    pub pushed: usize,
+    pub popped: Vec<u64>,  // sorted, including what was left to drain
+    pub stale_reads: usize,
+    pub reclaimed: usize,  // freed while the threads ran
+    pub elapsed: Duration,
+}
+
+impl StackRun {
+    pub fn exactly_once(&self) -> bool {
+        self.popped.len() == self.pushed && self.popped.iter().enumerate().all(|(i, &value)| value == i as u64)
+    }
+}
+
+// `threads` threads each push `ops` distinct values, popping one after
+// every push, on a checked stack with a widened window
+pub fn run(reclaim: Reclaim, threads: usize, ops: usize) -> StackRun {
+    let stack = TreiberStack::new(reclaim, threads).checked().widen_window();
+    let start = Instant::now();
+    let mut popped: Vec<u64> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|worker| {
+                let stack = &stack;
+                spawn_policy::spawn_scoped(scope, move || {
+                    let mut handle = stack.handle().expect("one handle per thread");
+                    let mut popped = Vec::with_capacity(ops);
+                    for i in 0..ops {
+                        handle.push((worker * ops + i) as u64);
+                        popped.extend(handle.pop());
+                    }
+                    popped
+                })
+            })
+            .collect();
+        workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect()
+    });
+    let elapsed = start.elapsed();
+    let reclaimed = stack.reclaimed();
+    let mut handle = stack.handle().expect("every thread's handle was dropped");
+    popped.extend(std::iter::from_fn(|| handle.pop()));
+    drop(handle);
+    popped.sort_unstable();
+    StackRun { pushed: threads * ops, popped, stale_reads: stack.stale_reads(), reclaimed, elapsed }
+}
@@ -0,0 +1,246 @@
+/*!
+ * Hedged Requests
+ *
+ * Most calls to a backend are quick, but a few hit a garbage-collection
+ * pause, a cold cache or a busy disk and take many times longer. Retrying
+ * after a timeout does not help those: the slow call has already cost the
+ * whole timeout. A hedged call waits only as long as most calls take - the
+ * 95th percentile of recent latencies - and then sends the same request to
+ * a second replica as well. Whichever answers first wins, and the other
+ * call is cancelled (its future is dropped), so at most one extra request
+ * goes out for the slowest ~5% of calls.
+ *
+ * HedgeLayer is a middleware layer: it wraps the primary service and holds
+ * the secondary one. The backends are simulated with seeded latencies on a
+ * paused runtime, so each run shows the same percentiles and takes no real
+ * time.
+ */
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::future::{self, Either};
+use tokio::runtime::Runtime;
+use tokio::time::{self, Instant};
+
+use crate::histogram::{Histogram, Snapshot};
+use crate::middleware::{Layer, Service, ServiceExt, ServiceFuture};
+use crate::rng::SeededRng;
+use crate::static_check;
+
+pub const REQUESTS: usize = static_check::nonzero(1000);
+pub const PERCENTILE: f64 = 95.0;        // hedge once a call is slower than this
+const RECENT: usize = static_check::nonzero(100);   // latencies the percentile is taken over
+const WARM_UP: usize = static_check::nonzero(20);   // no hedging until this many are known
+
+// Call counts shared by a backend and the futures it hands out
+#[derive(Debug, Default)]
+pub struct BackendStats {
+    pub calls: AtomicU32,
+    pub cancelled: AtomicU32,  // dropped before they answered
+}
+
+// A replica that usually answers in ~10ms, sometimes in ~50ms and now
+// and then takes a 200ms pause
+#[derive(Debug)]
+pub struct SimBackend {
+    rng: Mutex<SeededRng>,
+    stats: Arc<BackendStats>,
+}
+
+impl SimBackend {
+    pub fn new(seed: u64) -> Self {
+        SimBackend { rng: Mutex::new(SeededRng::new(seed)), stats: Arc::default() }
+    }
+
+    pub fn stats(&self) -> Arc<BackendStats> {
+        Arc::clone(&self.stats)
+    }
+
+    fn latency(&self) -> Duration {
+        let mut rng = self.rng.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let millis = if rng.chance(0.02) {
+            rng.range(180, 220)
+        } else if rng.chance(0.06) {
+            rng.range(40, 60)
+        } else {
+            rng.range(8, 12)
+        };
+        Duration::from_millis(millis as u64)
+    }
+}
+
+// Counts the call as cancelled if dropped before it answered
+struct Answering {
+    stats: Arc<BackendStats>,
+    answered: bool,
+}
+
+impl Answering {
+    fn answer(mut self) {
+        self.answered = true;
+    }
+}
+
+impl Drop for Answering {
+    fn drop(&mut self) {
+        if !self.answered {
+            self.stats.cancelled.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+impl Service<u32> for Arc<SimBackend> {
+    type Response = u32;
+
+    fn call(&self, request: u32) -> ServiceFuture<'_, u32> {
+        self.stats.calls.fetch_add(1, Ordering::SeqCst);
+        let latency = self.latency();
+        let answering = Answering { stats: self.stats(), answered: false };
+        Box::pin(async move {
+            time::sleep(latency).await;
+            answering.answer();
+            Ok(request * 2)
+        })
+    }
+}
+
+// Latencies of the last RECENT calls, as the client saw them
+#[derive(Debug, Default)]
+pub struct LatencyTracker {
+    recent: Mutex<VecDeque<Duration>>,
+}
+
+impl LatencyTracker {
+    pub fn record(&self, latency: Duration) {
+        let mut recent = self.recent.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if recent.len() == RECENT {
+            recent.pop_front();
+        }
+        recent.push_back(latency);
+    }
+
+    // The hedge delay: None until enough calls have been seen
+    pub fn threshold(&self) -> Option<Duration> {
+        let recent = self.recent.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        (recent.len() >= WARM_UP).then(|| {
+            let window = Histogram::new();
+            recent.iter().for_each(|&latency| window.record(latency));
+            window.snapshot().percentile(PERCENTILE)
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct HedgeStats {
+    pub hedges: AtomicU32,  // second requests sent
+    pub won: AtomicU32,     // ...that answered first
+}
+
+// Hedge the wrapped (primary) service with `secondary`
+pub struct HedgeLayer<S> {
+    secondary: S,
+    tracker: Arc<LatencyTracker>,
+    stats: Arc<HedgeStats>,
+}
+
+impl<S> HedgeLayer<S> {
+    pub fn new(secondary: S, stats: Arc<HedgeStats>) -> Self {
+        HedgeLayer { secondary, tracker: Arc::default(), stats }
+    }
+}
+
+pub struct Hedge<P, S> {
+    primary: P,
+    secondary: S,
+    tracker: Arc<LatencyTracker>,
+    stats: Arc<HedgeStats>,
+}
+
+impl<P, S: Clone> Layer<P> for HedgeLayer<S> {
+    type Service = Hedge<P, S>;
+
+    fn layer(&self, primary: P) -> Hedge<P, S> {
+        Hedge {
+            primary,
+            secondary: self.secondary.clone(),
+            tracker: Arc::clone(&self.tracker),
+            stats: Arc::clone(&self.stats),
+        }
+    }
+}
+
+impl<P, S, Request> Service<Request> for Hedge<P, S>
+where
+    P: Service<Request> + Sync,
+    S: Service<Request, Response = P::Response> + Sync,
+    P::Response: Send,
+    Request: Clone + Send + 'static,
+{
+    type Response = P::Response;
+
+    fn call(&self, request: Request) -> ServiceFuture<'_, P::Response> {
+        Box::pin(async move {
+            let start = Instant::now();
+            let primary = self.primary.call(request.clone());
+            let result = match self.tracker.threshold() {
+                None => primary.await,
+                Some(delay) => match future::select(primary, Box::pin(time::sleep(delay))).await {
+                    Either::Left((result, _)) => result,
+                    Either::Right(((), primary)) => {
+                        self.stats.hedges.fetch_add(1, Ordering::SeqCst);
+                        // The slower call is dropped here, mid-flight
+                        match future::select(primary, self.secondary.call(request)).await {
+                            Either::Left((result, _)) => result,
+                            Either::Right((result, _)) => {
+                                self.stats.won.fetch_add(1, Ordering::SeqCst);
+                                result
+                            }
+                        }
+                    }
+                },
+            };
+            self.tracker.record(start.elapsed());
+            result
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct TailRun {
+    pub latencies: Snapshot,
+    pub backend_calls: u32,
+    pub cancelled: u32,
+    pub hedges: u32,
+    pub hedges_won: u32,
+}
+
+// Send REQUESTS requests one after another, hedged or not; both runs see
+// the same primary latencies
+pub fn run(runtime: &Runtime, hedged: bool, seed: u64) -> TailRun {
+    let primary = Arc::new(SimBackend::new(seed));
+    let secondary = Arc::new(SimBackend::new(seed ^ 0x5EC0_4DA7));
+    let stats = Arc::new(HedgeStats::default());
+    let calls = |backend: &Arc<SimBackend>| backend.stats.calls.load(Ordering::SeqCst);
+    let cancelled = |backend: &Arc<SimBackend>| backend.stats.cancelled.load(Ordering::SeqCst);
+
+    let latencies = runtime.block_on(async {
+        let client = Arc::clone(&primary).with(HedgeLayer::new(Arc::clone(&secondary), Arc::clone(&stats)));
+        let latencies = Histogram::new();
+        for request in 0..REQUESTS as u32 {
+            let start = Instant::now();
+            let _ = if hedged { client.call(request).await } else { primary.call(request).await };
+            latencies.record(start.elapsed());
+        }
+        latencies.snapshot()
+    });
+    TailRun {
+        latencies,
+        backend_calls: calls(&primary) + calls(&secondary),
+        cancelled: cancelled(&primary) + cancelled(&secondary),
+        hedges: stats.hedges.load(Ordering::SeqCst),
+        hedges_won: stats.won.load(Ordering::SeqCst),
+    }
+}
@@ -0,0 +1,178 @@
+/*!
+ * Latency Histogram
+ *
+ * A list of every latency grows with the run and has to be sorted for a
+ * percentile; a min/avg/max summary hides the tail. Histogram keeps a
+ * fixed array of counts instead, one per bucket, and any number of
+ * threads record into it at once without a lock: a record is a
+ * fetch_add on its bucket, plus the running sum and max.
+ *
+ * Buckets grow exponentially, so one array covers nanoseconds to years
+ * at the same relative precision. Each power of two is split into 32
+ * equal sub-buckets (values below 64ns get a bucket each):
+ *
+ *   [64, 66) [66, 68) ... [126, 128)   2ns wide
+ *   [128, 132) ... [252, 256)          4ns wide, and so on
+ *
+ * A percentile is read from the bucket the rank falls in, as that
+ * bucket's top (or the max, if lower). It is never below the exact
+ * nearest-rank value, and at most 1/32 (3.1%) above it: tests/histogram.rs
+ * holds it to that against a sorted list.
+ *
+ * snapshot() copies the counts out. Snapshots merge by adding counts, so
+ * merging is exact, in any order: workers can each keep a histogram and
+ * a reporter merge them, as the mutex stress does, or share one, as the
+ * bounded-buffer stress does. The max and sum are updated before the
+ * bucket, and the bucket with Release; a snapshot reads the buckets with
+ * Acquire first, so it never counts a value whose max it missed. Taken
+ * while threads are still recording, the sum may include a few values
+ * the buckets do not yet.
+ */
+
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use crate::atomic64::AtomicU64;
+use crate::event_log;
+use crate::units::{Count, Millis, Quantity};
+
+const PRECISION: u32 = 5;
+const SUB_BUCKETS: usize = 1 << PRECISION;
+const BUCKETS: usize = (64 - PRECISION as usize + 1) * SUB_BUCKETS;
+
+// The bucket a value in nanoseconds is counted in
+fn bucket(nanos: u64) -> usize {
+    if nanos < SUB_BUCKETS as u64 {
+        return nanos as usize;
+    }
+    let shift = 63 - nanos.leading_zeros() - PRECISION;
+    shift as usize * SUB_BUCKETS + (nanos >> shift) as usize
+}
+
+// The largest value counted in `bucket`
+fn top(bucket: usize) -> u64 {
+    let shift = (bucket / SUB_BUCKETS).saturating_sub(1);
+    let low = ((bucket - shift * SUB_BUCKETS) as u64) << shift;
+    low + ((1u64 << shift) - 1)
+}
+
+// Latencies recorded by any number of threads at once
+pub struct Histogram {
+    buckets: Box<[AtomicU64]>,
+    sum: AtomicU64,  // nanoseconds
+    max: AtomicU64,
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        Histogram {
+            buckets: (0..BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            sum: AtomicU64::new(0),
+            max: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record(&self, latency: Duration) {
+        let nanos = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+        self.max.fetch_max(nanos, Ordering::Relaxed);
+        self.sum.fetch_add(nanos, Ordering::Relaxed);
+        self.buckets[bucket(nanos)].fetch_add(1, Ordering::Release);
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let counts = self.buckets.iter().map(|count| count.load(Ordering::Acquire)).collect();
+        Snapshot { counts, sum: self.sum.load(Ordering::Relaxed), max: self.max.load(Ordering::Relaxed) }
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Histogram::new()
+    }
+}
+
+impl fmt::Debug for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Histogram").field(&self.snapshot()).finish()
+    }
+}
+
+// A histogram's counts at one moment, to read percentiles from and merge
+#[derive(Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    counts: Vec<u64>,
+    sum: u64,
+    max: u64,
+}
+
+impl Default for Snapshot {
+    fn default() -> Self {
+        Snapshot { counts: vec![0; BUCKETS], sum: 0, max: 0 }
+    }
+}
+
+impl Snapshot {
+    pub fn merge(&mut self, other: &Snapshot) {
+        for (count, added) in self.counts.iter_mut().zip(&other.counts) {
+            *count += added;
+        }
+        self.sum = self.sum.saturating_add(other.sum);
+        self.max = self.max.max(other.max);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    // Every latency recorded, added up
+    pub fn total(&self) -> Duration {
+        Duration::from_nanos(self.sum)
+    }
+
+    pub fn mean(&self) -> Duration {
+        Duration::from_nanos(self.sum / self.count().max(1))
+    }
+
+    pub fn max(&self) -> Duration {
+        Duration::from_nanos(self.max)
+    }
+
+    // Nearest rank, to within a bucket: zero for an empty histogram
+    pub fn percentile(&self, p: f64) -> Duration {
+        let count = self.count();
+        if count == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((p / 100.0 * count as f64).ceil() as u64).clamp(1, count);
+        let mut seen = 0;
+        for (bucket, &in_bucket) in self.counts.iter().enumerate() {
+            seen += in_bucket;
+            if seen >= rank {
+                return Duration::from_nanos(top(bucket).min(self.max));
+            }
+        }
+        self.max()
+    }
+
+    // Add the count, p50, p99 and max to the event log as `<prefix>.<metric>`
+    pub fn record_metrics(&self, prefix: &str) {
+        event_log::metric::<Count>(&format!("{}.count", prefix), Quantity::new(self.count() as f64));
+        for (name, latency) in [("p50", self.percentile(50.0)), ("p99", self.percentile(99.0)), ("max", self.max())] {
+            event_log::metric(&format!("{}.{}", prefix, name), Quantity::<f64, Millis>::from_duration(latency));
+        }
+    }
+}
+
+// "p50 312.0ns, p99 76.2µs, max 1.2ms"
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "p50 {:.1?}, p99 {:.1?}, max {:.1?}", self.percentile(50.0), self.percentile(99.0), self.max())
+    }
+}
+
+impl fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Snapshot").field("count", &self.count()).field("mean", &self.mean()).field("max", &self.max()).finish()
+    }
+}
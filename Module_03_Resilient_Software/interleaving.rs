@@ -0,0 +1,162 @@
+/*!
+ * Recorded Interleavings
+ *
+ * A race that shows up once in a thousand stress iterations cannot be
+ * shown in a lecture. The lost_update workload runs its threads on the
+ * simulated CPU, where the scheduler - not the OS - decides which thread
+ * takes each step, and every decision is kept in the trace. A failing
+ * trace is saved as a schedule file; replaying it on a scripted CPU makes
+ * exactly the same decisions, so the same updates are lost every time.
+ *
+ * The bug itself is one Rust does not prevent: each increment is a load
+ * followed by a separate store. There is no data race (the counter is an
+ * atomic), but there is a race condition.
+ */
+
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+use crate::event_log;
+use crate::sim_cpu::{Policy, SimCpu};
+use crate::static_check;
+
+// Runs with more steps than this are too long to print step by step
+const TIMELINE_LIMIT: usize = static_check::nonzero(64);
+
+// Tasks are named A, B, C, ... in the trace
+pub const MAX_THREADS: usize = static_check::nonzero(26);
+const _: () = assert!(MAX_THREADS <= 26, "tasks are named A to Z");
+
+// Everything needed to run the same interleaving again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Schedule {
+    pub workload: String,
+    pub threads: usize,
+    pub size: usize,
+    pub trace: String,
+}
+
+#[derive(Debug)]
+pub struct Run {
+    pub trace: String,
+    pub timeline: Vec<String>,  // "A reads 3", ... (short runs only)
+    pub expected: usize,
+    pub counted: usize,
+}
+
+fn task_name(worker: usize) -> char {
+    (b'A' + worker as u8) as char
+}
+
+// `threads` tasks each increment a shared counter `size` times, reading
+// and writing in separate steps
+pub fn lost_update(threads: usize, size: usize, policy: Policy) -> Run {
+    let cpu = Arc::new(SimCpu::with_policy(policy));
+    let counter = Arc::new(AtomicUsize::new(0));
+    let keep_timeline = threads * size * 2 <= TIMELINE_LIMIT;
+    let timeline = Arc::new(Mutex::new(Vec::new()));
+
+    let tasks: Vec<char> = (0..threads.min(MAX_THREADS)).map(task_name).collect();
+    for &task in &tasks {
+        cpu.arrive(task, 0, 0);
+    }
+
+    let handles: Vec<_> = tasks
+        .iter()
+        .map(|&task| {
+            let (cpu, counter, timeline) = (Arc::clone(&cpu), Arc::clone(&counter), Arc::clone(&timeline));
+            thread::spawn(move || {
+                let log = |line: String| {
+                    if keep_timeline {
+                        timeline.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(line);
+                    }
+                };
+                for _ in 0..size {
+                    // Another task may run between these two steps
+                    let seen = cpu.tick_with(task, || {
+                        let value = counter.load(Ordering::SeqCst);
+                        log(format!("{} reads {}", task, value));
+                        value
+                    });
+                    cpu.tick_with(task, || {
+                        counter.store(seen + 1, Ordering::SeqCst);
+                        log(format!("{} writes {}", task, seen + 1));
+                    });
+                }
+                cpu.finish(task);
+            })
+        })
+        .collect();
+    for handle in handles {
+        let _ = handle.join();
+    }
+
+    let timeline = std::mem::take(&mut *timeline.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+    Run {
+        trace: cpu.trace(),
+        timeline,
+        expected: tasks.len() * size,
+        counted: counter.load(Ordering::SeqCst),
+    }
+}
+
+pub fn save(schedule: &Schedule, path: &Path) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(schedule).map_err(|error| error.to_string())?;
+    fs::write(path, json + "\n").map_err(|error| format!("cannot write {}: {}", path.display(), error))
+}
+
+fn load(path: &Path) -> Result<Schedule, String> {
+    let text = fs::read_to_string(path).map_err(|error| format!("cannot read {}: {}", path.display(), error))?;
+    let schedule: Schedule =
+        serde_json::from_str(&text).map_err(|error| format!("invalid schedule {}: {}", path.display(), error))?;
+    if schedule.workload != "lost_update" {
+        return Err(format!("unknown simulated workload '{}'", schedule.workload));
+    }
+    if schedule.threads == 0 || schedule.threads > MAX_THREADS {
+        return Err(format!("threads must be between 1 and {}", MAX_THREADS));
+    }
+    Ok(schedule)
+}
+
+// Run a saved schedule again, step by step if it is short.
+// Returns the exit code: 0 if the replay followed the recording exactly.
+pub fn replay_schedule(path: &Path) -> i32 {
+    let schedule = match load(path) {
+        Ok(schedule) => schedule,
+        Err(error) => {
+            eprintln!("{}", error);
+            return 2;
+        }
+    };
+
+    event_log::demo_started(&schedule.workload);
+    println!("{}", narration!("interleaving.heading", workload = schedule.workload, threads = schedule.threads,
+                              size = schedule.size, steps = schedule.trace.len()));
+    let run = lost_update(schedule.threads, schedule.size, Policy::Scripted(schedule.trace.chars().collect()));
+
+    if run.timeline.is_empty() {
+        println!("{}", narration!("interleaving.schedule_truncated", trace = &run.trace[..run.trace.len().min(TIMELINE_LIMIT)]));
+    } else {
+        println!("{}", narration!("interleaving.schedule", trace = run.trace));
+        for (step, line) in run.timeline.iter().enumerate() {
+            println!("  {:>3}  {}", step + 1, line);
+        }
+    }
+
+    event_log::check_invariant("no lost updates", run.counted == run.expected);
+    println!("{}", narration!("interleaving.counted", counted = run.counted, expected = run.expected,
+                              lost = run.expected - run.counted));
+
+    if event_log::check_invariant("replay follows the recorded schedule", run.trace == schedule.trace) {
+        println!("{}", narration!("interleaving.followed"));
+        0
+    } else {
+        println!("{}", narration!("interleaving.diverged"));
+        1
+    }
+}
@@ -0,0 +1,159 @@
+/*!
+ * Interrupts - handlers, masking and critical sections, simulated
+ *
+ * On a single-core microcontroller there are no threads to race, but
+ * there is still concurrency: an interrupt handler can run between any
+ * two instructions of the main loop, and finishes before main goes on.
+ * The tools differ from the thread-safety ones:
+ *
+ *   critical section   main masks interrupts, does its update, unmasks.
+ *                      A handler raised meanwhile is held pending and
+ *                      runs as soon as the mask comes off
+ *   no locks           a handler must never wait for main: main cannot
+ *                      run again until the handler returns. Data flows
+ *                      through queues it pushes to without waiting
+ *                      (spsc.rs), or cells only touched with interrupts
+ *                      masked
+ *
+ * Interrupts simulates one interrupt line on a hosted OS, where the
+ * handler runs on a thread of its own - a timer thread standing in for
+ * the hardware tick. The exclusion a real core gets for free is rebuilt
+ * with two flags: main raises `masked` and then waits out a handler
+ * already running; a handler raises `in_handler` and then backs off if
+ * main has masked. Both sides store before they look (SeqCst), so at
+ * least one of them sees the other.
+ *
+ * Code inside a critical section or a handler gets a CriticalSection
+ * token, and CsCell - after cortex-m's interrupt::Mutex - lends out its
+ * contents only against one. The token cannot be made anywhere else or
+ * sent to another thread. CsCell keeps its value in a Mutex only to stay
+ * in safe Rust: with a token in hand the other side is not running, so
+ * the lock is taken with try_lock and never waited for. Failing to get
+ * it is a bug - a second borrow, or a second thread acting as main - and
+ * panics, as a RefCell borrowed twice does.
+ */
+
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, TryLockError};
+use std::thread;
+
+// Proof that interrupts are masked, or that this is the handler
+pub struct CriticalSection {
+    _not_send: PhantomData<*const ()>,
+}
+
+impl CriticalSection {
+    fn new() -> Self {
+        CriticalSection { _not_send: PhantomData }
+    }
+}
+
+thread_local! {
+    static IN_HANDLER: Cell<bool> = const { Cell::new(false) };
+}
+
+// One interrupt line and the main loop's mask over it
+#[derive(Debug, Default)]
+pub struct Interrupts {
+    masked: AtomicUsize,     // critical sections main is in; they nest
+    in_handler: AtomicBool,  // a handler is running, or about to
+    raised: AtomicUsize,
+    deferred: AtomicUsize,   // raised while masked, and run once unmasked
+}
+
+impl Interrupts {
+    pub fn new() -> Self {
+        Interrupts::default()
+    }
+
+    // Run `body` with interrupts masked: no handler runs until it returns.
+    // Inside a handler interrupts are masked already, and `body` just runs
+    pub fn free<R>(&self, body: impl FnOnce(&CriticalSection) -> R) -> R {
+        if IN_HANDLER.with(Cell::get) {
+            return body(&CriticalSection::new());
+        }
+        self.masked.fetch_add(1, Ordering::SeqCst);
+        let _unmask = Unmask(self);
+        while self.in_handler.load(Ordering::SeqCst) {
+            thread::yield_now();  // a handler that started first finishes first
+        }
+        body(&CriticalSection::new())
+    }
+
+    // The hardware raising the line: run `handler` now, or as soon as
+    // main unmasks. Handlers never nest; a second one raised meanwhile
+    // waits its turn
+    pub fn raise<R>(&self, handler: impl FnOnce(&CriticalSection) -> R) -> R {
+        self.raised.fetch_add(1, Ordering::Relaxed);
+        let mut counted = false;
+        loop {
+            if self.in_handler.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_ok() {
+                if self.masked.load(Ordering::SeqCst) == 0 {
+                    break;
+                }
+                self.in_handler.store(false, Ordering::SeqCst);  // masked: pending until main is done
+                if !std::mem::replace(&mut counted, true) {
+                    self.deferred.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            thread::yield_now();
+        }
+        IN_HANDLER.with(|flag| flag.set(true));
+        let _returned = Return(self);
+        handler(&CriticalSection::new())
+    }
+
+    pub fn raised(&self) -> usize {
+        self.raised.load(Ordering::Relaxed)
+    }
+
+    // Handlers that had to wait for a critical section to end
+    pub fn deferred(&self) -> usize {
+        self.deferred.load(Ordering::Relaxed)
+    }
+}
+
+// Ends a critical section, even one whose body panicked, so the handler
+// is not held off for good
+struct Unmask<'a>(&'a Interrupts);
+
+impl Drop for Unmask<'_> {
+    fn drop(&mut self) {
+        self.0.masked.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Returns from a handler, even one that panicked
+struct Return<'a>(&'a Interrupts);
+
+impl Drop for Return<'_> {
+    fn drop(&mut self) {
+        IN_HANDLER.with(|flag| flag.set(false));
+        self.0.in_handler.store(false, Ordering::SeqCst);
+    }
+}
+
+// Data shared between main and a handler, reachable only in a critical
+// section
+#[derive(Debug, Default)]
+pub struct CsCell<T> {
+    value: Mutex<T>,
+}
+
+impl<T> CsCell<T> {
+    pub fn new(value: T) -> Self {
+        CsCell { value: Mutex::new(value) }
+    }
+
+    // A handler that panicked mid-update left the value as it stood; the
+    // demos only count, so it is still worth reading
+    pub fn borrow<'cs>(&'cs self, _cs: &'cs CriticalSection) -> MutexGuard<'cs, T> {
+        match self.value.try_lock() {
+            Ok(value) => value,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => panic!("CsCell borrowed twice, or by two threads acting as main"),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+/*!
+ * Lazy One-Time Initialization
+ *
+ * A shared Resource that is expensive to build (a connection pool, a
+ * parsed configuration) should be built once, by whichever thread needs
+ * it first, and then shared. The obvious way is check-then-act: look
+ * under a lock, find nothing, build one, store it. Every thread that
+ * looks before the first build finishes finds nothing, so each builds its
+ * own; the stores then overwrite each other, and the threads go on with
+ * different instances.
+ *
+ * std does the check and the build as one step:
+ *
+ *   OnceLock   get_or_init(build): the first caller builds, every caller
+ *              that arrives meanwhile blocks until it is done, and all
+ *              get the same instance. If the build panics the cell stays
+ *              empty, so the next caller tries again
+ *   LazyLock   the build is given up front and runs on first deref, on
+ *              top of the same mechanism. A build that panics poisons it:
+ *              every later deref panics too
+ *
+ * A counter bumped inside the build function shows how many times each
+ * approach ran it.
+ */
+
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use resilient_core::Resource;
+
+use crate::spawn_policy;
+
+pub const THREADS: usize = 8;
+const BUILD: Duration = Duration::from_millis(50);  // long enough for every thread to arrive meanwhile
+
+// How one approach fared with THREADS threads asking at once
+#[derive(Debug)]
+pub struct Race {
+    pub builds: usize,       // times the build function ran
+    pub instances: usize,    // distinct Resources the threads ended up using
+}
+
+// Slow to build, and counted
+fn build(builds: &AtomicUsize) -> Resource {
+    builds.fetch_add(1, Ordering::SeqCst);
+    thread::sleep(BUILD);
+    Resource::new(1, "Connection pool").expect("a positive id and a name")
+}
+
+// Which instance a thread was handed
+fn address(resource: &Resource) -> usize {
+    ptr::from_ref(resource) as usize
+}
+
+// THREADS threads each ask `get` for the Resource's address; how many
+// builds that took, and how many different instances they were handed.
+// The threads all start while the first build is still sleeping
+fn race(builds: &AtomicUsize, get: impl Fn() -> usize + Sync) -> Race {
+    let used: Vec<usize> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..THREADS).map(|_| spawn_policy::spawn_scoped(scope, &get)).collect();
+        workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+    });
+    let instances = used.iter().enumerate().filter(|&(i, address)| !used[..i].contains(address)).count();
+    Race { builds: builds.load(Ordering::SeqCst), instances }
+}
+
+// Look, then build and store if nothing was there: the lock is not held
+// across the build, so nothing stops the others from looking meanwhile
+pub fn check_then_act() -> Race {
+    let builds = AtomicUsize::new(0);
+    let slot: Mutex<Option<Arc<Resource>>> = Mutex::new(None);
+    // Instances stay alive until every thread is done, so no two share an address
+    let handed_out = Mutex::new(Vec::new());
+    race(&builds, || {
+        let existing = slot.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        let resource = existing.unwrap_or_else(|| {
+            let built = Arc::new(build(&builds));
+            *slot.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(Arc::clone(&built));
+            built
+        });
+        let used = address(&resource);
+        handed_out.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(resource);
+        used
+    })
+}
+
+pub fn once_lock() -> Race {
+    let builds = AtomicUsize::new(0);
+    let cell = OnceLock::new();
+    race(&builds, || address(cell.get_or_init(|| build(&builds))))
+}
+
+pub fn lazy_lock() -> Race {
+    let builds = AtomicUsize::new(0);
+    let pool = LazyLock::new(|| build(&builds));
+    race(&builds, || address(&pool))
+}
+
+// The first build panics; whether the OnceLock is still empty after it,
+// and whether a second get_or_init then builds
+pub fn once_lock_retry() -> (bool, bool) {
+    let builds = AtomicUsize::new(0);
+    let cell = OnceLock::new();
+    // The section reports the panic itself, in place of the usual report
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let failed = panic::catch_unwind(AssertUnwindSafe(|| {
+        cell.get_or_init(|| -> Resource { panic!("connection refused") });
+    }));
+    panic::set_hook(previous_hook);
+    let empty = failed.is_err() && cell.get().is_none();
+    cell.get_or_init(|| build(&builds));
+    (empty, builds.load(Ordering::SeqCst) == 1)
+}
@@ -0,0 +1,151 @@
+/*!
+ * Lecture Mode
+ *
+ * Pauses after every demonstration section so the instructor can talk
+ * through the output, then continues, re-runs the section, steps back,
+ * or jumps straight to a named section - instead of scrolling back
+ * through one long wall of output.
+ */
+
+use std::io::{self, BufRead, Write};
+
+use crate::env_config::EnvConfig;
+use crate::sections::{self, SECTIONS};
+
+enum Step {
+    Next,
+    Rerun,
+    Back,
+    Jump(usize),
+    List,
+    Quit,
+    Invalid(String),
+}
+
+// Find a section by exact name or unique prefix
+pub fn find_section(name: &str) -> Result<usize, String> {
+    if let Some(index) = SECTIONS.iter().position(|section| section.name == name) {
+        return Ok(index);
+    }
+
+    let matches: Vec<usize> = SECTIONS
+        .iter()
+        .enumerate()
+        .filter(|(_, section)| section.name.starts_with(name))
+        .map(|(index, _)| index)
+        .collect();
+
+    match matches.as_slice() {
+        [index] => Ok(*index),
+        [] => Err(narration!("lecture.no_such_section", name)),
+        _ => Err(narration!("lecture.ambiguous_section", name)),
+    }
+}
+
+fn parse_step(line: &str) -> Step {
+    let line = line.trim();
+    let (command, argument) = match line.split_once(' ') {
+        Some((command, argument)) => (command, argument.trim()),
+        None => (line, ""),
+    };
+
+    match command {
+        "" | "n" | "next" => Step::Next,
+        "r" | "rerun" => Step::Rerun,
+        "b" | "back" => Step::Back,
+        "l" | "list" => Step::List,
+        "q" | "quit" => Step::Quit,
+        "j" | "jump" if argument.is_empty() => Step::Invalid(narration!("lecture.jump_needs_name")),
+        "j" | "jump" => match find_section(argument) {
+            Ok(index) => Step::Jump(index),
+            Err(reason) => Step::Invalid(reason),
+        },
+        // A bare section name is a jump too
+        name => match find_section(name) {
+            Ok(index) => Step::Jump(index),
+            Err(reason) => Step::Invalid(reason),
+        },
+    }
+}
+
+fn list_sections(current: usize) {
+    for (index, section) in SECTIONS.iter().enumerate() {
+        let marker = if index == current { ">" } else { " " };
+        println!("  {} {}. {} ({})", marker, index + 1, section.name, section.module);
+        println!("         {}", section.description);
+        for invariant in section.invariants {
+            println!("{}", narration!("lecture.checks", invariant));
+        }
+    }
+}
+
+// Run every section with a pause after each; `start` picks the first one
+pub fn run_lecture(input: &mut impl BufRead, config: &EnvConfig, start: usize) {
+    let mut current = start;
+
+    narrate!("lecture.heading");
+
+    'sections: while current < SECTIONS.len() {
+        let section = SECTIONS[current];
+        let _ = sections::run_section(&section, config);  // a panic is said, and the lecture goes on
+
+        loop {
+            print!("{}", narration!("lecture.prompt", current = current + 1, sections = SECTIONS.len(), name = section.name));
+            let _ = io::stdout().flush();
+
+            let mut line = String::new();
+            match input.read_line(&mut line) {
+                // Input closed - finish the remaining sections without pausing
+                Ok(0) | Err(_) => {
+                    for section in &SECTIONS[current + 1..] {
+                        let _ = sections::run_section(section, config);
+                    }
+                    break 'sections;
+                }
+                Ok(_) => {}
+            }
+
+            match parse_step(&line) {
+                Step::Next => current += 1,
+                Step::Rerun => {}
+                Step::Back => current = current.saturating_sub(1),
+                Step::Jump(index) => current = index,
+                Step::List => {
+                    list_sections(current);
+                    continue;
+                }
+                Step::Quit => return,
+                Step::Invalid(reason) => {
+                    println!("{}", reason);
+                    continue;
+                }
+            }
+            continue 'sections;
+        }
+    }
+
+    sections::print_summary();
+}
+
+// `--lecture [section]` - returns the exit code
+pub fn lecture_command(args: &[String], config: &EnvConfig) -> i32 {
+    let start = match args {
+        [] => 0,
+        [name] => match find_section(name) {
+            Ok(index) => index,
+            Err(reason) => {
+                eprintln!("{}", reason);
+                list_sections(usize::MAX);
+                return 2;
+            }
+        },
+        _ => {
+            eprintln!("{}", narration!("lecture.usage"));
+            return 2;
+        }
+    };
+
+    let stdin = io::stdin();
+    run_lecture(&mut stdin.lock(), config, start);
+    0
+}
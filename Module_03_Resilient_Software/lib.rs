@@ -0,0 +1,95 @@
+/*!
+ * Shared Demo Infrastructure
+ *
+ * The modules every demo binary runs on: output, configuration, the
+ * message catalog, clocks and units, and the subsystems the demos
+ * exercise. thread_safe, option_safe, memory_safe and buffer_safe use
+ * them from here rather than compiling their own copies, and the tests
+ * under tests/ reach them the same way. The safety primitives students
+ * reuse live one level down, in resilient_core.
+ */
+
+#[macro_use]
+pub mod event_log;
+pub mod aimd;
+pub mod alloc_track;
+pub mod async_demo;
+pub mod async_lock;
+pub mod atomic64;
+pub mod atomic_store;
+pub mod auto_traits;
+pub mod batching;
+pub mod bloom_filter;
+pub mod bounded_buffer;
+pub mod budget;
+pub mod circuit_breaker;
+pub mod clock;
+pub mod console;
+pub mod cost_model;
+pub mod crash_only;
+pub mod crash_report;
+pub mod crdt;
+pub mod data_layout;
+pub mod delivery;
+pub mod demo_report;
+pub mod env_config;
+pub mod error_sink;
+pub mod exhaustion;
+pub mod experiment;
+pub mod extensions;
+pub mod fault;
+pub mod flake_hunt;
+pub mod golden;
+pub mod grade;
+pub mod hazard;
+pub mod hedge;
+pub mod histogram;
+pub mod interleaving;
+pub mod interrupts;
+pub mod lazy_init;
+pub mod lecture;
+pub mod lock_order;
+pub mod lockfree;
+pub mod messages;
+pub mod middleware;
+pub mod network_sim;
+pub mod orderings;
+pub mod panic_strategy;
+pub mod persistent_list;
+pub mod phase;
+pub mod platform;
+#[cfg(all(feature = "plugins", target_os = "linux"))]
+pub mod plugin_abi;
+#[cfg(all(feature = "plugins", target_os = "linux"))]
+pub mod plugins;
+pub mod progress;
+pub mod quiz;
+pub mod resource_cache;
+pub mod resource_manager;
+pub mod rng;
+pub mod sandbox;
+pub mod scenario;
+pub mod scheduler;
+pub mod sections;
+pub mod selftest;
+pub mod setting_flags;
+pub mod sim_cpu;
+pub mod slab;
+pub mod snapshot_diff;
+pub mod soak;
+pub mod spawn_policy;
+pub mod speculative;
+pub mod spsc;
+pub mod standalone;
+pub mod static_check;
+pub mod step;
+pub mod stress;
+pub mod task_scope;
+pub mod term;
+pub mod trace;
+pub mod tracked_arc;
+pub mod units;
+pub mod vector_clock;
+pub mod verify;
+pub mod versioned_store;
+pub mod work_stealing;
@@ -0,0 +1,213 @@
+/*!
+ * Lock-Order Detection
+ *
+ * Two threads that take the same two locks in opposite orders can each
+ * end up holding one and waiting forever for the other. Whether they do
+ * depends on timing, so a test run can pass a thousand times and the
+ * lecture demo hang on the first try.
+ *
+ * A deadlock needs a cycle in the order locks are taken, and that order
+ * can be checked without the unlucky timing. Every TrackedMutex belongs
+ * to a LockOrder, which records an edge A -> B whenever a thread holding
+ * A goes for B. An edge that closes a cycle - B -> ... -> A was seen
+ * before, by any thread, at any time - is a lock-order inversion:
+ * reported when the second order is first used, even if the two orders
+ * never overlapped and nothing hung. Taking a lock the thread already
+ * holds is the shortest cycle of all.
+ *
+ * The fix the reports point to is one global order: every thread takes
+ * the locks it needs in the same sequence, and no cycle can form.
+ */
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+// A cycle in the lock order: each lock was held while taking the next,
+// and the last one while taking the first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inversion {
+    pub cycle: Vec<&'static str>,  // starts at the lock created first
+}
+
+impl fmt::Display for Inversion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for name in &self.cycle {
+            write!(f, "{} -> ", name)?;
+        }
+        write!(f, "{}", self.cycle.first().copied().unwrap_or_default())
+    }
+}
+
+#[derive(Debug, Default)]
+struct OrderState {
+    names: Vec<&'static str>,                // by lock id
+    after: BTreeMap<usize, Vec<usize>>,      // edges: lock -> locks taken while holding it
+    held: HashMap<ThreadId, Vec<usize>>,     // per thread, in the order taken
+    inversions: Vec<Inversion>,
+}
+
+impl OrderState {
+    // A path of edges from `from` to `to`, if there is one
+    fn path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let mut stack = vec![vec![from]];
+        let mut seen = vec![false; self.names.len()];
+        while let Some(path) = stack.pop() {
+            let last = *path.last()?;
+            if last == to {
+                return Some(path);
+            }
+            if std::mem::replace(&mut seen[last], true) {
+                continue;
+            }
+            for &next in self.after.get(&last).into_iter().flatten() {
+                stack.push([path.as_slice(), &[next]].concat());
+            }
+        }
+        None
+    }
+
+    // The current thread holds `held` and goes for `lock`
+    fn edge(&mut self, held: usize, lock: usize) {
+        if self.after.get(&held).is_some_and(|after| after.contains(&lock)) {
+            return;  // seen before: reported then, if it closed a cycle
+        }
+        let cycle = if held == lock { Some(vec![lock]) } else { self.path(lock, held) };
+        self.after.entry(held).or_default().push(lock);
+        if let Some(mut cycle) = cycle {
+            // The same cycle reads the same whichever edge closed it
+            let first = (0..cycle.len()).min_by_key(|&index| cycle[index]).unwrap_or(0);
+            cycle.rotate_left(first);
+            self.inversions.push(Inversion { cycle: cycle.into_iter().map(|id| self.names[id]).collect() });
+        }
+    }
+}
+
+// The lock order seen by a set of TrackedMutexes
+#[derive(Debug, Default)]
+pub struct LockOrder {
+    state: Mutex<OrderState>,
+}
+
+impl LockOrder {
+    pub fn new() -> Arc<Self> {
+        Arc::new(LockOrder::default())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, OrderState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn register(&self, name: &'static str) -> usize {
+        let mut state = self.lock();
+        state.names.push(name);
+        state.names.len() - 1
+    }
+
+    // Before waiting for `lock`: an edge from every lock this thread holds
+    fn attempt(&self, lock: usize) {
+        let mut state = self.lock();
+        let held = state.held.get(&thread::current().id()).cloned().unwrap_or_default();
+        for held in held {
+            state.edge(held, lock);
+        }
+    }
+
+    fn acquired(&self, lock: usize) {
+        self.lock().held.entry(thread::current().id()).or_default().push(lock);
+    }
+
+    // Guards may be dropped in any order
+    fn released(&self, lock: usize) {
+        let mut state = self.lock();
+        let id = thread::current().id();
+        if let Some(held) = state.held.get_mut(&id) {
+            if let Some(index) = held.iter().rposition(|&taken| taken == lock) {
+                held.remove(index);
+            }
+            if held.is_empty() {
+                state.held.remove(&id);
+            }
+        }
+    }
+
+    // Every cycle found so far, in the order they were found
+    pub fn inversions(&self) -> Vec<Inversion> {
+        self.lock().inversions.clone()
+    }
+}
+
+// A Mutex whose acquisitions are recorded in a LockOrder
+#[derive(Debug)]
+pub struct TrackedMutex<T> {
+    name: &'static str,
+    id: usize,
+    order: Arc<LockOrder>,
+    inner: Mutex<T>,
+}
+
+impl<T> TrackedMutex<T> {
+    pub fn new(name: &'static str, value: T, order: &Arc<LockOrder>) -> Self {
+        TrackedMutex { name, id: order.register(name), order: Arc::clone(order), inner: Mutex::new(value) }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    // Blocks like Mutex::lock. The order is recorded first, so an
+    // inversion is reported even if this call never returns
+    pub fn lock(&self) -> TrackedGuard<'_, T> {
+        self.order.attempt(self.id);
+        let guard = self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        self.order.acquired(self.id);
+        TrackedGuard { guard, mutex: self }
+    }
+
+    // Gives up after `timeout`: a deadlock broken by the clock
+    pub fn try_lock_for(&self, timeout: Duration) -> Option<TrackedGuard<'_, T>> {
+        self.order.attempt(self.id);
+        let deadline = Instant::now() + timeout;
+        loop {
+            let guard = match self.inner.try_lock() {
+                Ok(guard) => guard,
+                Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                    thread::sleep(Duration::from_millis(1));
+                    continue;
+                }
+                Err(TryLockError::WouldBlock) => return None,
+            };
+            self.order.acquired(self.id);
+            return Some(TrackedGuard { guard, mutex: self });
+        }
+    }
+}
+
+pub struct TrackedGuard<'a, T> {
+    guard: MutexGuard<'a, T>,
+    mutex: &'a TrackedMutex<T>,
+}
+
+impl<T> Deref for TrackedGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for TrackedGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for TrackedGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.order.released(self.mutex.id);
+    }
+}
@@ -0,0 +1,324 @@
+/*!
+ * Lock-Free Bounded Queue
+ *
+ * The atomics demos stop at single counters and cells. LockFreeQueue is a
+ * whole data structure without a lock: a bounded multi-producer,
+ * multi-consumer FIFO after Dmitry Vyukov's bounded MPMC queue. Each slot
+ * of a fixed ring carries a sequence number that says whose turn it is:
+ *
+ *   sequence == position        free, for the producer at `position`
+ *   sequence == position + 1    full, for the consumer at `position`
+ *
+ * A producer claims the next position with a compare-and-swap on `tail`,
+ * writes its value, and only then publishes it by storing position + 1
+ * (Release). A consumer claims a position on `head` the same way, reads
+ * the value the sequence says is there (Acquire), and hands the slot to
+ * the next lap's producer with position + capacity. Two threads after
+ * the same position race on the CAS and the loser moves on to the next
+ * one, so some thread always makes progress.
+ *
+ * As in AtomicStore, values stay in safe Rust by packing into an
+ * AtomicU64 (PackedValue): the queue holds numbers and small Copy
+ * structs. Positions and sequences are u64 too, not usize: a 32-bit
+ * usize would wrap after four billion claims, and at the wrap a ring
+ * whose size is not a power of two would jump to the wrong slot. A
+ * target without 64-bit atomics gets AtomicU64's locked fallback, so the
+ * queue still works there but is no longer lock-free.
+ *
+ * LockedQueue is the baseline the demo races it against, a
+ * VecDeque behind a Mutex with the same bound and the same API.
+ */
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::marker::PhantomData;
+use std::panic;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::atomic64::{self, AtomicU64};
+use crate::atomic_store::PackedValue;
+use crate::spawn_policy::{self, SpawnError, Worker};
+
+// A bounded FIFO that any number of threads push to and pop from
+pub trait ConcurrentQueue<T>: Send + Sync {
+    // Err(value) when the queue is full
+    fn push(&self, value: T) -> Result<(), T>;
+    fn pop(&self) -> Option<T>;
+}
+
+// Keeps head and tail on separate cache lines, so producers and consumers
+// do not steal each other's line on every claim
+#[repr(align(64))]
+struct CachePadded(AtomicU64);
+
+struct Slot {
+    sequence: AtomicU64,
+    value: AtomicU64,
+}
+
+pub struct LockFreeQueue<T: PackedValue> {
+    slots: Box<[Slot]>,
+    head: CachePadded,   // next position to pop
+    tail: CachePadded,   // next position to push
+    retries: AtomicU64,  // CAS races lost, by producers and consumers
+    _values: PhantomData<fn(T) -> T>,  // stored as bits: Send and Sync whatever T is
+}
+
+impl<T: PackedValue> LockFreeQueue<T> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "a queue needs room for at least one value");
+        let slots = (0..capacity)
+            .map(|position| Slot { sequence: AtomicU64::new(position as u64), value: AtomicU64::new(0) })
+            .collect();
+        LockFreeQueue {
+            slots,
+            head: CachePadded(AtomicU64::new(0)),
+            tail: CachePadded(AtomicU64::new(0)),
+            retries: AtomicU64::new(0),
+            _values: PhantomData,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    // Values in the queue: exact when no other thread is at work, a
+    // snapshot otherwise
+    pub fn len(&self) -> usize {
+        let head = self.head.0.load(Ordering::SeqCst);
+        let tail = self.tail.0.load(Ordering::SeqCst);
+        tail.saturating_sub(head).min(self.capacity() as u64) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Whether pushes and pops are lock-free on this target
+    pub fn is_lock_free(&self) -> bool {
+        atomic64::LOCK_FREE
+    }
+
+    // Claims lost to another thread so far: the contention a lock would
+    // have turned into waiting
+    pub fn retries(&self) -> u64 {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    fn slot(&self, position: u64) -> &Slot {
+        &self.slots[(position % self.slots.len() as u64) as usize]
+    }
+
+    // How far a slot's sequence is from the one that means "your turn"
+    fn turn(slot: &Slot, expected: u64) -> i64 {
+        (slot.sequence.load(Ordering::Acquire) as i64).wrapping_sub(expected as i64)
+    }
+}
+
+impl<T: PackedValue> ConcurrentQueue<T> for LockFreeQueue<T> {
+    fn push(&self, value: T) -> Result<(), T> {
+        let mut position = self.tail.0.load(Ordering::Relaxed);
+        loop {
+            let slot = self.slot(position);
+            match Self::turn(slot, position) {
+                0 => match self.tail.0.compare_exchange_weak(position, position + 1, Ordering::Relaxed, Ordering::Relaxed) {
+                    Ok(_) => {
+                        slot.value.store(value.to_bits(), Ordering::Relaxed);
+                        slot.sequence.store(position + 1, Ordering::Release);  // publishes the value
+                        return Ok(());
+                    }
+                    Err(current) => {
+                        self.retries.fetch_add(1, Ordering::Relaxed);
+                        position = current;
+                    }
+                },
+                // The slot still holds last lap's value: full
+                distance if distance < 0 => return Err(value),
+                // Another producer took this position first
+                _ => position = self.tail.0.load(Ordering::Relaxed),
+            }
+        }
+    }
+
+    fn pop(&self) -> Option<T> {
+        let mut position = self.head.0.load(Ordering::Relaxed);
+        loop {
+            let slot = self.slot(position);
+            match Self::turn(slot, position + 1) {
+                0 => match self.head.0.compare_exchange_weak(position, position + 1, Ordering::Relaxed, Ordering::Relaxed) {
+                    Ok(_) => {
+                        let value = T::from_bits(slot.value.load(Ordering::Relaxed));
+                        slot.sequence.store(position + self.capacity() as u64, Ordering::Release);  // free for the next lap
+                        return Some(value);
+                    }
+                    Err(current) => {
+                        self.retries.fetch_add(1, Ordering::Relaxed);
+                        position = current;
+                    }
+                },
+                // No producer has published this position yet: empty
+                distance if distance < 0 => return None,
+                // Another consumer took this position first
+                _ => position = self.head.0.load(Ordering::Relaxed),
+            }
+        }
+    }
+}
+
+impl<T: PackedValue> fmt::Debug for LockFreeQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LockFreeQueue")
+            .field("capacity", &self.capacity())
+            .field("len", &self.len())
+            .field("retries", &self.retries())
+            .finish()
+    }
+}
+
+// The baseline: one lock around a VecDeque
+#[derive(Debug)]
+pub struct LockedQueue<T> {
+    items: Mutex<VecDeque<T>>,
+    capacity: usize,
+}
+
+impl<T> LockedQueue<T> {
+    pub fn new(capacity: usize) -> Self {
+        LockedQueue { items: Mutex::new(VecDeque::with_capacity(capacity)), capacity }
+    }
+}
+
+// A push or pop either happens whole or not at all, so a poisoned lock
+// still guards a consistent VecDeque
+impl<T: Send> ConcurrentQueue<T> for LockedQueue<T> {
+    fn push(&self, value: T) -> Result<(), T> {
+        let mut items = self.items.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if items.len() >= self.capacity {
+            return Err(value);
+        }
+        items.push_back(value);
+        Ok(())
+    }
+
+    fn pop(&self) -> Option<T> {
+        self.items.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).pop_front()
+    }
+}
+
+// How one producer/consumer run went
+#[derive(Debug)]
+pub struct Workload {
+    pub elapsed: Duration,
+    pub popped: Vec<Vec<u64>>,  // per consumer, in the order it popped them
+    pub full: u64,              // pushes refused by a full queue and tried again
+    items: usize,               // per producer
+    producers: usize,
+}
+
+impl Workload {
+    // Every item pushed was popped, and only once
+    pub fn exactly_once(&self) -> bool {
+        let mut popped: Vec<u64> = self.popped.iter().flatten().copied().collect();
+        popped.sort_unstable();
+        popped.len() == self.producers * self.items && popped.iter().enumerate().all(|(i, &value)| value == i as u64)
+    }
+
+    // Each consumer got every producer's items in the order they were
+    // pushed: what FIFO promises when several threads share the queue
+    pub fn in_order(&self) -> bool {
+        self.popped.iter().all(|popped| {
+            let mut last = BTreeMap::new();  // producer -> its last item this consumer saw
+            popped.iter().all(|&value| {
+                let producer = value / self.items as u64;
+                last.insert(producer, value).is_none_or(|previous| previous < value)
+            })
+        })
+    }
+
+    pub fn items_per_ms(&self) -> f64 {
+        (self.producers * self.items) as f64 / (self.elapsed.as_secs_f64() * 1000.0).max(f64::EPSILON)
+    }
+}
+
+fn join<T>(worker: Worker<'static, T>) -> T {
+    worker.join().unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
+
+// `producers` threads each push `items` numbers (producer p pushes
+// p * items up to (p + 1) * items - 1) while `consumers` threads pop
+// until every one is out. A push to a full queue or a pop from an empty
+// one yields and tries again, so every thread has to run alongside the
+// others
+pub fn run<Q: ConcurrentQueue<u64> + 'static>(
+    queue: Arc<Q>,
+    producers: usize,
+    consumers: usize,
+    items: usize,
+) -> Result<Workload, SpawnError> {
+    let total = producers * items;
+    let taken = Arc::new(AtomicUsize::new(0));
+    let full = Arc::new(AtomicU64::new(0));
+    let abandon = Arc::new(AtomicBool::new(false));  // a thread could not be started
+    let start = Instant::now();
+
+    let (mut consumer_workers, mut producer_workers) = (Vec::new(), Vec::new());
+    let mut started = Ok(());
+    for _ in 0..consumers {
+        let (queue, taken, abandon) = (Arc::clone(&queue), Arc::clone(&taken), Arc::clone(&abandon));
+        let worker = spawn_policy::spawn_required(move || {
+            let mut popped = Vec::new();
+            while taken.load(Ordering::Relaxed) < total && !abandon.load(Ordering::Relaxed) {
+                match queue.pop() {
+                    Some(value) => {
+                        taken.fetch_add(1, Ordering::Relaxed);
+                        popped.push(value);
+                    }
+                    None => thread::yield_now(),
+                }
+            }
+            popped
+        });
+        match worker {
+            Ok(worker) => consumer_workers.push(worker),
+            Err(error) => {
+                started = Err(error);
+                break;
+            }
+        }
+    }
+    for producer in 0..producers {
+        if started.is_err() {
+            break;
+        }
+        let (queue, full, abandon) = (Arc::clone(&queue), Arc::clone(&full), Arc::clone(&abandon));
+        let worker = spawn_policy::spawn_required(move || {
+            for i in 0..items {
+                let mut value = (producer * items + i) as u64;
+                while let Err(refused) = queue.push(value) {
+                    if abandon.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    full.fetch_add(1, Ordering::Relaxed);
+                    value = refused;
+                    thread::yield_now();
+                }
+            }
+        });
+        match worker {
+            Ok(worker) => producer_workers.push(worker),
+            Err(error) => started = Err(error),
+        }
+    }
+
+    if started.is_err() {
+        abandon.store(true, Ordering::Relaxed);
+    }
+    producer_workers.into_iter().for_each(join);
+    let popped = consumer_workers.into_iter().map(join).collect();
+    started?;
+    Ok(Workload { elapsed: start.elapsed(), popped, full: full.load(Ordering::Relaxed), items, producers })
+}
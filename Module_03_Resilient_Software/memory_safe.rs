@@ -1,4 +1,4 @@
-/**
+/*!
  * Rust Memory Safety Example - TYPE SAFE
  * 
  * This program demonstrates how Rust's ownership system prevents
@@ -6,31 +6,9 @@
  * These safety guarantees come with zero runtime overhead.
  */
 
-#[derive(Debug)]
-struct DataHolder {
-    value: i32,
-    name: String,
-}
-
-impl DataHolder {
-    fn new(value: i32, name: &str) -> Self {
-        println!("Created DataHolder: {} = {}", name, value);
-        DataHolder {
-            value,
-            name: name.to_string(),
-        }
-    }
-    
-    fn print(&self) {
-        println!("DataHolder {} has value: {}", self.name, self.value);
-    }
-}
-
-impl Drop for DataHolder {
-    fn drop(&mut self) {
-        println!("Destroyed DataHolder: {}", self.name);
-    }
-}
+use resilient_core::DataHolder;
+use safety_demo::slab::Slab;
+use safety_demo::{narrate, narration, standalone};
 
 fn demonstrate_ownership_safety() {
     let data = DataHolder::new(42, "safe");
@@ -43,7 +21,7 @@ fn demonstrate_ownership_safety() {
     // This would cause a COMPILE ERROR if uncommented:
     // data.print();  // Error: value borrowed here after move
     
-    println!("Ownership transferred safely - no use-after-free possible!");
+    narrate!("memory_safe.ownership_claim");
     
     // When moved_data goes out of scope, it's automatically cleaned up
 }
@@ -70,14 +48,14 @@ fn demonstrate_borrowing_safety() {
     // Now we can use mutable_data again
     mutable_data.print();
     
-    println!("Borrowing rules prevent data races and use-after-free!");
+    narrate!("memory_safe.borrowing_claim");
 }
 
 fn demonstrate_lifetime_safety() {
     let long_lived = DataHolder::new(789, "long_lived");
     
     let reference_to_long_lived = {
-        let short_lived = DataHolder::new(100, "short_lived");
+        let _short_lived = DataHolder::new(100, "short_lived");
         
         // This would cause COMPILE ERROR if we tried to return a reference to short_lived:
         // &short_lived  // Error: borrowed value does not live long enough
@@ -88,7 +66,7 @@ fn demonstrate_lifetime_safety() {
     // We can safely use the reference because the compiler verified lifetimes
     reference_to_long_lived.print();
     
-    println!("Lifetime analysis prevents dangling pointers!");
+    narrate!("memory_safe.lifetime_claim");
 }
 
 fn demonstrate_rc_safety() {
@@ -100,16 +78,16 @@ fn demonstrate_rc_safety() {
         let another_ref = Rc::clone(&shared_data);
         another_ref.print();
         
-        println!("Reference count: {}", Rc::strong_count(&shared_data));
+        narrate!("memory_safe.reference_count", count = Rc::strong_count(&shared_data));
         
         // another_ref goes out of scope here, but data is still alive
     }
     
-    println!("Reference count: {}", Rc::strong_count(&shared_data));
+    narrate!("memory_safe.reference_count", count = Rc::strong_count(&shared_data));
     shared_data.print();
     
     // Data is automatically freed when last Rc goes out of scope
-    println!("Reference counting prevents premature deallocation!");
+    narrate!("memory_safe.rc_claim");
 }
 
 fn demonstrate_box_safety() {
@@ -124,7 +102,7 @@ fn demonstrate_box_safety() {
     // heap_data.print();  // Error: value borrowed here after move
     
     // No double-free possible - only one owner at a time
-    println!("Box ownership prevents double-free errors!");
+    narrate!("memory_safe.box_claim");
 }
 
 fn demonstrate_vector_safety() {
@@ -148,7 +126,53 @@ fn demonstrate_vector_safety() {
     // Now we can modify again
     vec.push(DataHolder::new(3, "third"));
     
-    println!("Borrow checker prevents iterator invalidation!");
+    narrate!("memory_safe.vector_claim");
+}
+
+fn demonstrate_slab_safety() {
+    let mut holders = Slab::new();
+    let first = holders.insert(DataHolder::new(1, "first"));
+    let second = holders.insert(DataHolder::new(2, "second"));
+    let third = holders.insert(DataHolder::new(3, "third"));
+    
+    // Removing from a Vec would shift "third" down to index 1; in a slab
+    // nothing moves, so every other key still names the same holder
+    if let Some(removed) = holders.remove(second) {
+        narrate!("memory_safe.slab_removed", name = removed.name, key = second);
+    }
+    if let Some(holder) = holders.get(third) {
+        holder.print();
+    }
+    
+    // This would cause COMPILE ERROR if we held a reference across a removal:
+    // let first_ref = holders.get(first).unwrap();
+    // holders.remove(third);  // Error: cannot borrow as mutable
+    // first_ref.print();
+    
+    // The freed slot is reused, but under a new generation
+    let fourth = holders.insert(DataHolder::new(4, "fourth"));
+    narrate!("memory_safe.slab_reused", new = fourth, old = second);
+    match holders.get(second) {
+        Some(holder) => narrate!("memory_safe.slab_stale_found", key = second, name = holder.name),
+        None => narrate!("memory_safe.slab_stale_caught", key = second),
+    }
+    
+    if let Some(holder) = holders.get_mut(first) {
+        holder.value *= 10;
+    }
+    for (_, holder) in holders.iter_mut() {
+        holder.value += 1;
+    }
+    // Drop every holder of 5 or less; the survivors keep their keys
+    holders.retain(|_, holder| holder.value > 5);
+    for (key, holder) in holders.iter() {
+        narrate!("memory_safe.slab_key", key);
+        holder.print();
+    }
+    narrate!("memory_safe.slab_state", holders = holders.len(), slots = holders.slots(),
+             first = holders.contains(first), third = holders.contains(third), empty = holders.is_empty());
+    
+    narrate!("memory_safe.slab_claim");
 }
 
 // Demonstrate that even unsafe code requires explicit acknowledgment
@@ -162,42 +186,34 @@ fn demonstrate_unsafe_blocks() {
         
         // Even in unsafe blocks, the compiler helps where possible
         // Raw pointer dereferencing requires explicit unsafe
-        println!("Unsafe access: {:?}", (*ptr).value);
+        narrate!("memory_safe.unsafe_access", value = ?(*ptr).value);
     }
     
     // The vast majority of Rust code doesn't need unsafe blocks
-    println!("Unsafe operations are explicit and isolated!");
+    narrate!("memory_safe.unsafe_claim");
 }
 
 fn main() {
-    println!("=== Rust Memory Safety Guarantees ===");
-    
-    println!("\n1. Ownership Safety:");
-    demonstrate_ownership_safety();
-    
-    println!("\n2. Borrowing Safety:");
-    demonstrate_borrowing_safety();
-    
-    println!("\n3. Lifetime Safety:");
-    demonstrate_lifetime_safety();
-    
-    println!("\n4. Reference Counting Safety:");
-    demonstrate_rc_safety();
-    
-    println!("\n5. Box Ownership Safety:");
-    demonstrate_box_safety();
-    
-    println!("\n6. Vector Safety:");
-    demonstrate_vector_safety();
-    
-    println!("\n7. Unsafe Blocks:");
-    demonstrate_unsafe_blocks();
-    
-    println!("\nKey Safety Guarantees:");
-    println!("- No use-after-free: Ownership prevents using moved values");
-    println!("- No double-free: Only one owner can free memory");
-    println!("- No dangling pointers: Lifetime analysis ensures references are valid");
-    println!("- No data races: Borrowing rules prevent concurrent access violations");
-    println!("- Zero overhead: All safety checks happen at compile time");
-    println!("- Explicit unsafe: Dangerous operations require explicit acknowledgment");
+    standalone::start();
+    narrate!("memory_safe.heading");
+    
+    standalone::demonstrate("ownership_safety", &narration!("memory_safe.ownership_safety_heading"),
+                            demonstrate_ownership_safety);
+    standalone::demonstrate("borrowing_safety", &narration!("memory_safe.borrowing_safety_heading"),
+                            demonstrate_borrowing_safety);
+    standalone::demonstrate("lifetime_safety", &narration!("memory_safe.lifetime_safety_heading"), demonstrate_lifetime_safety);
+    standalone::demonstrate("rc_safety", &narration!("memory_safe.rc_safety_heading"), demonstrate_rc_safety);
+    standalone::demonstrate("box_safety", &narration!("memory_safe.box_safety_heading"), demonstrate_box_safety);
+    standalone::demonstrate("vector_safety", &narration!("memory_safe.vector_safety_heading"), demonstrate_vector_safety);
+    standalone::demonstrate("slab_safety", &narration!("memory_safe.slab_safety_heading"), demonstrate_slab_safety);
+    standalone::demonstrate("unsafe_blocks", &narration!("memory_safe.unsafe_blocks_heading"), demonstrate_unsafe_blocks);
+    
+    narrate!("memory_safe.guarantees_heading");
+    narrate!("memory_safe.guarantee_use_after_free");
+    narrate!("memory_safe.guarantee_double_free");
+    narrate!("memory_safe.guarantee_dangling");
+    narrate!("memory_safe.guarantee_stale_handles");
+    narrate!("memory_safe.guarantee_data_races");
+    narrate!("memory_safe.guarantee_zero_cost");
+    narrate!("memory_safe.guarantee_explicit_unsafe");
 }
@@ -0,0 +1,370 @@
+/*!
+ * Message Catalog
+ *
+ * User-facing text - the framework's titles, report headings and verdicts,
+ * the summary, each command's usage, headings, tables and results, and
+ * every demo's narration - lives in templates under messages/<locale>.toml
+ * instead of in format strings, so a course section can translate it
+ * without forking the code. Error types' Display text, invariant names
+ * (the event log's keys for them), the crash-only child's protocol lines
+ * and the runner's HTML/Markdown report bodies stay in the code. en-US is
+ * compiled in and is the default; SAFETY_DEMO_LANG picks another catalog,
+ * read from SAFETY_DEMO_MESSAGES_DIR or the messages/ directory found
+ * beside the executable or above it.
+ *
+ * Each framework message is a variant of Message with typed parameters,
+ * and each parameter fills the `{name}` placeholder of the same name.
+ * Narration lines, a demo's or a command's, are keyed `<table>.<key>`,
+ * one table per source file, and the narrate!/narration! arguments fill
+ * their placeholders by name.
+ * A key a catalog does not translate falls back to en-US; tests/messages.rs
+ * checks every catalog and every narration call against en-US.
+ */
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const DEFAULT_LOCALE: &str = "en-US";
+
+const EN_US: &str = include_str!("messages/en-US.toml");
+
+// Declares Message, its keys, and the placeholders each key may use
+macro_rules! messages {
+    ($($(#[$attr:meta])* $variant:ident { $($field:ident: $ty:ty),* } = $key:literal;)*) => {
+        #[derive(Debug, Clone)]
+        pub enum Message<'a> {
+            $($(#[$attr])* $variant { $($field: $ty),* },)*
+        }
+
+        impl Message<'_> {
+            pub fn key(&self) -> &'static str {
+                match self {
+                    $(Message::$variant { .. } => $key,)*
+                }
+            }
+
+            fn args(&self) -> Vec<(&'static str, String)> {
+                match self {
+                    $(Message::$variant { $($field),* } => vec![$((stringify!($field), $field.to_string())),*],)*
+                }
+            }
+        }
+
+        // Every key, with the placeholders its template may use
+        pub const KEYS: &[(&str, &[&str])] = &[$(($key, &[$(stringify!($field)),*])),*];
+    };
+}
+
+messages! {
+    Title {} = "title";
+    ReportHeading { demo: &'a str, module: &'a str } = "report_heading";
+    VerdictHeld {} = "verdict_held";
+    VerdictViolated {} = "verdict_violated";
+    VerdictNotChecked {} = "verdict_not_checked";
+    ClaimFailed { claim: &'a str } = "claim_failed";
+    SummaryHeading {} = "summary_heading";
+    Summary {} = "summary";
+    #[cfg_attr(not(all(feature = "plugins", target_os = "linux")), allow(dead_code))]
+    PluginRejected { error: String } = "plugin_rejected";
+    #[cfg_attr(not(all(feature = "plugins", target_os = "linux")), allow(dead_code))]
+    PluginHeading { name: &'a str, summary: &'a str } = "plugin_heading";
+    #[cfg_attr(all(feature = "plugins", target_os = "linux"), allow(dead_code))]
+    PluginsIgnored { dir: String } = "plugins_ignored";
+    DemoPanicked { demo: &'a str, message: &'a str } = "demo_panicked";
+    AbortIsolation {} = "abort_isolation";
+    SkippedNeedsUnwind { demo: &'a str } = "skipped_needs_unwind";
+    Interrupted { done: usize, total: usize } = "interrupted";
+    VerifyHeading {} = "verify_heading";
+    VerifyFailed { demo: &'a str, invariant: &'a str, verdict: String } = "verify_failed";
+    VerifyPanicked { demo: &'a str, message: &'a str } = "verify_panicked";
+    VerifySkipped { demo: &'a str } = "verify_skipped";
+    VerifyPassed { demos: usize, invariants: usize } = "verify_passed";
+    VerifyNotPassed { failed: usize, demos: usize } = "verify_not_passed";
+    ConfigError { error: String } = "config_error";
+    EventLogError { path: String, error: String } = "event_log_error";
+    UnknownCommand { command: &'a str } = "unknown_command";
+    Commands {} = "commands";
+}
+
+#[derive(Debug)]
+pub struct Catalog {
+    pub locale: String,
+    templates: BTreeMap<String, String>,
+}
+
+impl Catalog {
+    // Top-level keys are the framework's messages; each [table] holds the
+    // narration of one demo file, keyed `<table>.<name>`
+    pub fn parse(locale: &str, text: &str) -> Result<Catalog, String> {
+        let table: toml::Table = toml::from_str(text).map_err(|error| format!("{} catalog: {}", locale, error))?;
+        let mut templates = BTreeMap::new();
+        flatten("", table, &mut templates).map_err(|key| format!("{} catalog: {} is not a string", locale, key))?;
+        Ok(Catalog { locale: locale.to_string(), templates })
+    }
+
+    pub fn en_us() -> Catalog {
+        Catalog::parse(DEFAULT_LOCALE, EN_US).expect("the built-in en-US catalog parses")
+    }
+
+    // The built-in en-US catalog, or <dir>/<locale>.toml
+    pub fn load(locale: &str, dir: Option<&Path>) -> Result<Catalog, String> {
+        if locale == DEFAULT_LOCALE {
+            return Ok(Catalog::en_us());
+        }
+        let Some(dir) = dir else {
+            return Err(format!("no message catalog for {} (no messages directory found; set SAFETY_DEMO_MESSAGES_DIR)", locale));
+        };
+        let path = dir.join(format!("{}.toml", locale));
+        let text = fs::read_to_string(&path)
+            .map_err(|error| format!("no message catalog for {} ({}: {})", locale, path.display(), error))?;
+        Catalog::parse(locale, &text)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.templates.keys().map(String::as_str)
+    }
+
+    // The placeholder names in a key's template
+    pub fn placeholders(&self, key: &str) -> Option<Vec<&str>> {
+        self.templates.get(key).map(|template| placeholders(template))
+    }
+
+    // Keys this catalog lacks, keys no message has, and placeholders a
+    // template uses but its message does not supply. Narration keys are
+    // checked against en-US, which the tests check against the code
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (key, params) in KEYS {
+            match self.templates.get(*key) {
+                None => problems.push(format!("missing {}", key)),
+                Some(template) => {
+                    for placeholder in placeholders(template) {
+                        if !params.contains(&placeholder) {
+                            problems.push(format!("{} uses unknown placeholder {{{}}}", key, placeholder));
+                        }
+                    }
+                }
+            }
+        }
+        let reference = Catalog::en_us();
+        for key in reference.keys().filter(|key| is_narration(key)) {
+            if !self.templates.contains_key(key) {
+                problems.push(format!("missing {}", key));
+            }
+        }
+        for (key, template) in &self.templates {
+            if is_narration(key) {
+                match reference.placeholders(key) {
+                    None => problems.push(format!("unknown key {}", key)),
+                    Some(params) => {
+                        for placeholder in placeholders(template) {
+                            if !params.contains(&placeholder) {
+                                problems.push(format!("{} uses unknown placeholder {{{}}}", key, placeholder));
+                            }
+                        }
+                    }
+                }
+            } else if !KEYS.iter().any(|(known, _)| known == key) {
+                problems.push(format!("unknown key {}", key));
+            }
+        }
+        problems
+    }
+
+    // None when this catalog has no template for the message
+    fn render(&self, message: &Message) -> Option<String> {
+        let args = message.args();
+        let args: Vec<(&str, Arg)> = args.iter().map(|(name, value)| (*name, Arg::Display(value))).collect();
+        self.narration(message.key(), &args)
+    }
+
+    // A key's template with its placeholders filled; None when this
+    // catalog has no template for it
+    pub fn narration(&self, key: &str, args: &[(&str, Arg)]) -> Option<String> {
+        self.templates.get(key).map(|template| fill(template, args))
+    }
+}
+
+fn flatten(prefix: &str, table: toml::Table, templates: &mut BTreeMap<String, String>) -> Result<(), String> {
+    for (name, value) in table {
+        let key = if prefix.is_empty() { name } else { format!("{}.{}", prefix, name) };
+        match value {
+            toml::Value::String(template) => {
+                templates.insert(key, template);
+            }
+            toml::Value::Table(table) => flatten(&key, table, templates)?,
+            _ => return Err(key),
+        }
+    }
+    Ok(())
+}
+
+fn is_narration(key: &str) -> bool {
+    key.contains('.')
+}
+
+// A value for a narration placeholder, shown with Display or with Debug
+pub enum Arg<'a> {
+    Display(&'a dyn fmt::Display),
+    Debug(&'a dyn fmt::Debug),
+}
+
+// How a `{name:spec}` placeholder lays out its value: [[fill]align]
+// [width][.precision], as in format!. Without an alignment the value
+// is left-aligned, strings and numbers alike
+struct Spec {
+    fill: char,
+    align: char,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Option<Spec> {
+        let mut chars: Vec<char> = spec.chars().collect();
+        let mut layout = Spec { fill: ' ', align: '<', width: 0, precision: None };
+        if chars.len() >= 2 && matches!(chars[1], '<' | '^' | '>') {
+            layout.fill = chars[0];
+            layout.align = chars[1];
+            chars.drain(..2);
+        } else if chars.first().is_some_and(|c| matches!(c, '<' | '^' | '>')) {
+            layout.align = chars.remove(0);
+        }
+        let rest: String = chars.into_iter().collect();
+        let (width, precision) = match rest.split_once('.') {
+            Some((width, precision)) => (width, Some(precision.parse().ok()?)),
+            None => (rest.as_str(), None),
+        };
+        if !width.is_empty() {
+            layout.width = width.parse().ok()?;
+        }
+        layout.precision = precision;
+        Some(layout)
+    }
+
+    fn apply(&self, arg: &Arg) -> String {
+        let text = match (arg, self.precision) {
+            (Arg::Display(value), None) => value.to_string(),
+            (Arg::Display(value), Some(precision)) => format!("{:.*}", precision, value),
+            (Arg::Debug(value), None) => format!("{:?}", value),
+            (Arg::Debug(value), Some(precision)) => format!("{:.*?}", precision, value),
+        };
+        let padding = self.width.saturating_sub(text.chars().count());
+        let (before, after) = match self.align {
+            '>' => (padding, 0),
+            '^' => (padding / 2, padding - padding / 2),
+            _ => (0, padding),
+        };
+        let fill = |count| std::iter::repeat_n(self.fill, count).collect::<String>();
+        format!("{}{}{}", fill(before), text, fill(after))
+    }
+}
+
+// A `{name}` or `{name:spec}` placeholder at the start of `text`: its
+// name, its spec and its length
+fn placeholder(text: &str) -> Option<(&str, &str, usize)> {
+    let inner = text[..text.find('}')?].strip_prefix('{')?;
+    let end = inner.len() + 1;
+    let (name, spec) = inner.split_once(':').unwrap_or((inner, ""));
+    let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && Spec::parse(spec).is_some();
+    valid.then_some((name, spec, end + 1))
+}
+
+// Fill a template's placeholders from `args`; `{{` and `}}` are braces,
+// and a placeholder with no argument of its name is left as it is
+fn fill(template: &str, args: &[(&str, Arg)]) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            text.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        let found = placeholder(rest).and_then(|(name, spec, length)| {
+            let (_, arg) = args.iter().find(|(arg_name, _)| *arg_name == name)?;
+            Some((Spec::parse(spec)?.apply(arg), length))
+        });
+        match found {
+            Some((value, length)) => {
+                text.push_str(&value);
+                rest = &rest[length..];
+            }
+            None => {
+                text.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+// The names of the placeholders in a template
+fn placeholders(template: &str) -> Vec<&str> {
+    let mut found = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        rest = &rest[start..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            rest = &rest[2..];
+        } else if let Some((name, _, length)) = placeholder(rest) {
+            found.push(name);
+            rest = &rest[length..];
+        } else {
+            rest = &rest[1..];
+        }
+    }
+    found
+}
+
+// Where catalogs other than en-US are: SAFETY_DEMO_MESSAGES_DIR, else
+// the first messages/ directory with an en-US.toml beside the executable
+// or above it (in a checkout, the package's own). None when neither
+// exists - only the compiled-in en-US is available then
+pub fn catalog_dir(configured: Option<&Path>) -> Option<PathBuf> {
+    if let Some(dir) = configured {
+        return Some(dir.to_path_buf());
+    }
+    let exe = std::env::current_exe().ok()?;
+    exe.ancestors()
+        .skip(1)
+        .map(|dir| dir.join("messages"))
+        .find(|dir| dir.join(format!("{}.toml", DEFAULT_LOCALE)).is_file())
+}
+
+static ACTIVE: OnceLock<Catalog> = OnceLock::new();
+static FALLBACK: OnceLock<Catalog> = OnceLock::new();
+
+// Select the catalog for this run; later calls have no effect
+pub fn init(locale: &str, dir: Option<&Path>) -> Result<(), String> {
+    let catalog = Catalog::load(locale, catalog_dir(dir).as_deref())?;
+    let _ = ACTIVE.set(catalog);
+    Ok(())
+}
+
+fn lookup(key: &str, render: impl Fn(&Catalog) -> Option<String>) -> String {
+    let fallback = FALLBACK.get_or_init(Catalog::en_us);
+    ACTIVE
+        .get()
+        .and_then(&render)
+        .or_else(|| render(fallback))
+        .unwrap_or_else(|| key.to_string())
+}
+
+// The message in the active locale (en-US if none was selected)
+pub fn text(message: &Message) -> String {
+    lookup(message.key(), |catalog| catalog.render(message))
+}
+
+// A demo's narration line in the active locale, by key; see narration!
+pub fn narration(key: &str, args: &[(&str, Arg)]) -> String {
+    lookup(key, |catalog| catalog.narration(key, args))
+}
@@ -0,0 +1,309 @@
+/*!
+ * Async Resilience Middleware
+ *
+ * Timeout, retry and circuit breaking as tower-style layers. A Service
+ * turns a request into a future of a response; a Layer wraps one Service
+ * in another. Each policy is a layer, so the policies stack around any
+ * async call in any order without the call knowing about them:
+ *
+ *   service.with(TimeoutLayer).with(BreakerLayer).with(RetryLayer)
+ *
+ * runs every attempt under the timeout, counts each attempt in the
+ * breaker, and retries failed attempts - but never an open breaker.
+ * The breaker is the same CircuitBreaker the scenario engine uses.
+ */
+
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use tokio::runtime::Runtime;
+use tokio::time;
+
+use crate::circuit_breaker::{BreakerState, CircuitBreaker};
+use crate::clock::MockClock;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallError {
+    Failed(String),  // the service itself reported an error
+    TimedOut,        // no answer within the timeout
+    Rejected,        // the circuit breaker is open
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallError::Failed(reason) => write!(f, "failed: {}", reason),
+            CallError::TimedOut => write!(f, "timed out"),
+            CallError::Rejected => write!(f, "rejected (breaker open)"),
+        }
+    }
+}
+
+pub type ServiceFuture<'a, T> = BoxFuture<'a, Result<T, CallError>>;
+
+pub trait Service<Request> {
+    type Response;
+
+    fn call(&self, request: Request) -> ServiceFuture<'_, Self::Response>;
+}
+
+pub trait Layer<S> {
+    type Service;
+
+    fn layer(&self, inner: S) -> Self::Service;
+}
+
+// `service.with(layer)` wraps the service in the layer
+pub trait ServiceExt<Request>: Service<Request> + Sized {
+    fn with<L: Layer<Self>>(self, layer: L) -> L::Service {
+        layer.layer(self)
+    }
+}
+
+impl<S: Service<Request>, Request> ServiceExt<Request> for S {}
+
+// Every call gets at most `limit` to finish
+#[derive(Debug)]
+pub struct TimeoutLayer {
+    limit: Duration,
+}
+
+impl TimeoutLayer {
+    pub fn new(limit: Duration) -> Self {
+        TimeoutLayer { limit }
+    }
+}
+
+#[derive(Debug)]
+pub struct Timeout<S> {
+    inner: S,
+    limit: Duration,
+}
+
+impl<S> Layer<S> for TimeoutLayer {
+    type Service = Timeout<S>;
+
+    fn layer(&self, inner: S) -> Timeout<S> {
+        Timeout { inner, limit: self.limit }
+    }
+}
+
+impl<S, Request> Service<Request> for Timeout<S>
+where
+    S: Service<Request> + Sync,
+    S::Response: Send,
+    Request: Send + 'static,
+{
+    type Response = S::Response;
+
+    fn call(&self, request: Request) -> ServiceFuture<'_, S::Response> {
+        Box::pin(async move {
+            time::timeout(self.limit, self.inner.call(request))
+                .await
+                .unwrap_or(Err(CallError::TimedOut))
+        })
+    }
+}
+
+// Failed and timed-out calls are tried again, up to `attempts` in total,
+// waiting `backoff` times the attempt number in between
+#[derive(Debug)]
+pub struct RetryLayer {
+    attempts: u32,
+    backoff: Duration,
+}
+
+impl RetryLayer {
+    pub fn new(attempts: u32, backoff: Duration) -> Self {
+        RetryLayer { attempts: attempts.max(1), backoff }
+    }
+}
+
+#[derive(Debug)]
+pub struct Retry<S> {
+    inner: S,
+    attempts: u32,
+    backoff: Duration,
+}
+
+impl<S> Layer<S> for RetryLayer {
+    type Service = Retry<S>;
+
+    fn layer(&self, inner: S) -> Retry<S> {
+        Retry { inner, attempts: self.attempts, backoff: self.backoff }
+    }
+}
+
+impl<S, Request> Service<Request> for Retry<S>
+where
+    S: Service<Request> + Sync,
+    S::Response: Send,
+    Request: Clone + Send + Sync + 'static,
+{
+    type Response = S::Response;
+
+    fn call(&self, request: Request) -> ServiceFuture<'_, S::Response> {
+        Box::pin(async move {
+            let mut last_error = None;
+            for attempt in 1..=self.attempts {
+                match self.inner.call(request.clone()).await {
+                    // An open breaker will not answer a retry either: report
+                    // what went wrong before it opened
+                    Err(CallError::Rejected) => return Err(last_error.unwrap_or(CallError::Rejected)),
+                    Err(error) if attempt < self.attempts => {
+                        last_error = Some(error);
+                        time::sleep(self.backoff * attempt).await;
+                    }
+                    result => return result,
+                }
+            }
+            Err(last_error.unwrap_or(CallError::Rejected))
+        })
+    }
+}
+
+// Calls go through only while the breaker allows them
+#[derive(Debug)]
+pub struct BreakerLayer {
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl BreakerLayer {
+    pub fn new(breaker: Arc<CircuitBreaker>) -> Self {
+        BreakerLayer { breaker }
+    }
+}
+
+#[derive(Debug)]
+pub struct Breaker<S> {
+    inner: S,
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl<S> Layer<S> for BreakerLayer {
+    type Service = Breaker<S>;
+
+    fn layer(&self, inner: S) -> Breaker<S> {
+        Breaker { inner, breaker: Arc::clone(&self.breaker) }
+    }
+}
+
+impl<S, Request> Service<Request> for Breaker<S>
+where
+    S: Service<Request> + Sync,
+    S::Response: Send,
+    Request: Send + 'static,
+{
+    type Response = S::Response;
+
+    fn call(&self, request: Request) -> ServiceFuture<'_, S::Response> {
+        Box::pin(async move {
+            if !self.breaker.allow() {
+                return Err(CallError::Rejected);
+            }
+            let result = self.inner.call(request).await;
+            match &result {
+                Ok(_) => self.breaker.record_success(),
+                Err(_) => self.breaker.record_failure(),
+            }
+            result
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Behavior {
+    #[default]
+    Healthy,          // answers at once
+    Slow(Duration),   // answers after a delay
+    Failing,          // answers with an error
+}
+
+// A stand-in for a remote dependency whose behavior the demo controls
+#[derive(Debug, Default)]
+pub struct MockService {
+    behavior: Mutex<Behavior>,
+    calls: AtomicU32,
+}
+
+impl MockService {
+    pub fn new() -> Self {
+        MockService::default()
+    }
+
+    pub fn set_behavior(&self, behavior: Behavior) {
+        *self.behavior.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = behavior;
+    }
+
+    // Requests that actually reached the service
+    pub fn calls(&self) -> u32 {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+impl Service<u32> for Arc<MockService> {
+    type Response = u32;
+
+    fn call(&self, request: u32) -> ServiceFuture<'_, u32> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let behavior = *self.behavior.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        Box::pin(async move {
+            match behavior {
+                Behavior::Healthy => Ok(request * 2),
+                Behavior::Slow(delay) => {
+                    time::sleep(delay).await;
+                    Ok(request * 2)
+                }
+                Behavior::Failing => Err(CallError::Failed(format!("request {} refused", request))),
+            }
+        })
+    }
+}
+
+// One call of the walkthrough and what the stack looked like after it
+#[derive(Debug)]
+pub struct WalkStep {
+    pub label: &'static str,
+    pub result: Result<u32, CallError>,
+    pub state: BreakerState,
+    pub service_calls: u32,
+}
+
+// Drive a timeout + breaker + retry stack through closed -> open ->
+// half-open (failed trial) -> open -> half-open -> closed. `tick` scales
+// every duration. The breaker's cooldown runs on a MockClock that moves
+// only with the scripted pauses, so the states do not depend on how
+// promptly the runtime wakes up - on a paused runtime the whole walk
+// takes no real time at all.
+pub fn breaker_walkthrough(runtime: &Runtime, tick: Duration) -> Vec<WalkStep> {
+    let cooldown = tick * 5;
+    let mock = Arc::new(MockService::new());
+    let clock = Arc::new(MockClock::new());
+    let breaker = Arc::new(CircuitBreaker::new(2, cooldown, clock.clone()));
+    let client = Arc::clone(&mock)
+        .with(TimeoutLayer::new(tick * 3))
+        .with(BreakerLayer::new(Arc::clone(&breaker)))
+        .with(RetryLayer::new(2, tick));
+
+    let script = [
+        ("healthy", Behavior::Healthy, Duration::ZERO),
+        ("failing: retried, opens", Behavior::Failing, Duration::ZERO),
+        ("failing: rejected at once", Behavior::Failing, Duration::ZERO),
+        ("cooldown, slow trial", Behavior::Slow(tick * 10), cooldown + tick),
+        ("cooldown, healthy trial", Behavior::Healthy, cooldown + tick),
+    ];
+    runtime.block_on(async {
+        let mut steps = Vec::new();
+        for (request, (label, behavior, pause)) in (1..).zip(script) {
+            time::sleep(pause).await;
+            clock.advance(pause);
+            mock.set_behavior(behavior);
+            let result = client.call(request).await;
+            steps.push(WalkStep { label, result, state: breaker.state(), service_calls: mock.calls() });
+        }
+        steps
+    })
+}
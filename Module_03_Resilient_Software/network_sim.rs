@@ -0,0 +1,243 @@
+/*!
+ * Simulated Network
+ *
+ * In-process nodes exchange messages through a NetworkSim instead of real
+ * sockets. Time is a tick counter and every random choice comes from a
+ * seeded generator, so a lossy, partitioned run is exactly repeatable.
+ * Per scenario the network can drop, delay, duplicate and reorder
+ * messages, and split the nodes into groups that cannot reach each other
+ * until the partition heals - a message still in flight when the
+ * partition starts is lost too.
+ */
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Add;
+
+use crate::rng::SeededRng;
+
+pub type NodeId = usize;
+
+// What can go wrong on a link; the default is a perfect network
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LinkFaults {
+    pub drop: f64,        // probability a message is lost
+    pub duplicate: f64,   // probability it is delivered twice
+    pub reorder: f64,     // probability it is held back so later ones overtake it
+    pub min_delay: u64,   // ticks in flight
+    pub max_delay: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Envelope<M> {
+    pub from: NodeId,
+    pub to: NodeId,
+    pub message: M,
+    seq: u64,  // send order on the from -> to link
+    deliver_at: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NetStats {
+    pub sent: u64,
+    pub delivered: u64,
+    pub dropped: u64,      // lost to the `drop` probability
+    pub partitioned: u64,  // lost to a partition
+    pub duplicated: u64,
+    pub reordered: u64,    // delivered after a later message on the same link
+}
+
+impl Add for NetStats {
+    type Output = NetStats;
+
+    fn add(self, other: NetStats) -> NetStats {
+        NetStats {
+            sent: self.sent + other.sent,
+            delivered: self.delivered + other.delivered,
+            dropped: self.dropped + other.dropped,
+            partitioned: self.partitioned + other.partitioned,
+            duplicated: self.duplicated + other.duplicated,
+            reordered: self.reordered + other.reordered,
+        }
+    }
+}
+
+impl fmt::Display for NetStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} sent, {} delivered, {} dropped, {} partitioned, {} duplicated, {} reordered",
+            self.sent, self.delivered, self.dropped, self.partitioned, self.duplicated, self.reordered
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct NetworkSim<M> {
+    nodes: usize,
+    now: u64,
+    rng: SeededRng,
+    faults: LinkFaults,
+    groups: Option<Vec<usize>>,  // group of each node while partitioned
+    in_flight: Vec<Envelope<M>>,
+    next_seq: HashMap<(NodeId, NodeId), u64>,
+    last_delivered: HashMap<(NodeId, NodeId), u64>,
+    stats: NetStats,
+}
+
+impl<M: Clone> NetworkSim<M> {
+    pub fn new(nodes: usize, faults: LinkFaults, seed: u64) -> Self {
+        NetworkSim {
+            nodes,
+            now: 0,
+            rng: SeededRng::new(seed),
+            faults,
+            groups: None,
+            in_flight: Vec::new(),
+            next_seq: HashMap::new(),
+            last_delivered: HashMap::new(),
+            stats: NetStats::default(),
+        }
+    }
+
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    pub fn stats(&self) -> NetStats {
+        self.stats
+    }
+
+    // Change the faults for messages sent from now on
+    pub fn set_faults(&mut self, faults: LinkFaults) {
+        self.faults = faults;
+    }
+
+    pub fn send(&mut self, from: NodeId, to: NodeId, message: M) {
+        self.stats.sent += 1;
+        if self.rng.chance(self.faults.drop) {
+            self.stats.dropped += 1;
+            return;
+        }
+        let seq = self.next_seq.entry((from, to)).or_insert(0);
+        *seq += 1;
+        let seq = *seq;
+
+        let copies = if self.rng.chance(self.faults.duplicate) { 2 } else { 1 };
+        self.stats.duplicated += copies - 1;
+        for _ in 0..copies {
+            let mut delay = self.rng.range(self.faults.min_delay as usize, self.faults.max_delay as usize) as u64;
+            if self.rng.chance(self.faults.reorder) {
+                delay += self.faults.max_delay + 1 + self.rng.range(0, 3) as u64;
+            }
+            self.in_flight.push(Envelope {
+                from,
+                to,
+                message: message.clone(),
+                seq,
+                deliver_at: self.now + delay.max(1),
+            });
+        }
+    }
+
+    // Send to every other node
+    pub fn broadcast(&mut self, from: NodeId, message: M) {
+        for to in (0..self.nodes).filter(|to| *to != from) {
+            self.send(from, to, message.clone());
+        }
+    }
+
+    // Split the nodes into groups; nodes not listed form one more group
+    pub fn partition(&mut self, groups: &[&[NodeId]]) {
+        let mut assignment = vec![groups.len(); self.nodes];
+        for (group, members) in groups.iter().enumerate() {
+            for node in members.iter().filter(|node| **node < self.nodes) {
+                assignment[*node] = group;
+            }
+        }
+        self.groups = Some(assignment);
+    }
+
+    pub fn heal(&mut self) {
+        self.groups = None;
+    }
+
+    pub fn can_reach(&self, from: NodeId, to: NodeId) -> bool {
+        match &self.groups {
+            Some(groups) => groups.get(from) == groups.get(to),
+            None => true,
+        }
+    }
+
+    // Advance one tick and hand out what arrives, oldest first
+    pub fn tick(&mut self) -> Vec<Envelope<M>> {
+        self.now += 1;
+        let now = self.now;
+        let (mut due, waiting): (Vec<_>, Vec<_>) = self.in_flight.drain(..).partition(|envelope| envelope.deliver_at <= now);
+        self.in_flight = waiting;
+        due.sort_by_key(|envelope| (envelope.deliver_at, envelope.from, envelope.to, envelope.seq));
+
+        let mut delivered = Vec::new();
+        for envelope in due {
+            if !self.can_reach(envelope.from, envelope.to) {
+                self.stats.partitioned += 1;
+                continue;
+            }
+            let last = self.last_delivered.entry((envelope.from, envelope.to)).or_insert(0);
+            if envelope.seq < *last {
+                self.stats.reordered += 1;
+            }
+            *last = (*last).max(envelope.seq);
+            self.stats.delivered += 1;
+            delivered.push(envelope);
+        }
+        delivered
+    }
+}
+
+// A peer is suspected once nothing has been heard from it for this long
+pub const SUSPECT_AFTER: u64 = 5;
+
+#[derive(Debug)]
+pub struct HeartbeatRun {
+    pub stats: NetStats,
+    pub view: String,  // node 0's view of the last node per tick: '.' alive, 'x' suspected
+    pub suspected: Vec<u64>,  // ticks at which any node suspected any peer
+}
+
+// Every node broadcasts a heartbeat each tick for `ticks` ticks; during
+// `partition` (start..end ticks) the last node is cut off from the rest
+pub fn heartbeat_run(nodes: usize, ticks: u64, faults: LinkFaults, partition: Option<(u64, u64)>, seed: u64) -> HeartbeatRun {
+    let mut network = NetworkSim::new(nodes, faults, seed);
+    let mut last_heard = vec![vec![0u64; nodes]; nodes];
+    let mut view = String::new();
+    let mut suspected = Vec::new();
+    let isolated = nodes - 1;
+    let majority: Vec<NodeId> = (0..isolated).collect();
+
+    for _ in 0..ticks {
+        let now = network.now();
+        match partition {
+            Some((start, _)) if now == start => network.partition(&[&majority, &[isolated]]),
+            Some((_, end)) if now == end => network.heal(),
+            _ => {}
+        }
+        for node in 0..nodes {
+            network.broadcast(node, now);
+        }
+        // A heartbeat carries the tick it was sent at; a late one never
+        // makes a peer look more recent than it is
+        for envelope in network.tick() {
+            let heard = &mut last_heard[envelope.to][envelope.from];
+            *heard = (*heard).max(envelope.message);
+        }
+
+        let now = network.now();
+        let suspects = |node: NodeId, peer: NodeId| now - last_heard[node][peer] > SUSPECT_AFTER;
+        view.push(if suspects(0, isolated) { 'x' } else { '.' });
+        if (0..nodes).any(|node| (0..nodes).any(|peer| peer != node && suspects(node, peer))) {
+            suspected.push(now);
+        }
+    }
+    HeartbeatRun { stats: network.stats(), view, suspected }
+}
@@ -1,4 +1,4 @@
-/**
+/*!
  * Rust Option Safety Example - TYPE SAFE
  * 
  * This program demonstrates how Rust eliminates null pointer exceptions
@@ -6,52 +6,42 @@
  * and preventing null pointer dereferences at compile time.
  */
 
-#[derive(Debug)]
-struct Resource {
-    id: i32,
-    name: String,
-}
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::time::Duration;
 
-impl Resource {
-    fn new(id: i32, name: &str) -> Self {
-        println!("Created Resource: {} (id: {})", name, id);
-        Resource {
-            id,
-            name: name.to_string(),
-        }
-    }
-    
-    fn process(&self) {
-        println!("Processing resource: {} (id: {})", self.name, self.id);
-    }
-}
+use resilient_core::{RawResource, Resource, ResourceId};
+use safety_demo::clock::MockClock;
+use safety_demo::resource_cache::ResourceCache;
+use safety_demo::resource_manager::{Category, Quota, ResourceManager};
+use safety_demo::units::{Bytes, Quantity};
+use safety_demo::{narrate, narration, say, standalone};
 
-impl Drop for Resource {
-    fn drop(&mut self) {
-        println!("Destroyed Resource: {}", self.name);
-    }
+// A resource whose id and name the demo spells out itself, so they are valid
+fn resource(id: i32, name: &str) -> Resource {
+    Resource::new(id, name).expect("the demo's own resources have positive ids and names")
 }
 
 // Function that might not find a resource - returns Option<T>
 fn find_resource_by_id(resources: &[Resource], target_id: i32) -> Option<&Resource> {
-    resources.iter().find(|res| res.id == target_id)
+    resources.iter().find(|res| res.id() == target_id)
 }
 
 fn demonstrate_option_safety() {
     let resources = vec![
-        Resource::new(1, "Database"),
-        Resource::new(2, "FileSystem"),
-        Resource::new(3, "Network"),
+        resource(1, "Database"),
+        resource(2, "FileSystem"),
+        resource(3, "Network"),
     ];
     
     // Search for existing resource
     match find_resource_by_id(&resources, 2) {
         Some(resource) => {
-            println!("Found resource!");
+            narrate!("option_safe.found");
             resource.process();
         },
         None => {
-            println!("Resource not found");
+            narrate!("option_safe.not_found");
         }
     }
     
@@ -61,7 +51,7 @@ fn demonstrate_option_safety() {
             resource.process();
         },
         None => {
-            println!("Resource 999 not found - safely handled!");
+            narrate!("option_safe.not_found_handled");
         }
     }
     
@@ -73,102 +63,116 @@ fn demonstrate_option_safety() {
 
 fn demonstrate_option_methods() {
     let resources = vec![
-        Resource::new(10, "Cache"),
-        Resource::new(20, "Logger"),
+        resource(10, "Cache"),
+        resource(20, "Logger"),
     ];
     
     // Using if let for cleaner syntax
     if let Some(resource) = find_resource_by_id(&resources, 10) {
         resource.process();
     } else {
-        println!("Resource not found with if let");
+        narrate!("option_safe.not_found_if_let");
     }
     
     // Using unwrap_or_else for default behavior
+    let default_resource = resource(99, "Default");
     let resource_or_default = find_resource_by_id(&resources, 999)
         .unwrap_or_else(|| {
-            println!("Using default resource");
-            &Resource::new(0, "Default")
+            narrate!("option_safe.using_default");
+            &default_resource
         });
+    resource_or_default.process();
     
     // Using map to transform the Option
+    let unknown = narration!("option_safe.unknown");
     let resource_name = find_resource_by_id(&resources, 20)
-        .map(|res| &res.name)
-        .unwrap_or(&"Unknown".to_string());
+        .map(|res| res.name())
+        .unwrap_or(&unknown);
     
-    println!("Resource name: {}", resource_name);
+    narrate!("option_safe.resource_name", name = resource_name);
     
     // Using and_then for chaining operations
     let processed = find_resource_by_id(&resources, 10)
         .and_then(|res| {
-            if res.id > 5 {
-                Some(format!("Processed: {}", res.name))
+            if res.id() > 5 {
+                Some(narration!("option_safe.processed", name = res.name()))
             } else {
                 None
             }
         });
     
     match processed {
-        Some(msg) => println!("{}", msg),
-        None => println!("Processing conditions not met"),
+        Some(msg) => say!("{}", msg),
+        None => narrate!("option_safe.processing_conditions_not_met"),
     }
 }
 
 fn demonstrate_result_safety() {
-    // Result<T, E> for operations that can fail with error information
-    fn try_create_resource(id: i32, name: &str) -> Result<Resource, String> {
-        if id <= 0 {
-            Err("Invalid ID: must be positive".to_string())
-        } else if name.is_empty() {
-            Err("Invalid name: cannot be empty".to_string())
-        } else {
-            Ok(Resource::new(id, name))
-        }
-    }
+    // Result<T, E> for operations that can fail with error information:
+    // unchecked input becomes a Resource only through TryFrom
+    let raw = |id: i32, name: &str| RawResource { id, name: name.to_string() };
     
     // Handle Result with match
-    match try_create_resource(5, "ValidResource") {
+    match Resource::try_from(raw(5, "ValidResource")) {
         Ok(resource) => {
-            println!("Successfully created resource");
+            narrate!("option_safe.created");
             resource.process();
         },
         Err(error) => {
-            println!("Failed to create resource: {}", error);
+            narrate!("option_safe.create_failed", error);
         }
     }
     
     // Handle error case
-    match try_create_resource(-1, "InvalidResource") {
+    match Resource::try_from(raw(-1, "InvalidResource")) {
         Ok(resource) => resource.process(),
-        Err(error) => println!("Creation failed: {}", error),
+        Err(error) => narrate!("option_safe.creation_failed", error),
     }
     
     // Using unwrap_or_else with Result
-    let resource = try_create_resource(0, "")
-        .unwrap_or_else(|_| Resource::new(1, "Fallback"));
+    let resource = Resource::try_from(raw(0, ""))
+        .unwrap_or_else(|_| resource(1, "Fallback"));
     
     resource.process();
+    
+    // The same conversion from text, each failure with its own error
+    for text in ["7:Scheduler", "x:Broken", "8:", "9"] {
+        match Resource::try_from(text) {
+            Ok(resource) => narrate!("option_safe.parsed", text = ?text, name = resource.name()),
+            Err(error) => narrate!("option_safe.rejected", text = ?text, error),
+        }
+    }
+    
+    // Standard traits compose: collect stops at the first bad id
+    for list in ["1,2,3", "1,-2,3"] {
+        let ids: Result<Vec<ResourceId>, _> = list.split(',').map(ResourceId::try_from).collect();
+        match ids {
+            Ok(ids) => narrate!("option_safe.ids", list,
+                                ids = ids.iter().map(ResourceId::to_string).collect::<Vec<_>>().join(" ")),
+            Err(error) => narrate!("option_safe.ids_rejected", list, error),
+        }
+    }
 }
 
 fn demonstrate_option_collections() {
     // Vec<Option<T>> for collections that might contain missing values
     let maybe_resources: Vec<Option<Resource>> = vec![
-        Some(Resource::new(1, "First")),
+        Some(resource(1, "First")),
         None,  // Missing resource
-        Some(Resource::new(3, "Third")),
+        Some(resource(3, "Third")),
         None,  // Another missing resource
-        Some(Resource::new(5, "Fifth")),
+        Some(resource(5, "Fifth")),
     ];
     
     // Safe iteration over Option values
     for (index, maybe_resource) in maybe_resources.iter().enumerate() {
         match maybe_resource {
             Some(resource) => {
-                println!("Slot {}: Found resource", index);
+                narrate!("option_safe.slot_found", index);
                 resource.process();
             },
             None => {
-                println!("Slot {}: Empty slot", index);
+                narrate!("option_safe.slot_empty", index);
             }
         }
     }
@@ -179,16 +183,16 @@ fn demonstrate_option_collections() {
         .filter_map(|opt| opt.as_ref())
         .collect();
     
-    println!("Found {} existing resources", existing_resources.len());
+    narrate!("option_safe.existing_count", count = existing_resources.len());
     
     // Using flatten to remove None values
-    let resource_names: Vec<&String> = maybe_resources
+    let resource_names: Vec<&str> = maybe_resources
         .iter()
         .flatten()  // Removes None values
-        .map(|res| &res.name)
+        .map(|res| res.name())
         .collect();
     
-    println!("Resource names: {:?}", resource_names);
+    narrate!("option_safe.resource_names", names = ?resource_names);
 }
 
 fn demonstrate_no_null_dereference() {
@@ -201,11 +205,11 @@ fn demonstrate_no_null_dereference() {
     // Must explicitly handle the None case
     match maybe_resource {
         Some(resource) => resource.process(),
-        None => println!("No resource to process - safely handled!"),
+        None => narrate!("option_safe.nothing_to_process"),
     }
     
     // Even with references, no null pointers exist
-    let resources = vec![Resource::new(100, "Safe")];
+    let resources = [resource(100, "Safe")];
     let resource_ref: &Resource = &resources[0];  // Always valid
     
     // No way to create a "null reference" in safe Rust
@@ -218,59 +222,257 @@ fn demonstrate_option_chaining() {
     }
     
     impl Container {
-        fn get_resource_name(&self) -> Option<&String> {
-            self.resource.as_ref().map(|res| &res.name)
+        fn get_resource_name(&self) -> Option<&str> {
+            self.resource.as_ref().map(|res| res.name())
         }
         
         fn get_resource_id(&self) -> Option<i32> {
-            self.resource.as_ref().map(|res| res.id)
+            self.resource.as_ref().map(|res| res.id())
         }
     }
     
-    let containers = vec![
-        Container { resource: Some(Resource::new(1, "First")) },
+    let containers = [
+        Container { resource: Some(resource(1, "First")) },
         Container { resource: None },
-        Container { resource: Some(Resource::new(3, "Third")) },
+        Container { resource: Some(resource(3, "Third")) },
     ];
     
     for (index, container) in containers.iter().enumerate() {
         // Safe chaining of Option operations
         let info = container.get_resource_name()
             .zip(container.get_resource_id())
-            .map(|(name, id)| format!("Resource '{}' has ID {}", name, id))
-            .unwrap_or_else(|| "No resource in container".to_string());
+            .map(|(name, id)| narration!("option_safe.resource_has_id", name, id))
+            .unwrap_or_else(|| narration!("option_safe.container_empty"));
         
-        println!("Container {}: {}", index, info);
+        narrate!("option_safe.container_info", index, info);
+    }
+}
+
+// Database, FileSystem and Network resources owned by one manager: opened
+// after what they depend on, closed in the reverse order
+fn demonstrate_resource_lifecycle() {
+    // Declared out of order on purpose - the manager sorts them
+    let manager = ResourceManager::builder()
+        .resource("Cache", &["Network", "FileSystem"], || Ok(resource(4, "Cache")))
+        .resource("Network", &["Database"], || Ok(resource(3, "Network")))
+        .resource("FileSystem", &[], || Ok(resource(2, "FileSystem")))
+        .resource("Database", &[], || Ok(resource(1, "Database")))
+        .start();
+
+    match manager {
+        Ok(manager) => {
+            let startup: Vec<String> = manager.startup_order().iter().map(|name| name.to_string()).collect();
+            narrate!("option_safe.startup_order", order = startup.join(" -> "));
+            if let Some(network) = manager.get("Network") {
+                network.process();
+            }
+            let teardown = manager.shutdown();
+            narrate!("option_safe.teardown_order", order = teardown.join(" -> "));
+            let reversed: Vec<String> = startup.into_iter().rev().collect();
+            narrate!("option_safe.teardown_reverses_startup", reversed = teardown == reversed);
+        }
+        Err(error) => narrate!("option_safe.startup_failed", error),
+    }
+
+    // Network fails to open: what was already open is closed again, newest
+    // first, and Cache is never opened
+    narrate!("option_safe.partial_startup_heading");
+    let failed = ResourceManager::builder()
+        .resource("Database", &[], || Ok(resource(1, "Database")))
+        .resource("FileSystem", &[], || Ok(resource(2, "FileSystem")))
+        .resource("Network", &["Database"], || Err("connection refused".to_string()))
+        .resource("Cache", &["Network", "FileSystem"], || Ok(resource(4, "Cache")))
+        .start();
+    if let Err(error) = failed {
+        narrate!("option_safe.startup_failed", error);
+    }
+
+    // Dropping the manager tears down the same way as shutdown()
+    narrate!("option_safe.scope_drop_heading");
+    {
+        let _manager = ResourceManager::builder()
+            .resource("Network", &["Database"], || Ok(resource(3, "Network")))
+            .resource("Database", &[], || Ok(resource(1, "Database")))
+            .start();
+        narrate!("option_safe.leaving_scope");
+    }
+
+    // Misdeclared dependencies are reported before anything is opened
+    let cyclic = ResourceManager::builder()
+        .resource("Database", &["Network"], || Ok(resource(1, "Database")))
+        .resource("Network", &["Database"], || Ok(resource(3, "Network")))
+        .start();
+    if let Err(error) = cyclic {
+        narrate!("option_safe.startup_refused", error);
+    }
+}
+
+// Scoped access: a resource borrowed from the manager is always checked
+// back in, and a panic while holding it marks it failed
+fn demonstrate_scoped_access() {
+    let manager = match ResourceManager::builder()
+        .resource("Database", &[], || Ok(resource(1, "Database")))
+        .resource("Network", &["Database"], || Ok(resource(3, "Network")))
+        .start()
+    {
+        Ok(manager) => manager,
+        Err(error) => {
+            narrate!("option_safe.startup_failed", error);
+            return;
+        }
+    };
+
+    let id = manager.with_resource("Database", |database| {
+        database.process();
+        database.id()
+    });
+    narrate!("option_safe.borrowed", id = ?id, state = ?manager.state("Database"));
+
+    // Borrowing it again from inside the closure is refused, not a deadlock
+    let nested = manager.with_resource("Database", |_| manager.with_resource("Database", |_| ()));
+    narrate!("option_safe.borrowed_nested", nested = ?nested);
+
+    // The closure panics halfway through: the guard still runs during
+    // unwinding. A one-line hook stands in for the usual panic report.
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new(|info| {
+        narrate!("option_safe.panicked", payload = info.payload().downcast_ref::<&str>().unwrap_or(&"?"))
+    }));
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+        manager.with_resource("Network", |network| {
+            network.process();
+            panic!("network handler crashed");
+        })
+    }));
+    panic::set_hook(previous_hook);
+    narrate!("option_safe.borrow_panic_caught", caught = outcome.is_err());
+    narrate!("option_safe.borrow_after_panic", state = ?manager.state("Network"));
+
+    match manager.with_resource("Network", |network| network.process()) {
+        Ok(()) => narrate!("option_safe.borrow_reused"),
+        Err(error) => narrate!("option_safe.borrow_refused", error),
     }
+    narrate!("option_safe.borrow_missing", result = ?manager.with_resource("Printer", |_| ()));
+}
+
+// Quotas: connections and file buffers are acquired at run time, and a
+// category that is full refuses more instead of exhausting the system
+fn demonstrate_resource_quotas() {
+    let mut manager = match ResourceManager::builder()
+        .resource("Database", &[], || Ok(resource(1, "Database")))
+        .quota(Category::Network, Quota { max_open: Quantity::new(2), max_bytes: Quantity::new(64 * 1024) })
+        .quota(Category::FileSystem, Quota { max_open: Quantity::new(8), max_bytes: Quantity::new(1024 * 1024) })
+        .start()
+    {
+        Ok(manager) => manager,
+        Err(error) => {
+            narrate!("option_safe.startup_failed", error);
+            return;
+        }
+    };
+
+    let kib = |kib: u64| Quantity::<u64, Bytes>::new(kib * 1024);
+    let requests: [(&str, Category, Quantity<u64, Bytes>); 6] = [
+        ("conn-1", Category::Network, kib(16)),
+        ("conn-2", Category::Network, kib(16)),
+        ("conn-3", Category::Network, kib(16)),
+        ("buffer-a", Category::FileSystem, kib(600)),
+        ("buffer-b", Category::FileSystem, kib(600)),
+        ("query-1", Category::Database, kib(4)),
+    ];
+    for (id, (name, category, bytes)) in (10..).zip(requests) {
+        match manager.acquire(name, category, bytes, || Ok(resource(id, name))) {
+            Ok(()) => narrate!("option_safe.acquired", name, bytes),
+            Err(error) => narrate!("option_safe.refused", name, error),
+        }
+    }
+
+    // Releasing a connection frees its share of the quota
+    manager.release("conn-1");
+    match manager.acquire("conn-3", Category::Network, kib(16), || Ok(resource(12, "conn-3"))) {
+        Ok(()) => narrate!("option_safe.quota_reacquired"),
+        Err(error) => narrate!("option_safe.quota_refused_again", error),
+    }
+
+    narrate!("option_safe.quota_header");
+    for usage in manager.accounting() {
+        let (max_open, max_bytes) = match usage.quota {
+            Some(quota) => (quota.max_open.get().to_string(), quota.max_bytes.get().to_string()),
+            None => ("-".to_string(), "-".to_string()),
+        };
+        say!(
+            "{:<11} {:>5} {:>9} {:>5} {:>10} {:>10} {:>10} {:>8}",
+            format!("{:?}", usage.category),
+            usage.open.get(),
+            max_open,
+            usage.peak_open.get(),
+            usage.bytes.get(),
+            max_bytes,
+            usage.peak_bytes.get(),
+            usage.rejected
+        );
+    }
+}
+
+// Absence that depends on time: cached resources expire. A mock clock
+// makes every step exact instead of waiting for real seconds to pass.
+fn demonstrate_resource_expiry() {
+    let clock = Arc::new(MockClock::new());
+    let mut cache = ResourceCache::new(Arc::clone(&clock), 2, Duration::from_secs(30));
+    let describe = |found: Option<&Resource>| match found {
+        Some(resource) => format!("Some({})", resource.name()),
+        None => "None".to_string(),
+    };
+
+    cache.insert("Database", resource(1, "Database"));
+    clock.advance(Duration::from_secs(10));
+    cache.insert("Network", resource(3, "Network"));
+    narrate!("option_safe.expiry_database_fresh", found = describe(cache.get("Database")));
+
+    clock.advance(Duration::from_secs(25));
+    narrate!("option_safe.expiry_held_stale", held = cache.len());
+    narrate!("option_safe.expiry_database_expired", found = describe(cache.get("Database")));
+    narrate!("option_safe.expiry_held", held = cache.len());
+    narrate!("option_safe.expiry_network", found = describe(cache.get("Network")));
+
+    // Full cache: the least recently used entry makes room
+    cache.insert("FileSystem", resource(2, "FileSystem"));
+    if let Some(evicted) = cache.insert("Cache", resource(4, "Cache")) {
+        narrate!("option_safe.expiry_evicted", evicted);
+    }
+
+    clock.advance(Duration::from_secs(30));
+    narrate!("option_safe.expiry_filesystem", found = describe(cache.get("FileSystem")));
 }
 
 fn main() {
-    println!("=== Rust Option Safety System ===");
-    
-    println!("\n1. Basic Option Safety:");
-    demonstrate_option_safety();
-    
-    println!("\n2. Option Methods:");
-    demonstrate_option_methods();
-    
-    println!("\n3. Result Safety:");
-    demonstrate_result_safety();
-    
-    println!("\n4. Option Collections:");
-    demonstrate_option_collections();
-    
-    println!("\n5. No Null Dereference Possible:");
-    demonstrate_no_null_dereference();
-    
-    println!("\n6. Option Chaining:");
-    demonstrate_option_chaining();
-    
-    println!("\nKey Safety Features:");
-    println!("- No null pointers exist in safe Rust");
-    println!("- Option<T> makes absence explicit and type-safe");
-    println!("- Compiler forces handling of None cases");
-    println!("- Result<T, E> provides rich error information");
-    println!("- Method chaining allows safe composition");
-    println!("- Zero runtime overhead - all checks at compile time");
-    println!("- Impossible to accidentally dereference null");
+    standalone::start();
+    narrate!("option_safe.heading");
+    
+    standalone::demonstrate("option_safety", &narration!("option_safe.option_safety_heading"), demonstrate_option_safety);
+    standalone::demonstrate("option_methods", &narration!("option_safe.option_methods_heading"), demonstrate_option_methods);
+    standalone::demonstrate("result_safety", &narration!("option_safe.result_safety_heading"), demonstrate_result_safety);
+    standalone::demonstrate("option_collections", &narration!("option_safe.option_collections_heading"),
+                            demonstrate_option_collections);
+    standalone::demonstrate("no_null_dereference", &narration!("option_safe.no_null_dereference_heading"),
+                            demonstrate_no_null_dereference);
+    standalone::demonstrate("option_chaining", &narration!("option_safe.option_chaining_heading"), demonstrate_option_chaining);
+    standalone::demonstrate("resource_lifecycle", &narration!("option_safe.resource_lifecycle_heading"),
+                            demonstrate_resource_lifecycle);
+    standalone::demonstrate("scoped_access", &narration!("option_safe.scoped_access_heading"), demonstrate_scoped_access);
+    standalone::demonstrate("resource_quotas", &narration!("option_safe.resource_quotas_heading"), demonstrate_resource_quotas);
+    standalone::demonstrate("resource_expiry", &narration!("option_safe.resource_expiry_heading"), demonstrate_resource_expiry);
+    
+    narrate!("option_safe.features_heading");
+    narrate!("option_safe.feature_no_null");
+    narrate!("option_safe.feature_option");
+    narrate!("option_safe.feature_exhaustive");
+    narrate!("option_safe.feature_result");
+    narrate!("option_safe.feature_chaining");
+    narrate!("option_safe.feature_drop_order");
+    narrate!("option_safe.feature_drop_guards");
+    narrate!("option_safe.feature_quotas");
+    narrate!("option_safe.feature_expiry");
+    narrate!("option_safe.feature_zero_cost");
+    narrate!("option_safe.feature_no_null_deref");
 }
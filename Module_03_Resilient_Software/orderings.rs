@@ -0,0 +1,222 @@
+/*!
+ * Memory Orderings
+ *
+ * SafeCounter and most demos use SeqCst on every atomic access, which is
+ * never wrong. The other orderings promise less and can cost less. What
+ * each one promises shows up in two litmus tests, each run once per
+ * Model:
+ *
+ *   message passing   the writer stores `data`, then raises `flag`; a
+ *                     reader that sees the flag loads `data`. With
+ *                     Release on the flag store and Acquire on the load,
+ *                     the reader always sees that data or newer. With
+ *                     Relaxed the two stores or the two loads may be
+ *                     reordered, and the data read can be stale
+ *   store buffering   two threads each store their own variable and then
+ *                     load the other's. Only SeqCst rules out both loads
+ *                     missing both stores: Release/Acquire orders a store
+ *                     before later stores, not before later loads, and a
+ *                     store can wait in the CPU's store buffer while the
+ *                     load goes ahead
+ *
+ * An anomaly is allowed, not promised: whether one is seen depends on the
+ * hardware. x86 keeps stores in order and loads in order (TSO), so
+ * message passing comes out right even under Relaxed there; ARM, POWER
+ * and RISC-V may reorder both. Store buffering is seen on x86 as well. On
+ * a single core the two threads never run at the same time, so neither
+ * test can see anything. A count of zero therefore proves nothing about
+ * the weaker orderings. A count above zero for Release/Acquire in the
+ * first test, or for SeqCst in the second, would be a broken promise, and
+ * is what the section and the `orderings` self-test check look for.
+ *
+ * Each store and load costs something too. On x86 a SeqCst store is an
+ * xchg, which drains the store buffer, where a Release store is a plain
+ * mov; the message-passing writer shows the difference.
+ */
+
+use std::fmt;
+use std::hint;
+use std::panic;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::spawn_policy::{self, SpawnError, Worker};
+
+// The orderings a test's accesses use: stores, then loads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    Relaxed,
+    ReleaseAcquire,
+    SeqCst,
+}
+
+impl Model {
+    pub const ALL: [Model; 3] = [Model::Relaxed, Model::ReleaseAcquire, Model::SeqCst];
+
+    fn store(self) -> Ordering {
+        match self {
+            Model::Relaxed => Ordering::Relaxed,
+            Model::ReleaseAcquire => Ordering::Release,
+            Model::SeqCst => Ordering::SeqCst,
+        }
+    }
+
+    fn load(self) -> Ordering {
+        match self {
+            Model::Relaxed => Ordering::Relaxed,
+            Model::ReleaseAcquire => Ordering::Acquire,
+            Model::SeqCst => Ordering::SeqCst,
+        }
+    }
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Model::Relaxed => "Relaxed",
+            Model::ReleaseAcquire => "Release/Acquire",
+            Model::SeqCst => "SeqCst",
+        })
+    }
+}
+
+// Whether two threads can be seen to overlap here at all
+pub fn observable() -> bool {
+    thread::available_parallelism().is_ok_and(|cpus| cpus.get() > 1)
+}
+
+// The hardware promises more than the language on this target
+pub fn total_store_order() -> bool {
+    cfg!(any(target_arch = "x86", target_arch = "x86_64"))
+}
+
+#[derive(Debug)]
+pub struct MessagePassing {
+    pub messages: usize,
+    pub samples: usize,    // flag loads the reader made
+    pub stale: usize,      // of those, followed by older data than the flag announced
+    pub writer: Duration,  // to publish every message
+}
+
+impl MessagePassing {
+    pub fn messages_per_ms(&self) -> f64 {
+        self.messages as f64 / (self.writer.as_secs_f64() * 1000.0).max(f64::EPSILON)
+    }
+}
+
+fn join<T>(worker: Worker<'static, T>) -> T {
+    worker.join().unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
+
+// Spin a little between looks, then give the CPU away: on one core the
+// thread being waited for cannot run otherwise
+fn pause(spins: usize) {
+    if spins.is_multiple_of(64) {
+        thread::yield_now();
+    } else {
+        hint::spin_loop();
+    }
+}
+
+// Waits for `ready` while the other thread can still get there. False if
+// the other thread was never started
+fn spin_until(ready: impl Fn() -> bool, abandon: &AtomicBool) -> bool {
+    let mut spins = 0;
+    while !ready() {
+        if abandon.load(Ordering::Relaxed) {
+            return false;
+        }
+        spins += 1;
+        pause(spins);
+    }
+    true
+}
+
+// The writer publishes 1..=messages: each to `data`, then to `flag`. The
+// reader samples flag and then data until it sees the last one; data
+// older than the flag is a stale read
+pub fn message_passing(model: Model, messages: usize) -> Result<MessagePassing, SpawnError> {
+    let (data, flag) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+    let writer = {
+        let (data, flag) = (Arc::clone(&data), Arc::clone(&flag));
+        spawn_policy::spawn_required(move || {
+            let start = Instant::now();
+            for message in 1..=messages {
+                data.store(message, Ordering::Relaxed);
+                flag.store(message, model.store());
+            }
+            start.elapsed()
+        })?
+    };
+    // Started second, so it never waits for a writer that is not running
+    let reader = spawn_policy::spawn_required(move || {
+        let (mut samples, mut stale) = (0, 0);
+        loop {
+            let announced = flag.load(model.load());
+            samples += 1;
+            if data.load(Ordering::Relaxed) < announced {
+                stale += 1;
+            }
+            if announced == messages {
+                return (samples, stale);
+            }
+            pause(samples);
+        }
+    });
+    let writer = join(writer);
+    let (samples, stale) = join(reader?);
+    Ok(MessagePassing { messages, samples, stale, writer })
+}
+
+#[derive(Debug)]
+pub struct StoreBuffering {
+    pub rounds: usize,
+    pub both_missed: usize,  // rounds where neither load saw the other thread's store
+    pub elapsed: Duration,
+}
+
+impl StoreBuffering {
+    pub fn rounds_per_ms(&self) -> f64 {
+        self.rounds as f64 / (self.elapsed.as_secs_f64() * 1000.0).max(f64::EPSILON)
+    }
+}
+
+// Two threads run `rounds` rounds in step. In round r one stores r to x
+// and loads y, the other stores r to y and loads x; a load below r missed
+// the other thread's store. Each finishes a round before either starts
+// the next
+pub fn store_buffering(model: Model, rounds: usize) -> Result<StoreBuffering, SpawnError> {
+    let (x, y) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+    let done = [Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0))];  // rounds each side finished
+    let abandon = Arc::new(AtomicBool::new(false));
+    let side = |mine: &Arc<AtomicUsize>, theirs: &Arc<AtomicUsize>, which: usize| {
+        let (mine, theirs, abandon) = (Arc::clone(mine), Arc::clone(theirs), Arc::clone(&abandon));
+        let (finished, other) = (Arc::clone(&done[which]), Arc::clone(&done[1 - which]));
+        move || {
+            let mut seen = Vec::with_capacity(rounds);
+            for round in 1..=rounds {
+                if !spin_until(|| other.load(Ordering::Acquire) >= round - 1, &abandon) {
+                    break;
+                }
+                mine.store(round, model.store());
+                seen.push(theirs.load(model.load()));
+                finished.store(round, Ordering::Release);
+            }
+            seen
+        }
+    };
+
+    let start = Instant::now();
+    let first = spawn_policy::spawn_required(side(&x, &y, 0))?;
+    let second = spawn_policy::spawn_required(side(&y, &x, 1));
+    if second.is_err() {
+        abandon.store(true, Ordering::Relaxed);
+    }
+    let first = join(first);
+    let second = join(second?);
+    let elapsed = start.elapsed();
+    let both_missed = first.iter().zip(&second).zip(1..).filter(|((&a, &b), round)| a < *round && b < *round).count();
+    Ok(StoreBuffering { rounds, both_missed, elapsed })
+}
@@ -0,0 +1,208 @@
+/*!
+ * Panic Strategies: Unwind or Abort
+ *
+ * What a panic does is chosen per build, by the profile's `panic` key:
+ *
+ *   unwind   the default. The panicking thread's stack is unwound and
+ *            every destructor on it runs: guards unlock (poisoning their
+ *            Mutex), buffers flush, and catch_unwind or a JoinHandle can
+ *            stop the panic there and carry on. The binary carries the
+ *            unwind tables and landing pads that make this possible
+ *   abort    the process ends at the panic, every thread at once, with
+ *            SIGABRT. No destructor runs and nothing can catch it. The
+ *            binary is smaller, but the only isolation left is the
+ *            process boundary
+ *
+ * This crate's `release-abort` profile is the release build with
+ * `panic = "abort"`. The harness adapts to the build it finds itself in:
+ * where panics abort, a full run starts each section in its own child
+ * process, so a panic ends that child and the run goes on, and a section
+ * declared `needs_unwind` (one whose point is a panic caught in-process)
+ * says it is skipped instead of taking the process down.
+ */
+
+use std::env;
+use std::fs;
+use std::hint::black_box;
+use std::panic;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::demo_report::{Outcome, Section, Verdict};
+use crate::env_config::EnvConfig;
+use crate::event_log;
+use crate::messages::{self, Message};
+use crate::sandbox::{self, Exit, Isolated};
+use crate::verify::DemoPanic;
+
+// Whether this build unwinds on panic; false in a panic = "abort" build
+pub const UNWINDS: bool = cfg!(panic = "unwind");
+
+// The two profiles a size comparison looks for, and their strategies
+pub const PROFILES: [(&str, &str); 2] = [("release", "unwind"), ("release-abort", "abort")];
+
+pub fn name() -> &'static str {
+    if UNWINDS { "unwind" } else { "abort" }
+}
+
+// What a needs_unwind section returns where panics abort: a skipped
+// Outcome, which checked nothing and so does not pass. The event log
+// records the skip, for the runner's report
+pub fn skip(demo: &'static str) -> Outcome {
+    say!("{}", messages::text(&Message::SkippedNeedsUnwind { demo }));
+    event_log::demo_skipped(demo, "needs unwinding; this build aborts on panic");
+    Outcome { name: demo, verdicts: Vec::new(), skipped: true }
+}
+
+// A full run's section where panics abort: in a child process with the
+// same settings, which reports its verdicts back. A child that dies
+// (aborts on a panic, or overruns its hard budget) comes back as a
+// DemoPanic, and only that child has ended
+pub fn run_in_child(section: &Section, config: &EnvConfig) -> Result<Outcome, DemoPanic> {
+    let mut command = Command::new(env::current_exe().unwrap_or_default());
+    command.args(["sandbox", "--child", "outcome", section.name]);
+    config.pass_to(&mut command);
+    let child = sandbox::run_command(&mut command, config.budget().hard + sandbox::GRACE, true);
+    if child.exit != Exit::Passed {
+        return Err(DemoPanic { demo: section.name.to_string(), message: child.exit.to_string() });
+    }
+    let verdicts = child
+        .reported
+        .iter()
+        .filter_map(|line| {
+            let (verdict, invariant) = line.split_once('\t')?;
+            let invariant = section.invariants.iter().find(|declared| **declared == invariant)?;
+            let verdict = match verdict {
+                "Held" => Verdict::Held,
+                "Violated" => Verdict::Violated,
+                _ => Verdict::NotChecked,
+            };
+            Some((*invariant, verdict))
+        })
+        .collect();
+    Ok(Outcome { name: section.name, verdicts, skipped: false })
+}
+
+// Kept from being folded into the loop around it
+fn step(x: u64) -> u64 {
+    black_box(x).wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407)
+}
+
+// Time for `calls` calls made directly, and for the same calls each
+// wrapped in catch_unwind, and whether both summed to the same result
+pub fn catch_cost(calls: u64) -> (Duration, Duration, bool) {
+    let start = Instant::now();
+    let direct = (0..calls).fold(0u64, |sum, i| sum.wrapping_add(step(i)));
+    let direct_time = start.elapsed();
+    let start = Instant::now();
+    let caught = (0..calls).fold(0u64, |sum, i| sum.wrapping_add(panic::catch_unwind(|| step(i)).unwrap_or(0)));
+    (direct_time, start.elapsed(), direct == caught)
+}
+
+// Average time for a panic that catch_unwind stops, with the panic hook
+// silenced meanwhile; None where panics abort, as the first would end
+// the process
+pub fn caught_panic_cost(panics: u32) -> Option<Duration> {
+    if !UNWINDS {
+        return None;
+    }
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let start = Instant::now();
+    let caught = (0..panics).filter(|&i| panic::catch_unwind(|| if black_box(i) < u32::MAX { panic!("caught") }).is_err()).count();
+    let elapsed = start.elapsed();
+    panic::set_hook(previous_hook);
+    (caught == panics as usize).then(|| elapsed / panics.max(1))
+}
+
+// A thread that panicked holding a Mutex guard and a value with a Drop
+#[derive(Debug)]
+pub struct Unwound {
+    pub dropped: bool,   // the value's Drop ran during the unwind
+    pub poisoned: bool,  // the guard's Drop poisoned the Mutex
+    pub joined: bool,    // the spawner got the panic back from join()
+}
+
+// Sets its flag when dropped
+struct Flag<'a>(&'a Mutex<bool>);
+
+impl Drop for Flag<'_> {
+    fn drop(&mut self) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = true;
+    }
+}
+
+// What unwinding leaves behind; None where panics abort
+pub fn unwound_thread() -> Option<Unwound> {
+    if !UNWINDS {
+        return None;
+    }
+    let (dropped, shared) = (Mutex::new(false), Mutex::new(0u32));
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let joined = thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                let _flag = Flag(&dropped);
+                let mut guard = shared.lock().unwrap_or_else(PoisonError::into_inner);
+                *guard += 1;
+                panic!("worker failed halfway");
+            })
+            .join()
+            .is_err()
+    });
+    panic::set_hook(previous_hook);
+    let dropped = *dropped.lock().unwrap_or_else(PoisonError::into_inner);
+    Some(Unwound { dropped, poisoned: shared.is_poisoned(), joined })
+}
+
+// The size of this binary on disk
+pub fn own_size() -> Option<u64> {
+    Some(fs::metadata(env::current_exe().ok()?).ok()?.len())
+}
+
+// One profile's build of this binary, if it has been built
+#[derive(Debug)]
+pub struct Build {
+    pub profile: &'static str,
+    pub strategy: &'static str,
+    pub size: u64,        // bytes on disk
+    pub probe: Isolated,  // how its panic probe ended
+}
+
+impl Build {
+    // Whether the probe ended as the build's strategy says a panic does
+    pub fn probe_as_expected(&self) -> bool {
+        match self.strategy {
+            "unwind" => self.probe.exit == Exit::Failed(sandbox::PANIC_EXIT_CODE),
+            // SIGABRT on Unix; Windows ends an aborted process with a status code
+            _ => matches!(self.probe.exit, Exit::Signaled(_) | Exit::Failed(_))
+                && self.probe.exit != Exit::Failed(sandbox::PANIC_EXIT_CODE),
+        }
+    }
+}
+
+// The release and release-abort builds next to this one's target
+// directory, each with its size and its panic probe run
+pub fn builds() -> Vec<Build> {
+    let Some(exe) = env::current_exe().ok() else { return Vec::new() };
+    let (Some(file), Some(target)) = (exe.file_name(), exe.parent().and_then(|profile| profile.parent())) else {
+        return Vec::new();
+    };
+    PROFILES
+        .iter()
+        .filter_map(|&(profile, strategy)| {
+            let path: PathBuf = target.join(profile).join(file);
+            let size = fs::metadata(&path).ok()?.len();
+            let probe = sandbox::run_command(
+                Command::new(&path).args(["sandbox", "--child", "probe", "panic"]),
+                sandbox::PROBE_TIMEOUT,
+                false,
+            );
+            Some(Build { profile, strategy, size, probe })
+        })
+        .collect()
+}
@@ -0,0 +1,113 @@
+/*!
+ * Persistent List
+ *
+ * An immutable singly linked list with structural sharing: pushing to the
+ * front creates one new node that points at the existing list, which is
+ * left untouched. Every version stays valid, and versions share their
+ * common suffix through Arc - so threads can each extend the same base
+ * list without locks and without copying it.
+ */
+
+use std::sync::Arc;
+
+#[derive(Debug)]
+struct Node<T> {
+    value: T,
+    next: Option<Arc<Node<T>>>,
+}
+
+#[derive(Debug)]
+pub struct PersistentList<T> {
+    head: Option<Arc<Node<T>>>,
+    len: usize,
+}
+
+impl<T> PersistentList<T> {
+    pub fn new() -> Self {
+        PersistentList { head: None, len: 0 }
+    }
+
+    // A new version with `value` in front; `self` is unchanged and shared
+    pub fn push_front(&self, value: T) -> Self {
+        PersistentList {
+            head: Some(Arc::new(Node { value, next: self.head.clone() })),
+            len: self.len + 1,
+        }
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.head.as_ref().map(|node| &node.value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { next: self.head.as_deref() }
+    }
+
+    // True if `suffix` is literally the same nodes at the end of this list
+    // (shared memory, not just equal values)
+    pub fn shares_suffix(&self, suffix: &PersistentList<T>) -> bool {
+        if suffix.len > self.len {
+            return false;
+        }
+        let mut node = self.head.as_ref();
+        for _ in 0..self.len - suffix.len {
+            node = node.and_then(|current| current.next.as_ref());
+        }
+        match (node, suffix.head.as_ref()) {
+            (Some(ours), Some(theirs)) => Arc::ptr_eq(ours, theirs),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<T> Default for PersistentList<T> {
+    fn default() -> Self {
+        PersistentList::new()
+    }
+}
+
+// O(1): a new handle to the same nodes
+impl<T> Clone for PersistentList<T> {
+    fn clone(&self) -> Self {
+        PersistentList { head: self.head.clone(), len: self.len }
+    }
+}
+
+// Unlink nodes one at a time: the default recursive drop would overflow
+// the stack on a long list. Stops at the first node another version uses.
+impl<T> Drop for PersistentList<T> {
+    fn drop(&mut self) {
+        let mut next = self.head.take();
+        while let Some(node) = next {
+            match Arc::try_unwrap(node) {
+                Ok(mut node) => next = node.next.take(),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Iter<'a, T> {
+    next: Option<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.next.map(|node| {
+            self.next = node.next.as_deref();
+            &node.value
+        })
+    }
+}
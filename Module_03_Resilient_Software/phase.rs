@@ -0,0 +1,278 @@
+/*!
+ * Typed Phases
+ *
+ * A multi-stage thread workflow moves through Setup -> Running ->
+ * Draining -> Finished, and each operation only makes sense in some of
+ * those phases. Here the phase is a type parameter instead of a runtime
+ * flag: `submit` only exists on a Running pool, and `drain` consumes the
+ * Running pool and returns a Draining one. Submitting work after draining
+ * started is therefore a compile error rather than a lost job.
+ *
+ * The snippets in tests/compile_fail/ prove it; `cargo test` checks that
+ * each one is rejected with the error it expects.
+ */
+
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+// Only the four phases below exist; no one else can add one
+pub trait Phase: sealed::Sealed {
+    const NAME: &'static str;
+}
+
+#[derive(Debug)]
+pub struct Setup;
+#[derive(Debug)]
+pub struct Running;
+#[derive(Debug)]
+pub struct Draining;
+#[derive(Debug)]
+pub struct Finished;
+
+impl sealed::Sealed for Setup {}
+impl sealed::Sealed for Running {}
+impl sealed::Sealed for Draining {}
+impl sealed::Sealed for Finished {}
+
+impl Phase for Setup {
+    const NAME: &'static str = "Setup";
+}
+impl Phase for Running {
+    const NAME: &'static str = "Running";
+}
+impl Phase for Draining {
+    const NAME: &'static str = "Draining";
+}
+impl Phase for Finished {
+    const NAME: &'static str = "Finished";
+}
+
+type Job<T> = Box<dyn FnOnce() -> T + Send>;
+
+// A fixed set of worker threads taking jobs from one shared queue
+pub struct WorkerPool<P: Phase, T> {
+    workers: usize,
+    jobs: Option<Sender<Job<T>>>,       // closed when draining starts
+    handles: Vec<JoinHandle<Vec<T>>>,   // each worker returns its results
+    pending: Arc<AtomicUsize>,          // submitted but not yet finished
+    results: Vec<T>,
+    phase: PhantomData<P>,
+}
+
+impl<P: Phase, T> WorkerPool<P, T> {
+    // Swap the phase marker; everything else carries over
+    fn into_phase<Q: Phase>(self) -> WorkerPool<Q, T> {
+        WorkerPool {
+            workers: self.workers,
+            jobs: self.jobs,
+            handles: self.handles,
+            pending: self.pending,
+            results: self.results,
+            phase: PhantomData,
+        }
+    }
+
+    pub fn phase(&self) -> &'static str {
+        P::NAME
+    }
+}
+
+impl<T: Send + 'static> WorkerPool<Setup, T> {
+    pub fn new() -> Self {
+        WorkerPool {
+            workers: 1,
+            jobs: None,
+            handles: Vec::new(),
+            pending: Arc::new(AtomicUsize::new(0)),
+            results: Vec::new(),
+            phase: PhantomData,
+        }
+    }
+
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    pub fn start(mut self) -> WorkerPool<Running, T> {
+        let (sender, receiver) = mpsc::channel::<Job<T>>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        self.handles = (0..self.workers)
+            .map(|_| {
+                let (receiver, pending) = (Arc::clone(&receiver), Arc::clone(&self.pending));
+                thread::spawn(move || {
+                    let mut results = Vec::new();
+                    loop {
+                        // The guard is released before the job runs
+                        let job = receiver.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).recv();
+                        match job {
+                            Ok(job) => {
+                                results.push(job());
+                                pending.fetch_sub(1, Ordering::SeqCst);
+                            }
+                            Err(_) => return results,  // queue closed and empty
+                        }
+                    }
+                })
+            })
+            .collect();
+        self.jobs = Some(sender);
+        self.into_phase()
+    }
+}
+
+impl<T: Send + 'static> Default for WorkerPool<Setup, T> {
+    fn default() -> Self {
+        WorkerPool::new()
+    }
+}
+
+impl<T> WorkerPool<Running, T> {
+    pub fn submit(&self, job: impl FnOnce() -> T + Send + 'static) {
+        if let Some(jobs) = &self.jobs {
+            self.pending.fetch_add(1, Ordering::SeqCst);
+            if jobs.send(Box::new(job)).is_err() {
+                self.pending.fetch_sub(1, Ordering::SeqCst);  // every worker is gone
+            }
+        }
+    }
+
+    // Close the queue: queued jobs still run, new ones cannot be submitted
+    pub fn drain(mut self) -> WorkerPool<Draining, T> {
+        self.jobs = None;
+        self.into_phase()
+    }
+}
+
+impl<T> WorkerPool<Draining, T> {
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::SeqCst)
+    }
+
+    // Wait for the workers; a worker that panicked loses its results
+    pub fn finish(mut self) -> WorkerPool<Finished, T> {
+        for handle in std::mem::take(&mut self.handles) {
+            if let Ok(results) = handle.join() {
+                self.results.extend(results);
+            }
+        }
+        self.into_phase()
+    }
+}
+
+impl<T> WorkerPool<Finished, T> {
+    pub fn results(&self) -> &[T] {
+        &self.results
+    }
+}
+
+type Stage<T> = Box<dyn Fn(T) -> T + Send>;
+
+// Items flow through a chain of stages, one thread per stage
+pub struct Pipeline<P: Phase, T> {
+    stages: Vec<Stage<T>>,
+    input: Option<Sender<T>>,     // closed when draining starts
+    output: Option<Receiver<T>>,
+    handles: Vec<JoinHandle<()>>,
+    outputs: Vec<T>,
+    phase: PhantomData<P>,
+}
+
+impl<P: Phase, T> Pipeline<P, T> {
+    fn into_phase<Q: Phase>(self) -> Pipeline<Q, T> {
+        Pipeline {
+            stages: self.stages,
+            input: self.input,
+            output: self.output,
+            handles: self.handles,
+            outputs: self.outputs,
+            phase: PhantomData,
+        }
+    }
+
+    pub fn phase(&self) -> &'static str {
+        P::NAME
+    }
+}
+
+impl<T: Send + 'static> Pipeline<Setup, T> {
+    pub fn new() -> Self {
+        Pipeline {
+            stages: Vec::new(),
+            input: None,
+            output: None,
+            handles: Vec::new(),
+            outputs: Vec::new(),
+            phase: PhantomData,
+        }
+    }
+
+    pub fn stage(mut self, stage: impl Fn(T) -> T + Send + 'static) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    pub fn start(mut self) -> Pipeline<Running, T> {
+        let (input, mut upstream) = mpsc::channel::<T>();
+        for stage in std::mem::take(&mut self.stages) {
+            let (sender, receiver) = mpsc::channel();
+            self.handles.push(thread::spawn(move || {
+                for item in upstream {
+                    if sender.send(stage(item)).is_err() {
+                        return;
+                    }
+                }
+            }));
+            upstream = receiver;
+        }
+        self.input = Some(input);
+        self.output = Some(upstream);
+        self.into_phase()
+    }
+}
+
+impl<T: Send + 'static> Default for Pipeline<Setup, T> {
+    fn default() -> Self {
+        Pipeline::new()
+    }
+}
+
+impl<T> Pipeline<Running, T> {
+    pub fn send(&self, item: T) {
+        if let Some(input) = &self.input {
+            let _ = input.send(item);  // Fails only if a stage panicked
+        }
+    }
+
+    // Close the input: items already sent still flow to the end
+    pub fn drain(mut self) -> Pipeline<Draining, T> {
+        self.input = None;
+        self.into_phase()
+    }
+}
+
+impl<T> Pipeline<Draining, T> {
+    // Collect every output, then wait for the stage threads
+    pub fn finish(mut self) -> Pipeline<Finished, T> {
+        if let Some(output) = self.output.take() {
+            self.outputs.extend(output);
+        }
+        for handle in std::mem::take(&mut self.handles) {
+            let _ = handle.join();
+        }
+        self.into_phase()
+    }
+}
+
+impl<T> Pipeline<Finished, T> {
+    pub fn outputs(&self) -> &[T] {
+        &self.outputs
+    }
+}
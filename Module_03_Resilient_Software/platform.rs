@@ -0,0 +1,202 @@
+/*!
+ * Platform Layer - interrupts and the console on Unix and Windows
+ *
+ * The two things the demos ask of the OS that Unix and Windows do
+ * differently live here, so no other module needs a cfg:
+ *
+ *   Ctrl+C     Unix delivers SIGINT to a handler set with sigaction;
+ *              Windows calls a console control handler, registered with
+ *              SetConsoleCtrlHandler, on a thread of its own.
+ *   escapes    every Unix terminal understands ANSI colors and cursor
+ *              moves; a Windows console only once virtual terminal
+ *              processing is switched on for it (Windows 10 and later).
+ *              Where that fails, the console gets plain text.
+ *
+ * Interrupts are handled in two steps. The first Ctrl+C only raises a
+ * flag: the running section finishes, and the run stops before the next
+ * one and reports what it did. The second ends the process at once with
+ * INTERRUPTED_EXIT_CODE. A Unix signal handler may only do
+ * async-signal-safe work, so ours does nothing else.
+ */
+
+use std::env;
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+// 128 + SIGINT, what shells report for a run ended by Ctrl+C
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+static INTERRUPTS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+// Route Ctrl+C to interrupted() from now on. False if the OS refused
+// the handler; Ctrl+C then ends the process as it always does
+pub fn handle_interrupts() -> bool {
+    static INSTALLED: OnceLock<bool> = OnceLock::new();
+    *INSTALLED.get_or_init(imp::install_interrupt_handler)
+}
+
+// Whether Ctrl+C was pressed since handle_interrupts()
+pub fn interrupted() -> bool {
+    INTERRUPTS.load(Ordering::SeqCst) > 0
+}
+
+// Deliver a Ctrl+C to this process alone, as the sandbox probe does
+pub fn raise_interrupt() {
+    imp::raise_interrupt();
+}
+
+// What the handler does on each Ctrl+C; false: end the process now
+#[cfg_attr(not(any(unix, windows)), allow(dead_code))]
+fn interrupt() -> bool {
+    INTERRUPTS.fetch_add(1, Ordering::SeqCst) == 0
+}
+
+// Whether `stream` is a terminal that draws escape codes rather than
+// printing them: not redirected, TERM not "dumb", and on Windows a
+// console that took virtual terminal processing. Asked once per stream
+pub fn ansi_terminal(stream: Stream) -> bool {
+    static STDOUT: OnceLock<bool> = OnceLock::new();
+    static STDERR: OnceLock<bool> = OnceLock::new();
+    let (cached, terminal) = match stream {
+        Stream::Stdout => (&STDOUT, io::stdout().is_terminal()),
+        Stream::Stderr => (&STDERR, io::stderr().is_terminal()),
+    };
+    *cached.get_or_init(|| {
+        let dumb = env::var("TERM").is_ok_and(|term| term == "dumb");
+        terminal && !dumb && imp::enable_ansi(stream)
+    })
+}
+
+// The platform this build handles interrupts and escapes for
+pub fn name() -> &'static str {
+    imp::NAME
+}
+
+#[cfg(unix)]
+mod imp {
+    use super::{interrupt, Stream, INTERRUPTED_EXIT_CODE};
+
+    pub const NAME: &str = "unix (sigaction, ANSI)";
+
+    extern "C" fn on_sigint(_signal: libc::c_int) {
+        if !interrupt() {
+            // The second Ctrl+C: _exit is async-signal-safe, process::exit is not
+            // SAFETY: ends the process without touching any of its state
+            unsafe { libc::_exit(INTERRUPTED_EXIT_CODE) };
+        }
+    }
+
+    pub fn install_interrupt_handler() -> bool {
+        let handler: extern "C" fn(libc::c_int) = on_sigint;
+        // SAFETY: a zeroed sigaction is valid, and the handler only
+        // touches an atomic (or ends the process)
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handler as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;  // reads and sleeps carry on
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut()) == 0
+        }
+    }
+
+    pub fn raise_interrupt() {
+        // SAFETY: signals only this process
+        unsafe { libc::raise(libc::SIGINT) };
+    }
+
+    pub fn enable_ansi(_stream: Stream) -> bool {
+        true
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::ffi::c_void;
+
+    use super::{interrupt, Stream, INTERRUPTED_EXIT_CODE};
+
+    pub const NAME: &str = "windows (console control handler, virtual terminal)";
+
+    type Bool = i32;
+    type Handle = *mut c_void;
+
+    const CTRL_C_EVENT: u32 = 0;
+    const CTRL_BREAK_EVENT: u32 = 1;
+    const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+    const STD_ERROR_HANDLE: u32 = -12i32 as u32;
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x0004;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn SetConsoleCtrlHandler(handler: Option<unsafe extern "system" fn(u32) -> Bool>, add: Bool) -> Bool;
+        fn GetStdHandle(which: u32) -> Handle;
+        fn GetConsoleMode(console: Handle, mode: *mut u32) -> Bool;
+        fn SetConsoleMode(console: Handle, mode: u32) -> Bool;
+    }
+
+    // Runs on a thread Windows starts for it, so unlike a Unix signal
+    // handler it may exit the process itself. FALSE passes the event on
+    // to the default handler
+    unsafe extern "system" fn on_ctrl(event: u32) -> Bool {
+        match event {
+            CTRL_C_EVENT | CTRL_BREAK_EVENT if !interrupt() => std::process::exit(INTERRUPTED_EXIT_CODE),
+            CTRL_C_EVENT | CTRL_BREAK_EVENT => 1,
+            _ => 0,  // closing the window, logging off: no time to finish a section
+        }
+    }
+
+    pub fn install_interrupt_handler() -> bool {
+        // SAFETY: on_ctrl has the signature the handler list expects
+        unsafe { SetConsoleCtrlHandler(Some(on_ctrl), 1) != 0 }
+    }
+
+    // GenerateConsoleCtrlEvent would reach every process on the console,
+    // the parent too, so call the handler the way Windows would
+    pub fn raise_interrupt() {
+        // SAFETY: on_ctrl only touches an atomic (or ends the process)
+        unsafe { on_ctrl(CTRL_C_EVENT) };
+    }
+
+    // A redirected stream or a pre-Windows 10 console has no mode to set
+    pub fn enable_ansi(stream: Stream) -> bool {
+        let which = match stream {
+            Stream::Stdout => STD_OUTPUT_HANDLE,
+            Stream::Stderr => STD_ERROR_HANDLE,
+        };
+        // SAFETY: the mode is only read and set for this process's own handle
+        unsafe {
+            let console = GetStdHandle(which);
+            let mut mode = 0;
+            if console.is_null() || console as isize == -1 || GetConsoleMode(console, &mut mode) == 0 {
+                return false;
+            }
+            mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0
+                || SetConsoleMode(console, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+        }
+    }
+}
+
+// Anything else: Ctrl+C keeps its default, and escape codes are assumed
+#[cfg(not(any(unix, windows)))]
+mod imp {
+    use super::Stream;
+
+    pub const NAME: &str = "other (default Ctrl+C, ANSI)";
+
+    pub fn install_interrupt_handler() -> bool {
+        false
+    }
+
+    pub fn raise_interrupt() {}
+
+    pub fn enable_ansi(_stream: Stream) -> bool {
+        true
+    }
+}
@@ -0,0 +1,47 @@
+/*!
+ * Demo Plugin ABI
+ *
+ * The contract between thread_safe and a demo plugin built as a separate
+ * shared library. Rust has no stable ABI between separately compiled
+ * crates, so everything that crosses the boundary is #[repr(C)]: plain
+ * integers, NUL-terminated strings and `extern "C"` function pointers.
+ * Both sides include this file (plugins with #[path]), and the version
+ * number changes whenever a struct below does - the host refuses a plugin
+ * built against any other version rather than guess at its layout.
+ *
+ * A plugin exports one function, `safety_demo_plugin`, returning a
+ * pointer to a static PluginDescriptor. The host calls `run` with a
+ * HostApi through which the plugin prints and reports its invariants.
+ * A panic must not unwind out of `run`: catch it and report a failed
+ * invariant instead.
+ */
+
+use std::ffi::c_char;
+
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+// Exported symbol name, NUL-terminated for dlsym
+pub const ENTRY_SYMBOL: &[u8] = b"safety_demo_plugin\0";
+
+pub type EntryFn = unsafe extern "C" fn() -> *const PluginDescriptor;
+
+// What the host lends a running plugin
+#[derive(Debug)]
+#[repr(C)]
+pub struct HostApi {
+    pub abi_version: u32,
+    pub say: extern "C" fn(line: *const c_char),
+    pub check_invariant: extern "C" fn(invariant: *const c_char, passed: bool),
+}
+
+#[derive(Debug)]
+#[repr(C)]
+pub struct PluginDescriptor {
+    pub abi_version: u32,
+    pub name: *const c_char,     // section name, e.g. "sample_plugin"
+    pub summary: *const c_char,  // one line for the plugin listing
+    pub run: Option<extern "C" fn(host: &HostApi)>,
+}
+
+// Descriptors live in statics and only point at static strings
+unsafe impl Sync for PluginDescriptor {}
@@ -0,0 +1,205 @@
+/*!
+ * Demo Plugin Loader
+ *
+ * Loads every shared library in SAFETY_DEMO_PLUGIN_DIR and adds the demo
+ * it describes to the run, after the built-in sections. Only built with
+ * `--features plugins` (Linux, via dlopen).
+ *
+ * Every `unsafe` block of the plugin system is in this file. What crosses
+ * it is checked before anything else sees it: the entry symbol must
+ * exist, the ABI version must match, the name and summary must be valid
+ * UTF-8, and `run` must be present. The rest of the program only sees a
+ * LoadedPlugin, whose library stays open for as long as the LoadedPlugin
+ * lives, so its `run` pointer can never dangle.
+ */
+
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::fmt;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use crate::event_log;
+use crate::plugin_abi::{EntryFn, HostApi, PluginDescriptor, ENTRY_SYMBOL, PLUGIN_ABI_VERSION};
+
+#[derive(Debug)]
+pub enum PluginError {
+    Open { path: PathBuf, reason: String },
+    MissingEntry { path: PathBuf },
+    AbiMismatch { path: PathBuf, found: u32 },
+    BadDescriptor { path: PathBuf, reason: &'static str },
+}
+
+impl fmt::Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PluginError::Open { path, reason } => write!(f, "{}: cannot load ({})", path.display(), reason),
+            PluginError::MissingEntry { path } => write!(f, "{}: no safety_demo_plugin symbol", path.display()),
+            PluginError::AbiMismatch { path, found } => write!(
+                f,
+                "{}: built for plugin ABI {}, this host speaks {}",
+                path.display(),
+                found,
+                PLUGIN_ABI_VERSION
+            ),
+            PluginError::BadDescriptor { path, reason } => write!(f, "{}: {}", path.display(), reason),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LoadedPlugin {
+    pub name: String,
+    pub summary: String,
+    pub path: PathBuf,
+    run: extern "C" fn(host: &HostApi),
+    handle: *mut c_void,  // dlopen handle, closed on drop
+}
+
+// SAFETY: the handle is only touched by dlclose on drop, and the dl*
+// functions are thread-safe; `run` is a plain function pointer
+unsafe impl Send for LoadedPlugin {}
+unsafe impl Sync for LoadedPlugin {}
+
+impl LoadedPlugin {
+    pub fn run(&self) {
+        let host = HostApi { abi_version: PLUGIN_ABI_VERSION, say: host_say, check_invariant: host_check_invariant };
+        (self.run)(&host);
+    }
+}
+
+impl Drop for LoadedPlugin {
+    fn drop(&mut self) {
+        // SAFETY: the handle came from dlopen and is closed once; `run`
+        // is private and goes away with self
+        unsafe {
+            libc::dlclose(self.handle);
+        }
+    }
+}
+
+// Every *.so in `dir`, in name order, without loading any of them
+pub fn plugin_paths(dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|extension| extension == "so"))
+                .collect()
+        })
+        .unwrap_or_default();
+    paths.sort();
+    paths
+}
+
+// Load each *.so in `dir`, in name order; a broken plugin is reported and
+// skipped, the rest still load
+pub fn load_dir(dir: &Path) -> (Vec<LoadedPlugin>, Vec<PluginError>) {
+    if let Err(error) = fs::read_dir(dir) {
+        return (Vec::new(), vec![PluginError::Open { path: dir.to_path_buf(), reason: error.to_string() }]);
+    }
+
+    let (mut loaded, mut errors) = (Vec::new(), Vec::new());
+    for path in plugin_paths(dir) {
+        match load_file(&path) {
+            Ok(plugin) => loaded.push(plugin),
+            Err(error) => errors.push(error),
+        }
+    }
+    (loaded, errors)
+}
+
+pub fn load_file(path: &Path) -> Result<LoadedPlugin, PluginError> {
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| PluginError::Open { path: path.to_path_buf(), reason: "path contains NUL".to_string() })?;
+
+    // SAFETY: both strings are NUL-terminated; a failed dlopen returns null
+    // and dlerror describes it
+    let handle = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+    if handle.is_null() {
+        return Err(PluginError::Open { path: path.to_path_buf(), reason: last_dl_error() });
+    }
+
+    // From here the handle is closed on every error path by this guard
+    let mut plugin = LoadedPlugin {
+        name: String::new(),
+        summary: String::new(),
+        path: path.to_path_buf(),
+        run: noop,
+        handle,
+    };
+
+    // SAFETY: ENTRY_SYMBOL is NUL-terminated; the symbol's type is fixed by
+    // the ABI, which a matching version number vouches for below
+    let descriptor = unsafe {
+        let symbol = libc::dlsym(handle, ENTRY_SYMBOL.as_ptr().cast());
+        if symbol.is_null() {
+            return Err(PluginError::MissingEntry { path: path.to_path_buf() });
+        }
+        let entry: EntryFn = std::mem::transmute::<*mut c_void, EntryFn>(symbol);
+        entry()
+    };
+    let bad = |reason| PluginError::BadDescriptor { path: path.to_path_buf(), reason };
+
+    // SAFETY: a non-null descriptor points to a static in the library,
+    // which stays loaded while `plugin` holds the handle
+    let descriptor: &PluginDescriptor = unsafe { descriptor.as_ref() }.ok_or_else(|| bad("null descriptor"))?;
+    if descriptor.abi_version != PLUGIN_ABI_VERSION {
+        return Err(PluginError::AbiMismatch { path: path.to_path_buf(), found: descriptor.abi_version });
+    }
+    plugin.name = static_str(descriptor.name).ok_or_else(|| bad("name is null or not UTF-8"))?;
+    plugin.summary = static_str(descriptor.summary).ok_or_else(|| bad("summary is null or not UTF-8"))?;
+    if plugin.name.is_empty() {
+        return Err(bad("empty name"));
+    }
+    plugin.run = descriptor.run.ok_or_else(|| bad("no run function"))?;
+    Ok(plugin)
+}
+
+// Copy a C string out of the library
+fn static_str(pointer: *const c_char) -> Option<String> {
+    if pointer.is_null() {
+        return None;
+    }
+    // SAFETY: non-null and, per the ABI, NUL-terminated
+    let text = unsafe { CStr::from_ptr(pointer) };
+    text.to_str().ok().map(str::to_string)
+}
+
+fn last_dl_error() -> String {
+    // SAFETY: dlerror returns null or a NUL-terminated message
+    static_str(unsafe { libc::dlerror() }).unwrap_or_else(|| "unknown error".to_string())
+}
+
+extern "C" fn noop(_host: &HostApi) {}
+
+extern "C" fn host_say(line: *const c_char) {
+    if let Some(line) = static_str(line) {
+        event_log::say(line);
+    }
+}
+
+extern "C" fn host_check_invariant(invariant: *const c_char, passed: bool) {
+    let invariant = static_str(invariant).unwrap_or_else(|| "(unnamed plugin invariant)".to_string());
+    event_log::check_invariant(&invariant, passed);
+}
+
+// `plugins [dir]`: list what would load, and why anything would not
+pub fn plugins_command(args: &[String], configured: Option<&Path>) -> i32 {
+    let dir = match args.first().map(PathBuf::from).or_else(|| configured.map(Path::to_path_buf)) {
+        Some(dir) => dir,
+        None => {
+            eprintln!("{}", narration!("plugins.usage"));
+            return 2;
+        }
+    };
+    let (loaded, errors) = load_dir(&dir);
+    println!("{}", narration!("plugins.heading", version = PLUGIN_ABI_VERSION, dir = dir.display()));
+    for plugin in &loaded {
+        println!("  {:<20} {} ({})", plugin.name, plugin.summary, plugin.path.display());
+    }
+    for error in &errors {
+        println!("{}", narration!("plugins.rejected", error));
+    }
+    if errors.is_empty() { 0 } else { 1 }
+}
@@ -0,0 +1,246 @@
+/*!
+ * Progress Reporting
+ *
+ * A multi-minute stress run or benchmark that prints nothing looks like a
+ * hang, and students Ctrl+C it. A Progress draws a live status on stderr
+ * while the work runs:
+ *
+ *   stress     [###########-------------------]  37%  1107/3000  ETA 0:42  mutex
+ *     thread 0 [##############----------------]  48%
+ *     thread 1 [#########---------------------]  31%
+ *
+ * The top line counts steps against a step or time goal. Optional lanes
+ * count each worker thread's own steps toward a per-thread goal (and add
+ * them to the top line). Each lane sits on its own cache line, so a worker
+ * reporting progress does not slow its neighbours.
+ *
+ * A separate painter thread redraws the status and finish() erases it,
+ * so stdout output is left as it was. Lines printed while it runs go
+ * through println() so they land above it.
+ *
+ * Nothing is drawn unless stderr is a terminal that understands the
+ * cursor moves (platform.rs: TERM not "dumb", virtual terminal mode on
+ * Windows); the counters still run, so callers never need to check.
+ */
+
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::atomic64::AtomicU64;
+use crate::platform::{self, Stream};
+
+const BAR_WIDTH: usize = 30;
+const REDRAW: Duration = Duration::from_millis(250);
+const SHOWN_LANES: usize = 8;  // more lanes are summarized on one line
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Goal {
+    Steps(u64),      // done after this many steps
+    Time(Duration),  // done when this much time has passed
+}
+
+// One worker's counter, alone on its cache line
+#[repr(align(64))]
+#[derive(Debug, Default)]
+struct LaneCount(AtomicU64);
+
+#[derive(Debug)]
+struct Shared {
+    label: String,
+    goal: Goal,
+    start: Instant,
+    done: AtomicU64,
+    note: Mutex<String>,
+    lanes: Vec<LaneCount>,
+    lane_goal: u64,
+    stop: AtomicBool,
+    drawn: Mutex<usize>,  // status lines currently on screen
+}
+
+#[derive(Debug)]
+pub struct Progress {
+    shared: Arc<Shared>,
+    painter: Option<thread::JoinHandle<()>>,
+}
+
+// A worker's handle to its own lane; cheap to clone and move into a thread.
+// Its steps also count toward the top line.
+#[derive(Debug, Clone)]
+pub struct Lane {
+    shared: Arc<Shared>,
+    index: usize,
+}
+
+impl Lane {
+    pub fn advance(&self, steps: u64) {
+        self.shared.lanes[self.index].0.fetch_add(steps, Ordering::Relaxed);
+        self.shared.done.fetch_add(steps, Ordering::Relaxed);
+    }
+}
+
+pub fn enabled() -> bool {
+    platform::ansi_terminal(Stream::Stderr)
+}
+
+impl Progress {
+    // Overall progress only
+    pub fn start(label: &str, goal: Goal) -> Progress {
+        Progress::with_lanes(label, goal, 0, 0)
+    }
+
+    // Overall progress plus `lanes` per-thread counters of `lane_goal` steps each
+    pub fn with_lanes(label: &str, goal: Goal, lanes: usize, lane_goal: u64) -> Progress {
+        let shared = Arc::new(Shared {
+            label: label.to_string(),
+            goal,
+            start: Instant::now(),
+            done: AtomicU64::new(0),
+            note: Mutex::new(String::new()),
+            lanes: (0..lanes).map(|_| LaneCount::default()).collect(),
+            lane_goal,
+            stop: AtomicBool::new(false),
+            drawn: Mutex::new(0),
+        });
+        let painter = enabled().then(|| {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || paint(&shared))
+        });
+        Progress { shared, painter }
+    }
+
+    pub fn lane(&self, index: usize) -> Lane {
+        Lane { shared: Arc::clone(&self.shared), index }
+    }
+
+    pub fn tick(&self) {
+        self.shared.done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // Short text shown after the ETA, e.g. the current workload
+    pub fn note(&self, text: &str) {
+        let mut note = self.shared.note.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        note.clear();
+        note.push_str(text);
+    }
+
+    // Print a stdout line above the status
+    pub fn println(&self, line: &str) {
+        let mut drawn = self.shared.drawn.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if self.painter.is_some() {
+            let mut stderr = io::stderr().lock();
+            let _ = stderr.write_all(rewind(*drawn).as_bytes());
+            let _ = stderr.flush();
+            *drawn = 0;
+        }
+        println!("{}", line);
+    }
+
+    // The status as it would be drawn now
+    pub fn lines(&self) -> Vec<String> {
+        self.shared.lines(self.shared.start.elapsed())
+    }
+
+    // Stop drawing and erase the status
+    pub fn finish(mut self) {
+        self.stop_painter();
+    }
+
+    fn stop_painter(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+        if let Some(painter) = self.painter.take() {
+            let _ = painter.join();
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.stop_painter();
+    }
+}
+
+impl Shared {
+    fn lines(&self, elapsed: Duration) -> Vec<String> {
+        let done = self.done.load(Ordering::Relaxed);
+        let (fraction, count, eta) = match self.goal {
+            Goal::Steps(total) => {
+                let fraction = share(done, total);
+                let eta = (done > 0).then(|| elapsed.mul_f64((total.saturating_sub(done)) as f64 / done as f64));
+                (fraction, format!("{}/{}", done, total), eta)
+            }
+            Goal::Time(total) => {
+                let fraction = (elapsed.as_secs_f64() / total.as_secs_f64().max(f64::EPSILON)).min(1.0);
+                (fraction, format!("{} steps", done), Some(total.saturating_sub(elapsed)))
+            }
+        };
+        let eta = eta.map(clock).unwrap_or_else(|| "--:--".to_string());
+        let note = self.note.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut lines = vec![format!(
+            "{:<10} [{}] {:>3}%  {}  ETA {}  {}",
+            self.label, bar(fraction, BAR_WIDTH), (fraction * 100.0) as u32, count, eta, note
+        ).trim_end().to_string()];
+
+        let lanes: Vec<f64> = self.lanes.iter().map(|lane| share(lane.0.load(Ordering::Relaxed), self.lane_goal)).collect();
+        for (index, lane) in lanes.iter().enumerate().take(SHOWN_LANES) {
+            lines.push(format!("  thread {:<2} [{}] {:>3}%", index, bar(*lane, BAR_WIDTH), (lane * 100.0) as u32));
+        }
+        if lanes.len() > SHOWN_LANES {
+            let slowest = lanes.iter().copied().fold(1.0, f64::min);
+            lines.push(format!("  {} more threads, slowest at {}%", lanes.len() - SHOWN_LANES, (slowest * 100.0) as u32));
+        }
+        lines
+    }
+}
+
+fn share(done: u64, total: u64) -> f64 {
+    if total == 0 { 1.0 } else { (done as f64 / total as f64).min(1.0) }
+}
+
+// `width` cells, filled in proportion to `fraction`
+pub fn bar(fraction: f64, width: usize) -> String {
+    let filled = ((fraction.clamp(0.0, 1.0) * width as f64) as usize).min(width);
+    format!("{}{}", "#".repeat(filled), "-".repeat(width - filled))
+}
+
+// m:ss, or h:mm:ss from an hour up
+pub fn clock(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+// Redraw in place until stopped, then erase what was drawn
+fn paint(shared: &Shared) {
+    while !shared.stop.load(Ordering::Relaxed) {
+        draw(shared, true);
+        thread::sleep(REDRAW);
+    }
+    draw(shared, false);
+}
+
+// Replace the status on screen with the current one, or just erase it
+fn draw(shared: &Shared, status: bool) {
+    let mut drawn = shared.drawn.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut frame = rewind(*drawn);
+    *drawn = 0;
+    if status {
+        let lines = shared.lines(shared.start.elapsed());
+        frame.push_str(&lines.join("\n"));
+        *drawn = lines.len();
+    }
+    let mut stderr = io::stderr().lock();
+    let _ = stderr.write_all(frame.as_bytes());
+    let _ = stderr.flush();
+}
+
+// Move to the first of `drawn` status lines and clear to the end of the screen
+fn rewind(drawn: usize) -> String {
+    let up = if drawn > 1 { format!("\x1b[{}A", drawn - 1) } else { String::new() };
+    format!("{}\r\x1b[J", up)
+}
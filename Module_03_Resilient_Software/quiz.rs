@@ -0,0 +1,334 @@
+/*!
+ * Quiz Mode
+ *
+ * Before each demonstration section the student predicts its outcome
+ * (the final counter value, whether a snippet compiles, ...). The section
+ * then runs, the prediction is scored against what actually happened,
+ * and the full score sheet is written to a JSON report at the end -
+ * turning the demos into an assessment tool.
+ */
+
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+use serde::Serialize;
+
+use crate::env_config::EnvConfig;
+use crate::sections::async_tasks::ASYNC_ITEMS;
+use crate::sections::{self, SECTIONS};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(untagged)]
+enum Answer {
+    Number(i64),
+    YesNo(bool),
+}
+
+impl Answer {
+    fn parse(input: &str, expected: Answer) -> Option<Answer> {
+        let input = input.trim().to_lowercase();
+        match expected {
+            Answer::Number(_) => input.parse().ok().map(Answer::Number),
+            Answer::YesNo(_) => match input.as_str() {
+                "y" | "yes" => Some(Answer::YesNo(true)),
+                "n" | "no" => Some(Answer::YesNo(false)),
+                _ => None,
+            },
+        }
+    }
+
+    fn hint(self) -> String {
+        match self {
+            Answer::Number(_) => narration!("quiz.number"),
+            Answer::YesNo(_) => narration!("quiz.yes_no"),
+        }
+    }
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Answer::Number(value) => write!(f, "{}", value),
+            Answer::YesNo(true) => write!(f, "{}", narration!("quiz.yes")),
+            Answer::YesNo(false) => write!(f, "{}", narration!("quiz.no")),
+        }
+    }
+}
+
+struct Question {
+    section: &'static str,
+    prompt: String,
+    expected: Answer,
+}
+
+// One prediction per section; expected values follow the active config
+fn questions(config: &EnvConfig) -> Vec<Question> {
+    let writes = config.writes.get() as i64;
+    vec![
+        Question {
+            section: "counter_safety",
+            prompt: narration!("quiz.counter_safety", threads = config.threads.get(), increments = config.increments.get()),
+            expected: Answer::Number((config.threads.get() * config.increments.get()) as i64),
+        },
+        Question {
+            section: "mutex_safety",
+            prompt: narration!("quiz.mutex_safety", writes),
+            expected: Answer::Number(writes * (writes - 1) / 2),
+        },
+        Question {
+            section: "rwlock_safety",
+            prompt: narration!("quiz.rwlock_safety"),
+            expected: Answer::Number(6),
+        },
+        Question {
+            section: "lazy_init",
+            prompt: narration!("quiz.lazy_init"),
+            expected: Answer::Number(1),
+        },
+        Question {
+            section: "deadlock",
+            prompt: narration!("quiz.deadlock"),
+            expected: Answer::YesNo(true),
+        },
+        Question {
+            section: "send_sync_traits",
+            prompt: narration!("quiz.send_sync_traits"),
+            expected: Answer::YesNo(false),
+        },
+        Question {
+            section: "channel_safety",
+            prompt: narration!("quiz.channel_safety", messages = config.messages.get()),
+            expected: Answer::Number(config.messages.get() as i64),
+        },
+        Question {
+            section: "scoped_threads",
+            prompt: narration!("quiz.scoped_threads"),
+            expected: Answer::YesNo(true),
+        },
+        Question {
+            section: "typed_phases",
+            prompt: narration!("quiz.typed_phases"),
+            expected: Answer::YesNo(false),
+        },
+        Question {
+            section: "async_streams",
+            prompt: narration!("quiz.async_streams", items = ASYNC_ITEMS),
+            expected: Answer::Number((0..ASYNC_ITEMS).filter(|id| id % 7 != 6).count() as i64),
+        },
+        Question {
+            section: "async_mutex",
+            prompt: narration!("quiz.async_mutex"),
+            expected: Answer::YesNo(false),
+        },
+        Question {
+            section: "async_resilience",
+            prompt: narration!("quiz.async_resilience"),
+            expected: Answer::Number(2),
+        },
+        Question {
+            section: "network_partition",
+            prompt: narration!("quiz.network_partition"),
+            expected: Answer::YesNo(false),
+        },
+        Question {
+            section: "crdt_replication",
+            prompt: narration!("quiz.crdt_replication"),
+            expected: Answer::YesNo(true),
+        },
+        Question {
+            section: "delivery_semantics",
+            prompt: narration!("quiz.delivery_semantics"),
+            expected: Answer::Number(2),
+        },
+        Question {
+            section: "atomic_operations",
+            prompt: narration!("quiz.atomic_operations", threads = config.atomic_threads.get()),
+            expected: Answer::Number(config.atomic_threads.get() as i64),
+        },
+        Question {
+            section: "memory_orderings",
+            prompt: narration!("quiz.memory_orderings"),
+            expected: Answer::YesNo(true),
+        },
+        Question {
+            section: "bounded_counter",
+            prompt: narration!("quiz.bounded_counter", threads = config.atomic_threads.get(),
+                               increments = config.increments.get(),
+                               cap = config.atomic_threads.get() * config.increments.get() / 2),
+            expected: Answer::Number((config.atomic_threads.get() * config.increments.get() / 2) as i64),
+        },
+        Question {
+            section: "atomic_store",
+            prompt: narration!("quiz.atomic_store"),
+            expected: Answer::YesNo(false),
+        },
+        Question {
+            section: "bounded_buffer",
+            prompt: narration!("quiz.bounded_buffer"),
+            expected: Answer::YesNo(false),
+        },
+        Question {
+            section: "os_parking",
+            prompt: narration!("quiz.os_parking"),
+            expected: Answer::YesNo(false),
+        },
+        Question {
+            section: "lockfree_queue",
+            prompt: narration!("quiz.lockfree_queue"),
+            expected: Answer::YesNo(false),
+        },
+        Question {
+            section: "hazard_pointers",
+            prompt: narration!("quiz.hazard_pointers"),
+            expected: Answer::YesNo(false),
+        },
+        Question {
+            section: "versioned_store",
+            prompt: narration!("quiz.versioned_store"),
+            expected: Answer::YesNo(false),
+        },
+        Question {
+            section: "priority_inversion",
+            prompt: narration!("quiz.priority_inversion"),
+            expected: Answer::YesNo(true),
+        },
+        Question {
+            section: "work_stealing",
+            prompt: narration!("quiz.work_stealing"),
+            expected: Answer::YesNo(false),
+        },
+        Question {
+            section: "interrupt_handlers",
+            prompt: narration!("quiz.interrupt_handlers"),
+            expected: Answer::YesNo(false),
+        },
+        Question {
+            section: "extension_points",
+            prompt: narration!("quiz.extension_points"),
+            expected: Answer::YesNo(false),
+        },
+        Question {
+            section: "panic_strategy",
+            prompt: narration!("quiz.panic_strategy"),
+            expected: Answer::YesNo(false),
+        },
+        Question {
+            section: "compile_time_safety",
+            prompt: narration!("quiz.compile_time_safety"),
+            expected: Answer::YesNo(false),
+        },
+    ]
+}
+
+#[derive(Serialize)]
+struct ScoredAnswer {
+    section: &'static str,
+    question: String,
+    expected: Answer,
+    given: Option<Answer>,  // None when the student skipped the question
+    correct: bool,
+}
+
+#[derive(Serialize)]
+struct QuizReport {
+    score: usize,
+    total: usize,
+    answers: Vec<ScoredAnswer>,
+}
+
+// Keep asking until the answer parses; None on end of input or empty line
+fn ask(input: &mut impl BufRead, question: &Question) -> Option<Answer> {
+    loop {
+        print!("{}", narration!("quiz.predict", prompt = question.prompt, hint = question.expected.hint()));
+        let _ = io::stdout().flush();
+
+        let mut line = String::new();
+        match input.read_line(&mut line) {
+            Ok(0) | Err(_) => return None,
+            Ok(_) if line.trim().is_empty() => return None,
+            Ok(_) => {}
+        }
+
+        match Answer::parse(&line, question.expected) {
+            Some(answer) => return Some(answer),
+            None => println!("{}", narration!("quiz.answer_with", hint = question.expected.hint())),
+        }
+    }
+}
+
+fn run_quiz(input: &mut impl BufRead, config: &EnvConfig) -> QuizReport {
+    let questions = questions(config);
+    let mut answers = Vec::new();
+
+    narrate!("quiz.heading");
+
+    for section in SECTIONS {
+        let question = questions.iter().find(|question| question.section == section.name);
+        let given = question.and_then(|question| ask(input, question));
+
+        let _ = sections::run_section(&section, config);  // a panic is said, and the quiz goes on
+
+        if let Some(question) = question {
+            let correct = given == Some(question.expected);
+            if correct {
+                narrate!("quiz.correct", expected = question.expected);
+            } else {
+                narrate!("quiz.incorrect", expected = question.expected);
+            }
+            answers.push(ScoredAnswer {
+                section: question.section,
+                question: question.prompt.clone(),
+                expected: question.expected,
+                given,
+                correct,
+            });
+        }
+    }
+
+    QuizReport {
+        score: answers.iter().filter(|answer| answer.correct).count(),
+        total: answers.len(),
+        answers,
+    }
+}
+
+// `quiz [--report <path>]` - returns the exit code
+pub fn quiz_command(args: &[String], config: &EnvConfig) -> i32 {
+    let report_path = match args {
+        [] => "quiz_report.json".to_string(),
+        [flag, path] if flag == "--report" => path.clone(),
+        _ => {
+            eprintln!("{}", narration!("quiz.usage"));
+            return 2;
+        }
+    };
+
+    let stdin = io::stdin();
+    let report = run_quiz(&mut stdin.lock(), config);
+
+    narrate!("quiz.results_heading");
+    for answer in &report.answers {
+        let mark = if answer.correct { "+" } else { "-" };
+        say!("[{}] {}", mark, answer.section);
+    }
+    narrate!("quiz.score", score = report.score, total = report.total);
+
+    let json = match serde_json::to_string_pretty(&report) {
+        Ok(json) => json,
+        Err(error) => {
+            eprintln!("{}", narration!("quiz.serialize_failed", error));
+            return 1;
+        }
+    };
+    match fs::write(&report_path, json) {
+        Ok(()) => {
+            narrate!("quiz.written", path = report_path);
+            0
+        }
+        Err(error) => {
+            eprintln!("{}", narration!("quiz.write_failed", path = report_path, error));
+            1
+        }
+    }
+}
@@ -0,0 +1,76 @@
+/*!
+ * Checked Arithmetic
+ *
+ * On integers, `a + b` wraps silently in a release build and panics in
+ * a debug build. Neither is something a long-running device can act on.
+ * CheckedTotal adds with checked_add and reports an overflow as an
+ * error, leaving the total unchanged. The caller decides what happens
+ * next, and the result no longer depends on the build profile.
+ */
+
+use core::fmt;
+
+/// An addition that would have overflowed a [`CheckedTotal`].
+///
+/// # Examples
+///
+/// ```
+/// use resilient_core::{CheckedTotal, Overflow};
+///
+/// let mut total = CheckedTotal::new();
+/// total.try_add(u32::MAX).unwrap();
+/// let overflow = total.try_add(1).unwrap_err();
+/// assert_eq!(overflow, Overflow { total: u32::MAX, amount: 1 });
+/// assert_eq!(overflow.to_string(), "4294967295 + 1 overflows u32");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overflow {
+    /// The total before the addition.
+    pub total: u32,
+    /// The amount that did not fit.
+    pub amount: u32,
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} + {} overflows u32", self.total, self.amount)
+    }
+}
+
+impl core::error::Error for Overflow {}
+
+/// A running `u32` total whose additions never wrap.
+///
+/// # Examples
+///
+/// ```
+/// use resilient_core::CheckedTotal;
+///
+/// let mut total = CheckedTotal::new();
+/// assert_eq!(total.try_add(40), Ok(40));
+/// assert_eq!(total.try_add(2), Ok(42));
+/// assert!(total.try_add(u32::MAX).is_err());
+/// assert_eq!(total.get(), 42);  // unchanged by the refused addition
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CheckedTotal {
+    total: u32,
+}
+
+impl CheckedTotal {
+    /// A total of zero.
+    pub const fn new() -> Self {
+        CheckedTotal { total: 0 }
+    }
+
+    /// Add `amount` and return the new total, or say why it did not fit.
+    pub fn try_add(&mut self, amount: u32) -> Result<u32, Overflow> {
+        self.total = self.total.checked_add(amount).ok_or(Overflow { total: self.total, amount })?;
+        Ok(self.total)
+    }
+
+    /// The total so far.
+    pub fn get(&self) -> u32 {
+        self.total
+    }
+}
@@ -0,0 +1,62 @@
+/*!
+ * Lock-Free Counter
+ *
+ * A counter shared by reference between threads. Every update is one
+ * atomic read-modify-write, so two threads incrementing at once can never
+ * both read the old value and lose an increment, and no lock is needed.
+ */
+
+use core::sync::atomic::{AtomicI32, Ordering};
+
+/// A counter that any number of threads can increment through `&self`.
+///
+/// Share it with `Arc<SafeCounter>`; every increment is counted.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// use resilient_core::SafeCounter;
+///
+/// let counter = Arc::new(SafeCounter::new());
+/// let handles: Vec<_> = (0..4)
+///     .map(|_| {
+///         let counter = Arc::clone(&counter);
+///         thread::spawn(move || {
+///             for _ in 0..1000 {
+///                 counter.increment();
+///             }
+///         })
+///     })
+///     .collect();
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+/// assert_eq!(counter.get_count(), 4000);
+/// ```
+#[derive(Debug, Default)]
+pub struct SafeCounter {
+    count: AtomicI32,
+}
+
+impl SafeCounter {
+    /// A counter starting at zero. `const`, so it can be a `static`.
+    pub const fn new() -> Self {
+        SafeCounter {
+            count: AtomicI32::new(0),
+        }
+    }
+
+    /// Add one.
+    pub fn increment(&self) {
+        // Atomic operation - no race condition possible
+        self.count.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// The count so far.
+    pub fn get_count(&self) -> i32 {
+        self.count.load(Ordering::SeqCst)
+    }
+}
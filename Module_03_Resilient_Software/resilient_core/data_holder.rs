@@ -0,0 +1,57 @@
+/*!
+ * Data Holder
+ *
+ * A value with a name that prints a line when it is created and when it
+ * is dropped, so the console shows exactly when ownership ends - after a
+ * move, at the end of a scope, or never twice. Names are interned: holders
+ * with the same name share one allocation of it.
+ */
+
+use std::sync::Arc;
+
+use crate::names::intern;
+
+/// A named value that announces its creation and destruction on stdout.
+///
+/// # Examples
+///
+/// ```
+/// use resilient_core::DataHolder;
+///
+/// let holder = DataHolder::new(42, "answer");  // Created DataHolder: answer = 42
+/// holder.print();                              // DataHolder answer has value: 42
+/// let moved = holder;                          // a move prints nothing
+/// assert_eq!((moved.value, &*moved.name), (42, "answer"));
+/// drop(moved);                                 // Destroyed DataHolder: answer
+/// ```
+#[derive(Debug)]
+pub struct DataHolder {
+    /// The value held.
+    pub value: i32,
+    /// The name printed when the holder is created, printed and dropped;
+    /// shared with every other holder of the same name.
+    pub name: Arc<str>,
+}
+
+impl DataHolder {
+    /// Create a holder, printing `Created DataHolder: <name> = <value>`.
+    pub fn new(value: i32, name: &str) -> Self {
+        println!("Created DataHolder: {} = {}", name, value);
+        DataHolder {
+            value,
+            name: intern(name),
+        }
+    }
+
+    /// Print `DataHolder <name> has value: <value>`.
+    pub fn print(&self) {
+        println!("DataHolder {} has value: {}", self.name, self.value);
+    }
+}
+
+/// Prints `Destroyed DataHolder: <name>`.
+impl Drop for DataHolder {
+    fn drop(&mut self) {
+        println!("Destroyed DataHolder: {}", self.name);
+    }
+}
@@ -0,0 +1,130 @@
+/*!
+ * Event
+ *
+ * Mutex, Condvar, channels and the pools built on them all end the same
+ * way when a thread has to wait: they stop asking for CPU time and ask
+ * the OS to wake them later. Event is that bottom layer with nothing on
+ * top - a flag, and the threads parked until it goes up.
+ *
+ * `wait` registers the current thread, checks the flag, and parks. `set`
+ * raises the flag, then unparks every registered thread. Registering
+ * before checking is what makes this safe: a `set` that lands in between
+ * either is seen by the check or finds the thread on the list. A thread
+ * woken for any other reason - a spurious wakeup, a timeout - checks the
+ * flag again. `thread::park` is where the OS comes in: a futex wait on
+ * Linux, `WaitOnAddress` on Windows, a pthread condition elsewhere.
+ *
+ * The event stays set until `reset`, like a Win32 manual-reset event: a
+ * thread that arrives after `set` does not wait at all.
+ */
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+/// A flag that threads can wait for without spinning.
+///
+/// Waiting threads are parked by the OS until [`Event::set`] (or their
+/// timeout), and once set the event stays set until [`Event::reset`].
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// use resilient_core::Event;
+///
+/// let ready = Arc::new(Event::new());
+/// assert!(!ready.wait_timeout(Duration::from_millis(1)));
+///
+/// let waiter = {
+///     let ready = Arc::clone(&ready);
+///     thread::spawn(move || ready.wait())
+/// };
+/// ready.set();
+/// waiter.join().unwrap();
+/// assert!(ready.is_set());
+///
+/// ready.reset();
+/// assert!(!ready.is_set());
+/// ```
+#[derive(Default)]
+pub struct Event {
+    set: AtomicBool,
+    waiters: Mutex<Vec<Thread>>,  // parked, or about to park
+}
+
+impl Event {
+    /// An event that is not set.
+    pub fn new() -> Self {
+        Event::default()
+    }
+
+    /// Whether the event is set.
+    pub fn is_set(&self) -> bool {
+        self.set.load(Ordering::SeqCst)
+    }
+
+    /// Set the event and wake every thread waiting for it.
+    pub fn set(&self) {
+        self.set.store(true, Ordering::SeqCst);
+        for waiter in self.waiters().iter() {
+            waiter.unpark();
+        }
+    }
+
+    /// Clear the event; threads that wait from now on park again.
+    pub fn reset(&self) {
+        self.set.store(false, Ordering::SeqCst);
+    }
+
+    /// Park the calling thread until the event is set.
+    pub fn wait(&self) {
+        self.wait_until(None);
+    }
+
+    /// Park the calling thread until the event is set or `timeout`
+    /// passes. True if the event was set.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        self.wait_until(Instant::now().checked_add(timeout))
+    }
+
+    // The list is only pushed to and filtered, so a panic cannot leave it
+    // half changed
+    fn waiters(&self) -> MutexGuard<'_, Vec<Thread>> {
+        self.waiters.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // No deadline: wait for good
+    fn wait_until(&self, deadline: Option<Instant>) -> bool {
+        if self.is_set() {
+            return true;
+        }
+        let me = thread::current();
+        self.waiters().push(me.clone());
+        let set = loop {
+            if self.is_set() {
+                break true;
+            }
+            match deadline {
+                None => thread::park(),
+                Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                    Some(left) if !left.is_zero() => thread::park_timeout(left),
+                    _ => break false,
+                },
+            }
+        };
+        self.waiters().retain(|waiter| waiter.id() != me.id());
+        set
+    }
+}
+
+impl fmt::Debug for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Event").field("set", &self.is_set()).field("waiters", &self.waiters().len()).finish()
+    }
+}
@@ -0,0 +1,197 @@
+/*!
+ * Typed State Machine
+ *
+ * The core-only counterpart of the demos' typed phases (phase.rs). A
+ * Pipeline moves through Setup -> Running -> Draining -> Finished, and its
+ * state is a type parameter. `submit` only exists while the pipeline is
+ * Running, and `total` only once it is Finished. Each transition consumes
+ * the pipeline, so no handle to an earlier state survives. Submitting
+ * after draining has started is therefore a compile error rather than a
+ * lost value. Work waits in a RingBuffer and is summed into a
+ * CheckedTotal, so the machine needs neither a heap nor an operating
+ * system.
+ */
+
+use core::marker::PhantomData;
+
+use crate::checked::{CheckedTotal, Overflow};
+use crate::ring_buffer::RingBuffer;
+
+/// A state a [`Pipeline`] can be in. Other types can implement it, but
+/// transitions exist only between the four below.
+///
+/// # Examples
+///
+/// ```
+/// use resilient_core::{Pipeline, Setup, State};
+///
+/// fn describe<S: State>(_: &Pipeline<S, 4>) -> &'static str {
+///     S::NAME
+/// }
+/// assert_eq!(describe(&Pipeline::<Setup, 4>::new()), "setup");
+/// ```
+pub trait State {
+    /// The state's name, for messages.
+    const NAME: &'static str;
+}
+
+/// Being configured: nothing can be submitted yet.
+///
+/// # Examples
+///
+/// ```
+/// use resilient_core::{Pipeline, Setup};
+///
+/// let pipeline: Pipeline<Setup, 4> = Pipeline::new();
+/// assert_eq!(pipeline.pending(), 0);
+/// ```
+#[derive(Debug)]
+pub enum Setup {}
+
+/// Accepting work.
+///
+/// # Examples
+///
+/// ```
+/// use resilient_core::{Pipeline, Running, Setup};
+///
+/// let mut pipeline: Pipeline<Running, 4> = Pipeline::<Setup, 4>::new().start();
+/// pipeline.submit(1).unwrap();
+/// ```
+#[derive(Debug)]
+pub enum Running {}
+
+/// No longer accepting work, only finishing what is queued.
+///
+/// # Examples
+///
+/// ```
+/// use resilient_core::{Draining, Pipeline, Setup};
+///
+/// let mut pipeline = Pipeline::<Setup, 4>::new().start();
+/// pipeline.submit(5).unwrap();
+/// let draining: Pipeline<Draining, 4> = pipeline.drain();
+/// assert_eq!(draining.pending(), 1);
+/// ```
+#[derive(Debug)]
+pub enum Draining {}
+
+/// Done: the total is known.
+///
+/// # Examples
+///
+/// ```
+/// use resilient_core::{Finished, Pipeline, Setup};
+///
+/// let finished: Pipeline<Finished, 4> = Pipeline::<Setup, 4>::new().start().drain().finish().unwrap();
+/// assert_eq!(finished.total(), 0);
+/// ```
+#[derive(Debug)]
+pub enum Finished {}
+
+impl State for Setup {
+    const NAME: &'static str = "setup";
+}
+impl State for Running {
+    const NAME: &'static str = "running";
+}
+impl State for Draining {
+    const NAME: &'static str = "draining";
+}
+impl State for Finished {
+    const NAME: &'static str = "finished";
+}
+
+/// A pipeline in state `S` that queues up to `N` values and sums them.
+///
+/// # Examples
+///
+/// ```
+/// use resilient_core::Pipeline;
+///
+/// let mut pipeline = Pipeline::<_, 2>::new().start();
+/// pipeline.submit(40).unwrap();
+/// pipeline.submit(2).unwrap();
+/// assert_eq!(pipeline.submit(7), Err(7));  // the queue is full
+/// let finished = pipeline.drain().finish().unwrap();
+/// assert_eq!(finished.total(), 42);
+///
+/// let mut pipeline = Pipeline::<_, 2>::new().start();
+/// pipeline.submit(u32::MAX).unwrap();
+/// pipeline.submit(1).unwrap();
+/// assert!(pipeline.drain().finish().is_err());  // the sum would overflow
+/// ```
+///
+/// Work cannot be submitted once draining has started:
+///
+/// ```compile_fail
+/// use resilient_core::Pipeline;
+///
+/// let pipeline = Pipeline::<_, 2>::new().start();
+/// let mut draining = pipeline.drain();
+/// draining.submit(1);
+/// ```
+#[derive(Debug)]
+pub struct Pipeline<S: State, const N: usize> {
+    queue: RingBuffer<u32, N>,
+    total: CheckedTotal,
+    state: PhantomData<S>,
+}
+
+impl<S: State, const N: usize> Pipeline<S, N> {
+    /// How many submitted values have not been summed yet.
+    pub fn pending(&self) -> usize {
+        self.queue.len()
+    }
+
+    fn into_state<T: State>(self) -> Pipeline<T, N> {
+        Pipeline { queue: self.queue, total: self.total, state: PhantomData }
+    }
+}
+
+impl<const N: usize> Pipeline<Setup, N> {
+    /// A new pipeline with an empty queue.
+    pub fn new() -> Self {
+        Pipeline { queue: RingBuffer::new(), total: CheckedTotal::new(), state: PhantomData }
+    }
+
+    /// Start accepting work.
+    pub fn start(self) -> Pipeline<Running, N> {
+        self.into_state()
+    }
+}
+
+impl<const N: usize> Default for Pipeline<Setup, N> {
+    fn default() -> Self {
+        Pipeline::new()
+    }
+}
+
+impl<const N: usize> Pipeline<Running, N> {
+    /// Queue `value`, or return it if the queue is full.
+    pub fn submit(&mut self, value: u32) -> Result<(), u32> {
+        self.queue.push(value)
+    }
+
+    /// Stop accepting work.
+    pub fn drain(self) -> Pipeline<Draining, N> {
+        self.into_state()
+    }
+}
+
+impl<const N: usize> Pipeline<Draining, N> {
+    /// Sum everything still queued, or stop at the first overflow.
+    pub fn finish(mut self) -> Result<Pipeline<Finished, N>, Overflow> {
+        while let Some(value) = self.queue.pop() {
+            self.total.try_add(value)?;
+        }
+        Ok(self.into_state())
+    }
+}
+
+impl<const N: usize> Pipeline<Finished, N> {
+    /// The sum of every submitted value.
+    pub fn total(&self) -> u32 {
+        self.total.get()
+    }
+}
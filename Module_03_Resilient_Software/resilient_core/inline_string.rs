@@ -0,0 +1,262 @@
+/*!
+ * Inline Strings
+ *
+ * Resource names are short: "Database", "Cache", "res-1f". A String puts
+ * even those on the heap, one allocation per name. InlineString<N> keeps
+ * up to N bytes in the value itself and only moves to the heap when the
+ * text grows past them:
+ *
+ *   InlineString::<16>::from("Database")   inline, no allocation
+ *   InlineString::<16>::from("a-name-longer-than-16")   on the heap
+ *
+ * Once on the heap it stays there: shrinking it again does not copy it
+ * back. Everything that changes the text either copies in whole strings
+ * or cuts at char boundaries, so the contents are always valid UTF-8.
+ * Otherwise it behaves like a String: it derefs to str, and compares,
+ * orders and hashes like its text.
+ */
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+/// A string that holds up to `N` bytes without allocating. `N` is at
+/// most 255.
+///
+/// # Examples
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// use resilient_core::InlineString;
+///
+/// let mut name = InlineString::<16>::new();
+/// name.push_str("Database");
+/// name.push('-');
+/// write!(name, "{:x}", 31).unwrap();
+/// assert_eq!(name.as_str(), "Database-1f");
+/// assert!(name.is_inline());    // 11 bytes: no allocation
+///
+/// name.push_str("-replica");    // 19 bytes: moves to the heap
+/// assert!(!name.is_inline());
+/// assert_eq!(name.pop(), Some('a'));
+/// name.truncate(8);
+/// assert_eq!(name, "Database");
+/// name.clear();
+/// assert!(name.is_empty());
+/// ```
+#[derive(Clone)]
+pub struct InlineString<const N: usize> {
+    repr: Repr<N>,
+}
+
+#[derive(Clone)]
+enum Repr<const N: usize> {
+    Inline { len: u8, bytes: [u8; N] },  // bytes[..len] is valid UTF-8
+    Heap(String),
+}
+
+// The text of an Inline representation. Its bytes were only ever filled
+// with whole strs and cut at char boundaries, so this cannot fail
+fn inline_str(bytes: &[u8], len: u8) -> &str {
+    std::str::from_utf8(&bytes[..usize::from(len)]).expect("inline bytes are UTF-8")
+}
+
+impl<const N: usize> InlineString<N> {
+    const FITS: () = assert!(N <= u8::MAX as usize, "InlineString holds at most 255 bytes inline");
+
+    /// An empty string, inline.
+    pub const fn new() -> Self {
+        let () = Self::FITS;
+        InlineString { repr: Repr::Inline { len: 0, bytes: [0; N] } }
+    }
+
+    /// The text.
+    pub fn as_str(&self) -> &str {
+        match &self.repr {
+            Repr::Inline { len, bytes } => inline_str(bytes, *len),
+            Repr::Heap(string) => string,
+        }
+    }
+
+    // The text's bytes, without checking them again: enough to compare,
+    // since str orders by its bytes
+    fn bytes(&self) -> &[u8] {
+        match &self.repr {
+            Repr::Inline { len, bytes } => &bytes[..usize::from(*len)],
+            Repr::Heap(string) => string.as_bytes(),
+        }
+    }
+
+    /// Whether the text is still stored inline, without an allocation.
+    pub fn is_inline(&self) -> bool {
+        matches!(self.repr, Repr::Inline { .. })
+    }
+
+    /// Append `text`, moving to the heap if it no longer fits inline.
+    pub fn push_str(&mut self, text: &str) {
+        match &mut self.repr {
+            Repr::Inline { len, bytes } if usize::from(*len) + text.len() <= N => {
+                let start = usize::from(*len);
+                bytes[start..start + text.len()].copy_from_slice(text.as_bytes());
+                *len += text.len() as u8;  // at most N, which fits
+            }
+            Repr::Inline { len, bytes } => {
+                let mut spilled = String::with_capacity(usize::from(*len) + text.len());
+                spilled.push_str(inline_str(bytes, *len));
+                spilled.push_str(text);
+                self.repr = Repr::Heap(spilled);
+            }
+            Repr::Heap(string) => string.push_str(text),
+        }
+    }
+
+    /// Append one character.
+    pub fn push(&mut self, ch: char) {
+        self.push_str(ch.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Remove and return the last character, if any.
+    pub fn pop(&mut self) -> Option<char> {
+        let ch = self.as_str().chars().next_back()?;
+        self.truncate(self.len() - ch.len_utf8());
+        Some(ch)
+    }
+
+    /// Shorten to `new_len` bytes; longer lengths leave the text as it is.
+    ///
+    /// # Panics
+    ///
+    /// If `new_len` falls inside a character, as [`String::truncate`] does.
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len >= self.len() {
+            return;
+        }
+        assert!(self.as_str().is_char_boundary(new_len), "new length {} is not on a char boundary", new_len);
+        match &mut self.repr {
+            Repr::Inline { len, .. } => *len = new_len as u8,  // below the current length
+            Repr::Heap(string) => string.truncate(new_len),
+        }
+    }
+
+    /// Remove all text. A string on the heap keeps its allocation.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+}
+
+impl<const N: usize> Default for InlineString<N> {
+    fn default() -> Self {
+        InlineString::new()
+    }
+}
+
+impl<const N: usize> Deref for InlineString<N> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> AsRef<str> for InlineString<N> {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> Borrow<str> for InlineString<N> {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> From<&str> for InlineString<N> {
+    fn from(text: &str) -> Self {
+        let mut string = InlineString::new();
+        string.push_str(text);
+        string
+    }
+}
+
+/// Short text is copied inline; longer text keeps the String's allocation.
+impl<const N: usize> From<String> for InlineString<N> {
+    fn from(text: String) -> Self {
+        if text.len() <= N {
+            InlineString::from(text.as_str())
+        } else {
+            InlineString { repr: Repr::Heap(text) }
+        }
+    }
+}
+
+impl<const N: usize> From<InlineString<N>> for String {
+    fn from(string: InlineString<N>) -> Self {
+        match string.repr {
+            Repr::Inline { len, bytes } => inline_str(&bytes, len).to_string(),
+            Repr::Heap(string) => string,
+        }
+    }
+}
+
+/// `write!` into an InlineString, as into a String.
+impl<const N: usize> fmt::Write for InlineString<N> {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        self.push_str(text);
+        Ok(())
+    }
+}
+
+impl<const N: usize> fmt::Display for InlineString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl<const N: usize> fmt::Debug for InlineString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+// Compared, ordered and hashed by text alone: an inline and a heap copy
+// of the same text are equal, as Borrow<str> requires
+impl<const N: usize> PartialEq for InlineString<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes() == other.bytes()
+    }
+}
+
+impl<const N: usize> Eq for InlineString<N> {}
+
+impl<const N: usize> PartialEq<str> for InlineString<N> {
+    fn eq(&self, other: &str) -> bool {
+        self.bytes() == other.as_bytes()
+    }
+}
+
+impl<const N: usize> PartialEq<&str> for InlineString<N> {
+    fn eq(&self, other: &&str) -> bool {
+        self.bytes() == other.as_bytes()
+    }
+}
+
+impl<const N: usize> PartialOrd for InlineString<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<const N: usize> Ord for InlineString<N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.bytes().cmp(other.bytes())
+    }
+}
+
+impl<const N: usize> Hash for InlineString<N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
@@ -0,0 +1,116 @@
+/*!
+ * State Invariants
+ *
+ * What #[derive(Invariants)] implements. A type lists the conditions its
+ * state must always satisfy; check_invariants evaluates them all and
+ * names the ones that fail. Mutating methods call debug_check_invariants
+ * after each change, so a debug build stops at the first operation that
+ * breaks its own type, while a release build pays nothing. Demos report
+ * the same check as a regular invariant in their verification report.
+ *
+ * The derive is re-exported here next to the trait, so one import of
+ * resilient_core::invariants::Invariants brings both.
+ */
+
+use std::fmt;
+
+pub use safety_demo_macros::Invariants;
+
+/// The invariants a value broke, as `check_invariants` found them.
+///
+/// # Examples
+///
+/// ```
+/// use resilient_core::invariants::Invariants;
+/// use resilient_core::SharedData;
+///
+/// let violation = SharedData::from_parts(vec![1], 1, false).check_invariants().unwrap_err();
+/// assert_eq!(violation.broken, ["processing flips on every value"]);
+/// assert_eq!(violation.to_string(), "SharedData broke: processing flips on every value");
+/// ```
+#[derive(Debug, PartialEq)]
+pub struct InvariantViolation {
+    /// The type whose invariants broke.
+    pub type_name: &'static str,
+    /// The labels of the broken invariants, in declaration order.
+    pub broken: Vec<&'static str>,
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} broke: {}", self.type_name, self.broken.join(", "))
+    }
+}
+
+/// Conditions a type's state must always satisfy.
+///
+/// Implemented by `#[derive(Invariants)]` from each `#[invariant(...)]`
+/// attribute on the type's fields, or by hand.
+///
+/// # Examples
+///
+/// ```
+/// use resilient_core::invariants::Invariants;
+///
+/// #[derive(Invariants)]
+/// struct Window {
+///     #[invariant(self.used <= self.size, "used fits the window")]
+///     used: usize,
+///     size: usize,
+/// }
+///
+/// let window = Window { used: 5, size: 4 };
+/// assert_eq!(window.check_invariants().unwrap_err().broken, ["used fits the window"]);
+/// ```
+///
+/// By hand:
+///
+/// ```
+/// use resilient_core::invariants::{InvariantViolation, Invariants};
+///
+/// struct Range {
+///     low: i32,
+///     high: i32,
+/// }
+///
+/// impl Invariants for Range {
+///     const TYPE_NAME: &'static str = "Range";
+///
+///     fn invariants(&self) -> Vec<(&'static str, bool)> {
+///         vec![("low <= high", self.low <= self.high)]
+///     }
+/// }
+///
+/// let range = Range { low: 1, high: 2 };
+/// range.debug_check_invariants();  // holds, so no panic
+/// let inverted = Range { low: 3, high: 2 };
+/// let violation = InvariantViolation { type_name: "Range", broken: vec!["low <= high"] };
+/// assert_eq!(inverted.check_invariants(), Err(violation));
+/// ```
+pub trait Invariants {
+    /// The type's name, for reports.
+    const TYPE_NAME: &'static str;
+
+    /// Every declared invariant and whether it holds right now.
+    fn invariants(&self) -> Vec<(&'static str, bool)>;
+
+    /// `Err` naming every invariant that does not hold.
+    fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        let broken: Vec<&'static str> =
+            self.invariants().into_iter().filter(|(_, holds)| !holds).map(|(label, _)| label).collect();
+        if broken.is_empty() {
+            Ok(())
+        } else {
+            Err(InvariantViolation { type_name: Self::TYPE_NAME, broken })
+        }
+    }
+
+    /// Panics on a broken invariant in debug builds; compiled out in release.
+    fn debug_check_invariants(&self) {
+        if cfg!(debug_assertions) {
+            if let Err(violation) = self.check_invariants() {
+                panic!("{}", violation);
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Safety Primitives of Module 3
+//!
+//! The types the Module 3 demonstrations are built on, as a library
+//! students can use in their own projects:
+//!
+//! - [`SafeCounter`]: a counter any number of threads can increment
+//!   without losing an update.
+//! - [`ShardedCounter`]: the same count spread over one shard per CPU,
+//!   so threads stop contending for one cache line.
+//! - [`SharedData`]: values and their running sum, kept consistent behind
+//!   a `Mutex`, with cheap [`DataSnapshot`]s for readers.
+//! - [`Resource`]: a named resource with checked, fallible constructors.
+//! - [`DataHolder`]: a value that announces its creation and destruction,
+//!   to make ownership moves and drops visible.
+//! - [`intern`]: one shared allocation per distinct name, which
+//!   `Resource` and `DataHolder` names point to.
+//! - [`InlineString`]: a string that keeps short text inline and only
+//!   allocates once it grows past its inline capacity.
+//! - [`ThreadPool`]: a fixed set of worker threads that run queued jobs,
+//!   instead of a new thread per task; each job's [`JobHandle`] returns
+//!   its result.
+//! - [`parallel_map`]: a map over a borrowed slice, one scoped thread
+//!   per CPU, with the results in order.
+//! - [`Event`]: a flag threads wait for parked by the OS, not spinning -
+//!   the layer the blocking primitives bottom out in.
+//! - [`Semaphore`]: a count of permits built on an `Event`; a
+//!   [`SemaphorePermit`] gives its permit back when dropped.
+//! - [`RingBuffer`]: a fixed-capacity queue stored inline, which hands a
+//!   value back when full instead of growing.
+//! - [`CheckedTotal`]: a running total whose additions report an
+//!   [`Overflow`] instead of wrapping.
+//! - [`Pipeline`]: a state machine whose state is a type, so an
+//!   operation in the wrong state does not compile.
+//!
+//! [`invariants`] holds `#[derive(Invariants)]` and the trait it implements.
+//! `SharedData` declares its invariants with it.
+//!
+//! [`prelude`] re-exports all of the above, the API assignments are
+//! written against. Its names and signatures do not change during the
+//! semester; anything `#[doc(hidden)]` is internal to the demos.
+//!
+//! # Without `std`
+//!
+//! The `std` feature is on by default. With `default-features = false`
+//! the crate is `no_std` and keeps only what needs nothing but `core`:
+//! `SafeCounter`, `RingBuffer`, `CheckedTotal` and `Pipeline`. Everything
+//! else needs threads, locks or the heap. `examples/embedded` builds that
+//! core for a Cortex-M4 (`thumbv7em-none-eabihf`).
+
+#![cfg_attr(not(feature = "std"), no_std)]
+#![warn(missing_docs)]
+
+// Derived Invariants impls name ::resilient_core, here as in any other crate
+extern crate self as resilient_core;
+
+mod checked;
+mod counter;
+#[cfg(feature = "std")]
+mod data_holder;
+#[cfg(feature = "std")]
+mod event;
+mod fsm;
+#[cfg(feature = "std")]
+mod inline_string;
+#[cfg(feature = "std")]
+pub mod invariants;
+#[cfg(feature = "std")]
+mod names;
+#[cfg(feature = "std")]
+mod parallel;
+pub mod prelude;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub mod resource;  // for the demos' `crate::resource` paths; students use the re-exports
+mod ring_buffer;
+#[cfg(feature = "std")]
+mod semaphore;
+#[cfg(feature = "std")]
+mod sharded_counter;
+#[cfg(feature = "std")]
+mod shared_data;
+#[cfg(feature = "std")]
+mod thread_pool;
+
+pub use checked::{CheckedTotal, Overflow};
+pub use counter::SafeCounter;
+pub use fsm::{Draining, Finished, Pipeline, Running, Setup, State};
+pub use ring_buffer::RingBuffer;
+
+#[cfg(feature = "std")]
+pub use data_holder::DataHolder;
+#[cfg(feature = "std")]
+pub use event::Event;
+#[cfg(feature = "std")]
+pub use inline_string::InlineString;
+#[cfg(feature = "std")]
+pub use names::intern;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use names::interned;
+#[cfg(feature = "std")]
+pub use parallel::parallel_map;
+#[cfg(feature = "std")]
+pub use resource::{RawResource, Resource, ResourceError, ResourceId};
+#[cfg(feature = "std")]
+pub use semaphore::{Semaphore, SemaphorePermit};
+#[cfg(feature = "std")]
+pub use shared_data::{DataSnapshot, SharedData};
+#[cfg(feature = "std")]
+pub use sharded_counter::ShardedCounter;
+#[cfg(feature = "std")]
+pub use thread_pool::{JobHandle, ThreadPool};
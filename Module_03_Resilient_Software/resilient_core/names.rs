@@ -0,0 +1,74 @@
+/*!
+ * Interned Names
+ *
+ * A demo creates thousands of objects from a handful of names -
+ * "Database", "Cache", "worker". Giving each its own String means one
+ * allocation per object, and one more for every copy a log line or a
+ * report takes. Interned, each distinct name is allocated once: every
+ * object holds an Arc<str> to the same text, and a copy is a reference
+ * count increment.
+ *
+ * Lookups share a read lock, so constructors on many threads naming
+ * objects after names already interned never wait on each other; only
+ * the first request for a new name takes the write lock.
+ *
+ * Names stay interned while something still holds them. Once the table
+ * has doubled since it was last cleaned, names nobody else holds are
+ * dropped, so a long run that keeps making up new names does not grow
+ * the table without bound.
+ */
+
+use std::collections::BTreeSet;
+use std::sync::{Arc, RwLock};
+
+// Never clean a table smaller than this: the demos' own names fit
+const MIN_CLEAN_AT: usize = 64;
+
+struct Table {
+    names: BTreeSet<Arc<str>>,
+    clean_at: usize,  // clean out unused names once this many are interned
+}
+
+static TABLE: RwLock<Table> = RwLock::new(Table { names: BTreeSet::new(), clean_at: MIN_CLEAN_AT });
+
+/// The shared copy of `name`. Only the first request for a name
+/// allocates; later ones return the same text.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use resilient_core::{intern, interned};
+///
+/// let first = intern("Database");
+/// let second = intern(&String::from("Database"));
+/// assert!(Arc::ptr_eq(&first, &second));  // one allocation of the name
+/// assert!(interned() >= 1);  // a diagnostic for the demos' checks, not part of the prelude
+/// ```
+pub fn intern(name: &str) -> Arc<str> {
+    let table = TABLE.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(shared) = table.names.get(name) {
+        return Arc::clone(shared);
+    }
+    drop(table);
+    // Another thread may have interned it between the two locks
+    let mut table = TABLE.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(shared) = table.names.get(name) {
+        return Arc::clone(shared);
+    }
+    if table.names.len() >= table.clean_at {
+        table.names.retain(|name| Arc::strong_count(name) > 1);
+        table.clean_at = (table.names.len() * 2).max(MIN_CLEAN_AT);
+    }
+    let shared: Arc<str> = Arc::from(name);
+    table.names.insert(Arc::clone(&shared));
+    shared
+}
+
+/// How many distinct names are interned, including unused ones not yet
+/// cleaned out.
+#[doc(hidden)]
+pub fn interned() -> usize {
+    TABLE.read().unwrap_or_else(|poisoned| poisoned.into_inner()).names.len()
+}
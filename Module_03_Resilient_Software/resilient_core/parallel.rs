@@ -0,0 +1,70 @@
+/*!
+ * Parallel Map
+ *
+ * thread::scope lets threads borrow from the stack frame that starts
+ * them: the scope does not return until every thread in it has finished,
+ * so nothing they borrow can be dropped under them. parallel_map uses
+ * that to spread a map over a borrowed slice without copying it or
+ * wrapping it in an Arc. The slice is cut into one contiguous chunk per
+ * CPU, each chunk is mapped by its own thread (the first by the calling
+ * thread, which would otherwise only wait), and the chunks' results are
+ * joined back in order.
+ */
+
+use std::thread;
+
+/// `items.iter().map(map).collect()`, spread over one scoped thread per
+/// CPU. The results are in the same order as `items`.
+///
+/// `map` only borrows each item, and may itself borrow anything that
+/// outlives the call; it runs on several threads at once, so it has to be
+/// `Sync`. A `&mut [T]` works as well, as the mutable borrow is reborrowed
+/// as a shared one for the duration of the call.
+///
+/// # Panics
+///
+/// If `map` panics, once every thread has finished; or if a thread
+/// cannot be started.
+///
+/// # Examples
+///
+/// ```
+/// use resilient_core::{parallel_map, SharedData};
+///
+/// let mut data = SharedData::new();
+/// for value in 1..=1000 {
+///     data.add_value(value);
+/// }
+/// let offset = 10;  // borrowed by every thread
+/// let shifted = parallel_map(data.values(), |value| value + offset);
+/// assert_eq!(shifted.len(), 1000);
+/// assert_eq!(shifted[0], 11);
+/// assert_eq!(shifted[999], 1010);
+///
+/// let mut words = vec!["scoped", "threads"];
+/// assert_eq!(parallel_map(&mut words, |word| word.len()), [6, 7]);
+/// ```
+pub fn parallel_map<T, U, F>(items: &[T], map: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync,
+{
+    let cpus = thread::available_parallelism().map_or(1, |cpus| cpus.get());
+    let chunk_len = items.len().div_ceil(cpus).max(1);
+    let mut chunks = items.chunks(chunk_len);
+    let Some(first) = chunks.next() else {
+        return Vec::new();
+    };
+    let map = &map;
+    thread::scope(|scope| {
+        let rest: Vec<_> = chunks.map(|chunk| scope.spawn(move || chunk.iter().map(map).collect::<Vec<U>>())).collect();
+        let mut results: Vec<U> = first.iter().map(map).collect();
+        results.reserve(items.len() - first.len());
+        for worker in rest {
+            // A panic in `map` goes on to the caller
+            results.extend(worker.join().unwrap_or_else(|payload| std::panic::resume_unwind(payload)));
+        }
+        results
+    })
+}
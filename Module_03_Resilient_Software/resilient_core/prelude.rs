@@ -0,0 +1,42 @@
+/*!
+ * Prelude - the Supported Teaching API
+ *
+ * Everything an assignment needs, in one import. These names and their
+ * signatures (listed in `api.txt` next to this file) hold for the whole
+ * semester: the demos may refactor what sits behind them, but code
+ * written against the prelude keeps compiling. Items marked
+ * `#[doc(hidden)]` serve the demos' own checks and may change at any
+ * time.
+ *
+ * # Examples
+ *
+ * ```
+ * use resilient_core::prelude::*;
+ *
+ * let counter = SafeCounter::new();
+ * counter.increment();
+ * assert_eq!(counter.get_count(), 1);
+ *
+ * // The rest needs the `std` feature
+ * # #[cfg(feature = "std")]
+ * # {
+ * let mut data = SharedData::new();
+ * data.add_value(5);
+ * assert!(data.check_invariants().is_ok());
+ *
+ * let resource = Resource::try_from("7:Scheduler").unwrap();
+ * assert_eq!(resource.name(), &*intern("Scheduler"));
+ * # }
+ * ```
+ */
+
+pub use crate::{CheckedTotal, Draining, Finished, Overflow, Pipeline, RingBuffer, Running, SafeCounter, Setup, State};
+
+#[cfg(feature = "std")]
+pub use crate::invariants::{InvariantViolation, Invariants};
+#[cfg(feature = "std")]
+pub use crate::{intern, DataHolder, DataSnapshot, InlineString, ShardedCounter, SharedData};
+#[cfg(feature = "std")]
+pub use crate::{RawResource, Resource, ResourceError, ResourceId};
+#[cfg(feature = "std")]
+pub use crate::{parallel_map, Event, JobHandle, Semaphore, SemaphorePermit, ThreadPool};
@@ -0,0 +1,210 @@
+/*!
+ * Resources and Their Fallible Constructors
+ *
+ * A Resource has a positive id and a non-empty name. Input that has not
+ * been checked yet - a RawResource read from a request, or an "id:name"
+ * string - becomes a Resource through TryFrom, and a bare id through
+ * ResourceId::try_from. The conversion either yields a valid value or a
+ * ResourceError saying what was wrong; there is no half-built Resource.
+ * Because these are the standard conversion traits, they compose with
+ * generic code: `inputs.map(ResourceId::try_from).collect::<Result<Vec<_>, _>>()`
+ * parses a whole list or stops at the first bad entry.
+ *
+ * Resource::new makes the same checks, for values the program itself
+ * spells out. A Resource's fields are private, so every Resource that
+ * exists has passed them.
+ *
+ * A Resource's name is interned (names.rs): a thousand "Database"
+ * resources share one allocation of the name.
+ */
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::names::intern;
+
+/// Why input could not become a [`Resource`] or [`ResourceId`].
+///
+/// # Examples
+///
+/// ```
+/// use resilient_core::{Resource, ResourceError};
+///
+/// let error = Resource::try_from("Database").unwrap_err();
+/// assert_eq!(error, ResourceError::MissingName("Database".to_string()));
+/// assert_eq!(error.to_string(), "\"Database\" is not in id:name form");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResourceError {
+    /// The id text is not a number.
+    NotANumber(String),
+    /// The id is zero or negative.
+    InvalidId(i32),
+    /// The name is empty or only whitespace.
+    EmptyName,
+    /// The text has no ':' before the name.
+    MissingName(String),
+}
+
+impl fmt::Display for ResourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResourceError::NotANumber(text) => write!(f, "Invalid ID: {:?} is not a number", text),
+            ResourceError::InvalidId(id) => write!(f, "Invalid ID: must be positive (got {})", id),
+            ResourceError::EmptyName => write!(f, "Invalid name: cannot be empty"),
+            ResourceError::MissingName(text) => write!(f, "{:?} is not in id:name form", text),
+        }
+    }
+}
+
+impl std::error::Error for ResourceError {}
+
+/// A checked resource id: always positive.
+///
+/// # Examples
+///
+/// ```
+/// use resilient_core::{ResourceError, ResourceId};
+///
+/// let ids: Vec<ResourceId> = ["3", " 7 "].into_iter().map(ResourceId::try_from).collect::<Result<_, _>>().unwrap();
+/// assert_eq!(ids[1].get(), 7);
+/// assert_eq!(ResourceId::try_from(0), Err(ResourceError::InvalidId(0)));
+/// assert!(matches!(ResourceId::try_from("seven"), Err(ResourceError::NotANumber(_))));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ResourceId(i32);
+
+impl ResourceId {
+    /// The id as a number.
+    pub fn get(self) -> i32 {
+        self.0
+    }
+}
+
+impl fmt::Display for ResourceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl TryFrom<i32> for ResourceId {
+    type Error = ResourceError;
+
+    fn try_from(id: i32) -> Result<Self, ResourceError> {
+        if id > 0 { Ok(ResourceId(id)) } else { Err(ResourceError::InvalidId(id)) }
+    }
+}
+
+impl TryFrom<&str> for ResourceId {
+    type Error = ResourceError;
+
+    fn try_from(text: &str) -> Result<Self, ResourceError> {
+        let id: i32 = text.trim().parse().map_err(|_| ResourceError::NotANumber(text.to_string()))?;
+        ResourceId::try_from(id)
+    }
+}
+
+/// Fields as they arrive, before anything is checked.
+///
+/// # Examples
+///
+/// ```
+/// use resilient_core::{RawResource, Resource, ResourceError};
+///
+/// let raw = RawResource { id: 7, name: " Scheduler ".to_string() };
+/// assert_eq!(Resource::try_from(raw).unwrap().name(), "Scheduler");
+/// let blank = RawResource { id: 8, name: "  ".to_string() };
+/// assert_eq!(Resource::try_from(blank).unwrap_err(), ResourceError::EmptyName);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawResource {
+    /// The id, not yet checked to be positive.
+    pub id: i32,
+    /// The name, not yet checked to be non-empty.
+    pub name: String,
+}
+
+/// A named resource that announces its creation and destruction on stdout.
+///
+/// # Examples
+///
+/// ```
+/// use resilient_core::{Resource, ResourceError};
+///
+/// let database = Resource::new(1, "Database").unwrap();  // Created Resource: Database (id: 1)
+/// database.process();                                    // Processing resource: Database (id: 1)
+/// assert_eq!(database.to_string(), "Database (id: 1)");
+/// assert_eq!((database.id(), database.name()), (1, "Database"));
+/// assert_eq!(Resource::new(0, "Nothing").unwrap_err(), ResourceError::InvalidId(0));
+/// let scheduler = Resource::try_from("7:Scheduler").unwrap();
+/// assert_eq!(scheduler.id(), 7);
+/// // Destroyed Resource: Scheduler, then Database
+/// ```
+#[derive(Debug)]
+pub struct Resource {
+    id: ResourceId,
+    name: Arc<str>,  // shared with every resource of the same name
+}
+
+impl Resource {
+    /// Create a resource, printing `Created Resource: <name> (id: <id>)`,
+    /// or say why `id` and `name` cannot make one. The name is trimmed.
+    pub fn new(id: i32, name: &str) -> Result<Self, ResourceError> {
+        let id = ResourceId::try_from(id)?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(ResourceError::EmptyName);
+        }
+        let resource = Resource { id, name: intern(name) };
+        println!("Created Resource: {}", resource);
+        Ok(resource)
+    }
+
+    /// The resource's id, always positive.
+    pub fn id(&self) -> i32 {
+        self.id.get()
+    }
+
+    /// The resource's name, never empty.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Print `Processing resource: <name> (id: <id>)`.
+    pub fn process(&self) {
+        println!("Processing resource: {}", self);
+    }
+}
+
+// "Database (id: 1)"
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (id: {})", self.name, self.id)
+    }
+}
+
+impl TryFrom<RawResource> for Resource {
+    type Error = ResourceError;
+
+    fn try_from(raw: RawResource) -> Result<Self, ResourceError> {
+        Resource::new(raw.id, &raw.name)
+    }
+}
+
+// "7:Scheduler"
+impl TryFrom<&str> for Resource {
+    type Error = ResourceError;
+
+    fn try_from(text: &str) -> Result<Self, ResourceError> {
+        let (id, name) = text.split_once(':').ok_or_else(|| ResourceError::MissingName(text.to_string()))?;
+        let id = ResourceId::try_from(id)?;
+        Resource::try_from(RawResource { id: id.get(), name: name.to_string() })
+    }
+}
+
+/// Prints `Destroyed Resource: <name>`.
+impl Drop for Resource {
+    fn drop(&mut self) {
+        println!("Destroyed Resource: {}", self.name);
+    }
+}
@@ -0,0 +1,89 @@
+/*!
+ * Fixed-Capacity Ring Buffer
+ *
+ * A first-in, first-out queue of at most N values, stored in an array
+ * that is part of the buffer itself. Nothing is allocated, so it works
+ * where there is no heap, such as on a microcontroller. When the buffer
+ * is full, push hands the value back. It never grows, and it never
+ * overwrites the oldest entry, so no value is dropped without the
+ * caller knowing.
+ */
+
+/// A queue of at most `N` values, stored inline.
+///
+/// # Examples
+///
+/// ```
+/// use resilient_core::RingBuffer;
+///
+/// let mut ring = RingBuffer::<u32, 2>::new();
+/// assert_eq!(ring.capacity(), 2);
+/// assert_eq!(ring.push(1), Ok(()));
+/// assert_eq!(ring.push(2), Ok(()));
+/// assert!(ring.is_full());
+/// assert_eq!(ring.push(3), Err(3));  // full: the value comes back
+/// assert_eq!(ring.pop(), Some(1));
+/// assert_eq!(ring.len(), 1);
+/// assert_eq!(ring.pop(), Some(2));
+/// assert!(ring.is_empty());
+/// ```
+#[derive(Debug)]
+pub struct RingBuffer<T, const N: usize> {
+    slots: [Option<T>; N],
+    head: usize,  // slot of the oldest value
+    len: usize,
+}
+
+impl<T, const N: usize> RingBuffer<T, N> {
+    /// An empty buffer.
+    pub fn new() -> Self {
+        RingBuffer { slots: core::array::from_fn(|_| None), head: 0, len: 0 }
+    }
+
+    /// The most values the buffer holds at once.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// How many values are waiting.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no value is waiting.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether a push would be refused.
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Queue `value` behind the others, or return it if the buffer is full.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(value);
+        }
+        self.slots[(self.head + self.len) % N] = Some(value);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Take the oldest value, if there is one.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        let value = self.slots[self.head].take();
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        value
+    }
+}
+
+impl<T, const N: usize> Default for RingBuffer<T, N> {
+    fn default() -> Self {
+        RingBuffer::new()
+    }
+}
@@ -0,0 +1,127 @@
+/*!
+ * Semaphore
+ *
+ * A count of permits: `acquire` takes one, waiting while there are none,
+ * and dropping the permit gives it back. Built from two pieces only - an
+ * atomic count, and an Event that a release sets - to show how little a
+ * blocking primitive needs on top of OS parking.
+ *
+ * Taking a permit is a compare-and-swap on the count; no lock is held.
+ * A thread that finds none resets the event, looks once more, and only
+ * then waits: a permit given back after the reset sets the event again,
+ * one given back before it is found by the second look. A release wakes
+ * every waiter and they race for the permit; the losers wait again.
+ * Because a reset can hide a release meant for another waiter, a thread
+ * that gets a permit right after resetting sets the event again if more
+ * are left.
+ */
+
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::Event;
+
+/// A count of permits that threads take and give back, waiting while
+/// none are left.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::thread;
+///
+/// use resilient_core::Semaphore;
+///
+/// let slots = Semaphore::new(2);
+/// let (busy, peak) = (AtomicUsize::new(0), AtomicUsize::new(0));
+/// thread::scope(|scope| {
+///     for _ in 0..6 {
+///         scope.spawn(|| {
+///             let _permit = slots.acquire();
+///             peak.fetch_max(busy.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+///             thread::yield_now();
+///             busy.fetch_sub(1, Ordering::SeqCst);
+///         });
+///     }
+/// });
+/// assert!(peak.load(Ordering::SeqCst) <= 2);
+///
+/// let first = slots.try_acquire().unwrap();
+/// let _second = slots.try_acquire().unwrap();
+/// assert!(slots.try_acquire().is_none());
+/// drop(first);
+/// assert_eq!(slots.available(), 1);
+/// ```
+pub struct Semaphore {
+    permits: AtomicUsize,
+    released: Event,
+}
+
+impl Semaphore {
+    /// A semaphore holding `permits` permits.
+    pub fn new(permits: usize) -> Self {
+        Semaphore { permits: AtomicUsize::new(permits), released: Event::new() }
+    }
+
+    /// Permits not taken right now.
+    pub fn available(&self) -> usize {
+        self.permits.load(Ordering::SeqCst)
+    }
+
+    /// Take a permit if one is free, without waiting.
+    pub fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        self.permits
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |permits| permits.checked_sub(1))
+            .ok()
+            .map(|_| SemaphorePermit { semaphore: self })
+    }
+
+    /// Take a permit, parking until one is given back if none is free.
+    pub fn acquire(&self) -> SemaphorePermit<'_> {
+        loop {
+            if let Some(permit) = self.try_acquire() {
+                return permit;
+            }
+            self.released.reset();
+            if let Some(permit) = self.try_acquire() {
+                if self.available() > 0 {
+                    self.released.set();  // the reset may have hidden this one from a waiter
+                }
+                return permit;
+            }
+            self.released.wait();
+        }
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Semaphore").field("available", &self.available()).finish()
+    }
+}
+
+/// A permit taken from a [`Semaphore`], given back when dropped.
+///
+/// # Examples
+///
+/// ```
+/// use resilient_core::Semaphore;
+///
+/// let semaphore = Semaphore::new(1);
+/// {
+///     let _permit = semaphore.acquire();
+///     assert_eq!(semaphore.available(), 0);
+/// }
+/// assert_eq!(semaphore.available(), 1);
+/// ```
+#[derive(Debug)]
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        self.semaphore.permits.fetch_add(1, Ordering::SeqCst);
+        self.semaphore.released.set();
+    }
+}
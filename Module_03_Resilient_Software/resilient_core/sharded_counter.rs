@@ -0,0 +1,115 @@
+/*!
+ * Sharded Counter
+ *
+ * SafeCounter never loses an increment, but every increment from every
+ * thread goes to one cache line. Each core has to take that line over
+ * before it can add, so with many threads on many cores the increments
+ * queue for the line one at a time. ShardedCounter spreads them out:
+ * one counter per shard, each on a cache line of its own, and each
+ * thread always adds to the same shard. Threads on different shards
+ * never touch each other's line. Reading sums every shard, so a read
+ * costs a pass over the shards - the right trade for a count that is
+ * written far more often than it is read.
+ *
+ * A thread's shard is fixed the first time it increments any
+ * ShardedCounter: threads are numbered in the order they arrive, and
+ * thread n uses shard n modulo the shard count. With at least as many
+ * shards as threads, no two threads share one.
+ *
+ * Each shard is an AtomicIsize: an AtomicI64 on 64-bit targets, and
+ * still there on 32-bit ones that have no 64-bit atomics, where one
+ * shard holds up to 2^31 - 1. The total is summed as an i64.
+ */
+
+use std::fmt;
+use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+use std::thread;
+
+// Threads numbered in the order they first increment a ShardedCounter
+static NEXT_THREAD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static THREAD: usize = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+}
+
+// 128 bytes, not 64: x86 fetches cache lines in adjacent pairs, and
+// Apple's ARM cores have 128-byte lines
+#[repr(align(128))]
+#[derive(Default)]
+struct Shard(AtomicIsize);
+
+/// A counter that many threads can increment without contending for one
+/// cache line; reading it adds up every shard.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+///
+/// use resilient_core::ShardedCounter;
+///
+/// let counter = ShardedCounter::with_shards(4);
+/// thread::scope(|scope| {
+///     for _ in 0..8 {
+///         scope.spawn(|| {
+///             for _ in 0..1000 {
+///                 counter.increment();
+///             }
+///         });
+///     }
+/// });
+/// assert_eq!(counter.get_count(), 8000);
+/// assert_eq!(counter.shards(), 4);
+/// assert!(ShardedCounter::new().shards() >= 1);
+/// ```
+pub struct ShardedCounter {
+    shards: Box<[Shard]>,
+}
+
+impl ShardedCounter {
+    /// A counter at zero with one shard per CPU.
+    pub fn new() -> Self {
+        ShardedCounter::with_shards(thread::available_parallelism().map_or(1, |cpus| cpus.get()))
+    }
+
+    /// A counter at zero with `shards` shards.
+    ///
+    /// # Panics
+    ///
+    /// If `shards` is zero.
+    pub fn with_shards(shards: usize) -> Self {
+        assert!(shards > 0, "a sharded counter needs at least one shard");
+        ShardedCounter { shards: (0..shards).map(|_| Shard::default()).collect() }
+    }
+
+    /// Add one, to the calling thread's shard.
+    pub fn increment(&self) {
+        let shard = THREAD.with(|thread| thread % self.shards.len());
+        // Nothing waits on a count, so no ordering beyond the add itself
+        self.shards[shard].0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The sum of every shard. Exact once the incrementing threads are
+    /// joined; while they run, it includes some of their increments and
+    /// not others.
+    pub fn get_count(&self) -> i64 {
+        self.shards.iter().map(|shard| shard.0.load(Ordering::Relaxed) as i64).sum()
+    }
+
+    /// How many shards the count is spread over.
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+}
+
+impl Default for ShardedCounter {
+    fn default() -> Self {
+        ShardedCounter::new()
+    }
+}
+
+impl fmt::Debug for ShardedCounter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ShardedCounter").field("count", &self.get_count()).field("shards", &self.shards()).finish()
+    }
+}
@@ -0,0 +1,166 @@
+/*!
+ * Shared Data
+ *
+ * A list of values and their running sum, meant to live behind a Mutex.
+ * add_value changes three fields, and only the lock keeps another thread
+ * from seeing them half-updated; the fields are private so that nothing
+ * else can change one without the others. The invariants are declared
+ * with #[derive(Invariants)] and checked after every change in debug
+ * builds.
+ *
+ * Readers that want to walk every value take a DataSnapshot: an Arc of
+ * the values, cloned under a brief lock, so the walk does not hold up
+ * writers. A writer copies the values only while a snapshot still shares
+ * them.
+ */
+
+use std::sync::{Arc, Mutex};
+
+use crate::invariants::Invariants;
+
+/// Values and their running sum, kept consistent by `add_value`.
+///
+/// Share it as `Arc<Mutex<SharedData>>`. Its invariants: `sum` is the
+/// total of the values, and `is_processing` flips with every value added.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::{Arc, Mutex};
+///
+/// use resilient_core::invariants::Invariants;
+/// use resilient_core::SharedData;
+///
+/// let shared = Arc::new(Mutex::new(SharedData::new()));
+/// assert!(shared.lock().unwrap().is_empty());
+/// for value in [3, 4, 5] {
+///     shared.lock().unwrap().add_value(value);
+/// }
+/// let data = shared.lock().unwrap();
+/// assert_eq!(data.values(), [3, 4, 5]);
+/// assert_eq!((data.sum(), data.len(), data.is_processing()), (12, 3, true));
+/// assert!(data.check_invariants().is_ok());
+///
+/// // Parts that disagree break an invariant
+/// let broken = SharedData::from_parts(vec![1, 2], 4, false);
+/// assert_eq!(broken.check_invariants().unwrap_err().broken, ["sum matches data"]);
+/// ```
+#[derive(Debug, Invariants)]
+pub struct SharedData {
+    data: Arc<Vec<i32>>,  // shared with snapshots, copied on write
+    #[invariant(self.sum == self.data.iter().sum::<i32>(), "sum matches data")]
+    sum: i32,
+    #[invariant(self.processing != self.data.len().is_multiple_of(2), "processing flips on every value")]
+    processing: bool,
+}
+
+/// A consistent, immutable view of [`SharedData`] that outlives the lock.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Mutex;
+///
+/// use resilient_core::SharedData;
+///
+/// let shared = Mutex::new(SharedData::new());
+/// shared.lock().unwrap().add_value(1);
+/// let snapshot = SharedData::iter_snapshot(&shared);
+/// shared.lock().unwrap().add_value(2);  // copies the values the snapshot still shares
+/// assert_eq!(snapshot.values(), [1]);
+/// assert_eq!(snapshot.iter().sum::<i32>(), snapshot.sum());
+/// assert_eq!(shared.lock().unwrap().values(), [1, 2]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DataSnapshot {
+    data: Arc<Vec<i32>>,
+    sum: i32,
+}
+
+impl DataSnapshot {
+    /// The values at the time of the snapshot, in order.
+    pub fn iter(&self) -> std::slice::Iter<'_, i32> {
+        self.data.iter()
+    }
+
+    /// The values at the time of the snapshot, as a slice.
+    pub fn values(&self) -> &[i32] {
+        &self.data
+    }
+
+    /// The sum recorded with those values.
+    pub fn sum(&self) -> i32 {
+        self.sum
+    }
+}
+
+impl Default for SharedData {
+    fn default() -> Self {
+        SharedData::new()
+    }
+}
+
+impl SharedData {
+    /// No values, sum 0.
+    pub fn new() -> Self {
+        SharedData {
+            data: Arc::new(Vec::new()),
+            sum: 0,
+            processing: false,
+        }
+    }
+
+    /// Fields as given, consistent or not.
+    ///
+    /// For showing what a broken invariant looks like: `check_invariants`
+    /// reports whatever these parts get wrong.
+    pub fn from_parts(data: Vec<i32>, sum: i32, processing: bool) -> Self {
+        SharedData { data: Arc::new(data), sum, processing }
+    }
+
+    /// Append `value` and update the sum and the processing flag.
+    pub fn add_value(&mut self, value: i32) {
+        // Copies the Vec only if a snapshot still refers to it
+        Arc::make_mut(&mut self.data).push(value);
+        self.sum += value;
+        self.processing = !self.processing;
+    }
+
+    /// The values, in the order they were added.
+    pub fn values(&self) -> &[i32] {
+        &self.data
+    }
+
+    /// The running sum of the values.
+    pub fn sum(&self) -> i32 {
+        self.sum
+    }
+
+    /// Whether an odd number of values has been added.
+    pub fn is_processing(&self) -> bool {
+        self.processing
+    }
+
+    /// The number of values.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Whether no value has been added yet.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Take a snapshot under a brief lock - an Arc clone, not a copy - so
+    /// the caller can iterate as long as it likes without blocking writers.
+    /// A poisoned lock is recovered: the data is consistent after every
+    /// completed `add_value`.
+    pub fn iter_snapshot(shared: &Mutex<SharedData>) -> DataSnapshot {
+        let data = shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        data.debug_check_invariants();
+        DataSnapshot {
+            data: Arc::clone(&data.data),
+            sum: data.sum,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! The Library's Public Surface
+//!
+//! Two checks over the crate's own sources, so they run with every
+//! `cargo test`:
+//!
+//! - Every public type has a runnable example in its docs, and every
+//!   public function is called in an example in its file. The doctests
+//!   are the course's minimal examples for the whole library.
+//! - The public API is exactly the list in `api.txt`. A missing or
+//!   changed line breaks code students wrote against the prelude; a new
+//!   one must be added to the list on purpose.
+//!
+//! Both read the source text line by line, so they rely on rustfmt-style
+//! layout: one signature per line, impl and type blocks closing at
+//! column 0.
+
+use std::fs;
+use std::path::Path;
+
+mod doc_examples;
+
+use doc_examples::missing_examples;
+
+// Every .rs file of the crate, with its name, in a stable order
+fn sources() -> Vec<(String, String)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .unwrap_or_else(|error| panic!("cannot read {}: {}", dir.display(), error))
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "rs"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no sources in {}", dir.display());
+    paths
+        .into_iter()
+        .map(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            let source = fs::read_to_string(&path).unwrap_or_else(|error| panic!("{}: {}", name, error));
+            (name, source)
+        })
+        .collect()
+}
+
+// The public items a source file declares, one line each:
+// "fn SafeCounter::new() -> Self", "field DataHolder::value: i32",
+// "variant ResourceError::InvalidId(i32)", "fn Invariants::invariants(..)".
+// Items under #[doc(hidden)] are internal and left out
+fn public_api(source: &str) -> Vec<String> {
+    let (mut items, mut owner, mut hidden) = (Vec::new(), None::<String>, false);
+    let mut body = "";  // "enum" or "trait" while inside one, whose members are public too
+    for raw in source.lines() {
+        let line = raw.trim_start();
+        if line.starts_with("//") || line.is_empty() {
+            continue;
+        }
+        if line == "#[doc(hidden)]" {
+            hidden = true;
+            continue;
+        }
+        if line.starts_with("#[") {
+            continue;
+        }
+        if raw.starts_with('}') {
+            (owner, body) = (None, "");
+            continue;
+        }
+        let signature = line.trim_end_matches(['{', ';', ',', ' ']);
+        if let Some(rest) = raw.strip_prefix("impl") {
+            // Inherent impls own their pub fns; trait impls have none
+            let rest = match rest.strip_prefix('<') {
+                Some(generic) => generic.split_once("> ").map_or("", |(_, rest)| rest),
+                None => rest,
+            };
+            owner = (!rest.contains(" for ")).then(|| rest.trim().split(['<', ' ']).next().unwrap_or_default().to_string());
+        } else if let Some(item) = signature.strip_prefix("pub ").filter(|item| !item.starts_with("use ")) {
+            let words: Vec<&str> = item.split([' ', '<', '(', ':']).take(2).collect();
+            let kept = !std::mem::take(&mut hidden);
+            match (&words[..], owner.clone()) {
+                ([kind @ ("struct" | "enum" | "trait"), name], _) => {
+                    owner = raw.ends_with('{').then(|| name.to_string());
+                    body = if *kind == "struct" { "" } else { kind };
+                    if kept {
+                        items.push(item.split('(').next().unwrap_or_default().to_string());  // a tuple struct's field is private
+                    }
+                }
+                (["fn" | "const", ..], Some(owner)) if kept => {
+                    let (qualifiers, function) = item.split_once("fn ").unwrap_or_default();
+                    items.push(format!("{}fn {}::{}", qualifiers, owner, function));
+                }
+                (["fn" | "const" | "mod", ..], None) if kept => items.push(item.to_string()),
+                ([_, ..], Some(owner)) if kept && item.contains(':') => items.push(format!("field {}::{}", owner, item)),
+                _ => {}
+            }
+        } else if let (Some(owner), true) = (&owner, raw.starts_with("    ") && !raw.starts_with("     ")) {
+            match body {
+                "enum" if signature.starts_with(char::is_uppercase) => items.push(format!("variant {}::{}", owner, signature)),
+                "trait" => {
+                    if let Some((kind, member)) = signature.split_once(' ').filter(|(kind, _)| ["fn", "const"].contains(kind)) {
+                        items.push(format!("{} {}::{}", kind, owner, member.trim_end_matches(" {")));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    items
+}
+
+#[test]
+fn every_public_item_has_an_example() {
+    let missing: Vec<String> = sources().iter().flat_map(|(name, source)| missing_examples(name, source)).collect();
+    assert!(missing.is_empty(), "{}", missing.join("\n"));
+}
+
+#[test]
+fn public_api_matches_api_txt() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("api.txt");
+    let snapshot = fs::read_to_string(&path).unwrap_or_else(|error| panic!("cannot read {}: {}", path.display(), error));
+    let listed: Vec<&str> = snapshot.lines().filter(|line| !line.is_empty() && !line.starts_with('#')).collect();
+    let declared: Vec<String> = sources().iter().flat_map(|(_, source)| public_api(source)).collect();
+    assert!(!declared.is_empty(), "no public items found");
+
+    let removed: Vec<&str> = listed.iter().copied().filter(|line| !declared.iter().any(|item| item == line)).collect();
+    let added: Vec<&str> = declared.iter().map(String::as_str).filter(|item| !listed.contains(item)).collect();
+    let mut problems = Vec::new();
+    if !removed.is_empty() {
+        problems.push(format!("removed or changed, breaking student code:\n  {}", removed.join("\n  ")));
+    }
+    if !added.is_empty() {
+        problems.push(format!("public but not in api.txt:\n  {}", added.join("\n  ")));
+    }
+    assert!(problems.is_empty(), "{}", problems.join("\n"));
+}
@@ -0,0 +1,53 @@
+/*!
+ * Doc Example Coverage
+ *
+ * Whether a source file's docs give every public type a runnable example
+ * and call every public function in one. Shared by resilient_core's
+ * api.rs and the safety_demo library's tests/doc_examples.rs.
+ */
+
+// Whether `code` calls `function`, and not just a longer name ending in it
+fn calls(code: &str, function: &str) -> bool {
+    let call = format!("{}(", function);
+    code.match_indices(&call).any(|(at, _)| !code[..at].ends_with(|ch: char| ch.is_alphanumeric() || ch == '_'))
+}
+
+// What one source file's docs leave out: types without an example, and
+// public functions no example in the file calls
+pub fn missing_examples(name: &str, source: &str) -> Vec<String> {
+    let (mut examples, mut functions, mut missing) = (String::new(), Vec::new(), Vec::new());
+    let (mut in_example, mut documented) = (false, false);  // documented: the docs so far have an example
+    for line in source.lines().map(str::trim_start) {
+        if let Some(doc) = line.strip_prefix("///").or_else(|| line.strip_prefix("//!")) {
+            let doc = doc.strip_prefix(' ').unwrap_or(doc);
+            if doc.starts_with("```") {
+                in_example = !in_example;
+                documented = true;
+            } else if in_example {
+                examples.push_str(doc);
+                examples.push('\n');
+            }
+            continue;
+        }
+        if line.starts_with("#[") {
+            continue;  // attributes between the docs and the item
+        }
+        let words: Vec<&str> = line
+            .strip_prefix("pub ")
+            .unwrap_or_default()
+            .split(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+            .filter(|word| !word.is_empty())
+            .take(3)
+            .collect();
+        match words[..] {
+            ["struct" | "enum" | "trait", item, ..] if !documented => missing.push(format!("{}: {} has no example", name, item)),
+            ["fn", function, ..] | ["const", "fn", function] => functions.push(function.to_string()),
+            _ => {}
+        }
+        documented = false;
+    }
+    for function in functions.iter().filter(|function| !calls(&examples, function)) {
+        missing.push(format!("{}: no example calls {}", name, function));
+    }
+    missing
+}
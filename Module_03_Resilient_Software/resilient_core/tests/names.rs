@@ -0,0 +1,44 @@
+/*!
+ * Interning From Many Threads
+ *
+ * Threads that intern the same name at once, whether it is already in
+ * the table or new to it, all come away holding the one shared copy.
+ */
+
+#![cfg(feature = "std")]
+
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+use resilient_core::intern;
+
+const THREADS: usize = 8;
+
+// Every thread interns `name` at the same moment; returns their copies
+fn intern_together(name: &'static str) -> Vec<Arc<str>> {
+    let barrier = Arc::new(Barrier::new(THREADS));
+    let handles: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let barrier = Arc::clone(&barrier);
+            thread::spawn(move || {
+                barrier.wait();
+                intern(name)
+            })
+        })
+        .collect();
+    handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+}
+
+#[test]
+fn a_new_name_raced_by_many_threads_is_allocated_once() {
+    let copies = intern_together("names-test-new");
+    assert!(copies.iter().all(|copy| Arc::ptr_eq(copy, &copies[0])));
+    assert!(Arc::ptr_eq(&copies[0], &intern("names-test-new")));
+}
+
+#[test]
+fn an_interned_name_is_shared_by_every_reader() {
+    let first = intern("names-test-known");
+    let copies = intern_together("names-test-known");
+    assert!(copies.iter().all(|copy| Arc::ptr_eq(copy, &first)));
+}
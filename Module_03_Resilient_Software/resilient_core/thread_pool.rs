@@ -0,0 +1,170 @@
+/*!
+ * Thread Pool
+ *
+ * Spawning a thread per task pays for a new OS thread every time and puts
+ * no bound on how many run at once. A pool starts a fixed number of worker
+ * threads up front. `execute` puts a job on a queue - an mpsc channel whose
+ * one receiver the workers share behind a Mutex - and the next idle worker
+ * takes it. A worker holds the lock only while it takes a job, never while
+ * it runs one.
+ *
+ * A job that panics does not take its worker with it: the panic is caught
+ * and handed to whoever joins the job, as `JoinHandle::join` would. Dropping
+ * the pool closes the queue; the workers finish every job already queued,
+ * then exit, and the drop joins them.
+ */
+
+use std::fmt;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// A fixed set of worker threads that run queued jobs in turn.
+///
+/// Dropping the pool waits for every queued job to finish.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use resilient_core::{SafeCounter, ThreadPool};
+///
+/// let counter = Arc::new(SafeCounter::new());
+/// let pool = ThreadPool::new(4);
+/// assert_eq!(pool.size(), 4);
+/// let jobs: Vec<_> = (0..10)
+///     .map(|_| {
+///         let counter = Arc::clone(&counter);
+///         pool.execute(move || counter.increment())
+///     })
+///     .collect();
+/// for job in jobs {
+///     job.join().unwrap();
+/// }
+/// assert_eq!(counter.get_count(), 10);
+///
+/// assert!(ThreadPool::try_new(0).is_err());
+/// ```
+pub struct ThreadPool {
+    workers: Vec<JoinHandle<()>>,
+    queue: Option<Sender<Job>>,  // None once the pool is shutting down
+}
+
+impl ThreadPool {
+    /// A pool of `size` worker threads.
+    ///
+    /// # Panics
+    ///
+    /// If `size` is zero or a worker thread cannot be started; see
+    /// [`ThreadPool::try_new`].
+    pub fn new(size: usize) -> Self {
+        match ThreadPool::try_new(size) {
+            Ok(pool) => pool,
+            Err(error) => panic!("cannot start a pool of {} threads: {}", size, error),
+        }
+    }
+
+    /// A pool of `size` worker threads, or the reason it cannot be had:
+    /// a size of zero, or the OS refusing a thread. Workers already
+    /// started are shut down again.
+    pub fn try_new(size: usize) -> io::Result<Self> {
+        if size == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "a pool needs at least one thread"));
+        }
+        let (queue, jobs) = mpsc::channel::<Job>();
+        let jobs = Arc::new(Mutex::new(jobs));
+        let mut pool = ThreadPool { workers: Vec::with_capacity(size), queue: Some(queue) };
+        for index in 0..size {
+            let jobs = Arc::clone(&jobs);
+            let worker = thread::Builder::new()
+                .name(format!("pool-worker-{}", index))
+                .spawn(move || work(&jobs))?;
+            pool.workers.push(worker);
+        }
+        Ok(pool)
+    }
+
+    /// The number of worker threads.
+    pub fn size(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Queue `job` for the next idle worker. The handle returns its result.
+    pub fn execute<F, T>(&self, job: F) -> JobHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (sender, result) = mpsc::sync_channel(1);
+        let job: Job = Box::new(move || {
+            // Nobody may be waiting for the result; that is fine
+            let _ = sender.send(panic::catch_unwind(AssertUnwindSafe(job)));
+        });
+        if let Some(queue) = &self.queue {
+            // Fails only if no worker is left, and then the handle says so
+            let _ = queue.send(job);
+        }
+        JobHandle { result }
+    }
+}
+
+// Take jobs until the queue is closed and empty. The lock guard is a
+// temporary, dropped before the job runs
+fn work(jobs: &Mutex<Receiver<Job>>) {
+    loop {
+        let job = jobs.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).recv();
+        match job {
+            Ok(job) => job(),
+            Err(_) => break,
+        }
+    }
+}
+
+impl fmt::Debug for ThreadPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ThreadPool").field("size", &self.size()).finish()
+    }
+}
+
+impl Drop for ThreadPool {
+    // Close the queue, then wait for the workers to run what is left on it
+    fn drop(&mut self) {
+        drop(self.queue.take());
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();  // jobs' panics are caught, so workers never panic
+        }
+    }
+}
+
+/// The result of one job queued on a [`ThreadPool`].
+///
+/// # Examples
+///
+/// ```
+/// use resilient_core::ThreadPool;
+///
+/// let pool = ThreadPool::new(2);
+/// let answer = pool.execute(|| 6 * 7);
+/// let failed = pool.execute(|| -> i32 { panic!("job failed") });
+/// assert_eq!(answer.join().unwrap(), 42);
+/// assert!(failed.join().is_err());
+/// // The worker survived the panic
+/// assert_eq!(pool.execute(|| 1).join().unwrap(), 1);
+/// ```
+#[derive(Debug)]
+pub struct JobHandle<T> {
+    result: Receiver<thread::Result<T>>,
+}
+
+impl<T> JobHandle<T> {
+    /// Wait for the job to finish. Like `JoinHandle::join`, an `Err`
+    /// holds the panic the job raised.
+    pub fn join(self) -> thread::Result<T> {
+        self.result.recv().unwrap_or_else(|_| Err(Box::new("the job never ran: the pool has no workers left")))
+    }
+}
@@ -0,0 +1,77 @@
+/*!
+ * Resource Cache
+ *
+ * Keeps up to `capacity` open resources for reuse, each for at most `ttl`
+ * after it was inserted. A lookup is an Option: None when the resource was
+ * never cached, was pushed out as least recently used, or has expired.
+ * Expiry is lazy - a stale entry stays (and stays open) until a lookup
+ * finds it, then it is evicted and dropped right there. Debug builds check
+ * the capacity bound after every insert and lookup.
+ */
+
+use std::time::Duration;
+
+use resilient_core::invariants::Invariants;
+
+use crate::clock::Clock;
+use resilient_core::Resource;
+
+#[derive(Debug)]
+struct Entry {
+    name: String,
+    resource: Resource,
+    expires: Duration,  // clock time
+}
+
+#[derive(Debug, Invariants)]
+pub struct ResourceCache<C: Clock> {
+    clock: C,
+    capacity: usize,
+    ttl: Duration,
+    #[invariant(self.entries.len() <= self.capacity, "holds at most capacity entries")]
+    entries: Vec<Entry>,  // least recently used first
+}
+
+impl<C: Clock> ResourceCache<C> {
+    pub fn new(clock: C, capacity: usize, ttl: Duration) -> Self {
+        ResourceCache { clock, capacity: capacity.max(1), ttl, entries: Vec::new() }
+    }
+
+    // Cache `resource` under `name`; returns the name of the least recently
+    // used entry if it had to make room
+    pub fn insert(&mut self, name: &str, resource: Resource) -> Option<String> {
+        self.entries.retain(|entry| entry.name != name);
+        let evicted = if self.entries.len() >= self.capacity {
+            Some(self.entries.remove(0).name)
+        } else {
+            None
+        };
+        let expires = self.clock.now() + self.ttl;
+        self.entries.push(Entry { name: name.to_string(), resource, expires });
+        self.debug_check_invariants();
+        evicted
+    }
+
+    // The cached resource, unless it is missing or expired. An expired
+    // entry is evicted by this call.
+    pub fn get(&mut self, name: &str) -> Option<&Resource> {
+        let index = self.entries.iter().position(|entry| entry.name == name)?;
+        if self.clock.now() >= self.entries[index].expires {
+            self.entries.remove(index);
+            self.debug_check_invariants();
+            return None;
+        }
+        let entry = self.entries.remove(index);
+        self.entries.push(entry);
+        self.entries.last().map(|entry| &entry.resource)
+    }
+
+    // Entries held, including expired ones nobody has looked up yet
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
@@ -0,0 +1,440 @@
+/*!
+ * Resource Manager
+ *
+ * Owns a set of named resources (Database, FileSystem, Network, ...) that
+ * depend on each other. They are opened in dependency order and torn down
+ * in exactly the reverse order - when the manager is dropped, or at once
+ * when one of them fails to open, so a half-started system never leaks
+ * the resources that did come up.
+ *
+ * A plain Vec would drop its elements first to last, which would close
+ * the Database before the Network that still uses it. The manager pops
+ * them from the back instead.
+ *
+ * While the manager runs, a resource is borrowed with `with_resource`,
+ * which checks it out for the duration of a closure. A drop guard checks
+ * it back in when the closure returns - or marks it Failed when the
+ * closure panics, so nobody else picks up a half-updated resource.
+ *
+ * More resources can be acquired at run time in a category (Database,
+ * FileSystem, Network). Each category may have a quota on open resources
+ * and on bytes held; an acquisition over quota is refused with a typed
+ * QuotaExceeded before anything is opened, and the accounting report
+ * shows usage, peaks and refusals per category. The manager checks its
+ * own bookkeeping after every acquire and release in debug builds.
+ *
+ * Slot names are InlineStrings: a name up to 16 bytes long, which all of
+ * the demos' are, costs no allocation of its own.
+ *
+ * The manager is generic over what it owns. The demos give it
+ * resilient_core's Resource; tests/resource_manager.rs gives it values
+ * that record their own drops, to check the teardown order.
+ */
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::thread;
+
+use resilient_core::InlineString;
+use resilient_core::invariants::Invariants;
+
+use crate::units::{Bytes, Count, Quantity};
+use resilient_core::Resource;
+
+type Opener<R> = Box<dyn FnOnce() -> Result<R, String>>;
+type SlotName = InlineString<16>;
+
+struct Spec<R> {
+    name: String,
+    depends_on: Vec<String>,
+    open: Opener<R>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum StartError {
+    UnknownDependency { resource: String, dependency: String },
+    Cycle(Vec<String>),  // resources that wait on each other
+    Failed { resource: String, reason: String, torn_down: Vec<String> },
+}
+
+impl fmt::Display for StartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StartError::UnknownDependency { resource, dependency } => {
+                write!(f, "{} depends on unknown resource {}", resource, dependency)
+            }
+            StartError::Cycle(names) => write!(f, "dependency cycle between {}", names.join(", ")),
+            StartError::Failed { resource, reason, torn_down } => write!(
+                f,
+                "{} failed to open ({}); tore down {}",
+                resource,
+                reason,
+                if torn_down.is_empty() { "nothing".to_string() } else { torn_down.join(", ") }
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SlotState {
+    Available,
+    CheckedOut,  // inside a with_resource closure
+    Failed,      // a closure panicked while holding it
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AccessError {
+    NotFound(String),
+    InUse(String),
+    Failed(String),
+}
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccessError::NotFound(name) => write!(f, "no resource named {}", name),
+            AccessError::InUse(name) => write!(f, "{} is already checked out", name),
+            AccessError::Failed(name) => write!(f, "{} was marked failed", name),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Slot<R> {
+    name: SlotName,
+    resource: R,
+    state: Cell<SlotState>,
+    usage: Option<(Category, Quantity<u64, Bytes>)>,  // set for resources taken with acquire
+}
+
+// Checks the slot back in when dropped - Failed if dropped by a panic
+struct CheckIn<'a>(&'a Cell<SlotState>);
+
+impl Drop for CheckIn<'_> {
+    fn drop(&mut self) {
+        let state = if thread::panicking() { SlotState::Failed } else { SlotState::Available };
+        self.0.set(state);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    Database,
+    FileSystem,
+    Network,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quota {
+    pub max_open: Quantity<usize, Count>,
+    pub max_bytes: Quantity<u64, Bytes>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum QuotaLimit {
+    Open { max: Quantity<usize, Count> },
+    Bytes { max: Quantity<u64, Bytes>, in_use: Quantity<u64, Bytes>, requested: Quantity<u64, Bytes> },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct QuotaExceeded {
+    pub category: Category,
+    pub limit: QuotaLimit,
+}
+
+impl fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.limit {
+            QuotaLimit::Open { max } => write!(f, "{:?} quota of {} open resources reached", self.category, max),
+            QuotaLimit::Bytes { max, in_use, requested } => write!(
+                f,
+                "{:?} quota of {} exceeded ({} in use, {} requested)",
+                self.category, max, in_use, requested
+            ),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AcquireError {
+    QuotaExceeded(QuotaExceeded),
+    Duplicate(String),
+    Failed { resource: String, reason: String },
+}
+
+impl fmt::Display for AcquireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AcquireError::QuotaExceeded(exceeded) => write!(f, "{}", exceeded),
+            AcquireError::Duplicate(name) => write!(f, "{} is already open", name),
+            AcquireError::Failed { resource, reason } => write!(f, "{} failed to open ({})", resource, reason),
+        }
+    }
+}
+
+// Running totals for one category
+#[derive(Debug, Clone, Copy, Default)]
+struct Account {
+    open: Quantity<usize, Count>,
+    bytes: Quantity<u64, Bytes>,
+    peak_open: Quantity<usize, Count>,
+    peak_bytes: Quantity<u64, Bytes>,
+    rejected: u32,
+}
+
+// One row of the accounting report
+#[derive(Debug)]
+pub struct Usage {
+    pub category: Category,
+    pub quota: Option<Quota>,  // None: unlimited
+    pub open: Quantity<usize, Count>,
+    pub bytes: Quantity<u64, Bytes>,
+    pub peak_open: Quantity<usize, Count>,
+    pub peak_bytes: Quantity<u64, Bytes>,
+    pub rejected: u32,
+}
+
+pub struct ResourceManagerBuilder<R = Resource> {
+    specs: Vec<Spec<R>>,
+    quotas: BTreeMap<Category, Quota>,
+}
+
+impl<R> Default for ResourceManagerBuilder<R> {
+    fn default() -> Self {
+        ResourceManagerBuilder { specs: Vec::new(), quotas: BTreeMap::new() }
+    }
+}
+
+impl<R> ResourceManagerBuilder<R> {
+    // Declare a resource; declaration order does not matter
+    pub fn resource(
+        mut self,
+        name: &str,
+        depends_on: &[&str],
+        open: impl FnOnce() -> Result<R, String> + 'static,
+    ) -> Self {
+        self.specs.push(Spec {
+            name: name.to_string(),
+            depends_on: depends_on.iter().map(|dependency| dependency.to_string()).collect(),
+            open: Box::new(open),
+        });
+        self
+    }
+
+    // Limit what `acquire` may open in a category
+    pub fn quota(mut self, category: Category, quota: Quota) -> Self {
+        self.quotas.insert(category, quota);
+        self
+    }
+
+    // Open every resource after the ones it depends on (declaration order
+    // among equals). On the first failure everything opened so far is torn
+    // down in reverse before the error is returned.
+    pub fn start(self) -> Result<ResourceManager<R>, StartError> {
+        for spec in &self.specs {
+            if let Some(dependency) = spec
+                .depends_on
+                .iter()
+                .find(|dependency| !self.specs.iter().any(|other| &other.name == *dependency))
+            {
+                return Err(StartError::UnknownDependency {
+                    resource: spec.name.clone(),
+                    dependency: dependency.clone(),
+                });
+            }
+        }
+
+        let mut manager = ResourceManager { live: Vec::new(), quotas: self.quotas, accounts: BTreeMap::new() };
+        let mut pending = self.specs;
+        while !pending.is_empty() {
+            let ready = pending.iter().position(|spec| {
+                spec.depends_on.iter().all(|dependency| manager.live.iter().any(|slot| slot.name == dependency.as_str()))
+            });
+            let spec = match ready {
+                Some(index) => pending.remove(index),
+                None => return Err(StartError::Cycle(pending.into_iter().map(|spec| spec.name).collect())),
+            };
+            match (spec.open)() {
+                Ok(resource) => manager.live.push(Slot {
+                    name: SlotName::from(spec.name),
+                    resource,
+                    state: Cell::new(SlotState::Available),
+                    usage: None,
+                }),
+                Err(reason) => {
+                    return Err(StartError::Failed {
+                        resource: spec.name,
+                        reason,
+                        torn_down: manager.shutdown(),
+                    })
+                }
+            }
+        }
+        Ok(manager)
+    }
+}
+
+#[derive(Debug, Invariants)]
+#[invariant(self.accounts_match_live(), "accounts match acquired resources")]
+pub struct ResourceManager<R = Resource> {
+    #[invariant(self.names_unique(), "resource names are unique")]
+    live: Vec<Slot<R>>,  // in the order they were opened
+    quotas: BTreeMap<Category, Quota>,
+    #[invariant(
+        self.accounts.values().all(|account| account.peak_open >= account.open && account.peak_bytes >= account.bytes),
+        "peaks cover current usage"
+    )]
+    accounts: BTreeMap<Category, Account>,
+}
+
+impl<R> ResourceManager<R> {
+    pub fn builder() -> ResourceManagerBuilder<R> {
+        ResourceManagerBuilder::default()
+    }
+
+    pub fn startup_order(&self) -> Vec<&str> {
+        self.live.iter().map(|slot| slot.name.as_str()).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&R> {
+        self.slot(name).map(|slot| &slot.resource)
+    }
+
+    pub fn state(&self, name: &str) -> Option<SlotState> {
+        self.slot(name).map(|slot| slot.state.get())
+    }
+
+    // Run `body` with the resource checked out. It is checked back in
+    // afterwards, or marked Failed if `body` panics (the panic carries on
+    // to the caller).
+    pub fn with_resource<T>(&self, name: &str, body: impl FnOnce(&R) -> T) -> Result<T, AccessError> {
+        let slot = self.slot(name).ok_or_else(|| AccessError::NotFound(name.to_string()))?;
+        match slot.state.get() {
+            SlotState::Available => {}
+            SlotState::CheckedOut => return Err(AccessError::InUse(name.to_string())),
+            SlotState::Failed => return Err(AccessError::Failed(name.to_string())),
+        }
+        slot.state.set(SlotState::CheckedOut);
+        let _check_in = CheckIn(&slot.state);
+        Ok(body(&slot.resource))
+    }
+
+    // Open one more resource in `category`, holding `bytes`. The quota is
+    // checked first, so a refused acquisition never opens anything.
+    pub fn acquire(
+        &mut self,
+        name: &str,
+        category: Category,
+        bytes: Quantity<u64, Bytes>,
+        open: impl FnOnce() -> Result<R, String>,
+    ) -> Result<(), AcquireError> {
+        if self.slot(name).is_some() {
+            return Err(AcquireError::Duplicate(name.to_string()));
+        }
+        let account = self.accounts.entry(category).or_default();
+        if let Some(quota) = self.quotas.get(&category) {
+            let limit = if account.open >= quota.max_open {
+                Some(QuotaLimit::Open { max: quota.max_open })
+            } else if account.bytes + bytes > quota.max_bytes {
+                Some(QuotaLimit::Bytes { max: quota.max_bytes, in_use: account.bytes, requested: bytes })
+            } else {
+                None
+            };
+            if let Some(limit) = limit {
+                account.rejected += 1;
+                return Err(AcquireError::QuotaExceeded(QuotaExceeded { category, limit }));
+            }
+        }
+
+        let resource = open().map_err(|reason| AcquireError::Failed { resource: name.to_string(), reason })?;
+        account.open += Quantity::new(1);
+        account.bytes += bytes;
+        account.peak_open = account.peak_open.max(account.open);
+        account.peak_bytes = account.peak_bytes.max(account.bytes);
+        self.live.push(Slot {
+            name: SlotName::from(name),
+            resource,
+            state: Cell::new(SlotState::Available),
+            usage: Some((category, bytes)),
+        });
+        self.debug_check_invariants();
+        Ok(())
+    }
+
+    // Close an acquired resource and give its share of the quota back.
+    // Resources opened at startup stay until shutdown.
+    pub fn release(&mut self, name: &str) -> bool {
+        let index = match self.live.iter().position(|slot| slot.name == name && slot.usage.is_some()) {
+            Some(index) => index,
+            None => return false,
+        };
+        let slot = self.live.remove(index);
+        if let Some((category, bytes)) = slot.usage {
+            let account = self.accounts.entry(category).or_default();
+            account.open -= Quantity::new(1);
+            account.bytes -= bytes;
+        }
+        self.debug_check_invariants();
+        true
+    }
+
+    // Usage per category that has a quota or has been used
+    pub fn accounting(&self) -> Vec<Usage> {
+        let mut categories: Vec<Category> = self.quotas.keys().chain(self.accounts.keys()).copied().collect();
+        categories.sort();
+        categories.dedup();
+        categories
+            .into_iter()
+            .map(|category| {
+                let account = self.accounts.get(&category).copied().unwrap_or_default();
+                Usage {
+                    category,
+                    quota: self.quotas.get(&category).copied(),
+                    open: account.open,
+                    bytes: account.bytes,
+                    peak_open: account.peak_open,
+                    peak_bytes: account.peak_bytes,
+                    rejected: account.rejected,
+                }
+            })
+            .collect()
+    }
+
+    fn slot(&self, name: &str) -> Option<&Slot<R>> {
+        self.live.iter().find(|slot| slot.name == name)
+    }
+
+    fn names_unique(&self) -> bool {
+        self.live.iter().enumerate().all(|(index, slot)| self.live[..index].iter().all(|other| other.name != slot.name))
+    }
+
+    // Each category's running totals equal what its acquired slots hold
+    fn accounts_match_live(&self) -> bool {
+        self.accounts.iter().all(|(category, account)| {
+            let held = self.live.iter().filter_map(|slot| slot.usage).filter(|(used_in, _)| used_in == category);
+            let (open, bytes) = held.fold((0, 0), |(open, bytes), (_, size)| (open + 1, bytes + size.get()));
+            account.open.get() == open && account.bytes.get() == bytes
+        })
+    }
+
+    // Tear everything down now; returns the teardown order
+    pub fn shutdown(mut self) -> Vec<String> {
+        self.tear_down()
+    }
+
+    fn tear_down(&mut self) -> Vec<String> {
+        let mut order = Vec::new();
+        while let Some(slot) = self.live.pop() {
+            drop(slot.resource);
+            order.push(slot.name.into());
+        }
+        order
+    }
+}
+
+impl<R> Drop for ResourceManager<R> {
+    fn drop(&mut self) {
+        self.tear_down();
+    }
+}
@@ -0,0 +1,334 @@
+/*!
+ * Run Reports
+ *
+ * `runner report --out report.html` runs every demo, as `runner all`
+ * does, but captures what `all` prints: each demo's output, how long it
+ * took and how it exited. thread_safe runs with --verify and an event
+ * log. The log gives the time of each of its sections and the outcome
+ * of every invariant check in them. The result is a single file a TA
+ * can attach to a submission. It is HTML for a browser, or Markdown when
+ * the file name ends in .md.
+ */
+
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::process::{self, Command};
+use std::time::{Duration, Instant};
+
+use safety_demo::narration;
+use serde::Deserialize;
+
+use crate::{demo_path, start_error, DEMOS};
+
+const TITLE: &str = "Module 3 Demonstration Report";
+
+// The demo whose sections and invariant checks come from its event log
+const SECTIONED: &str = "thread_safe";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Html,
+    Markdown,
+}
+
+// One event log record, as far as the report reads it
+#[derive(Debug, Deserialize)]
+struct Record {
+    elapsed_ms: u64,
+    demo: String,
+    event: String,
+    #[serde(default)]
+    invariant: String,
+    #[serde(default)]
+    passed: bool,
+}
+
+// Every check of one invariant within a section
+#[derive(Debug)]
+struct Check {
+    invariant: String,
+    held: u64,
+    failed: u64,
+}
+
+#[derive(Debug)]
+struct Section {
+    name: String,
+    elapsed: Duration,
+    checks: Vec<Check>,
+    skipped: bool,  // not run, so it checked nothing
+}
+
+#[derive(Debug)]
+struct DemoRun {
+    command: &'static str,
+    summary: String,
+    exit: String,  // "ok", or how it failed
+    passed: bool,
+    elapsed: Duration,
+    output: String,
+    sections: Vec<Section>,  // thread_safe only
+}
+
+impl DemoRun {
+    // "(held) held, (failed) failed", or None without an event log
+    fn check_counts(&self) -> Option<(u64, u64)> {
+        if self.sections.is_empty() {
+            return None;
+        }
+        let checks = self.sections.iter().flat_map(|section| &section.checks);
+        Some(checks.fold((0, 0), |(held, failed), check| (held + check.held, failed + check.failed)))
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<(&Path, Format), String> {
+    let out = match args {
+        [flag, out] if flag == "--out" => Path::new(out),
+        _ => return Err("report needs --out <file.html|file.md>".to_string()),
+    };
+    let format = match out.extension().and_then(|extension| extension.to_str()) {
+        Some("html" | "htm") => Format::Html,
+        Some("md" | "markdown") => Format::Markdown,
+        _ => return Err(format!("cannot tell the format of {}: name it .html or .md", out.display())),
+    };
+    Ok((out, format))
+}
+
+// The sections of an event log, in the order they started, with their
+// invariant checks
+fn read_sections(log: &Path) -> Vec<Section> {
+    let text = fs::read_to_string(log).unwrap_or_default();
+    let records: Vec<Record> = text.lines().filter_map(|line| serde_json::from_str(line).ok()).collect();
+    let end = records.last().map_or(0, |record| record.elapsed_ms);
+
+    let mut sections: Vec<(Section, u64)> = Vec::new();  // with the start time
+    for record in &records {
+        match record.event.as_str() {
+            "demo_started" | "demo_skipped" => {
+                let skipped = record.event == "demo_skipped";
+                let section = Section { name: record.demo.clone(), elapsed: Duration::ZERO, checks: Vec::new(), skipped };
+                sections.push((section, record.elapsed_ms));
+            }
+            "invariant_checked" => {
+                let Some((section, _)) = sections.iter_mut().rev().find(|(section, _)| section.name == record.demo) else {
+                    continue;
+                };
+                let index = match section.checks.iter().position(|check| check.invariant == record.invariant) {
+                    Some(index) => index,
+                    None => {
+                        section.checks.push(Check { invariant: record.invariant.clone(), held: 0, failed: 0 });
+                        section.checks.len() - 1
+                    }
+                };
+                let check = &mut section.checks[index];
+                if record.passed {
+                    check.held += 1;
+                } else {
+                    check.failed += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    // A section runs until the next one starts
+    let starts: Vec<u64> = sections.iter().map(|(_, start)| *start).chain([end]).collect();
+    sections
+        .into_iter()
+        .zip(starts.windows(2))
+        .map(|((mut section, _), window)| {
+            section.elapsed = Duration::from_millis(window[1].saturating_sub(window[0]));
+            section
+        })
+        .collect()
+}
+
+fn run_captured(command: &'static str, binary: &str, summary: String, vars: &[(String, String)]) -> Result<DemoRun, String> {
+    let mut child = Command::new(demo_path(binary)?);
+    child.envs(vars.iter().map(|(var, value)| (var, value)));
+    let log = (binary == SECTIONED).then(|| env::temp_dir().join(format!("runner-report-{}.jsonl", process::id())));
+    if let Some(log) = &log {
+        let _ = fs::remove_file(log);  // the log appends
+        child.arg("--verify").env("SAFETY_DEMO_EVENT_LOG", log);
+    }
+
+    let start = Instant::now();
+    let output = child.output().map_err(|error| start_error(binary, error))?;
+    let elapsed = start.elapsed();
+    let sections = match &log {
+        Some(log) => {
+            let sections = read_sections(log);
+            let _ = fs::remove_file(log);
+            sections
+        }
+        None => Vec::new(),
+    };
+
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stderr.trim().is_empty() {
+        text.push_str("\n--- stderr ---\n");
+        text.push_str(&stderr);
+    }
+    Ok(DemoRun {
+        command,
+        summary,
+        exit: if output.status.success() { narration!("runner.ok") } else { narration!("runner.failed", status = output.status) },
+        passed: output.status.success(),
+        elapsed,
+        output: text,
+        sections,
+    })
+}
+
+fn seconds(duration: Duration) -> String {
+    format!("{:.2}s", duration.as_secs_f64())
+}
+
+fn check_counts_text(run: &DemoRun) -> String {
+    match run.check_counts() {
+        Some((held, failed)) => format!("{} held, {} failed", held, failed),
+        None => "-".to_string(),
+    }
+}
+
+fn check_result(check: &Check) -> String {
+    match (check.held, check.failed) {
+        (1, 0) => "held".to_string(),
+        (held, 0) => format!("held ({} checks)", held),
+        (held, failed) => format!("VIOLATED ({} of {} checks)", failed, held + failed),
+    }
+}
+
+// (section, time, invariant, result) for every invariant check; the
+// section and its time only on its first row
+fn section_rows(sections: &[Section]) -> Vec<[String; 4]> {
+    let mut rows = Vec::new();
+    for section in sections {
+        let (name, elapsed) = (section.name.clone(), seconds(section.elapsed));
+        if section.skipped {
+            rows.push([name, elapsed, "-".to_string(), "skipped".to_string()]);
+            continue;
+        }
+        if section.checks.is_empty() {
+            rows.push([name, elapsed, "-".to_string(), "-".to_string()]);
+            continue;
+        }
+        for (index, check) in section.checks.iter().enumerate() {
+            let (name, elapsed) = if index == 0 { (name.clone(), elapsed.clone()) } else { Default::default() };
+            rows.push([name, elapsed, check.invariant.clone(), check_result(check)]);
+        }
+    }
+    rows
+}
+
+fn headline(runs: &[DemoRun], total: Duration) -> String {
+    let passed = runs.iter().filter(|run| run.passed).count();
+    narration!("run_report.headline", demos = runs.len(), passed, failed = runs.len() - passed, seconds = seconds(total))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn html_row(cells: &[String], header: bool, class: Option<&str>) -> String {
+    let tag = if header { "th" } else { "td" };
+    let class = class.map(|class| format!(" class=\"{}\"", class)).unwrap_or_default();
+    let cells: String = cells.iter().map(|cell| format!("<{0}>{1}</{0}>", tag, escape_html(cell))).collect();
+    format!("<tr{}>{}</tr>\n", class, cells)
+}
+
+fn render_html(runs: &[DemoRun], total: Duration) -> String {
+    let mut html = String::new();
+    let _ = writeln!(html, "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>", TITLE);
+    html.push_str("<style>\n\
+        body { font-family: sans-serif; margin: 2em auto; max-width: 72em; }\n\
+        table { border-collapse: collapse; margin-bottom: 1em; }\n\
+        th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }\n\
+        .failed { color: #b00020; font-weight: bold; }\n\
+        pre { background: #f6f6f6; padding: 1em; overflow-x: auto; }\n\
+        </style>\n</head>\n<body>\n");
+    let _ = writeln!(html, "<h1>{}</h1>\n<p>{}</p>", TITLE, escape_html(&headline(runs, total)));
+
+    html.push_str("<h2>Summary</h2>\n<table>\n");
+    html.push_str(&html_row(&["Demo", "What it shows", "Result", "Time", "Invariant checks"].map(String::from), true, None));
+    for run in runs {
+        let cells = [run.command.to_string(), run.summary.clone(), run.exit.clone(), seconds(run.elapsed), check_counts_text(run)];
+        html.push_str(&html_row(&cells, false, (!run.passed).then_some("failed")));
+    }
+    html.push_str("</table>\n");
+
+    for run in runs {
+        let _ = writeln!(html, "<h2 id=\"{0}\">{0}</h2>\n<p>{1} - {2} in {3}</p>",
+                         run.command, escape_html(&run.summary), escape_html(&run.exit), seconds(run.elapsed));
+        if !run.sections.is_empty() {
+            html.push_str("<table>\n");
+            html.push_str(&html_row(&["Section", "Time", "Invariant", "Result"].map(String::from), true, None));
+            for row in section_rows(&run.sections) {
+                let failed = row[3].starts_with("VIOLATED");
+                html.push_str(&html_row(&row, false, failed.then_some("failed")));
+            }
+            html.push_str("</table>\n");
+        }
+        let _ = writeln!(html, "<details>\n<summary>Output ({} lines)</summary>\n<pre>{}</pre>\n</details>",
+                         run.output.lines().count(), escape_html(&run.output));
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn markdown_row(cells: &[String]) -> String {
+    let cells: Vec<String> = cells.iter().map(|cell| cell.replace('|', "\\|")).collect();
+    format!("| {} |\n", cells.join(" | "))
+}
+
+fn markdown_header(cells: &[&str]) -> String {
+    format!("| {} |\n|{}\n", cells.join(" | "), "---|".repeat(cells.len()))
+}
+
+fn render_markdown(runs: &[DemoRun], total: Duration) -> String {
+    let mut markdown = format!("# {}\n\n{}\n\n## Summary\n\n", TITLE, headline(runs, total));
+    markdown.push_str(&markdown_header(&["Demo", "What it shows", "Result", "Time", "Invariant checks"]));
+    for run in runs {
+        let cells = [run.command.to_string(), run.summary.clone(), run.exit.clone(), seconds(run.elapsed), check_counts_text(run)];
+        markdown.push_str(&markdown_row(&cells));
+    }
+
+    for run in runs {
+        let _ = write!(markdown, "\n## {}\n\n{} - {} in {}\n\n", run.command, run.summary, run.exit, seconds(run.elapsed));
+        if !run.sections.is_empty() {
+            markdown.push_str(&markdown_header(&["Section", "Time", "Invariant", "Result"]));
+            for row in section_rows(&run.sections) {
+                markdown.push_str(&markdown_row(&row));
+            }
+            markdown.push('\n');
+        }
+        let _ = write!(markdown, "<details>\n<summary>Output ({} lines)</summary>\n\n```text\n{}\n```\n\n</details>\n",
+                       run.output.lines().count(), run.output.trim_end());
+    }
+    markdown
+}
+
+// `report --out <file>`: run every demo and write the report; returns
+// the exit code, 1 if any demo failed
+pub fn report_command(args: &[String], vars: &[(String, String)]) -> Result<i32, String> {
+    let (out, format) = parse_args(args)?;
+    let start = Instant::now();
+    let mut runs = Vec::new();
+    for (command, binary, summary) in DEMOS {
+        println!("{}", narration!("run_report.running", command));
+        let run = run_captured(command, binary, summary(), vars)?;
+        println!("{}", narration!("run_report.finished", command, exit = run.exit, seconds = seconds(run.elapsed)));
+        runs.push(run);
+    }
+
+    let total = start.elapsed();
+    let text = match format {
+        Format::Html => render_html(&runs, total),
+        Format::Markdown => render_markdown(&runs, total),
+    };
+    fs::write(out, text).map_err(|error| narration!("run_report.write_failed", path = out.display(), error))?;
+    println!("{}", narration!("run_report.written", headline = headline(&runs, total), path = out.display()));
+    Ok(if runs.iter().all(|run| run.passed) { 0 } else { 1 })
+}
@@ -0,0 +1,187 @@
+/*!
+ * Demo Runner
+ *
+ * One entry point for every demonstration in this module. Each demo is
+ * still its own binary with its own main; the runner starts the one a
+ * subcommand names, next to its own executable, and passes the rest of
+ * the command line through:
+ *
+ *   runner thread-safe [args...]   thread_safe [args...]
+ *   runner option-safe             option_safe
+ *   runner memory-safe             memory_safe
+ *   runner buffer-safe             buffer_safe
+ *   runner all                     all four in turn, then a summary
+ *   runner report --out <file>     all four, captured into a report
+ *
+ * Flags before the subcommand are shared by every demo it starts. They
+ * are handed on as SAFETY_DEMO_* variables, the way lab machines set
+ * them, so a demo reads them whether or not it parses flags itself.
+ * Besides the switches below, that is every flag in SETTING_FLAGS
+ * (--threads, --iterations, --sleep-ms, --seed).
+ */
+
+use std::env;
+use std::io;
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus};
+
+mod run_report;
+
+use safety_demo::narration;
+use safety_demo::setting_flags::SETTING_FLAGS;
+
+// What a demo shows, from the message catalog
+type Summary = fn() -> String;
+
+// Subcommand, binary, what it shows
+const DEMOS: [(&str, &str, Summary); 4] = [
+    ("thread-safe", "thread_safe", || narration!("runner.thread_safe_summary")),
+    ("option-safe", "option_safe", || narration!("runner.option_safe_summary")),
+    ("memory-safe", "memory_safe", || narration!("runner.memory_safe_summary")),
+    ("buffer-safe", "buffer_safe", || narration!("runner.buffer_safe_summary")),
+];
+
+// Shared flag, the variable it sets, and the value a bare flag sets
+const SHARED_FLAGS: [(&str, &str, Option<&str>); 4] = [
+    ("--level", "SAFETY_DEMO_LEVEL", None),
+    ("--interleaved", "SAFETY_DEMO_OUTPUT", Some("interleaved")),
+    ("--interactive", "SAFETY_DEMO_INTERACTIVE", Some("on")),
+    ("--verify", "SAFETY_DEMO_VERIFY", Some("on")),
+];
+
+fn usage() -> String {
+    let mut usage = narration!("runner.usage");
+    for (command, _, summary) in DEMOS {
+        usage.push_str(&format!("  {:<12} {}\n", command, summary()));
+    }
+    usage.push_str(&format!("  {:<12} {}\n", "all", narration!("runner.all_summary")));
+    usage.push_str(&format!("  {:<12} {}\n", "report", narration!("runner.report_summary")));
+    usage
+}
+
+// The shared flag `arg`, the variable it sets, and the value a bare flag sets
+fn shared_flag(arg: &str) -> Option<(&'static str, String, Option<&'static str>)> {
+    let shared = SHARED_FLAGS.iter().find(|(flag, ..)| *flag == arg).map(|&(flag, var, bare)| (flag, var.to_string(), bare));
+    shared.or_else(|| {
+        let &(flag, setting) = SETTING_FLAGS.iter().find(|(flag, _)| *flag == arg)?;
+        Some((flag, format!("SAFETY_DEMO_{}", setting), None))
+    })
+}
+
+// Take the shared flags leading `args` as (variable, value) pairs
+fn take_shared_flags(args: &mut Vec<String>) -> Result<Vec<(String, String)>, String> {
+    let mut vars = Vec::new();
+    while let Some((flag, var, bare)) = args.first().and_then(|arg| shared_flag(arg)) {
+        args.remove(0);
+        let value = match bare {
+            Some(value) => value.to_string(),
+            None if args.is_empty() => return Err(narration!("runner.needs_value", flag)),
+            None => args.remove(0),
+        };
+        vars.push((var, value));
+    }
+    Ok(vars)
+}
+
+// The command that builds every demo in this runner's profile
+fn build_command() -> String {
+    let profile = env::current_exe().ok().and_then(|exe| Some(exe.parent()?.file_name()?.to_string_lossy().into_owned()));
+    match profile.as_deref() {
+        Some("release") => "cargo build --bins --release".to_string(),
+        Some(profile) if profile != "debug" => format!("cargo build --bins --profile {}", profile),
+        _ => "cargo build --bins".to_string(),
+    }
+}
+
+fn not_built(binaries: &[&str]) -> String {
+    narration!("runner.not_built", binaries = binaries.join(", "), command = build_command())
+}
+
+// The demo binary built alongside this one
+fn demo_path(binary: &str) -> Result<PathBuf, String> {
+    let exe = env::current_exe().map_err(|error| narration!("runner.cannot_locate", error))?;
+    let path = exe.with_file_name(format!("{}{}", binary, env::consts::EXE_SUFFIX));
+    if !path.exists() {
+        return Err(not_built(&[binary]));
+    }
+    Ok(path)
+}
+
+// Every demo is built, or which are not: checked before `all` or
+// `report` starts the first one, so neither stops halfway
+fn check_all_built() -> Result<(), String> {
+    let missing: Vec<&str> = DEMOS.iter().map(|&(_, binary, _)| binary).filter(|binary| demo_path(binary).is_err()).collect();
+    if missing.is_empty() { Ok(()) } else { Err(not_built(&missing)) }
+}
+
+// Why `binary` did not start. NotFound here means it vanished after
+// demo_path found it, as when a rebuild is replacing it
+fn start_error(binary: &str, error: io::Error) -> String {
+    match error.kind() {
+        io::ErrorKind::NotFound => not_built(&[binary]),
+        _ => narration!("runner.cannot_start", binary, error),
+    }
+}
+
+fn run_demo(binary: &str, args: &[String], vars: &[(String, String)]) -> Result<ExitStatus, String> {
+    Command::new(demo_path(binary)?)
+        .args(args)
+        .envs(vars.iter().map(|(var, value)| (var, value)))
+        .status()
+        .map_err(|error| start_error(binary, error))
+}
+
+fn exit_code(status: ExitStatus) -> i32 {
+    status.code().unwrap_or(1)
+}
+
+fn run_all(vars: &[(String, String)]) -> Result<i32, String> {
+    let mut results = Vec::new();
+    for (command, binary, _) in DEMOS {
+        println!("{}", narration!("runner.banner", command));
+        results.push((command, run_demo(binary, &[], vars)?));
+    }
+    println!("{}", narration!("runner.summary_banner"));
+    for (command, status) in &results {
+        let outcome = if status.success() { narration!("runner.ok") } else { narration!("runner.failed", status) };
+        println!("  {:<12} {}", command, outcome);
+    }
+    Ok(if results.iter().all(|(_, status)| status.success()) { 0 } else { 1 })
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let vars = match take_shared_flags(&mut args) {
+        Ok(vars) => vars,
+        Err(error) => {
+            eprintln!("{}", narration!("runner.flag_error", error, usage = usage()));
+            std::process::exit(2);
+        }
+    };
+    let result = match args.first().map(String::as_str) {
+        Some("all") if args.len() > 1 => {
+            eprintln!("{}", narration!("runner.all_takes_no_arguments", usage = usage()));
+            std::process::exit(2);
+        }
+        Some("all") => check_all_built().and_then(|()| run_all(&vars)),
+        Some("report") => check_all_built().and_then(|()| run_report::report_command(&args[1..], &vars)),
+        Some(command) => match DEMOS.iter().find(|(name, ..)| *name == command) {
+            Some((_, binary, _)) => run_demo(binary, &args[1..], &vars).map(exit_code),
+            None => {
+                eprintln!("{}", narration!("runner.unknown_demo", command, usage = usage()));
+                std::process::exit(2);
+            }
+        },
+        None => {
+            eprint!("{}", usage());
+            std::process::exit(2);
+        }
+    };
+    match result {
+        Ok(code) => std::process::exit(code),
+        Err(error) => {
+            eprintln!("{}", narration!("runner.error", error));
+            std::process::exit(2);
+        }
+    }
+}
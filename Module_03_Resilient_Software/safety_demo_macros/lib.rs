@@ -0,0 +1,209 @@
+/*!
+ * #[safety_demo] Attribute
+ *
+ * Declares a thread_safe demonstration in one place:
+ *
+ *   #[safety_demo(
+ *       name = "counter_safety",
+ *       module = "Data Races",
+ *       description = "Threads increment one atomic counter without losing an update",
+ *       invariants("no lost increments")
+ *   )]
+ *   fn demonstrate_counter_safety(config: &EnvConfig) { ... }
+ *
+ * The function body is wrapped in a DemoRecorder, which records the demo's
+ * start and, at the end, reports every declared invariant as held,
+ * violated, or never checked (a declared invariant the demo forgot to
+ * check counts as a failure). The function returns the report's Outcome.
+ * Next to the function a `Section` constant named after the demo
+ * (COUNTER_SAFETY) carries the name, module, description, invariants and
+ * entry point, so the SECTIONS registry only lists constants. A demo may
+ * take `&EnvConfig` or nothing, and returns nothing itself.
+ *
+ * A bare `needs_unwind` marks a demo that catches a panic in-process. In
+ * a build whose panics abort, it says it is skipped and returns an
+ * Outcome marked skipped, which does not pass, instead of running.
+ *
+ * #[derive(Invariants)] Attribute
+ *
+ * States what must always hold for a struct, next to the fields it is
+ * about:
+ *
+ *   #[derive(Invariants)]
+ *   struct SharedData {
+ *       #[invariant(self.sum == self.data.iter().sum::<i32>(), "sum matches data")]
+ *       sum: i32,
+ *       ...
+ *   }
+ *
+ * `#[invariant(expr)]` goes on a field, or on the struct when it spans
+ * several fields; the optional string labels it in reports (otherwise the
+ * expression is the label). The derive implements
+ * ::resilient_core::invariants::Invariants, whose check_invariants
+ * evaluates every expression in declaration order. Import it from there,
+ * where resilient_core re-exports it next to the trait.
+ */
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Expr, ExprLit, Ident, ItemFn, Lit, LitStr, Meta, ReturnType, Token};
+
+struct DemoArgs {
+    name: LitStr,
+    module: LitStr,
+    description: LitStr,
+    invariants: Vec<LitStr>,
+    needs_unwind: bool,
+}
+
+fn string_value(meta: &Meta) -> syn::Result<LitStr> {
+    match meta {
+        Meta::NameValue(pair) => match &pair.value {
+            Expr::Lit(ExprLit { lit: Lit::Str(text), .. }) => Ok(text.clone()),
+            other => Err(syn::Error::new_spanned(other, "expected a string literal")),
+        },
+        other => Err(syn::Error::new_spanned(other, "expected `key = \"value\"`")),
+    }
+}
+
+fn parse_args(metas: Punctuated<Meta, Token![,]>) -> syn::Result<DemoArgs> {
+    let (mut name, mut module, mut description, mut invariants) = (None, None, None, Vec::new());
+    let mut needs_unwind = false;
+    for meta in metas {
+        if meta.path().is_ident("needs_unwind") {
+            meta.require_path_only()?;
+            needs_unwind = true;
+        } else if meta.path().is_ident("name") {
+            name = Some(string_value(&meta)?);
+        } else if meta.path().is_ident("module") {
+            module = Some(string_value(&meta)?);
+        } else if meta.path().is_ident("description") {
+            description = Some(string_value(&meta)?);
+        } else if meta.path().is_ident("invariants") {
+            let list = meta.require_list()?;
+            invariants = list.parse_args_with(Punctuated::<LitStr, Token![,]>::parse_terminated)?.into_iter().collect();
+        } else {
+            return Err(syn::Error::new_spanned(meta.path(), "unknown key (expected name, module, description, invariants or needs_unwind)"));
+        }
+    }
+    let missing = |key| syn::Error::new(Span::call_site(), format!("#[safety_demo] needs `{} = \"...\"`", key));
+    Ok(DemoArgs {
+        name: name.ok_or_else(|| missing("name"))?,
+        module: module.ok_or_else(|| missing("module"))?,
+        description: description.ok_or_else(|| missing("description"))?,
+        invariants,
+        needs_unwind,
+    })
+}
+
+#[proc_macro_attribute]
+pub fn safety_demo(args: TokenStream, item: TokenStream) -> TokenStream {
+    let metas = parse_macro_input!(args with Punctuated::<Meta, Token![,]>::parse_terminated);
+    let function = parse_macro_input!(item as ItemFn);
+    let args = match parse_args(metas) {
+        Ok(args) => args,
+        Err(error) => return error.to_compile_error().into(),
+    };
+    if function.sig.inputs.len() > 1 {
+        return syn::Error::new_spanned(&function.sig.inputs, "a demo takes `&EnvConfig` or nothing")
+            .to_compile_error()
+            .into();
+    }
+    if let ReturnType::Type(_, output) = &function.sig.output {
+        return syn::Error::new_spanned(output, "a demo returns nothing; its DemoRecorder supplies the Outcome")
+            .to_compile_error()
+            .into();
+    }
+
+    let DemoArgs { name, module, description, invariants, needs_unwind } = args;
+    let ItemFn { attrs, vis, mut sig, block } = function;
+    sig.output = syn::parse_quote! { -> crate::demo_report::Outcome };
+    let ident = &sig.ident;
+    let section = Ident::new(&name.value().to_uppercase(), name.span());
+    let run = if sig.inputs.is_empty() {
+        quote! { |_| #ident() }
+    } else {
+        quote! { #ident }
+    };
+    let skip = if needs_unwind {
+        quote! {
+            if !crate::panic_strategy::UNWINDS {
+                return crate::panic_strategy::skip(#name);
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        #(#attrs)*
+        #vis #sig {
+            #skip
+            let report = crate::demo_report::DemoRecorder::begin(#name, #module, &[#(#invariants),*]);
+            #[allow(clippy::redundant_closure_call)]
+            (|| #block)();
+            report.finish()
+        }
+
+        #vis const #section: crate::demo_report::Section = crate::demo_report::Section {
+            name: #name,
+            module: #module,
+            description: #description,
+            invariants: &[#(#invariants),*],
+            needs_unwind: #needs_unwind,
+            run: #run,
+        };
+    }
+    .into()
+}
+
+// One `#[invariant(expr)]` or `#[invariant(expr, "label")]`
+fn parse_invariant(attr: &Attribute) -> syn::Result<(Expr, String)> {
+    attr.parse_args_with(|input: syn::parse::ParseStream| {
+        let expr: Expr = input.parse()?;
+        let label = if input.is_empty() {
+            quote!(#expr).to_string()
+        } else {
+            input.parse::<Token![,]>()?;
+            input.parse::<LitStr>()?.value()
+        };
+        Ok((expr, label))
+    })
+}
+
+#[proc_macro_derive(Invariants, attributes(invariant))]
+pub fn derive_invariants(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return syn::Error::new_spanned(&input.ident, "Invariants can only be derived for a struct")
+                .to_compile_error()
+                .into()
+        }
+    };
+
+    let attrs = input.attrs.iter().chain(fields.iter().flat_map(|field| field.attrs.iter()));
+    let invariants: syn::Result<Vec<(Expr, String)>> =
+        attrs.filter(|attr| attr.path().is_ident("invariant")).map(parse_invariant).collect();
+    let (exprs, labels): (Vec<Expr>, Vec<String>) = match invariants {
+        Ok(invariants) => invariants.into_iter().unzip(),
+        Err(error) => return error.to_compile_error().into(),
+    };
+
+    let ident = &input.ident;
+    let type_name = ident.to_string();
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::resilient_core::invariants::Invariants for #ident #type_generics #where_clause {
+            const TYPE_NAME: &'static str = #type_name;
+
+            fn invariants(&self) -> ::std::vec::Vec<(&'static str, bool)> {
+                ::std::vec![#((#labels, #exprs)),*]
+            }
+        }
+    }
+    .into()
+}
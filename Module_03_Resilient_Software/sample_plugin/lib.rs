@@ -0,0 +1,70 @@
+/*!
+ * Sample Demo Plugin
+ *
+ * A demo built outside thread_safe and loaded at run time. It depends on
+ * nothing but the ABI file, exports `safety_demo_plugin`, and talks to the
+ * host only through the HostApi it is handed. The demo itself: a
+ * fetch_add counter shared by four threads, with its invariant reported
+ * back to the host's event log.
+ */
+
+#[path = "../plugin_abi.rs"]
+#[allow(dead_code)]  // the loader's half of the ABI
+mod plugin_abi;
+
+use std::ffi::CString;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use plugin_abi::{HostApi, PluginDescriptor, PLUGIN_ABI_VERSION};
+
+static DESCRIPTOR: PluginDescriptor = PluginDescriptor {
+    abi_version: PLUGIN_ABI_VERSION,
+    name: c"sample_plugin".as_ptr(),
+    summary: c"Four threads share an AtomicUsize counter".as_ptr(),
+    run: Some(run),
+};
+
+#[no_mangle]
+pub extern "C" fn safety_demo_plugin() -> *const PluginDescriptor {
+    &DESCRIPTOR
+}
+
+fn say(host: &HostApi, line: &str) {
+    if let Ok(line) = CString::new(line) {
+        (host.say)(line.as_ptr());
+    }
+}
+
+fn check(host: &HostApi, invariant: &str, passed: bool) {
+    if let Ok(invariant) = CString::new(invariant) {
+        (host.check_invariant)(invariant.as_ptr(), passed);
+    }
+}
+
+extern "C" fn run(host: &HostApi) {
+    // A panic must not cross the C boundary: report it instead
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| counter_demo(host)));
+    if outcome.is_err() {
+        check(host, "sample_plugin finished without panicking", false);
+    }
+}
+
+fn counter_demo(host: &HostApi) {
+    const THREADS: usize = 4;
+    const INCREMENTS: usize = 10_000;
+    let counter = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|| {
+                for _ in 0..INCREMENTS {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+    let total = counter.load(Ordering::Relaxed);
+    say(host, &format!("{} threads x {} increments = {}", THREADS, INCREMENTS, total));
+    check(host, "plugin counter has no lost updates", total == THREADS * INCREMENTS);
+}
@@ -0,0 +1,484 @@
+/*!
+ * Sandbox Runner
+ *
+ * Some demonstrations are meant to go wrong. The advanced level adds the
+ * unsafe counterparts (lost updates, split locks, a receiver that hangs
+ * up), a hard budget ends the whole process, and a plugin is foreign code
+ * that can segfault. Run in-process, any of these takes the rest of the
+ * lecture down with it.
+ *
+ * `sandbox` runs each section (and each plugin, with the plugins feature)
+ * at the advanced level in its own child process. Nothing a child does
+ * can reach the parent. It reports how each child ended - exit code,
+ * signal, or killed after a timeout - with the tail of its stderr.
+ * `--probes` runs small children that fail on purpose, one per way a
+ * process can die, to show what each looks like from outside. A panic
+ * exits with 101 where panics unwind, and is a SIGABRT where they abort.
+ *
+ * Children do not write the event log: one killed mid-write would tear it.
+ */
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::budget::{self, CANCELLED_EXIT_CODE};
+use crate::env_config::EnvConfig;
+use crate::platform::{self, INTERRUPTED_EXIT_CODE};
+use crate::term::{self, Severity};
+
+use crate::lecture;
+use crate::panic_strategy;
+use crate::sections::SECTIONS;
+use crate::setting_flags;
+
+const STDERR_TAIL: usize = 6;      // stderr lines kept per child
+pub const GRACE: Duration = Duration::from_secs(5);  // past the hard budget
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+pub const PANIC_EXIT_CODE: i32 = 101;  // what Rust exits with after a panic in main
+
+// How a child process ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum Exit {
+    Passed,
+    Failed(i32),         // non-zero exit code
+    Cancelled,           // over its hard budget
+    Signaled(i32),       // killed by a signal
+    TimedOut,            // killed by the sandbox
+    NotStarted(String),
+}
+
+impl Exit {
+    fn from_status(status: ExitStatus) -> Exit {
+        match status.code() {
+            Some(0) => Exit::Passed,
+            Some(CANCELLED_EXIT_CODE) => Exit::Cancelled,
+            Some(code) => Exit::Failed(code),
+            None => Exit::Signaled(signal_of(status)),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn signal_of(status: ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal().unwrap_or(0)
+}
+
+#[cfg(not(unix))]
+fn signal_of(_status: ExitStatus) -> i32 {
+    0
+}
+
+fn signal_name(signal: i32) -> String {
+    match signal {
+        4 => "SIGILL".to_string(),
+        6 => "SIGABRT".to_string(),
+        7 => "SIGBUS".to_string(),
+        8 => "SIGFPE".to_string(),
+        9 => "SIGKILL".to_string(),
+        11 => "SIGSEGV".to_string(),
+        15 => "SIGTERM".to_string(),
+        other => narration!("sandbox.signal", number = other),
+    }
+}
+
+impl fmt::Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Exit::Passed => f.write_str(&narration!("sandbox.exit_ok")),
+            Exit::Failed(PANIC_EXIT_CODE) => f.write_str(&narration!("sandbox.exit_panicked", code = PANIC_EXIT_CODE)),
+            Exit::Failed(INTERRUPTED_EXIT_CODE) => {
+                f.write_str(&narration!("sandbox.exit_interrupted", code = INTERRUPTED_EXIT_CODE))
+            }
+            Exit::Failed(code) => f.write_str(&narration!("sandbox.exit_failed", code)),
+            Exit::Cancelled => f.write_str(&narration!("sandbox.exit_cancelled")),
+            Exit::Signaled(signal) => f.write_str(&narration!("sandbox.exit_signaled", signal = signal_name(*signal))),
+            Exit::TimedOut => f.write_str(&narration!("sandbox.exit_timed_out")),
+            Exit::NotStarted(reason) => f.write_str(&narration!("sandbox.exit_not_started", reason)),
+        }
+    }
+}
+
+// A child's stdout line that starts with this is kept for the parent
+// instead of echoed
+pub const REPORT_PREFIX: &str = "[sandbox-report] ";
+
+#[derive(Debug)]
+pub struct Isolated {
+    pub exit: Exit,
+    pub elapsed: Duration,
+    pub stderr: Vec<String>,    // the last STDERR_TAIL lines
+    pub reported: Vec<String>,  // REPORT_PREFIX lines, without the prefix
+}
+
+// Run this executable with `args` in a child process. Its stdout is echoed
+// (or dropped), its stderr tail kept, and it is killed after `timeout`.
+pub fn run_in_sandbox(args: &[String], timeout: Duration, echo: bool) -> Isolated {
+    match std::env::current_exe() {
+        Ok(exe) => run_command(Command::new(exe).args(args), timeout, echo),
+        Err(error) => Isolated {
+            exit: Exit::NotStarted(format!("cannot locate executable: {}", error)),
+            elapsed: Duration::ZERO,
+            stderr: Vec::new(),
+            reported: Vec::new(),
+        },
+    }
+}
+
+// run_in_sandbox, for any command: another build of this executable, say
+pub fn run_command(command: &mut Command, timeout: Duration, echo: bool) -> Isolated {
+    let start = Instant::now();
+    let not_started =
+        |reason: String| Isolated { exit: Exit::NotStarted(reason), elapsed: start.elapsed(), stderr: Vec::new(), reported: Vec::new() };
+    let mut child = match command
+        .env_remove("SAFETY_DEMO_EVENT_LOG")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(error) => return not_started(error.to_string()),
+    };
+
+    // Readers on their own threads, so a chatty child never blocks on a full pipe
+    let stdout = child.stdout.take().map(|stdout| {
+        thread::spawn(move || {
+            let mut reported = Vec::new();
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                match line.strip_prefix(REPORT_PREFIX) {
+                    Some(report) => reported.push(report.to_string()),
+                    None if echo => println!("  | {}", line),
+                    None => {}
+                }
+            }
+            reported
+        })
+    });
+    let stderr = child.stderr.take().map(|stderr| {
+        thread::spawn(move || {
+            let mut tail = VecDeque::with_capacity(STDERR_TAIL);
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                if tail.len() == STDERR_TAIL {
+                    tail.pop_front();
+                }
+                tail.push_back(line);
+            }
+            Vec::from(tail)
+        })
+    });
+
+    let exit = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Exit::from_status(status),
+            Ok(None) if start.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break Exit::TimedOut;
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(10)),
+            Err(error) => break Exit::NotStarted(error.to_string()),
+        }
+    };
+    let reported = stdout.and_then(|reader| reader.join().ok()).unwrap_or_default();
+    let stderr = stderr.and_then(|reader| reader.join().ok()).unwrap_or_default();
+    Isolated { exit, elapsed: start.elapsed(), stderr, reported }
+}
+
+// --- Probes ----------------------------------------------------------------
+
+// One deliberate way for a process to die
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Probe {
+    Panic,
+    Abort,
+    Segfault,
+    StackOverflow,
+    Exit,
+    Interrupt,
+    Hang,
+}
+
+pub const PROBES: [Probe; 7] = [
+    Probe::Panic, Probe::Abort, Probe::Segfault, Probe::StackOverflow, Probe::Exit, Probe::Interrupt, Probe::Hang,
+];
+
+impl Probe {
+    pub fn name(self) -> &'static str {
+        match self {
+            Probe::Panic => "panic",
+            Probe::Abort => "abort",
+            Probe::Segfault => "segfault",
+            Probe::StackOverflow => "stack-overflow",
+            Probe::Exit => "exit",
+            Probe::Interrupt => "interrupt",
+            Probe::Hang => "hang",
+        }
+    }
+
+    // How the sandbox should see it end
+    pub fn expected(self) -> Exit {
+        match self {
+            Probe::Panic if panic_strategy::UNWINDS => Exit::Failed(PANIC_EXIT_CODE),
+            Probe::Panic => Exit::Signaled(6),  // built with panic = "abort"
+            Probe::Abort | Probe::StackOverflow => Exit::Signaled(6),
+            Probe::Segfault => Exit::Signaled(11),
+            Probe::Exit => Exit::Failed(7),
+            Probe::Interrupt => Exit::Failed(INTERRUPTED_EXIT_CODE),
+            Probe::Hang => Exit::TimedOut,
+        }
+    }
+
+    // Run in the child
+    fn trigger(self) {
+        match self {
+            Probe::Panic => panic!("probe: deliberate panic"),
+            Probe::Abort => std::process::abort(),
+            Probe::Segfault => segfault(),
+            Probe::StackOverflow => {
+                eprintln!("{}", narration!("sandbox.probe_recursing"));
+                println!("{}", recurse(0));
+            }
+            Probe::Exit => std::process::exit(7),
+            // Ctrl+C twice: the first only raises the flag, the second ends the run
+            Probe::Interrupt => {
+                platform::handle_interrupts();
+                platform::raise_interrupt();
+                if !platform::interrupted() {
+                    eprintln!("{}", narration!("sandbox.probe_interrupt_unnoticed"));
+                    std::process::exit(1);
+                }
+                eprintln!("{}", narration!("sandbox.probe_interrupt_noted"));
+                platform::raise_interrupt();
+                loop {
+                    thread::sleep(Duration::from_secs(1));
+                }
+            }
+            Probe::Hang => loop {
+                thread::sleep(Duration::from_secs(1));
+            },
+        }
+    }
+
+    // Run in a sandboxed child and report how it ended
+    pub fn run(self) -> Isolated {
+        let args = ["sandbox", "--child", "probe", self.name()].map(str::to_string);
+        run_in_sandbox(&args, PROBE_TIMEOUT, false)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn segfault() {
+    // The runtime's own SIGSEGV handler (for stack overflows) would ignore
+    // a raised signal, so restore the default action first.
+    // SAFETY: both calls only change and signal this process
+    unsafe {
+        libc::signal(libc::SIGSEGV, libc::SIG_DFL);
+        libc::raise(libc::SIGSEGV);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn segfault() {
+    std::process::abort()
+}
+
+// Each frame keeps a buffer alive across the call, so this cannot be
+// turned into a loop; the stack runs out long before the base case
+fn recurse(depth: u64) -> u64 {
+    if depth == u64::MAX {
+        return 0;
+    }
+    let frame = std::hint::black_box([depth; 64]);
+    frame[0] + recurse(depth + 1) + frame[63]
+}
+
+// --- The command -----------------------------------------------------------
+
+// What the parent runs, each in its own child
+struct Job {
+    label: String,
+    args: Vec<String>,
+    timeout: Duration,
+}
+
+fn section_jobs(names: &[String], config: &EnvConfig, timeout: Duration) -> Result<Vec<Job>, String> {
+    let indices: Vec<usize> = if names.is_empty() {
+        (0..SECTIONS.len()).collect()
+    } else {
+        names.iter().map(|name| lecture::find_section(name)).collect::<Result<_, _>>()?
+    };
+    Ok(indices
+        .into_iter()
+        .map(|index| Job {
+            label: SECTIONS[index].name.to_string(),
+            args: setting_flags::setting_args(config)
+                .into_iter()
+                .chain(["sandbox", "--child", "section", SECTIONS[index].name, "--level", "advanced"].map(str::to_string))
+                .collect(),
+            timeout,
+        })
+        .collect())
+}
+
+#[cfg(all(feature = "plugins", target_os = "linux"))]
+fn plugin_jobs(config: &EnvConfig, timeout: Duration) -> Vec<Job> {
+    let Some(dir) = &config.plugin_dir else { return Vec::new() };
+    crate::plugins::plugin_paths(dir)
+        .into_iter()
+        .map(|path| Job {
+            label: narration!("sandbox.plugin", file = path.file_name().unwrap_or_default().to_string_lossy()),
+            args: vec!["sandbox".to_string(), "--child".to_string(), "plugin".to_string(), path.display().to_string()],
+            timeout,
+        })
+        .collect()
+}
+
+#[cfg(not(all(feature = "plugins", target_os = "linux")))]
+fn plugin_jobs(_config: &EnvConfig, _timeout: Duration) -> Vec<Job> {
+    Vec::new()
+}
+
+// The child's side: run one section, plugin or probe and exit. An
+// `outcome` child is a section that reports its verdicts to the parent
+fn run_child(kind: &str, name: &str, config: &EnvConfig) -> i32 {
+    match kind {
+        "section" | "outcome" => match lecture::find_section(name) {
+            Ok(index) => {
+                let section = SECTIONS[index];
+                let outcome = budget::run_budgeted(section.name, config.budget(), || (section.run)(config));
+                if kind == "outcome" {
+                    for (invariant, verdict) in &outcome.verdicts {
+                        println!("{}{:?}\t{}", REPORT_PREFIX, verdict, invariant);
+                    }
+                }
+                0
+            }
+            Err(error) => {
+                eprintln!("{}", error);
+                2
+            }
+        },
+        "probe" => match PROBES.iter().find(|probe| probe.name() == name) {
+            Some(probe) => {
+                probe.trigger();
+                0
+            }
+            None => {
+                eprintln!("{}", narration!("sandbox.no_probe_named", name));
+                2
+            }
+        },
+        #[cfg(all(feature = "plugins", target_os = "linux"))]
+        "plugin" => match crate::plugins::load_file(std::path::Path::new(name)) {
+            Ok(plugin) => {
+                budget::run_budgeted(&plugin.name, config.budget(), || plugin.run());
+                0
+            }
+            Err(error) => {
+                eprintln!("{}", error);
+                2
+            }
+        },
+        other => {
+            eprintln!("{}", narration!("sandbox.cannot_sandbox", kind = other));
+            2
+        }
+    }
+}
+
+fn print_outcome(label: &str, outcome: &Isolated, passed: bool) {
+    let line = format!("{:<28} {:<26} {:>7.2}s", label, outcome.exit.to_string(), outcome.elapsed.as_secs_f64());
+    println!("{}", term::paint(if passed { Severity::Safe } else { Severity::Violation }, &line));
+    if !passed {
+        for stderr in &outcome.stderr {
+            println!("    {}", stderr);
+        }
+    }
+}
+
+// `sandbox [section...] [--probes] [--timeout <s>] [--quiet]` - returns the exit code
+pub fn sandbox_command(args: &[String], config: &EnvConfig) -> i32 {
+    if let [flag, kind, name, ..] = args {
+        if flag == "--child" {
+            return run_child(kind, name, config);
+        }
+    }
+
+    let mut names = Vec::new();
+    let (mut probes, mut echo) = (false, true);
+    let mut timeout = config.budget().hard + GRACE;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--probes" => probes = true,
+            "--quiet" => echo = false,
+            "--timeout" => match args.next().and_then(|value| value.parse().ok()) {
+                Some(secs) => timeout = Duration::from_secs(secs),
+                None => {
+                    eprintln!("{}", narration!("sandbox.timeout_needs_seconds"));
+                    return 2;
+                }
+            },
+            flag if flag.starts_with("--") => {
+                eprintln!("{}", narration!("sandbox.usage"));
+                return 2;
+            }
+            name => names.push(name.to_string()),
+        }
+    }
+
+    if probes {
+        println!("{}", narration!("sandbox.probes_heading"));
+        let mut surprises = 0;
+        for probe in PROBES {
+            let outcome = probe.run();
+            let matched = outcome.exit == probe.expected();
+            if !matched {
+                surprises += 1;
+            }
+            print_outcome(probe.name(), &outcome, matched);
+        }
+        return if surprises == 0 { 0 } else { 1 };
+    }
+
+    let mut jobs = match section_jobs(&names, config, timeout) {
+        Ok(jobs) => jobs,
+        Err(error) => {
+            eprintln!("{}", error);
+            return 2;
+        }
+    };
+    if names.is_empty() {
+        jobs.extend(plugin_jobs(config, timeout));
+    }
+
+    println!("{}", narration!("sandbox.heading", demos = jobs.len()));
+    let mut outcomes = Vec::new();
+    for job in &jobs {
+        if echo {
+            println!("\n--- {} ---", job.label);
+        }
+        outcomes.push(run_in_sandbox(&job.args, job.timeout, echo));
+    }
+
+    println!("{}", narration!("sandbox.header"));
+    let mut failed = 0;
+    for (job, outcome) in jobs.iter().zip(&outcomes) {
+        let passed = outcome.exit == Exit::Passed;
+        if !passed {
+            failed += 1;
+        }
+        print_outcome(&job.label, outcome, passed);
+    }
+    if failed == 0 {
+        println!("{}", narration!("sandbox.all_clean", demos = jobs.len()));
+        0
+    } else {
+        println!("{}", narration!("sandbox.not_clean", failed, demos = jobs.len()));
+        1
+    }
+}
@@ -0,0 +1,458 @@
+/*!
+ * Scenario Scripts
+ *
+ * A scenario is a TOML file describing a composite demonstration as a
+ * timeline: start producers, inject a fault at 200ms, trip the circuit
+ * breaker, then assert the system recovered within a second. The engine
+ * runs a small supervised producer/consumer system behind a circuit
+ * breaker, fires faults through a FaultSwitch, can run any stress
+ * workload by name, and records every assertion as an invariant check.
+ *
+ * Steps run in order. `at_ms` is measured from the start of the scenario;
+ * a step without it runs straight after the previous one. All timing
+ * goes through one Clock shared by the engine, producers and breaker.
+ *
+ * The whole scenario runs under the demo time budget; a `[budget]` table
+ * overrides the SAFETY_DEMO_BUDGET_MS / _HARD_BUDGET_MS defaults for it.
+ */
+
+use std::fs;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::budget::{self, Budget};
+use crate::circuit_breaker::{BreakerState, CircuitBreaker};
+use crate::clock::{Clock, SharedClock, SystemClock};
+use crate::event_log;
+use crate::fault::{self, FaultKind, FaultPlan, FaultSwitch};
+use crate::stress::{Params, WORKLOADS};
+use crate::term::{self, Severity};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Scenario {
+    name: String,
+    #[serde(default = "default_tick_ms")]
+    tick_ms: u64,  // pause between two sends of one producer
+    #[serde(default)]
+    breaker: BreakerSettings,
+    #[serde(default)]
+    budget: BudgetSettings,
+    #[serde(rename = "step")]
+    steps: Vec<Step>,
+}
+
+fn default_tick_ms() -> u64 {
+    10
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BreakerSettings {
+    failure_threshold: u32,
+    cooldown_ms: u64,
+}
+
+impl Default for BreakerSettings {
+    fn default() -> Self {
+        BreakerSettings { failure_threshold: 3, cooldown_ms: 200 }
+    }
+}
+
+// Either limit may be left out to keep the configured default
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BudgetSettings {
+    soft_ms: Option<u64>,
+    hard_ms: Option<u64>,
+}
+
+impl BudgetSettings {
+    fn over(&self, default: Budget) -> Budget {
+        Budget {
+            soft: self.soft_ms.map(Duration::from_millis).unwrap_or(default.soft),
+            hard: self.hard_ms.map(Duration::from_millis).unwrap_or(default.hard),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Step {
+    at_ms: Option<u64>,
+    #[serde(flatten)]
+    action: Action,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+enum Action {
+    StartProducers { count: usize },
+    StopProducers,
+    InjectPanic { worker: usize },
+    InjectDelay { worker: usize, ms: u64 },
+    InjectErrors { worker: Option<usize>, count: usize },  // no worker: every producer
+    OpenBreaker,
+    RunWorkload { name: String, threads: usize, size: usize },
+    AssertRecovery { within_ms: u64 },
+    AssertDelivered { min: usize },
+    AssertBreaker { state: BreakerState },
+}
+
+// Catch script mistakes before anything is started
+fn validate(scenario: &Scenario) -> Result<(), String> {
+    let mut producers = 0;
+    let mut last_at = 0;
+    for (index, step) in scenario.steps.iter().enumerate() {
+        let number = index + 1;
+        if let Some(at_ms) = step.at_ms {
+            if at_ms < last_at {
+                return Err(format!("step {}: at_ms {} is earlier than the step before it ({})",
+                                   number, at_ms, last_at));
+            }
+            last_at = at_ms;
+        }
+        match &step.action {
+            Action::StartProducers { count } => producers += count,
+            Action::InjectPanic { worker }
+            | Action::InjectDelay { worker, .. }
+            | Action::InjectErrors { worker: Some(worker), .. } if *worker >= producers => {
+                return Err(format!("step {}: worker {} has not been started ({} producer(s) so far)",
+                                   number, worker, producers));
+            }
+            Action::RunWorkload { name, .. } if !WORKLOADS.iter().any(|(known, _)| known == name) => {
+                let known: Vec<&str> = WORKLOADS.iter().map(|(known, _)| *known).collect();
+                return Err(format!("step {}: unknown workload '{}' (known: {})",
+                                   number, name, known.join(", ")));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+struct Producer {
+    handle: Option<JoinHandle<()>>,
+    stop: Arc<AtomicBool>,
+}
+
+// Everything a producer thread shares with the engine
+#[derive(Clone)]
+struct Shared {
+    clock: SharedClock,
+    breaker: Arc<CircuitBreaker>,
+    switch: Arc<FaultSwitch>,
+    rejected: Arc<AtomicUsize>,  // calls refused by the open breaker
+    failed: Arc<AtomicUsize>,    // calls that failed (injected errors)
+    tick: Duration,
+}
+
+fn spawn_producer(worker: usize, shared: Shared, sender: mpsc::Sender<usize>, stop: Arc<AtomicBool>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut step = 0;
+        while !stop.load(Ordering::Relaxed) {
+            let failed = shared.switch.check(worker, step);  // May panic or stall
+            if !shared.breaker.allow() {
+                shared.rejected.fetch_add(1, Ordering::Relaxed);
+            } else if failed {
+                shared.failed.fetch_add(1, Ordering::Relaxed);
+                shared.breaker.record_failure();
+            } else if sender.send(worker).is_ok() {
+                shared.breaker.record_success();
+            }
+            step += 1;
+            shared.clock.sleep(shared.tick);
+        }
+    })
+}
+
+struct Engine {
+    start: Duration,  // clock time
+    shared: Shared,
+    sender: Option<mpsc::Sender<usize>>,
+    consumer: Option<JoinHandle<()>>,
+    delivered: Arc<AtomicUsize>,
+    last_delivery: Arc<Mutex<Vec<Option<Duration>>>>,  // per worker, clock time
+    producers: Vec<Producer>,
+    restarts: usize,
+    last_disruption: Option<Duration>,
+    failures: Vec<String>,
+}
+
+impl Engine {
+    fn new(scenario: &Scenario, clock: SharedClock) -> Self {
+        let (sender, receiver) = mpsc::channel::<usize>();
+        let delivered = Arc::new(AtomicUsize::new(0));
+        let last_delivery = Arc::new(Mutex::new(Vec::new()));
+
+        let consumer = {
+            let (delivered, last_delivery) = (Arc::clone(&delivered), Arc::clone(&last_delivery));
+            let clock = Arc::clone(&clock);
+            thread::spawn(move || {
+                for worker in receiver {
+                    delivered.fetch_add(1, Ordering::Relaxed);
+                    let mut last = last_delivery.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    if let Some(slot) = last.get_mut(worker) {
+                        *slot = Some(clock.now());
+                    }
+                }
+            })
+        };
+
+        Engine {
+            start: clock.now(),
+            shared: Shared {
+                breaker: Arc::new(CircuitBreaker::new(
+                    scenario.breaker.failure_threshold,
+                    Duration::from_millis(scenario.breaker.cooldown_ms),
+                    Arc::clone(&clock),
+                )),
+                clock,
+                switch: Arc::new(FaultSwitch::new()),
+                rejected: Arc::new(AtomicUsize::new(0)),
+                failed: Arc::new(AtomicUsize::new(0)),
+                tick: Duration::from_millis(scenario.tick_ms),
+            },
+            sender: Some(sender),
+            consumer: Some(consumer),
+            delivered,
+            last_delivery,
+            producers: Vec::new(),
+            restarts: 0,
+            last_disruption: None,
+            failures: Vec::new(),
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.shared.clock.now().saturating_sub(self.start)
+    }
+
+    fn elapsed_ms(&self) -> u128 {
+        self.elapsed().as_millis()
+    }
+
+    fn spawn(&self, worker: usize, stop: Arc<AtomicBool>) -> JoinHandle<()> {
+        let sender = self.sender.clone().expect("engine is running");
+        spawn_producer(worker, self.shared.clone(), sender, stop)
+    }
+
+    // Restart producers that died while they were supposed to be running
+    fn supervise(&mut self) {
+        for worker in 0..self.producers.len() {
+            let producer = &self.producers[worker];
+            let died = !producer.stop.load(Ordering::Relaxed)
+                && producer.handle.as_ref().is_some_and(|handle| handle.is_finished());
+            if died {
+                if let Some(handle) = self.producers[worker].handle.take() {
+                    let _ = handle.join();
+                }
+                let stop = Arc::clone(&self.producers[worker].stop);
+                self.producers[worker].handle = Some(self.spawn(worker, stop));
+                self.restarts += 1;
+                let line = format!("[{:>6}ms]   supervisor restarted producer {}", self.elapsed_ms(), worker);
+                println!("{}", term::paint(Severity::Recovered, &line));
+            }
+        }
+    }
+
+    // Let time pass (supervising) until `offset` after the start
+    fn advance_to(&mut self, offset: Duration) {
+        while self.elapsed() < offset {
+            self.supervise();
+            self.shared.clock.sleep(Duration::from_millis(5));
+        }
+    }
+
+    // Every producer alive and delivering again, breaker closed
+    fn recovered_since(&self, since: Duration) -> bool {
+        if self.shared.breaker.state() != BreakerState::Closed {
+            return false;
+        }
+        let last = self.last_delivery.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        self.producers.iter().enumerate().all(|(worker, producer)| {
+            producer.stop.load(Ordering::Relaxed)
+                || (producer.handle.as_ref().is_some_and(|handle| !handle.is_finished())
+                    && last.get(worker).copied().flatten().is_some_and(|at| at > since))
+        })
+    }
+
+    fn assert(&mut self, invariant: String, passed: bool, detail: String) {
+        let (mark, severity) = if passed { ("PASS", Severity::Safe) } else { ("FAIL", Severity::Violation) };
+        let line = format!("[{:>6}ms]   {} {} - {}", self.elapsed_ms(), mark, invariant, detail);
+        println!("{}", term::paint(severity, &line));
+        if !event_log::check_invariant(&invariant, passed) {
+            self.failures.push(format!("{}: {}", invariant, detail));
+        }
+    }
+
+    fn disrupt(&mut self, worker: usize, kind: FaultKind) {
+        self.shared.switch.trigger(worker, kind);
+        self.last_disruption = Some(self.shared.clock.now());
+    }
+
+    fn run(&mut self, action: &Action) {
+        match action {
+            Action::StartProducers { count } => {
+                for _ in 0..*count {
+                    let worker = self.producers.len();
+                    self.last_delivery.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(None);
+                    let stop = Arc::new(AtomicBool::new(false));
+                    let handle = self.spawn(worker, Arc::clone(&stop));
+                    self.producers.push(Producer { handle: Some(handle), stop });
+                }
+            }
+            Action::StopProducers => {
+                for producer in &mut self.producers {
+                    producer.stop.store(true, Ordering::Relaxed);
+                    if let Some(handle) = producer.handle.take() {
+                        let _ = handle.join();
+                    }
+                }
+            }
+            Action::InjectPanic { worker } => self.disrupt(*worker, FaultKind::Panic),
+            Action::InjectDelay { worker, ms } => {
+                self.disrupt(*worker, FaultKind::Delay(Duration::from_millis(*ms)))
+            }
+            Action::InjectErrors { worker, count } => {
+                let targets = match worker {
+                    Some(worker) => *worker..*worker + 1,
+                    None => 0..self.producers.len(),
+                };
+                for target in targets {
+                    for _ in 0..*count {
+                        self.disrupt(target, FaultKind::Error);
+                    }
+                }
+            }
+            Action::OpenBreaker => {
+                self.shared.breaker.force_open();
+                self.last_disruption = Some(self.shared.clock.now());
+            }
+            Action::RunWorkload { name, threads, size } => {
+                let workload = WORKLOADS
+                    .iter()
+                    .find(|(known, _)| known == name)
+                    .map(|(_, workload)| *workload)
+                    .expect("validated before the run");
+                let params = Params { threads: *threads, size: *size, seed: 0 };
+                let violations = workload(&params, &FaultPlan::none());
+                let detail = if violations.is_empty() {
+                    format!("{} threads x {} steps", threads, size)
+                } else {
+                    violations.join("; ")
+                };
+                self.assert(format!("workload {} holds", name), violations.is_empty(), detail);
+            }
+            Action::AssertRecovery { within_ms } => {
+                // Measured from the latest fault, so place this right after it
+                let clock = Arc::clone(&self.shared.clock);
+                let since = self.last_disruption.unwrap_or_else(|| clock.now());
+                let waited = || clock.now().saturating_sub(since);
+                let limit = Duration::from_millis(*within_ms);
+                let recovered = loop {
+                    self.supervise();
+                    if self.recovered_since(since) {
+                        break true;
+                    }
+                    if waited() > limit {
+                        break false;
+                    }
+                    clock.sleep(Duration::from_millis(5));
+                };
+                let detail = if recovered {
+                    format!("recovered after {}ms", waited().as_millis())
+                } else {
+                    format!("not recovered after {}ms (breaker {:?})",
+                            waited().as_millis(), self.shared.breaker.state())
+                };
+                self.assert(format!("recovery within {}ms", within_ms), recovered, detail);
+            }
+            Action::AssertDelivered { min } => {
+                let delivered = self.delivered.load(Ordering::Relaxed);
+                self.assert(format!("delivered at least {}", min), delivered >= *min,
+                            format!("{} delivered", delivered));
+            }
+            Action::AssertBreaker { state } => {
+                let actual = self.shared.breaker.state();
+                self.assert(format!("breaker is {:?}", state), actual == *state,
+                            format!("breaker is {:?}", actual));
+            }
+        }
+    }
+
+    fn shutdown(&mut self) {
+        self.run(&Action::StopProducers);
+        self.sender = None;  // Last sender gone: the consumer finishes
+        if let Some(consumer) = self.consumer.take() {
+            let _ = consumer.join();
+        }
+    }
+}
+
+// `scenario <file.toml>` - exit code 1 if any assertion failed, 3 if the
+// scenario ran past its hard budget
+pub fn scenario_command(args: &[String], default_budget: Budget) -> i32 {
+    let path = match args {
+        [path] => path,
+        _ => {
+            eprintln!("{}", narration!("scenario.usage"));
+            return 2;
+        }
+    };
+    let scenario: Scenario = match fs::read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|text| toml::from_str(&text).map_err(|error| error.to_string()))
+    {
+        Ok(scenario) => scenario,
+        Err(error) => {
+            eprintln!("{}", narration!("scenario.invalid", path, error));
+            return 2;
+        }
+    };
+    if let Err(error) = validate(&scenario) {
+        eprintln!("{}", narration!("scenario.invalid", path, error));
+        return 2;
+    }
+
+    println!("{}", narration!("scenario.heading", name = scenario.name, steps = scenario.steps.len()));
+    fault::install_quiet_panic_hook();
+    event_log::demo_started("scenario");
+
+    let engine = budget::run_budgeted("scenario", scenario.budget.over(default_budget), || {
+        let mut engine = Engine::new(&scenario, SystemClock::shared());
+        for step in &scenario.steps {
+            if let Some(at_ms) = step.at_ms {
+                engine.advance_to(Duration::from_millis(at_ms));
+            }
+            let line = narration!("scenario.step", ms = engine.elapsed_ms(), action = ?step.action);
+            match step.action {
+                Action::InjectPanic { .. } | Action::InjectDelay { .. } | Action::InjectErrors { .. } | Action::OpenBreaker => {
+                    println!("{}", term::paint(Severity::Fault, &line))
+                }
+                _ => println!("{}", line),
+            }
+            engine.run(&step.action);
+        }
+        engine.shutdown();
+        engine
+    });
+
+    println!("{}", narration!("scenario.totals", delivered = engine.delivered.load(Ordering::Relaxed),
+                              rejected = engine.shared.rejected.load(Ordering::Relaxed),
+                              failed = engine.shared.failed.load(Ordering::Relaxed), restarts = engine.restarts));
+
+    if engine.failures.is_empty() {
+        println!("{}", term::paint(Severity::Safe, &narration!("scenario.all_held")));
+        0
+    } else {
+        println!("{}", term::paint(Severity::Violation, &narration!("scenario.failures", count = engine.failures.len())));
+        for failure in &engine.failures {
+            println!("{}", term::paint(Severity::Violation, &format!("  {}", failure)));
+        }
+        1
+    }
+}
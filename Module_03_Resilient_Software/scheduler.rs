@@ -0,0 +1,213 @@
+/*!
+ * Reproducible Scheduling
+ *
+ * The mutex and RwLock demos pace their threads with sleeps, so which
+ * thread gets the lock first - and what each one prints - depends on the
+ * OS scheduler, and differs from run to run. A Scheduler paces them
+ * instead, in one of two modes:
+ *
+ *   real           pause() sleeps for real; the OS decides the order
+ *   seeded(seed)   one task runs at a time, in virtual time. pause()
+ *                  puts the task to sleep for its ticks plus a seeded
+ *                  random delay and hands the turn, over a channel, to
+ *                  the task due to wake first. No real time passes.
+ *
+ * In seeded mode the interleaving depends only on the seed, so the same
+ * seed replays it exactly and another seed shows another one. trace()
+ * lists who ran, in order.
+ *
+ * Only the task holding the turn runs, so a seeded task must not block on
+ * a lock another task holds - that task could never run to release it.
+ * acquire() takes a non-blocking attempt as well as the blocking call, and
+ * in seeded mode retries the attempt once per tick instead of blocking.
+ * If a turn still never comes - a task that ran inline because its
+ * thread could not be spawned, and waits for one that has not started -
+ * the scheduler gives up after STALL and the run finishes in real time.
+ */
+
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, LockResult, Mutex, TryLockError, TryLockResult};
+use std::thread;
+use std::time::Duration;
+
+use crate::rng::SeededRng;
+use crate::static_check;
+
+const TICK: u64 = static_check::nonzero(100) as u64;  // virtual time units per tick
+const JITTER: f64 = 0.5;                       // the seeded delay, at most, as a share of the pause
+const STALL: Duration = Duration::from_secs(2);  // no turn for this long: give up on seeding
+
+#[derive(Debug)]
+struct Turns {
+    rng: SeededRng,
+    now: u64,
+    next: usize,                              // arrival order, breaks ties in wake time
+    waiting: BTreeMap<(u64, usize), usize>,   // (wake time, arrival) -> task
+    wakers: Vec<Sender<()>>,                  // one per task: "your turn"
+    names: Vec<String>,
+    running: Option<usize>,
+    trace: Vec<usize>,
+    abandoned: bool,  // stalled once; every task now runs freely
+}
+
+impl Turns {
+    fn wait(&mut self, task: usize, ticks: u64) {
+        let pause = ticks * TICK;
+        let jitter = self.rng.range(0, (pause as f64 * JITTER) as usize) as u64;
+        self.waiting.insert((self.now + pause + jitter, self.next), task);
+        self.next += 1;
+    }
+
+    // Give the turn to the task due to wake first, if any
+    fn dispatch(&mut self) {
+        self.running = None;
+        if self.abandoned {
+            return;
+        }
+        if let Some(((wake, _), task)) = self.waiting.pop_first() {
+            self.now = self.now.max(wake);
+            self.running = Some(task);
+            self.trace.push(task);
+            let _ = self.wakers[task].send(());
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    tick: Duration,
+    turns: Option<Arc<Mutex<Turns>>>,  // None: real timing
+}
+
+impl Scheduler {
+    pub fn real(tick: Duration) -> Self {
+        Scheduler { tick, turns: None }
+    }
+
+    pub fn seeded(seed: u64) -> Self {
+        let turns = Turns {
+            rng: SeededRng::new(seed),
+            now: 0,
+            next: 0,
+            waiting: BTreeMap::new(),
+            wakers: Vec::new(),
+            names: Vec::new(),
+            running: None,
+            trace: Vec::new(),
+            abandoned: false,
+        };
+        Scheduler { tick: Duration::ZERO, turns: Some(Arc::new(Mutex::new(turns))) }
+    }
+
+    // Seeded, and not given up on
+    pub fn is_seeded(&self) -> bool {
+        self.turns.as_ref().is_some_and(|turns| !lock(turns).abandoned)
+    }
+
+    // Register a task. Register every task of a run before starting any,
+    // so the seeded order cannot depend on which thread started first.
+    pub fn task(&self, name: &str) -> Task {
+        let (waker, turn) = mpsc::channel();
+        let id = self.turns.as_ref().map(|turns| {
+            let mut turns = lock(turns);
+            let id = turns.wakers.len();
+            turns.wakers.push(waker);
+            turns.names.push(name.to_string());
+            turns.wait(id, 0);
+            id
+        });
+        Task { scheduler: self.clone(), id: id.unwrap_or(0), turn }
+    }
+
+    // The names of the tasks in the order they ran (seeded mode only)
+    pub fn trace(&self) -> Vec<String> {
+        self.turns.as_ref().map_or_else(Vec::new, |turns| {
+            let turns = lock(turns);
+            turns.trace.iter().map(|&task| turns.names[task].clone()).collect()
+        })
+    }
+}
+
+fn lock(turns: &Mutex<Turns>) -> std::sync::MutexGuard<'_, Turns> {
+    turns.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[derive(Debug)]
+pub struct Task {
+    scheduler: Scheduler,
+    id: usize,
+    turn: Receiver<()>,
+}
+
+// Ends the task's turn for good, even if it panics
+struct Finished<'a>(&'a Task);
+
+impl Drop for Finished<'_> {
+    fn drop(&mut self) {
+        if let Some(turns) = &self.0.scheduler.turns {
+            lock(turns).dispatch();
+        }
+    }
+}
+
+impl Task {
+    // Run the task's work on the calling thread, taking turns with the
+    // other tasks in seeded mode
+    pub fn run<T>(self, work: impl FnOnce(&Task) -> T) -> T {
+        if let Some(turns) = self.scheduler.turns.as_ref().filter(|_| self.scheduler.is_seeded()) {
+            let mut state = lock(turns);
+            // The first task to arrive starts the round
+            if state.running.is_none() {
+                state.dispatch();
+            }
+            drop(state);
+            self.await_turn();
+        }
+        let _finished = Finished(&self);
+        work(&self)
+    }
+
+    // Sleep for `ticks` - for real, or in virtual time while the others run
+    pub fn pause(&self, ticks: u64) {
+        match &self.scheduler.turns {
+            Some(turns) if self.scheduler.is_seeded() => {
+                let mut state = lock(turns);
+                state.wait(self.id, ticks);
+                state.dispatch();
+                drop(state);
+                self.await_turn();
+            }
+            _ => thread::sleep(self.scheduler.tick * ticks as u32),
+        }
+    }
+
+    // Wait for a lock. `attempt` must not block: with real timing `block`
+    // waits, in seeded mode `attempt` is retried once per tick.
+    pub fn acquire<G>(&self, mut attempt: impl FnMut() -> TryLockResult<G>, block: impl FnOnce() -> LockResult<G>) -> LockResult<G> {
+        if !self.scheduler.is_seeded() {
+            return block();
+        }
+        loop {
+            match attempt() {
+                Ok(guard) => return Ok(guard),
+                Err(TryLockError::Poisoned(poisoned)) => return Err(poisoned),
+                Err(TryLockError::WouldBlock) => self.pause(1),
+            }
+        }
+    }
+
+    fn await_turn(&self) {
+        if self.turn.recv_timeout(STALL).is_ok() {
+            return;
+        }
+        // Stalled: release every task, seeded or waiting
+        if let Some(turns) = &self.scheduler.turns {
+            let mut turns = lock(turns);
+            turns.abandoned = true;
+            for waker in &turns.wakers {
+                let _ = waker.send(());
+            }
+        }
+    }
+}
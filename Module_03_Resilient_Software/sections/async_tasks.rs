@@ -0,0 +1,286 @@
+/*!
+ * Async Sections
+ *
+ * Tasks on a tokio runtime: a stream with per-item timeouts, a lock held
+ * across an await, timeouts, retries and a circuit breaker as
+ * middleware, and hedged requests against a slow tail.
+ */
+
+use std::time::Duration;
+
+use safety_demo_macros::safety_demo;
+
+use crate::{async_demo, async_lock, event_log, hedge, middleware, spawn_policy};
+use crate::circuit_breaker::BreakerState;
+use crate::demo_report;
+use crate::env_config::{EnvConfig, Level};
+use crate::term::Severity;
+
+// Items in the async stream workload; every seventh one stalls
+pub const ASYNC_ITEMS: u64 = 16;
+
+#[safety_demo(
+    name = "async_streams",
+    module = "Async",
+    description = "The pipeline's workload as an async stream with per-item timeouts",
+    invariants(
+        "every item is delivered or timed out"
+    )
+)]
+pub fn demonstrate_async_streams(config: &EnvConfig) {
+    narrate!("async_tasks.stream_heading");
+
+    let runtime = match async_demo::runtime(2) {
+        Ok(runtime) => runtime,
+        Err(error) => {
+            narrate!("async_tasks.cannot_start_async_runtime", error);
+            return;
+        }
+    };
+    let tick = config.ticks(1).max(Duration::from_millis(1));
+    let timeout = tick * 10;
+    let items = async_demo::workload(ASYNC_ITEMS, tick);
+    let stalled = items.iter().filter(|item| item.latency > timeout).count();
+    narrate!("async_tasks.workload", items = items.len(), stalled);
+
+    let runs = [
+        (narration!("async_tasks.threads_one_per_stage"), async_demo::threaded(&items)),
+        ("stream: buffered(1)".to_string(), async_demo::streamed(&runtime, &items, 1, true, timeout)),
+        ("stream: buffered(8)".to_string(), async_demo::streamed(&runtime, &items, 8, true, timeout)),
+        ("stream: buffer_unordered(8)".to_string(), async_demo::streamed(&runtime, &items, 8, false, timeout)),
+    ];
+    narrate!("async_tasks.model_header");
+    for (label, run) in &runs {
+        narrate!("async_tasks.model_row", label, elapsed = run.elapsed.as_secs_f64() * 1000.0,
+                 outputs = run.outputs.len(), timed_out = run.timed_out,
+                 in_order = if run.in_order() { narration!("async_tasks.yes") } else { narration!("async_tasks.no") });
+    }
+    let accounted = event_log::check_invariant("every item is delivered or timed out",
+                                               runs.iter().all(|(_, run)| run.outputs.len() + run.timed_out == items.len()));
+    demo_report::claim(accounted, &narration!("async_tasks.threads_wait_out_every_stall"));
+    
+    if config.level >= Level::Intermediate {
+        let reports = blocking_isolation_metrics(config);
+        async_runtime_metrics(reports);
+    }
+}
+
+// A std Mutex guard held across .await, and the two ways out
+#[safety_demo(
+    name = "async_mutex",
+    module = "Async",
+    description = "A std Mutex guard held across .await stalls the executor; tokio's Mutex or a narrower scope does not",
+    invariants(
+        "the detector flags a std guard held across .await",
+        "the async mutex and the narrowed scope never stall the executor",
+        "no update is lost with either fix"
+    )
+)]
+pub fn demonstrate_async_mutex(config: &EnvConfig) {
+    use std::sync::mpsc;
+    
+    narrate!("async_tasks.await_locks_heading");
+    const ROUNDS: u32 = 5;
+    const HOLD: Duration = Duration::from_millis(20);
+    const GIVE_UP: Duration = Duration::from_millis(100);  // lock_for, without --allow-hang
+    const WATCH: Duration = Duration::from_secs(1);        // how long a hang is waited out
+    const STALL: Duration = Duration::from_millis(50);     // a heartbeat this late means a blocked executor
+    
+    narrate!("async_tasks.one_current_thread_runtime", rounds = ROUNDS, hold = HOLD.as_millis());
+    narrate!("async_tasks.contender_updates_often");
+    
+    // The bug, left to block: the contender's lock() waits for a guard
+    // only this same thread can release
+    if config.allow_hang {
+        narrate!("async_tasks.no_timeout_heading", fix = async_lock::Fix::None);
+        let (done, finished) = mpsc::channel();
+        let spawned = spawn_policy::spawn_required(move || {
+            let _ = done.send(async_lock::run(async_lock::Fix::None, ROUNDS, HOLD, None));
+        });
+        match spawned {
+            Ok(worker) => match finished.recv_timeout(WATCH) {
+                Ok(_) => {
+                    let _ = worker.join();
+                    narrate_as!(Severity::Violation, "async_tasks.run_finished_though_contender");
+                }
+                Err(_) => {
+                    narrate!("async_tasks.not_finished", watch = WATCH.as_secs());
+                    narrate!("async_tasks.holder_can_only_release_guard");
+                    drop(worker);  // detached: joining would hang this thread too
+                }
+            },
+            Err(error) => narrate!("async_tasks.hang_skipped", error),
+        }
+    }
+    
+    let mut runs = Vec::new();
+    for fix in async_lock::Fix::ALL {
+        match async_lock::run(fix, ROUNDS, HOLD, Some(GIVE_UP)) {
+            Ok(run) => runs.push(run),
+            Err(error) => {
+                narrate!("async_tasks.cannot_start_async_runtime", error);
+                return;
+            }
+        }
+    }
+    narrate!("async_tasks.holder_header");
+    for run in &runs {
+        narrate!("async_tasks.holder_row", fix = run.fix, elapsed = run.elapsed.as_secs_f64() * 1000.0,
+                 gave_up = run.gave_up, count = run.count, total = 2 * ROUNDS,
+                 worst_heartbeat = run.worst_heartbeat.as_secs_f64() * 1000.0);
+    }
+    for finding in runs.iter().flat_map(|run| &run.findings) {
+        narrate_as!(Severity::Violation, "async_tasks.await_holding_lock", finding);
+    }
+    
+    let (bug, fixes) = (&runs[0], &runs[1..]);
+    let flagged = event_log::check_invariant("the detector flags a std guard held across .await",
+                                             bug.findings == ["task `holder` awaited while holding `counter`"]
+                                                 && fixes.iter().all(|run| run.findings.is_empty()));
+    demo_report::claim(flagged && bug.worst_heartbeat >= STALL, &narration!("async_tasks.holding_std_guard_across_await"));
+    narrate!("async_tasks.contender_gave_up_times_after", gave_up = bug.gave_up, give_up = GIVE_UP.as_millis());
+    event_log::check_invariant("the async mutex and the narrowed scope never stall the executor",
+                               fixes.iter().all(|run| run.gave_up == 0 && run.worst_heartbeat < STALL));
+    let kept = event_log::check_invariant("no update is lost with either fix",
+                                          fixes.iter().all(|run| run.count == run.expected && run.count == 2 * u64::from(ROUNDS)));
+    demo_report::claim(kept, &narration!("async_tasks.tokio_mutex_suspends_waiting"));
+    if !config.allow_hang {
+        narrate!("async_tasks.run_allow_hang_let_contender");
+    }
+    narrate!("async_tasks.tokio_spawn_would_refuse_holder");
+}
+
+#[safety_demo(
+    name = "async_resilience",
+    module = "Async",
+    description = "Timeout, retry and circuit breaker middleware around a flaky async service",
+    invariants(
+        "breaker closes after a healthy trial"
+    )
+)]
+pub fn demonstrate_async_resilience(config: &EnvConfig) {
+    narrate!("async_tasks.middleware_heading");
+    
+    let runtime = match async_demo::runtime(1) {
+        Ok(runtime) => runtime,
+        Err(error) => {
+            narrate!("async_tasks.cannot_start_async_runtime", error);
+            return;
+        }
+    };
+    let tick = config.ticks(1).max(Duration::from_millis(1));
+    narrate!("async_tasks.stack_retry_attempts_circuit");
+    narrate!("async_tasks.call_result_header");
+    
+    let steps = middleware::breaker_walkthrough(&runtime, tick);
+    for step in &steps {
+        let result = match &step.result {
+            Ok(value) => narration!("async_tasks.call_ok", value),
+            Err(error) => error.to_string(),
+        };
+        say!("{:<26} {:<28} {:<9} {:>13}", step.label, result, format!("{:?}", step.state), step.service_calls);
+    }
+    event_log::check_invariant("breaker closes after a healthy trial",
+                               steps.last().is_some_and(|step| step.state == BreakerState::Closed));
+    narrate!("async_tasks.while_breaker_open_calls_failed");
+}
+
+// Tail latency of one replica against a pair that hedges slow calls
+#[safety_demo(
+    name = "hedged_requests",
+    module = "Async",
+    description = "Slow calls also go to a second replica, and the first answer wins",
+    invariants(
+        "hedging lowers p99 latency",
+        "every hedge cancels the slower call",
+        "hedging adds at most 10% more backend calls"
+    )
+)]
+pub fn demonstrate_hedged_requests() {
+    narrate!("async_tasks.hedged_heading");
+    
+    let runtime = match async_demo::paused_runtime() {
+        Ok(runtime) => runtime,
+        Err(error) => {
+            narrate!("async_tasks.cannot_start_async_runtime", error);
+            return;
+        }
+    };
+    const SEED: u64 = 0x4ED6E;
+    let plain = hedge::run(&runtime, false, SEED);
+    let hedged = hedge::run(&runtime, true, SEED);
+    
+    narrate!("async_tasks.hedge_workload", requests = hedge::REQUESTS);
+    narrate!("async_tasks.hedge_policy", percentile = hedge::PERCENTILE);
+    narrate!("async_tasks.latency_header");
+    for (label, run) in [(narration!("async_tasks.one_replica"), &plain), (narration!("async_tasks.hedged"), &hedged)] {
+        let millis = |p: f64| format!("{}ms", run.latencies.percentile(p).as_millis());
+        say!("{:<12} {:>6} {:>6} {:>6} {:>6} {:>14}", label, millis(50.0), millis(95.0), millis(99.0), millis(100.0),
+             run.backend_calls);
+    }
+    narrate!("async_tasks.hedges_sent_answered_first", hedges = hedged.hedges, hedges_won = hedged.hedges_won,
+             cancelled = hedged.cancelled);
+    
+    let shorter_tail = event_log::check_invariant("hedging lowers p99 latency",
+                                                   hedged.latencies.percentile(99.0) < plain.latencies.percentile(99.0));
+    let cancelled = event_log::check_invariant("every hedge cancels the slower call", hedged.cancelled == hedged.hedges);
+    let few_calls = event_log::check_invariant("hedging adds at most 10% more backend calls",
+                                               hedged.backend_calls <= plain.backend_calls + plain.backend_calls / 10);
+    demo_report::claim(shorter_tail && cancelled && few_calls, &narration!("async_tasks.few_percent_more_calls_buy"));
+}
+
+// Returns the runtime metrics of each run, for async_runtime_metrics
+fn blocking_isolation_metrics(config: &EnvConfig) -> Vec<(&'static str, async_demo::RuntimeReport)> {
+    use async_demo::CpuPlacement;
+    
+    let interval = config.ticks(1).max(Duration::from_millis(1));
+    let work = interval * 20;
+    narrate!("async_tasks.heartbeat_metrics_heading", work = work.as_millis());
+    narrate!("async_tasks.heartbeat_header");
+    
+    let mut worst = Vec::new();
+    let mut reports = Vec::new();
+    for (label, metric, placement) in [(narration!("async_tasks.on_runtime"), "cpu_on_runtime", CpuPlacement::OnRuntime),
+                                       (narration!("async_tasks.on_blocking_pool"), "cpu_on_blocking_pool", CpuPlacement::BlockingPool)] {
+        match async_demo::heartbeat_under_cpu_load(placement, work, interval) {
+            Ok(latency) => {
+                let slowest = latency.lateness.max();
+                narrate!("async_tasks.heartbeat_row", label, median = latency.lateness.percentile(50.0).as_secs_f64() * 1000.0,
+                         worst = slowest.as_secs_f64() * 1000.0);
+                worst.push(slowest);
+                reports.push((metric, latency.runtime));
+            }
+            Err(error) => narrate!("async_tasks.cpu_work_failed", label, error),
+        }
+    }
+    if let [stalled, isolated] = worst[..] {
+        narrate!("async_tasks.moving_computation_spawn", stalled = stalled.as_secs_f64() * 1000.0,
+                 isolated = isolated.as_secs_f64() * 1000.0);
+    }
+    reports
+}
+
+fn async_runtime_metrics(mut reports: Vec<(&'static str, async_demo::RuntimeReport)>) {
+    let messages = 10_000;
+    match async_demo::drain_ready_channel(messages) {
+        Ok(report) => reports.push(("drain_ready_channel", report)),
+        Err(error) => narrate!("async_tasks.cannot_start_async_runtime", error),
+    }
+    
+    narrate!("async_tasks.runtime_metrics_heading");
+    narrate!("async_tasks.runtime_header");
+    for (label, report) in &reports {
+        let forced = report.forced_yields.map_or("n/a".to_string(), |count| count.to_string());
+        narrate!("async_tasks.runtime_row", label, tasks = format!("{}/{}", report.completed, report.spawned),
+                 polls = report.polls, busy = report.busy.as_secs_f64() * 1000.0,
+                 mean_poll = report.mean_poll.as_secs_f64() * 1000.0,
+                 slowest_poll = report.slowest_poll.as_secs_f64() * 1000.0, forced);
+        report.record(&format!("async.{}", label));
+    }
+    if let Some((_, drain)) = reports.iter().find(|(label, _)| *label == "drain_ready_channel") {
+        narrate!("async_tasks.draining_ready_messages_never", messages, polls = drain.polls);
+    }
+    if reports.iter().all(|(_, report)| report.forced_yields.is_none()) {
+        narrate!("async_tasks.forced_yield_counts_need");
+    }
+}
@@ -0,0 +1,190 @@
+/*!
+ * Distributed Systems Sections
+ *
+ * Replicas that only talk over an unreliable network: CRDTs that
+ * converge anyway, delivery guarantees over a lossy link, and failure
+ * detection across a partition.
+ */
+
+use safety_demo_macros::safety_demo;
+
+use crate::{crdt, delivery, demo_report, event_log, network_sim, static_check};
+
+// Counters and a set replicated over a lossy, reordering simulated
+// network: CRDT replicas converge exactly, a naive replica does not
+#[safety_demo(
+    name = "crdt_replication",
+    module = "Distributed Systems",
+    description = "Replicated counters and sets that converge over a lossy, reordering network",
+    invariants(
+        "PN-counter replicas converge to the exact total",
+        "OR-Set concurrent add wins over remove"
+    )
+)]
+pub fn demonstrate_crdt_replication() {
+    use crdt::{Crdt, OrSet, PNCounter};
+    use network_sim::LinkFaults;
+    
+    narrate!("distributed.crdt_heading");
+    
+    const NODES: usize = static_check::nonzero(3);
+    const ROUNDS: u64 = 12;
+    let faults = LinkFaults { drop: 0.3, duplicate: 0.1, reorder: 0.2, min_delay: 1, max_delay: 3 };
+    narrate!("distributed.crdt_workload", nodes = NODES, rounds = ROUNDS);
+    narrate!("distributed.crdt_faults");
+    
+    // Node n adds n + 1 every round; node 2 also subtracts 1 every other round
+    let counter_update = |node: usize, round: u64| -> (u64, u64) {
+        (node as u64 + 1, if node == 2 && round.is_multiple_of(2) { 1 } else { 0 })
+    };
+    let expected: i64 = (0..ROUNDS)
+        .flat_map(|round| (0..NODES).map(move |node| (node, round)))
+        .map(|(node, round)| {
+            let (up, down) = counter_update(node, round);
+            up as i64 - down as i64
+        })
+        .sum();
+    
+    // The naive replica: whatever state arrives replaces the local one
+    #[derive(Debug, Clone, PartialEq)]
+    struct Overwrite(i64);
+    impl Crdt for Overwrite {
+        fn merge(&mut self, other: &Self) {
+            self.0 = other.0;
+        }
+    }
+    
+    let agree = |replicas: &[PNCounter]| replicas.windows(2).all(|pair| pair[0] == pair[1]);
+    let pn = crdt::replicate(vec![PNCounter::default(); NODES], ROUNDS, faults, 11, |node, round, replica| {
+        let (up, down) = counter_update(node, round);
+        replica.increment(node, up);
+        replica.decrement(node, down);
+    }, agree);
+    let naive = crdt::replicate(vec![Overwrite(0); NODES], ROUNDS, faults, 11, |node, round, replica| {
+        let (up, down) = counter_update(node, round);
+        replica.0 += up as i64 - down as i64;
+    }, |replicas| replicas.windows(2).all(|pair| pair[0] == pair[1]));
+    
+    narrate!("distributed.replica_header");
+    let values: Vec<String> = pn.replicas.iter().map(|replica| replica.value().to_string()).collect();
+    narrate!("distributed.pn_counter_row", expected, values = values.join(","), diverged_rounds = pn.diverged_rounds,
+             settle_rounds = pn.settle_rounds);
+    let values: Vec<String> = naive.replicas.iter().map(|replica| replica.0.to_string()).collect();
+    narrate!("distributed.naive_row", expected, values = values.join(","), diverged_rounds = naive.diverged_rounds,
+             settle_rounds = naive.settle_rounds);
+    event_log::check_invariant("PN-counter replicas converge to the exact total",
+                               pn.replicas.iter().all(|replica| replica.value() == expected));
+    
+    // OR-Set: node 0 removes "pear" in the same round node 1 adds it again
+    let set = crdt::replicate((0..NODES).map(OrSet::new).collect(), ROUNDS, faults, 11, |node, round, replica| {
+        match (node, round) {
+            (0, 0) => replica.add("apple"),
+            (1, 0) => replica.add("pear"),
+            (0, 6) => replica.remove(&"pear"),
+            (1, 6) => replica.add("pear"),
+            (2, 2) => replica.add("fig"),
+            (2, 9) => replica.remove(&"fig"),
+            _ => {}
+        }
+    }, |replicas: &[OrSet<&str>]| replicas.windows(2).all(|pair| pair[0].same_state(&pair[1])));
+    let elements: Vec<String> = set.replicas.iter().map(|replica| format!("{{{}}}", replica.elements().join(","))).collect();
+    narrate!("distributed.or_set_scenario");
+    narrate!("distributed.or_set_replicas", elements = elements.join(" "));
+    event_log::check_invariant("OR-Set concurrent add wins over remove",
+                               set.replicas.iter().all(|replica| replica.contains(&"pear") && !replica.contains(&"fig")));
+    narrate!("distributed.network", stats = pn.stats + naive.stats + set.stats);
+    narrate!("distributed.naive_agree");
+}
+
+// The same transfers sent at-most-once, at-least-once and at-least-once
+// to an idempotent consumer, over a link that loses messages and acks
+#[safety_demo(
+    name = "delivery_semantics",
+    module = "Distributed Systems",
+    description = "At-most-once, at-least-once and idempotent delivery over a lossy link",
+    invariants(
+        "at-most-once never applies a transfer twice",
+        "at-least-once never loses a transfer",
+        "idempotent consumer ends with the exact balance"
+    )
+)]
+pub fn demonstrate_delivery_semantics() {
+    use delivery::{Guarantee, RETRY_AFTER};
+    use network_sim::LinkFaults;
+    
+    narrate!("distributed.delivery_heading");
+    
+    const TRANSFERS: u64 = 40;
+    let faults = LinkFaults { drop: 0.2, duplicate: 0.0, reorder: 0.2, min_delay: 1, max_delay: 3 };
+    let amount = |id: u64| (id as i64 % 5 + 1) * 10;
+    narrate!("distributed.delivery_workload", transfers = TRANSFERS);
+    narrate!("distributed.delivery_retries", retry_after = RETRY_AFTER);
+    
+    narrate!("distributed.delivery_header");
+    let mut runs = Vec::new();
+    for (label, guarantee) in [
+        (narration!("distributed.at_most_once"), Guarantee::AtMostOnce),
+        (narration!("distributed.at_least_once"), Guarantee::AtLeastOnce),
+        (narration!("distributed.idempotent"), Guarantee::Idempotent),
+    ] {
+        let run = delivery::run(guarantee, TRANSFERS, amount, faults, 21);
+        say!("{:<28} {:>8} {:>5} {:>10} {:>8} {:>8} {:>8}",
+             label, run.attempts, run.lost, run.duplicates, run.ignored, run.balance, run.expected);
+        runs.push(run);
+    }
+    
+    let no_duplicates = event_log::check_invariant("at-most-once never applies a transfer twice", runs[0].duplicates == 0);
+    let no_losses = event_log::check_invariant("at-least-once never loses a transfer", runs[1].lost == 0 && runs[2].lost == 0);
+    let exact = event_log::check_invariant("idempotent consumer ends with the exact balance", runs[2].balance == runs[2].expected);
+    demo_report::claim(no_duplicates && no_losses && exact, &narration!("distributed.delivery_claim"));
+}
+
+// Heartbeats between three simulated nodes over a faulty network, and
+// what a partition does to each node's view of the others
+#[safety_demo(
+    name = "network_partition",
+    module = "Distributed Systems",
+    description = "Heartbeat failure detection between simulated nodes across a network partition",
+    invariants(
+        "partitioned node is suspected",
+        "suspicion clears after the partition heals"
+    )
+)]
+pub fn demonstrate_network_partition() {
+    use network_sim::{LinkFaults, SUSPECT_AFTER};
+    
+    narrate!("distributed.partition_heading");
+    
+    const NODES: usize = static_check::nonzero(3);
+    const TICKS: u64 = 40;
+    const PARTITION: (u64, u64) = (10, 25);
+    let jitter = LinkFaults { min_delay: 1, max_delay: 2, ..LinkFaults::default() };
+    let lossy = LinkFaults { drop: 0.2, duplicate: 0.1, reorder: 0.1, min_delay: 1, max_delay: 3 };
+    let scenarios = [
+        (narration!("distributed.reliable"), LinkFaults::default(), None),
+        (narration!("distributed.lossy"), lossy, None),
+        (narration!("distributed.partitioned"), jitter, Some(PARTITION)),
+        (narration!("distributed.lossy_partitioned"), lossy, Some(PARTITION)),
+    ];
+    narrate!("distributed.heartbeat_workload", nodes = NODES, suspect_after = SUSPECT_AFTER);
+    narrate!("distributed.partition_schedule", node = NODES - 1, start = PARTITION.0, end = PARTITION.1);
+    narrate!("distributed.scenario_header", node = NODES - 1);
+    
+    for (name, faults, partition) in scenarios {
+        let run = network_sim::heartbeat_run(NODES, TICKS, faults, partition, 7);
+        let stats = run.stats;
+        say!("{:<20} {:>5} {:>9} {:>7} {:>11} {:>4} {:>9}  {}", name, stats.sent, stats.delivered,
+             stats.dropped, stats.partitioned, stats.duplicated, stats.reordered, run.view);
+        
+        if let Some((start, end)) = partition {
+            // Suspected once the silence outlasts the timeout, trusted again
+            // once heartbeats flow after the heal
+            let cut_off = run.view[(start + SUSPECT_AFTER + 2) as usize..end as usize].chars().all(|c| c == 'x');
+            let settled = end + SUSPECT_AFTER + 2;
+            let recovered = run.suspected.iter().all(|tick| *tick < settled);
+            event_log::check_invariant("partitioned node is suspected", cut_off);
+            event_log::check_invariant("suspicion clears after the partition heals", recovered);
+        }
+    }
+    narrate!("distributed.partition_looks_like_crash");
+}
@@ -0,0 +1,267 @@
+/*!
+ * Embedded Section
+ *
+ * A timer interrupt feeding the main loop through a lock-free SPSC ring,
+ * and critical sections keeping shared updates whole.
+ */
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use safety_demo_macros::safety_demo;
+
+use crate::{event_log, spawn_policy, spsc};
+use crate::demo_report;
+use crate::env_config::{EnvConfig, Level};
+use crate::interrupts::{CriticalSection, CsCell, Interrupts};
+use crate::spawn_policy::SpawnError;
+use crate::term::Severity;
+
+// Demonstrate interrupt-style concurrency: a simulated timer interrupt
+// hands samples to the main loop through an SPSC queue, and main updates
+// what it shares with the handler in critical sections
+#[safety_demo(
+    name = "interrupt_handlers",
+    module = "Embedded",
+    description = "A timer interrupt feeds the main loop through a lock-free SPSC queue; critical sections keep shared updates whole",
+    invariants(
+        "every sample the handler queued reaches main once, in order",
+        "a full queue drops samples in the handler instead of blocking it",
+        "the handler never sees an update made in a critical section half done"
+    )
+)]
+pub fn demonstrate_interrupt_handlers(config: &EnvConfig) {
+    const TICKS: u32 = 200;
+    const PERIOD: Duration = Duration::from_micros(200);
+    const UPDATE: Duration = Duration::from_micros(50);  // between the two halves of main's update
+    
+    narrate!("embedded.interrupt_heading");
+    narrate!("embedded.timer_workload", ticks = TICKS, period = PERIOD.as_micros());
+    
+    #[derive(Default)]
+    struct HandlerStats {
+        queued: usize,
+        dropped: usize,
+        torn: usize,  // main's critical-section pair seen half updated
+    }
+    
+    // Main drains the queue and keeps updating a pair the handler reads:
+    // once in a critical section, once as two plain atomics
+    narrate!("embedded.fed_by_handler_heading");
+    let interrupts = Arc::new(Interrupts::new());
+    let stats = Arc::new(CsCell::new(HandlerStats::default()));
+    let pair = Arc::new(CsCell::new((0u64, 0u64)));
+    let racy = Arc::new((AtomicUsize::new(0), AtomicUsize::new(0), AtomicUsize::new(0)));  // halves, and torn reads
+    let (mut producer, mut consumer) = spsc::channel::<u32>(256);
+    let handler = {
+        let (stats, pair, racy) = (Arc::clone(&stats), Arc::clone(&pair), Arc::clone(&racy));
+        move |tick: u32, cs: &CriticalSection| {
+            let mut stats = stats.borrow(cs);
+            match producer.push(tick) {
+                Ok(()) => stats.queued += 1,
+                Err(_) => stats.dropped += 1,
+            }
+            let (first, second) = *pair.borrow(cs);
+            stats.torn += usize::from(first != second);
+            if racy.0.load(Ordering::SeqCst) != racy.1.load(Ordering::SeqCst) {
+                racy.2.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    };
+    let timer = match start_timer(&interrupts, TICKS, PERIOD, handler) {
+        Ok(timer) => timer,
+        Err(error) => {
+            narrate!("embedded.timer_skipped", error);
+            return;
+        }
+    };
+    let mut received = Vec::new();
+    loop {
+        let finished = timer.is_finished();
+        while let Some(sample) = consumer.pop() {
+            received.push(sample);
+        }
+        if finished {
+            break;
+        }
+        interrupts.free(|cs| {
+            let mut pair = pair.borrow(cs);
+            pair.0 += 1;
+            thread::sleep(UPDATE);
+            pair.1 += 1;
+        });
+        racy.0.fetch_add(1, Ordering::SeqCst);
+        thread::sleep(UPDATE);
+        racy.1.fetch_add(1, Ordering::SeqCst);
+    }
+    timer.join();
+    let (queued, dropped, torn) = interrupts.free(|cs| {
+        let stats = stats.borrow(cs);
+        (stats.queued, stats.dropped, stats.torn)
+    });
+    let in_order = received.iter().copied().eq(0..TICKS);
+    narrate!("embedded.fed_summary", raised = interrupts.raised(), queued, dropped, received = received.len(),
+             order = if in_order { narration!("embedded.in_order") } else { narration!("embedded.out_of_order") });
+    event_log::check_invariant("every sample the handler queued reaches main once, in order",
+                               queued == TICKS as usize && in_order);
+    narrate!("embedded.critical_section_torn", torn, deferred = interrupts.deferred());
+    narrate!("embedded.atomics_torn", racy = racy.2.load(Ordering::Relaxed));
+    event_log::check_invariant("the handler never sees an update made in a critical section half done", torn == 0);
+    
+    // Main stuck in a long job: the queue fills, and the handler must not wait
+    const CAPACITY: usize = 16;
+    narrate!("embedded.main_too_busy_heading", capacity = CAPACITY);
+    let interrupts = Arc::new(Interrupts::new());
+    let stats = Arc::new(CsCell::new(HandlerStats::default()));
+    let (mut producer, mut consumer) = spsc::channel::<u32>(CAPACITY);
+    let handler = {
+        let stats = Arc::clone(&stats);
+        move |tick: u32, cs: &CriticalSection| {
+            let mut stats = stats.borrow(cs);
+            match producer.push(tick) {
+                Ok(()) => stats.queued += 1,
+                Err(_) => stats.dropped += 1,
+            }
+        }
+    };
+    let ticks = 4 * CAPACITY as u32;
+    match start_timer(&interrupts, ticks, PERIOD, handler) {
+        Ok(timer) => timer.join(),
+        Err(error) => {
+            narrate!("embedded.timer_skipped", error);
+            return;
+        }
+    }
+    let waiting = consumer.len();
+    let received: Vec<u32> = std::iter::from_fn(|| consumer.pop()).collect();
+    let (queued, dropped) = interrupts.free(|cs| {
+        let stats = stats.borrow(cs);
+        (stats.queued, stats.dropped)
+    });
+    narrate!("embedded.busy_summary", raised = interrupts.raised(), waiting, dropped);
+    narrate!("embedded.busy_received", first = received.first().unwrap_or(&0), last = received.last().map_or(0, |last| last + 1));
+    let dropped_not_blocked = interrupts.raised() == ticks as usize && queued == CAPACITY
+        && dropped == ticks as usize - CAPACITY && received.iter().copied().eq(0..CAPACITY as u32);
+    event_log::check_invariant("a full queue drops samples in the handler instead of blocking it", dropped_not_blocked);
+    demo_report::claim(dropped_not_blocked, &narration!("embedded.busy_claim"));
+    
+    if config.level >= Level::Intermediate {
+        spsc_vs_mpsc(config);
+    }
+}
+
+// INTERMEDIATE: the ring against std's mpsc::sync_channel with one
+// producer and one consumer, both bounded to the same capacity. The ring
+// never blocks, so its ends yield while it is full or empty
+fn spsc_vs_mpsc(config: &EnvConfig) {
+    const MESSAGES: u32 = 100_000;
+    const CAPACITY: usize = 256;
+    
+    let rounds = config.stress_rounds.get();
+    narrate!("embedded.queue_race_heading", messages = MESSAGES, capacity = CAPACITY, rounds);
+    if cfg!(debug_assertions) {
+        narrate!("embedded.debug_build");
+    }
+    let mut best = [Duration::MAX; 2];
+    for _ in 0..rounds {
+        let runs = [ring_run(MESSAGES, CAPACITY), sync_channel_run(MESSAGES, CAPACITY)];
+        for (best, run) in best.iter_mut().zip(runs) {
+            match run {
+                Ok(Some(elapsed)) => *best = (*best).min(elapsed),
+                Ok(None) => {
+                    narrate_as!(Severity::Violation, "embedded.queue_lost_order");
+                    return;
+                }
+                Err(error) => {
+                    narrate!("embedded.queue_skipped", error);
+                    return;
+                }
+            }
+        }
+    }
+    narrate!("embedded.queue_header");
+    for (name, elapsed) in [narration!("embedded.spsc_ring"), "mpsc::sync_channel".to_string()].into_iter().zip(best) {
+        say!("{:<24} {:>12.2?} {:>10.0}", name, elapsed, elapsed.as_nanos() as f64 / MESSAGES as f64);
+    }
+    narrate!("embedded.ring_versus_channel");
+}
+
+// Time to move `messages` through the ring; None if they did not arrive in order
+fn ring_run(messages: u32, capacity: usize) -> Result<Option<Duration>, SpawnError> {
+    let (mut producer, mut consumer) = spsc::channel::<u32>(capacity);
+    let start = Instant::now();
+    let sender = spawn_policy::spawn_required(move || {
+        for message in 0..messages {
+            while producer.push(message).is_err() {
+                thread::yield_now();
+            }
+        }
+    })?;
+    let mut in_order = true;
+    for expected in 0..messages {
+        let message = loop {
+            match consumer.pop() {
+                Some(message) => break message,
+                None => thread::yield_now(),
+            }
+        };
+        in_order &= message == expected;
+    }
+    let elapsed = start.elapsed();
+    sender.join().unwrap();
+    Ok(in_order.then_some(elapsed))
+}
+
+fn sync_channel_run(messages: u32, capacity: usize) -> Result<Option<Duration>, SpawnError> {
+    use std::sync::mpsc;
+    
+    let (sender, receiver) = mpsc::sync_channel::<u32>(capacity);
+    let start = Instant::now();
+    let producer = spawn_policy::spawn_required(move || {
+        for message in 0..messages {
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    })?;
+    let in_order = receiver.iter().take(messages as usize).eq(0..messages);
+    let elapsed = start.elapsed();
+    producer.join().unwrap();
+    Ok(in_order.then_some(elapsed))
+}
+
+// The hardware timer: raise the line `ticks` times, `period` apart, on a
+// thread of its own
+fn start_timer<F>(interrupts: &Arc<Interrupts>, ticks: u32, period: Duration, mut handler: F) -> Result<TimerThread, SpawnError>
+where
+    F: FnMut(u32, &CriticalSection) + Send + 'static,
+{
+    let interrupts = Arc::clone(interrupts);
+    let finished = Arc::new(AtomicBool::new(false));
+    let done = Arc::clone(&finished);
+    let worker = spawn_policy::spawn_required(move || {
+        for tick in 0..ticks {
+            thread::sleep(period);
+            interrupts.raise(|cs| handler(tick, cs));
+        }
+        done.store(true, Ordering::SeqCst);
+    })?;
+    Ok(TimerThread { worker, finished })
+}
+
+struct TimerThread {
+    worker: spawn_policy::Worker<'static, ()>,
+    finished: Arc<AtomicBool>,
+}
+
+impl TimerThread {
+    fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::SeqCst)
+    }
+
+    fn join(self) {
+        self.worker.join().unwrap();
+    }
+}
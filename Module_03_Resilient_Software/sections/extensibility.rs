@@ -0,0 +1,85 @@
+/*!
+ * Extensibility Section
+ *
+ * Plugin traits students implement, with sealed extensions that keep the
+ * safety checks in the crate.
+ */
+
+use std::sync::Mutex;
+
+use safety_demo_macros::safety_demo;
+
+use crate::{event_log, extensions};
+
+// Demonstrate priority inversion and a lock-server mitigation
+// The built-in plugins, and a broken backend the crate catches
+#[safety_demo(
+    name = "extension_points",
+    module = "Extensibility",
+    description = "Plugin traits whose sealed extensions keep the safety checks in the crate",
+    invariants(
+        "a corrupted frame is never opened",
+        "only admitted requests run",
+        "a backend's output is only accepted in order and complete",
+        "every value reaches the aggregator exactly once"
+    )
+)]
+pub fn demonstrate_extension_points() {
+    use extensions::{
+        Aggregator, AggregatorExt, ChannelBackend, ChannelBackendExt, Checksum, ChecksumExt,
+        Fletcher32, Limiter, LimiterExt, Mean, MutexQueue, Sequenced, TokenBucket,
+    };
+    
+    narrate!("extensibility.extension_heading");
+    narrate!("extensibility.extension_intro");
+    
+    let checksum = Fletcher32;
+    let mut frame = checksum.frame(b"transfer 42 to account 7".to_vec());
+    let intact = checksum.open(&frame).is_ok();
+    frame.corrupt(9);
+    let caught = checksum.open(&frame);
+    let after_flip = caught.as_ref().map_or_else(|corrupted| corrupted.to_string(), |_| narration!("extensibility.opened"));
+    narrate!("extensibility.checksum_result", name = checksum.name(), intact, after_flip);
+    event_log::check_invariant("a corrupted frame is never opened", intact && caught.is_err());
+    
+    let mut bucket = TokenBucket::new(3, 4);
+    let arrivals: Vec<u64> = (0..20).collect();
+    let mut ran = 0;
+    let (_, stats) = bucket.drive(&arrivals, |_| ran += 1);
+    narrate!("extensibility.limiter_result", name = bucket.name(), stats);
+    event_log::check_invariant("only admitted requests run", ran == stats.admitted);
+    
+    // A backend a student might write: a stack hands items out newest first
+    struct Stack(Mutex<Vec<Sequenced<u32>>>);
+    impl ChannelBackend<u32> for Stack {
+        fn name(&self) -> &str {
+            "stack (LIFO)"
+        }
+        fn push(&self, item: Sequenced<u32>) {
+            self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(item);
+        }
+        fn pop(&self) -> Option<Sequenced<u32>> {
+            self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).pop()
+        }
+    }
+    let queue = MutexQueue::default();
+    let stack = Stack(Mutex::new(Vec::new()));
+    let items: Vec<u32> = (0..1000).collect();
+    let through_queue = queue.transfer(items.clone());
+    let through_stack = stack.transfer(items.clone());
+    narrate!("extensibility.queue_delivered", name = ChannelBackend::<u32>::name(&queue),
+             through_queue = through_queue.as_ref().map_or(0, Vec::len));
+    match &through_stack {
+        Ok(_) => narrate!("extensibility.stack_kept_up", name = stack.name()),
+        Err(violation) => narrate!("extensibility.stack_rejected", name = stack.name(), violation),
+    }
+    // Whatever the timing, accepted output is exactly the input
+    event_log::check_invariant("a backend's output is only accepted in order and complete",
+                               through_queue.as_ref() == Ok(&items)
+                               && through_stack.as_ref().map_or(true, |received| *received == items));
+    
+    let values: Vec<i64> = (1..=1000).collect();
+    let mean = Mean::aggregate(&values, 4);
+    narrate!("extensibility.aggregator_result", name = Mean::empty().name(), mean = ?mean);
+    event_log::check_invariant("every value reaches the aggregator exactly once", mean == Some(500.5));
+}
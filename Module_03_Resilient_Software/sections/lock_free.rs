@@ -0,0 +1,427 @@
+/*!
+ * Lock-Free Sections
+ *
+ * Atomics without locks: read-modify-write operations, what each memory
+ * ordering promises, a bounded counter built from compare-and-swap, an
+ * atomic cell for small structs, a lock-free queue, and hazard pointers
+ * for freeing its nodes safely.
+ */
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use safety_demo_macros::safety_demo;
+
+use crate::{event_log, hazard, lockfree, orderings, spawn_policy};
+use crate::atomic_store::{AtomicStore, AtomicValue, Locked, Native, PackedValue};
+use crate::demo_report;
+use crate::env_config::EnvConfig;
+use crate::lockfree::{LockFreeQueue, LockedQueue};
+use crate::orderings::Model;
+
+// A counter that never exceeds `max`: fetch_add cannot enforce a limit, so
+// each increment is a compare-and-swap loop that re-checks the bound
+#[derive(Debug)]
+struct BoundedCounter {
+    value: AtomicUsize,
+    max: usize,
+}
+
+impl BoundedCounter {
+    fn new(max: usize) -> Self {
+        BoundedCounter {
+            value: AtomicUsize::new(0),
+            max,
+        }
+    }
+    
+    // Ok(new value) or Err(value at the limit), plus the number of CAS retries
+    fn try_increment(&self) -> (Result<usize, usize>, u32) {
+        let mut attempts = 0u32;
+        let result = self.value.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| {
+            if attempts > 0 {
+                backoff(attempts);  // Another thread won the race - back off
+            }
+            attempts += 1;
+            if current >= self.max {
+                None  // Stop retrying: the bound is reached
+            } else {
+                Some(current + 1)
+            }
+        });
+        (result.map(|old| old + 1), attempts.saturating_sub(1))
+    }
+    
+    fn get(&self) -> usize {
+        self.value.load(Ordering::SeqCst)
+    }
+}
+
+// Exponential backoff: spin 2, 4, 8 ... 64 times, then also yield the core
+fn backoff(attempt: u32) {
+    for _ in 0..1u32 << attempt.min(6) {
+        std::hint::spin_loop();
+    }
+    if attempt > 6 {
+        thread::yield_now();
+    }
+}
+
+// Two coordinates packed into 64 bits: AtomicStore keeps it lock-free
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl PackedValue for Point {
+    fn to_bits(self) -> u64 {
+        ((self.x as u32 as u64) << 32) | self.y as u32 as u64
+    }
+    
+    fn from_bits(bits: u64) -> Self {
+        Point {
+            x: (bits >> 32) as u32 as i32,
+            y: bits as u32 as i32,
+        }
+    }
+}
+
+impl AtomicValue for Point {
+    type Storage = Native;
+}
+
+// 32 bytes do not fit in one native atomic: AtomicStore uses a lock
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reading {
+    pub sequence: u64,
+    pub samples: [u64; 3],
+}
+
+impl AtomicValue for Reading {
+    type Storage = Locked<Self>;
+}
+
+#[safety_demo(
+    name = "atomic_operations",
+    module = "Lock-Free",
+    description = "Atomic loads, stores, swaps and read-modify-write operations"
+)]
+pub fn demonstrate_atomic_operations(config: &EnvConfig) {
+    narrate!("lock_free.atomic_heading");
+    
+    let counter = Arc::new(AtomicUsize::new(0));
+    let flag = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    
+    let mut handles = vec![];
+    
+    // Multiple threads doing atomic operations
+    for i in 0..config.atomic_threads.get() {
+        let counter_clone = Arc::clone(&counter);
+        let flag_clone = Arc::clone(&flag);
+        
+        let handle = spawn_policy::spawn(move || {
+            // Atomic increment
+            let old_value = counter_clone.fetch_add(1, Ordering::SeqCst);
+            narrate!("lock_free.thread_incremented", i, old_value);
+            
+            // Atomic compare-and-swap
+            if old_value == 2 {
+                flag_clone.store(true, Ordering::SeqCst);
+                narrate!("lock_free.thread_set_flag", i);
+            }
+        });
+        
+        handles.push(handle);
+    }
+    
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    
+    narrate!("lock_free.final_counter", counter = counter.load(Ordering::SeqCst));
+    narrate!("lock_free.final_flag", flag = flag.load(Ordering::SeqCst));
+}
+
+// Demonstrate what each memory ordering promises, and what it costs
+#[safety_demo(
+    name = "memory_orderings",
+    module = "Lock-Free",
+    description = "Message passing and store buffering under Relaxed, Release/Acquire and SeqCst",
+    invariants(
+        "an acquired flag never comes with older data than it announced",
+        "SeqCst stores are never both missed"
+    )
+)]
+pub fn demonstrate_memory_orderings() {
+    const MESSAGES: usize = 200_000;
+    const ROUNDS: usize = 20_000;
+    
+    narrate!("lock_free.orderings_heading");
+    narrate!("lock_free.machine", machine = match (orderings::observable(), orderings::total_store_order()) {
+        (false, _) => narration!("lock_free.one_cpu"),
+        (true, true) => narration!("lock_free.x86_tso"),
+        (true, false) => narration!("lock_free.weakly_ordered"),
+    });
+    
+    narrate!("lock_free.message_passing_heading", messages = MESSAGES);
+    narrate!("lock_free.message_passing_header");
+    let mut passing = Vec::new();
+    for model in Model::ALL {
+        match orderings::message_passing(model, MESSAGES) {
+            Ok(run) => {
+                say!("{:<16} {:>12.0} {:>13} {:>12}", model, run.messages_per_ms(), run.samples, run.stale);
+                passing.push((model, run));
+            }
+            Err(error) => {
+                narrate!("lock_free.message_passing_skipped", model, error);
+                return;
+            }
+        }
+    }
+    let acquired = event_log::check_invariant("an acquired flag never comes with older data than it announced",
+                                              passing.iter().all(|(model, run)| *model == Model::Relaxed || run.stale == 0));
+    
+    narrate!("lock_free.store_buffering_heading", rounds = ROUNDS);
+    narrate!("lock_free.store_buffering_header");
+    let mut buffering = Vec::new();
+    for model in Model::ALL {
+        match orderings::store_buffering(model, ROUNDS) {
+            Ok(run) => {
+                say!("{:<16} {:>12.0} {:>12}", model, run.rounds_per_ms(), run.both_missed);
+                buffering.push((model, run));
+            }
+            Err(error) => {
+                narrate!("lock_free.store_buffering_skipped", model, error);
+                return;
+            }
+        }
+    }
+    let sequential = event_log::check_invariant("SeqCst stores are never both missed",
+                                                buffering.iter().all(|(model, run)| *model != Model::SeqCst || run.both_missed == 0));
+    
+    let weak = buffering.iter().filter(|(model, run)| *model != Model::SeqCst && run.both_missed > 0).count();
+    if weak > 0 {
+        narrate!("lock_free.store_buffering_seen");
+    } else {
+        narrate!("lock_free.no_weak_ordering_seen");
+    }
+    let cost = |model| passing.iter().find(|(m, _)| *m == model).map_or(0.0, |(_, run)| run.messages_per_ms());
+    narrate!("lock_free.seqcst_cost", cost = cost(Model::ReleaseAcquire) / cost(Model::SeqCst).max(f64::EPSILON));
+    narrate!("lock_free.safecounter_ordering");
+    demo_report::claim(acquired && sequential, &narration!("lock_free.orderings_claim"));
+}
+
+// Demonstrate CAS failure handling: a bounded counter under contention
+#[safety_demo(
+    name = "bounded_counter",
+    module = "Lock-Free",
+    description = "A compare-and-swap retry loop keeps a shared counter under its limit",
+    invariants(
+        "bounded counter never exceeds max"
+    )
+)]
+pub fn demonstrate_bounded_counter(config: &EnvConfig) {
+    narrate!("lock_free.bounded_heading");
+    
+    let attempts_per_thread = config.increments.get();
+    let max = config.atomic_threads.get() * attempts_per_thread / 2;
+    let counter = Arc::new(BoundedCounter::new(max));
+    narrate!("lock_free.bounded_workload", atomic_threads = config.atomic_threads.get(), attempts_per_thread, max);
+    
+    // Each thread returns (successes, rejections, retries per operation)
+    let handles: Vec<_> = (0..config.atomic_threads.get())
+        .map(|_| {
+            let counter_clone = Arc::clone(&counter);
+            spawn_policy::spawn(move || {
+                let (mut successes, mut rejections) = (0, 0);
+                let mut retries = Vec::with_capacity(attempts_per_thread);
+                for _ in 0..attempts_per_thread {
+                    let (result, retried) = counter_clone.try_increment();
+                    match result {
+                        Ok(_) => successes += 1,
+                        Err(_) => rejections += 1,
+                    }
+                    retries.push(retried);
+                }
+                (successes, rejections, retries)
+            })
+        })
+        .collect();
+    
+    let (mut successes, mut rejections) = (0, 0);
+    let mut retries = Vec::new();
+    for handle in handles {
+        let (thread_successes, thread_rejections, thread_retries) = handle.join().unwrap();
+        successes += thread_successes;
+        rejections += thread_rejections;
+        retries.extend(thread_retries);
+    }
+    
+    let final_value = counter.get();
+    narrate!("lock_free.bounded_final", final_value, max);
+    narrate!("lock_free.bounded_outcomes", successes, rejections);
+    event_log::check_invariant("bounded counter never exceeds max",
+                               final_value == max && successes == max);
+    
+    // Retry distribution: how often did a thread lose the CAS race?
+    let total_retries: u64 = retries.iter().map(|&r| r as u64).sum();
+    let worst = retries.iter().copied().max().unwrap_or(0);
+    narrate!("lock_free.cas_retries", total_retries, per_operation = total_retries as f64 / retries.len().max(1) as f64, worst);
+    if total_retries == 0 {
+        narrate!("lock_free.no_cas_failed");
+    }
+    let buckets = [("0", 0, 0), ("1", 1, 1), ("2-3", 2, 3), ("4-7", 4, 7), ("8+", 8, u32::MAX)];
+    for (label, low, high) in buckets {
+        let count = retries.iter().filter(|&&r| (low..=high).contains(&r)).count();
+        narrate!("lock_free.retry_histogram_row", label, count);
+    }
+}
+
+// Demonstrate atomically shared structs: a packed Point and a larger Reading
+#[safety_demo(
+    name = "atomic_store",
+    module = "Lock-Free",
+    description = "Small structs read and written atomically, lock-free when they fit in 64 bits",
+    invariants(
+        "atomic store reads are never torn"
+    )
+)]
+pub fn demonstrate_atomic_store(config: &EnvConfig) {
+    narrate!("lock_free.atomic_store_heading");
+    
+    let point = Arc::new(AtomicStore::new(Point { x: 0, y: 0 }));
+    let reading = Arc::new(AtomicStore::new(Reading { sequence: 0, samples: [0; 3] }));
+    say!("{:?}", point);
+    say!("{:?}", reading);
+    
+    // Writers keep x + y == 0 and samples == [sequence; 3]
+    let writers: Vec<_> = (0..config.atomic_threads.get())
+        .map(|_| {
+            let (point, reading, updates) = (Arc::clone(&point), Arc::clone(&reading), config.increments.get());
+            spawn_policy::spawn(move || {
+                for _ in 0..updates {
+                    point.fetch_update(|p| Point { x: p.x + 1, y: p.y - 1 });
+                    reading.fetch_update(|r| Reading { sequence: r.sequence + 1, samples: [r.sequence + 1; 3] });
+                }
+            })
+        })
+        .collect();
+    
+    // A reader checks that no load ever sees half of an update
+    let reader = {
+        let (point, reading) = (Arc::clone(&point), Arc::clone(&reading));
+        let updates = config.atomic_threads.get() * config.increments.get();
+        spawn_policy::spawn(move || {
+            let (mut loads, mut torn) = (0, 0);
+            loop {
+                let p = point.load();
+                let r = reading.load();
+                loads += 1;
+                if p.x + p.y != 0 || r.samples.iter().any(|&sample| sample != r.sequence) {
+                    torn += 1;
+                }
+                if r.sequence as usize == updates && p.x as usize == updates {
+                    return (loads, torn);
+                }
+                thread::yield_now();
+            }
+        })
+    };
+    
+    for writer in writers {
+        writer.join().unwrap();
+    }
+    let (loads, torn) = reader.join().unwrap();
+    
+    narrate!("lock_free.final_state", value = ?point);
+    narrate!("lock_free.final_state", value = ?reading);
+    narrate!("lock_free.torn_loads", loads, torn);
+    event_log::check_invariant("atomic store reads are never torn", torn == 0);
+    narrate!("lock_free.is_lock_free", point = point.is_lock_free(), reading = reading.is_lock_free());
+}
+
+// Demonstrate a whole lock-free structure: an MPMC queue against a Mutex<VecDeque>
+#[safety_demo(
+    name = "lockfree_queue",
+    module = "Lock-Free",
+    description = "A bounded lock-free MPMC queue raced against a Mutex<VecDeque> under contention",
+    invariants(
+        "every queued item is popped exactly once",
+        "each consumer sees a producer's items in order"
+    )
+)]
+pub fn demonstrate_lockfree_queue(config: &EnvConfig) {
+    narrate!("lock_free.mpmc_heading");
+    
+    let (threads, items, capacity) = (config.atomic_threads.get(), config.increments.get() * 10, 64);
+    narrate!("lock_free.mpmc_workload", threads, items, capacity);
+    let lock_free = Arc::new(LockFreeQueue::<u64>::new(capacity));
+    let runs = lockfree::run(Arc::clone(&lock_free), threads, threads, items)
+        .and_then(|lock_free_run| Ok((lock_free_run, lockfree::run(Arc::new(LockedQueue::new(capacity)), threads, threads, items)?)));
+    let (lock_free_run, locked_run) = match runs {
+        Ok(runs) => runs,
+        Err(error) => {
+            narrate!("lock_free.mpmc_skipped", error);
+            return;
+        }
+    };
+    
+    narrate!("lock_free.queue_header");
+    let retries = lock_free.retries().to_string();
+    for (name, run, retries) in [("LockFreeQueue", &lock_free_run, retries.as_str()), ("Mutex<VecDeque>", &locked_run, "-")] {
+        say!("{:<18} {:>9} {:>10.0} {:>13} {:>12}",
+             name, format!("{:.1?}", run.elapsed), run.items_per_ms(), run.full, retries);
+    }
+    narrate!("lock_free.cas_retry_meaning");
+    if !lock_free.is_lock_free() {
+        narrate!("lock_free.no_64_bit_atomics");
+    } else if lock_free.retries() == 0 {
+        narrate!("lock_free.no_cas_failed");
+    } else {
+        narrate!("lock_free.claims_lost", retries = lock_free.retries());
+    }
+    narrate!("lock_free.queue_speedup",
+             elapsed = locked_run.elapsed.as_secs_f64() / lock_free_run.elapsed.as_secs_f64().max(f64::EPSILON));
+    
+    let exactly_once = event_log::check_invariant("every queued item is popped exactly once",
+                                                  lock_free_run.exactly_once() && locked_run.exactly_once());
+    let in_order = event_log::check_invariant("each consumer sees a producer's items in order",
+                                              lock_free_run.in_order() && locked_run.in_order());
+    demo_report::claim(exactly_once && in_order, &narration!("lock_free.queue_claim"));
+}
+
+// Demonstrate safe memory reclamation: a Treiber stack that frees popped
+// nodes at once, against one that frees them through hazard pointers
+#[safety_demo(
+    name = "hazard_pointers",
+    module = "Lock-Free",
+    description = "A lock-free stack reads freed nodes when pops free at once; hazard pointers free only what no thread is reading",
+    invariants(
+        "no node is read after hazard pointers free it",
+        "every pushed item is popped exactly once"
+    )
+)]
+pub fn demonstrate_hazard_pointers(config: &EnvConfig) {
+    narrate!("lock_free.hazard_heading");
+    
+    let (threads, ops) = (config.atomic_threads.get().max(2), config.increments.get());
+    narrate!("lock_free.hazard_workload", threads, ops);
+    narrate!("lock_free.hazard_pop_yields");
+    narrate!("lock_free.hazard_freed_marked");
+    let runs = [
+        (narration!("lock_free.free_when_popped"), hazard::run(hazard::Reclaim::Immediate, threads, ops)),
+        (narration!("lock_free.with_hazard_pointers"), hazard::run(hazard::Reclaim::HazardPointers, threads, ops)),
+    ];
+    
+    narrate!("lock_free.reclamation_header");
+    for (label, run) in &runs {
+        say!("{:<18} {:>9} {:>7} {:>10} {:>12}", label, format!("{:.1?}", run.elapsed), run.popped.len(), run.reclaimed, run.stale_reads);
+    }
+    let (immediate, hazard) = (&runs[0].1, &runs[1].1);
+    demo_report::claim(immediate.stale_reads > 0, &narration!("lock_free.immediate_claim"));
+    let protected = event_log::check_invariant("no node is read after hazard pointers free it", hazard.stale_reads == 0);
+    demo_report::claim(protected && hazard.reclaimed > 0, &narration!("lock_free.hazard_claim"));
+    event_log::check_invariant("every pushed item is popped exactly once", runs.iter().all(|(_, run)| run.exactly_once()));
+}
@@ -0,0 +1,492 @@
+/*!
+ * Message Passing Sections
+ *
+ * Threads that share nothing and send messages instead: channels,
+ * their ordering and hang-ups, batching and deduplication, and a
+ * bounded buffer whose producers wait for room.
+ */
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use safety_demo_macros::safety_demo;
+
+use crate::{batching, error_sink, event_log, spawn_policy, step, vector_clock};
+use crate::bloom_filter::BloomFilter;
+use crate::bounded_buffer::BoundedBuffer;
+use crate::demo_report;
+use crate::env_config::{EnvConfig, Level};
+use crate::error_sink::ErrorKind;
+use crate::histogram::Histogram;
+
+#[safety_demo(
+    name = "channel_safety",
+    module = "Message Passing",
+    description = "Producers hand values to consumers over channels instead of sharing them"
+)]
+pub fn demonstrate_channel_safety(config: &EnvConfig) {
+    narrate!("message_passing.channel_heading");
+    
+    use std::sync::mpsc;
+    
+    let (sender, receiver) = mpsc::channel();
+    let (sink, errors) = error_sink::channel();
+    let received = Arc::new(AtomicUsize::new(0));
+    
+    // Producer thread
+    let messages = config.messages.get();
+    step::pause(&narration!("message_passing.before_start", messages), || narration!("message_passing.nothing_sent_yet"));
+    let send_delay = config.ticks(10);
+    let producer = spawn_policy::spawn(move || {
+        for i in 0..messages {
+            // SAFE: Ownership transferred
+            if sender.send(narration!("message_passing.message_text", i)).is_err() {
+                sink.report("producer", ErrorKind::ChannelClosed,
+                            narration!("message_passing.message_undelivered", i, messages));
+                break;
+            }
+            thread::sleep(send_delay);
+        }
+        // sender is dropped here, signaling end of messages
+    });
+    
+    // Consumer thread
+    let counted = Arc::clone(&received);
+    let consumer = spawn_policy::spawn(move || {
+        while let Ok(message) = receiver.recv() {  // SAFE: Exclusive ownership
+            narrate!("message_passing.received", message);
+            counted.fetch_add(1, Ordering::Relaxed);
+        }
+        narrate!("message_passing.all_messages_received");
+    });
+    
+    producer.join().unwrap();
+    consumer.join().unwrap();
+    errors.summarize();
+    step::pause(&narration!("message_passing.joined"), || {
+        narration!("message_passing.channel_closed_early", received = received.load(Ordering::Relaxed), messages)
+    });
+    
+    if config.level >= Level::Intermediate {
+        channel_latency_metrics(config);
+        batched_send_metrics(config);
+        channel_dedup_with_bloom(config);
+        causal_message_trace();
+    }
+    if config.level >= Level::Advanced {
+        demonstrate_receiver_hangup(config);
+    }
+}
+
+// INTERMEDIATE: three threads pass messages carrying vector clocks. A
+// sends m1 to B and then m2 to C; B forwards m3 to C after receiving m1.
+// m3 depends on m1, but m2 and m3 are concurrent - C may get them in
+// either order, and no timestamp could say which "really" came first.
+fn causal_message_trace() {
+    use std::sync::mpsc;
+    use vector_clock::{MessageAction, MessageEvent, VectorClock};
+    
+    narrate!("message_passing.causality_heading");
+    const PROCESSES: usize = 3;
+    let recorded = Mutex::new(Vec::new());
+    let events = &recorded;
+    let note = |events: &Mutex<Vec<MessageEvent>>, event: MessageEvent| {
+        event_log::message(&event);
+        events.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(event);
+    };
+    let event = |process: &str, action, peer: &str, label: &str, clock: &VectorClock| MessageEvent {
+        process: process.to_string(),
+        action,
+        peer: peer.to_string(),
+        label: label.to_string(),
+        clock: clock.clone(),
+    };
+    
+    let (to_b, b_inbox) = mpsc::channel::<(String, VectorClock)>();
+    let (to_c, c_inbox) = mpsc::channel::<(&str, String, VectorClock)>();
+    let to_c_from_b = to_c.clone();
+    thread::scope(|scope| {
+        spawn_policy::spawn_scoped(scope, move || {
+            let mut clock = VectorClock::new(PROCESSES);
+            for (label, peer) in [("m1", "B"), ("m2", "C")] {
+                clock.tick(0);
+                note(events, event("A", MessageAction::Send, peer, label, &clock));
+                let sent = match peer {
+                    "B" => to_b.send((label.to_string(), clock.clone())).is_ok(),
+                    _ => to_c.send(("A", label.to_string(), clock.clone())).is_ok(),
+                };
+                if !sent {
+                    break;
+                }
+            }
+        });
+        spawn_policy::spawn_scoped(scope, move || {
+            let mut clock = VectorClock::new(PROCESSES);
+            if let Ok((label, sender_clock)) = b_inbox.recv() {
+                clock.receive(1, &sender_clock);
+                note(events, event("B", MessageAction::Receive, "A", &label, &clock));
+                clock.tick(1);
+                note(events, event("B", MessageAction::Send, "C", "m3", &clock));
+                let _ = to_c_from_b.send(("B", "m3".to_string(), clock.clone()));
+            }
+        });
+        spawn_policy::spawn_scoped(scope, move || {
+            let mut clock = VectorClock::new(PROCESSES);
+            for (sender, label, sender_clock) in c_inbox.iter().take(2) {
+                clock.receive(2, &sender_clock);
+                note(events, event("C", MessageAction::Receive, sender, &label, &clock));
+            }
+        });
+    });
+    
+    let events = recorded.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner());
+    for event in &events {
+        match event.action {
+            MessageAction::Send => narrate!("message_passing.event_send", process = event.process, label = event.label,
+                                            peer = event.peer, clock = event.clock),
+            MessageAction::Receive => narrate!("message_passing.event_receive", process = event.process,
+                                               label = event.label, peer = event.peer, clock = event.clock),
+        }
+    }
+    
+    let concurrent = vector_clock::concurrent_sends(&events);
+    let labels: Vec<String> = concurrent.iter()
+        .map(|(first, second)| format!("{} || {}", events[*first].label, events[*second].label))
+        .collect();
+    narrate!("message_passing.concurrent_sends",
+             pairs = if labels.is_empty() { narration!("message_passing.no_pairs") } else { labels.join(", ") });
+    event_log::check_invariant("vector clocks find exactly m2 || m3", labels == ["m2 || m3"]);
+    narrate!("message_passing.sequence_diagram");
+    for line in vector_clock::sequence_diagram(&events).lines() {
+        say!("{}", line);
+    }
+}
+
+// ADVANCED: the consumer gives up halfway. With `send(..).unwrap()` the
+// producer would panic; here each failure is reported to the error sink
+// and the producer stops cleanly.
+fn demonstrate_receiver_hangup(config: &EnvConfig) {
+    use std::sync::mpsc;
+    
+    narrate!("message_passing.failure_heading", received = config.messages.get() / 2, messages = config.messages.get());
+    
+    // Rendezvous channel: a send completes only when the consumer takes it
+    let (sender, receiver) = mpsc::sync_channel(0);
+    let (sink, errors) = error_sink::channel();
+    let messages = config.messages.get();
+    
+    // Inline, it would wait for a producer that does not exist yet
+    let consumer = spawn_policy::spawn_required(move || {
+        receiver.iter().take(messages / 2).count()
+        // receiver is dropped here: later sends fail
+    });
+    let consumer = match consumer {
+        Ok(consumer) => consumer,
+        Err(error) => {
+            narrate!("message_passing.failure_skipped", error);
+            return;
+        }
+    };
+    let producer = spawn_policy::spawn(move || {
+        let mut sent = 0;
+        for i in 0..messages {
+            if sender.send(i).is_err() {
+                sink.report("producer", ErrorKind::ChannelClosed,
+                            narration!("message_passing.message_undelivered", i, messages));
+                break;
+            }
+            sent += 1;
+        }
+        sent
+    });
+    
+    let received = consumer.join().unwrap();
+    let sent = producer.join().unwrap();
+    narrate!("message_passing.failure_noticed", received, sent);
+    let reported = errors.summarize();
+    event_log::check_invariant("channel failures are reported", reported > 0 || sent == messages);
+}
+
+// INTERMEDIATE: time each message from send to receive
+fn channel_latency_metrics(config: &EnvConfig) {
+    use std::sync::mpsc;
+    
+    narrate!("message_passing.stress_heading", increments = config.increments.get());
+    
+    let (sender, receiver) = mpsc::channel::<Instant>();
+    let count = config.increments.get();
+    let start = Instant::now();
+    
+    let (sink, errors) = error_sink::channel();
+    let producer = spawn_policy::spawn(move || {
+        for i in 0..count {
+            if sender.send(Instant::now()).is_err() {
+                sink.report("producer", ErrorKind::ChannelClosed,
+                            narration!("message_passing.message_undelivered", i, messages = count));
+                break;
+            }
+        }
+    });
+    let latencies = Histogram::new();
+    receiver.iter().for_each(|sent| latencies.record(sent.elapsed()));
+    producer.join().unwrap();
+    errors.summarize();
+    
+    let elapsed = start.elapsed();
+    let latencies = latencies.snapshot();
+    latencies.record_metrics("channel.latency");
+    narrate!("message_passing.delivered", delivered = latencies.count(), count);
+    narrate!("message_passing.latency", mean = ?latencies.mean(), latencies);
+    narrate!("message_passing.throughput", rate = latencies.count() as f64 / elapsed.as_secs_f64());
+}
+
+// INTERMEDIATE: the same stream sent item by item and in batches
+fn batched_send_metrics(config: &EnvConfig) {
+    use std::sync::mpsc;
+    
+    // An odd count, so the last batch is always partial
+    let items = config.increments.get() * 100 + 7;
+    let producers = 2;
+    narrate!("message_passing.batching_heading", producers, items);
+    narrate!("message_passing.batch_header");
+    
+    let expected = producers * items;
+    let mut baseline = 0.0;
+    for batch_size in [1, 16, 256] {
+        let start = Instant::now();
+        let received = if batch_size == 1 {
+            let (sender, receiver) = mpsc::channel::<usize>();
+            let handles: Vec<_> = (0..producers)
+                .map(|_| {
+                    let sender = sender.clone();
+                    spawn_policy::spawn(move || {
+                        for i in 0..items {
+                            if sender.send(i).is_err() {
+                                break;
+                            }
+                        }
+                    })
+                })
+                .collect();
+            drop(sender);
+            let received = receiver.iter().count();
+            for handle in handles {
+                handle.join().unwrap();
+            }
+            received
+        } else {
+            let (sender, receiver) = batching::batch_channel::<usize>(batch_size);
+            let handles: Vec<_> = (0..producers)
+                .map(|_| {
+                    let mut sender = sender.clone();
+                    spawn_policy::spawn(move || {
+                        for i in 0..items {
+                            if sender.send(i).is_err() {
+                                break;
+                            }
+                        }
+                        // No explicit flush: dropping the sender sends the partial tail
+                    })
+                })
+                .collect();
+            drop(sender);
+            let received = receiver.iter().count();
+            for handle in handles {
+                handle.join().unwrap();
+            }
+            received
+        };
+        
+        let rate = received as f64 / start.elapsed().as_secs_f64();
+        if batch_size == 1 {
+            baseline = rate;
+        }
+        say!("{:<12} {:>14.0} {:>11.1}x {:>10}", batch_size, rate, rate / baseline, received);
+        event_log::check_invariant("batched channel delivers the tail", received == expected);
+    }
+}
+
+// INTERMEDIATE: producers with overlapping message ids share one Bloom
+// filter and skip ids another producer already sent - no lock involved
+fn channel_dedup_with_bloom(config: &EnvConfig) {
+    use std::collections::HashSet;
+    use std::sync::mpsc;
+    
+    let producers = config.threads.get().max(2);
+    let per_producer = config.increments.get();
+    let stride = per_producer / 2;  // Neighbouring producers overlap by half
+    let unique = stride * (producers - 1) + per_producer;
+    narrate!("message_passing.dedup_heading", producers, per_producer, unique);
+    
+    let filter = Arc::new(BloomFilter::with_rate(unique, 0.01));
+    narrate!("message_passing.dedup_filter", bit_count = filter.bit_count(), size = filter.size(),
+             hash_count = filter.hash_count(), false_positives = filter.expected_false_positive_rate(unique) * 100.0);
+    
+    let (sender, receiver) = mpsc::channel::<usize>();
+    let handles: Vec<_> = (0..producers)
+        .map(|producer| {
+            let (filter, sender) = (Arc::clone(&filter), sender.clone());
+            spawn_policy::spawn(move || {
+                let first = producer * stride;
+                let mut skipped = 0;
+                for id in first..first + per_producer {
+                    if filter.insert(&id) {
+                        skipped += 1;  // Seen before (or a false positive)
+                    } else if sender.send(id).is_err() {
+                        break;
+                    }
+                }
+                skipped
+            })
+        })
+        .collect();
+    drop(sender);
+    
+    let received: Vec<usize> = receiver.iter().collect();
+    let skipped: usize = handles.into_iter().map(|handle| handle.join().unwrap()).sum();
+    let distinct: HashSet<usize> = received.iter().copied().collect();
+    
+    let duplicates = received.len() - distinct.len();
+    let lost = unique - distinct.len();
+    narrate!("message_passing.dedup_counts", received = received.len(), skipped, distinct = distinct.len());
+    narrate!("message_passing.dedup_duplicates", duplicates);
+    narrate!("message_passing.dedup_false_positives", lost, percent = 100.0 * lost as f64 / unique as f64);
+    event_log::check_invariant("every received id was inserted", received.iter().all(|id| filter.contains(id)));
+}
+
+// Demonstrate condition variables: a bounded buffer whose producers wait
+// while it is full and whose consumers wait while it is empty
+#[safety_demo(
+    name = "bounded_buffer",
+    module = "Message Passing",
+    description = "A Mutex and two Condvars make producers wait on a full buffer and consumers on an empty one",
+    invariants(
+        "a push to a full buffer waits for a pop",
+        "a pop from an empty buffer waits for a push",
+        "every pushed item is popped exactly once"
+    )
+)]
+pub fn demonstrate_bounded_buffer(config: &EnvConfig) {
+    narrate!("message_passing.bounded_buffer_heading");
+    
+    const CAPACITY: usize = 3;
+    const ITEMS: usize = 5;
+    const PATIENCE: Duration = Duration::from_secs(5);  // a thread that never blocks is a bug, not a slow machine
+    let buffer = Arc::new(BoundedBuffer::new(CAPACITY));
+    narrate!("message_passing.bounded_buffer_intro", capacity = buffer.capacity());
+    
+    narrate!("message_passing.full_heading", items = ITEMS);
+    let producing = Arc::clone(&buffer);
+    let producer = spawn_policy::spawn_required(move || {
+        (0..ITEMS).take_while(|&item| producing.push(item).is_ok()).count()
+    });
+    let producer = match producer {
+        Ok(producer) => producer,
+        Err(error) => {
+            narrate!("message_passing.full_skipped", error);
+            return;
+        }
+    };
+    let blocked = buffer.wait_until(PATIENCE, |stats| stats.len == CAPACITY && stats.waiting_producers == 1);
+    if blocked {
+        narrate!("message_passing.producer_asleep", capacity = CAPACITY, next = CAPACITY + 1);
+    }
+    let popped: Vec<usize> = (0..ITEMS).map_while(|_| if blocked { buffer.pop() } else { None }).collect();
+    buffer.close();  // wakes the producer if it never got room
+    let pushed = producer.join().unwrap();
+    narrate!("message_passing.main_popped", popped = ?popped);
+    let full_waits = event_log::check_invariant("a push to a full buffer waits for a pop", blocked && pushed == ITEMS);
+    
+    narrate!("message_passing.empty_heading");
+    let buffer = Arc::new(BoundedBuffer::new(CAPACITY));
+    let mut consumers = Vec::new();
+    for _ in 0..2 {
+        let consuming = Arc::clone(&buffer);
+        match spawn_policy::spawn_required(move || std::iter::from_fn(|| consuming.pop()).collect::<Vec<usize>>()) {
+            Ok(consumer) => consumers.push(consumer),
+            Err(error) => {
+                narrate!("message_passing.empty_skipped", error);
+                buffer.close();
+                return;
+            }
+        }
+    }
+    let blocked = buffer.wait_until(PATIENCE, |stats| stats.waiting_consumers == 2);
+    if blocked {
+        narrate!("message_passing.consumers_asleep");
+    }
+    let pushed = (0..ITEMS).take_while(|&item| buffer.push(item).is_ok()).count();
+    buffer.close();
+    let mut received: Vec<usize> = consumers.into_iter().flat_map(|consumer| consumer.join().unwrap()).collect();
+    narrate!("message_passing.main_pushed", pushed);
+    received.sort_unstable();
+    narrate!("message_passing.consumers_popped", received = received.len());
+    let empty_waits = event_log::check_invariant("a pop from an empty buffer waits for a push", blocked);
+    let mut exactly_once = event_log::check_invariant("every pushed item is popped exactly once",
+                                                      popped == [0, 1, 2, 3, 4] && received == [0, 1, 2, 3, 4]);
+    
+    if config.level >= Level::Intermediate {
+        exactly_once &= bounded_buffer_contention(config);
+    }
+    demo_report::claim(full_waits && empty_waits && exactly_once, &narration!("message_passing.bounded_buffer_claim"));
+}
+
+// INTERMEDIATE: many producers and consumers on a small buffer, counting
+// how often each side had to sleep
+fn bounded_buffer_contention(config: &EnvConfig) -> bool {
+    let (threads, items, capacity) = (config.threads.get(), config.increments.get(), 4);
+    narrate!("message_passing.contention_heading", threads, items, capacity);
+    
+    let buffer = Arc::new(BoundedBuffer::new(capacity));
+    let (push_waits, pop_waits) = (Histogram::new(), Histogram::new());  // shared by every producer, every consumer
+    let start = Instant::now();
+    let received = thread::scope(|scope| {
+        let consumers: Vec<_> = (0..threads)
+            .map(|_| {
+                spawn_policy::spawn_scoped(scope, || {
+                    std::iter::from_fn(|| {
+                        let start = Instant::now();
+                        let item = buffer.pop()?;
+                        pop_waits.record(start.elapsed());
+                        Some(item)
+                    })
+                    .collect::<Vec<usize>>()
+                })
+            })
+            .collect();
+        let producers: Vec<_> = (0..threads)
+            .map(|producer| {
+                let (buffer, push_waits) = (&buffer, &push_waits);
+                spawn_policy::spawn_scoped(scope, move || {
+                    for item in producer * items..(producer + 1) * items {
+                        let start = Instant::now();
+                        if buffer.push(item).is_err() {
+                            break;
+                        }
+                        push_waits.record(start.elapsed());
+                    }
+                })
+            })
+            .collect();
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        buffer.close();
+        consumers.into_iter().flat_map(|consumer| consumer.join().unwrap()).collect::<Vec<usize>>()
+    });
+    
+    let stats = buffer.stats();
+    narrate!("message_passing.contention_moved", received = received.len(), elapsed = ?start.elapsed());
+    narrate!("message_passing.contention_slept", blocked_pushes = stats.blocked_pushes, blocked_pops = stats.blocked_pops);
+    let (push_waits, pop_waits) = (push_waits.snapshot(), pop_waits.snapshot());
+    push_waits.record_metrics("bounded_buffer.push");
+    pop_waits.record_metrics("bounded_buffer.pop");
+    narrate!("message_passing.push_waits", push_waits);
+    narrate!("message_passing.pop_waits", pop_waits);
+    let mut sorted = received;
+    sorted.sort_unstable();
+    event_log::check_invariant("every pushed item is popped exactly once",
+                               sorted.iter().copied().eq(0..threads * items))
+}
@@ -0,0 +1,214 @@
+/*!
+ * Thread Safety Sections
+ *
+ * The thread_safe demonstrations, one file per module of the course, and
+ * what runs them: SECTIONS lists them in presentation order, registry()
+ * adds the other demo binaries, and run_section() runs one section under
+ * its time budget wherever a section runs - the full run, the lecture,
+ * the quiz and the sandbox. The thread_safe binary only parses its
+ * arguments and dispatches here.
+ */
+
+pub mod async_tasks;
+pub mod distributed;
+pub mod embedded;
+pub mod extensibility;
+pub mod lock_free;
+pub mod message_passing;
+pub mod resilience;
+pub mod scheduling;
+pub mod shared_state;
+pub mod type_system;
+pub mod versioned_data;
+
+use std::panic::AssertUnwindSafe;
+
+#[cfg(all(feature = "plugins", target_os = "linux"))]
+use crate::plugins;
+use crate::{budget, messages, platform};
+use crate::demo_report::{self, BinaryDemo, Demo, Outcome, Section};
+use crate::env_config::EnvConfig;
+use crate::messages::Message;
+use crate::panic_strategy;
+use crate::term::Severity;
+use crate::verify;
+
+use async_tasks::{ASYNC_MUTEX, ASYNC_RESILIENCE, ASYNC_STREAMS, HEDGED_REQUESTS};
+use distributed::{CRDT_REPLICATION, DELIVERY_SEMANTICS, NETWORK_PARTITION};
+use embedded::INTERRUPT_HANDLERS;
+use extensibility::EXTENSION_POINTS;
+use lock_free::{ATOMIC_OPERATIONS, ATOMIC_STORE, BOUNDED_COUNTER, HAZARD_POINTERS, LOCKFREE_QUEUE, MEMORY_ORDERINGS};
+use message_passing::{BOUNDED_BUFFER, CHANNEL_SAFETY};
+use resilience::{ADAPTIVE_CONCURRENCY, PANIC_STRATEGY, RESOURCE_EXHAUSTION, SPECULATIVE_EXECUTION};
+use scheduling::{PRIORITY_INVERSION, WORK_STEALING};
+use shared_state::{COUNTER_SAFETY, DEADLOCK, LAZY_INIT, MUTEX_SAFETY, OS_PARKING, RWLOCK_SAFETY};
+use type_system::{COMPILE_TIME_SAFETY, SCOPED_THREADS, SEND_SYNC_TRAITS, TYPED_PHASES};
+use versioned_data::{PERSISTENT_LIST, VERSIONED_STORE};
+
+// Every demonstration, in presentation order
+pub const SECTIONS: [Section; 35] = [
+    COUNTER_SAFETY,
+    MUTEX_SAFETY,
+    RWLOCK_SAFETY,
+    LAZY_INIT,
+    DEADLOCK,
+    SEND_SYNC_TRAITS,
+    CHANNEL_SAFETY,
+    BOUNDED_BUFFER,
+    OS_PARKING,
+    SCOPED_THREADS,
+    TYPED_PHASES,
+    ASYNC_STREAMS,
+    ASYNC_MUTEX,
+    ASYNC_RESILIENCE,
+    HEDGED_REQUESTS,
+    NETWORK_PARTITION,
+    CRDT_REPLICATION,
+    DELIVERY_SEMANTICS,
+    ATOMIC_OPERATIONS,
+    MEMORY_ORDERINGS,
+    BOUNDED_COUNTER,
+    ATOMIC_STORE,
+    LOCKFREE_QUEUE,
+    HAZARD_POINTERS,
+    VERSIONED_STORE,
+    PERSISTENT_LIST,
+    PRIORITY_INVERSION,
+    WORK_STEALING,
+    INTERRUPT_HANDLERS,
+    EXTENSION_POINTS,
+    ADAPTIVE_CONCURRENCY,
+    SPECULATIVE_EXECUTION,
+    RESOURCE_EXHAUSTION,
+    PANIC_STRATEGY,
+    COMPILE_TIME_SAFETY,
+];
+
+// Lecture and quiz look sections up by name
+const _: () = assert!(demo_report::names_distinct(&SECTIONS), "two sections share a name");
+
+// The other demo binaries, run as child processes
+pub const BINARY_DEMOS: [BinaryDemo; 3] = [
+    BinaryDemo {
+        binary: "memory_safe",
+        description: "Ownership, borrowing and lifetimes rule out use after free and double free",
+        properties: &["no use after free", "no double free"],
+    },
+    BinaryDemo {
+        binary: "option_safe",
+        description: "Option and Result replace null pointers and unchecked errors",
+        properties: &["no null dereference"],
+    },
+    BinaryDemo {
+        binary: "buffer_safe",
+        description: "Bounds checks stop reads and writes past the end of a buffer",
+        properties: &["no out-of-bounds access"],
+    },
+];
+
+// Every demonstration tooling can list and run: the sections, then the other binaries
+pub fn registry() -> Vec<Box<dyn Demo>> {
+    let sections = SECTIONS.into_iter().map(|section| Box::new(section) as Box<dyn Demo>);
+    sections.chain(BINARY_DEMOS.into_iter().map(|demo| Box::new(demo) as Box<dyn Demo>)).collect()
+}
+
+pub fn print_summary() {
+    say!("{}", messages::text(&Message::SummaryHeading {}));
+    for line in messages::text(&Message::Summary {}).lines() {
+        say!("{}", line);
+    }
+}
+
+// Say that a demo panicked and the run goes on without it
+fn report_panic(panicked: &verify::DemoPanic) {
+    let (demo, message) = (panicked.demo.as_str(), panicked.message.as_str());
+    say_as!(Severity::Violation, "{}", messages::text(&Message::DemoPanicked { demo, message }));
+}
+
+// Run one section under its time budget, wherever a section runs: the
+// full run, the lecture and the quiz. A panic in it becomes a DemoPanic,
+// said here, and the caller goes on with the next section. catch_unwind
+// cannot stop a panic that aborts; a child process can
+pub fn run_section(section: &Section, config: &EnvConfig) -> Result<Outcome, verify::DemoPanic> {
+    let run = AssertUnwindSafe(|| budget::run_budgeted(section.name, config.budget(), || (section.run)(config)));
+    let outcome = match (panic_strategy::UNWINDS, section.needs_unwind) {
+        (true, _) => verify::run_isolated(section.name, run),
+        (false, true) => Ok(panic_strategy::skip(section.name)),
+        (false, false) => panic_strategy::run_in_child(section, config),
+    };
+    if let Err(panicked) = &outcome {
+        report_panic(panicked);
+    }
+    outcome
+}
+
+// Returns the exit code: 0, or with --verify, whether every declared
+// invariant held; without it, 1 if a demo panicked. A Ctrl+C lets the
+// running section finish, then skips the rest and returns
+// INTERRUPTED_EXIT_CODE
+pub fn run_demonstrations(config: &EnvConfig) -> i32 {
+    say!("{}", messages::text(&Message::Title {}));
+    platform::handle_interrupts();
+    
+    if !panic_strategy::UNWINDS {
+        say!("{}", messages::text(&Message::AbortIsolation {}));
+    }
+    
+    let (mut results, mut done) = (Vec::new(), 0);
+    for section in SECTIONS.iter().take_while(|_| !platform::interrupted()) {
+        let outcome = run_section(section, config);
+        results.push(verify::Verified { name: section.name, outcome });
+        done += 1;
+    }
+    let interrupted = platform::interrupted();
+    let mut plugins_panicked = false;
+    if interrupted {
+        say!("{}", messages::text(&Message::Interrupted { done, total: SECTIONS.len() }));
+    } else {
+        plugins_panicked = run_plugins(config);
+    }
+    
+    print_summary();
+    let verified = if config.verify {
+        verify::report(&results)
+    } else if plugins_panicked || results.iter().any(|result| result.outcome.is_err()) {
+        verify::FAILED_EXIT_CODE
+    } else {
+        0
+    };
+    if interrupted { platform::INTERRUPTED_EXIT_CODE } else { verified }
+}
+
+// Demos from plugin libraries run after the built-in sections, each as
+// its own section under the same budget; returns whether one panicked
+#[cfg(all(feature = "plugins", target_os = "linux"))]
+fn run_plugins(config: &EnvConfig) -> bool {
+    let Some(dir) = &config.plugin_dir else { return false };
+    let (loaded, errors) = plugins::load_dir(dir);
+    for error in &errors {
+        say!("{}", messages::text(&Message::PluginRejected { error: error.to_string() }));
+    }
+    let mut panicked = false;
+    for plugin in &loaded {
+        let run = AssertUnwindSafe(|| {
+            budget::run_budgeted(&plugin.name, config.budget(), || {
+                crate::event_log::demo_started(&plugin.name);
+                say!("{}", messages::text(&Message::PluginHeading { name: &plugin.name, summary: &plugin.summary }));
+                plugin.run();
+            })
+        });
+        if let Err(plugin_panic) = verify::run_isolated(&plugin.name, run) {
+            report_panic(&plugin_panic);
+            panicked = true;
+        }
+    }
+    panicked
+}
+
+#[cfg(not(all(feature = "plugins", target_os = "linux")))]
+fn run_plugins(config: &EnvConfig) -> bool {
+    if let Some(dir) = &config.plugin_dir {
+        say!("{}", messages::text(&Message::PluginsIgnored { dir: dir.display().to_string() }));
+    }
+    false
+}
@@ -0,0 +1,249 @@
+/*!
+ * Resilience Sections
+ *
+ * Staying up under load and faults: adaptive concurrency, speculative
+ * execution, running out of resources, and what a panic costs under
+ * each panic strategy.
+ */
+
+use std::sync::Mutex;
+
+use safety_demo_macros::safety_demo;
+
+use crate::{aimd, event_log, exhaustion, speculative};
+use crate::demo_report;
+use crate::env_config::EnvConfig;
+use crate::panic_strategy;
+use crate::sandbox;
+use crate::term::Severity;
+use crate::versioned_store::VersionedStore;
+
+// A fixed pool against an AIMD-sized one, on a backend that slows down twice
+#[safety_demo(
+    name = "adaptive_concurrency",
+    module = "Resilience",
+    description = "A fixed worker pool against an AIMD-sized one on a backend that slows down",
+    invariants(
+        "workers back off within a window of each slowdown",
+        "workers climb back after each slowdown",
+        "adaptive workers keep more windows under target"
+    )
+)]
+pub fn demonstrate_adaptive_concurrency() {
+    narrate!("resilience.aimd_heading");
+    
+    narrate!("resilience.backend", capacity = aimd::HEALTHY.capacity, service = aimd::HEALTHY.service.as_millis(),
+             degraded_capacity = aimd::DEGRADED.capacity, degraded_service = aimd::DEGRADED.service.as_millis());
+    let fixed = aimd::run(aimd::Sizing::Fixed(aimd::MAX_WORKERS));
+    let adaptive = aimd::run(aimd::Sizing::Adaptive(aimd::adaptive()));
+    
+    narrate!("resilience.aimd_rule", target = aimd::TARGET.as_millis());
+    for line in aimd::plot(&adaptive) {
+        say!("  {}", line);
+    }
+    
+    let fixed_label = narration!("resilience.fixed_workers", max_workers = aimd::MAX_WORKERS);
+    for (label, windows) in [(fixed_label, &fixed), (narration!("resilience.aimd"), &adaptive)] {
+        let worst = windows.iter().map(|window| window.latency).max().unwrap_or_default();
+        let served: u64 = windows.iter().map(|window| window.served).sum();
+        narrate!("resilience.aimd_row", label, over_target = aimd::over_target(windows), windows = windows.len(),
+                 worst = worst.as_millis(), served);
+    }
+    
+    let backs_off = event_log::check_invariant("workers back off within a window of each slowdown", aimd::backs_off(&adaptive));
+    let recovers = event_log::check_invariant("workers climb back after each slowdown", aimd::recovers(&adaptive));
+    let on_target = event_log::check_invariant("adaptive workers keep more windows under target",
+                                               aimd::over_target(&adaptive) < aimd::over_target(&fixed));
+    demo_report::claim(backs_off && recovers && on_target, &narration!("resilience.aimd_claim"));
+}
+
+// Who won a race, and what became of the others
+fn describe_race<T, E: std::fmt::Display>(won: &speculative::Won<T, E>) -> String {
+    let list = |names: &[String]| if names.is_empty() { narration!("resilience.none") } else { names.join(", ") };
+    let failed: Vec<String> = won.failed.iter().map(|(name, error)| format!("{} ({})", name, error)).collect();
+    let mut text = narration!("resilience.race_outcome", winner = won.winner, cancelled = list(&won.cancelled),
+                              too_late = list(&won.discarded), failed = list(&failed));
+    if !won.panicked.is_empty() {
+        text += &narration!("resilience.panicked", panicked = won.panicked.join(", "));
+    }
+    text
+}
+
+// Racing three strategies that write as they go, then the same strategies
+// staging their writes for a versioned commit
+#[safety_demo(
+    name = "speculative_execution",
+    module = "Resilience",
+    description = "Racing strategies, where only the winner's staged writes commit",
+    invariants(
+        "a race on a stale snapshot commits nothing",
+        "the staged race applies the transfers exactly once"
+    )
+)]
+pub fn demonstrate_speculative_execution(config: &EnvConfig) {
+    narrate!("resilience.speculative_heading");
+    
+    let tick = config.ticks(1);
+    let opening = vec![speculative::OPENING_BALANCE; speculative::ACCOUNTS];
+    let names: Vec<&str> = speculative::STRATEGIES.iter().map(|strategy| strategy.name).collect();
+    narrate!("resilience.racing", names = names.join(", "));
+    
+    let ledger = Mutex::new(opening.clone());
+    match speculative::naive_race(&ledger, tick) {
+        Ok(won) => narrate!("resilience.in_place_won", race = describe_race(&won)),
+        Err(error) => narrate!("resilience.in_place_failed", error),
+    }
+    let naive = ledger.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let expected = speculative::applied_once(opening.clone());
+    let off = naive.iter().zip(&expected).filter(|(actual, expected)| actual != expected).count();
+    narrate_as!(if off > 0 { Severity::Violation } else { Severity::Safe }, "resilience.in_place_ledger",
+                naive = ?naive, off, accounts = speculative::ACCOUNTS);
+    
+    let store = VersionedStore::new(opening);
+    let stale = store.pin();
+    store.update(|ledger| {
+        let mut ledger = ledger.clone();
+        ledger[0] += 500;
+        ledger
+    });
+    narrate!("resilience.staged_stale");
+    let version = store.latest_version();
+    match speculative::staged_race(&store, &stale, tick) {
+        Ok(won) => say!("  {}", describe_race(&won)),
+        Err(error) => say!("  {}", error),
+    }
+    event_log::check_invariant("a race on a stale snapshot commits nothing", store.latest_version() == version);
+    drop(stale);
+    
+    narrate!("resilience.staged_latest");
+    let base = store.pin();
+    let expected = speculative::applied_once(base.to_vec());
+    match speculative::staged_race(&store, &base, tick) {
+        Ok(won) => narrate!("resilience.committed_version", race = describe_race(&won), version = won.value),
+        Err(error) => say!("  {}", error),
+    }
+    let committed = store.pin();
+    narrate!("resilience.ledger", committed = ?*committed);
+    let exactly_once = event_log::check_invariant("the staged race applies the transfers exactly once",
+                                                  *committed == expected && committed.version() == base.version() + 1);
+    demo_report::claim(exactly_once, &narration!("resilience.speculative_claim"));
+}
+
+// Threads, files and sockets past a lowered OS limit: the naive version
+// gets a typed error instead of a panic, the resilient one makes do
+#[safety_demo(
+    name = "resource_exhaustion",
+    module = "Resilience",
+    description = "Threads, files and sockets past a lowered OS limit, handled as typed errors",
+    invariants(
+        "work completes on the threads that could be spawned",
+        "file pool reads every file within the descriptor limit",
+        "one connection at a time serves every request"
+    )
+)]
+pub fn demonstrate_resource_exhaustion() {
+    narrate!("resilience.exhaustion_heading");
+    
+    narrate!("resilience.thread_budget", stacks_that_fit = exhaustion::STACKS_THAT_FIT,
+             wanted_workers = exhaustion::WANTED_WORKERS);
+    match exhaustion::threads_demo() {
+        Ok(run) => {
+            if let Some(error) = &run.error {
+                say_as!(Severity::Fault, "  {}", error);
+            }
+            narrate!("resilience.workers_ran", spawned = run.spawned, wanted_workers = exhaustion::WANTED_WORKERS,
+                     sum = run.sum, expected = run.expected);
+            event_log::check_invariant("work completes on the threads that could be spawned", run.sum == run.expected);
+        }
+        Err(error) => narrate!("resilience.skipped", error),
+    }
+    
+    narrate!("resilience.file_budget", spare_fds = exhaustion::SPARE_FDS, files = exhaustion::FILES);
+    match exhaustion::files_demo() {
+        Ok(run) => {
+            if let Some(error) = &run.naive {
+                narrate_as!(Severity::Fault, "resilience.files_all_open", error);
+            }
+            narrate!("resilience.files_pooled", read = run.read, reads = 2 * exhaustion::FILES,
+                     peak_open = run.peak_open, evictions = run.evictions);
+            event_log::check_invariant("file pool reads every file within the descriptor limit",
+                                       run.read == 2 * exhaustion::FILES);
+        }
+        Err(error) => narrate!("resilience.skipped", error),
+    }
+    
+    narrate!("resilience.connection_budget", spare_fds = exhaustion::SPARE_FDS, requests = exhaustion::REQUESTS);
+    match exhaustion::sockets_demo() {
+        Ok(run) => {
+            if let Some(error) = &run.naive {
+                narrate_as!(Severity::Fault, "resilience.connections_all_open", error);
+            }
+            narrate!("resilience.connections_one_at_a_time", served = run.served, requests = exhaustion::REQUESTS);
+            event_log::check_invariant("one connection at a time serves every request", run.served == exhaustion::REQUESTS);
+        }
+        Err(error) => narrate!("resilience.skipped", error),
+    }
+    narrate!("resilience.exhaustion_claim");
+}
+
+// Unwind or abort: what a panic costs and what it leaves behind, in this
+// build and in the release builds of both strategies where they exist
+#[safety_demo(
+    name = "panic_strategy",
+    module = "Resilience",
+    description = "Panics that unwind against panics that abort: binary size, catch_unwind cost and what survives a panic",
+    invariants(
+        "catch_unwind returns what the direct calls do",
+        "each build's panic ends its process as its strategy says"
+    )
+)]
+pub fn demonstrate_panic_strategy() {
+    const CALLS: u64 = 1_000_000;
+    const PANICS: u32 = 1_000;
+    narrate!("resilience.panic_heading");
+    narrate!("resilience.panic_build", name = panic_strategy::name());
+    
+    narrate!("resilience.catch_unwind_cost_heading", calls = CALLS);
+    let (direct, caught, same) = panic_strategy::catch_cost(CALLS);
+    narrate!("resilience.catch_unwind_cost", direct = ?direct, caught = ?caught);
+    let same = event_log::check_invariant("catch_unwind returns what the direct calls do", same);
+    demo_report::claim(same, &narration!("resilience.catch_unwind_cost_claim"));
+    match panic_strategy::caught_panic_cost(PANICS) {
+        Some(each) => narrate!("resilience.caught_panic_cost", each = ?each, panics = PANICS),
+        None => narrate!("resilience.caught_panic_cost_none"),
+    }
+    
+    narrate!("resilience.poison_heading");
+    match panic_strategy::unwound_thread() {
+        Some(unwound) => {
+            narrate!("resilience.poison_outcome", dropped = unwound.dropped, poisoned = unwound.poisoned,
+                     joined = unwound.joined);
+            demo_report::claim(unwound.dropped && unwound.poisoned && unwound.joined, &narration!("resilience.poison_claim"));
+        }
+        None => narrate!("resilience.poison_abort"),
+    }
+    
+    narrate!("resilience.child_heading");
+    let own = sandbox::Probe::Panic.run();
+    let kib = |size: u64| narration!("resilience.kib", size = size / 1024);
+    narrate!("resilience.build_sizes_header");
+    narrate!("resilience.build_sizes_own", name = panic_strategy::name(),
+             size = panic_strategy::own_size().map_or("-".to_string(), kib), exit = own.exit);
+    let builds = panic_strategy::builds();
+    for (profile, strategy) in panic_strategy::PROFILES {
+        match builds.iter().find(|build| build.profile == profile) {
+            Some(build) => say!("  {:<14} {:<8} {:>10}  {}", profile, strategy, kib(build.size), build.probe.exit),
+            None => narrate!("resilience.build_sizes_missing", profile, strategy),
+        }
+    }
+    event_log::check_invariant("each build's panic ends its process as its strategy says",
+                               own.exit == sandbox::Probe::Panic.expected()
+                                   && builds.iter().all(panic_strategy::Build::probe_as_expected));
+    match &builds[..] {
+        [unwind, abort] => narrate!("resilience.build_sizes_saving",
+                                    size = unwind.size.saturating_sub(abort.size) / 1024,
+                                    percent = 100.0 * unwind.size.saturating_sub(abort.size) as f64 / unwind.size.max(1) as f64),
+        _ => narrate!("resilience.build_sizes_missing_both"),
+    }
+    narrate!("resilience.panic_claim");
+}
@@ -0,0 +1,233 @@
+/*!
+ * Scheduling Sections
+ *
+ * Who runs when: priority inversion on a simulated CPU and the priority
+ * ceiling that fixes it, and a work-stealing pool.
+ */
+
+use std::sync::{Arc, Mutex};
+
+use safety_demo_macros::safety_demo;
+
+use crate::{event_log, spawn_policy};
+use crate::demo_report;
+use crate::env_config::EnvConfig;
+use crate::sim_cpu::SimCpu;
+use crate::spawn_policy::SpawnError;
+use crate::work_stealing::{StealPolicy, WorkStealing, WorkerStats};
+
+// Priority inversion cast: (task, priority, arrival tick, ticks before the
+// critical section, ticks inside it). Medium never touches the resource.
+const LOW: (char, u8, u64, u32, u32) = ('L', 1, 0, 1, 4);
+const HIGH: (char, u8, u64, u32, u32) = ('H', 3, 2, 1, 2);
+const MEDIUM: (char, u8, u64, u32) = ('M', 2, 3, 10);
+const LOCK_SERVER: (char, u8) = ('S', 4);  // above every client: the priority ceiling
+
+// A task's critical section: `units` ticks of work on the shared resource
+type Request = (char, u8, u32, std::sync::mpsc::Sender<()>);
+
+// Run the three tasks on a simulated CPU; returns (trace, high's response time).
+// Every task waits for its turn on the CPU, so none can run inline: if one
+// cannot start, those already started stay blocked and the run is abandoned
+fn run_priority_scenario(use_lock_server: bool) -> Result<(String, u64), SpawnError> {
+    use std::sync::mpsc;
+    use std::sync::TryLockError;
+    
+    let cpu = Arc::new(SimCpu::new());
+    let resource = Arc::new(Mutex::new(0u32));
+    for (task, priority, arrival, _, _) in [LOW, HIGH] {
+        cpu.arrive(task, priority, arrival);
+    }
+    cpu.arrive(MEDIUM.0, MEDIUM.1, MEDIUM.2);
+    
+    // Mitigation: only the lock server touches the resource, and it runs
+    // every critical section at its own (ceiling) priority
+    let (request_sender, requests) = mpsc::channel::<Request>();
+    let server = use_lock_server.then(|| {
+        let (cpu, resource) = (Arc::clone(&cpu), Arc::clone(&resource));
+        spawn_policy::spawn_required(move || {
+            for (client, priority, units, reply) in requests {
+                let mut value = resource.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                for _ in 0..units {
+                    cpu.tick(LOCK_SERVER.0);
+                    *value += 1;
+                }
+                drop(value);
+                cpu.handoff(LOCK_SERVER.0, client, priority);
+                let _ = reply.send(());
+            }
+        })
+    }).transpose()?;
+    
+    let clients: Vec<_> = [LOW, HIGH]
+        .into_iter()
+        .map(|(task, priority, arrival, before, critical)| {
+            let (cpu, resource, request_sender) = (Arc::clone(&cpu), Arc::clone(&resource), request_sender.clone());
+            spawn_policy::spawn_required(move || {
+                for _ in 0..before {
+                    cpu.tick(task);
+                }
+                if use_lock_server {
+                    let (reply, done) = mpsc::channel();
+                    cpu.handoff(task, LOCK_SERVER.0, LOCK_SERVER.1);
+                    let _ = request_sender.send((task, priority, critical, reply));
+                    let _ = done.recv();
+                } else {
+                    let mut value = loop {
+                        match resource.try_lock() {
+                            Ok(guard) => break guard,
+                            Err(TryLockError::Poisoned(poisoned)) => break poisoned.into_inner(),
+                            Err(TryLockError::WouldBlock) => cpu.block_on_resource(task),
+                        }
+                    };
+                    for _ in 0..critical {
+                        cpu.tick(task);
+                        *value += 1;
+                    }
+                    drop(value);
+                    cpu.release_resource();
+                }
+                // Read before finishing: once it has, M may take the next ticks
+                let response = cpu.now() - arrival;  // response time in ticks
+                cpu.finish(task);
+                response
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    drop(request_sender);  // The server stops once both clients are done
+    
+    let medium = {
+        let cpu = Arc::clone(&cpu);
+        spawn_policy::spawn_required(move || {
+            for _ in 0..MEDIUM.3 {
+                cpu.tick(MEDIUM.0);  // CPU hog: never needs the resource
+            }
+            cpu.finish(MEDIUM.0);
+        })?
+    };
+    
+    let responses: Vec<u64> = clients.into_iter().map(|client| client.join().unwrap()).collect();
+    medium.join().unwrap();
+    if let Some(server) = server {
+        server.join().unwrap();
+    }
+    Ok((cpu.trace(), responses[1]))
+}
+
+#[safety_demo(
+    name = "priority_inversion",
+    module = "Scheduling",
+    description = "Priority inversion on a simulated CPU, and a lock server that bounds it",
+    invariants(
+        "lock server bounds high-priority wait"
+    )
+)]
+pub fn demonstrate_priority_inversion() {
+    narrate!("scheduling.inversion_heading");
+    narrate!("scheduling.inversion_low_high", holds = LOW.4, arrives = HIGH.2);
+    narrate!("scheduling.inversion_medium", arrives = MEDIUM.2, work = MEDIUM.3);
+    
+    let scenarios = run_priority_scenario(false).and_then(|inverted| Ok((inverted, run_priority_scenario(true)?)));
+    let ((trace, inverted), (served_trace, served)) = match scenarios {
+        Ok(scenarios) => scenarios,
+        Err(error) => {
+            narrate!("scheduling.skipped_task", error);
+            return;
+        }
+    };
+    narrate!("scheduling.mutex_trace", trace);
+    narrate!("scheduling.mutex_response", inverted);
+    
+    narrate!("scheduling.server_trace", trace = served_trace);
+    narrate!("scheduling.server_response", served);
+    
+    // Without preemption by M, H waits at most for one critical section
+    let bound = (LOW.4 + HIGH.3 + HIGH.4) as u64;
+    event_log::check_invariant("lock server bounds high-priority wait", served <= bound);
+    narrate!("scheduling.inversion_bound", bound, extra = inverted.saturating_sub(served));
+}
+
+// Demonstrate work stealing: one worker is dealt every long task, and the
+// others take its backlog over once their own deques run dry
+#[safety_demo(
+    name = "work_stealing",
+    module = "Scheduling",
+    description = "Per-worker deques: idle workers steal the backlog of a worker dealt every long task",
+    invariants(
+        "every task runs exactly once under every policy",
+        "without stealing, each worker runs only the tasks it was dealt"
+    )
+)]
+pub fn demonstrate_work_stealing(config: &EnvConfig) {
+    narrate!("scheduling.stealing_heading");
+    
+    const WORKERS: usize = 4;
+    const TASKS: usize = 32;
+    const LONG: u64 = 8;  // ticks; every other task takes 1
+    // Dealt round-robin, and every WORKERS-th task is long: worker 0 gets them all
+    let mut dealt = vec![Vec::new(); WORKERS];
+    for id in 0..TASKS {
+        dealt[id % WORKERS].push((id, if id % WORKERS == 0 { LONG } else { 1 }));
+    }
+    narrate!("scheduling.stealing_deal", tasks = TASKS, workers = WORKERS, long_tasks = TASKS / WORKERS, long = LONG);
+    narrate!("scheduling.stealing_rule");
+    
+    let (mut exactly_once, mut static_split) = (true, true);
+    let mut busiest = Vec::new();
+    for policy in [StealPolicy::Never, StealPolicy::StealOne, StealPolicy::StealHalf] {
+        let Some((stats, mut ran)) = steal_run(config, WorkStealing::new(dealt.clone(), policy)) else { return };
+        ran.sort_unstable();
+        exactly_once &= ran.iter().copied().eq(0..TASKS);
+        if policy == StealPolicy::Never {
+            static_split = stats.iter().all(|worker| worker.stolen == 0 && worker.ran == TASKS / WORKERS);
+        }
+        
+        narrate!("scheduling.policy_heading", policy);
+        narrate!("scheduling.stealing_header");
+        for (worker, worker_stats) in stats.iter().enumerate() {
+            say!("{:<8} {:>4} {:>4} {:>7} {:>7} {:>11}", worker, worker_stats.ran, worker_stats.own(),
+                 worker_stats.stolen, worker_stats.steals, worker_stats.busy_ticks);
+        }
+        let most = stats.iter().map(|worker| worker.busy_ticks).max().unwrap_or_default();
+        let steals: usize = stats.iter().map(|worker| worker.steals).sum();
+        narrate!("scheduling.stealing_busiest", most, steals);
+        busiest.push((most, steals));
+    }
+    
+    event_log::check_invariant("every task runs exactly once under every policy", exactly_once);
+    event_log::check_invariant("without stealing, each worker runs only the tasks it was dealt", static_split);
+    let [(alone, _), (one, one_trips), (half, half_trips)] = busiest[..] else { return };
+    demo_report::claim(one < alone && half < alone, &narration!("scheduling.stealing_claim"));
+    demo_report::claim(half_trips < one_trips, &narration!("scheduling.steal_half_claim"));
+}
+
+// Run `pool` to empty with one seeded task per worker; each task takes
+// its ticks. The workers' stats, and every task id that ran
+fn steal_run(config: &EnvConfig, pool: WorkStealing<(usize, u64)>) -> Option<(Vec<WorkerStats>, Vec<usize>)> {
+    let pool = Arc::new(pool);
+    let scheduler = config.scheduler();
+    let tasks: Vec<_> = (0..pool.workers()).map(|worker| scheduler.task(&narration!("scheduling.worker", worker))).collect();
+    let mut workers = Vec::new();
+    for (worker, task) in tasks.into_iter().enumerate() {
+        let pool = Arc::clone(&pool);
+        let spawned = spawn_policy::spawn_required(move || task.run(|task| {
+            let (mut stats, mut ran) = (WorkerStats::default(), Vec::new());
+            while let Some(taken) = pool.next(worker) {
+                let (id, ticks) = taken.task;
+                task.pause(ticks);
+                stats.record(worker, &taken, ticks);
+                ran.push(id);
+            }
+            (stats, ran)
+        }));
+        match spawned {
+            Ok(spawned) => workers.push(spawned),
+            Err(error) => {
+                narrate!("scheduling.skipped_worker", error);
+                return None;
+            }
+        }
+    }
+    let (stats, ran): (Vec<WorkerStats>, Vec<Vec<usize>>) = workers.into_iter().map(|worker| worker.join().unwrap()).unzip();
+    Some((stats, ran.concat()))
+}
@@ -0,0 +1,1087 @@
+/*!
+ * Shared State Sections
+ *
+ * Threads sharing one piece of state: an atomic counter, a Mutex and an
+ * RwLock around SharedData, one-time initialization, a deadlock and the
+ * lock-order check that catches it, and the OS parking the blocking
+ * primitives come down to.
+ */
+
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use resilient_core::invariants::Invariants as _;
+use resilient_core::{Event, SafeCounter, Semaphore, ShardedCounter, SharedData, ThreadPool};
+use safety_demo_macros::safety_demo;
+
+use crate::{data_layout, error_sink, event_log, lazy_init, spawn_policy, step};
+use crate::data_layout::{SharedColumns, SharedTable};
+use crate::demo_report;
+use crate::env_config::{EnvConfig, Level};
+use crate::histogram::{Histogram, Snapshot};
+use crate::lock_order::{LockOrder, TrackedMutex};
+use crate::scheduler::Scheduler;
+use crate::term::Severity;
+use crate::tracked_arc::TrackedArc;
+
+// The shared data as a step shows it
+fn describe_shared(shared: &Mutex<SharedData>) -> String {
+    let data = shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    narration!("shared_state.describe_shared", values = ?data.values(), sum = data.sum(), is_processing = data.is_processing())
+}
+
+// Console output for SharedData, which resilient_core leaves to its users
+trait PrintStats {
+    fn print_stats(&self);
+}
+
+impl PrintStats for SharedData {
+    fn print_stats(&self) {
+        self.debug_check_invariants();
+        narrate!("shared_state.stats", len = self.len(), sum = self.sum(), is_processing = self.is_processing());
+        
+        let mut values = String::new();
+        for value in self.values() {
+            values.push_str(&format!("{} ", value));
+        }
+        narrate!("shared_state.stats_values", values);
+    }
+}
+
+#[safety_demo(
+    name = "counter_safety",
+    module = "Shared State",
+    description = "Threads increment one atomic counter without losing an update",
+    invariants(
+        "no lost increments"
+    )
+)]
+pub fn demonstrate_counter_safety(config: &EnvConfig) {
+    narrate!("shared_state.counter_heading");
+    
+    let counter = TrackedArc::new("counter", SafeCounter::new());
+    let num_threads = config.threads.get();
+    let increments_per_thread = config.increments.get();
+    
+    step::pause(&narration!("shared_state.counter_before_start", num_threads, increments_per_thread),
+                || narration!("shared_state.counter", count = counter.get_count()));
+    let pool = task_pool(config, num_threads, 1);
+    let mut handles = vec![];
+    let start = Instant::now();
+    let first_finished = Arc::new(AtomicBool::new(false));
+    
+    // Launch threads that increment counter
+    for _ in 0..num_threads {
+        let counter_clone = counter.clone();
+        let first_finished = Arc::clone(&first_finished);
+        let handle = spawn_policy::spawn_in(pool.as_ref(), move || {
+            let mut stats = ThreadStats::default();
+            for _ in 0..increments_per_thread {
+                counter_clone.increment();  // SAFE: Atomic operation
+                stats.increments += 1;
+                // How far along was this thread when the first one finished?
+                if stats.at_first_finish.is_none() && first_finished.load(Ordering::Relaxed) {
+                    stats.at_first_finish = Some(stats.increments - 1);
+                }
+            }
+            first_finished.store(true, Ordering::Relaxed);
+            stats.finished = start.elapsed();
+            stats
+        });
+        handles.push(handle);
+    }
+    
+    // Wait for all threads to complete
+    let stats: Vec<ThreadStats> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+    step::pause(&narration!("shared_state.counter_joined"), || narration!("shared_state.counter", count = counter.get_count()));
+    
+    let expected = (num_threads * increments_per_thread) as i32;
+    let actual = counter.get_count();
+    
+    narrate!("shared_state.expected", expected);
+    narrate!("shared_state.actual", actual);
+    let exact = event_log::check_invariant("no lost increments", actual == expected);
+    demo_report::claim(exact, &narration!("shared_state.counter_claim"));
+    print_thread_distribution(&stats);
+    print_ref_counts(&counter);
+    
+    assert_eq!(actual, expected, "Counter should be exact with atomic operations");
+    
+    if config.level >= Level::Intermediate {
+        counter_stress_rounds(config);
+        sharded_counter_benchmark(config);
+    }
+    if config.level >= Level::Advanced {
+        demonstrate_lost_updates(config);
+    }
+}
+
+// Under SAFETY_DEMO_POOL=N, a pool of N threads to run the demo's `tasks`
+// on - at least `concurrent` of them, for tasks that must overlap. None
+// (a thread per task) by default, or when the pool cannot start
+fn task_pool(config: &EnvConfig, tasks: usize, concurrent: usize) -> Option<ThreadPool> {
+    let size = match config.pool.get() {
+        0 => return None,
+        size => size.max(concurrent),
+    };
+    match ThreadPool::try_new(size) {
+        Ok(pool) => {
+            narrate!("shared_state.pool_running", tasks, size = pool.size());
+            Some(pool)
+        }
+        Err(error) => {
+            narrate!("shared_state.pool_failed", size, error);
+            None
+        }
+    }
+}
+
+// What one counter thread did, for the per-thread distribution table
+#[derive(Debug, Default)]
+struct ThreadStats {
+    increments: usize,
+    at_first_finish: Option<usize>,  // increments done when the first thread finished
+    finished: Duration,              // completion time since the threads were launched
+}
+
+// The total hides the schedule: show each thread's share and finish time.
+// A fair, truly parallel run finishes threads close together; a run where
+// threads go one after another shows most threads at 0 (or already done)
+// when the first one finished.
+fn print_thread_distribution(stats: &[ThreadStats]) {
+    let total: usize = stats.iter().map(|s| s.increments).sum();
+    let last = stats.iter().map(|s| s.finished).max().unwrap_or_default();
+    
+    narrate!("shared_state.distribution_heading");
+    narrate!("shared_state.distribution_header");
+    for (index, thread_stats) in stats.iter().enumerate() {
+        let share = 100.0 * thread_stats.increments as f64 / total.max(1) as f64;
+        let at_first = match thread_stats.at_first_finish {
+            Some(count) => count.to_string(),
+            None => narration!("shared_state.done"),
+        };
+        let ratio = thread_stats.finished.as_secs_f64() / last.as_secs_f64().max(f64::EPSILON);
+        let bar = "#".repeat((ratio * 20.0).round() as usize);
+        say!("{:<8} {:>10} {:>6.1}% {:>12} {:>13.3}  {}", index, thread_stats.increments, share,
+             at_first, thread_stats.finished.as_secs_f64() * 1000.0, bar);
+    }
+    
+    let finish_times = Histogram::new();
+    stats.iter().for_each(|s| finish_times.record(s.finished));
+    let (min, avg, max) = timing_summary(&finish_times.snapshot());
+    narrate!("shared_state.finish_times", min, avg, max, spread = max - min);
+}
+
+// How the strong count moved while threads shared the value
+fn print_ref_counts<T>(shared: &TrackedArc<T>) {
+    let history = TrackedArc::history(shared);
+    let mut timeline: Vec<String> = history.iter().take(40).map(|count| count.to_string()).collect();
+    if history.len() > timeline.len() {
+        timeline.push(narration!("shared_state.timeline_more", more = history.len() - timeline.len()));
+    }
+    narrate!("shared_state.reference_count", timeline = timeline.join(" "), now = TrackedArc::strong_count(shared),
+             peak = TrackedArc::peak(shared));
+}
+
+// Summarize a histogram of timings as (min, average, max) in milliseconds;
+// the min is read from the lowest bucket, so it is at most 3.1% high
+fn timing_summary(timings: &Snapshot) -> (f64, f64, f64) {
+    let millis = |timing: Duration| timing.as_secs_f64() * 1000.0;
+    (millis(timings.percentile(0.0)), millis(timings.mean()), millis(timings.max()))
+}
+
+// INTERMEDIATE: repeat the counter workload and report timing metrics
+fn counter_stress_rounds(config: &EnvConfig) {
+    narrate!("shared_state.counter_stress_heading", rounds = config.stress_rounds.get(), threads = config.threads.get(),
+             increments = config.increments.get());
+    
+    let expected = (config.threads.get() * config.increments.get()) as i32;
+    let timings = Histogram::new();
+    
+    for _ in 0..config.stress_rounds.get() {
+        let counter = Arc::new(SafeCounter::new());
+        let start = Instant::now();
+        
+        let handles: Vec<_> = (0..config.threads.get())
+            .map(|_| {
+                let counter_clone = Arc::clone(&counter);
+                let increments = config.increments.get();
+                spawn_policy::spawn(move || {
+                    for _ in 0..increments {
+                        counter_clone.increment();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        
+        timings.record(start.elapsed());
+        let actual = counter.get_count();
+        event_log::check_invariant("no lost increments", actual == expected);
+        assert_eq!(actual, expected, "Counter should be exact in every stress round");
+    }
+    
+    let (min, avg, max) = timing_summary(&timings.snapshot());
+    narrate!("shared_state.round_times", min, avg, max);
+    narrate!("shared_state.throughput", millions = expected as f64 / (avg * 1000.0));
+    narrate!("shared_state.counter_stress_claim");
+}
+
+// INTERMEDIATE: SafeCounter against ShardedCounter as the thread count
+// grows, each thread doing the same number of increments
+fn sharded_counter_benchmark(config: &EnvConfig) {
+    let increments = config.increments.get() * 100;
+    let shards = ShardedCounter::new().shards();  // one per CPU
+    narrate!("shared_state.sharding_heading",
+             cpus = if shards == 1 { narration!("shared_state.one_cpu") } else { narration!("shared_state.cpus", shards) },
+             increments);
+    narrate!("shared_state.sharding_header");
+    
+    // Million increments a second, or None if a total came out wrong
+    fn rate(threads: usize, increments: usize, increment: &(dyn Fn() + Sync), total: &dyn Fn() -> i64) -> Option<f64> {
+        let start = Instant::now();
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| spawn_policy::spawn_scoped(scope, || (0..increments).for_each(|_| increment())))
+                .collect();
+            workers.into_iter().for_each(|worker| worker.join().unwrap());
+        });
+        let elapsed = start.elapsed();
+        (total() == (threads * increments) as i64)
+            .then(|| (threads * increments) as f64 / elapsed.as_secs_f64().max(f64::EPSILON) / 1e6)
+    }
+    
+    let mut exact = true;
+    let mut last = 1.0;
+    for threads in [1, 2, 4, 8, 16, 32] {
+        let (safe, sharded) = (SafeCounter::new(), ShardedCounter::new());
+        let safe_rate = rate(threads, increments, &|| safe.increment(), &|| safe.get_count() as i64);
+        let sharded_rate = rate(threads, increments, &|| sharded.increment(), &|| sharded.get_count());
+        match (safe_rate, sharded_rate) {
+            (Some(safe_rate), Some(sharded_rate)) => {
+                last = sharded_rate / safe_rate;
+                say!("{:<8} {:>18.1} {:>21.1} {:>7.1}x", threads, safe_rate, sharded_rate, last);
+            }
+            _ => {
+                exact = false;
+                narrate_as!(Severity::Violation, "shared_state.sharding_lost", threads);
+            }
+        }
+    }
+    event_log::check_invariant("no lost increments", exact);
+    if shards == 1 {
+        narrate!("shared_state.sharding_one_cpu");
+    } else {
+        narrate!("shared_state.sharding_claim", last);
+    }
+}
+
+// ADVANCED: the unsafe counterpart - a separate load and store instead of
+// one atomic read-modify-write. Each access is still atomic (no undefined
+// behavior), but concurrent increments overwrite each other exactly like
+// the C++ data_race.cpp example.
+fn demonstrate_lost_updates(config: &EnvConfig) {
+    narrate!("shared_state.racy_counter_heading");
+    
+    let counter = Arc::new(AtomicI32::new(0));
+    let handles: Vec<_> = (0..config.threads.get())
+        .map(|_| {
+            let counter_clone = Arc::clone(&counter);
+            let increments = config.increments.get();
+            spawn_policy::spawn(move || {
+                for _ in 0..increments {
+                    let value = counter_clone.load(Ordering::Relaxed);
+                    thread::yield_now();  // Widen the race window
+                    counter_clone.store(value + 1, Ordering::Relaxed);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    
+    let expected = (config.threads.get() * config.increments.get()) as i32;
+    let actual = counter.load(Ordering::SeqCst);
+    narrate!("shared_state.expected", expected);
+    narrate!("shared_state.actual", actual);
+    if actual < expected {
+        narrate!("shared_state.racy_counter_lost", lost = expected - actual);
+    } else {
+        narrate!("shared_state.racy_counter_lucky");
+    }
+}
+
+#[safety_demo(
+    name = "mutex_safety",
+    module = "Shared State",
+    description = "A writer and a reader share values and their sum behind a Mutex",
+    invariants(
+        "sum matches data"
+    )
+)]
+pub fn demonstrate_mutex_safety(config: &EnvConfig) {
+    narrate!("shared_state.mutex_heading");
+    
+    let shared_data = TrackedArc::new("shared_data", Mutex::new(SharedData::new()));
+    let (sink, errors) = error_sink::channel();
+    step::pause(&narration!("shared_state.mutex_before_start"), || describe_shared(&shared_data));
+    let scheduler = config.scheduler();
+    let (writer_task, reader_task) = (scheduler.task("writer"), scheduler.task("reader"));
+    let pool = task_pool(config, 2, 2);  // the writer and reader take turns
+    
+    // Thread 1: Adds data safely
+    let shared_data_writer = shared_data.clone();
+    let writer_sink = sink.clone();
+    let writes = config.writes.get() as i32;
+    let writer = spawn_policy::spawn_in(pool.as_ref(), move || writer_task.run(|task| {
+        for i in 0..writes {
+            {
+                let mut data = writer_sink.recover(shared_data_writer.lock(), "writer", "shared_data");
+                data.add_value(i);  // SAFE: Exclusive access via mutex
+            }  // Lock automatically released here
+            task.pause(1);
+        }
+    }));
+    
+    // Thread 2: Reads data safely
+    let shared_data_reader = shared_data.clone();
+    let reads = config.reads.get();
+    let reader = spawn_policy::spawn_in(pool.as_ref(), move || reader_task.run(|task| {
+        for _ in 0..reads {
+            {
+                let data = sink.recover(shared_data_reader.lock(), "reader", "shared_data");
+                data.print_stats();  // SAFE: Exclusive access via mutex
+            }  // Lock automatically released here
+            task.pause(5);
+        }
+    }));
+    
+    writer.join().unwrap();
+    reader.join().unwrap();
+    errors.summarize();
+    step::pause(&narration!("shared_state.mutex_joined"), || describe_shared(&shared_data));
+    describe_schedule(config, &scheduler);
+    
+    narrate!("shared_state.mutex_final_stats");
+    let final_data = shared_data.lock().unwrap();
+    final_data.print_stats();
+    event_log::check_invariant("sum matches data", final_data.check_invariants().is_ok());
+    drop(final_data);
+    print_ref_counts(&shared_data);
+    
+    if config.level >= Level::Intermediate {
+        mutex_contention_metrics(config);
+        snapshot_iteration_metrics(config);
+        layout_metrics();
+    }
+    if config.level >= Level::Advanced {
+        demonstrate_split_locks(config);
+    }
+}
+
+// Under SAFETY_DEMO_SEED (or --seed), the order the tasks ran in
+fn describe_schedule(config: &EnvConfig, scheduler: &Scheduler) {
+    let Some(seed) = config.seed else { return };
+    if !scheduler.is_seeded() {
+        narrate!("shared_state.schedule_gave_up", seed);
+        return;
+    }
+    narrate!("shared_state.schedule_trace", seed, trace = scheduler.trace().join(" > "));
+}
+
+// INTERMEDIATE: many writers hammering one Mutex, measuring lock wait time
+fn mutex_contention_metrics(config: &EnvConfig) {
+    narrate!("shared_state.mutex_stress_heading", threads = config.threads.get(), increments = config.increments.get());
+    
+    let shared_data = Arc::new(Mutex::new(SharedData::new()));
+    let (sink, errors) = error_sink::channel();
+    let handles: Vec<_> = (0..config.threads.get())
+        .map(|worker| {
+            let (shared_clone, sink) = (Arc::clone(&shared_data), sink.clone());
+            let updates = config.increments.get();
+            spawn_policy::spawn(move || {
+                let name = narration!("shared_state.writer", writer = worker);
+                let waits = Histogram::new();
+                for i in 0..updates {
+                    let start = Instant::now();
+                    let mut data = sink.recover(shared_clone.lock(), &name, "shared_data");
+                    waits.record(start.elapsed());
+                    data.add_value((i % 100) as i32);  // Small values keep the sum in range
+                }
+                waits.snapshot()
+            })
+        })
+        .collect();
+    
+    // Each writer kept its own histogram; merged, they give every wait
+    let per_writer: Vec<Snapshot> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+    let waits = Histogram::new();
+    per_writer.iter().for_each(|writer| waits.record(writer.total()));
+    let (min, avg, max) = timing_summary(&waits.snapshot());
+    let mut every_wait = Snapshot::default();
+    per_writer.iter().for_each(|writer| every_wait.merge(writer));
+    every_wait.record_metrics("mutex.lock_wait");
+    errors.summarize();
+    
+    let data = shared_data.lock().unwrap();
+    let consistent = data.check_invariants().is_ok();
+    event_log::check_invariant("sum matches data under contention", consistent);
+    narrate!("shared_state.mutex_acquisitions", acquisitions = data.len());
+    narrate!("shared_state.mutex_waits", min, avg, max);
+    narrate!("shared_state.mutex_each_wait", every_wait);
+    narrate!("shared_state.mutex_invariant", consistent);
+}
+
+// INTERMEDIATE: a slow reader iterating under the Mutex vs over a snapshot,
+// while writers keep adding values
+fn snapshot_iteration_metrics(config: &EnvConfig) {
+    narrate!("shared_state.snapshot_heading");
+    
+    narrate!("shared_state.snapshot_header");
+    for use_snapshot in [false, true] {
+        let shared_data = Arc::new(Mutex::new(SharedData::new()));
+        {
+            let mut data = shared_data.lock().unwrap();
+            for i in 0..config.increments.get() {
+                data.add_value((i % 100) as i32);
+            }
+        }
+        let done = Arc::new(AtomicBool::new(false));
+        
+        // The reader does a little work per element, as a real consumer would
+        let reader = {
+            let (shared_data, done) = (Arc::clone(&shared_data), Arc::clone(&done));
+            spawn_policy::spawn_required(move || {
+                let (mut passes, mut inconsistent) = (0, 0);
+                let consume = |values: std::slice::Iter<'_, i32>| {
+                    values.map(|&value| std::hint::black_box(value as i64 * 3 % 7)).sum::<i64>()
+                };
+                while !done.load(Ordering::SeqCst) {
+                    if use_snapshot {
+                        let snapshot = SharedData::iter_snapshot(&shared_data);
+                        consume(snapshot.iter());
+                        if snapshot.sum() != snapshot.iter().sum::<i32>() {
+                            inconsistent += 1;
+                        }
+                    } else {
+                        let data = shared_data.lock().unwrap();
+                        consume(data.values().iter());  // Writers wait for the whole pass
+                    }
+                    passes += 1;
+                }
+                (passes, inconsistent)
+            })
+        };
+        // It reads until the writers are done, so it cannot run inline first
+        let reader = match reader {
+            Ok(reader) => reader,
+            Err(error) => {
+                narrate!("shared_state.snapshot_skipped_reader", error);
+                return;
+            }
+        };
+        
+        let start = Instant::now();
+        let writers: Vec<_> = (0..config.threads.get())
+            .map(|_| {
+                let shared_clone = Arc::clone(&shared_data);
+                let updates = config.increments.get() / 10;
+                spawn_policy::spawn(move || {
+                    let mut max_wait = Duration::ZERO;
+                    for i in 0..updates {
+                        let wait_start = Instant::now();
+                        let mut data = shared_clone.lock().unwrap();
+                        max_wait = max_wait.max(wait_start.elapsed());
+                        data.add_value((i % 100) as i32);
+                    }
+                    max_wait
+                })
+            })
+            .collect();
+        let max_wait = writers.into_iter().map(|writer| writer.join().unwrap()).max().unwrap_or_default();
+        let writers_took = start.elapsed();
+        done.store(true, Ordering::SeqCst);
+        let (passes, inconsistent) = reader.join().unwrap();
+        
+        if use_snapshot {
+            event_log::check_invariant("snapshot sum matches snapshot data", inconsistent == 0);
+        }
+        let label = if use_snapshot { narration!("shared_state.snapshot") } else { narration!("shared_state.under_lock") };
+        say!("{:<12} {:>12} {:>16.2} {:>16.3}", label,
+             passes, writers_took.as_secs_f64() * 1000.0, max_wait.as_secs_f64() * 1000.0);
+    }
+    narrate!("shared_state.snapshot_claim");
+}
+
+// INTERMEDIATE: the statistics pass over one SharedData per sensor (array
+// of structs) vs the same table stored column by column (struct of arrays)
+fn layout_metrics() {
+    narrate!("shared_state.layout_heading", sensors = data_layout::SENSORS);
+    
+    let rows: Vec<SharedData> = data_layout::filled(data_layout::SENSORS, data_layout::VALUES);
+    let columns: SharedColumns = data_layout::filled(data_layout::SENSORS, data_layout::VALUES);
+    let (row_stats, row_time) = data_layout::time_stats(&rows);
+    let (column_stats, column_time) = data_layout::time_stats(&columns);
+    
+    event_log::check_invariant("both layouts compute the same statistics", row_stats == column_stats);
+    event_log::check_invariant("sum matches data in both layouts", rows.consistent() && columns.consistent());
+    narrate!("shared_state.layout_header");
+    narrate!("shared_state.layout_rows", bytes = rows.len() * std::mem::size_of::<SharedData>(),
+             millis = row_time.as_secs_f64() * 1000.0);
+    narrate!("shared_state.layout_columns",
+             bytes = columns.sensors() * (std::mem::size_of::<i32>() + std::mem::size_of::<bool>()),
+             millis = column_time.as_secs_f64() * 1000.0);
+    narrate!("shared_state.layout_results", total = column_stats.total, largest = column_stats.largest,
+             processing = column_stats.processing);
+    narrate!("shared_state.layout_claim", speedup = row_time.as_secs_f64() / column_time.as_secs_f64().max(1e-9));
+}
+
+// ADVANCED: the unsafe counterpart - data and sum behind separate locks.
+// Each field is individually protected, yet a reader can observe them out
+// of step: the invariant spans both fields, so one lock must cover both.
+fn demonstrate_split_locks(config: &EnvConfig) {
+    narrate!("shared_state.split_locks_heading");
+    
+    let data = Arc::new(Mutex::new(Vec::<i64>::new()));
+    let sum = Arc::new(Mutex::new(0i64));
+    let done = Arc::new(AtomicBool::new(false));
+    let (sink, errors) = error_sink::channel();
+    
+    let writer = {
+        let (data, sum, done, sink) = (Arc::clone(&data), Arc::clone(&sum), Arc::clone(&done), sink.clone());
+        let updates = config.increments.get() as i64;
+        spawn_policy::spawn(move || {
+            for i in 1..=updates {
+                sink.recover(data.lock(), "writer", "data").push(i);
+                thread::yield_now();  // Another thread may look right now
+                *sink.recover(sum.lock(), "writer", "sum") += i;
+            }
+            done.store(true, Ordering::SeqCst);
+        })
+    };
+    
+    let reader = {
+        let (data, sum, done) = (Arc::clone(&data), Arc::clone(&sum), Arc::clone(&done));
+        spawn_policy::spawn(move || {
+            let (mut observations, mut torn) = (0, 0);
+            while !done.load(Ordering::SeqCst) {
+                let data_total: i64 = sink.recover(data.lock(), "reader", "data").iter().sum();
+                let recorded_sum = *sink.recover(sum.lock(), "reader", "sum");
+                observations += 1;
+                if data_total != recorded_sum {
+                    torn += 1;
+                }
+                thread::yield_now();
+            }
+            (observations, torn)
+        })
+    };
+    
+    writer.join().unwrap();
+    let (observations, torn) = reader.join().unwrap();
+    errors.summarize();
+    narrate!("shared_state.split_locks_observations", observations);
+    narrate!("shared_state.split_locks_torn", torn);
+    narrate!("shared_state.split_locks_claim");
+}
+
+#[safety_demo(
+    name = "rwlock_safety",
+    module = "Shared State",
+    description = "Readers share an RwLock while a writer waits for exclusive access"
+)]
+pub fn demonstrate_rwlock_safety(config: &EnvConfig) {
+    narrate!("shared_state.rwlock_heading");
+    
+    let shared_data = Arc::new(RwLock::new(vec![1, 2, 3, 4, 5]));
+    let (sink, errors) = error_sink::channel();
+    let show = || narration!("shared_state.rwlock_shared",
+                             data = ?*shared_data.read().unwrap_or_else(|poisoned| poisoned.into_inner()));
+    step::pause(&narration!("shared_state.rwlock_before_start", readers = config.readers.get()), show);
+    let scheduler = config.scheduler();
+    let reader_tasks: Vec<_> = (0..config.readers.get())
+        .map(|i| scheduler.task(&narration!("shared_state.reader", reader = i)))
+        .collect();
+    let writer_task = scheduler.task("writer");
+    let mut handles = vec![];
+    
+    // Multiple reader threads - can run concurrently
+    for (i, reader_task) in reader_tasks.into_iter().enumerate() {
+        let (data_clone, sink) = (Arc::clone(&shared_data), sink.clone());
+        let handle = spawn_policy::spawn(move || reader_task.run(|task| {
+            // SAFE: Multiple readers allowed
+            let data = sink.recover(data_clone.read(), &narration!("shared_state.reader", reader = i), "shared_data");
+            narrate!("shared_state.rwlock_reader_length", reader = i, length = data.len());
+            
+            // Simulate some work
+            task.pause(10);
+            
+            narrate!("shared_state.rwlock_reader_first", reader = i, first = data[0]);
+        }));
+        handles.push(handle);
+    }
+    
+    // Single writer thread - must wait for all readers
+    let data_writer = Arc::clone(&shared_data);
+    let writer_handle = spawn_policy::spawn(move || writer_task.run(|task| {
+        task.pause(5);
+        
+        {
+            // SAFE: Exclusive write access
+            let lock = task.acquire(|| data_writer.try_write(), || data_writer.write());
+            let mut data = sink.recover(lock, "writer", "shared_data");
+            narrate!("shared_state.rwlock_writer_adding");
+            data.push(6);
+        }  // Write lock released here
+        
+        narrate!("shared_state.rwlock_writer_done");
+    }));
+    handles.push(writer_handle);
+    
+    // Wait for all threads
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    errors.summarize();
+    step::pause(&narration!("shared_state.rwlock_joined"), show);
+    describe_schedule(config, &scheduler);
+    
+    let final_data = shared_data.read().unwrap();
+    narrate!("shared_state.rwlock_final_data", data = ?*final_data);
+    
+    if config.level >= Level::Intermediate {
+        rwlock_overlap_metrics(config);
+    }
+    if config.level >= Level::Advanced {
+        rwlock_fairness(config);
+    }
+}
+
+// INTERMEDIATE: measure how many readers actually hold the lock at once
+fn rwlock_overlap_metrics(config: &EnvConfig) {
+    narrate!("shared_state.rwlock_stress_heading", readers = config.readers.get(), rounds = config.stress_rounds.get());
+    
+    let shared_data = Arc::new(RwLock::new(vec![1, 2, 3, 4, 5]));
+    let active = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let (sink, errors) = error_sink::channel();
+    
+    let handles: Vec<_> = (0..config.readers.get())
+        .map(|worker| {
+            let (data, active, peak) = (Arc::clone(&shared_data), Arc::clone(&active), Arc::clone(&peak));
+            let sink = sink.clone();
+            let (rounds, hold) = (config.stress_rounds.get(), config.ticks(2));
+            spawn_policy::spawn(move || {
+                let name = narration!("shared_state.reader", reader = worker);
+                for _ in 0..rounds {
+                    let _guard = sink.recover(data.read(), &name, "shared_data");
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(hold);
+                    active.fetch_sub(1, Ordering::SeqCst);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    errors.summarize();
+    
+    narrate!("shared_state.rwlock_peak_readers", peak = peak.load(Ordering::SeqCst), readers = config.readers.get());
+    narrate!("shared_state.rwlock_stress_claim");
+}
+
+// Whether the platform RwLock lets a writer in while readers keep it busy.
+// Readers take the lock back to back, so a lock that always admits a new
+// reader never has a moment without one; a writer-preferring lock stops
+// admitting readers once a writer waits. For 1, 2, 4, ... readers up to
+// FAIRNESS_READERS, the writer takes STRESS_ROUNDS blocking writes.
+// After FAIRNESS_LIMIT the readers stop, so a starved writer ends the trial
+// instead of the run
+fn rwlock_fairness(config: &EnvConfig) {
+    const HOLD: Duration = Duration::from_micros(500);         // each read
+    const FAIRNESS_LIMIT: Duration = Duration::from_millis(500);  // per reader count
+    
+    let most = config.fairness_readers.get();
+    let writes = config.stress_rounds.get();
+    narrate!("shared_state.starvation_heading", most, writes);
+    narrate!("shared_state.starvation_rule", hold = HOLD.as_micros(), limit = FAIRNESS_LIMIT.as_millis());
+    
+    let mut counts: Vec<usize> = std::iter::successors(Some(1), |count| Some(count * 2)).take_while(|&count| count < most).collect();
+    counts.push(most);
+    narrate!("shared_state.starvation_header");
+    let mut starved = Vec::new();
+    for readers in counts {
+        let Some(trial) = fairness_trial(readers, writes, HOLD, FAIRNESS_LIMIT) else { return };
+        let mean = trial.waits.iter().sum::<Duration>() / trial.waits.len().max(1) as u32;
+        let worst = trial.waits.iter().max().copied().unwrap_or_default();
+        let verdict = if trial.waits.len() < writes {
+            narration!("shared_state.starved")
+        } else {
+            narration!("shared_state.writer_got_in")
+        };
+        let line = format!("{:>8} {:>8} {:>7} {:>12.2?} {:>12.2?}  {}", readers, trial.reads,
+                           format!("{}/{}", trial.waits.len(), writes), mean, worst, verdict);
+        if trial.waits.len() < writes {
+            starved.push(readers);
+            say_as!(Severity::Violation, "{}", line);
+        } else {
+            say!("{}", line);
+        }
+    }
+    if starved.is_empty() {
+        narrate!("shared_state.starvation_none");
+    } else {
+        let counts: Vec<String> = starved.iter().map(ToString::to_string).collect();
+        narrate!("shared_state.starvation_found", counts = counts.join(", "));
+    }
+    narrate!("shared_state.starvation_claim");
+}
+
+struct FairnessTrial {
+    reads: usize,
+    waits: Vec<Duration>,  // one per write that got the lock within the limit
+}
+
+fn fairness_trial(readers: usize, writes: usize, hold: Duration, limit: Duration) -> Option<FairnessTrial> {
+    use std::sync::mpsc;
+    
+    let lock = Arc::new(RwLock::new(0u64));
+    let stop = Arc::new(AtomicBool::new(false));
+    let reads = Arc::new(AtomicUsize::new(0));
+    let mut workers = Vec::new();
+    for reader in 0..readers {
+        let (shared, stopped, count) = (Arc::clone(&lock), Arc::clone(&stop), Arc::clone(&reads));
+        let spawned = spawn_policy::spawn_required(move || {
+            while !stopped.load(Ordering::SeqCst) {
+                let _value = shared.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+                count.fetch_add(1, Ordering::Relaxed);
+                thread::sleep(hold);
+            }
+        });
+        match spawned {
+            Ok(spawned) => workers.push(spawned),
+            Err(error) => {
+                narrate!("shared_state.starvation_skipped_reader", reader, error);
+                stop.store(true, Ordering::SeqCst);
+                return None;
+            }
+        }
+    }
+    // Every reader in its loop before the writer starts
+    while reads.load(Ordering::Relaxed) < readers {
+        thread::yield_now();
+    }
+    let (waited, waits) = mpsc::channel();
+    let writer = {
+        let (lock, stop) = (Arc::clone(&lock), Arc::clone(&stop));
+        spawn_policy::spawn_required(move || {
+            for _ in 0..writes {
+                let start = Instant::now();
+                let mut value = lock.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+                if stop.load(Ordering::SeqCst) {
+                    break;  // in only because the readers left
+                }
+                let _ = waited.send(start.elapsed());
+                *value += 1;
+                drop(value);
+                thread::sleep(hold);
+            }
+        })
+    };
+    let deadline = Instant::now() + limit;
+    let mut recorded = Vec::new();
+    if writer.is_ok() {
+        while recorded.len() < writes {
+            match waits.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(wait) => recorded.push(wait),
+                Err(_) => break,
+            }
+        }
+    }
+    stop.store(true, Ordering::SeqCst);
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    match writer {
+        Ok(writer) => writer.join().unwrap(),
+        Err(error) => {
+            narrate!("shared_state.starvation_skipped_writer", error);
+            return None;
+        }
+    }
+    Some(FairnessTrial { reads: reads.load(Ordering::Relaxed), waits: recorded })
+}
+
+// An expensive shared resource, built on first use by racing threads
+#[safety_demo(
+    name = "lazy_init",
+    module = "Shared State",
+    description = "Racing threads build a shared resource once with OnceLock and LazyLock, where check-then-act builds it per thread",
+    invariants(
+        "OnceLock and LazyLock build the resource exactly once",
+        "every thread is handed the same instance",
+        "a panicking initializer leaves OnceLock empty for the next caller"
+    ),
+    needs_unwind
+)]
+pub fn demonstrate_lazy_init() {
+    narrate!("shared_state.lazy_heading");
+    narrate!("shared_state.lazy_setup", threads = lazy_init::THREADS);
+    
+    narrate!("shared_state.check_then_build_heading");
+    let racy = lazy_init::check_then_act();
+    narrate!("shared_state.once_lock_heading");
+    let once = lazy_init::once_lock();
+    narrate!("shared_state.lazy_lock_heading");
+    let lazy = lazy_init::lazy_lock();
+    
+    narrate!("shared_state.lazy_header");
+    let check_then_build = narration!("shared_state.check_then_build");
+    for (label, race) in [(check_then_build.as_str(), &racy), ("OnceLock", &once), ("LazyLock", &lazy)] {
+        say!("{:<18} {:>6} {:>10}", label, race.builds, race.instances);
+    }
+    demo_report::claim(racy.builds > 1, &narration!("shared_state.lazy_claim"));
+    event_log::check_invariant("OnceLock and LazyLock build the resource exactly once", once.builds == 1 && lazy.builds == 1);
+    event_log::check_invariant("every thread is handed the same instance", once.instances == 1 && lazy.instances == 1);
+    
+    narrate!("shared_state.panicking_build_heading");
+    let (empty, rebuilt) = lazy_init::once_lock_retry();
+    let recovered = event_log::check_invariant("a panicking initializer leaves OnceLock empty for the next caller", empty && rebuilt);
+    demo_report::claim(recovered, &narration!("shared_state.once_lock_retry_claim"));
+    narrate!("shared_state.lazy_lock_poisoned");
+}
+
+// Demonstrate a deadlock: two threads take the same two locks in opposite
+// orders. The lock-order detector reports the cycle whether or not the
+// threads overlap; --allow-hang lets the crossed pair block for real
+#[safety_demo(
+    name = "deadlock",
+    module = "Shared State",
+    description = "Two locks taken in opposite orders deadlock; a lock-order detector reports the cycle without waiting for a hang",
+    invariants(
+        "the detector reports opposite lock orders even when they never overlap",
+        "opposite lock orders deadlock two threads that overlap",
+        "one global lock order reports no inversion"
+    )
+)]
+pub fn demonstrate_deadlock(config: &EnvConfig) {
+    use std::sync::mpsc;
+    use std::sync::Barrier;
+    
+    const GIVE_UP: Duration = Duration::from_millis(100);  // try_lock_for, without --allow-hang
+    const WATCH: Duration = Duration::from_secs(1);        // how long a hang is waited out
+    const ROUNDS: usize = 100;
+    
+    narrate!("shared_state.deadlock_heading");
+    narrate!("shared_state.deadlock_orders");
+    
+    // One after the other: nothing can hang, and the cycle is there all the same
+    narrate!("shared_state.sequential_heading");
+    let order = LockOrder::new();
+    let locks = [TrackedMutex::new("accounts", 0u64, &order), TrackedMutex::new("audit_log", 0u64, &order)];
+    for (first, second) in [(0, 1), (1, 0)] {
+        let mut held = locks[first].lock();
+        *held += 1;
+        *locks[second].lock() += 1;
+    }
+    narrate!("shared_state.sequential_finished");
+    let inversions = order.inversions();
+    for inversion in &inversions {
+        narrate_as!(Severity::Violation, "shared_state.inversion_reported", inversion);
+    }
+    event_log::check_invariant("the detector reports opposite lock orders even when they never overlap",
+                               inversions.len() == 1 && inversions[0].cycle == ["accounts", "audit_log"]);
+    
+    // At the same time: each takes its first lock, then goes for the other's
+    narrate!("shared_state.concurrent_heading");
+    let order = LockOrder::new();
+    let locks = Arc::new([TrackedMutex::new("accounts", 0u64, &order), TrackedMutex::new("audit_log", 0u64, &order)]);
+    let (holding, tried) = (Arc::new(Barrier::new(2)), Arc::new(Barrier::new(2)));
+    let (done, finished) = mpsc::channel();
+    let allow_hang = config.allow_hang;
+    let mut workers = Vec::new();
+    for (who, first, second) in [("transfer", 0, 1), ("auditor", 1, 0)] {
+        let (locks, holding, tried, done) = (Arc::clone(&locks), Arc::clone(&holding), Arc::clone(&tried), done.clone());
+        let spawned = spawn_policy::spawn_required(move || {
+            let _held = locks[first].lock();
+            holding.wait();  // both hold their first lock
+            let got = if allow_hang { Some(locks[second].lock()) } else { locks[second].try_lock_for(GIVE_UP) };
+            let got = got.is_some();
+            tried.wait();  // nobody lets go before both have tried
+            let _ = done.send((who, first, second, got));
+        });
+        match spawned {
+            Ok(spawned) => workers.push(spawned),
+            Err(error) => {
+                // A thread already started waits at the barrier for good
+                narrate!("shared_state.deadlock_skipped", who, error);
+                return;
+            }
+        }
+    }
+    let mut outcomes: Vec<_> = (0..workers.len()).map_while(|_| finished.recv_timeout(WATCH).ok()).collect();
+    outcomes.sort_by_key(|&(_, first, ..)| first);  // whichever gave up first, print in one order
+    let deadlocked = if outcomes.is_empty() {
+        narrate!("shared_state.deadlock_hung", seconds = WATCH.as_secs());
+        narrate!("shared_state.deadlock_hung_why");
+        drop(workers);  // detached: joining would hang this thread too
+        allow_hang
+    } else {
+        for &(who, first, second, got) in &outcomes {
+            if got {
+                narrate!("shared_state.holds_took", who, held = locks[first].name(), wanted = locks[second].name());
+            } else {
+                narrate!("shared_state.holds_gave_up", who, held = locks[first].name(), wanted = locks[second].name());
+            }
+        }
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        narrate!("shared_state.deadlock_timed_out", give_up = GIVE_UP.as_millis());
+        if !allow_hang {
+            narrate!("shared_state.deadlock_allow_hang");
+        }
+        !allow_hang && outcomes.len() == 2 && outcomes.iter().all(|&(.., got)| !got)
+    };
+    for inversion in order.inversions() {
+        narrate_as!(Severity::Violation, "shared_state.inversion_reported", inversion);
+    }
+    event_log::check_invariant("opposite lock orders deadlock two threads that overlap", deadlocked);
+    
+    // The fix: every thread takes accounts before audit_log
+    narrate!("shared_state.global_order_heading");
+    let order = LockOrder::new();
+    let locks = Arc::new([TrackedMutex::new("accounts", 0u64, &order), TrackedMutex::new("audit_log", 0u64, &order)]);
+    let mut workers = Vec::new();
+    for who in ["transfer", "auditor"] {
+        let locks = Arc::clone(&locks);
+        let spawned = spawn_policy::spawn_required(move || {
+            for _ in 0..ROUNDS {
+                let mut accounts = locks[0].lock();
+                let mut audit_log = locks[1].lock();
+                *accounts += 1;
+                *audit_log += 1;
+            }
+        });
+        match spawned {
+            Ok(spawned) => workers.push(spawned),
+            Err(error) => {
+                narrate!("shared_state.deadlock_skipped", who, error);
+                return;
+            }
+        }
+    }
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    let (accounts, audit_log) = (*locks[0].lock(), *locks[1].lock());
+    let inversions = order.inversions();
+    narrate!("shared_state.global_order_result", rounds = ROUNDS, accounts, audit_log, inversions = inversions.len());
+    let consistent = inversions.is_empty() && accounts == 2 * ROUNDS as u64 && audit_log == 2 * ROUNDS as u64;
+    event_log::check_invariant("one global lock order reports no inversion", consistent);
+    demo_report::claim(consistent, &narration!("shared_state.global_order_claim"));
+}
+
+// What thread::park comes down to on this platform
+const PARK: &str = if cfg!(any(target_os = "linux", target_os = "android")) {
+    "a futex wait (FUTEX_WAIT)"
+} else if cfg!(windows) {
+    "WaitOnAddress"
+} else if cfg!(target_vendor = "apple") {
+    "a __ulock_wait"
+} else {
+    "a pthread condition variable"
+};
+
+// Demonstrate the layer the blocking primitives bottom out in: an Event
+// parks threads until it is set, and a Semaphore built on one parks
+// threads while its permits are taken
+#[safety_demo(
+    name = "os_parking",
+    module = "Shared State",
+    description = "An Event parks threads until it is set; a Semaphore built on one caps how many run at once",
+    invariants(
+        "no waiter returns before the event is set",
+        "every waiter returns once the event is set",
+        "never more threads hold a permit than the semaphore has"
+    )
+)]
+pub fn demonstrate_os_parking() {
+    const WAITERS: usize = 4;
+    const PERMITS: usize = 3;
+    const WORKERS: usize = 8;
+    const HOLD: Duration = Duration::from_millis(10);
+    
+    narrate!("shared_state.parking_heading");
+    narrate!("shared_state.parking_mechanism", park = PARK);
+    
+    narrate!("shared_state.event_heading", waiters = WAITERS);
+    let event = Arc::new(Event::new());
+    let returned = Arc::new(AtomicUsize::new(0));
+    let mut waiters = Vec::new();
+    for waiter in 0..WAITERS {
+        let (ready, count) = (Arc::clone(&event), Arc::clone(&returned));
+        match spawn_policy::spawn_required(move || {
+            ready.wait();
+            count.fetch_add(1, Ordering::SeqCst);
+        }) {
+            Ok(spawned) => waiters.push(spawned),
+            Err(error) => {
+                event.set();  // let the ones already waiting go
+                narrate!("shared_state.event_skipped_waiter", waiter, error);
+                return;
+            }
+        }
+    }
+    thread::sleep(HOLD);
+    let early = returned.load(Ordering::SeqCst);
+    narrate!("shared_state.event_before_set", hold = HOLD.as_millis(), early, waiters = WAITERS);
+    event.set();
+    for waiter in waiters {
+        waiter.join().unwrap();
+    }
+    let woken = returned.load(Ordering::SeqCst);
+    narrate!("shared_state.event_after_set", woken, waiters = WAITERS);
+    event_log::check_invariant("no waiter returns before the event is set", early == 0);
+    event_log::check_invariant("every waiter returns once the event is set", woken == WAITERS);
+    let gave_up = !Event::new().wait_timeout(HOLD);
+    if gave_up {
+        narrate!("shared_state.unset_wait_timed_out");
+    } else {
+        narrate!("shared_state.unset_wait_returned");
+    }
+    
+    narrate!("shared_state.semaphore_heading", permits = PERMITS, workers = WORKERS);
+    let semaphore = Arc::new(Semaphore::new(PERMITS));
+    let (busy, peak) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+    let mut workers = Vec::new();
+    for worker in 0..WORKERS {
+        let (semaphore, busy, peak) = (Arc::clone(&semaphore), Arc::clone(&busy), Arc::clone(&peak));
+        match spawn_policy::spawn_required(move || {
+            let _permit = semaphore.acquire();
+            peak.fetch_max(busy.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+            thread::sleep(HOLD);
+            busy.fetch_sub(1, Ordering::SeqCst);
+        }) {
+            Ok(spawned) => workers.push(spawned),
+            Err(error) => {
+                narrate!("shared_state.semaphore_skipped_worker", worker, error);
+                return;
+            }
+        }
+    }
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    let peak = peak.load(Ordering::SeqCst);
+    narrate!("shared_state.semaphore_peak", peak, permits = PERMITS, available = semaphore.available());
+    event_log::check_invariant("never more threads hold a permit than the semaphore has", peak <= PERMITS);
+    demo_report::claim(peak == PERMITS && semaphore.available() == PERMITS, &narration!("shared_state.semaphore_claim"));
+}
@@ -0,0 +1,239 @@
+/*!
+ * Type System Sections
+ *
+ * What the compiler checks before anything runs: Send and Sync, scoped
+ * threads borrowing from their parent, phases as types, and the data
+ * races that do not compile.
+ */
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use resilient_core::{parallel_map, SharedData};
+use safety_demo_macros::safety_demo;
+
+use crate::{auto_traits, error_sink, event_log, spawn_policy};
+use crate::env_config::EnvConfig;
+use crate::phase::{Pipeline, WorkerPool};
+use crate::task_scope::{task_scope, TaskError};
+
+#[safety_demo(
+    name = "send_sync_traits",
+    module = "Type System",
+    description = "Send and Sync decide at compile time what may move to or be shared between threads"
+)]
+pub fn demonstrate_send_sync_traits() {
+    narrate!("type_system.send_sync_heading");
+    
+    // Types that implement Send can be moved between threads
+    // Types that implement Sync can be shared between threads
+    // One field decides for a whole struct; every row below is asserted
+    // while the program compiles (see auto_traits.rs)
+    narrate!("type_system.auto_traits_header");
+    let mark = |holds: bool| if holds { narration!("type_system.yes") } else { narration!("type_system.no") };
+    for row in auto_traits::TABLE {
+        say!("{:<44} {:<5} {}", row.ty, mark(row.send), mark(row.sync));
+        say!("    {}", row.why);
+    }
+    
+    // Moving an RcField into thread::spawn would be a COMPILE ERROR:
+    // let handle = thread::spawn(move || rc_field.0.clone());  // Error: Rc<i32> cannot be sent between threads safely
+    
+    // Safe alternatives
+    let thread_safe_data = Arc::new(42);
+    let data_clone = Arc::clone(&thread_safe_data);
+    
+    let handle = spawn_policy::spawn(move || {
+        narrate!("type_system.thread_safe_data", data = data_clone);  // SAFE: Arc implements Send+Sync
+    });
+    
+    handle.join().unwrap();
+    narrate!("type_system.original_data", data = thread_safe_data);
+}
+
+// Collatz steps for every value in a SharedData, mapped by parallel_map:
+// its threads borrow the values and the step limit straight from this
+// stack frame, with no Arc and no copy
+fn parallel_shared_data() {
+    const VALUES: i32 = 50_000;  // the running sum stays within an i32
+    let mut shared = SharedData::new();
+    for value in 1..=VALUES {
+        shared.add_value(value);
+    }
+    let limit = 1_000;  // borrowed by every thread
+    let steps = |&value: &i32| {
+        let (mut n, mut steps) = (value as u64, 0);
+        while n != 1 && steps < limit {
+            n = if n.is_multiple_of(2) { n / 2 } else { 3 * n + 1 };
+            steps += 1;
+        }
+        steps
+    };
+    
+    let start = Instant::now();
+    let sequential: Vec<u32> = shared.values().iter().map(steps).collect();
+    let sequential_time = start.elapsed();
+    let start = Instant::now();
+    let parallel = parallel_map(shared.values(), steps);
+    let parallel_time = start.elapsed();
+    
+    let cpus = thread::available_parallelism().map_or(1, |cpus| cpus.get());
+    narrate!("type_system.parallel_map", values = shared.len(), cpus);
+    if let Some((longest, value)) = parallel.iter().zip(shared.values()).max_by_key(|&(steps, _)| *steps) {
+        narrate!("type_system.collatz_longest", longest, start = value);
+    }
+    narrate!("type_system.parallel_map_times", sequential = ?sequential_time, parallel = ?parallel_time);
+    event_log::check_invariant("parallel_map returns the sequential map's results, in order", parallel == sequential);
+}
+
+#[safety_demo(
+    name = "scoped_threads",
+    module = "Type System",
+    description = "Scoped threads borrow local data, and the scope reports every task that failed",
+    invariants(
+        "parallel_map returns the sequential map's results, in order",
+        "scope fails exactly when a task fails"
+    )
+)]
+pub fn demonstrate_scoped_threads(config: &EnvConfig) {
+    narrate!("type_system.scoped_heading");
+    
+    let mut data = vec![1, 2, 3, 4, 5];
+    
+    // Scoped threads can borrow local data safely
+    thread::scope(|s| {
+        // Spawn thread that reads data
+        let reader = spawn_policy::spawn_scoped(s, || {
+            narrate!("type_system.scoped_reader", data = ?data);  // SAFE: Borrow guaranteed valid
+        });
+        
+        // Spawn thread that modifies data (requires mutable borrow)
+        // This would cause COMPILE ERROR if both threads tried to access mutably:
+        // let writer = s.spawn(|| {
+        //     data.push(6);  // Error: cannot borrow as mutable
+        // });
+        
+        reader.join().unwrap();
+        // All scoped threads finish before scope ends
+    });
+    
+    // Now we can safely modify data
+    data.push(6);
+    narrate!("type_system.scoped_after", data = ?data);
+    
+    parallel_shared_data();
+
+    // A fallible parallel computation: each task sums one borrowed chunk,
+    // and the first bad value cancels the tasks still running
+    let valid = ["1 2 3 4", "5 6 7 8", "9 10 11 12"];
+    let invalid = ["1 2 3 4 5 6 7 8", "9 x10 11", "12 13 14 15 16 17 18 19"];
+    let (all_valid, one_bad) = (narration!("type_system.all_valid"), narration!("type_system.one_bad_value"));
+    for (label, chunks, should_fail) in [(all_valid, valid, false), (one_bad, invalid, true)] {
+        let result = task_scope(|scope| {
+            for (index, chunk) in chunks.iter().enumerate() {
+                scope.spawn(&narration!("type_system.chunk", index), move |token| {
+                    let mut sum = 0;
+                    for word in chunk.split_whitespace() {
+                        token.check()?;  // Stop early once a sibling failed
+                        thread::sleep(config.ticks(1));
+                        sum += word.parse::<i64>().map_err(|_| TaskError::Failed(narration!("type_system.bad_value", word)))?;
+                    }
+                    Ok(sum)
+                });
+            }
+        });
+        match &result {
+            Ok(sums) => narrate!("type_system.task_scope_sums", label, sums = ?sums, total = sums.iter().sum::<i64>()),
+            Err(error) => narrate!("type_system.task_scope_failed", label, error),
+        }
+        event_log::check_invariant("scope fails exactly when a task fails", result.is_err() == should_fail);
+    }
+}
+
+#[safety_demo(
+    name = "typed_phases",
+    module = "Type System",
+    description = "A worker pool whose phases are types, so submitting to a drained pool does not compile",
+    invariants(
+        "every submitted job ran once"
+    )
+)]
+pub fn demonstrate_typed_phases(config: &EnvConfig) {
+    narrate!("type_system.typestate_heading");
+
+    let pool = WorkerPool::new().workers(config.threads.get());
+    narrate!("type_system.pool_started", phase = pool.phase(), threads = config.threads.get());
+    let pool = pool.start();
+    narrate!("type_system.pool_draining", phase = pool.phase());
+    for job in 1..=10u64 {
+        pool.submit(move || job * job);
+    }
+
+    let pool = pool.drain();
+    narrate!("type_system.pool_pending", phase = pool.phase(), pending = pool.pending());
+
+    // These would cause COMPILE ERRORS if uncommented (see tests/compile_fail/):
+    // pool.submit(|| 11);  // Error: no method named `submit` found for `WorkerPool<Draining, u64>`
+    // let pool = pool.start();  // Error: no method named `start` - a pool only starts once
+
+    let pool = pool.finish();
+    let mut squares = pool.results().to_vec();
+    squares.sort_unstable();
+    narrate!("type_system.pool_finished", phase = pool.phase(), squares = ?squares);
+    event_log::check_invariant("every submitted job ran once", squares == (1..=10u64).map(|job| job * job).collect::<Vec<_>>());
+
+    let pipeline = Pipeline::new()
+        .stage(|text: String| text.trim().to_string())
+        .stage(|text| text.to_uppercase())
+        .start();
+    for word in [" send ", "sync ", " scope"] {
+        pipeline.send(word.to_string());
+    }
+    // pipeline.stage(|text| text);  // Error: stages can only be added in Setup
+    let pipeline = pipeline.drain().finish();
+    narrate!("type_system.pipeline_finished", phase = pipeline.phase(), outputs = ?pipeline.outputs());
+}
+
+// Demonstrate that data races are impossible at compile time
+#[safety_demo(
+    name = "compile_time_safety",
+    module = "Type System",
+    description = "Code that could race is rejected by the compiler"
+)]
+pub fn demonstrate_compile_time_safety() {
+    narrate!("type_system.race_prevention_heading");
+    
+    let data = vec![1, 2, 3];
+    
+    // These would cause COMPILE ERRORS if uncommented:
+    
+    // Example 1: Cannot share mutable reference
+    // let handle = thread::spawn(|| {
+    //     data.push(4);  // Error: captured variable cannot be sent between threads safely
+    // });
+    
+    // Example 2: Cannot have multiple mutable references
+    // let ref1 = &mut data;
+    // let ref2 = &mut data;  // Error: cannot borrow as mutable more than once
+    
+    // Example 3: Cannot mix mutable and immutable references
+    // let immutable_ref = &data;
+    // let mutable_ref = &mut data;  // Error: cannot borrow as mutable
+    
+    // Safe alternative: Use Arc<Mutex<T>>
+    let safe_data = Arc::new(Mutex::new(data));
+    let safe_data_clone = Arc::clone(&safe_data);
+    let (sink, errors) = error_sink::channel();
+    
+    let handle = spawn_policy::spawn(move || {
+        let mut guard = sink.recover(safe_data_clone.lock(), "worker", "safe_data");
+        guard.push(4);  // SAFE: Exclusive access guaranteed
+    });
+    
+    handle.join().unwrap();
+    errors.summarize();
+    
+    let final_data = safe_data.lock().unwrap();
+    narrate!("type_system.race_prevention_result", data = ?*final_data);
+}
@@ -0,0 +1,183 @@
+/*!
+ * Versioned Data Sections
+ *
+ * Readers that never block writers: a store that keeps old versions,
+ * and a list whose versions share their common tail.
+ */
+
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+
+use safety_demo_macros::safety_demo;
+
+use crate::{event_log, spawn_policy, static_check};
+use crate::env_config::EnvConfig;
+use crate::persistent_list::PersistentList;
+use crate::versioned_store::VersionedStore;
+
+// Demonstrate MVCC: readers pin consistent versions while writers commit new ones
+#[safety_demo(
+    name = "versioned_store",
+    module = "Versioned Data",
+    description = "Readers pin consistent versions while writers commit new ones (MVCC)",
+    invariants(
+        "snapshot totals are conserved",
+        "unpinned versions are collected"
+    )
+)]
+pub fn demonstrate_versioned_store(config: &EnvConfig) {
+    narrate!("versioned_data.mvcc_heading");
+    
+    const ACCOUNTS: usize = static_check::nonzero(4);
+    const TOTAL: i64 = 400;
+    const _: () = assert!(TOTAL % ACCOUNTS as i64 == 0, "the total must split evenly, or no snapshot adds up");
+    let store = Arc::new(VersionedStore::new(vec![TOTAL / ACCOUNTS as i64; ACCOUNTS]));
+    narrate!("versioned_data.mvcc_setup", accounts = ACCOUNTS, total = TOTAL);
+    
+    // Writers: each transfer builds a new version from the latest one
+    let writers: Vec<_> = (0..config.threads.get())
+        .map(|worker| {
+            let (store, transfers) = (Arc::clone(&store), config.writes.get());
+            spawn_policy::spawn(move || {
+                let mut conflicts = 0;
+                for i in 0..transfers {
+                    let (from, to) = ((worker + i) % ACCOUNTS, (worker + i + 1) % ACCOUNTS);
+                    let amount = (i % 10) as i64 + 1;
+                    let (_, rejected) = store.update(|balances| {
+                        let mut next = balances.clone();
+                        next[from] -= amount;
+                        next[to] += amount;
+                        next
+                    });
+                    conflicts += rejected;
+                }
+                conflicts
+            })
+        })
+        .collect();
+    
+    // Readers: a pinned snapshot stays consistent while commits go on
+    let readers: Vec<_> = (0..config.readers.get())
+        .map(|_| {
+            let (store, rounds, pause) = (Arc::clone(&store), config.reads.get(), config.ticks(1));
+            spawn_policy::spawn(move || {
+                let (mut audits, mut unbalanced, mut peak_live) = (0, 0, 0);
+                for _ in 0..rounds {
+                    let snapshot = store.pin();
+                    let before: i64 = snapshot.iter().sum();
+                    thread::sleep(pause);  // Writers commit meanwhile
+                    peak_live = peak_live.max(store.live_versions().len());
+                    let after: i64 = snapshot.iter().sum();
+                    audits += 1;
+                    if before != TOTAL || after != TOTAL {
+                        unbalanced += 1;
+                    }
+                }
+                (audits, unbalanced, peak_live)
+            })
+        })
+        .collect();
+    
+    let conflicts: usize = writers.into_iter().map(|writer| writer.join().unwrap()).sum();
+    let (mut audits, mut unbalanced, mut peak_live) = (0, 0, 0);
+    for reader in readers {
+        let (reader_audits, reader_unbalanced, reader_peak) = reader.join().unwrap();
+        audits += reader_audits;
+        unbalanced += reader_unbalanced;
+        peak_live = peak_live.max(reader_peak);
+    }
+    
+    narrate!("versioned_data.mvcc_commits", versions = store.latest_version(), conflicts);
+    narrate!("versioned_data.mvcc_audits", audits, unbalanced, peak_live);
+    event_log::check_invariant("snapshot totals are conserved", unbalanced == 0);
+    
+    // A commit built on an old version is refused rather than overwriting
+    let stale = store.pin();
+    store.update(|balances| balances.clone());
+    match store.try_commit(stale.version(), stale.to_vec()) {
+        Ok(version) => narrate!("versioned_data.stale_commit_accepted", version),
+        Err(conflict) => narrate!("versioned_data.stale_commit_rejected", base = conflict.base, latest = conflict.latest),
+    }
+    drop(stale);
+    
+    let live = store.live_versions();
+    narrate!("versioned_data.mvcc_unpinned", live = ?live);
+    event_log::check_invariant("unpinned versions are collected", live == [store.latest_version()]);
+    narrate!("versioned_data.mvcc_final_balances", balances = ?*store.pin());
+}
+
+// Demonstrate lock-free sharing through structural sharing: every thread
+// extends the same base list without copying or locking it
+#[safety_demo(
+    name = "persistent_list",
+    module = "Versioned Data",
+    description = "Threads extend one base list without copying or locking it",
+    invariants(
+        "versions share the base list"
+    )
+)]
+pub fn demonstrate_persistent_list(config: &EnvConfig) {
+    narrate!("versioned_data.persistent_heading");
+    
+    let (base_len, own) = (config.increments.get(), config.writes.get());
+    let mut base = PersistentList::new();
+    for i in 0..base_len {
+        base = base.push_front(i as i64);
+    }
+    let base_sum: i64 = base.iter().sum();
+    
+    // Each thread gets an O(1) handle and builds its own version on top
+    let start = Instant::now();
+    let handles: Vec<_> = (0..config.threads.get())
+        .map(|worker| {
+            let mut version = base.clone();
+            spawn_policy::spawn(move || {
+                for i in 0..own {
+                    version = version.push_front((worker * 1000 + i) as i64);
+                }
+                version
+            })
+        })
+        .collect();
+    let versions: Vec<PersistentList<i64>> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+    let shared_took = start.elapsed();
+    
+    let mut all_share = true;
+    for (worker, version) in versions.iter().enumerate() {
+        let tail_sum: i64 = version.iter().skip(own).sum();
+        all_share &= version.len() == base_len + own && version.shares_suffix(&base) && tail_sum == base_sum;
+        if worker < 3 {
+            narrate!("versioned_data.persistent_thread", worker, length = version.len(), front = ?version.front(),
+                     shares = version.shares_suffix(&base));
+        }
+    }
+    narrate!("versioned_data.persistent_base", length = base.len(), front = ?base.front());
+    event_log::check_invariant("versions share the base list",
+                               all_share && base.len() == base_len && (base_len == 0) == base.is_empty());
+    
+    // The same job with a private copy per thread
+    let base_vec: Arc<Vec<i64>> = Arc::new(base.iter().copied().collect());
+    let start = Instant::now();
+    let handles: Vec<_> = (0..config.threads.get())
+        .map(|worker| {
+            let base_vec = Arc::clone(&base_vec);
+            spawn_policy::spawn(move || {
+                let mut copy = (*base_vec).clone();
+                for i in 0..own {
+                    copy.push((worker * 1000 + i) as i64);
+                }
+                copy.len()
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    let copied_took = start.elapsed();
+    
+    narrate!("versioned_data.persistent_elements", shared = base_len + config.threads.get() * own,
+             copied = config.threads.get() * (base_len + own));
+    narrate!("versioned_data.persistent_times", shared = shared_took.as_secs_f64() * 1000.0,
+             copied = copied_took.as_secs_f64() * 1000.0);
+}
@@ -0,0 +1,1533 @@
+/*!
+ * Platform Self-Test
+ *
+ * Probes the host (core count, atomic widths, timer resolution, page
+ * size), runs a quick sanity check of each subsystem, and reports which
+ * demonstrations are meaningful on this machine. A single-core VM will
+ * still pass the counter demo, but it never exercises real contention -
+ * students should know that before drawing conclusions from the output.
+ */
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::rc::Rc;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tokio::runtime;
+use tokio::task::{self, LocalSet};
+
+use resilient_core::{SafeCounter, Semaphore, SharedData, ThreadPool};
+use crate::aimd::{self, Aimd};
+use crate::alloc_track;
+use crate::async_demo;
+use crate::async_lock::{self, AwaitCheck, WatchedMutex};
+use crate::atomic64::AtomicU64;
+use crate::bounded_buffer::BoundedBuffer;
+use crate::circuit_breaker::{BreakerState, CircuitBreaker};
+use crate::clock::MockClock;
+use crate::cost_model::{self, Complexity};
+use crate::crash_only;
+use crate::crash_report;
+use crate::data_layout::{self, SharedColumns, SharedTable, TableStats};
+use crate::delivery::{self, Guarantee};
+use crate::env_config::{self, EnvConfig};
+use crate::event_log::{Event, Record};
+use crate::exhaustion::{self, ExhaustionError};
+use crate::extensions::{
+    BackendViolation, ChannelBackend, ChannelBackendExt, ChecksumExt, Fletcher32, LimiterExt, Sequenced, TokenBucket,
+};
+use crate::fault::{self, InjectedPanic};
+use crate::hedge;
+use crate::interrupts::{CsCell, Interrupts};
+use crate::lock_order::{Inversion, LockOrder, TrackedMutex};
+use crate::lockfree::{self, ConcurrentQueue, LockFreeQueue, LockedQueue};
+use crate::network_sim::{self, LinkFaults, NetworkSim};
+use crate::orderings::{self, Model};
+use crate::phase::{Pipeline, WorkerPool};
+use crate::platform;
+use crate::progress::{self, Goal, Progress};
+use crate::scheduler::Scheduler;
+use crate::sim_cpu::{self, SimCpu};
+use crate::snapshot_diff::{self, Change};
+use crate::soak;
+use crate::spawn_policy;
+use crate::speculative::{self, Alternative, RaceError, Staged};
+use crate::spsc::{self, Steps};
+use crate::task_scope::{CancellationToken, TaskError};
+use crate::term::{self, Severity};
+use crate::trace;
+use crate::vector_clock::{Causality, VectorClock};
+use crate::versioned_store::VersionedStore;
+use crate::work_stealing::{StealPolicy, WorkStealing, WorkerStats};
+
+use crate::demo_report::{Outcome, Verdict};
+use resilient_core::invariants::Invariants;
+use crate::sandbox::{self, Exit, Probe};
+use crate::verify;
+use crate::sections;
+use crate::sections::type_system::TYPED_PHASES;
+use crate::setting_flags;
+
+struct HostProbe {
+    cores: usize,
+    atomic_widths: Vec<&'static str>,
+    clock_resolution: Duration,  // smallest observable Instant step
+    sleep_overshoot: Duration,   // how late a 1ms sleep wakes up
+    page_size: Option<usize>,
+}
+
+fn probe_host() -> HostProbe {
+    let cores = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+
+    let mut atomic_widths = Vec::new();
+    if cfg!(target_has_atomic = "8") {
+        atomic_widths.push("8");
+    }
+    if cfg!(target_has_atomic = "16") {
+        atomic_widths.push("16");
+    }
+    if cfg!(target_has_atomic = "32") {
+        atomic_widths.push("32");
+    }
+    if cfg!(target_has_atomic = "64") {
+        atomic_widths.push("64");
+    }
+    if cfg!(target_has_atomic = "ptr") {
+        atomic_widths.push("ptr");
+    }
+
+    // Smallest nonzero difference between two consecutive clock reads
+    let mut clock_resolution = Duration::MAX;
+    for _ in 0..1000 {
+        let start = Instant::now();
+        let mut now = Instant::now();
+        while now == start {
+            now = Instant::now();
+        }
+        clock_resolution = clock_resolution.min(now - start);
+    }
+
+    // Median overshoot of a short sleep
+    let mut overshoots: Vec<Duration> = (0..11)
+        .map(|_| {
+            let start = Instant::now();
+            thread::sleep(Duration::from_millis(1));
+            start.elapsed().saturating_sub(Duration::from_millis(1))
+        })
+        .collect();
+    overshoots.sort();
+
+    HostProbe {
+        cores,
+        atomic_widths,
+        clock_resolution,
+        sleep_overshoot: overshoots[overshoots.len() / 2],
+        page_size: page_size(),
+    }
+}
+
+fn page_size() -> Option<usize> {
+    let output = Command::new("getconf").arg("PAGESIZE").output().ok()?;
+    String::from_utf8(output.stdout).ok()?.trim().parse().ok()
+}
+
+// Each check returns Err(reason) when the subsystem misbehaves
+type SubsystemCheck = fn() -> Result<(), String>;
+
+fn check_env_config() -> Result<(), String> {
+    let vars = vec![("SAFETY_DEMO_THREADS".to_string(), "4".to_string())];
+    let config = EnvConfig::from_vars(vars).map_err(|e| e.to_string())?;
+    if config.threads.get() != 4 {
+        return Err(format!("expected 4 threads, parsed {}", config.threads.get()));
+    }
+
+    // settings() must name every key, and loading it back changes nothing
+    let settings = config.settings();
+    if settings.iter().map(|(key, _)| *key).ne(env_config::KNOWN_KEYS) {
+        return Err("settings() and KNOWN_KEYS list different keys".to_string());
+    }
+    let reloaded = EnvConfig::from_vars(settings.into_iter().map(|(key, value)| (format!("{}{}", env_config::ENV_PREFIX, key), value)))
+        .map_err(|e| e.to_string())?;
+    if reloaded != config {
+        return Err("loading settings() back changed the config".to_string());
+    }
+
+    // Flags before the command override the environment, the command's
+    // own flags are left to it, and setting_args() reproduces them
+    let mut flagged = config.clone();
+    let mut args: Vec<String> = ["--iterations", "50", "--sleep-ms", "0", "stress", "--iterations", "3"].map(str::to_string).to_vec();
+    setting_flags::take_global_flags(&mut args, &mut flagged)?;
+    if args != ["stress", "--iterations", "3"] || flagged.threads.get() != 4 || flagged.increments.get() != 50 || flagged.tick.get() != 0 {
+        return Err(format!("global flags misapplied: left {:?}, got {:?}", args, flagged));
+    }
+    let mut reproduced = EnvConfig::default();
+    setting_flags::take_global_flags(&mut setting_flags::setting_args(&flagged), &mut reproduced)?;
+    if (reproduced.threads, reproduced.increments, reproduced.tick) != (flagged.threads, flagged.increments, flagged.tick) {
+        return Err("setting_args() did not reproduce the flags".to_string());
+    }
+    match setting_flags::take_global_flags(&mut vec!["--threads".to_string(), "0".to_string()], &mut flagged) {
+        Err(error) if error.contains("--threads") => {}
+        other => return Err(format!("out-of-range --threads not reported by flag: {:?}", other)),
+    }
+
+    let bad = vec![("SAFETY_DEMO_THREADS".to_string(), "many".to_string())];
+    match EnvConfig::from_vars(bad) {
+        Ok(_) => Err("accepted a non-numeric thread count".to_string()),
+        Err(_) => Ok(()),
+    }
+}
+
+fn check_event_log() -> Result<(), String> {
+    let record = Record {
+        run: 1,
+        seq: 0,
+        elapsed_ms: 0,
+        thread: "selftest".to_string(),
+        demo: "selftest".to_string(),
+        event: Event::InvariantChecked {
+            invariant: "round trip".to_string(),
+            passed: true,
+        },
+    };
+    let line = serde_json::to_string(&record).map_err(|e| e.to_string())?;
+    let parsed: Record = serde_json::from_str(&line).map_err(|e| e.to_string())?;
+    if parsed.event != record.event {
+        return Err(format!("round trip changed the event: {}", line));
+    }
+    Ok(())
+}
+
+// Every registered demo describes itself under a unique name, the JSON
+// listing has them all, and running one through the registry reports
+// its outcome in the exit code
+fn check_demo_registry() -> Result<(), String> {
+    let demos = sections::registry();
+    let names: BTreeMap<&str, &str> = demos.iter().map(|demo| (demo.name(), demo.description())).collect();
+    if names.len() != demos.len() {
+        return Err("two registered demos share a name".to_string());
+    }
+    if let Some((name, _)) = names.iter().find(|(_, description)| description.is_empty()) {
+        return Err(format!("{} has no description", name));
+    }
+
+    let exe = std::env::current_exe().map_err(|error| error.to_string())?;
+    let listing = Command::new(&exe).args(["demos", "--json"]).output().map_err(|error| error.to_string())?;
+    let listed: Vec<serde_json::Value> = serde_json::from_slice(&listing.stdout).map_err(|error| error.to_string())?;
+    if listed.len() != demos.len() {
+        return Err(format!("--json listed {} of {} demos", listed.len(), demos.len()));
+    }
+    let run = Command::new(&exe)
+        .args(["demos", "run", "counter_safety"])
+        .env("SAFETY_DEMO_TICK_MS", "0")
+        .env_remove("SAFETY_DEMO_EVENT_LOG")
+        .output()
+        .map_err(|error| error.to_string())?;
+    if !run.status.success() || !String::from_utf8_lossy(&run.stdout).contains("counter_safety: passed") {
+        return Err(format!("demos run counter_safety: exit {}", run.status));
+    }
+    Ok(())
+}
+
+fn check_counter() -> Result<(), String> {
+    let counter = Arc::new(SafeCounter::new());
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let counter = Arc::clone(&counter);
+            thread::spawn(move || {
+                for _ in 0..1000 {
+                    counter.increment();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().map_err(|_| "counter thread panicked".to_string())?;
+    }
+    match counter.get_count() {
+        4000 => Ok(()),
+        other => Err(format!("expected 4000 increments, counted {}", other)),
+    }
+}
+
+fn check_shared_data() -> Result<(), String> {
+    let shared = Arc::new(Mutex::new(SharedData::new()));
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || {
+                for value in 0..100 {
+                    shared.lock().unwrap().add_value(i * 100 + value);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().map_err(|_| "writer thread panicked".to_string())?;
+    }
+    let data = shared.lock().map_err(|_| "mutex poisoned".to_string())?;
+    data.check_invariants().map_err(|violation| violation.to_string())?;
+    if data.len() != 400 {
+        return Err(format!("expected 400 values, found {}", data.len()));
+    }
+
+    // The derived check must notice a sum that drifted from its data
+    let drifted = SharedData::from_parts(data.values().to_vec(), data.sum() + 1, data.is_processing());
+    match drifted.check_invariants() {
+        Err(violation) if violation.broken == ["sum matches data"] => Ok(()),
+        other => Err(format!("drifted sum not caught: {:?}", other)),
+    }
+}
+
+// diff() reports each added, removed and changed entry, in entry order
+fn check_snapshot_diff() -> Result<(), String> {
+    let before: BTreeMap<&str, i32> = [("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+    let after: BTreeMap<&str, i32> = [("b", 2), ("c", 4), ("d", 5)].into_iter().collect();
+    let changes = snapshot_diff::diff(&before, &after).to_string();
+    if changes != "- a = 1; ~ c: 3 -> 4; + d = 5" {
+        return Err(format!("map diff: {}", changes));
+    }
+
+    // A sum that missed an update shows as exactly that change
+    let mut data = SharedData::new();
+    for value in 0..12 {
+        data.add_value(value);
+    }
+    let drifted = SharedData::from_parts(data.values().to_vec(), data.sum() - 11, data.is_processing());
+    let changes = snapshot_diff::diff(&snapshot_diff::implied(&drifted), &drifted);
+    if changes.changes != [Change::Changed { key: "sum".to_string(), before: "66".to_string(), after: "55".to_string() }] {
+        return Err(format!("drifted sum explained as: {}", changes));
+    }
+
+    // Values are ordered by index, and a long diff is cut short
+    let shorter = SharedData::from_parts(data.values()[..2].to_vec(), 1, false);
+    let changes = snapshot_diff::diff(&data, &shorter).to_string();
+    if !changes.starts_with("- data[2] = 2; - data[3] = 3;") || !changes.ends_with("; ... and 4 more") {
+        return Err(format!("truncated diff: {}", changes));
+    }
+    if !snapshot_diff::diff(&data, &data).is_empty() {
+        return Err("a snapshot differs from itself".to_string());
+    }
+    Ok(())
+}
+
+// Both layouts of a table of SharedData give the same statistics as the
+// values put into them
+fn check_data_layout() -> Result<(), String> {
+    let (sensors, values) = (1000, 5);
+    let rows: Vec<SharedData> = data_layout::filled(sensors, values);
+    let columns: SharedColumns = data_layout::filled(sensors, values);
+    if !rows.consistent() || !columns.consistent() {
+        return Err("a sum drifted from its data".to_string());
+    }
+    let sums: Vec<i32> = (0..sensors)
+        .map(|sensor| (0..values).map(|round| ((sensor * 7 + round * 13) % 100) as i32).sum())
+        .collect();
+    let expected = TableStats {
+        total: sums.iter().map(|&sum| sum as i64).sum(),
+        largest: sums.iter().copied().max().unwrap_or(0),
+        processing: sensors,  // an odd number of values each
+    };
+    for (layout, stats) in [("array of structs", rows.stats()), ("struct of arrays", columns.stats())] {
+        if stats != expected {
+            return Err(format!("{}: expected {:?}, got {:?}", layout, expected, stats));
+        }
+    }
+    Ok(())
+}
+
+fn check_channel() -> Result<(), String> {
+    let (sender, receiver) = mpsc::channel();
+    let producer = thread::spawn(move || {
+        for i in 0..100u64 {
+            sender.send(i).unwrap();
+        }
+    });
+    let received: Vec<u64> = receiver.iter().collect();
+    producer.join().map_err(|_| "producer panicked".to_string())?;
+    if received != (0..100).collect::<Vec<_>>() {
+        return Err("messages lost or reordered".to_string());
+    }
+    Ok(())
+}
+
+// AtomicU64 keeps all 64 bits on every target; tests/atomic64.rs
+// checks LockedU64 against the native type operation by operation
+fn check_atomics() -> Result<(), String> {
+    let value = AtomicU64::new(0);
+    value.fetch_add(u32::MAX as u64 + 1, Ordering::SeqCst);
+    if value.load(Ordering::SeqCst) != 1 << 32 {
+        return Err("64-bit atomic arithmetic lost high bits".to_string());
+    }
+    Ok(())
+}
+
+// The two litmus tests under the orderings that promise something:
+// Release/Acquire passes a message with its data, and SeqCst stores are
+// never both missed. Relaxed is run too, for its results' shape only -
+// what it shows depends on the CPU
+fn check_orderings() -> Result<(), String> {
+    for model in Model::ALL {
+        let passing = orderings::message_passing(model, 20_000).map_err(|error| error.to_string())?;
+        if passing.samples == 0 || (model != Model::Relaxed && passing.stale > 0) {
+            return Err(format!("{} message passing: {} stale of {} reads", model, passing.stale, passing.samples));
+        }
+        let buffering = orderings::store_buffering(model, 2_000).map_err(|error| error.to_string())?;
+        if buffering.rounds != 2_000 || (model == Model::SeqCst && buffering.both_missed > 0) {
+            return Err(format!("{} store buffering: both stores missed in {} of {} rounds",
+                               model, buffering.both_missed, buffering.rounds));
+        }
+    }
+    Ok(())
+}
+
+// AwaitCheck flags a task that suspends holding a guard, and only that
+// task: not one that locks between awaits, nor one polled while another
+// task's guard is held. Each fix then keeps every update
+fn check_await_lock() -> Result<(), String> {
+    let runtime = runtime::Builder::new_current_thread().build().map_err(|error| error.to_string())?;
+    let check = Arc::new(AwaitCheck::default());
+    let (first, second) = (Rc::new(WatchedMutex::new("first", 0)), Rc::new(WatchedMutex::new("second", 0)));
+    LocalSet::new().block_on(&runtime, {
+        let check = Arc::clone(&check);
+        async move {
+            let holder = {
+                let first = Rc::clone(&first);
+                task::spawn_local(check.watch("holder", async move {
+                    let mut guard = first.lock();
+                    task::yield_now().await;
+                    *guard += 1;
+                }))
+            };
+            let between = task::spawn_local(check.watch("between", async move {
+                for _ in 0..3 {
+                    *second.lock() += 1;
+                    task::yield_now().await;
+                }
+            }));
+            let _ = (holder.await, between.await);
+        }
+    });
+    let findings = check.findings();
+    if findings != ["task `holder` awaited while holding `first`"] {
+        return Err(format!("findings {:?}", findings));
+    }
+    for fix in async_lock::Fix::ALL {
+        let run = async_lock::run(fix, 3, Duration::from_millis(2), Some(Duration::from_millis(10))).map_err(|error| error.to_string())?;
+        if run.count != run.expected || (fix == async_lock::Fix::None) == run.findings.is_empty() {
+            return Err(format!("{}: counter {} of {} expected, findings {:?}", fix, run.count, run.expected, run.findings));
+        }
+    }
+    Ok(())
+}
+
+// The bounded buffer is FIFO, a push to a full buffer sleeps until a pop
+// and a pop from an empty one until a push, and close() wakes every
+// sleeper: producers get their value back, consumers drain then get None
+fn check_bounded_buffer() -> Result<(), String> {
+    const PATIENCE: Duration = Duration::from_secs(5);
+    let buffer = Arc::new(BoundedBuffer::new(2));
+    for lap in 0..3 {
+        for value in [lap, lap + 10] {
+            buffer.push(value).map_err(|value| format!("lap {}: push of {} refused", lap, value))?;
+        }
+        let popped = [buffer.pop(), buffer.pop()];
+        if popped != [Some(lap), Some(lap + 10)] {
+            return Err(format!("lap {}: popped {:?}", lap, popped));
+        }
+    }
+
+    buffer.push(1).and_then(|()| buffer.push(2)).map_err(|value| format!("push of {} refused", value))?;
+    let producing = Arc::clone(&buffer);
+    let producer = thread::spawn(move || producing.push(3));
+    if !buffer.wait_until(PATIENCE, |stats| stats.waiting_producers == 1) {
+        buffer.close();
+        return Err("a push to a full buffer did not wait".to_string());
+    }
+    let drained = [buffer.pop(), buffer.pop(), buffer.pop()];
+    if producer.join().map_err(|_| "the producer panicked")? != Ok(()) || drained != [Some(1), Some(2), Some(3)] {
+        return Err(format!("after a blocked push, popped {:?}", drained));
+    }
+
+    let consuming = Arc::clone(&buffer);
+    let consumer = thread::spawn(move || [consuming.pop(), consuming.pop()]);
+    if !buffer.wait_until(PATIENCE, |stats| stats.waiting_consumers == 1) {
+        buffer.close();
+        return Err("a pop from an empty buffer did not wait".to_string());
+    }
+    buffer.push(4).map_err(|value| format!("push of {} refused", value))?;
+    if !buffer.wait_until(PATIENCE, |stats| stats.len == 0 && stats.waiting_consumers == 1) {
+        buffer.close();
+        return Err("a push did not wake the waiting consumer".to_string());
+    }
+    buffer.close();
+    let popped = consumer.join().map_err(|_| "the consumer panicked")?;
+    if popped != [Some(4), None] || buffer.push(5) != Err(5) {
+        return Err(format!("around close(), popped {:?} and the closed buffer took a push", popped));
+    }
+    // Two pops had to sleep; draining may add one, if it outran the woken producer
+    let stats = buffer.stats();
+    if stats.blocked_pushes != 1 || stats.blocked_pops < 2 || stats.waiting_producers + stats.waiting_consumers != 0 {
+        return Err(format!("stats counted {:?}", stats));
+    }
+    Ok(())
+}
+
+// A pool runs every job on one of its own threads and hands back each
+// result. A panicking job comes back as Err and its worker lives on, and
+// dropping the pool waits for the jobs still queued
+fn check_thread_pool() -> Result<(), String> {
+    let pool = ThreadPool::new(3);
+    let jobs: Vec<_> = (0..30)
+        .map(|job| pool.execute(move || (job * 2, thread::current().name().map(str::to_string))))
+        .collect();
+    let mut workers = Vec::new();
+    for (job, handle) in jobs.into_iter().enumerate() {
+        let (doubled, worker) = handle.join().map_err(|_| format!("job {} panicked", job))?;
+        if doubled != job * 2 {
+            return Err(format!("job {} returned {}", job, doubled));
+        }
+        workers.push(worker.unwrap_or_default());
+    }
+    workers.sort();
+    workers.dedup();
+    if workers.len() > 3 || !workers.iter().all(|worker| worker.starts_with("pool-worker-")) {
+        return Err(format!("3 pool threads, but jobs ran on {:?}", workers));
+    }
+
+    fault::install_quiet_panic_hook();
+    let failed = pool.execute(|| std::panic::panic_any(InjectedPanic));
+    if failed.join().is_ok() {
+        return Err("a panicking job joined as Ok".to_string());
+    }
+    let after: Result<Vec<_>, _> = (0..6).map(|job| pool.execute(move || job)).map(|handle| handle.join()).collect();
+    if after.ok() != Some((0..6).collect()) {
+        return Err("the pool stopped running jobs after one panicked".to_string());
+    }
+
+    let counter = Arc::new(SafeCounter::new());
+    for _ in 0..20 {
+        let counter = Arc::clone(&counter);
+        drop(pool.execute(move || {
+            thread::sleep(Duration::from_millis(1));
+            counter.increment();
+        }));
+    }
+    drop(pool);
+    if counter.get_count() != 20 {
+        return Err(format!("dropping the pool left {} of 20 queued jobs unrun", 20 - counter.get_count()));
+    }
+    match ThreadPool::try_new(0) {
+        Ok(_) => Err("a pool of 0 threads was created".to_string()),
+        Err(_) => Ok(()),
+    }
+}
+
+// One order, even with guards dropped out of order, reports nothing. A
+// longer cycle is reported once, from the lock created first, and
+// relocking a held Mutex is a cycle of one
+fn check_lock_order() -> Result<(), String> {
+    let order = LockOrder::new();
+    let [a, b, c] = ["a", "b", "c"].map(|name| TrackedMutex::new(name, 0, &order));
+    for _ in 0..2 {
+        let first = a.lock();
+        let second = b.lock();
+        drop(first);
+        let _third = c.lock();
+        drop(second);
+    }
+    if !order.inversions().is_empty() {
+        return Err(format!("one lock order reported {:?}", order.inversions()));
+    }
+
+    for _ in 0..2 {
+        let _c = c.lock();
+        let _a = a.lock();
+    }
+    let reported: Vec<String> = order.inversions().iter().map(ToString::to_string).collect();
+    if reported != ["a -> b -> c -> a"] {
+        return Err(format!("a -> b -> c -> a was reported as {:?}", reported));
+    }
+
+    let _b = b.lock();
+    if b.try_lock_for(Duration::from_millis(5)).is_some() {
+        return Err("a Mutex the thread held was locked again".to_string());
+    }
+    match order.inversions().last() {
+        Some(Inversion { cycle }) if cycle == &["b"] => Ok(()),
+        last => Err(format!("relocking b reported {:?}", last)),
+    }
+}
+
+// The SPSC ring refuses a push when full and keeps FIFO order across its
+// wrap and across threads. A handler raised in a critical section waits
+// for it to end, and a critical section inside a handler just runs
+fn check_interrupts() -> Result<(), String> {
+    let (mut producer, mut consumer) = spsc::channel::<u32>(3);
+    for lap in 0..4 {
+        for value in lap * 3..lap * 3 + 3 {
+            producer.push(value).map_err(|value| format!("pushing {} to a ring with room failed", value))?;
+        }
+        if producer.push(99).is_ok() {
+            return Err("a full ring took another value".to_string());
+        }
+        let popped: Vec<u32> = std::iter::from_fn(|| consumer.pop()).collect();
+        if popped != [lap * 3, lap * 3 + 1, lap * 3 + 2] {
+            return Err(format!("lap {} of the ring gave {:?}", lap, popped));
+        }
+    }
+
+    let (mut producer, mut consumer) = spsc::channel::<u64>(8);
+    let received = thread::scope(|scope| {
+        scope.spawn(move || {
+            for value in 0..10_000 {
+                while producer.push(value).is_err() {
+                    thread::yield_now();
+                }
+            }
+        });
+        let mut received = Vec::new();
+        while received.len() < 10_000 {
+            match consumer.pop() {
+                Some(value) => received.push(value),
+                None => thread::yield_now(),
+            }
+        }
+        received
+    });
+    if !received.iter().copied().eq(0..10_000) {
+        return Err("10000 values through an 8-slot ring arrived out of order".to_string());
+    }
+
+    let interrupts = Interrupts::new();
+    let (cell, ran) = (CsCell::new(0), AtomicBool::new(false));
+    let early = thread::scope(|scope| {
+        let early = interrupts.free(|_| {
+            scope.spawn(|| {
+                interrupts.raise(|cs| {
+                    *cell.borrow(cs) += 1;
+                    ran.store(true, Ordering::SeqCst);
+                    interrupts.free(|cs| *cell.borrow(cs) += 10);
+                })
+            });
+            thread::sleep(Duration::from_millis(20));
+            ran.load(Ordering::SeqCst)
+        });
+        early
+    });
+    let total = interrupts.free(|cs| *cell.borrow(cs));
+    if early || total != 11 || interrupts.deferred() != 1 {
+        return Err(format!("a handler raised in a critical section ran early: {}, left {} (expected 11), deferred {}",
+                           early, total, interrupts.deferred()));
+    }
+    Ok(())
+}
+
+// Each access of a push or pop as one tick of the simulated CPU
+struct OnCpu<'a>(&'a SimCpu, char);
+
+impl Steps for OnCpu<'_> {
+    fn step<R>(&mut self, access: impl FnOnce() -> R) -> R {
+        self.0.tick_with(self.1, access)
+    }
+}
+
+// Every interleaving of a producer pushing 0 and 1 and a consumer popping
+// twice through a one-slot ring, an atomic access per tick. In each, the
+// consumer gets what was pushed, in order, once - whether it found the
+// ring empty, the producer found it full, or neither
+fn check_spsc_schedules() -> Result<(), String> {
+    let runs = sim_cpu::explore(|policy| {
+        let cpu = SimCpu::with_policy(policy);
+        let (mut producer, mut consumer) = spsc::channel::<u32>(1);
+        cpu.arrive('P', 0, 0);
+        cpu.arrive('C', 0, 0);
+        let (pushed, popped) = thread::scope(|scope| {
+            let produce = scope.spawn(|| {
+                let pushed: Vec<u32> = (0..2).filter(|&value| producer.push_in(value, &mut OnCpu(&cpu, 'P')).is_ok()).collect();
+                cpu.finish('P');
+                pushed
+            });
+            let consume = scope.spawn(|| {
+                let popped: Vec<u32> = (0..2).filter_map(|_| consumer.pop_in(&mut OnCpu(&cpu, 'C'))).collect();
+                cpu.finish('C');
+                popped
+            });
+            (produce.join(), consume.join())
+        });
+        let (Ok(pushed), Ok(popped)) = (pushed, popped) else {
+            return Err(format!("a task panicked in schedule {}", cpu.trace()));
+        };
+        let left: Vec<u32> = std::iter::from_fn(|| consumer.pop()).collect();
+        if [popped.as_slice(), &left].concat() != pushed || left.len() > 1 {
+            return Err(format!("schedule {}: pushed {:?}, popped {:?}, left {:?}", cpu.trace(), pushed, popped, left));
+        }
+        Ok(cpu.trace())
+    })?;
+    // Pushes and pops of two, three or four ticks each: far fewer
+    // interleavings than 16 free ticks would give, but more than a few
+    if runs < 100 {
+        return Err(format!("only {} interleavings were played", runs));
+    }
+    Ok(())
+}
+
+// A set Event lets waiters through until reset, and a timed-out waiter
+// leaves no trace. A Semaphore under churn neither over-grants nor loses
+// a wakeup; a lost one would park a worker for good, so the workers are
+// left behind if an Event does not report them done in time
+fn check_event() -> Result<(), String> {
+    let event = resilient_core::Event::new();
+    if event.wait_timeout(Duration::from_millis(5)) {
+        return Err("wait_timeout on an unset event returned true".to_string());
+    }
+    if !format!("{:?}", event).contains("waiters: 0") {
+        return Err(format!("a timed-out waiter stayed registered: {:?}", event));
+    }
+    event.set();
+    event.wait();
+    event.reset();
+    if event.is_set() || event.wait_timeout(Duration::ZERO) {
+        return Err("reset left the event set".to_string());
+    }
+
+    const WORKERS: usize = 8;
+    let semaphore = Arc::new(Semaphore::new(2));
+    let (busy, peak, done) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)), Arc::new(resilient_core::Event::new()));
+    let finished = Arc::new(AtomicUsize::new(0));
+    for _ in 0..WORKERS {
+        let (semaphore, busy, peak, done, finished) =
+            (Arc::clone(&semaphore), Arc::clone(&busy), Arc::clone(&peak), Arc::clone(&done), Arc::clone(&finished));
+        thread::Builder::new()
+            .spawn(move || {
+                for _ in 0..200 {
+                    let _permit = semaphore.acquire();
+                    peak.fetch_max(busy.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                    thread::yield_now();
+                    busy.fetch_sub(1, Ordering::SeqCst);
+                }
+                if finished.fetch_add(1, Ordering::SeqCst) + 1 == WORKERS {
+                    done.set();
+                }
+            })
+            .map_err(|error| format!("cannot start a semaphore worker: {}", error))?;
+    }
+    if !done.wait_timeout(Duration::from_secs(20)) {
+        return Err(format!("{} of {} semaphore workers still parked after 20s: a lost wakeup",
+                           WORKERS - finished.load(Ordering::SeqCst), WORKERS));
+    }
+    match (peak.load(Ordering::SeqCst), semaphore.available()) {
+        (peak, 2) if peak <= 2 => Ok(()),
+        (peak, available) => Err(format!("{} threads held 2 permits at once; {} free at the end", peak, available)),
+    }
+}
+
+// Owners pop their newest task and thieves the oldest; a half-steal
+// carries half the rest home, still counted as stolen. Under contention
+// every task runs exactly once
+fn check_work_stealing() -> Result<(), String> {
+    let never = WorkStealing::new(vec![vec![0, 1], vec![]], StealPolicy::Never);
+    if never.next(1).is_some() || never.backlog() != [2, 0] {
+        return Err("a worker stole under StealPolicy::Never".to_string());
+    }
+
+    let one = WorkStealing::new(vec![vec![0, 1, 2, 3], vec![]], StealPolicy::StealOne);
+    let (own, stolen) = (one.next(0), one.next(1));
+    match (&own, &stolen) {
+        (Some(own), Some(stolen)) if own.task == 3 && !own.steal && stolen.task == 0 && stolen.steal && stolen.dealt_to == 0 => {}
+        _ => return Err(format!("owner took {:?} and thief {:?}, expected 3 and 0", own, stolen)),
+    }
+    if one.backlog() != [2, 0] {
+        return Err(format!("stealing one left {:?}", one.backlog()));
+    }
+
+    let half = WorkStealing::new(vec![vec![0, 1, 2, 3, 4], vec![]], StealPolicy::StealHalf);
+    let mut stats = WorkerStats::default();
+    let mut ran = Vec::new();
+    for taken in (0..3).filter_map(|_| half.next(1)) {
+        stats.record(1, &taken, 1);
+        ran.push(taken.task);
+    }
+    if ran != [0, 2, 1] || half.backlog() != [2, 0] || (stats.stolen, stats.steals, stats.own()) != (3, 1, 0) {
+        return Err(format!("a half-steal ran {:?}, left {:?} and counted {:?}", ran, half.backlog(), stats));
+    }
+
+    let pool = WorkStealing::new(vec![(0..2000).collect(), Vec::new(), Vec::new(), Vec::new()], StealPolicy::StealHalf);
+    let mut ran: Vec<usize> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..4)
+            .map(|worker| {
+                let pool = &pool;
+                scope.spawn(move || std::iter::from_fn(|| pool.next(worker).map(|taken| taken.task)).collect::<Vec<usize>>())
+            })
+            .collect();
+        workers.into_iter().flat_map(|worker| worker.join().unwrap_or_default()).collect()
+    });
+    ran.sort_unstable();
+    if !ran.iter().copied().eq(0..2000) {
+        return Err(format!("4 workers stealing from one deque ran {} of 2000 tasks, or some twice", ran.len()));
+    }
+    Ok(())
+}
+
+// The lock-free queue is FIFO, refuses a push when full and a pop when
+// empty, and reuses its slots lap after lap. Under contention it
+// delivers every item once and in each producer's order, as the Mutex
+// baseline does
+fn check_lockfree() -> Result<(), String> {
+    let queue = LockFreeQueue::<i32>::new(3);  // not a power of two
+    for lap in 0..5 {
+        for value in [lap, -lap, 100] {
+            queue.push(value).map_err(|value| format!("lap {}: push of {} refused", lap, value))?;
+        }
+        if queue.push(7) != Err(7) || queue.len() != 3 {
+            return Err(format!("lap {}: a full queue took a fourth value", lap));
+        }
+        let popped: Vec<i32> = std::iter::from_fn(|| queue.pop()).collect();
+        if popped != [lap, -lap, 100] || !queue.is_empty() {
+            return Err(format!("lap {}: popped {:?}", lap, popped));
+        }
+    }
+
+    let lock_free = lockfree::run(Arc::new(LockFreeQueue::new(4)), 3, 3, 2000).map_err(|error| error.to_string())?;
+    let locked = lockfree::run(Arc::new(LockedQueue::new(4)), 3, 3, 2000).map_err(|error| error.to_string())?;
+    for (name, run) in [("lock-free", &lock_free), ("locked", &locked)] {
+        if !run.exactly_once() || !run.in_order() {
+            return Err(format!("the {} queue lost, repeated or reordered items", name));
+        }
+    }
+    Ok(())
+}
+
+// A pool and a pipeline walked through every phase lose no work
+fn check_typed_phases() -> Result<(), String> {
+    let pool = WorkerPool::new().workers(3).start();
+    for job in 0..100u64 {
+        pool.submit(move || job * job);
+    }
+    let pool = pool.drain().finish();
+    let total: u64 = pool.results().iter().sum();
+    if pool.results().len() != 100 || total != (0..100u64).map(|job| job * job).sum::<u64>() {
+        return Err(format!("pool returned {} results summing to {}", pool.results().len(), total));
+    }
+
+    let pipeline = Pipeline::new().stage(|x: u64| x + 1).stage(|x| x * 2).start();
+    for item in 0..50u64 {
+        pipeline.send(item);
+    }
+    let pipeline = pipeline.drain().finish();
+    let expected: Vec<u64> = (0..50u64).map(|item| (item + 1) * 2).collect();
+    if pipeline.outputs() != expected.as_slice() {
+        return Err("pipeline lost or reordered items".to_string());
+    }
+    Ok(())
+}
+
+// Every transition on a mock clock: no waiting, same result every run
+fn check_circuit_breaker() -> Result<(), String> {
+    let clock = Arc::new(MockClock::new());
+    let cooldown = Duration::from_secs(30);
+    let breaker = CircuitBreaker::new(2, cooldown, clock.clone());
+    let expect = |step: &str, state: BreakerState| match breaker.state() {
+        actual if actual == state => Ok(()),
+        actual => Err(format!("{}: breaker is {:?}, expected {:?}", step, actual, state)),
+    };
+
+    breaker.record_failure();
+    expect("one failure", BreakerState::Closed)?;
+    breaker.record_failure();
+    expect("two failures", BreakerState::Open)?;
+    clock.advance(cooldown - Duration::from_millis(1));
+    expect("just before the cooldown", BreakerState::Open)?;
+    clock.advance(Duration::from_millis(1));
+    expect("after the cooldown", BreakerState::HalfOpen)?;
+    if !breaker.allow() || breaker.allow() {
+        return Err("half-open breaker must allow exactly one trial call".to_string());
+    }
+    breaker.record_failure();
+    expect("failed trial", BreakerState::Open)?;
+    clock.advance(cooldown);
+    breaker.allow();
+    breaker.record_success();
+    expect("successful trial", BreakerState::Closed)
+}
+
+// Same seed, same run; nothing crosses a partition
+fn check_network_sim() -> Result<(), String> {
+    let lossy = LinkFaults { drop: 0.2, duplicate: 0.1, reorder: 0.1, min_delay: 1, max_delay: 3 };
+    let first = network_sim::heartbeat_run(3, 30, lossy, Some((5, 15)), 42);
+    let second = network_sim::heartbeat_run(3, 30, lossy, Some((5, 15)), 42);
+    if first.stats != second.stats || first.view != second.view {
+        return Err(format!("two runs with one seed differ: {:?} vs {:?}", first.stats, second.stats));
+    }
+
+    let mut network = NetworkSim::new(2, LinkFaults::default(), 0);
+    network.partition(&[&[0], &[1]]);
+    network.send(0, 1, "across");
+    if !network.tick().is_empty() {
+        return Err("a message crossed the partition".to_string());
+    }
+    network.heal();
+    network.send(0, 1, "healed");
+    match network.tick().as_slice() {
+        [envelope] if envelope.message == "healed" => Ok(()),
+        delivered => Err(format!("expected one message after healing, got {}", delivered.len())),
+    }
+}
+
+// A sends to B; C acts alone
+fn check_vector_clock() -> Result<(), String> {
+    let (mut a, mut b, mut c) = (VectorClock::new(3), VectorClock::new(3), VectorClock::new(3));
+    a.tick(0);
+    let send = a.clone();
+    b.receive(1, &send);
+    c.tick(2);
+    let checks = [
+        ("send vs receive", send.compare(&b), Causality::Before),
+        ("receive vs send", b.compare(&send), Causality::After),
+        ("send vs unrelated event", send.compare(&c), Causality::Concurrent),
+        ("event vs itself", c.compare(&c.clone()), Causality::Equal),
+    ];
+    for (name, actual, expected) in checks {
+        if actual != expected {
+            return Err(format!("{}: {:?}, expected {:?}", name, actual, expected));
+        }
+    }
+    Ok(())
+}
+
+// Retries lose nothing, and the idempotent consumer applies each transfer
+// once, identically for the same seed
+fn check_delivery() -> Result<(), String> {
+    let faults = LinkFaults { drop: 0.3, duplicate: 0.1, reorder: 0.3, min_delay: 1, max_delay: 3 };
+    let run = |guarantee| delivery::run(guarantee, 25, |_| 1, faults, 9);
+    let retried = run(Guarantee::AtLeastOnce);
+    if retried.lost != 0 {
+        return Err(format!("at-least-once lost {} transfers", retried.lost));
+    }
+    let idempotent = run(Guarantee::Idempotent);
+    if idempotent.lost != 0 || idempotent.duplicates != 0 || idempotent.balance != 25 {
+        return Err(format!("idempotent consumer: {} lost, {} duplicates, balance {}",
+                           idempotent.lost, idempotent.duplicates, idempotent.balance));
+    }
+    if idempotent.stats != run(Guarantee::Idempotent).stats {
+        return Err("the same seed gave a different run".to_string());
+    }
+    Ok(())
+}
+
+// Lanes count per thread and toward the total; bars and ETAs render
+fn check_progress() -> Result<(), String> {
+    let progress = Progress::with_lanes("check", Goal::Steps(4), 2, 2);
+    progress.lane(0).advance(2);
+    progress.lane(1).advance(1);
+    let lines = progress.lines();
+    progress.finish();
+    if lines.len() != 3 || !lines[0].contains("3/4") || !lines[1].ends_with("100%") || !lines[2].ends_with("50%") {
+        return Err(format!("unexpected status {:?}", lines));
+    }
+    let cases = [
+        (progress::bar(0.5, 10), "#####-----"),
+        (progress::bar(1.5, 4), "####"),
+        (progress::clock(Duration::from_secs(75)), "1:15"),
+        (progress::clock(Duration::from_secs(3725)), "1:02:05"),
+    ];
+    for (actual, expected) in cases {
+        if actual != expected {
+            return Err(format!("rendered {:?}, expected {:?}", actual, expected));
+        }
+    }
+    Ok(())
+}
+
+// The tracking allocator sees a buffer come and go, and the soak check
+// flags a rising floor but not a sawtooth
+fn check_soak() -> Result<(), String> {
+    let before = alloc_track::live_bytes();
+    let buffer = vec![0u8; 1 << 20];
+    let held = alloc_track::live_bytes();
+    drop(buffer);
+    let after = alloc_track::live_bytes();
+    if held < before + (1 << 20) || after + (1 << 20) > held {
+        return Err(format!("live bytes {} -> {} -> {} around a 1 MiB buffer", before, held, after));
+    }
+
+    let leak = [50, 40, 10, 11, 12, 13, 14, 15, 16, 17];
+    let sawtooth = [50, 40, 10, 30, 10, 30, 10, 30, 10, 30];
+    if !soak::floor_keeps_rising(&leak, 5) {
+        return Err("a steadily rising series was not flagged".to_string());
+    }
+    if soak::floor_keeps_rising(&leak, 10) || soak::floor_keeps_rising(&sawtooth, 0) {
+        return Err("growth within tolerance, or a sawtooth, was flagged".to_string());
+    }
+    Ok(())
+}
+
+// A child killed halfway through a log record recovers cleanly; one
+// killed halfway through rewriting its state file in place does not
+fn check_crash_only() -> Result<(), String> {
+    for point in ["torn-append", "torn-snapshot", "before-truncate"] {
+        crash_only::crash_once(point, 9, 4, false).map_err(|problem| format!("{}: {}", point, problem))?;
+    }
+    if crash_only::crash_once("torn-append", 3, 4, true).is_ok() {
+        return Err("a torn in-place state file was recovered".to_string());
+    }
+    Ok(())
+}
+
+// Each way a child can die is told apart, and a clean section exits 0
+// and reports a verdict for each declared invariant
+fn check_sandbox() -> Result<(), String> {
+    for probe in sandbox::PROBES.into_iter().filter(|probe| *probe != Probe::Hang) {
+        let outcome = probe.run();
+        if outcome.exit != probe.expected() {
+            return Err(format!("{} probe ended as '{}', expected '{}'", probe.name(), outcome.exit, probe.expected()));
+        }
+    }
+    let args = ["sandbox", "--child", "outcome", "typed_phases"].map(str::to_string);
+    let outcome = sandbox::run_in_sandbox(&args, Duration::from_secs(30), false);
+    if outcome.exit != Exit::Passed {
+        return Err(format!("typed_phases in a child ended as '{}'", outcome.exit));
+    }
+    let expected: Vec<String> = TYPED_PHASES.invariants.iter().map(|invariant| format!("Held\t{}", invariant)).collect();
+    if outcome.reported != expected {
+        return Err(format!("typed_phases reported {:?}, expected {:?}", outcome.reported, expected));
+    }
+    Ok(())
+}
+
+// A child that really panics leaves a report that names the panic, the
+// settings and the code path; old reports are pruned
+fn check_crash_report() -> Result<(), String> {
+    let dir = std::env::temp_dir().join(format!("safety-demo-crash-check-{}", std::process::id()));
+    let exe = std::env::current_exe().map_err(|error| error.to_string())?;
+    let status = Command::new(exe)
+        .args(["sandbox", "--child", "probe", "panic"])
+        .env("SAFETY_DEMO_CRASH_DIR", &dir)
+        .env("SAFETY_DEMO_THREADS", "7")
+        .env_remove("SAFETY_DEMO_EVENT_LOG")
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map_err(|error| error.to_string())?;
+    let result = (|| {
+        if status.code() != Some(101) {
+            return Err(format!("the panic probe exited with {}", status));
+        }
+        let reports: Vec<_> = fs::read_dir(&dir)
+            .map_err(|error| format!("no crash report directory: {}", error))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        let [report] = reports.as_slice() else {
+            return Err(format!("expected one crash report, found {}", reports.len()));
+        };
+        let text = fs::read_to_string(report).map_err(|error| error.to_string())?;
+        let json: serde_json::Value = serde_json::from_str(&text).map_err(|error| error.to_string())?;
+        if !json["message"].as_str().is_some_and(|message| message.contains("deliberate panic")) {
+            return Err(format!("wrong message: {}", json["message"]));
+        }
+        if !json["location"].as_str().is_some_and(|location| location.starts_with("sandbox.rs:")) {
+            return Err(format!("wrong location: {}", json["location"]));
+        }
+        if json["config"]["SAFETY_DEMO_THREADS"] != "7" {
+            return Err("the report does not carry the settings".to_string());
+        }
+        let backtrace = json["backtrace"].as_array().map(Vec::as_slice).unwrap_or_default();
+        if !backtrace.iter().any(|frame| frame.as_str().is_some_and(|frame| frame.contains("Probe::trigger"))) {
+            return Err("the backtrace does not reach the panicking function".to_string());
+        }
+
+        for copy in 0..3 {
+            fs::copy(report, dir.join(format!("crash-{}-0-0.json", copy))).map_err(|error| error.to_string())?;
+        }
+        crash_report::prune(&dir, 2).map_err(|error| error.to_string())?;
+        let left = fs::read_dir(&dir).map_err(|error| error.to_string())?.count();
+        if left != 2 || !report.exists() {
+            return Err(format!("pruning to 2 left {} report(s){}", left, if report.exists() { "" } else { " and removed the newest" }));
+        }
+        Ok(())
+    })();
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+// Each lowered limit is really hit, comes back as the matching error,
+// and the degraded path still finishes the work
+fn check_exhaustion() -> Result<(), String> {
+    if !cfg!(target_os = "linux") {
+        return Ok(());
+    }
+    let threads = exhaustion::threads_demo().map_err(|error| error.to_string())?;
+    if !matches!(threads.error, Some(ExhaustionError::Threads { .. })) || threads.sum != threads.expected {
+        return Err(format!("threads: spawned {}, sum {} of {}", threads.spawned, threads.sum, threads.expected));
+    }
+    let files = exhaustion::files_demo().map_err(|error| format!("files: {}", error))?;
+    if !matches!(files.naive, Some(ExhaustionError::Files { .. })) || files.read != 2 * exhaustion::FILES {
+        return Err(format!("files: read {} through the pool", files.read));
+    }
+    let sockets = exhaustion::sockets_demo().map_err(|error| format!("sockets: {}", error))?;
+    if !matches!(sockets.naive, Some(ExhaustionError::Sockets { .. })) || sockets.served != exhaustion::REQUESTS {
+        return Err(format!("sockets: served {}", sockets.served));
+    }
+    Ok(())
+}
+
+// With every spawn failing, a section's workers all run inline and its
+// invariants still hold; with every other one failing, some do
+// Collects what a tracing subscriber writes
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for Captured {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// A line said on a worker thread is traced inside its spawner's demo span
+fn check_trace() -> Result<(), String> {
+    let buffer = Arc::new(Mutex::new(Vec::new()));
+    let writer = Arc::clone(&buffer);
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::try_new("demo=info").map_err(|error| error.to_string())?)
+        .with_writer(move || Captured(Arc::clone(&writer)))
+        .finish();
+    tracing::subscriber::with_default(subscriber, || {
+        let _span = trace::demo_span("trace_check").entered();
+        spawn_policy::spawn(|| trace::emit(&Event::Output { line: "from a worker".to_string() }))
+            .join()
+            .map_err(|_| "worker panicked".to_string())?;
+        trace::emit(&Event::Metric { name: "filtered out".to_string(), value: 1.0, unit: String::new() });
+        Ok::<_, String>(())
+    })?;
+
+    let traced = String::from_utf8_lossy(&buffer.lock().unwrap_or_else(|poisoned| poisoned.into_inner())).into_owned();
+    let line = traced.lines().find(|line| line.contains("from a worker")).ok_or(format!("worker line not traced: {:?}", traced))?;
+    if !line.contains("demo{name=\"trace_check\"}:thread{worker=") {
+        return Err(format!("worker line outside its spans: {:?}", line));
+    }
+    if traced.contains("filtered out") {
+        return Err("a DEBUG event passed an info filter".to_string());
+    }
+    if trace::check_filter("demo=[").is_ok() {
+        return Err("accepted a malformed filter".to_string());
+    }
+    Ok(())
+}
+
+// Verification passes only when every declared invariant held, and a
+// demo that panics is caught at run_isolated and failed, with its
+// message, instead of ending the run
+fn check_verify() -> Result<(), String> {
+    let outcome = |verdict| Outcome { name: "demo", verdicts: vec![("invariant", verdict)], skipped: false };
+    let verified = |outcome| verify::Verified { name: "demo", outcome };
+    fault::install_quiet_panic_hook();
+    let panicked = verified(verify::run_isolated("panicked", || -> Outcome { std::panic::panic_any(InjectedPanic) }));
+    // A string panic, to see its message carried over; kept off the console
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let unwrapped = verify::run_isolated("unwrapped", || -> Outcome { panic!("no outcome") });
+    std::panic::set_hook(previous_hook);
+    let cases = [
+        (vec![Verdict::Held, Verdict::Held], 0),
+        (vec![Verdict::Held, Verdict::Violated], verify::FAILED_EXIT_CODE),
+        (vec![Verdict::NotChecked], verify::FAILED_EXIT_CODE),
+    ];
+    for (verdicts, expected) in cases {
+        let results: Vec<verify::Verified> =
+            verdicts.iter().map(|&verdict| verified(verify::run_isolated("demo", || outcome(verdict)))).collect();
+        if verify::exit_code(&results) != expected {
+            return Err(format!("{:?} exited {}, expected {}", verdicts, verify::exit_code(&results), expected));
+        }
+    }
+    if unwrapped != Err(verify::DemoPanic { demo: "unwrapped".to_string(), message: "no outcome".to_string() }) {
+        return Err(format!("a panic's message was lost: {:?}", unwrapped));
+    }
+    match panicked.outcome {
+        Err(_) if verify::exit_code(&[panicked]) == verify::FAILED_EXIT_CODE => Ok(()),
+        _ => Err("a panicking demo was not caught and failed".to_string()),
+    }
+}
+
+// An interactive section stops at each step until Enter, showing the
+// state so far, and the wait does not count against its time budget
+fn check_step() -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|error| error.to_string())?;
+    let mut child = Command::new(exe)
+        .args(["--interactive", "sandbox", "--child", "section", "counter_safety"])
+        .env("SAFETY_DEMO_TICK_MS", "0")
+        .env("SAFETY_DEMO_BUDGET_MS", "1000")
+        .env("SAFETY_DEMO_HARD_BUDGET_MS", "1000")
+        .env_remove("SAFETY_DEMO_EVENT_LOG")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|error| error.to_string())?;
+    // Longer than the hard budget
+    thread::sleep(Duration::from_millis(1500));
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(b"\n\n").map_err(|error| error.to_string())?;
+    }
+    let output = child.wait_with_output().map_err(|error| error.to_string())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() {
+        return Err(format!("interactive run exited with {}", output.status));
+    }
+    if stdout.matches("[step]").count() < 4 || !stdout.contains("counter = 10000") || stdout.contains("budget") {
+        return Err(format!("unexpected interactive output: {:?}", stdout));
+    }
+    Ok(())
+}
+
+// Three tasks appending to one log, each pausing between appends
+fn scheduled_run(scheduler: &Scheduler) -> Result<Vec<usize>, String> {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let tasks: Vec<_> = (0..3).map(|id| scheduler.task(&format!("task {}", id))).collect();
+    let handles: Vec<_> = tasks
+        .into_iter()
+        .enumerate()
+        .map(|(id, task)| {
+            let log = Arc::clone(&log);
+            spawn_policy::spawn(move || task.run(|task| {
+                for _ in 0..4 {
+                    log.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(id);
+                    task.pause(id as u64 + 1);
+                }
+            }))
+        })
+        .collect();
+    for handle in handles {
+        handle.join().map_err(|_| "task panicked".to_string())?;
+    }
+    let log = log.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+    Ok(log)
+}
+
+// A seed replays its interleaving exactly; another seed gives another one
+fn check_scheduler() -> Result<(), String> {
+    let first = Scheduler::seeded(7);
+    let observed = scheduled_run(&first)?;
+    let again = Scheduler::seeded(7);
+    if scheduled_run(&again)? != observed || again.trace() != first.trace() {
+        return Err(format!("seed 7 did not replay: {:?} vs {:?}", first.trace(), again.trace()));
+    }
+    if !first.is_seeded() || observed.len() != 12 {
+        return Err(format!("seeded run gave up or lost appends: {:?}", observed));
+    }
+    let traces: Vec<Vec<String>> = (1..=4)
+        .map(|seed| {
+            let scheduler = Scheduler::seeded(seed);
+            scheduled_run(&scheduler).map(|_| scheduler.trace())
+        })
+        .collect::<Result<_, _>>()?;
+    if traces.iter().all(|trace| *trace == traces[0]) {
+        return Err("seeds 1-4 all gave the same interleaving".to_string());
+    }
+    let real = Scheduler::real(Duration::ZERO);
+    let appended = scheduled_run(&real)?.len();
+    if appended != 12 || !real.trace().is_empty() {
+        return Err(format!("real timing: {} appends, trace {:?}", appended, real.trace()));
+    }
+    Ok(())
+}
+
+// Batched output keeps each rwlock reader's two lines together, though
+// the readers sleep between them
+fn check_console() -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|error| error.to_string())?;
+    let output = Command::new(exe)
+        .args(["sandbox", "--child", "section", "rwlock_safety"])
+        .env("SAFETY_DEMO_OUTPUT", "batched")
+        .env_remove("SAFETY_DEMO_EVENT_LOG")
+        .output()
+        .map_err(|error| error.to_string())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    let mut readers = 0;
+    for (index, line) in lines.iter().enumerate() {
+        if let Some(reader) = line.strip_suffix(": Data length = 5") {
+            readers += 1;
+            let next = lines.get(index + 1).copied().unwrap_or_default();
+            if !next.starts_with(&format!("{}: First element", reader)) {
+                return Err(format!("'{}' was followed by '{}'", line, next));
+            }
+        }
+    }
+    if readers == 0 {
+        return Err(format!("no reader output (exit {})", output.status));
+    }
+    Ok(())
+}
+
+// The controller adds one worker per good window, halves on a bad one and
+// stays within its bounds; on the pipeline it backs off and recovers
+fn check_aimd() -> Result<(), String> {
+    let target = Duration::from_millis(10);
+    let mut controller = Aimd::new(3, 1, 5, target);
+    let steps = [(target, 4), (target, 5), (target, 5), (target * 2, 2), (target * 2, 1), (target * 2, 1), (target, 2)];
+    for (step, (latency, expected)) in steps.into_iter().enumerate() {
+        let limit = controller.observe(latency);
+        if limit != expected {
+            return Err(format!("step {}: limit {} after {:?}, expected {}", step, limit, latency, expected));
+        }
+    }
+    let windows = aimd::run(aimd::Sizing::Adaptive(aimd::adaptive()));
+    if windows.iter().map(|window| window.index).ne(0..aimd::WINDOWS) {
+        return Err("pipeline lost or reordered windows".to_string());
+    }
+    if !aimd::backs_off(&windows) || !aimd::recovers(&windows) {
+        return Err(format!("workers per window: {:?}", windows.iter().map(|window| window.workers).collect::<Vec<_>>()));
+    }
+    Ok(())
+}
+
+// Hedging cuts the tail, and every hedge cancels exactly one call
+fn check_hedge() -> Result<(), String> {
+    let runtime = async_demo::paused_runtime().map_err(|error| error.to_string())?;
+    let plain = hedge::run(&runtime, false, 7);
+    let hedged = hedge::run(&runtime, true, 7);
+    if plain.hedges != 0 || plain.cancelled != 0 {
+        return Err(format!("unhedged run sent {} hedges and cancelled {} calls", plain.hedges, plain.cancelled));
+    }
+    if hedged.hedges == 0 || hedged.cancelled != hedged.hedges {
+        return Err(format!("{} hedges but {} cancelled calls", hedged.hedges, hedged.cancelled));
+    }
+    let (hedged_p99, plain_p99) = (hedged.latencies.percentile(99.0), plain.latencies.percentile(99.0));
+    if hedged_p99 >= plain_p99 {
+        return Err(format!("p99 {:?} hedged, {:?} without", hedged_p99, plain_p99));
+    }
+    Ok(())
+}
+
+// The first success wins and cancels a loser that checks its token; with
+// no success the race fails; a staged winner commits only on the latest
+// version
+fn check_speculative() -> Result<(), String> {
+    let spin: Alternative<'_, u32, String> = Box::new(|token: &CancellationToken| {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Instant::now() < deadline {
+            token.check()?;
+            thread::yield_now();
+        }
+        Ok(0)
+    });
+    let quick: Alternative<'_, u32, String> = Box::new(|_: &CancellationToken| Ok(1));
+    let won = speculative::race(vec![("quick", quick), ("spin", spin)]).map_err(|error| error.to_string())?;
+    if won.winner != "quick" || won.value != 1 || won.cancelled != ["spin"] {
+        return Err(format!("{} won with {}, cancelled {:?}", won.winner, won.value, won.cancelled));
+    }
+
+    let fail = |reason: &'static str| -> Alternative<'_, u32, String> {
+        Box::new(move |_: &CancellationToken| Err(TaskError::Failed(reason.to_string())))
+    };
+    match speculative::race(vec![("a", fail("down")), ("b", fail("busy"))]) {
+        Err(RaceError::AllFailed { failed, .. }) if failed.len() == 2 => {}
+        other => return Err(format!("two failures gave {:?}", other.map(|won| won.winner))),
+    }
+
+    let store = VersionedStore::new(10u32);
+    let stale = store.pin();
+    store.update(|value| value + 1);
+    let double = || -> Staged<'_, u32, String> { Box::new(|value: &u32, _: &CancellationToken| Ok(value * 2)) };
+    if !matches!(speculative::race_commit(&store, &stale, vec![("double", double())]), Err(RaceError::Conflict(_))) {
+        return Err("a stale winner was committed".to_string());
+    }
+    drop(stale);
+    let base = store.pin();
+    let won = speculative::race_commit(&store, &base, vec![("double", double())]).map_err(|error| error.to_string())?;
+    if won.value != 2 || *store.pin() != 22 {
+        return Err(format!("committed version {} holding {}", won.value, *store.pin()));
+    }
+    Ok(())
+}
+
+// Synthetic timings fit the model they were made from, fixed overhead
+// included, and only faster growth than annotated fails the verdict
+fn check_cost_model() -> Result<(), String> {
+    let sizes: Vec<usize> = (0..6).map(|step| 1024 << step).collect();
+    let timed = |nanos: &dyn Fn(f64) -> f64| -> Vec<(usize, Duration)> {
+        sizes.iter().map(|&n| (n, Duration::from_nanos(nanos(n as f64) as u64))).collect()
+    };
+    let cases = [
+        ("10n", timed(&|n| 10.0 * n), Complexity::Linear),
+        ("50us + 5n", timed(&|n| 50_000.0 + 5.0 * n), Complexity::Linear),
+        ("n log n", timed(&|n| n * n.log2()), Complexity::NLogN),
+        ("n^2 / 100", timed(&|n| n * n / 100.0), Complexity::Quadratic),
+    ];
+    for (label, samples, model) in &cases {
+        let fit = cost_model::fit(samples);
+        if fit.model != *model {
+            return Err(format!("{} fitted as {}, expected {}", label, fit.model, model));
+        }
+        if !cost_model::within(*model, &fit, samples) {
+            return Err(format!("{} is not within its own model (n^{:.2})", label, fit.exponent));
+        }
+    }
+    let quadratic = &cases[3].1;
+    if cost_model::within(Complexity::Linear, &cost_model::fit(quadratic), quadratic) {
+        return Err("quadratic timings passed as O(n)".to_string());
+    }
+    if (Complexity::Linear.exponent(1024, 32768) - 1.0).abs() > 1e-9 {
+        return Err(format!("O(n) grows as n^{}", Complexity::Linear.exponent(1024, 32768)));
+    }
+    Ok(())
+}
+
+// The sealed extension traits catch a corrupted frame and a backend that
+// repeats itself, and refuse what the limiter refuses
+fn check_extensions() -> Result<(), String> {
+    let mut frame = Fletcher32.frame(vec![1, 2, 3, 4]);
+    frame.corrupt(2);
+    if Fletcher32.open(&frame).is_ok() {
+        return Err("a corrupted frame was opened".to_string());
+    }
+
+    let (ran, stats) = TokenBucket::new(2, 10).drive(&[0, 0, 0, 5, 10], |tick| tick);
+    if ran != [0, 0, 10] || stats.refused != 2 {
+        return Err(format!("token bucket ran {:?} and refused {}", ran, stats.refused));
+    }
+
+    // Hands out the front item without removing it
+    struct Echo(Mutex<Option<Sequenced<u8>>>);
+    impl ChannelBackend<u8> for Echo {
+        fn name(&self) -> &str {
+            "echo"
+        }
+        fn push(&self, item: Sequenced<u8>) {
+            self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get_or_insert(item);
+        }
+        fn pop(&self) -> Option<Sequenced<u8>> {
+            self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+        }
+    }
+    let echo = Echo(Mutex::new(None));
+    match echo.transfer(vec![7, 8, 9]) {
+        Err(BackendViolation::Duplicated { seq: 0 }) => Ok(()),
+        other => Err(format!("{} backend gave {:?}, expected a duplicate of 0", echo.name(), other)),
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_micros(1) {
+        format!("{}ns", duration.as_nanos())
+    } else if duration < Duration::from_millis(1) {
+        format!("{:.1}us", duration.as_nanos() as f64 / 1_000.0)
+    } else {
+        format!("{:.2}ms", duration.as_secs_f64() * 1_000.0)
+    }
+}
+
+// `selftest` - returns the exit code (nonzero if any subsystem check fails)
+pub fn selftest_command(config: &EnvConfig) -> i32 {
+    println!("{}", narration!("selftest.heading"));
+
+    let host = probe_host();
+    println!("{}", narration!("selftest.host_heading"));
+    println!("{}", narration!("selftest.cores", cores = host.cores));
+    println!("{}", narration!("selftest.atomic_widths", widths = host.atomic_widths.join(", ")));
+    let endian = if cfg!(target_endian = "big") { narration!("selftest.big_endian") } else { narration!("selftest.little_endian") };
+    println!("{}", narration!("selftest.target", bits = usize::BITS, endian));
+    println!("{}", narration!("selftest.console", name = platform::name()));
+    println!("{}", narration!("selftest.clock_resolution", resolution = format_duration(host.clock_resolution)));
+    println!("{}", narration!("selftest.sleep_overshoot", overshoot = format_duration(host.sleep_overshoot)));
+    match host.page_size {
+        Some(size) => println!("{}", narration!("selftest.page_size", size)),
+        None => println!("{}", narration!("selftest.page_size_unknown")),
+    }
+
+    let checks: [(&str, SubsystemCheck); 40] = [
+        ("env_config", check_env_config),
+        ("event_log", check_event_log),
+        ("demo_registry", check_demo_registry),
+        ("safe_counter", check_counter),
+        ("shared_data", check_shared_data),
+        ("lock_order", check_lock_order),
+        ("snapshot_diff", check_snapshot_diff),
+        ("data_layout", check_data_layout),
+        ("channel", check_channel),
+        ("atomics", check_atomics),
+        ("lockfree", check_lockfree),
+        ("orderings", check_orderings),
+        ("await_lock", check_await_lock),
+        ("bounded_buffer", check_bounded_buffer),
+        ("thread_pool", check_thread_pool),
+        ("event", check_event),
+        ("work_stealing", check_work_stealing),
+        ("interrupts", check_interrupts),
+        ("spsc_schedules", check_spsc_schedules),
+        ("typed_phases", check_typed_phases),
+        ("circuit_breaker", check_circuit_breaker),
+        ("network_sim", check_network_sim),
+        ("vector_clock", check_vector_clock),
+        ("delivery", check_delivery),
+        ("extensions", check_extensions),
+        ("hedge", check_hedge),
+        ("speculative", check_speculative),
+        ("progress", check_progress),
+        ("cost_model", check_cost_model),
+        ("soak", check_soak),
+        ("crash_only", check_crash_only),
+        ("sandbox", check_sandbox),
+        ("crash_report", check_crash_report),
+        ("exhaustion", check_exhaustion),
+        ("verify", check_verify),
+        ("trace", check_trace),
+        ("scheduler", check_scheduler),
+        ("console", check_console),
+        ("step", check_step),
+        ("aimd", check_aimd),
+    ];
+
+    println!("{}", narration!("selftest.subsystems_heading"));
+    let mut failures = 0;
+    for (name, check) in checks {
+        match check() {
+            Ok(()) => println!("{}", term::paint(Severity::Safe, &narration!("selftest.pass", name))),
+            Err(reason) => {
+                failures += 1;
+                println!("{}", term::paint(Severity::Violation, &narration!("selftest.fail", name, reason)));
+            }
+        }
+    }
+
+    // Which demonstrations say something meaningful on this host
+    println!("{}", narration!("selftest.demonstrations_heading"));
+    let contention = if host.cores >= 2 {
+        narration!("selftest.meaningful")
+    } else {
+        narration!("selftest.single_core")
+    };
+    println!("{}", narration!("selftest.contention_demos", contention));
+
+    let tick = config.ticks(1);
+    let timing = if tick.is_zero() {
+        narration!("selftest.no_delays")
+    } else if host.sleep_overshoot > tick {
+        narration!("selftest.overshoot", overshoot = format_duration(host.sleep_overshoot), tick = config.tick)
+    } else {
+        narration!("selftest.meaningful")
+    };
+    println!("{}", narration!("selftest.timing_demos", timing));
+
+    let wide = if cfg!(target_has_atomic = "64") {
+        narration!("selftest.meaningful")
+    } else {
+        narration!("selftest.no_native_atomics")
+    };
+    println!("{}", narration!("selftest.wide_counters", wide));
+    println!("{}", narration!("selftest.compile_time_demos"));
+
+    if failures == 0 {
+        println!("{}", narration!("selftest.all_passed"));
+        0
+    } else {
+        println!("{}", narration!("selftest.failed", failures));
+        1
+    }
+}
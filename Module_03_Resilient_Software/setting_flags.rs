@@ -0,0 +1,74 @@
+/*!
+ * Setting Flags
+ *
+ * The numeric flags that override an EnvConfig setting. thread_safe
+ * parses them, and the runner hands them on to every demo it starts as
+ * SAFETY_DEMO_<setting> variables. Both read this one table, so a new
+ * setting flag works through the runner as soon as it is added here.
+ *
+ * take_global_flags() applies them, and thread_safe's switches, to an
+ * EnvConfig; setting_args() turns an EnvConfig back into the flags, for
+ * a child process that must run with the same settings.
+ */
+
+use crate::console;
+use crate::env_config::{self, EnvConfig};
+
+// Numeric global flags and the settings they override
+pub const SETTING_FLAGS: [(&str, &str); 4] = [
+    ("--threads", "THREADS"),        // counter, mutex and stress worker threads
+    ("--iterations", "INCREMENTS"),  // increments or updates per thread
+    ("--sleep-ms", "TICK_MS"),       // base unit of every simulated delay
+    ("--seed", "SEED"),              // reproducible mutex and RwLock interleavings
+];
+
+// The SETTING_FLAGS that reproduce `config`, for a child process
+pub fn setting_args(config: &EnvConfig) -> Vec<String> {
+    let settings = config.settings();
+    SETTING_FLAGS
+        .iter()
+        .flat_map(|&(flag, name)| {
+            let value = settings.iter().find(|(key, _)| *key == name).map(|(_, value)| value.clone());
+            [flag.to_string(), value.unwrap_or_default()]
+        })
+        .collect()
+}
+
+// Global flags override the environment. --level and --interleaved may
+// appear anywhere; SETTING_FLAGS only before the command, since commands
+// such as `stress --iterations` and `experiment --threads` have their own
+pub fn take_global_flags(args: &mut Vec<String>, config: &mut EnvConfig) -> Result<(), String> {
+    while let Some(index) = args.iter().position(|arg| arg == "--level") {
+        if index + 1 >= args.len() {
+            return Err(format!("--level needs a value ({})", env_config::LEVEL_CHOICES));
+        }
+        let value = args.remove(index + 1);
+        args.remove(index);
+        config.level = env_config::parse_level("--level", &value).map_err(|e| e.to_string())?;
+    }
+    while let Some(index) = args.iter().position(|arg| arg == "--interleaved") {
+        args.remove(index);
+        config.output = console::OutputMode::Interleaved;
+    }
+    while let Some(index) = args.iter().position(|arg| arg == "--interactive") {
+        args.remove(index);
+        config.interactive = true;
+    }
+    while let Some(index) = args.iter().position(|arg| arg == "--verify") {
+        args.remove(index);
+        config.verify = true;
+    }
+    while let Some(index) = args.iter().position(|arg| arg == "--allow-hang") {
+        args.remove(index);
+        config.allow_hang = true;
+    }
+    while let Some(&(flag, name)) = args.first().and_then(|arg| SETTING_FLAGS.iter().find(|(flag, _)| flag == arg)) {
+        if args.len() < 2 {
+            return Err(format!("{} needs a value", flag));
+        }
+        let value = args.remove(1);
+        args.remove(0);
+        config.set(name, flag, value).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,213 @@
+/*!
+ * Simulated Single CPU
+ *
+ * Real OS priorities cannot be set portably (and a multi-core host would
+ * hide the effect anyway), so priority-sensitive demos run their threads
+ * on a simulated CPU. Each task is a real thread, but it only makes
+ * progress through `tick`, and the CPU hands every tick to the highest-
+ * priority ready task (first come, first served among equals). The
+ * resulting schedule is deterministic and is kept as a trace, one
+ * character per tick ('.' for idle).
+ *
+ * Other policies pick a seeded random ready task each tick, or follow a
+ * recorded trace - which replays that exact interleaving. explore()
+ * scripts one run after another until every interleaving has been played.
+ */
+
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+use crate::rng::SeededRng;
+
+// How the CPU chooses among ready tasks
+#[derive(Debug, Clone, Default)]
+pub enum Policy {
+    #[default]
+    Priority,            // highest priority, first come first served
+    Random(u64),         // a seeded random choice each tick
+    Scripted(Vec<char>), // the task at this tick of a recorded trace
+}
+
+#[derive(Debug, Default)]
+struct CpuState {
+    policy: Policy,
+    clock: u64,
+    ready: Vec<(char, u8)>,          // runnable tasks and their priorities
+    arrivals: Vec<(u64, char, u8)>,  // tasks that become ready at a tick
+    blocked: Vec<(char, u8)>,        // tasks waiting for the shared resource
+    trace: String,
+}
+
+impl CpuState {
+    // Admit due arrivals; if nothing can run, let the clock idle forward
+    fn settle(&mut self) {
+        loop {
+            let clock = self.clock;
+            let (due, later): (Vec<_>, Vec<_>) = self.arrivals.drain(..).partition(|(at, _, _)| *at <= clock);
+            self.arrivals = later;
+            self.ready.extend(due.into_iter().map(|(_, task, priority)| (task, priority)));
+
+            if !self.ready.is_empty() || self.arrivals.is_empty() {
+                return;
+            }
+            self.clock += 1;
+            self.trace.push('.');
+        }
+    }
+
+    // The task that gets the current tick. Depends only on the clock and
+    // the ready set, so the choice is the same whichever thread asks.
+    fn running(&self) -> Option<char> {
+        let ready = |task: &char| self.ready.iter().any(|(candidate, _)| candidate == task);
+        match &self.policy {
+            Policy::Priority => self.highest_priority(),
+            Policy::Random(_) if self.ready.is_empty() => None,
+            Policy::Random(seed) => {
+                let mut rng = SeededRng::new(seed.wrapping_add(self.clock));
+                Some(self.ready[rng.range(0, self.ready.len() - 1)].0)
+            }
+            // Off the script (the trace came from different code): fall back
+            Policy::Scripted(script) => match script.get(self.clock as usize) {
+                Some(task) if ready(task) => Some(*task),
+                _ => self.highest_priority(),
+            },
+        }
+    }
+
+    // Highest priority wins; among equals, the one that became ready first
+    fn highest_priority(&self) -> Option<char> {
+        let mut best: Option<(char, u8)> = None;
+        for &(task, priority) in &self.ready {
+            if best.is_none_or(|(_, top)| priority > top) {
+                best = Some((task, priority));
+            }
+        }
+        best.map(|(task, _)| task)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SimCpu {
+    state: Mutex<CpuState>,
+    changed: Condvar,
+}
+
+impl SimCpu {
+    pub fn new() -> Self {
+        SimCpu::default()
+    }
+
+    pub fn with_policy(policy: Policy) -> Self {
+        let cpu = SimCpu::default();
+        cpu.lock().policy = policy;
+        cpu
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CpuState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Block the calling thread until `task` is the one the CPU runs
+    fn wait_turn<'a>(&self, mut state: MutexGuard<'a, CpuState>, task: char) -> MutexGuard<'a, CpuState> {
+        loop {
+            state.settle();
+            if state.running() == Some(task) {
+                return state;
+            }
+            state = self.changed.wait(state).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    // `task` becomes ready at tick `at` (register before spawning threads)
+    pub fn arrive(&self, task: char, priority: u8, at: u64) {
+        let mut state = self.lock();
+        state.arrivals.push((at, task, priority));
+        state.settle();
+        self.changed.notify_all();
+    }
+
+    // Run one unit of work for `task` once it is scheduled
+    pub fn tick(&self, task: char) {
+        self.tick_with(task, || ());
+    }
+
+    // Like tick, but `work` runs during the tick while no other task can,
+    // so its effects happen in trace order
+    pub fn tick_with<R>(&self, task: char, work: impl FnOnce() -> R) -> R {
+        let mut state = self.wait_turn(self.lock(), task);
+        let result = work();
+        state.clock += 1;
+        state.trace.push(task);
+        state.settle();
+        self.changed.notify_all();
+        result
+    }
+
+    // `task` found the resource taken: it leaves the CPU until released,
+    // then returns once it is scheduled again
+    pub fn block_on_resource(&self, task: char) {
+        let mut state = self.lock();
+        if let Some(index) = state.ready.iter().position(|(ready, _)| *ready == task) {
+            let entry = state.ready.remove(index);
+            state.blocked.push(entry);
+        }
+        self.changed.notify_all();
+        drop(self.wait_turn(state, task));
+    }
+
+    // The resource was released: every task blocked on it is ready again
+    pub fn release_resource(&self) {
+        let mut state = self.lock();
+        let woken: Vec<_> = state.blocked.drain(..).collect();
+        state.ready.extend(woken);
+        self.changed.notify_all();
+    }
+
+    // `from` stops running and `to` becomes ready (a request to a server
+    // thread, or the server's reply)
+    pub fn handoff(&self, from: char, to: char, priority: u8) {
+        let mut state = self.lock();
+        state.ready.retain(|(ready, _)| *ready != from);
+        state.ready.push((to, priority));
+        state.settle();
+        self.changed.notify_all();
+    }
+
+    pub fn finish(&self, task: char) {
+        let mut state = self.lock();
+        state.ready.retain(|(ready, _)| *ready != task);
+        state.settle();
+        self.changed.notify_all();
+    }
+
+    pub fn now(&self) -> u64 {
+        self.lock().clock
+    }
+
+    pub fn trace(&self) -> String {
+        self.lock().trace.clone()
+    }
+}
+
+// Play every interleaving of a run whose tasks all arrive at tick 0 and
+// never block: `run` plays the schedule a policy picks and returns its
+// trace. A task is ready at a tick exactly when it still has ticks left
+// in the trace, so each run shows where another task could have gone
+// instead; every such prefix is scripted in turn, depth first. The number
+// of runs - one per interleaving - or the first error `run` reports
+pub fn explore<E>(mut run: impl FnMut(Policy) -> Result<String, E>) -> Result<usize, E> {
+    let mut prefixes = vec![Vec::new()];
+    let mut runs = 0;
+    while let Some(prefix) = prefixes.pop() {
+        let trace: Vec<char> = run(Policy::Scripted(prefix.clone()))?.chars().collect();
+        runs += 1;
+        for at in prefix.len()..trace.len() {
+            let mut others: Vec<char> = trace[at + 1..].iter().copied().filter(|&task| task != trace[at]).collect();
+            others.sort_unstable();
+            others.dedup();
+            for other in others {
+                prefixes.push([&trace[..at], &[other]].concat());
+            }
+        }
+    }
+    Ok(runs)
+}
@@ -0,0 +1,147 @@
+/*!
+ * Slab Store with Stable Keys
+ *
+ * A Vec index is not a stable name for an element: remove one and every
+ * later element shifts down, so an index saved earlier now names a
+ * different element - or none. A reference into the Vec cannot be kept
+ * across the removal either; the borrow checker refuses (see
+ * tests/compile_fail/slab_remove_while_borrowed.rs).
+ *
+ * Slab<T> hands out a Key on insert instead. Removing an element leaves
+ * its slot vacant and pushes it on a free list, so nothing moves and
+ * every other key stays valid. The next insert reuses the slot, which
+ * is where a plain index would go wrong a second time: the old key would
+ * now name the new element. Each slot therefore counts its generation,
+ * bumped on every removal, and a Key carries the generation it was
+ * issued for - a stale key finds nothing instead of the wrong element.
+ */
+
+use std::fmt;
+use std::mem;
+
+// Names one element for as long as it stays in the slab
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    index: usize,
+    generation: u32,
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}v{}", self.index, self.generation)
+    }
+}
+
+#[derive(Debug)]
+enum Slot<T> {
+    Occupied { generation: u32, value: T },
+    Vacant { generation: u32, next_free: Option<usize> },  // the free list runs through vacant slots
+}
+
+#[derive(Debug)]
+pub struct Slab<T> {
+    slots: Vec<Slot<T>>,
+    free: Option<usize>,  // most recently vacated slot
+    len: usize,
+}
+
+impl<T> Default for Slab<T> {
+    fn default() -> Self {
+        Slab::new()
+    }
+}
+
+impl<T> Slab<T> {
+    pub fn new() -> Self {
+        Slab { slots: Vec::new(), free: None, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Slots allocated so far, occupied or vacant
+    pub fn slots(&self) -> usize {
+        self.slots.len()
+    }
+
+    // Store `value` in a vacant slot if there is one, else in a new slot
+    pub fn insert(&mut self, value: T) -> Key {
+        self.len += 1;
+        match self.free {
+            Some(index) => {
+                let generation = match self.slots[index] {
+                    Slot::Vacant { generation, next_free } => {
+                        self.free = next_free;
+                        generation
+                    }
+                    Slot::Occupied { .. } => unreachable!("free list points at an occupied slot"),
+                };
+                self.slots[index] = Slot::Occupied { generation, value };
+                Key { index, generation }
+            }
+            None => {
+                self.slots.push(Slot::Occupied { generation: 0, value });
+                Key { index: self.slots.len() - 1, generation: 0 }
+            }
+        }
+    }
+
+    // Take the element out; its slot joins the free list under a new
+    // generation, so `key` (and every copy of it) goes stale
+    pub fn remove(&mut self, key: Key) -> Option<T> {
+        self.get(key)?;
+        let vacant = Slot::Vacant { generation: key.generation.wrapping_add(1), next_free: self.free };
+        let Slot::Occupied { value, .. } = mem::replace(&mut self.slots[key.index], vacant) else {
+            unreachable!("get found the slot occupied")
+        };
+        self.free = Some(key.index);
+        self.len -= 1;
+        Some(value)
+    }
+
+    pub fn get(&self, key: Key) -> Option<&T> {
+        match self.slots.get(key.index)? {
+            Slot::Occupied { generation, value } if *generation == key.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: Key) -> Option<&mut T> {
+        match self.slots.get_mut(key.index)? {
+            Slot::Occupied { generation, value } if *generation == key.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, key: Key) -> bool {
+        self.get(key).is_some()
+    }
+
+    // Every element with its key, in slot order
+    pub fn iter(&self) -> impl Iterator<Item = (Key, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| match slot {
+            Slot::Occupied { generation, value } => Some((Key { index, generation: *generation }, value)),
+            Slot::Vacant { .. } => None,
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Key, &mut T)> {
+        self.slots.iter_mut().enumerate().filter_map(|(index, slot)| match slot {
+            Slot::Occupied { generation, value } => Some((Key { index, generation: *generation }, value)),
+            Slot::Vacant { .. } => None,
+        })
+    }
+
+    // Remove every element `keep` returns false for; the others keep their keys
+    pub fn retain(&mut self, mut keep: impl FnMut(Key, &mut T) -> bool) {
+        let doomed: Vec<Key> = self.iter_mut().filter_map(|(key, value)| (!keep(key, value)).then_some(key)).collect();
+        for key in doomed {
+            self.remove(key);
+        }
+    }
+}
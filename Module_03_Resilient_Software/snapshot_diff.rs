@@ -0,0 +1,144 @@
+/*!
+ * Snapshot Diffing
+ *
+ * "sum matches data: sum 45 vs data total 44" says that an invariant
+ * broke, not what the state looked like when it did. diff() compares two
+ * immutable snapshots entry by entry and reports what was added, removed
+ * or changed between them, so a violation can be explained in terms of
+ * concrete state: which value appeared without its sum being updated,
+ * which registry entry went missing.
+ *
+ * Anything that can list its state as named entries (Entries) can be
+ * diffed: SharedData and its snapshots as their fields plus one entry per
+ * value, and any BTreeMap - such as the invariant tally - as its keys.
+ */
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use resilient_core::{DataSnapshot, SharedData};
+
+use crate::static_check;
+
+const SHOWN: usize = static_check::nonzero(8);  // changes listed before "... and N more"
+
+// State as named, printable entries
+pub trait Entries {
+    fn entries(&self) -> BTreeMap<String, String>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added { key: String, value: String },
+    Removed { key: String, value: String },
+    Changed { key: String, before: String, after: String },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { key, value } => write!(f, "+ {} = {}", key, value),
+            Change::Removed { key, value } => write!(f, "- {} = {}", key, value),
+            Change::Changed { key, before, after } => write!(f, "~ {}: {} -> {}", key, before, after),
+        }
+    }
+}
+
+// Every change from one snapshot to the next, in key order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diff {
+    pub changes: Vec<Change>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+// The first SHOWN changes, separated by "; "
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no changes");
+        }
+        let shown: Vec<String> = self.changes.iter().take(SHOWN).map(Change::to_string).collect();
+        write!(f, "{}", shown.join("; "))?;
+        if self.changes.len() > SHOWN {
+            write!(f, "; ... and {} more", self.changes.len() - SHOWN)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn diff<T: Entries + ?Sized>(before: &T, after: &T) -> Diff {
+    let (before, mut after) = (before.entries(), after.entries());
+    let mut changes = Vec::new();
+    for (key, old) in before {
+        match after.remove(&key) {
+            None => changes.push(Change::Removed { key, value: old }),
+            Some(new) if new != old => changes.push(Change::Changed { key, before: old, after: new }),
+            Some(_) => {}
+        }
+    }
+    changes.extend(after.into_iter().map(|(key, value)| Change::Added { key, value }));
+    // BTreeMap order puts data[10] before data[2]; sort by the entries' own order
+    changes.sort_by(|a, b| order(key_of(a)).cmp(&order(key_of(b))));
+    Diff { changes }
+}
+
+fn key_of(change: &Change) -> &str {
+    match change {
+        Change::Added { key, .. } | Change::Removed { key, .. } | Change::Changed { key, .. } => key,
+    }
+}
+
+// Sort "name[12]" by name, then numerically by index
+fn order(key: &str) -> (&str, Option<usize>, &str) {
+    match key.split_once('[') {
+        Some((name, rest)) => (name, rest.trim_end_matches(']').parse().ok(), rest),
+        None => (key, None, ""),
+    }
+}
+
+fn data_entries(entries: &mut BTreeMap<String, String>, data: &[i32]) {
+    entries.insert("len".to_string(), data.len().to_string());
+    for (index, value) in data.iter().enumerate() {
+        entries.insert(format!("data[{}]", index), value.to_string());
+    }
+}
+
+impl Entries for SharedData {
+    fn entries(&self) -> BTreeMap<String, String> {
+        let mut entries = BTreeMap::new();
+        entries.insert("sum".to_string(), self.sum().to_string());
+        entries.insert("processing".to_string(), self.is_processing().to_string());
+        data_entries(&mut entries, self.values());
+        entries
+    }
+}
+
+impl Entries for DataSnapshot {
+    fn entries(&self) -> BTreeMap<String, String> {
+        let mut entries = BTreeMap::new();
+        entries.insert("sum".to_string(), self.sum().to_string());
+        data_entries(&mut entries, self.values());
+        entries
+    }
+}
+
+impl<K: fmt::Display, V: fmt::Debug> Entries for BTreeMap<K, V> {
+    fn entries(&self) -> BTreeMap<String, String> {
+        self.iter().map(|(key, value)| (key.to_string(), format!("{:?}", value))).collect()
+    }
+}
+
+// The consistent state `data` holds values for: what a SharedData with
+// the same values would look like had every add_value completed
+pub fn implied(data: &SharedData) -> SharedData {
+    let mut consistent = SharedData::new();
+    for &value in data.values() {
+        consistent.add_value(value);
+    }
+    consistent
+}
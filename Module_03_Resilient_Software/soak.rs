@@ -0,0 +1,246 @@
+/*!
+ * Soak Testing
+ *
+ * `stress` finds races in seconds; `soak` finds what only shows up after
+ * hours: memory that is never freed, file descriptors that are never
+ * closed, threads that are never joined. It runs the stress workloads
+ * (with seeded random sizes and faults) in a loop for the whole duration
+ * and samples the process between runs:
+ *
+ *   heap     bytes still allocated, from the tracking allocator
+ *   rss      resident memory, from /proc/self/statm
+ *   fds      open file descriptors, from /proc/self/fd
+ *   threads  live threads, from /proc/self/status
+ *
+ * The /proc metrics are only sampled on Linux.
+ *
+ * A metric is flagged when its floor keeps rising. Allocators and thread
+ * pools make any single sample noisy, so the samples after warm-up are
+ * split into quarters. A leak raises the minimum of every quarter over
+ * the one before. A metric is flagged when all four minima rise, by more
+ * than the metric's tolerance overall.
+ */
+
+use std::fs;
+use std::time::{Duration, Instant};
+
+use crate::alloc_track;
+use crate::event_log;
+use crate::fault;
+use crate::progress::{self, Goal, Progress};
+use crate::rng::SeededRng;
+use crate::stress::{self, Workload, WORKLOADS};
+use crate::term::{self, Severity};
+
+const WARMUP_SAMPLES: usize = 2;  // caches and pools fill up first
+const QUARTERS: usize = 4;
+const MIB: u64 = 1 << 20;
+
+struct Metric {
+    name: &'static str,
+    bytes: bool,     // shown in KiB or MiB
+    tolerance: u64,  // growth of the floor that is still not a leak
+    read: fn() -> Option<u64>,
+}
+
+const METRICS: [Metric; 4] = [
+    Metric { name: "heap", bytes: true, tolerance: MIB, read: heap_bytes },
+    Metric { name: "rss", bytes: true, tolerance: 4 * MIB, read: rss_bytes },
+    Metric { name: "fds", bytes: false, tolerance: 0, read: open_fds },
+    Metric { name: "threads", bytes: false, tolerance: 0, read: live_threads },
+];
+
+fn heap_bytes() -> Option<u64> {
+    Some(alloc_track::live_bytes() as u64)
+}
+
+#[cfg(target_os = "linux")]
+fn rss_bytes() -> Option<u64> {
+    // statm: size resident shared ... in pages
+    let statm = fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    // SAFETY: sysconf only reads a system constant
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(pages * u64::try_from(page_size).ok()?)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn rss_bytes() -> Option<u64> {
+    None
+}
+
+fn open_fds() -> Option<u64> {
+    // Includes the descriptor read_dir itself holds, the same in every sample
+    Some(fs::read_dir("/proc/self/fd").ok()?.count() as u64)
+}
+
+fn live_threads() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    status.lines().find_map(|line| line.strip_prefix("Threads:")?.trim().parse().ok())
+}
+
+fn show(metric: &Metric, value: Option<u64>) -> String {
+    match value {
+        None => "n/a".to_string(),
+        Some(value) if metric.bytes && value < MIB => format!("{:.1} KiB", value as f64 / 1024.0),
+        Some(value) if metric.bytes => format!("{:.1} MiB", value as f64 / MIB as f64),
+        Some(value) => value.to_string(),
+    }
+}
+
+// True when the floor of `samples` (after warm-up) rises in every quarter
+// and by more than `tolerance` in all
+pub fn floor_keeps_rising(samples: &[u64], tolerance: u64) -> bool {
+    let settled = samples.get(WARMUP_SAMPLES..).unwrap_or(&[]);
+    if settled.len() < QUARTERS {
+        return false;
+    }
+    let quarter = settled.len() / QUARTERS;
+    let floors: Vec<u64> = (0..QUARTERS)
+        .map(|index| {
+            let end = if index + 1 == QUARTERS { settled.len() } else { (index + 1) * quarter };
+            settled[index * quarter..end].iter().copied().min().unwrap_or(0)
+        })
+        .collect();
+    floors.windows(2).all(|pair| pair[1] > pair[0]) && floors[QUARTERS - 1] - floors[0] > tolerance
+}
+
+struct SoakOptions {
+    duration: Duration,
+    sample_every: Duration,
+    seed: u64,
+    fault_rate: f64,
+    workloads: Vec<(&'static str, Workload)>,
+}
+
+fn parse_options(args: &[String]) -> Result<SoakOptions, String> {
+    let mut options = SoakOptions {
+        duration: Duration::from_secs(3600),
+        sample_every: Duration::from_secs(30),
+        seed: SeededRng::time_seed(),
+        fault_rate: 0.1,
+        workloads: WORKLOADS.to_vec(),
+    };
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{} needs a value", flag))?;
+        let invalid = || format!("invalid value for {}: {}", flag, value);
+        match flag.as_str() {
+            "--duration" => options.duration = Duration::from_secs(value.parse().map_err(|_| invalid())?),
+            "--sample" => {
+                let secs: u64 = value.parse().map_err(|_| invalid())?;
+                if secs == 0 {
+                    return Err(invalid());
+                }
+                options.sample_every = Duration::from_secs(secs);
+            }
+            "--seed" => options.seed = value.parse().map_err(|_| invalid())?,
+            "--fault-rate" => {
+                options.fault_rate = value.parse().map_err(|_| invalid())?;
+                if !(0.0..=1.0).contains(&options.fault_rate) {
+                    return Err(invalid());
+                }
+            }
+            "--demos" => options.workloads = stress::select_workloads(value)?,
+            other => return Err(format!("unknown option {}", other)),
+        }
+    }
+
+    if options.workloads.is_empty() {
+        return Err("no workloads selected".to_string());
+    }
+    Ok(options)
+}
+
+// `soak [--duration <s>] [--sample <s>] [--seed <n>] [--fault-rate <p>] [--demos a,b]`
+// - returns the exit code
+pub fn soak_command(args: &[String]) -> i32 {
+    let options = match parse_options(args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            eprintln!("{}", narration!("soak.usage"));
+            return 2;
+        }
+    };
+
+    println!("{}", narration!("soak.heading", seconds = options.duration.as_secs(),
+                              sample = options.sample_every.as_secs(), seed = options.seed));
+    println!("{}", narration!("soak.samples_header"));
+
+    fault::install_quiet_panic_hook();
+    let mut rng = SeededRng::new(options.seed);
+    // Sized up front so the soak's own bookkeeping does not look like a leak
+    let capacity = (options.duration.as_secs() / options.sample_every.as_secs()) as usize + 2;
+    let mut samples: Vec<[Option<u64>; 4]> = Vec::with_capacity(capacity);
+    let mut violations = 0;
+    let mut runs = 0;
+    let start = Instant::now();
+    let mut next_sample = Duration::ZERO;
+    let progress = Progress::start("soak", Goal::Time(options.duration));
+
+    loop {
+        let elapsed = start.elapsed();
+        if elapsed >= next_sample && samples.len() < capacity {
+            // Between runs, so every workload thread has been joined
+            let sample = METRICS.each_ref().map(|metric| (metric.read)());
+            progress.println(&format!(
+                "{:>8} {:>8} {:>12} {:>12} {:>6} {:>8}",
+                progress::clock(elapsed), runs,
+                show(&METRICS[0], sample[0]), show(&METRICS[1], sample[1]),
+                show(&METRICS[2], sample[2]), show(&METRICS[3], sample[3]),
+            ));
+            samples.push(sample);
+            while next_sample <= elapsed {
+                next_sample += options.sample_every;
+            }
+        }
+        if elapsed >= options.duration {
+            break;
+        }
+
+        let (name, workload, params, plan) = stress::next_run(&mut rng, &options.workloads, options.fault_rate);
+        event_log::demo_started(name);
+        progress.note(name);
+        violations += workload(&params, &plan).len();
+        runs += 1;
+        progress.tick();
+    }
+    progress.finish();
+
+    println!("{}", narration!("soak.totals", runs, violations, allocations = alloc_track::live_blocks()));
+    println!("{}", narration!("soak.metrics_header"));
+    let mut growing = 0;
+    for (index, metric) in METRICS.iter().enumerate() {
+        let series: Vec<u64> = samples.iter().filter_map(|sample| sample[index]).collect();
+        let (first, last, peak) = (series.first().copied(), series.last().copied(), series.iter().max().copied());
+        let line = format!("{:<8} {:>12} {:>12} {:>12}", metric.name,
+                           show(metric, first), show(metric, last), show(metric, peak));
+        if series.is_empty() {
+            println!("{}", narration!("soak.not_available", line));
+            continue;
+        }
+        let steady = event_log::check_invariant(
+            &format!("{} does not keep growing", metric.name),
+            !floor_keeps_rising(&series, metric.tolerance),
+        );
+        if steady {
+            println!("{}", term::paint(Severity::Safe, &narration!("soak.steady", line)));
+        } else {
+            growing += 1;
+            println!("{}", term::paint(Severity::Violation, &narration!("soak.growing", line)));
+        }
+    }
+
+    if samples.len() < WARMUP_SAMPLES + QUARTERS {
+        println!("{}", narration!("soak.too_few_samples", samples = samples.len()));
+    }
+    if growing == 0 && violations == 0 {
+        0
+    } else {
+        println!("{}", narration!("soak.reproduce", seed = options.seed, seconds = options.duration.as_secs(),
+                                  sample = options.sample_every.as_secs()));
+        1
+    }
+}
@@ -0,0 +1,175 @@
+/*!
+ * Spawn Policy
+ *
+ * `thread::spawn` panics when the OS will not create a thread - no
+ * address space for its stack, or a process or thread limit reached.
+ * The demos in sections/ spawn through this module instead, and
+ * each call site picks what happens when no thread can be had:
+ *
+ *   spawn           run the work on the caller's thread, right away: the
+ *                   same work gets done by fewer threads. Right for work
+ *                   that never waits on something the caller does later.
+ *   spawn_required  the work has to run alongside the caller - it loops
+ *                   until the caller sets a flag, or waits for a thread
+ *                   started after it. The caller gets a SpawnError and
+ *                   skips what needed the thread.
+ *   spawn_in        queue the work on a ThreadPool when the demo has one
+ *                   (SAFETY_DEMO_POOL), and `spawn` it otherwise. The
+ *                   pool's threads already exist, so nothing can fail.
+ *
+ * Either way the caller joins a Worker, which behaves like a JoinHandle:
+ * a panic in inline work comes back from join() just as a thread's would.
+ * Threads started here are console workers: their lines are batched.
+ *
+ * inject_failures() makes every Nth spawn fail on purpose
+ * (SAFETY_DEMO_FAIL_SPAWNS), so the fallbacks run without exhausting the
+ * machine.
+ */
+
+use std::fmt;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle, Scope, ScopedJoinHandle};
+
+use resilient_core::{JobHandle, ThreadPool};
+
+use crate::console;
+use crate::trace;
+
+static FAIL_EVERY: AtomicUsize = AtomicUsize::new(0);  // 0: never
+static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug)]
+pub struct SpawnError {
+    pub source: io::Error,
+}
+
+impl fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cannot start a thread: {}", self.source)
+    }
+}
+
+impl std::error::Error for SpawnError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+// A thread's handle, or the result of work that ran inline instead
+pub enum Worker<'scope, T> {
+    Thread(JoinHandle<T>),
+    Scoped(ScopedJoinHandle<'scope, T>),
+    Inline(thread::Result<T>),
+    Pooled(JobHandle<T>),
+}
+
+impl<T> Worker<'_, T> {
+    pub fn join(self) -> thread::Result<T> {
+        match self {
+            Worker::Thread(handle) => handle.join(),
+            Worker::Scoped(handle) => handle.join(),
+            Worker::Inline(result) => result,
+            Worker::Pooled(job) => job.join(),
+        }
+    }
+}
+
+fn injected_failure() -> Option<io::Error> {
+    let every = FAIL_EVERY.load(Ordering::Relaxed);
+    let fail = every > 0 && ATTEMPTS.fetch_add(1, Ordering::Relaxed) % every == every - 1;
+    fail.then(|| io::Error::other("injected spawn failure"))
+}
+
+fn run_inline<T>(source: io::Error, work: impl FnOnce() -> T) -> thread::Result<T> {
+    narrate!("spawn_policy.running_inline", error = SpawnError { source });
+    panic::catch_unwind(AssertUnwindSafe(work))
+}
+
+// Spawning consumes the closure even when it fails; the work waits in a
+// slot so the fallback can take it back
+fn take<F>(slot: &Mutex<Option<F>>) -> F {
+    slot.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take().expect("the work runs once")
+}
+
+// Start `work` on a new thread, or run it inline if there is none
+pub fn spawn<F, T>(work: F) -> Worker<'static, T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    if let Some(error) = injected_failure() {
+        return Worker::Inline(run_inline(error, work));
+    }
+    let slot = Arc::new(Mutex::new(Some(work)));
+    let parked = Arc::clone(&slot);
+    let context = trace::worker_context();
+    match thread::Builder::new().spawn(move || context.enter(|| console::worker(take(&parked)))) {
+        Ok(handle) => Worker::Thread(handle),
+        Err(error) => Worker::Inline(run_inline(error, take(&slot))),
+    }
+}
+
+// `spawn` as a job on `pool`, if there is one: it waits for an idle pool
+// thread instead of starting a thread of its own
+pub fn spawn_in<F, T>(pool: Option<&ThreadPool>, work: F) -> Worker<'static, T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let Some(pool) = pool else { return spawn(work) };
+    let context = trace::worker_context();
+    Worker::Pooled(pool.execute(move || context.enter(|| console::worker(work))))
+}
+
+// `spawn` for a thread::scope
+pub fn spawn_scoped<'scope, F, T>(scope: &'scope Scope<'scope, '_>, work: F) -> Worker<'scope, T>
+where
+    F: FnOnce() -> T + Send + 'scope,
+    T: Send + 'scope,
+{
+    if let Some(error) = injected_failure() {
+        return Worker::Inline(run_inline(error, work));
+    }
+    let slot = Arc::new(Mutex::new(Some(work)));
+    let parked = Arc::clone(&slot);
+    let context = trace::worker_context();
+    match thread::Builder::new().spawn_scoped(scope, move || context.enter(|| console::worker(take(&parked)))) {
+        Ok(handle) => Worker::Scoped(handle),
+        Err(error) => Worker::Inline(run_inline(error, take(&slot))),
+    }
+}
+
+// Start `work` on a new thread, or report that it could not be started
+pub fn spawn_required<F, T>(work: F) -> Result<Worker<'static, T>, SpawnError>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    if let Some(source) = injected_failure() {
+        return Err(SpawnError { source });
+    }
+    let context = trace::worker_context();
+    thread::Builder::new()
+        .spawn(move || context.enter(|| console::worker(work)))
+        .map(Worker::Thread)
+        .map_err(|source| SpawnError { source })
+}
+
+// Make every `every`th spawn fail (0: none) until the guard is dropped
+pub fn inject_failures(every: usize) -> InjectedFailures {
+    ATTEMPTS.store(0, Ordering::Relaxed);
+    InjectedFailures { previous: FAIL_EVERY.swap(every, Ordering::Relaxed) }
+}
+
+pub struct InjectedFailures {
+    previous: usize,
+}
+
+impl Drop for InjectedFailures {
+    fn drop(&mut self) {
+        FAIL_EVERY.store(self.previous, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,238 @@
+/*!
+ * Speculative Execution
+ *
+ * When several implementations can produce the same answer - a cache and
+ * a recomputation, two replicas - running them all at once and keeping
+ * the first success trades extra work for lower latency. race() does
+ * that on scoped threads: the first Ok wins, and the others are cancelled
+ * through the CancellationToken that task_scope uses. Cancellation is
+ * cooperative, so a loser keeps running until it next checks the token.
+ *
+ * That is harmless for pure computations, but a loser that writes to
+ * shared state as it goes leaves its writes behind, and they add to the
+ * winner's. race_commit() keeps side effects safe by staging them. Every
+ * alternative builds its result from the same pinned VersionedStore
+ * snapshot, without touching the store. Only the winner's result is
+ * committed, by try_commit on that snapshot's version. A loser's staged
+ * work is dropped with it. If another writer committed in the meantime,
+ * the race commits nothing and reports the Conflict.
+ */
+
+use std::fmt;
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::spawn_policy;
+use crate::static_check;
+use crate::task_scope::{CancellationToken, TaskError};
+use crate::versioned_store::{Conflict, Snapshot, VersionedStore};
+
+// One way of producing the answer; it should check the token as it goes
+pub type Alternative<'a, T, E> = Box<dyn FnOnce(&CancellationToken) -> Result<T, TaskError<E>> + Send + 'a>;
+
+// One way of building the next state from a snapshot, without writing it
+pub type Staged<'a, S, E> = Box<dyn FnOnce(&S, &CancellationToken) -> Result<S, TaskError<E>> + Send + 'a>;
+
+// The first success, and what became of the other alternatives
+#[derive(Debug)]
+pub struct Won<T, E> {
+    pub winner: String,
+    pub value: T,
+    pub cancelled: Vec<String>,     // stopped at a token check
+    pub discarded: Vec<String>,     // succeeded too late; result dropped
+    pub failed: Vec<(String, E)>,
+    pub panicked: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum RaceError<E> {
+    AllFailed { failed: Vec<(String, E)>, panicked: Vec<String> },
+    Conflict(Conflict),  // the winner's result was built on an outdated version
+}
+
+impl<E: fmt::Display> fmt::Display for RaceError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RaceError::AllFailed { failed, panicked } => {
+                let failed: Vec<String> = failed.iter().map(|(name, error)| format!("{} ({})", name, error)).collect();
+                write!(f, "no alternative succeeded; failed: {}", failed.join(", "))?;
+                if !panicked.is_empty() {
+                    write!(f, "; panicked: {}", panicked.join(", "))?;
+                }
+                Ok(())
+            }
+            RaceError::Conflict(conflict) => write!(f, "winner not committed: {}", conflict),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for RaceError<E> {}
+
+// Run every alternative at once; the first to succeed wins and cancels the rest
+pub fn race<T: Send, E: Send>(alternatives: Vec<(&str, Alternative<'_, T, E>)>) -> Result<Won<T, E>, RaceError<E>> {
+    let names: Vec<String> = alternatives.iter().map(|(name, _)| name.to_string()).collect();
+    let token = CancellationToken::new();
+    let (sender, results) = mpsc::channel();
+    let (finished, panicked) = thread::scope(|scope| {
+        let workers: Vec<_> = alternatives
+            .into_iter()
+            .enumerate()
+            .map(|(index, (_, run))| {
+                let (token, sender) = (token.clone(), sender.clone());
+                spawn_policy::spawn_scoped(scope, move || {
+                    let result = run(&token);
+                    if result.is_ok() {
+                        token.cancel();
+                    }
+                    let _ = sender.send((index, result));
+                })
+            })
+            .collect();
+        drop(sender);
+        // In the order they finished; a panicked alternative never reports
+        let finished: Vec<_> = results.iter().collect();
+        let panicked: Vec<String> = workers
+            .into_iter()
+            .zip(&names)
+            .filter_map(|(worker, name)| worker.join().is_err().then(|| name.clone()))
+            .collect();
+        (finished, panicked)
+    });
+
+    let (mut winner, mut cancelled, mut discarded, mut failed) = (None, Vec::new(), Vec::new(), Vec::new());
+    for (index, result) in finished {
+        let name = names[index].clone();
+        match result {
+            Ok(value) if winner.is_none() => winner = Some((name, value)),
+            Ok(_) => discarded.push(name),
+            Err(TaskError::Cancelled) => cancelled.push(name),
+            Err(TaskError::Failed(error)) => failed.push((name, error)),
+        }
+    }
+    match winner {
+        Some((winner, value)) => Ok(Won { winner, value, cancelled, discarded, failed, panicked }),
+        None => Err(RaceError::AllFailed { failed, panicked }),
+    }
+}
+
+// Race alternatives that each stage the next state from `base`, and commit
+// only the winner's; Ok holds the committed version
+pub fn race_commit<S: Send + Sync, E: Send>(
+    store: &VersionedStore<S>,
+    base: &Snapshot<'_, S>,
+    alternatives: Vec<(&str, Staged<'_, S, E>)>,
+) -> Result<Won<u64, E>, RaceError<E>> {
+    let state: &S = base;
+    let alternatives = alternatives
+        .into_iter()
+        .map(|(name, staged)| (name, Box::new(move |token: &CancellationToken| staged(state, token)) as Alternative<'_, S, E>))
+        .collect();
+    let won = race(alternatives)?;
+    let version = store.try_commit(base.version(), won.value).map_err(RaceError::Conflict)?;
+    Ok(Won {
+        winner: won.winner,
+        value: version,
+        cancelled: won.cancelled,
+        discarded: won.discarded,
+        failed: won.failed,
+        panicked: won.panicked,
+    })
+}
+
+// The demo workload: a batch of transfers between accounts
+
+pub const ACCOUNTS: usize = static_check::nonzero(6);
+pub const OPENING_BALANCE: i64 = 1000;
+const TRANSFERS: usize = static_check::nonzero(12);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Transfer {
+    pub from: usize,
+    pub to: usize,
+    pub amount: i64,
+}
+
+pub fn transfers() -> Vec<Transfer> {
+    (0..TRANSFERS)
+        .map(|step| Transfer { from: step % ACCOUNTS, to: (step * 5 + 1) % ACCOUNTS, amount: 10 * (step as i64 + 1) })
+        .collect()
+}
+
+pub fn apply(ledger: &mut [i64], transfer: &Transfer) {
+    ledger[transfer.from] -= transfer.amount;
+    ledger[transfer.to] += transfer.amount;
+}
+
+// The ledger after applying every transfer exactly once
+pub fn applied_once(mut ledger: Vec<i64>) -> Vec<i64> {
+    for transfer in transfers() {
+        apply(&mut ledger, &transfer);
+    }
+    ledger
+}
+
+// Three ways to apply the batch: at different speeds, and one that fails
+#[derive(Debug, Clone, Copy)]
+pub struct Strategy {
+    pub name: &'static str,
+    pause_every: usize,       // transfers between pauses of one tick
+    fails_at: Option<usize>,  // gives up before this transfer
+}
+
+pub const STRATEGIES: [Strategy; 3] = [
+    Strategy { name: "replica", pause_every: 1, fails_at: Some(1) },
+    Strategy { name: "recompute", pause_every: 1, fails_at: None },
+    Strategy { name: "cached", pause_every: 4, fails_at: None },
+];
+
+impl Strategy {
+    // Feed every transfer to `write`, stopping early if cancelled
+    fn run(&self, tick: Duration, token: &CancellationToken, mut write: impl FnMut(&Transfer)) -> Result<(), TaskError<String>> {
+        for (step, transfer) in transfers().iter().enumerate() {
+            token.check()?;
+            if step % self.pause_every == 0 {
+                thread::sleep(tick);
+            }
+            if self.fails_at == Some(step) {
+                return Err(TaskError::Failed(format!("{} unavailable", self.name)));
+            }
+            write(transfer);
+        }
+        Ok(())
+    }
+}
+
+// Every strategy writes straight into the shared ledger as it goes
+pub fn naive_race(ledger: &Mutex<Vec<i64>>, tick: Duration) -> Result<Won<(), String>, RaceError<String>> {
+    let alternatives = STRATEGIES
+        .iter()
+        .map(|strategy| {
+            let run: Alternative<'_, (), String> = Box::new(move |token: &CancellationToken| {
+                strategy.run(tick, token, |transfer| {
+                    apply(&mut ledger.lock().unwrap_or_else(|poisoned| poisoned.into_inner()), transfer)
+                })
+            });
+            (strategy.name, run)
+        })
+        .collect();
+    race(alternatives)
+}
+
+// Every strategy stages its writes on a copy of `base`; the winner's copy
+// is committed
+pub fn staged_race(store: &VersionedStore<Vec<i64>>, base: &Snapshot<'_, Vec<i64>>, tick: Duration) -> Result<Won<u64, String>, RaceError<String>> {
+    let alternatives = STRATEGIES
+        .iter()
+        .map(|strategy| {
+            let staged: Staged<'_, Vec<i64>, String> = Box::new(move |ledger: &Vec<i64>, token: &CancellationToken| {
+                let mut copy = ledger.clone();
+                strategy.run(tick, token, |transfer| apply(&mut copy, transfer))?;
+                Ok(copy)
+            });
+            (strategy.name, staged)
+        })
+        .collect();
+    race_commit(store, base, alternatives)
+}
@@ -0,0 +1,137 @@
+/*!
+ * Single-Producer, Single-Consumer Queue
+ *
+ * The queue embedded code puts between an interrupt handler and the main
+ * loop. A handler may not wait for a lock main holds - main cannot run
+ * until the handler returns - so it needs a queue it can push to without
+ * ever blocking, and the simplest one that exists: one producer, one
+ * consumer, a ring and two counters.
+ *
+ *   tail   written only by the producer: the next position to fill
+ *   head   written only by the consumer: the next position to empty
+ *
+ * With one writer per counter there is nothing to race for, so no
+ * compare-and-swap: the producer writes its slot, then publishes it by
+ * storing tail (Release); the consumer reads tail (Acquire), takes the
+ * slot, and hands it back by storing head (Release). A full ring refuses
+ * the push instead of waiting - what a handler does with the value then
+ * (count it, drop it) is its choice. So both ends are wait-free, not just
+ * lock-free: a push or pop is at most four atomic accesses, with no retry
+ * loop, however the other end is scheduled.
+ *
+ * Every one of those accesses goes through Steps. push and pop run them
+ * straight through; the spsc_schedules self-test check runs each as one
+ * tick of the simulated CPU and plays every interleaving of a producer
+ * and a consumer. That covers the order of the accesses, not weaker
+ * memory orderings. tests/loom_spsc.rs covers those: it builds this file
+ * against loom's AtomicU64, which may hand a Relaxed load any value the
+ * memory model allows. A Release on tail weakened to Relaxed fails
+ * there. Loom does not model load buffering, though, so the producer's
+ * Acquire on head (which keeps it from refilling a slot the consumer is
+ * still reading) is still only argued, not tested.
+ *
+ * spsc::channel returns the two ends. Neither is Clone, so "single
+ * producer, single consumer" is something the type system keeps, not a
+ * rule the caller has to remember. As in LockFreeQueue, values pack into
+ * an AtomicU64 (PackedValue) and positions are u64, so the queue needs
+ * no unsafe code and never wraps on a 32-bit target.
+ */
+
+use std::marker::PhantomData;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crate::atomic64::AtomicU64;
+use crate::atomic_store::PackedValue;
+
+struct Ring {
+    slots: Box<[AtomicU64]>,
+    head: AtomicU64,
+    tail: AtomicU64,
+}
+
+impl Ring {
+    fn slot(&self, position: u64) -> &AtomicU64 {
+        &self.slots[(position % self.slots.len() as u64) as usize]
+    }
+}
+
+// Runs each atomic access of a push or pop
+pub trait Steps {
+    fn step<R>(&mut self, access: impl FnOnce() -> R) -> R;
+}
+
+// Each access at once: what push and pop use
+pub struct Direct;
+
+impl Steps for Direct {
+    fn step<R>(&mut self, access: impl FnOnce() -> R) -> R {
+        access()
+    }
+}
+
+// The handler's end
+pub struct Producer<T: PackedValue> {
+    ring: Arc<Ring>,
+    _values: PhantomData<fn(T) -> T>,
+}
+
+// Main's end
+pub struct Consumer<T: PackedValue> {
+    ring: Arc<Ring>,
+    _values: PhantomData<fn(T) -> T>,
+}
+
+pub fn channel<T: PackedValue>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    assert!(capacity > 0, "a queue needs room for at least one value");
+    let ring = Arc::new(Ring {
+        slots: (0..capacity).map(|_| AtomicU64::new(0)).collect(),
+        head: AtomicU64::new(0),
+        tail: AtomicU64::new(0),
+    });
+    (Producer { ring: Arc::clone(&ring), _values: PhantomData }, Consumer { ring, _values: PhantomData })
+}
+
+impl<T: PackedValue> Producer<T> {
+    // Never waits: Err(value) when the ring is full
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        self.push_in(value, &mut Direct)
+    }
+
+    pub fn push_in(&mut self, value: T, steps: &mut impl Steps) -> Result<(), T> {
+        let ring = &self.ring;
+        let tail = steps.step(|| ring.tail.load(Ordering::Relaxed));  // ours alone
+        if tail - steps.step(|| ring.head.load(Ordering::Acquire)) == ring.slots.len() as u64 {
+            return Err(value);
+        }
+        steps.step(|| ring.slot(tail).store(value.to_bits(), Ordering::Relaxed));
+        steps.step(|| ring.tail.store(tail + 1, Ordering::Release));  // publishes the value
+        Ok(())
+    }
+}
+
+impl<T: PackedValue> Consumer<T> {
+    pub fn pop(&mut self) -> Option<T> {
+        self.pop_in(&mut Direct)
+    }
+
+    pub fn pop_in(&mut self, steps: &mut impl Steps) -> Option<T> {
+        let ring = &self.ring;
+        let head = steps.step(|| ring.head.load(Ordering::Relaxed));  // ours alone
+        if head == steps.step(|| ring.tail.load(Ordering::Acquire)) {
+            return None;
+        }
+        let value = T::from_bits(steps.step(|| ring.slot(head).load(Ordering::Relaxed)));
+        steps.step(|| ring.head.store(head + 1, Ordering::Release));  // hands the slot back
+        Some(value)
+    }
+
+    // Values waiting: exact while the producer is idle, a snapshot otherwise
+    pub fn len(&self) -> usize {
+        (self.ring.tail.load(Ordering::Acquire) - self.ring.head.load(Ordering::Relaxed)) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,42 @@
+/*!
+ * Standalone Demo Binaries
+ *
+ * memory_safe, option_safe and buffer_safe run on their own or as child
+ * processes of thread_safe. Either way they read the same SAFETY_DEMO_*
+ * settings, say their lines through the console and the event log as
+ * thread_safe's sections do, and run each demonstration in its own demo
+ * span, so SAFETY_DEMO_TRACE shows which one a line came from.
+ */
+
+use crate::console;
+use crate::env_config::EnvConfig;
+use crate::messages::{self, Message};
+use crate::trace;
+
+// Settings from the environment, with messages, tracing and the console
+// set up from them; a bad setting exits with status 2, as in thread_safe
+pub fn start() -> EnvConfig {
+    let config = EnvConfig::from_env().map_err(|error| error.to_string()).and_then(|config| {
+        messages::init(&config.locale, config.messages_dir.as_deref())?;
+        trace::init(config.trace.as_deref())?;
+        Ok(config)
+    });
+    match config {
+        Ok(config) => {
+            console::set_mode(config.output);
+            config
+        }
+        Err(error) => {
+            eprintln!("{}", messages::text(&Message::ConfigError { error }));
+            std::process::exit(2);
+        }
+    }
+}
+
+// Say the heading after a blank line, then run the demonstration in its span
+pub fn demonstrate(name: &str, heading: &str, demonstration: impl FnOnce()) {
+    say!();
+    say!("{}", heading);
+    let _span = trace::demo_span(name).entered();
+    demonstration();
+}
@@ -0,0 +1,48 @@
+/*!
+ * Compile-Time Configuration Checks
+ *
+ * Constants that configure the demos - buffer sizes, section names,
+ * config keys - have rules of their own. These validators are const fns,
+ * so a constant defined through them, or a `const _: () = assert!(...)`
+ * that calls them, is checked while the program compiles: a zero buffer
+ * size or a duplicated name fails the build (error E0080) instead of
+ * surfacing as a confusing result at run time.
+ */
+
+// `value`, if it is not zero
+pub const fn nonzero(value: usize) -> usize {
+    assert!(value > 0, "must not be zero");
+    value
+}
+
+// String equality for const contexts, where == on &str is not available
+pub const fn same_str(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+// True when no name appears twice
+pub const fn distinct(names: &[&str]) -> bool {
+    let mut i = 0;
+    while i < names.len() {
+        let mut j = i + 1;
+        while j < names.len() {
+            if same_str(names[i], names[j]) {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    true
+}
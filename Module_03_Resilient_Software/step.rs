@@ -0,0 +1,71 @@
+/*!
+ * Interactive Step-Through
+ *
+ * A demo's output scrolls past faster than anyone can narrate it. With
+ * SAFETY_DEMO_INTERACTIVE=on (or --interactive) the shared-state and
+ * channel demos stop at each phase - before their threads start, after
+ * they are joined - print the shared data as it stands, and wait for
+ * Enter:
+ *
+ *   Enter   continue to the next phase
+ *   c       continue without stopping again
+ *
+ * A pause first waits for the console writer to catch up, so everything
+ * said so far is on screen. Time spent waiting does not count against
+ * the demo's time budget. If stdin is closed, the run simply continues.
+ */
+
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::console;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+// Time spent waiting for Enter so far, and since when the current wait runs
+static PAUSED: Mutex<(Duration, Option<Instant>)> = Mutex::new((Duration::ZERO, None));
+
+pub fn enable(on: bool) {
+    ENABLED.store(on, Ordering::Relaxed);
+}
+
+// Total time spent waiting at pauses, including a wait in progress
+pub fn paused_time() -> Duration {
+    let paused = PAUSED.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    paused.0 + paused.1.map_or(Duration::ZERO, |since| since.elapsed())
+}
+
+fn set_waiting(waiting: bool) {
+    let mut paused = PAUSED.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    match (waiting, paused.1.take()) {
+        (true, _) => paused.1 = Some(Instant::now()),
+        (false, Some(since)) => paused.0 += since.elapsed(),
+        (false, None) => {}
+    }
+}
+
+// Stop before the next phase and show `state`, when interactive; call
+// only while none of the demo's threads are running
+pub fn pause(phase: &str, state: impl FnOnce() -> String) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    narrate!("step.phase", phase);
+    narrate!("step.state", state = state());
+    console::drain();
+    print!("{}", narration!("step.prompt"));
+    let _ = io::stdout().flush();
+
+    set_waiting(true);
+    let mut answer = String::new();
+    let read = io::stdin().lock().read_line(&mut answer);
+    set_waiting(false);
+    if !matches!(read, Ok(bytes) if bytes > 0) {
+        println!();
+        enable(false);  // stdin is closed: nobody to wait for
+    } else if answer.trim().eq_ignore_ascii_case("c") {
+        enable(false);
+    }
+}
@@ -0,0 +1,489 @@
+/*!
+ * Randomized Stress Mode
+ *
+ * The single-shot demonstrations run one interleaving and hide every
+ * timing-dependent behavior. `stress` runs quiet versions of the same
+ * workloads over and over with seeded random thread counts, sizes, and
+ * injected faults, checking invariants while the threads are still
+ * running, and summarizes every violation with what is needed to
+ * reproduce it (seed and iteration).
+ */
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use resilient_core::{SafeCounter, SharedData};
+
+use crate::event_log;
+use crate::fault::{self, FaultPlan};
+use crate::interleaving::{self, Schedule};
+use crate::progress::{Goal, Progress};
+use crate::rng::SeededRng;
+use crate::sim_cpu::Policy;
+use crate::snapshot_diff;
+use crate::term::{self, Severity};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Params {
+    pub threads: usize,
+    pub size: usize,  // steps per worker
+    pub seed: u64,    // for workloads that make their own random choices
+}
+
+// A workload returns one message per invariant violation it observed
+pub type Workload = fn(&Params, &FaultPlan) -> Vec<String>;
+
+pub const WORKLOADS: [(&str, Workload); 5] = [
+    ("counter_safety", stress_counter),
+    ("mutex_safety", stress_mutex),
+    ("rwlock_safety", stress_rwlock),
+    ("channel_safety", stress_channel),
+    ("atomic_operations", stress_atomic),
+];
+
+// Workloads with a deliberate race, on the simulated CPU. They fail by
+// design, so they only run when named with --demos.
+pub const SIMULATED: [(&str, Workload); 1] = [
+    ("lost_update", stress_lost_update),
+];
+
+// Steps each worker finishes before its planned panic (if any)
+fn completed_steps(params: &Params, plan: &FaultPlan) -> usize {
+    (0..params.threads)
+        .map(|worker| plan.panic_step(worker).unwrap_or(params.size))
+        .sum()
+}
+
+fn check(violations: &mut Vec<String>, invariant: &str, passed: bool, detail: String) {
+    if !event_log::check_invariant(invariant, passed) {
+        violations.push(format!("{}: {}", invariant, detail));
+    }
+}
+
+// Every completed increment is counted exactly once
+fn stress_counter(params: &Params, plan: &FaultPlan) -> Vec<String> {
+    let counter = Arc::new(SafeCounter::new());
+    let handles: Vec<_> = (0..params.threads)
+        .map(|worker| {
+            let (counter, plan, size) = (Arc::clone(&counter), plan.clone(), params.size);
+            thread::spawn(move || {
+                for step in 0..size {
+                    plan.at_step(worker, step);
+                    counter.increment();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        let _ = handle.join();  // Injected panics are expected
+    }
+
+    let expected = completed_steps(params, plan) as i32;
+    let actual = counter.get_count();
+    let mut violations = Vec::new();
+    check(&mut violations, "no lost increments", actual == expected,
+          format!("expected {}, counted {}", expected, actual));
+    violations
+}
+
+// sum == total of data at every moment a monitor can observe, even after
+// a writer panics while holding the lock (poisoning is recovered)
+fn stress_mutex(params: &Params, plan: &FaultPlan) -> Vec<String> {
+    let shared = Arc::new(Mutex::new(SharedData::new()));
+    let done = Arc::new(AtomicBool::new(false));
+
+    let monitor = {
+        let (shared, done) = (Arc::clone(&shared), Arc::clone(&done));
+        thread::spawn(move || {
+            let (mut torn, mut first_torn) = (0, None);
+            while !done.load(Ordering::SeqCst) {
+                let data = shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                if data.sum() != data.values().iter().sum::<i32>() {
+                    torn += 1;
+                    first_torn.get_or_insert_with(|| explain(&data));
+                }
+                drop(data);
+                thread::yield_now();
+            }
+            (torn, first_torn)
+        })
+    };
+
+    let writers: Vec<_> = (0..params.threads)
+        .map(|worker| {
+            let (shared, plan, size) = (Arc::clone(&shared), plan.clone(), params.size);
+            thread::spawn(move || {
+                for step in 0..size {
+                    let mut data = shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    plan.at_step(worker, step);  // A panic here poisons the lock
+                    data.add_value((step % 7) as i32);
+                }
+            })
+        })
+        .collect();
+    for writer in writers {
+        let _ = writer.join();
+    }
+    done.store(true, Ordering::SeqCst);
+    let (torn, first_torn) = monitor.join().unwrap_or((usize::MAX, None));
+
+    let data = shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let expected = completed_steps(params, plan);
+    let mut violations = Vec::new();
+    check(&mut violations, "sum matches data while running", torn == 0,
+          format!("{} inconsistent observations; the first differed from its data by: {}",
+                  torn, first_torn.unwrap_or_default()));
+    let total = data.values().iter().sum::<i32>();
+    // Diffing walks every value, so only for a violation
+    let detail = if data.sum() == total { String::new() } else { explain(&data) };
+    check(&mut violations, "sum matches data", data.sum() == total,
+          format!("sum {} vs data total {}; the state differs from its data by: {}", data.sum(), total, detail));
+    check(&mut violations, "no lost writes", data.len() == expected,
+          format!("expected {} values, found {}", expected, data.len()));
+    violations
+}
+
+// What an inconsistent SharedData has that the state its values imply
+// does not - "~ sum: 45 -> 44" for a sum that missed an update
+fn explain(observed: &SharedData) -> String {
+    snapshot_diff::diff(&snapshot_diff::implied(observed), observed).to_string()
+}
+
+// Readers only ever see a complete prefix 0, 1, 2, ... of the writer's values
+fn stress_rwlock(params: &Params, plan: &FaultPlan) -> Vec<String> {
+    let shared = Arc::new(RwLock::new(Vec::<usize>::new()));
+    let torn = Arc::new(AtomicUsize::new(0));
+
+    // Worker 0 writes; every other worker reads
+    let writer = {
+        let (shared, plan, size) = (Arc::clone(&shared), plan.clone(), params.size);
+        thread::spawn(move || {
+            for step in 0..size {
+                let mut data = shared.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+                plan.at_step(0, step);
+                data.push(step);
+            }
+        })
+    };
+    let readers: Vec<_> = (1..params.threads)
+        .map(|worker| {
+            let (shared, plan, torn, size) =
+                (Arc::clone(&shared), plan.clone(), Arc::clone(&torn), params.size);
+            thread::spawn(move || {
+                let mut last_len = 0;
+                for step in 0..size {
+                    plan.at_step(worker, step);
+                    let data = shared.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    let prefix = data.iter().enumerate().all(|(index, value)| index == *value);
+                    if !prefix || data.len() < last_len {
+                        torn.fetch_add(1, Ordering::SeqCst);
+                    }
+                    last_len = data.len();
+                }
+            })
+        })
+        .collect();
+    let _ = writer.join();
+    for reader in readers {
+        let _ = reader.join();
+    }
+
+    let data = shared.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let expected = plan.panic_step(0).unwrap_or(params.size);
+    let torn = torn.load(Ordering::SeqCst);
+    let mut violations = Vec::new();
+    check(&mut violations, "readers see consistent prefixes", torn == 0,
+          format!("{} torn reads", torn));
+    check(&mut violations, "no lost writes", data.len() == expected,
+          format!("expected {} values, found {}", expected, data.len()));
+    violations
+}
+
+// Each producer's messages arrive once, complete, and in order
+fn stress_channel(params: &Params, plan: &FaultPlan) -> Vec<String> {
+    let (sender, receiver) = mpsc::channel::<(usize, usize)>();
+    let producers: Vec<_> = (0..params.threads)
+        .map(|worker| {
+            let (sender, plan, size) = (sender.clone(), plan.clone(), params.size);
+            thread::spawn(move || {
+                for step in 0..size {
+                    plan.at_step(worker, step);
+                    let _ = sender.send((worker, step));
+                }
+            })
+        })
+        .collect();
+    drop(sender);  // Only the producers' clones keep the channel open
+
+    let mut next = vec![0; params.threads];
+    let mut out_of_order = 0;
+    for (worker, step) in receiver.iter() {
+        if step != next[worker] {
+            out_of_order += 1;
+        }
+        next[worker] = step + 1;
+    }
+    for producer in producers {
+        let _ = producer.join();
+    }
+
+    let received: usize = next.iter().sum();
+    let expected = completed_steps(params, plan);
+    let mut violations = Vec::new();
+    check(&mut violations, "messages arrive in order", out_of_order == 0,
+          format!("{} out-of-order messages", out_of_order));
+    check(&mut violations, "no lost messages", received == expected,
+          format!("expected {}, received {}", expected, received));
+    violations
+}
+
+// fetch_add hands out every ticket exactly once
+fn stress_atomic(params: &Params, plan: &FaultPlan) -> Vec<String> {
+    let total = params.threads * params.size;
+    let counter = Arc::new(AtomicUsize::new(0));
+    let seen: Arc<Vec<AtomicBool>> = Arc::new((0..total).map(|_| AtomicBool::new(false)).collect());
+    let duplicates = Arc::new(AtomicUsize::new(0));
+
+    let handles: Vec<_> = (0..params.threads)
+        .map(|worker| {
+            let (counter, seen, duplicates) =
+                (Arc::clone(&counter), Arc::clone(&seen), Arc::clone(&duplicates));
+            let (plan, size) = (plan.clone(), params.size);
+            thread::spawn(move || {
+                for step in 0..size {
+                    plan.at_step(worker, step);
+                    let ticket = counter.fetch_add(1, Ordering::SeqCst);
+                    if seen[ticket].swap(true, Ordering::SeqCst) {
+                        duplicates.fetch_add(1, Ordering::SeqCst);
+                    }
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        let _ = handle.join();
+    }
+
+    let issued = counter.load(Ordering::SeqCst);
+    let marked = seen.iter().filter(|flag| flag.load(Ordering::SeqCst)).count();
+    let duplicates = duplicates.load(Ordering::SeqCst);
+    let mut violations = Vec::new();
+    check(&mut violations, "tickets are unique", duplicates == 0 && marked == issued,
+          format!("{} duplicates, {} of {} tickets marked", duplicates, marked, issued));
+    check(&mut violations, "every step took a ticket", issued == completed_steps(params, plan),
+          format!("{} tickets for {} steps", issued, completed_steps(params, plan)));
+    violations
+}
+
+// The interleaving is chosen by the seeded scheduler, so the same params
+// give the same run - which is what makes a failure recordable
+fn stress_lost_update(params: &Params, _plan: &FaultPlan) -> Vec<String> {
+    let run = interleaving::lost_update(params.threads, params.size, Policy::Random(params.seed));
+    let mut violations = Vec::new();
+    check(&mut violations, "no lost updates", run.counted == run.expected,
+          format!("expected {}, counted {}", run.expected, run.counted));
+    violations
+}
+
+// Find workloads by exact name or unique prefix
+pub fn select_workloads(names: &str) -> Result<Vec<(&'static str, Workload)>, String> {
+    let mut selected = Vec::new();
+    for name in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        let matches: Vec<_> = WORKLOADS
+            .iter()
+            .chain(SIMULATED.iter())
+            .filter(|(workload, _)| *workload == name || workload.starts_with(name))
+            .collect();
+        match matches.as_slice() {
+            [workload] => selected.push(**workload),
+            [] => return Err(format!("no stress workload named '{}'", name)),
+            _ => return Err(format!("'{}' matches more than one workload", name)),
+        }
+    }
+    Ok(selected)
+}
+
+struct StressOptions {
+    duration: Duration,
+    iterations: Option<usize>,  // exact iteration count, for reproduction
+    seed: u64,
+    fault_rate: f64,
+    workloads: Vec<(&'static str, Workload)>,
+    record: Option<PathBuf>,  // where to save the shortest failing interleaving
+    replay: Option<PathBuf>,  // a saved interleaving to run instead
+}
+
+fn parse_options(args: &[String]) -> Result<StressOptions, String> {
+    let mut options = StressOptions {
+        duration: Duration::from_secs(10),
+        iterations: None,
+        seed: SeededRng::time_seed(),
+        fault_rate: 0.3,
+        workloads: WORKLOADS.to_vec(),
+        record: None,
+        replay: None,
+    };
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{} needs a value", flag))?;
+        let invalid = || format!("invalid value for {}: {}", flag, value);
+        match flag.as_str() {
+            "--duration" => {
+                options.duration = Duration::from_secs(value.parse().map_err(|_| invalid())?)
+            }
+            "--iterations" => options.iterations = Some(value.parse().map_err(|_| invalid())?),
+            "--seed" => options.seed = value.parse().map_err(|_| invalid())?,
+            "--fault-rate" => {
+                options.fault_rate = value.parse().map_err(|_| invalid())?;
+                if !(0.0..=1.0).contains(&options.fault_rate) {
+                    return Err(invalid());
+                }
+            }
+            "--demos" => options.workloads = select_workloads(value)?,
+            "--record" => options.record = Some(PathBuf::from(value)),
+            "--replay" => options.replay = Some(PathBuf::from(value)),
+            other => return Err(format!("unknown option {}", other)),
+        }
+    }
+
+    if options.workloads.is_empty() {
+        return Err("no workloads selected".to_string());
+    }
+    Ok(options)
+}
+
+// The next run: a workload with seeded random parameters and faults.
+// A panicking task would stall the simulated CPU, so no faults there.
+pub fn next_run(rng: &mut SeededRng, workloads: &[(&'static str, Workload)], fault_rate: f64)
+                -> (&'static str, Workload, Params, FaultPlan) {
+    let (name, workload) = workloads[rng.range(0, workloads.len() - 1)];
+    let params = Params {
+        threads: rng.range(2, 16),
+        size: rng.range(1, 2000),
+        seed: rng.next_u64(),
+    };
+    let plan = if is_simulated(name) {
+        FaultPlan::none()
+    } else {
+        FaultPlan::random(rng, params.threads, params.size, fault_rate)
+    };
+    (name, workload, params, plan)
+}
+
+fn is_simulated(name: &str) -> bool {
+    SIMULATED.iter().any(|(simulated, _)| *simulated == name)
+}
+
+#[derive(Default)]
+struct Tally {
+    runs: usize,
+    faults: usize,
+    violations: usize,
+}
+
+// `stress [--duration <s>] [--iterations <n>] [--seed <n>] [--fault-rate <p>] [--demos a,b]
+//  [--record <file>] [--replay <file>]`
+pub fn stress_command(args: &[String]) -> i32 {
+    let options = match parse_options(args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            eprintln!("{}", narration!("stress.usage"));
+            return 2;
+        }
+    };
+    if let Some(path) = &options.replay {
+        return interleaving::replay_schedule(path);
+    }
+
+    match options.iterations {
+        Some(count) => println!("{}", narration!("stress.heading_iterations", count, seed = options.seed)),
+        None => println!("{}", narration!("stress.heading_duration", seconds = options.duration.as_secs(), seed = options.seed)),
+    }
+
+    fault::install_quiet_panic_hook();
+    let mut rng = SeededRng::new(options.seed);
+    let mut tallies: HashMap<&str, Tally> = HashMap::new();
+    let mut reports = Vec::new();
+    let mut shortest: Option<Schedule> = None;
+    let start = Instant::now();
+    let mut iteration = 0;
+    let progress = Progress::start("stress", match options.iterations {
+        Some(count) => Goal::Steps(count as u64),
+        None => Goal::Time(options.duration),
+    });
+
+    loop {
+        let finished = match options.iterations {
+            Some(count) => iteration >= count,
+            None => start.elapsed() >= options.duration,
+        };
+        if finished {
+            break;
+        }
+
+        let (name, workload, params, plan) = next_run(&mut rng, &options.workloads, options.fault_rate);
+
+        event_log::demo_started(name);
+        progress.note(name);
+        let violations = workload(&params, &plan);
+
+        let tally = tallies.entry(name).or_default();
+        tally.runs += 1;
+        tally.faults += plan.faults().len();
+        tally.violations += violations.len();
+
+        // Rerun with the same seed to capture the failing interleaving
+        if is_simulated(name) && !violations.is_empty() && options.record.is_some() {
+            let run = interleaving::lost_update(params.threads, params.size, Policy::Random(params.seed));
+            if shortest.as_ref().is_none_or(|best| run.trace.len() < best.trace.len()) {
+                shortest = Some(Schedule {
+                    workload: name.to_string(),
+                    threads: params.threads,
+                    size: params.size,
+                    trace: run.trace,
+                });
+            }
+        }
+
+        for violation in violations {
+            let faults: Vec<String> = plan.faults().iter().map(|fault| fault.to_string()).collect();
+            reports.push(narration!("stress.violation", iteration, name, threads = params.threads, size = params.size,
+                                    faults = faults.join(", "), violation));
+        }
+        iteration += 1;
+        progress.tick();
+    }
+    progress.finish();
+
+    println!("{}", narration!("stress.header"));
+    for (name, _) in &options.workloads {
+        if let Some(tally) = tallies.get(name) {
+            println!("{:<20} {:>8} {:>8} {:>11}", name, tally.runs, tally.faults, tally.violations);
+        }
+    }
+
+    if let (Some(path), Some(schedule)) = (&options.record, &shortest) {
+        match interleaving::save(schedule, path) {
+            Ok(()) => println!("{}", narration!("stress.recorded", steps = schedule.trace.len(), path = path.display())),
+            Err(error) => eprintln!("{}", error),
+        }
+    }
+
+    if reports.is_empty() {
+        println!("\n{}", term::paint(Severity::Safe, &narration!("stress.no_violations", iterations = iteration)));
+        0
+    } else {
+        println!("\n{}", term::paint(Severity::Violation, &narration!("stress.violations", count = reports.len())));
+        for report in &reports {
+            println!("{}", term::paint(Severity::Violation, &format!("  {}", report)));
+        }
+        println!("{}", narration!("stress.reproduce", seed = options.seed, iterations = iteration));
+        1
+    }
+}
@@ -0,0 +1,152 @@
+/*!
+ * Structured Concurrency
+ *
+ * A TaskScope runs child tasks on scoped threads, so they may borrow local
+ * data, and waits for all of them before returning - no task outlives the
+ * scope. The children share a CancellationToken: the first child to fail
+ * (or panic) cancels it, siblings that check the token stop early, and
+ * the scope returns one error listing what failed and what was cancelled.
+ */
+
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, Scope, ScopedJoinHandle};
+
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    // For `token.check()?` at the points where a task may stop early
+    pub fn check<E>(&self) -> Result<(), TaskError<E>> {
+        if self.is_cancelled() {
+            Err(TaskError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TaskError<E> {
+    Failed(E),  // the task's own failure: cancels its siblings
+    Cancelled,  // the task stopped because a sibling failed
+}
+
+impl<E: fmt::Display> fmt::Display for TaskError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskError::Failed(error) => write!(f, "failed: {}", error),
+            TaskError::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+// Why the scope failed, task by task (spawn order)
+#[derive(Debug)]
+pub struct ScopeError<E> {
+    pub failed: Vec<(String, E)>,
+    pub panicked: Vec<String>,
+    pub cancelled: Vec<String>,
+    pub completed: Vec<String>,
+}
+
+impl<E: fmt::Display> fmt::Display for ScopeError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failed: Vec<String> = self.failed.iter().map(|(task, error)| format!("{} ({})", task, error)).collect();
+        write!(f, "failed: {}", failed.join(", "))?;
+        if !self.panicked.is_empty() {
+            write!(f, "; panicked: {}", self.panicked.join(", "))?;
+        }
+        let list = |tasks: &[String]| if tasks.is_empty() { "none".to_string() } else { tasks.join(", ") };
+        write!(f, "; cancelled: {}; completed: {}", list(&self.cancelled), list(&self.completed))
+    }
+}
+
+// Cancels the siblings if the task unwinds instead of returning
+struct CancelOnPanic<'a>(&'a CancellationToken);
+
+impl Drop for CancelOnPanic<'_> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.0.cancel();
+        }
+    }
+}
+
+type TaskHandle<'scope, T, E> = ScopedJoinHandle<'scope, Result<T, TaskError<E>>>;
+
+pub struct TaskScope<'scope, 'env: 'scope, T, E> {
+    scope: &'scope Scope<'scope, 'env>,
+    token: CancellationToken,
+    tasks: RefCell<Vec<(String, TaskHandle<'scope, T, E>)>>,
+}
+
+impl<'scope, 'env, T: Send + 'scope, E: Send + 'scope> TaskScope<'scope, 'env, T, E> {
+    pub fn spawn<F>(&self, name: &str, task: F)
+    where
+        F: FnOnce(&CancellationToken) -> Result<T, TaskError<E>> + Send + 'scope,
+    {
+        let token = self.token.clone();
+        let handle = self.scope.spawn(move || {
+            let _guard = CancelOnPanic(&token);
+            let result = task(&token);
+            if let Err(TaskError::Failed(_)) = &result {
+                token.cancel();
+            }
+            result
+        });
+        self.tasks.borrow_mut().push((name.to_string(), handle));
+    }
+
+    fn join(self) -> Result<Vec<T>, ScopeError<E>> {
+        let mut values = Vec::new();
+        let mut error = ScopeError { failed: Vec::new(), panicked: Vec::new(), cancelled: Vec::new(), completed: Vec::new() };
+        for (name, handle) in self.tasks.into_inner() {
+            match handle.join() {
+                Ok(Ok(value)) => {
+                    values.push(value);
+                    error.completed.push(name);
+                }
+                Ok(Err(TaskError::Failed(failure))) => error.failed.push((name, failure)),
+                Ok(Err(TaskError::Cancelled)) => error.cancelled.push(name),
+                Err(_) => error.panicked.push(name),
+            }
+        }
+        if error.failed.is_empty() && error.panicked.is_empty() {
+            Ok(values)
+        } else {
+            Err(error)
+        }
+    }
+}
+
+// Run `body`, which spawns the tasks, then wait for every task. Ok holds
+// each task's value in spawn order.
+pub fn task_scope<'env, T, E, F>(body: F) -> Result<Vec<T>, ScopeError<E>>
+where
+    T: Send + 'env,
+    E: Send + 'env,
+    F: for<'scope> FnOnce(&TaskScope<'scope, 'env, T, E>),
+{
+    thread::scope(|scope| {
+        let tasks = TaskScope { scope, token: CancellationToken::new(), tasks: RefCell::new(Vec::new()) };
+        body(&tasks);
+        tasks.join()
+    })
+}
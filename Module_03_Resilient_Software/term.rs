@@ -0,0 +1,65 @@
+/*!
+ * Terminal Presentation
+ *
+ * Colors a console line by what it reports, so the few lines that matter
+ * stand out in a long demo run:
+ *
+ *   Safe       green    an invariant held, a check passed
+ *   Recovered  yellow   an error was caught and handled (poisoned lock,
+ *                       supervisor restart)
+ *   Fault      magenta  a fault was injected on purpose
+ *   Violation  red      an invariant broke or was never checked
+ *
+ * Color is only added for a terminal that draws it (platform.rs): not
+ * when stdout is redirected, when TERM is "dumb", or on a Windows console
+ * without virtual terminal support - and never when NO_COLOR is set
+ * (https://no-color.org).
+ * Only the console gets the escape codes; the event log records the
+ * plain text.
+ */
+
+use std::env;
+use std::sync::OnceLock;
+
+use crate::platform::{self, Stream};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Safe,
+    Recovered,
+    Fault,
+    Violation,
+}
+
+impl Severity {
+    fn ansi(self) -> &'static str {
+        match self {
+            Severity::Safe => "32",
+            Severity::Recovered => "33",
+            Severity::Fault => "35",
+            Severity::Violation => "1;31",
+        }
+    }
+}
+
+pub fn color_enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| {
+        let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        !no_color && platform::ansi_terminal(Stream::Stdout)
+    })
+}
+
+// `text` wrapped in the severity's color when `color` is on
+pub fn styled(severity: Severity, text: &str, color: bool) -> String {
+    if color {
+        format!("\x1b[{}m{}\x1b[0m", severity.ansi(), text)
+    } else {
+        text.to_string()
+    }
+}
+
+// `text` as it should appear on this console
+pub fn paint(severity: Severity, text: &str) -> String {
+    styled(severity, text, color_enabled())
+}
@@ -0,0 +1,64 @@
+/*!
+ * 64-bit Atomic Fallback
+ *
+ * LockedU64 answers every operation exactly as AtomicU64 does, wrapping
+ * and failed exchanges included, and threads incrementing it at once
+ * lose no count. Where the target has no 64-bit atomics AtomicU64 is
+ * LockedU64 itself, so `cross test` still runs every operation there.
+ */
+
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+
+use safety_demo::atomic64::{AtomicU64, LockedU64};
+
+#[test]
+fn high_bits_survive_arithmetic() {
+    let value = AtomicU64::new(0);
+    value.fetch_add(u32::MAX as u64 + 1, Ordering::SeqCst);
+    assert_eq!(value.load(Ordering::SeqCst), 1 << 32);
+}
+
+#[test]
+fn every_operation_matches_the_native_type() {
+    let (native, locked) = (AtomicU64::new(u64::MAX - 1), LockedU64::new(u64::MAX - 1));
+    macro_rules! both {
+        ($($operation:ident($($argument:expr),*)),* $(,)?) => {$(
+            assert_eq!(
+                locked.$operation($($argument),*),
+                native.$operation($($argument),*),
+                "LockedU64::{}",
+                stringify!($operation)
+            );
+        )*};
+    }
+    both!(
+        fetch_add(3, Ordering::SeqCst),  // wraps past u64::MAX
+        fetch_or(1 << 40, Ordering::SeqCst),
+        fetch_max(7, Ordering::SeqCst),
+        swap(5, Ordering::SeqCst),
+        compare_exchange(4, 9, Ordering::SeqCst, Ordering::SeqCst),
+        compare_exchange(5, 9, Ordering::SeqCst, Ordering::SeqCst),
+        compare_exchange_weak(9, 2, Ordering::SeqCst, Ordering::SeqCst),
+        fetch_update(Ordering::SeqCst, Ordering::SeqCst, |value| value.checked_sub(3)),
+        fetch_update(Ordering::SeqCst, Ordering::SeqCst, |value| value.checked_sub(3)),
+        store(8, Ordering::SeqCst),
+        load(Ordering::SeqCst),
+    );
+}
+
+#[test]
+fn threads_incrementing_at_once_lose_no_count() {
+    let shared = Arc::new(LockedU64::default());
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || (0..1000).for_each(|_| { shared.fetch_add(1, Ordering::Relaxed); }))
+        })
+        .collect();
+    for handle in handles {
+        handle.join().expect("a LockedU64 incrementer panicked");
+    }
+    assert_eq!(shared.load(Ordering::SeqCst), 4000);
+}
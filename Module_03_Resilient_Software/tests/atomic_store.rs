@@ -0,0 +1,59 @@
+/*!
+ * AtomicStore Round Trips
+ *
+ * A type that packs into 64 bits is stored natively where the target has
+ * 64-bit atomics; a larger one falls back to a lock. Either way, values
+ * round-trip exactly, compare_exchange sees a stale value, and threads
+ * updating at once lose nothing.
+ */
+
+use std::thread;
+
+use safety_demo::atomic64;
+use safety_demo::atomic_store::AtomicStore;
+use safety_demo::sections::lock_free::{Point, Reading};
+
+#[test]
+fn a_point_round_trips_through_native_storage() {
+    let point = AtomicStore::new(Point { x: -3, y: 7 });
+    assert_eq!(point.is_lock_free(), atomic64::LOCK_FREE);
+    assert_eq!(point.load(), Point { x: -3, y: 7 });
+    assert!(point.compare_exchange(Point { x: 0, y: 0 }, Point { x: 1, y: 1 }).is_err(), "compare_exchange succeeded with a stale value");
+    assert_eq!(point.compare_exchange(Point { x: -3, y: 7 }, Point { x: i32::MIN, y: -1 }), Ok(Point { x: -3, y: 7 }));
+    assert_eq!(point.swap(Point { x: 0, y: 0 }), Point { x: i32::MIN, y: -1 }, "negative coordinates");
+}
+
+#[test]
+fn a_reading_too_large_for_an_atomic_is_locked_and_loses_no_update() {
+    let reading = AtomicStore::new(Reading { sequence: 0, samples: [0; 3] });
+    assert!(!reading.is_lock_free(), "a 32-byte Reading claims to be lock-free");
+    thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for _ in 0..1000 {
+                    reading.fetch_update(|r| Reading { sequence: r.sequence + 1, samples: [r.sequence + 1; 3] });
+                }
+            });
+        }
+    });
+    assert_eq!(reading.load(), Reading { sequence: 4000, samples: [4000; 3] });
+}
+
+#[test]
+fn bool_and_f64_round_trip() {
+    let flag = AtomicStore::new(false);
+    flag.store(true);
+    assert!(flag.load());
+    assert_eq!(AtomicStore::new(-0.5f64).load(), -0.5);
+}
+
+// compare_exchange compares as ==, not by bits: -0.0 matches 0.0 and
+// NaN matches nothing, as in the locked storage
+#[test]
+fn compare_exchange_on_floats_compares_as_eq() {
+    let zero = AtomicStore::new(-0.0f64);
+    assert!(zero.compare_exchange(0.0, 1.0).is_ok(), "compare_exchange(0.0) did not replace a stored -0.0");
+    assert_eq!(zero.load(), 1.0);
+    let nan = AtomicStore::new(f64::NAN);
+    assert!(nan.compare_exchange(f64::NAN, 1.0).is_err(), "compare_exchange(NaN) replaced a stored NaN");
+}
@@ -0,0 +1,45 @@
+/*!
+ * Bloom Filter Accuracy
+ *
+ * No false negatives, and the measured false-positive rate stays near
+ * the target, even when threads insert concurrently.
+ */
+
+use std::thread;
+
+use safety_demo::bloom_filter::BloomFilter;
+
+const TARGET: f64 = 0.01;
+
+// A filter sized for 4000 items at the target rate, with 0..4000 inserted
+// by four threads at once
+fn filled() -> BloomFilter {
+    let filter = BloomFilter::with_rate(4000, TARGET);
+    thread::scope(|scope| {
+        for worker in 0..4u64 {
+            let filter = &filter;
+            scope.spawn(move || {
+                for i in 0..1000 {
+                    filter.insert(&(worker * 1000 + i));
+                }
+            });
+        }
+    });
+    filter
+}
+
+#[test]
+fn concurrent_inserts_give_no_false_negatives() {
+    let filter = filled();
+    let missing: Vec<u64> = (0..4000u64).filter(|item| !filter.contains(item)).collect();
+    assert!(missing.is_empty(), "false negatives for inserted items {:?}", missing);
+}
+
+#[test]
+fn false_positive_rate_stays_near_the_target() {
+    let filter = filled();
+    let probes = 20_000u64;
+    let false_positives = (1_000_000..1_000_000 + probes).filter(|item| filter.contains(item)).count();
+    let rate = false_positives as f64 / probes as f64;
+    assert!(rate <= TARGET * 2.0, "false-positive rate {:.4} exceeds twice the target {}", rate, TARGET);
+}
@@ -0,0 +1,18 @@
+/*!
+ * Mistakes That Do Not Compile
+ *
+ * Each snippet in compile_fail/ is a misuse the types are meant to rule
+ * out: submitting to a draining pool, mixing units, an AtomicValue with
+ * no storage, a duplicate config key, and so on. Every one must fail to
+ * build against the safety_demo library, with the errors recorded in the
+ * .stderr file next to it. A snippet that compiles, or fails some other
+ * way, fails this test.
+ *
+ * After a deliberate change to a snippet or the types it misuses, rerun
+ * with TRYBUILD=overwrite and review the new .stderr files.
+ */
+
+#[test]
+fn misuses_do_not_compile() {
+    trybuild::TestCases::new().compile_fail("tests/compile_fail/*.rs");
+}
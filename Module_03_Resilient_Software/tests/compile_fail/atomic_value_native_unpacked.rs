@@ -0,0 +1,15 @@
+// Native storage holds only types that say how they pack into 64 bits.
+
+use safety_demo::atomic_store::{AtomicValue, Native};
+
+#[derive(Clone, Copy)]
+pub struct Point {
+    x: i32,
+    y: i32,
+}
+
+impl AtomicValue for Point {
+    type Storage = Native;
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+// An AtomicValue has to say where AtomicStore keeps it; there is no default.
+
+use safety_demo::atomic_store::AtomicValue;
+
+#[derive(Clone, Copy)]
+pub struct Point {
+    x: i32,
+    y: i32,
+}
+
+impl AtomicValue for Point {}
+
+fn main() {}
@@ -0,0 +1,6 @@
+// Cell<i32> is written through a shared reference without a lock, so a
+// struct holding one is not Sync: claiming that it is does not compile.
+
+safety_demo::assert_impl!(safety_demo::auto_traits::CellField: Sync);
+
+fn main() {}
@@ -0,0 +1,6 @@
+// PhantomData<fn() -> *const i32> marks a type without taking Send away:
+// claiming that it is not Send does not compile either.
+
+safety_demo::assert_not_impl!(safety_demo::auto_traits::PhantomFn: Send);
+
+fn main() {}
@@ -0,0 +1,17 @@
+// Only the crate implements the *Ext traits, so a plugin cannot replace the checks.
+
+use safety_demo::extensions::{ChecksumExt, Frame};
+
+struct Unchecked;
+
+impl ChecksumExt for Unchecked {
+    fn frame(&self, _payload: Vec<u8>) -> Frame {
+        unimplemented!()
+    }
+
+    fn open<'a>(&self, _frame: &'a Frame) -> Result<&'a [u8], safety_demo::extensions::Corrupted> {
+        unimplemented!()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+// `drain` consumes the Running pool, so the old handle cannot be kept
+// around to submit through.
+
+use safety_demo::phase::WorkerPool;
+
+pub fn reuse_running_pool() {
+    let pool = WorkerPool::<_, u32>::new().start();
+    let draining = pool.drain();
+    pool.submit(|| 1);
+    draining.finish();
+}
+
+fn main() {}
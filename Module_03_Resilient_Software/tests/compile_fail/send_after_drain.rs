@@ -0,0 +1,12 @@
+// A draining pipeline accepts no more items.
+
+use safety_demo::phase::Pipeline;
+
+pub fn send_after_drain() {
+    let pipeline = Pipeline::new().stage(|x: u32| x + 1).start();
+    pipeline.send(1);
+    let pipeline = pipeline.drain();
+    pipeline.send(2);
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+// A reference into the slab cannot outlive a removal; keep the Key instead.
+
+use safety_demo::slab::Slab;
+
+pub fn remove_while_borrowed() {
+    let mut names = Slab::new();
+    let first = names.insert("first".to_string());
+    let second = names.insert("second".to_string());
+    let first_ref = names.get(first).unwrap();
+    names.remove(second);
+    println!("{}", first_ref);
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+// Stages are wired up in Setup; a running pipeline cannot grow one.
+
+use safety_demo::phase::Pipeline;
+
+pub fn stage_while_running() {
+    let pipeline = Pipeline::new().stage(|x: u32| x + 1).start();
+    let pipeline = pipeline.stage(|x: u32| x * 2);
+    pipeline.send(1);
+}
+
+fn main() {}
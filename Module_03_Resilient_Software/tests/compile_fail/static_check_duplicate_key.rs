@@ -0,0 +1,7 @@
+// Two config keys with the same name would shadow each other.
+
+const KNOWN_KEYS: [&str; 3] = ["THREADS", "READS", "THREADS"];
+
+const _: () = assert!(safety_demo::static_check::distinct(&KNOWN_KEYS), "duplicate config key");
+
+fn main() {}
@@ -0,0 +1,5 @@
+// A buffer size of zero is rejected while compiling, not when first used.
+
+pub const TIMELINE_LIMIT: usize = safety_demo::static_check::nonzero(0);
+
+fn main() {}
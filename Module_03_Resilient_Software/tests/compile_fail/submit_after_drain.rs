@@ -0,0 +1,12 @@
+// Once draining starts the pool has no `submit` method at all.
+
+use safety_demo::phase::WorkerPool;
+
+pub fn submit_after_drain() {
+    let pool = WorkerPool::<_, u32>::new().workers(2).start();
+    pool.submit(|| 1);
+    let pool = pool.drain();
+    pool.submit(|| 2);
+}
+
+fn main() {}
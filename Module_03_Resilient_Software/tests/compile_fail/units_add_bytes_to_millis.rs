@@ -0,0 +1,11 @@
+// Quantities only add up when their units agree.
+
+use safety_demo::units::{Bytes, Millis, Quantity};
+
+pub fn add_bytes_to_millis() {
+    let budget: Quantity<u64, Millis> = Quantity::new(10_000);
+    let buffer: Quantity<u64, Bytes> = Quantity::new(4096);
+    let _total = budget + buffer;
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+// A bare number has no unit; it must be wrapped in the Quantity it means.
+
+use safety_demo::units::{Bytes, Quantity};
+
+fn reserve(_size: Quantity<u64, Bytes>) {}
+
+pub fn bare_number() {
+    reserve(4096);
+}
+
+fn main() {}
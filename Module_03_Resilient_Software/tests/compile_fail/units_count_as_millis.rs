@@ -0,0 +1,12 @@
+// A thread count cannot be passed where a timeout in milliseconds is expected.
+
+use safety_demo::units::{Count, Millis, Quantity};
+
+fn wait_for(_timeout: Quantity<u64, Millis>) {}
+
+pub fn count_as_millis() {
+    let threads: Quantity<u64, Count> = Quantity::new(8);
+    wait_for(threads);
+}
+
+fn main() {}
@@ -0,0 +1,57 @@
+/*!
+ * CRDT Convergence
+ *
+ * Merges commute and repeat harmlessly, and replicas converge over a
+ * network that loses, duplicates and reorders their states.
+ */
+
+use safety_demo::crdt::{self, Crdt, PNCounter, Replication};
+use safety_demo::network_sim::LinkFaults;
+
+const FAULTS: LinkFaults = LinkFaults { drop: 0.3, duplicate: 0.2, reorder: 0.3, min_delay: 1, max_delay: 3 };
+
+// Three replicas, each counting ten increments of its own
+fn replicate(seed: u64) -> Replication<PNCounter> {
+    crdt::replicate(vec![PNCounter::default(); 3], 10, FAULTS, seed, |node, _, replica| {
+        replica.increment(node, 1);
+    }, |replicas: &[PNCounter]| replicas.windows(2).all(|pair| pair[0] == pair[1]))
+}
+
+fn two_counters() -> (PNCounter, PNCounter) {
+    let (mut a, mut b) = (PNCounter::default(), PNCounter::default());
+    a.increment(0, 5);
+    b.increment(1, 3);
+    b.decrement(1, 1);
+    (a, b)
+}
+
+#[test]
+fn merge_is_commutative() {
+    let (a, b) = two_counters();
+    let (mut ab, mut ba) = (a.clone(), b.clone());
+    ab.merge(&b);
+    ba.merge(&a);
+    assert_eq!(ab, ba);
+}
+
+#[test]
+fn repeated_merges_change_nothing() {
+    let (mut merged, b) = two_counters();
+    merged.merge(&b);
+    let once = merged.clone();
+    merged.merge(&b);
+    merged.merge(&once);
+    assert_eq!(merged, once);
+    assert_eq!(merged.value(), 7);
+}
+
+#[test]
+fn replicas_converge_over_a_faulty_network() {
+    let values: Vec<i64> = replicate(5).replicas.iter().map(PNCounter::value).collect();
+    assert_eq!(values, [30, 30, 30]);
+}
+
+#[test]
+fn the_same_seed_gives_the_same_run() {
+    assert_eq!(replicate(5).stats, replicate(5).stats);
+}
@@ -0,0 +1,30 @@
+/*!
+ * The Library's Minimal Examples
+ *
+ * The circuit breaker and the demo registry are public types of the
+ * safety_demo library, like resilient_core's SafeCounter and RingBuffer,
+ * so their docs carry runnable examples too. The same check as
+ * resilient_core's api.rs: every public type has an example, and every
+ * public function is called in one.
+ */
+
+use std::fs;
+use std::path::Path;
+
+#[path = "../resilient_core/tests/doc_examples/mod.rs"]
+mod doc_examples;
+
+use doc_examples::missing_examples;
+
+#[test]
+fn every_public_item_has_an_example() {
+    let missing: Vec<String> = ["circuit_breaker.rs", "demo_report.rs"]
+        .iter()
+        .flat_map(|name| {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(name);
+            let source = fs::read_to_string(&path).unwrap_or_else(|error| panic!("cannot read {}: {}", path.display(), error));
+            missing_examples(name, &source)
+        })
+        .collect();
+    assert!(missing.is_empty(), "{}", missing.join("\n"));
+}
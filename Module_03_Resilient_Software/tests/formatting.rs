@@ -0,0 +1,64 @@
+/*!
+ * Display Output
+ *
+ * Display output is part of the interface: reports, logs and tests
+ * compare it as text, so it must not drift. A change to one of these
+ * strings fails here rather than showing up as a silently different
+ * report.
+ */
+
+use resilient_core::invariants::InvariantViolation;
+use safety_demo::demo_report::Verdict;
+use safety_demo::event_log::{Event, Record};
+use safety_demo::extensions::{BackendViolation, Corrupted, LimitStats};
+use safety_demo::network_sim::NetStats;
+use safety_demo::task_scope::TaskError;
+use safety_demo::term::{self, Severity};
+use safety_demo::units::{Bytes, Millis, Quantity};
+use safety_demo::versioned_store::Conflict;
+
+#[test]
+fn errors_say_what_went_wrong() {
+    assert_eq!(Corrupted { expected: 0xbeef, actual: 0xbeee }.to_string(), "checksum mismatch: frame says 0xbeef, payload gives 0xbeee");
+    assert_eq!(BackendViolation::Reordered { expected: 4, got: 6 }.to_string(), "expected item 4, got 6");
+    assert_eq!(Conflict { base: 3, latest: 5 }.to_string(), "built on version 3, but version 5 is already committed");
+    assert_eq!(TaskError::Failed("disk full").to_string(), "failed: disk full");
+    assert_eq!(TaskError::<String>::Cancelled.to_string(), "cancelled");
+    assert_eq!(
+        InvariantViolation { type_name: "SharedData", broken: vec!["sum matches data"] }.to_string(),
+        "SharedData broke: sum matches data"
+    );
+}
+
+#[test]
+fn statistics_list_their_counts() {
+    let stats = NetStats { sent: 5, delivered: 3, dropped: 1, partitioned: 1, duplicated: 2, reordered: 0 };
+    assert_eq!(stats.to_string(), "5 sent, 3 delivered, 1 dropped, 1 partitioned, 2 duplicated, 0 reordered");
+    assert_eq!(LimitStats { admitted: 7, refused: 13 }.to_string(), "7 admitted, 13 refused");
+}
+
+#[test]
+fn verdicts_and_quantities_honor_width_and_precision() {
+    assert_eq!(format!("[{:<12}]", Verdict::NotChecked), "[NOT CHECKED ]");
+    assert_eq!(Quantity::<u64, Bytes>::new(4096).to_string(), "4096 B");
+    assert_eq!(format!("{:.1}", Quantity::<f64, Millis>::new(2.34)), "2.3ms");
+}
+
+#[test]
+fn an_event_log_record_reads_as_one_line() {
+    let metric = Record {
+        run: 0,
+        seq: 0,
+        elapsed_ms: 12,
+        thread: "main".to_string(),
+        demo: String::new(),
+        event: Event::Metric { name: "busy".to_string(), value: 1.5, unit: "ms".to_string() },
+    };
+    assert_eq!(metric.to_string(), "[+12ms main] metric busy = 1.5 ms");
+}
+
+#[test]
+fn styling_adds_color_only_when_asked() {
+    assert_eq!(term::styled(Severity::Violation, "FAIL", true), "\x1b[1;31mFAIL\x1b[0m");
+    assert_eq!(term::styled(Severity::Fault, "InjectPanic", false), "InjectPanic");
+}
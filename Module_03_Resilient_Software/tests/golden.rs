@@ -0,0 +1,66 @@
+/*!
+ * Golden Output
+ *
+ * The normalizer takes out what varies between runs and nothing else,
+ * and every demo's output, run by the thread_safe binary, still matches
+ * its golden file. `thread_safe golden --bless <demo>` rewrites one after
+ * an intended change.
+ */
+
+use std::path::Path;
+
+use safety_demo::golden::{self, Golden};
+use safety_demo::sections;
+
+#[test]
+fn timings_thread_ids_and_addresses_are_normalized() {
+    let cases = [
+        ("Elapsed     76ms  16", "Elapsed [duration] 16"),
+        ("min 0.131ms, avg 2.5s (t=10s)", "min [duration], avg [duration] (t=[duration])"),
+        ("0      1000   10.0%    0.131  #########", "0      1000   10.0% [time] [bar]"),
+        ("ThreadId(12) at 0x7ffd5e8c3a10", "ThreadId([id]) at [address]"),
+        ("node address: 0x55d0c8a2b2c0, next ptr=0x0", "node address: [address], next ptr=[address]"),
+    ];
+    for (line, expected) in cases {
+        assert_eq!(golden::normalize(&[line.to_string()]).trim_end(), expected, "normalizing {:?}", line);
+    }
+}
+
+#[test]
+fn hex_not_labeled_an_address_is_kept() {
+    for line in [
+        "0x0102030405060708 as little-endian bytes: [8, 7, 6, 5, 4, 3, 2, 1]",
+        "frame says 0xbeef; m2 res-1f 10000 requests",
+        "format 0x7ffd5e8c3a10, data 0x7ffd5e8c3a10",
+    ] {
+        assert_eq!(golden::normalize(&[line.to_string()]).trim_end(), line);
+    }
+}
+
+#[test]
+fn threads_lines_are_grouped_and_ordered() {
+    let printed = [("main", "start"), ("b", "b1"), ("a", "a1"), ("b", "b2"), ("a", "a2"), ("main", "end")];
+    let printed = printed.map(|(thread, line)| (thread.to_string(), line.to_string())).to_vec();
+    assert_eq!(golden::canonical_order("main", printed), ["start", "a1", "a2", "b1", "b2", "end"]);
+}
+
+#[test]
+fn a_wildcard_matches_text_within_a_line() {
+    assert!(golden::line_matches("checked [..] loads, [..] torn", "checked 2 loads, 0 torn"));
+    assert!(!golden::line_matches("a[..]b", "ab c"));
+}
+
+#[test]
+fn every_demo_matches_its_golden_file() {
+    let exe = Path::new(env!("CARGO_BIN_EXE_thread_safe"));
+    let problems: Vec<String> = sections::registry()
+        .iter()
+        .filter_map(|demo| match golden::compare(exe, demo.name()) {
+            Ok(Golden::Matched) => None,
+            Ok(Golden::Missing) => Some(format!("{} has no golden file", demo.name())),
+            Ok(Golden::Differs(lines)) => Some(format!("{}:\n  {}", demo.name(), lines.join("\n  "))),
+            Err(error) => Some(format!("{}: {}", demo.name(), error)),
+        })
+        .collect();
+    assert!(problems.is_empty(), "{}", problems.join("\n"));
+}
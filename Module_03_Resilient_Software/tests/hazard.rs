@@ -0,0 +1,63 @@
+/*!
+ * Hazard Pointer Reclamation
+ *
+ * The Treiber stack pops in LIFO order and drops every value once:
+ * popped, left on the stack, or retired by a handle dropped first. With
+ * hazard pointers no pop reads a node after it was freed, with threads
+ * racing through a widened window.
+ */
+
+use std::sync::Arc;
+use std::thread;
+
+use safety_demo::hazard::{self, Reclaim, TreiberStack};
+
+#[test]
+fn pops_are_lifo_and_every_value_is_dropped_once() {
+    let tracker = Arc::new(());
+    {
+        let stack = TreiberStack::new(Reclaim::HazardPointers, 2);
+        let mut handle = stack.handle().expect("a handle on a new stack");
+        for i in 0..10 {
+            handle.push((i, Arc::clone(&tracker)));
+        }
+        let popped: Vec<i32> = (0..3).filter_map(|_| handle.pop()).map(|(i, _)| i).collect();
+        assert_eq!(popped, [9, 8, 7]);
+        let second = stack.handle().expect("a second handle on a stack with two slots");
+        assert!(stack.handle().is_none(), "a third handle on a stack with two slots");
+        drop((handle, second));
+    }
+    assert_eq!(Arc::strong_count(&tracker), 1, "values never dropped");
+}
+
+#[test]
+fn nodes_retired_by_finished_threads_are_freed() {
+    let tracker = Arc::new(());
+    {
+        let stack = TreiberStack::new(Reclaim::HazardPointers, 4);
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let mut handle = stack.handle().expect("four slots for four threads");
+                    for _ in 0..2000 {
+                        handle.push(Arc::clone(&tracker));
+                        handle.push(Arc::clone(&tracker));
+                        drop(handle.pop());
+                    }
+                });
+            }
+        });
+        // A handle that dropped while another still held a hazard left
+        // its nodes as orphans; a last handle, with no hazard up, frees them
+        drop(stack.handle());
+        assert_eq!(stack.reclaimed(), 4 * 2000, "popped nodes freed once the threads were done");
+    }
+    assert_eq!(Arc::strong_count(&tracker), 1, "values never dropped after the threaded run");
+}
+
+#[test]
+fn no_pop_reads_a_freed_node() {
+    let run = hazard::run(Reclaim::HazardPointers, 4, 2000);
+    assert_eq!(run.stale_reads, 0, "reads of freed nodes");
+    assert!(run.exactly_once(), "{} of {} items popped", run.popped.len(), run.pushed);
+}
@@ -0,0 +1,96 @@
+/*!
+ * Latency Histogram Accuracy
+ *
+ * Seeded runs of latencies spread over nanoseconds to minutes: every
+ * percentile is at or above the exact nearest-rank value and within 1/32
+ * of it, two histograms merged in either order equal one that saw every
+ * value, and threads recording at once lose no count.
+ */
+
+use std::thread;
+use std::time::Duration;
+
+use safety_demo::histogram::{Histogram, Snapshot};
+use safety_demo::rng::SeededRng;
+
+const SEEDS: u64 = 20;
+
+// Between 1 and 3000 latencies, each anywhere from nanoseconds to minutes
+fn latencies(seed: u64) -> Vec<Duration> {
+    let mut rng = SeededRng::new(seed);
+    let samples = rng.range(1, 3000);
+    (0..samples).map(|_| Duration::from_nanos(rng.next_u64() >> rng.range(24, 63))).collect()
+}
+
+// The exact nearest-rank percentile, from a sorted copy
+fn exact_percentile(values: &[Duration], p: f64) -> Duration {
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    let rank = ((p / 100.0 * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+    sorted[rank - 1]
+}
+
+fn recorded(values: &[Duration]) -> Histogram {
+    let histogram = Histogram::new();
+    values.iter().for_each(|&value| histogram.record(value));
+    histogram
+}
+
+#[test]
+fn an_empty_histogram_has_a_zero_median() {
+    assert_eq!(Snapshot::default().percentile(50.0), Duration::ZERO);
+}
+
+#[test]
+fn percentiles_are_within_a_thirty_second_above_exact() {
+    for seed in 0..SEEDS {
+        let values = latencies(seed);
+        let snapshot = recorded(&values).snapshot();
+        for p in [0.0, 1.0, 50.0, 90.0, 99.0, 99.9, 100.0] {
+            let exact = exact_percentile(&values, p);
+            let estimate = snapshot.percentile(p);
+            assert!(
+                estimate >= exact && estimate <= exact + exact / 32,
+                "seed {}: p{} of {} values is {:?}, exactly {:?}",
+                seed,
+                p,
+                values.len(),
+                estimate,
+                exact
+            );
+        }
+    }
+}
+
+#[test]
+fn merging_in_either_order_equals_one_histogram() {
+    for seed in 0..SEEDS {
+        let values = latencies(seed);
+        let (first, second) = (Histogram::new(), Histogram::new());
+        for (i, &value) in values.iter().enumerate() {
+            if i % 3 == 0 { first.record(value) } else { second.record(value) }
+        }
+        let (first, second) = (first.snapshot(), second.snapshot());
+        let whole = recorded(&values).snapshot();
+        let (mut forward, mut backward) = (first.clone(), second.clone());
+        forward.merge(&second);
+        backward.merge(&first);
+        assert_eq!(forward, whole, "seed {}: merged forward", seed);
+        assert_eq!(backward, whole, "seed {}: merged backward", seed);
+    }
+}
+
+#[test]
+fn threads_recording_at_once_lose_no_count() {
+    let shared = Histogram::new();
+    thread::scope(|scope| {
+        for worker in 1..=4u64 {
+            let shared = &shared;
+            scope.spawn(move || (0..10_000).for_each(|_| shared.record(Duration::from_micros(worker))));
+        }
+    });
+    let shared = shared.snapshot();
+    assert_eq!(shared.count(), 40_000);
+    assert_eq!(shared.total(), Duration::from_micros(100_000));
+    assert_eq!(shared.max(), Duration::from_micros(4));
+}
@@ -0,0 +1,84 @@
+/*!
+ * InlineString Against String
+ *
+ * Random edits to an InlineString and a String leave the same text,
+ * with ASCII and multi-byte characters, across the move to the heap;
+ * strings that fit allocate nothing. Allocations are counted the way
+ * thread_safe counts them, with TrackingAllocator as the global
+ * allocator.
+ */
+
+use std::sync::Mutex;
+
+use resilient_core::InlineString;
+use safety_demo::alloc_track::{self, TrackingAllocator};
+use safety_demo::rng::SeededRng;
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+// The allocation count is process-wide: one test at a time
+static SERIAL: Mutex<()> = Mutex::new(());
+
+const PIECES: [&str; 6] = ["a", "Cache", "é", "€uro", "😀", ""];
+
+#[test]
+fn random_edits_match_string() {
+    let _serial = SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut rng = SeededRng::new(681);
+    for round in 0..500 {
+        let (mut inline, mut string) = (InlineString::<8>::new(), String::new());
+        for _ in 0..rng.range(1, 30) {
+            let piece = PIECES[rng.range(0, PIECES.len() - 1)];
+            match rng.range(0, 4) {
+                0 => {
+                    inline.push_str(piece);
+                    string.push_str(piece);
+                }
+                1 => {
+                    let ch = piece.chars().next().unwrap_or('x');
+                    inline.push(ch);
+                    string.push(ch);
+                }
+                2 => assert_eq!(inline.pop(), string.pop(), "round {}: pop", round),
+                3 => {
+                    // Only to a char boundary: anywhere else both panic
+                    let mut cut = rng.range(0, string.len() + 1);
+                    while !string.is_char_boundary(cut.min(string.len())) {
+                        cut -= 1;
+                    }
+                    inline.truncate(cut);
+                    string.truncate(cut);
+                }
+                _ => {
+                    let mut copy = inline.clone();
+                    copy.clear();
+                    assert!(copy.is_empty(), "round {}: clear left {:?}", round, copy);
+                    assert_eq!(copy.as_str(), "", "round {}", round);
+                }
+            }
+            assert_eq!(inline.as_str(), string, "round {}", round);
+            assert_eq!(inline.len(), string.len(), "round {}", round);
+            assert!(inline.chars().eq(string.chars()), "round {}: chars of {:?}", round, inline);
+            assert!(!inline.is_inline() || inline.len() <= 8, "round {}: {} bytes held inline in 8", round, inline.len());
+        }
+        let other = InlineString::<8>::from(PIECES[rng.range(0, PIECES.len() - 1)]);
+        assert_eq!(inline.cmp(&other), string.as_str().cmp(other.as_str()), "round {}: {:?} and {:?}", round, inline, other);
+        assert_eq!(inline == other, string == other.as_str(), "round {}: {:?} and {:?}", round, inline, other);
+        assert_eq!(String::from(inline), string, "round {}: conversion back to String", round);
+    }
+}
+
+#[test]
+fn short_strings_allocate_nothing() {
+    let _serial = SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let before = alloc_track::allocations();
+    let mut names: Vec<InlineString<16>> = Vec::with_capacity(1000);
+    for index in 0..1000 {
+        names.push(InlineString::from(["Database", "FileSystem", "Network"][index % 3]));
+    }
+    // The test harness's own threads may allocate meanwhile
+    let allocated = alloc_track::allocations() - before;
+    assert!(allocated <= 100, "1000 short names made {} allocations", allocated);
+    assert!(names.iter().all(InlineString::is_inline));
+}
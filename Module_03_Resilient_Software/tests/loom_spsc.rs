@@ -0,0 +1,81 @@
+/*!
+ * The SPSC Ring Under Loom
+ *
+ * spsc_schedules in selftest plays every interleaving of the ring's
+ * atomic accesses, but each one sees every earlier write: sequential
+ * consistency. That cannot tell a correct Release/Acquire pair from a
+ * Relaxed one. Loom can: it models the C++11 memory model, so a Relaxed
+ * load may return any value the ordering rules still allow, and it tries
+ * each one. Weaken the Release on tail in push and `values_arrive_in_order`
+ * fails, because the consumer can see the new tail but the old slot.
+ * Loom does not model load buffering, so weakening the producer's
+ * Acquire on head passes here all the same.
+ *
+ * These tests compile spsc.rs itself, with loom's AtomicU64 in place of
+ * atomic64's. Only when built with the cfg set:
+ *
+ *   RUSTFLAGS="--cfg loom" cargo test --release --test loom_spsc
+ */
+
+#![cfg(loom)]
+
+// What spsc.rs and atomic_store.rs import from atomic64, as loom types
+mod atomic64 {
+    pub use loom::sync::atomic::AtomicU64;
+
+    pub const LOCK_FREE: bool = true;
+}
+
+#[allow(dead_code)]
+#[path = "../atomic_store.rs"]
+mod atomic_store;
+
+#[allow(dead_code)]
+#[path = "../spsc.rs"]
+mod spsc;
+
+use loom::thread;
+
+// A producer pushing 0, 1 and 2 through a one-slot ring, retrying while it
+// is full, and a consumer popping until it has all three. The ring wraps
+// twice; in every execution the values arrive once each, in order
+#[test]
+fn values_arrive_in_order() {
+    loom::model(|| {
+        let (mut producer, mut consumer) = spsc::channel::<u32>(1);
+        let produce = thread::spawn(move || {
+            for value in 0..3 {
+                while producer.push(value).is_err() {
+                    thread::yield_now();
+                }
+            }
+        });
+        let mut received = Vec::new();
+        while received.len() < 3 {
+            match consumer.pop() {
+                Some(value) => received.push(value),
+                None => thread::yield_now(),
+            }
+        }
+        produce.join().unwrap();
+        assert_eq!(received, [0, 1, 2]);
+        assert_eq!(consumer.pop(), None);
+    });
+}
+
+// Pushes that never retry against one pop: whatever the producer was told
+// was accepted is what the consumer gets, in order, and nothing else. A
+// refused push left nothing behind
+#[test]
+fn refused_pushes_leave_nothing() {
+    loom::model(|| {
+        let (mut producer, mut consumer) = spsc::channel::<u32>(2);
+        let produce = thread::spawn(move || (1..=3).filter(|&value| producer.push(value).is_ok()).collect::<Vec<u32>>());
+        let first = consumer.pop();
+        let accepted = produce.join().unwrap();
+        let rest = std::iter::from_fn(|| consumer.pop());
+        let received: Vec<u32> = first.into_iter().chain(rest).collect();
+        assert_eq!(received, accepted);
+        assert!(accepted.len() >= 2, "a two-slot ring refused a push while it had room: {:?}", accepted);
+    });
+}
@@ -0,0 +1,221 @@
+/*!
+ * Message Catalogs
+ *
+ * The demos' narration and the commands' output come from
+ * messages/<locale>.toml, by key; error values and invariant names are
+ * written in the code and not checked here. A key the code asks for and
+ * en-US lacks would print as the bare key, and a placeholder the call
+ * does not supply would print as written, so the narration calls in the
+ * sources are checked against en-US here, and every other catalog
+ * against en-US and the Message variants.
+ */
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use safety_demo::messages::{self, Arg, Catalog};
+
+const NARRATION_MACROS: [&str; 3] = ["narrate!(", "narrate_as!(", "narration!("];
+
+fn package_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+// A narration call in the sources: where it is, its key, and the names
+// of the arguments it passes
+struct Call {
+    place: String,
+    key: String,
+    args: BTreeSet<String>,
+}
+
+// The .rs files of the library and the binaries
+fn sources() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for dir in [package_dir().to_path_buf(), package_dir().join("sections")] {
+        for entry in fs::read_dir(&dir).unwrap_or_else(|error| panic!("cannot read {}: {}", dir.display(), error)) {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|extension| extension == "rs") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+// The text up to the parenthesis that closes the call, skipping string
+// and char literals
+fn call_body(text: &str) -> &str {
+    let bytes = text.as_bytes();
+    let mut depth = 1;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                i += 1;
+                while bytes[i] != b'"' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+            }
+            b'\'' if bytes.get(i + 2) == Some(&b'\'') => i += 2,
+            b'\'' if bytes.get(i + 1) == Some(&b'\\') => i += text[i + 1..].find('\'').unwrap() + 1,
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return &text[..i];
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    panic!("unclosed narration call: {}", &text[..text.len().min(80)]);
+}
+
+// The call's arguments, split at the commas outside brackets and strings
+fn arguments(body: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start, mut in_string, mut escaped) = (0, 0, false, false);
+    for (i, c) in body.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            _ if in_string => {}
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(body[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(body[start..].trim());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+fn calls() -> Vec<Call> {
+    let mut calls = Vec::new();
+    for path in sources() {
+        let source = fs::read_to_string(&path).unwrap();
+        let name = path.strip_prefix(package_dir()).unwrap().display().to_string();
+        let code: String = source
+            .lines()
+            .map(|line| if line.trim_start().starts_with("//") { "" } else { line })
+            .collect::<Vec<_>>()
+            .join("\n");
+        for pattern in NARRATION_MACROS {
+            for (start, _) in code.match_indices(pattern) {
+                let preceding = code[..start].chars().next_back();
+                if preceding.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+                    continue;  // narrate_as! seen as narrate!, or a longer name
+                }
+                let body = call_body(&code[start + pattern.len()..]);
+                let args = arguments(body);
+                let Some(index) = args.iter().position(|arg| arg.starts_with('"')) else {
+                    continue;  // the macros' own definitions pass the key through
+                };
+                let line = code[..start].lines().count().max(1);
+                calls.push(Call {
+                    place: format!("{}:{}", name, line),
+                    key: args[index].trim_matches('"').to_string(),
+                    args: args[index + 1..]
+                        .iter()
+                        .map(|arg| arg.split('=').next().unwrap().trim().to_string())
+                        .collect(),
+                });
+            }
+        }
+    }
+    calls
+}
+
+#[test]
+fn every_catalog_is_complete() {
+    let dir = package_dir().join("messages");
+    let mut problems = Vec::new();
+    let mut locales = Vec::new();
+    for entry in fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|extension| extension == "toml") {
+            locales.push(path.file_stem().unwrap().to_string_lossy().into_owned());
+        }
+    }
+    assert!(locales.iter().any(|locale| locale == messages::DEFAULT_LOCALE), "no en-US catalog in {}", dir.display());
+    for locale in &locales {
+        let catalog = Catalog::load(locale, Some(&dir)).unwrap();
+        problems.extend(catalog.problems().into_iter().map(|problem| format!("{}: {}", locale, problem)));
+    }
+    assert!(problems.is_empty(), "{}", problems.join("\n"));
+}
+
+#[test]
+fn every_narration_call_matches_its_template() {
+    let catalog = Catalog::en_us();
+    let calls = calls();
+    assert!(!calls.is_empty(), "found no narration calls");
+    let mut problems = Vec::new();
+    for call in &calls {
+        match catalog.placeholders(&call.key) {
+            None => problems.push(format!("{}: {} is not in en-US", call.place, call.key)),
+            Some(placeholders) => {
+                let placeholders: BTreeSet<String> = placeholders.into_iter().map(str::to_string).collect();
+                if placeholders != call.args {
+                    problems.push(format!("{}: {} has placeholders {:?} but is given {:?}", call.place, call.key, placeholders, call.args));
+                }
+            }
+        }
+    }
+    assert!(problems.is_empty(), "{}", problems.join("\n"));
+}
+
+#[test]
+fn every_narration_key_is_used() {
+    let used: BTreeSet<String> = calls().into_iter().map(|call| call.key).collect();
+    let catalog = Catalog::en_us();
+    let unused: Vec<&str> = catalog.keys().filter(|key| key.contains('.') && !used.contains(*key)).collect();
+    assert!(unused.is_empty(), "en-US keys no code uses: {}", unused.join(", "));
+}
+
+#[test]
+fn placeholders_take_format_specs() {
+    let catalog = Catalog::parse("xx-TEST", "[demo]\nline = \"{count:>5}|{name:<6}|{rate:.1}|{ids}|{tag:*^7}|{{literal}}|{missing}\"").unwrap();
+    let ids = vec![1, 2];
+    let args = [
+        ("count", Arg::Display(&42)),
+        ("name", Arg::Display(&"ab")),
+        ("rate", Arg::Display(&2.25)),
+        ("ids", Arg::Debug(&ids)),
+        ("tag", Arg::Display(&"mid")),
+    ];
+    assert_eq!(catalog.narration("demo.line", &args).unwrap(), "   42|ab    |2.2|[1, 2]|**mid**|{literal}|{missing}");
+    assert_eq!(catalog.narration("demo.other", &args), None);
+}
+
+#[test]
+fn a_catalog_outside_the_package_is_found_through_the_setting() {
+    let dir = std::env::temp_dir().join(format!("safety-demo-messages-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("xx-TEST.toml"), "unknown_command = \"Unbekannt: {command}\"\n").unwrap();
+
+    assert!(Catalog::load("xx-TEST", None).unwrap_err().contains("SAFETY_DEMO_MESSAGES_DIR"));
+    assert_eq!(messages::catalog_dir(Some(&dir)), Some(dir.clone()));
+    let output = Command::new(env!("CARGO_BIN_EXE_thread_safe"))
+        .arg("no-such-command")
+        .env("SAFETY_DEMO_LANG", "xx-TEST")
+        .env("SAFETY_DEMO_MESSAGES_DIR", &dir)
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr.contains("Unbekannt: no-such-command"), "{}", stderr);
+    assert!(stderr.contains("Commands:"), "untranslated keys fall back to en-US: {}", stderr);
+}
@@ -0,0 +1,30 @@
+/*!
+ * Async Middleware Walkthrough
+ *
+ * The circuit breaker layer walked through closed, open and half-open
+ * around a mock service. On a paused runtime the timeouts, backoffs and
+ * cooldown pauses fire without real waiting.
+ */
+
+use std::time::Duration;
+
+use safety_demo::async_demo;
+use safety_demo::circuit_breaker::BreakerState;
+use safety_demo::middleware::{self, CallError};
+
+#[test]
+fn breaker_walkthrough_opens_rejects_and_recovers() {
+    let runtime = async_demo::paused_runtime().expect("a paused runtime");
+    let steps = middleware::breaker_walkthrough(&runtime, Duration::from_secs(1));
+    let expected = [
+        (Ok(2), BreakerState::Closed, 1),
+        (Err(CallError::Failed("request 2 refused".to_string())), BreakerState::Open, 3),
+        (Err(CallError::Rejected), BreakerState::Open, 3),
+        (Err(CallError::TimedOut), BreakerState::Open, 4),
+        (Ok(10), BreakerState::Closed, 5),
+    ];
+    assert_eq!(steps.len(), expected.len(), "walkthrough steps");
+    for (step, (result, state, calls)) in steps.iter().zip(expected) {
+        assert_eq!((&step.result, step.state, step.service_calls), (&result, state, calls), "step '{}'", step.label);
+    }
+}
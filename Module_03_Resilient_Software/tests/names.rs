@@ -0,0 +1,56 @@
+/*!
+ * Interned Names
+ *
+ * Objects named from a handful of names allocate each name once when
+ * interned, and once per object as a String of their own; names nobody
+ * holds any more are cleaned out as new ones come in. Allocations are
+ * counted the way thread_safe counts them, with TrackingAllocator as
+ * the global allocator.
+ */
+
+use std::sync::{Arc, Mutex};
+
+use safety_demo::alloc_track::{self, TrackingAllocator};
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+// The allocation count and the intern table are process-wide: one test
+// at a time
+static SERIAL: Mutex<()> = Mutex::new(());
+
+const OBJECTS: usize = 100_000;
+const NAMES: [&str; 5] = ["Database", "FileSystem", "Network", "Cache", "Logger"];
+
+fn name(object: usize) -> &'static str {
+    NAMES[object % NAMES.len()]
+}
+
+#[test]
+fn interning_allocates_each_name_once() {
+    let _serial = SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut copies: Vec<String> = Vec::with_capacity(OBJECTS);
+    let mut shared: Vec<Arc<str>> = Vec::with_capacity(OBJECTS);
+
+    let before = alloc_track::allocations();
+    copies.extend((0..OBJECTS).map(|object| name(object).to_string()));
+    let copied = alloc_track::allocations() - before;
+    let before = alloc_track::allocations();
+    shared.extend((0..OBJECTS).map(|object| resilient_core::intern(name(object))));
+    let interned = alloc_track::allocations() - before;
+
+    // The test harness's own threads may allocate meanwhile
+    assert!(copied >= OBJECTS, "{} objects made only {} allocations as Strings", OBJECTS, copied);
+    assert!(interned <= NAMES.len() + 100, "{} objects made {} allocations interned", OBJECTS, interned);
+    assert!(Arc::ptr_eq(&shared[0], &shared[NAMES.len()]), "two objects with the same name hold separate copies");
+}
+
+#[test]
+fn unused_names_are_cleaned_out() {
+    let _serial = SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    for scratch in 0..1000 {
+        resilient_core::intern(&format!("scratch-{}", scratch));
+    }
+    let count = resilient_core::interned();
+    assert!(count <= 128, "{} names still interned after 1000 unused ones", count);
+}
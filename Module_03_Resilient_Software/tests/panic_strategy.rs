@@ -0,0 +1,43 @@
+/*!
+ * Sections Skipped Where Panics Abort
+ *
+ * A needs_unwind section is not run in a build whose panics abort. What
+ * it returns instead is a skipped Outcome: it checked nothing, so it
+ * does not pass, and verification does not count it as held - even
+ * though it has no failed verdict either. The skip is recorded in the
+ * event log, where the runner's report reads it.
+ */
+
+use safety_demo::demo_report::{Outcome, Verdict};
+use safety_demo::event_log::{self, Event};
+use safety_demo::panic_strategy;
+use safety_demo::verify::{self, Verified};
+
+fn held(name: &'static str) -> Verified {
+    let outcome = Outcome { name, verdicts: vec![("invariant", Verdict::Held)], skipped: false };
+    Verified { name, outcome: Ok(outcome) }
+}
+
+#[test]
+fn a_skipped_section_does_not_pass() {
+    let outcome = panic_strategy::skip("lazy_init");
+    assert!(outcome.skipped);
+    assert!(outcome.verdicts.is_empty());
+    assert!(!outcome.passed());
+}
+
+#[test]
+fn verification_fails_a_run_with_a_skipped_section() {
+    let skipped = Verified { name: "lazy_init", outcome: Ok(panic_strategy::skip("lazy_init")) };
+    assert_eq!(verify::exit_code(&[held("counter_safety")]), 0);
+    assert_eq!(verify::exit_code(&[held("counter_safety"), skipped]), verify::FAILED_EXIT_CODE);
+}
+
+#[test]
+fn the_event_log_records_the_skip() {
+    panic_strategy::skip("skipped_for_the_log");
+    let skipped = event_log::recent().into_iter().find(|record| {
+        matches!(&record.event, Event::DemoSkipped { name, .. } if name == "skipped_for_the_log")
+    });
+    assert!(skipped.is_some_and(|record| record.demo == "skipped_for_the_log"));
+}
@@ -0,0 +1,101 @@
+/*!
+ * ResourceCache Expiry and Eviction
+ *
+ * The cache reads time from the MockClock the test holds, so every step
+ * is exact and no test waits for real time to pass: an entry is found up
+ * to the last nanosecond before its ttl and gone at the ttl itself. Expiry
+ * is lazy, so a stale entry still counts in len() until a lookup evicts
+ * it. A full cache makes room by evicting the least recently used entry,
+ * where a lookup counts as a use.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use resilient_core::Resource;
+use safety_demo::clock::MockClock;
+use safety_demo::resource_cache::ResourceCache;
+
+const TTL: Duration = Duration::from_secs(30);
+
+fn cache(capacity: usize) -> (Arc<MockClock>, ResourceCache<Arc<MockClock>>) {
+    let clock = Arc::new(MockClock::new());
+    let cache = ResourceCache::new(Arc::clone(&clock), capacity, TTL);
+    (clock, cache)
+}
+
+fn resource(id: i32, name: &str) -> Resource {
+    Resource::new(id, name).unwrap()
+}
+
+fn name(found: Option<&Resource>) -> Option<&str> {
+    found.map(Resource::name)
+}
+
+#[test]
+fn entry_expires_exactly_at_ttl() {
+    let (clock, mut cache) = cache(2);
+    cache.insert("Database", resource(1, "Database"));
+
+    clock.advance(TTL - Duration::from_nanos(1));
+    assert_eq!(name(cache.get("Database")), Some("Database"));
+
+    clock.advance(Duration::from_nanos(1));
+    assert_eq!(name(cache.get("Database")), None);
+}
+
+// The ttl runs from the latest insert; a lookup does not extend it
+#[test]
+fn reinsert_restarts_the_ttl() {
+    let (clock, mut cache) = cache(2);
+    cache.insert("Database", resource(1, "Database"));
+    clock.advance(Duration::from_secs(20));
+    assert_eq!(name(cache.get("Database")), Some("Database"));
+    cache.insert("Network", resource(3, "Network"));
+
+    clock.advance(Duration::from_secs(10));
+    assert_eq!(name(cache.get("Database")), None);
+    assert_eq!(name(cache.get("Network")), Some("Network"));
+    cache.insert("Network", resource(3, "Network"));
+
+    clock.advance(Duration::from_secs(29));
+    assert_eq!(name(cache.get("Network")), Some("Network"));
+}
+
+#[test]
+fn expired_entries_count_until_a_lookup_evicts_them() {
+    let (clock, mut cache) = cache(3);
+    cache.insert("Database", resource(1, "Database"));
+    cache.insert("Network", resource(3, "Network"));
+    clock.advance(TTL);
+    assert_eq!(cache.len(), 2, "expiry alone evicts nothing");
+
+    assert_eq!(name(cache.get("Database")), None);
+    assert_eq!(cache.len(), 1);
+    assert_eq!(name(cache.get("Database")), None);
+    assert_eq!(cache.len(), 1, "a miss on an evicted entry changes nothing");
+
+    assert_eq!(name(cache.get("Network")), None);
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn full_cache_evicts_the_least_recently_used() {
+    let (_clock, mut cache) = cache(2);
+    assert_eq!(cache.insert("Database", resource(1, "Database")), None);
+    assert_eq!(cache.insert("Network", resource(3, "Network")), None);
+
+    // The lookup makes Database the most recently used
+    assert_eq!(name(cache.get("Database")), Some("Database"));
+    assert_eq!(cache.insert("FileSystem", resource(2, "FileSystem")), Some("Network".to_string()));
+    assert_eq!(name(cache.get("Network")), None);
+
+    // Database was used before FileSystem, so it goes next
+    assert_eq!(cache.insert("Cache", resource(4, "Cache")), Some("Database".to_string()));
+    assert_eq!(name(cache.get("FileSystem")), Some("FileSystem"));
+    assert_eq!(name(cache.get("Cache")), Some("Cache"));
+
+    // Replacing an entry that is already cached makes no room
+    assert_eq!(cache.insert("Cache", resource(4, "Cache")), None);
+    assert_eq!(cache.len(), 2);
+}
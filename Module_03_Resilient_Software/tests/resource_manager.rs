@@ -0,0 +1,125 @@
+/*!
+ * ResourceManager Teardown and Scoped Access
+ *
+ * Every resource here records its own drop in a shared log, so the tests
+ * see the order resources were actually closed in, not only the order the
+ * manager says it used. Network depends on FileSystem, which depends on
+ * Database, and they are declared out of order. Whichever way the manager
+ * goes away - shutdown(), a plain drop, or a failure partway through
+ * startup - what was opened is closed newest first.
+ *
+ * with_resource's drop guard is checked on both ways out of the closure:
+ * a normal return checks the resource back in, and a panic marks it
+ * Failed on its way to the caller.
+ */
+
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+
+use safety_demo::resource_manager::{AccessError, ResourceManager, ResourceManagerBuilder, SlotState, StartError};
+
+type DropLog = Rc<RefCell<Vec<String>>>;
+
+// Writes its name to the log when dropped
+struct Tracked {
+    name: String,
+    log: DropLog,
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.log.borrow_mut().push(self.name.clone());
+    }
+}
+
+fn opener(name: &str, log: &DropLog) -> impl FnOnce() -> Result<Tracked, String> + 'static {
+    let (name, log) = (name.to_string(), Rc::clone(log));
+    move || Ok(Tracked { name, log })
+}
+
+// Network -> FileSystem -> Database, declared newest first
+fn builder(log: &DropLog) -> ResourceManagerBuilder<Tracked> {
+    ResourceManager::builder()
+        .resource("Network", &["FileSystem"], opener("Network", log))
+        .resource("FileSystem", &["Database"], opener("FileSystem", log))
+        .resource("Database", &[], opener("Database", log))
+}
+
+#[test]
+fn shutdown_closes_in_reverse_startup_order() {
+    let log = DropLog::default();
+    let manager = builder(&log).start().unwrap();
+    assert_eq!(manager.startup_order(), ["Database", "FileSystem", "Network"]);
+    assert!(log.borrow().is_empty());
+
+    let teardown = manager.shutdown();
+    assert_eq!(teardown, ["Network", "FileSystem", "Database"]);
+    assert_eq!(*log.borrow(), teardown);
+}
+
+#[test]
+fn drop_closes_in_reverse_startup_order() {
+    let log = DropLog::default();
+    let manager = builder(&log).start().unwrap();
+    drop(manager);
+    assert_eq!(*log.borrow(), ["Network", "FileSystem", "Database"]);
+}
+
+// Network fails to open: the two already open are closed, newest first,
+// before start returns, and the error names them in that order
+#[test]
+fn failed_startup_closes_what_was_opened_in_reverse() {
+    let log = DropLog::default();
+    let result = ResourceManager::builder()
+        .resource("Network", &["FileSystem"], || Err::<Tracked, _>("connection refused".to_string()))
+        .resource("FileSystem", &["Database"], opener("FileSystem", &log))
+        .resource("Database", &[], opener("Database", &log))
+        .start();
+
+    let torn_down = vec!["FileSystem".to_string(), "Database".to_string()];
+    assert_eq!(
+        result.err(),
+        Some(StartError::Failed {
+            resource: "Network".to_string(),
+            reason: "connection refused".to_string(),
+            torn_down: torn_down.clone(),
+        })
+    );
+    assert_eq!(*log.borrow(), torn_down);
+}
+
+#[test]
+fn normal_return_checks_the_resource_back_in() {
+    let log = DropLog::default();
+    let manager = builder(&log).start().unwrap();
+    let inside = manager.with_resource("Database", |database| (database.name.clone(), manager.state("Database")));
+    assert_eq!(inside, Ok(("Database".to_string(), Some(SlotState::CheckedOut))));
+    assert_eq!(manager.state("Database"), Some(SlotState::Available));
+    assert_eq!(manager.with_resource("Database", |_| ()), Ok(()));
+}
+
+// The slot is checked out for the whole closure, so the same resource
+// cannot be lent out twice; the outer call still checks it back in
+#[test]
+fn nested_access_is_in_use() {
+    let log = DropLog::default();
+    let manager = builder(&log).start().unwrap();
+    let nested = manager.with_resource("Database", |_| manager.with_resource("Database", |_| ()));
+    assert_eq!(nested, Ok(Err(AccessError::InUse("Database".to_string()))));
+    assert_eq!(manager.state("Database"), Some(SlotState::Available));
+}
+
+#[test]
+fn panic_marks_the_resource_failed() {
+    let log = DropLog::default();
+    let manager = builder(&log).start().unwrap();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        manager.with_resource("Database", |_| panic!("left half-updated"))
+    }));
+    assert!(result.is_err(), "the panic reaches the caller");
+    assert_eq!(manager.state("Database"), Some(SlotState::Failed));
+    assert_eq!(manager.with_resource("Database", |_| ()), Err(AccessError::Failed("Database".to_string())));
+    assert_eq!(manager.state("FileSystem"), Some(SlotState::Available));
+    assert!(log.borrow().is_empty(), "nothing is closed until the manager goes");
+}
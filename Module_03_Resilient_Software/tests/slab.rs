@@ -0,0 +1,57 @@
+/*!
+ * Slab Keys
+ *
+ * Keys stay valid across removals, freed slots are reused, and a key to
+ * a freed slot never reaches the element that reused it.
+ */
+
+use safety_demo::slab::{Key, Slab};
+
+// A slab holding 0..6, and the key each value got
+fn filled() -> (Slab<usize>, Vec<Key>) {
+    let mut slab = Slab::new();
+    let keys = (0..6).map(|value| slab.insert(value)).collect();
+    (slab, keys)
+}
+
+#[test]
+fn removing_a_key_twice_returns_the_value_once() {
+    let (mut slab, keys) = filled();
+    assert_eq!(slab.remove(keys[2]), Some(2));
+    assert_eq!(slab.remove(keys[2]), None);
+}
+
+#[test]
+fn a_removal_leaves_the_other_keys_alone() {
+    let (mut slab, keys) = filled();
+    slab.remove(keys[2]);
+    for (value, &key) in keys.iter().enumerate().filter(|(value, _)| *value != 2) {
+        assert_eq!(slab.get(key), Some(&value));
+    }
+}
+
+#[test]
+fn a_freed_slot_is_reused_but_not_through_its_stale_key() {
+    let (mut slab, keys) = filled();
+    slab.remove(keys[2]);
+    let reused = slab.insert(20);
+    assert_eq!(slab.slots(), 6, "the freed slot was not reused");
+    assert!(!slab.contains(keys[2]), "the stale key found {:?}", slab.get(keys[2]));
+    assert_eq!(slab.get(reused), Some(&20));
+}
+
+#[test]
+fn updates_and_retain_go_through_the_keys() {
+    let (mut slab, keys) = filled();
+    if let Some(value) = slab.get_mut(keys[0]) {
+        *value = 10;
+    }
+    for (_, value) in slab.iter_mut() {
+        *value += 1;
+    }
+    slab.retain(|_, value| *value % 2 == 0);
+    let left: Vec<(Option<usize>, usize)> = slab.iter().map(|(key, &value)| (keys.iter().position(|&k| k == key), value)).collect();
+    assert_eq!(left, [(Some(1), 2), (Some(3), 4), (Some(5), 6)]);
+    assert_eq!(slab.len(), 3);
+    assert!(!slab.is_empty());
+}
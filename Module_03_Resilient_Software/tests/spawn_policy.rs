@@ -0,0 +1,40 @@
+/*!
+ * Spawn Fallbacks
+ *
+ * With spawns failing on purpose, `spawn` runs the work inline and hands
+ * back its result (or its panic) from join(), and `spawn_required`
+ * reports the failure. A whole section run with every first or second
+ * spawn failing still holds its invariants, falling back inline.
+ */
+
+use std::process::Command;
+
+use safety_demo::spawn_policy::{self, Worker};
+
+#[test]
+fn failed_spawns_run_inline_or_report_the_error() {
+    let _failing = spawn_policy::inject_failures(1);
+    let worker = spawn_policy::spawn(|| 6 * 7);
+    assert!(matches!(worker, Worker::Inline(_)), "a failed spawn did not run inline");
+    assert_eq!(worker.join().ok(), Some(42));
+    assert!(spawn_policy::spawn(|| panic!("inline work panicked")).join().is_err(), "an inline panic was lost");
+    assert!(spawn_policy::spawn_required(|| ()).is_err(), "spawn_required ran without a thread");
+}
+
+#[test]
+fn a_section_holds_with_spawns_failing() {
+    for every in ["1", "2"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_thread_safe"))
+            .args(["sandbox", "--child", "section", "counter_safety"])
+            .env("SAFETY_DEMO_FAIL_SPAWNS", every)
+            .env("SAFETY_DEMO_TICK_MS", "0")
+            .env_remove("SAFETY_DEMO_EVENT_LOG")
+            .output()
+            .expect("thread_safe runs");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let inline = stdout.lines().filter(|line| line.starts_with("[spawn]")).count();
+        assert!(output.status.success(), "failing every {} spawn(s): exit {}", every, output.status);
+        assert!(inline > 0, "failing every {} spawn(s): nothing ran inline", every);
+        assert!(stdout.contains("held") && !stdout.contains("violated"), "failing every {} spawn(s):\n{}", every, stdout);
+    }
+}
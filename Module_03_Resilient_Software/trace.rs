@@ -0,0 +1,99 @@
+/*!
+ * Tracing Diagnostics
+ *
+ * The console shows what a demo says, but not which thread said it or
+ * when. With SAFETY_DEMO_TRACE set to a filter - the EnvFilter syntax
+ * RUST_LOG uses, e.g. "info", "debug" or "demo=trace" - every event the
+ * event log records is also emitted through `tracing`, and written to
+ * stderr with a timestamp, the thread it came from and the spans it
+ * happened in:
+ *
+ *   demo{name=...}    one per demonstration, entered by its DemoRecorder
+ *                     (or by standalone::demonstrate in the other binaries)
+ *   thread{worker=N}  one per thread started through spawn_policy, inside
+ *                     the span of whoever spawned it
+ *
+ * Levels follow severity: console lines are INFO; passed invariant
+ * checks, metrics and messages DEBUG; reference counts TRACE; faults,
+ * worker errors, failed invariants and exceeded budgets WARN; panics
+ * ERROR. stdout does not change, so the batched console, `grade` and
+ * `replay` work as before. Without SAFETY_DEMO_TRACE no subscriber is
+ * installed and each event costs one check.
+ */
+
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tracing::{debug, dispatcher, error, info, info_span, trace, warn, Dispatch, Span};
+use tracing_subscriber::EnvFilter;
+
+use crate::event_log::Event;
+
+static WORKERS: AtomicUsize = AtomicUsize::new(0);
+
+// Reject a filter EnvFilter would not parse
+pub fn check_filter(filter: &str) -> Result<(), String> {
+    EnvFilter::try_new(filter).map(drop).map_err(|error| error.to_string())
+}
+
+// Send events passing `filter` to stderr for the rest of the run
+pub fn init(filter: Option<&str>) -> Result<(), String> {
+    let Some(filter) = filter else { return Ok(()) };
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_new(filter).map_err(|error| error.to_string())?)
+        .with_writer(io::stderr)
+        .with_thread_names(true)
+        .with_thread_ids(true)
+        .try_init()
+        .map_err(|error| error.to_string())
+}
+
+// One event log entry as a tracing event
+pub fn emit(event: &Event) {
+    match event {
+        Event::DemoStarted { name } => debug!(target: "demo", demo = %name, "demo started"),
+        Event::DemoSkipped { name, reason } => warn!(target: "demo", demo = %name, %reason, "demo skipped"),
+        Event::Output { line } => info!(target: "demo", "{}", line),
+        Event::InvariantChecked { invariant, passed: true } => debug!(target: "demo", %invariant, "invariant held"),
+        Event::InvariantChecked { invariant, passed: false } => warn!(target: "demo", %invariant, "invariant violated"),
+        Event::FaultInjected { fault } => warn!(target: "demo", %fault, "fault injected"),
+        Event::ThreadPanicked { message } => error!(target: "demo", %message, "thread panicked"),
+        Event::RefCount { name, change, strong } => trace!(target: "demo", %name, %change, strong, "reference count"),
+        Event::WorkerError { worker, error } => warn!(target: "demo", %worker, %error, "worker error"),
+        Event::Metric { name, value, unit } => debug!(target: "demo", %name, value, %unit, "metric"),
+        Event::BudgetExceeded { demo, budget_ms, hard } => warn!(target: "demo", %demo, budget_ms, hard, "time budget exceeded"),
+        Event::Message { process, action, peer, label, .. } => {
+            debug!(target: "demo", %process, ?action, %peer, %label, "message")
+        }
+    }
+}
+
+// The subscriber and span a new thread should carry on in
+#[derive(Clone)]
+pub struct ThreadContext {
+    dispatch: Dispatch,
+    span: Span,
+}
+
+impl ThreadContext {
+    // Call on the new thread
+    pub fn enter<T>(self, work: impl FnOnce() -> T) -> T {
+        dispatcher::with_default(&self.dispatch, || self.span.in_scope(work))
+    }
+}
+
+// The span a demonstration runs in
+pub fn demo_span(name: &str) -> Span {
+    info_span!(target: "demo", "demo", name)
+}
+
+// For a thread that carries on the current thread's work, in its span
+pub fn current_context() -> ThreadContext {
+    ThreadContext { dispatch: dispatcher::get_default(Dispatch::clone), span: Span::current() }
+}
+
+// For a worker thread, numbered in the order they were spawned
+pub fn worker_context() -> ThreadContext {
+    let worker = WORKERS.fetch_add(1, Ordering::Relaxed);
+    ThreadContext { dispatch: dispatcher::get_default(Dispatch::clone), span: info_span!(target: "demo", "thread", worker) }
+}
@@ -0,0 +1,100 @@
+/*!
+ * TrackedArc - an Arc whose reference count can be watched
+ *
+ * Behaves like Arc<T> (clone shares, deref reads, the value is freed with
+ * the last handle) but records every new/clone/drop with the resulting
+ * strong count in the event log and keeps the count's history and peak,
+ * so the count can be followed over a whole demo instead of sampled by
+ * hand.
+ */
+
+use std::fmt;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::event_log::{self, Event};
+
+#[derive(Debug)]
+struct Tracker {
+    name: String,
+    peak: AtomicUsize,
+    history: Mutex<Vec<usize>>,  // strong count after every change
+}
+
+impl Tracker {
+    fn note(&self, change: &str, strong: usize) {
+        self.peak.fetch_max(strong, Ordering::SeqCst);
+        self.history.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(strong);
+        event_log::record(Event::RefCount {
+            name: self.name.clone(),
+            change: change.to_string(),
+            strong,
+        });
+    }
+}
+
+pub struct TrackedArc<T> {
+    inner: Arc<T>,
+    tracker: Arc<Tracker>,
+}
+
+impl<T> TrackedArc<T> {
+    pub fn new(name: &str, value: T) -> Self {
+        let tracker = Arc::new(Tracker {
+            name: name.to_string(),
+            peak: AtomicUsize::new(0),
+            history: Mutex::new(Vec::new()),
+        });
+        tracker.note("new", 1);
+        TrackedArc { inner: Arc::new(value), tracker }
+    }
+
+    // Associated functions (like Arc's) so they never shadow T's methods
+    pub fn strong_count(this: &Self) -> usize {
+        Arc::strong_count(&this.inner)
+    }
+
+    pub fn peak(this: &Self) -> usize {
+        this.tracker.peak.load(Ordering::SeqCst)
+    }
+
+    pub fn history(this: &Self) -> Vec<usize> {
+        this.tracker.history.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+}
+
+impl<T> Clone for TrackedArc<T> {
+    fn clone(&self) -> Self {
+        let inner = Arc::clone(&self.inner);
+        // Other threads may clone or drop concurrently: the count is a snapshot
+        self.tracker.note("clone", Arc::strong_count(&inner));
+        TrackedArc { inner, tracker: Arc::clone(&self.tracker) }
+    }
+}
+
+impl<T> Drop for TrackedArc<T> {
+    fn drop(&mut self) {
+        // The count this handle leaves behind once its Arc is released
+        let remaining = Arc::strong_count(&self.inner) - 1;
+        self.tracker.note("drop", remaining);
+    }
+}
+
+impl<T> Deref for TrackedArc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for TrackedArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrackedArc")
+            .field("name", &self.tracker.name)
+            .field("strong", &Arc::strong_count(&self.inner))
+            .field("value", &*self.inner)
+            .finish()
+    }
+}
@@ -0,0 +1,121 @@
+/*!
+ * Unit-Safe Quantities
+ *
+ * A bare u64 cannot say whether it counts milliseconds, bytes or threads,
+ * so nothing stops `budget_ms + max_bytes`. Quantity<T, U> carries its
+ * unit as a phantom type parameter: it costs nothing at run time (the
+ * unit is a zero-sized marker that never exists as a value), yet adding
+ * Millis to Bytes, or passing a Count where Millis are expected, is a
+ * compile error. Arithmetic is defined only where the units agree; a
+ * quantity scales by a plain number of its own representation.
+ *
+ * The snippets in tests/compile_fail/units_*.rs are the mistakes this rules out.
+ */
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Add, AddAssign, Mul, Sub, SubAssign};
+use std::time::Duration;
+
+pub trait Unit {
+    const SUFFIX: &'static str;
+}
+
+// Unit markers: uninhabited, only ever used as type parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Bytes {}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Millis {}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Count {}
+
+impl Unit for Bytes {
+    const SUFFIX: &'static str = " B";
+}
+impl Unit for Millis {
+    const SUFFIX: &'static str = "ms";
+}
+impl Unit for Count {
+    const SUFFIX: &'static str = "";
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Quantity<T, U> {
+    value: T,
+    unit: PhantomData<U>,
+}
+
+impl<T, U> Quantity<T, U> {
+    pub const fn new(value: T) -> Self {
+        Quantity { value, unit: PhantomData }
+    }
+}
+
+impl<T: Copy, U> Quantity<T, U> {
+    pub fn get(self) -> T {
+        self.value
+    }
+}
+
+impl<T: Default, U> Default for Quantity<T, U> {
+    fn default() -> Self {
+        Quantity::new(T::default())
+    }
+}
+
+impl Quantity<u64, Millis> {
+    pub fn duration(self) -> Duration {
+        Duration::from_millis(self.value)
+    }
+}
+
+impl Quantity<f64, Millis> {
+    pub fn from_duration(duration: Duration) -> Self {
+        Quantity::new(duration.as_secs_f64() * 1000.0)
+    }
+}
+
+impl<T: Add<Output = T>, U> Add for Quantity<T, U> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Quantity::new(self.value + other.value)
+    }
+}
+
+impl<T: Sub<Output = T>, U> Sub for Quantity<T, U> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Quantity::new(self.value - other.value)
+    }
+}
+
+impl<T: AddAssign, U> AddAssign for Quantity<T, U> {
+    fn add_assign(&mut self, other: Self) {
+        self.value += other.value;
+    }
+}
+
+impl<T: SubAssign, U> SubAssign for Quantity<T, U> {
+    fn sub_assign(&mut self, other: Self) {
+        self.value -= other.value;
+    }
+}
+
+// Scaling: 3 ticks of 10ms are 30ms
+impl<T: Mul<Output = T>, U> Mul<T> for Quantity<T, U> {
+    type Output = Self;
+
+    fn mul(self, factor: T) -> Self {
+        Quantity::new(self.value * factor)
+    }
+}
+
+impl<T: fmt::Display, U: Unit> fmt::Display for Quantity<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Precision and width apply to the number
+        fmt::Display::fmt(&self.value, f)?;
+        f.write_str(U::SUFFIX)
+    }
+}
@@ -0,0 +1,148 @@
+/*!
+ * Vector Clocks
+ *
+ * Wall-clock timestamps from different threads cannot say whether one
+ * event could have influenced another. A vector clock can: each process
+ * counts its own events, every message carries the sender's counts, and
+ * the receiver merges them. Event a happened before event b exactly when
+ * a's clock is <= b's in every entry (and smaller in one); when neither
+ * is before the other they are concurrent - no message chain links them,
+ * whatever order they happened to print in.
+ *
+ * Message events are also written to the event log, and
+ * `replay --sequence` renders them as a Mermaid sequence diagram that
+ * notes which sends were concurrent.
+ */
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct VectorClock(Vec<u64>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Causality {
+    Before,
+    After,
+    Equal,
+    Concurrent,
+}
+
+impl VectorClock {
+    pub fn new(processes: usize) -> Self {
+        VectorClock(vec![0; processes])
+    }
+
+    pub fn counts(&self) -> &[u64] {
+        &self.0
+    }
+
+    // A local event (including a send) of `process`
+    pub fn tick(&mut self, process: usize) {
+        if process >= self.0.len() {
+            self.0.resize(process + 1, 0);
+        }
+        self.0[process] += 1;
+    }
+
+    // A receive: take the larger count for every process, then tick
+    pub fn receive(&mut self, process: usize, sender: &VectorClock) {
+        if sender.0.len() > self.0.len() {
+            self.0.resize(sender.0.len(), 0);
+        }
+        for (mine, theirs) in self.0.iter_mut().zip(&sender.0) {
+            *mine = (*mine).max(*theirs);
+        }
+        self.tick(process);
+    }
+
+    pub fn compare(&self, other: &VectorClock) -> Causality {
+        let len = self.0.len().max(other.0.len());
+        let entry = |clock: &VectorClock, index: usize| clock.0.get(index).copied().unwrap_or(0);
+        let (mut less, mut greater) = (false, false);
+        for index in 0..len {
+            match entry(self, index).cmp(&entry(other, index)) {
+                Ordering::Less => less = true,
+                Ordering::Greater => greater = true,
+                Ordering::Equal => {}
+            }
+        }
+        match (less, greater) {
+            (false, false) => Causality::Equal,
+            (true, false) => Causality::Before,
+            (false, true) => Causality::After,
+            (true, true) => Causality::Concurrent,
+        }
+    }
+}
+
+impl fmt::Display for VectorClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts: Vec<String> = self.0.iter().map(u64::to_string).collect();
+        write!(f, "[{}]", counts.join(","))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageAction {
+    Send,
+    Receive,
+}
+
+// One send or receive, as the message demos record it
+#[derive(Debug, Clone)]
+pub struct MessageEvent {
+    pub process: String,
+    pub action: MessageAction,
+    pub peer: String,  // receiver of a send, sender of a receive
+    pub label: String,
+    pub clock: VectorClock,
+}
+
+// Every pair of sends (by position in `events`) that are concurrent
+pub fn concurrent_sends(events: &[MessageEvent]) -> Vec<(usize, usize)> {
+    let sends: Vec<usize> = (0..events.len()).filter(|index| events[*index].action == MessageAction::Send).collect();
+    let mut pairs = Vec::new();
+    for (position, first) in sends.iter().enumerate() {
+        for second in &sends[position + 1..] {
+            if events[*first].clock.compare(&events[*second].clock) == Causality::Concurrent {
+                pairs.push((*first, *second));
+            }
+        }
+    }
+    pairs
+}
+
+// A Mermaid sequence diagram of the messages, in causal order, with each
+// arrow labelled by the send's clock and a note on concurrent sends
+pub fn sequence_diagram(events: &[MessageEvent]) -> String {
+    let mut diagram = vec!["sequenceDiagram".to_string()];
+    let mut participants: Vec<&str> = Vec::new();
+    for event in events {
+        for name in [event.process.as_str(), event.peer.as_str()] {
+            if !participants.contains(&name) {
+                participants.push(name);
+                diagram.push(format!("    participant {}", name));
+            }
+        }
+    }
+
+    // Any order consistent with happened-before: fewer total events first
+    let mut sends: Vec<&MessageEvent> = events.iter().filter(|event| event.action == MessageAction::Send).collect();
+    sends.sort_by_key(|event| event.clock.counts().iter().sum::<u64>());
+    for send in &sends {
+        diagram.push(format!("    {}->>{}: {} {}", send.process, send.peer, send.label, send.clock));
+    }
+    for (first, second) in concurrent_sends(events) {
+        let (first, second) = (&events[first], &events[second]);
+        diagram.push(format!(
+            "    Note over {},{}: {} || {} (concurrent)",
+            first.process, second.process, first.label, second.label
+        ));
+    }
+    diagram.join("\n")
+}
@@ -0,0 +1,107 @@
+/*!
+ * Verification Mode
+ *
+ * An autograder needs a pass/fail signal, not prose. With --verify (or
+ * SAFETY_DEMO_VERIFY=on) the full run shows every demonstration as
+ * usual, then goes through the invariants each one declared and ends
+ * with an exit code a script can test:
+ *
+ *   0   every declared invariant was checked and held
+ *   1   an invariant was violated or never checked, or a demo panicked
+ *       or was skipped
+ *   2   bad configuration or usage, as without --verify
+ *   3   a demo overran its hard time budget, as without --verify
+ *
+ * Every demo runs inside run_isolated, verifying or not, in a full run, a
+ * lecture or a quiz (sections::run_section): a panic in it
+ * (a student's closure that unwraps a None, say) is caught at that
+ * boundary and turned into a DemoPanic, the run says so and goes on with
+ * the next demo, so one broken demo does not hide the state of the
+ * others. Verifying, the DemoPanic is that demo's failure. Where panics
+ * abort there is nothing to catch, and run_section isolates each demo in
+ * a child process instead (panic_strategy.rs).
+ */
+
+use std::error::Error;
+use std::fmt;
+use std::panic::{self, UnwindSafe};
+
+use crate::demo_report::{Outcome, Verdict};
+use crate::event_log;
+use crate::messages::{self, Message};
+use crate::term::Severity;
+
+pub const FAILED_EXIT_CODE: i32 = 1;
+
+// A demo that panicked instead of returning (or, in a child process,
+// ended without reporting)
+#[derive(Debug, Clone, PartialEq)]
+pub struct DemoPanic {
+    pub demo: String,
+    pub message: String,  // the panic's payload, if it was a string
+}
+
+impl fmt::Display for DemoPanic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} panicked: {}", self.demo, self.message)
+    }
+}
+
+impl Error for DemoPanic {}
+
+// Run `demo`, turning a panic in it into a DemoPanic. The panic hook has
+// already reported it (and written a crash report) by the time this
+// returns. A caller whose closure captures shared state by reference
+// wraps it in AssertUnwindSafe: the demos keep no state that outlives
+// them, and a lock a panic poisons is recovered by its next user
+pub fn run_isolated<T>(demo: &str, f: impl FnOnce() -> T + UnwindSafe) -> Result<T, DemoPanic> {
+    panic::catch_unwind(f).map_err(|payload| DemoPanic { demo: demo.to_string(), message: event_log::panic_message(&*payload) })
+}
+
+// How one demo fared: its Outcome, or the panic that ended it first
+#[derive(Debug)]
+pub struct Verified {
+    pub name: &'static str,
+    pub outcome: Result<Outcome, DemoPanic>,
+}
+
+impl Verified {
+    pub fn passed(&self) -> bool {
+        self.outcome.as_ref().is_ok_and(Outcome::passed)
+    }
+}
+
+pub fn exit_code(results: &[Verified]) -> i32 {
+    if results.iter().all(Verified::passed) { 0 } else { FAILED_EXIT_CODE }
+}
+
+// Say every failure and a one-line result; returns the exit code
+pub fn report(results: &[Verified]) -> i32 {
+    say!("{}", messages::text(&Message::VerifyHeading {}));
+    for result in results.iter().filter(|result| !result.passed()) {
+        match &result.outcome {
+            Ok(outcome) if outcome.skipped => {
+                say_as!(Severity::Violation, "{}", messages::text(&Message::VerifySkipped { demo: result.name }));
+            }
+            Ok(outcome) => {
+                for (invariant, verdict) in outcome.verdicts.iter().filter(|(_, verdict)| *verdict != Verdict::Held) {
+                    let verdict = verdict.to_string();
+                    say_as!(Severity::Violation, "{}", messages::text(&Message::VerifyFailed { demo: result.name, invariant, verdict }));
+                }
+            }
+            Err(panicked) => {
+                let message = panicked.message.as_str();
+                say_as!(Severity::Violation, "{}", messages::text(&Message::VerifyPanicked { demo: result.name, message }));
+            }
+        }
+    }
+
+    let failed = results.iter().filter(|result| !result.passed()).count();
+    if failed == 0 {
+        let invariants = results.iter().filter_map(|result| result.outcome.as_ref().ok()).map(|outcome| outcome.verdicts.len()).sum();
+        say_as!(Severity::Safe, "{}", messages::text(&Message::VerifyPassed { demos: results.len(), invariants }));
+    } else {
+        say_as!(Severity::Violation, "{}", messages::text(&Message::VerifyNotPassed { failed, demos: results.len() }));
+    }
+    exit_code(results)
+}
@@ -0,0 +1,146 @@
+/*!
+ * VersionedStore - multi-version concurrency control in one process
+ *
+ * Writers never modify a value in place: each commit adds a new immutable
+ * version. A reader pins a version and keeps a consistent view of it for
+ * as long as it likes, no matter how many commits happen meanwhile.
+ * Commits are optimistic - a writer that started from an outdated version
+ * is rejected and retries - and versions that are neither the latest nor
+ * pinned are garbage collected.
+ */
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+#[derive(Debug)]
+struct Versions<T> {
+    values: BTreeMap<u64, Arc<T>>,
+    pins: BTreeMap<u64, usize>,  // version -> readers holding it
+    latest: u64,
+}
+
+impl<T> Versions<T> {
+    // Drop every version no one can reach any more
+    fn collect_garbage(&mut self) {
+        let (latest, pins) = (self.latest, &self.pins);
+        self.values.retain(|version, _| *version == latest || pins.contains_key(version));
+    }
+}
+
+#[derive(Debug)]
+pub struct VersionedStore<T> {
+    versions: Mutex<Versions<T>>,
+}
+
+// A pinned version: reads never change underneath it
+#[derive(Debug)]
+pub struct Snapshot<'a, T> {
+    store: &'a VersionedStore<T>,
+    version: u64,
+    value: Arc<T>,
+}
+
+// The commit lost the race: `latest` moved past the version it was built on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conflict {
+    pub base: u64,
+    pub latest: u64,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "built on version {}, but version {} is already committed", self.base, self.latest)
+    }
+}
+
+impl<T> VersionedStore<T> {
+    pub fn new(initial: T) -> Self {
+        VersionedStore {
+            versions: Mutex::new(Versions {
+                values: BTreeMap::from([(0, Arc::new(initial))]),
+                pins: BTreeMap::new(),
+                latest: 0,
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Versions<T>> {
+        self.versions.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn pin(&self) -> Snapshot<'_, T> {
+        let mut versions = self.lock();
+        let latest = versions.latest;
+        *versions.pins.entry(latest).or_default() += 1;
+        Snapshot {
+            store: self,
+            version: latest,
+            value: Arc::clone(&versions.values[&latest]),
+        }
+    }
+
+    // Add `value` as the next version, if nothing was committed since `base`
+    pub fn try_commit(&self, base: u64, value: T) -> Result<u64, Conflict> {
+        let mut versions = self.lock();
+        if versions.latest != base {
+            return Err(Conflict { base, latest: versions.latest });
+        }
+        let version = base + 1;
+        versions.values.insert(version, Arc::new(value));
+        versions.latest = version;
+        versions.collect_garbage();
+        Ok(version)
+    }
+
+    // Build the next version from the latest one, retrying on conflicts;
+    // returns the committed version and how many attempts were rejected
+    pub fn update(&self, mut build: impl FnMut(&T) -> T) -> (u64, usize) {
+        let mut conflicts = 0;
+        loop {
+            let snapshot = self.pin();
+            let next = build(&snapshot);
+            match self.try_commit(snapshot.version(), next) {
+                Ok(version) => return (version, conflicts),
+                Err(_) => conflicts += 1,
+            }
+        }
+    }
+
+    pub fn latest_version(&self) -> u64 {
+        self.lock().latest
+    }
+
+    // Versions still held in memory (the latest plus every pinned one)
+    pub fn live_versions(&self) -> Vec<u64> {
+        self.lock().values.keys().copied().collect()
+    }
+}
+
+impl<T> Snapshot<'_, T> {
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+}
+
+impl<T> Deref for Snapshot<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> Drop for Snapshot<'_, T> {
+    fn drop(&mut self) {
+        let mut versions = self.store.lock();
+        if let Some(count) = versions.pins.get_mut(&self.version) {
+            *count -= 1;
+            if *count == 0 {
+                versions.pins.remove(&self.version);
+            }
+        }
+        versions.collect_garbage();
+    }
+}
@@ -0,0 +1,140 @@
+/*!
+ * Work Stealing - per-worker deques and a stealing policy
+ *
+ * A pool with one shared queue makes every worker take its next task
+ * through the same lock. A work-stealing pool gives each worker a deque
+ * of its own instead:
+ *
+ *   owner   pushes and pops at the back - newest first, while its data
+ *           is still in cache - and mostly touches only its own lock
+ *   thief   a worker whose deque is empty takes from the front of
+ *           another's - the oldest task, the one its owner would reach
+ *           last
+ *
+ * When tasks take about as long as each other, nobody steals and the
+ * deques cost nothing extra. When one worker is dealt the long tasks, the
+ * others run dry and take over its backlog: the load balances itself,
+ * without a central queue deciding who gets what.
+ *
+ * Real runtimes (rayon, tokio) use lock-free Chase-Lev deques. A Mutex
+ * per deque keeps the policy visible; the owner and a thief only meet on
+ * the same lock when they go for the same deque.
+ */
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+
+// What an idle worker does when its own deque is empty
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StealPolicy {
+    Never,      // a static split: each worker runs what it was dealt
+    StealOne,   // take the oldest task of the first worker that has any
+    StealHalf,  // move the older half of its backlog over, in one trip
+}
+
+impl fmt::Display for StealPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            StealPolicy::Never => "never steal",
+            StealPolicy::StealOne => "steal one",
+            StealPolicy::StealHalf => "steal half",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// A task a worker took, and whose it was
+#[derive(Debug)]
+pub struct Taken<T> {
+    pub task: T,
+    pub dealt_to: usize,  // the worker it was dealt to
+    pub steal: bool,      // taking it was a trip to another worker's deque
+}
+
+// Tasks remember who they were dealt to, so one carried home by a
+// half-steal - or stolen again from there - is still counted as stolen
+#[derive(Debug)]
+pub struct WorkStealing<T> {
+    deques: Vec<Mutex<VecDeque<(usize, T)>>>,
+    policy: StealPolicy,
+}
+
+impl<T> WorkStealing<T> {
+    // One deque per worker, holding the tasks it was dealt
+    pub fn new(dealt: Vec<Vec<T>>, policy: StealPolicy) -> Self {
+        assert!(!dealt.is_empty(), "work stealing needs at least one worker");
+        let deques = dealt
+            .into_iter()
+            .enumerate()
+            .map(|(worker, tasks)| Mutex::new(tasks.into_iter().map(|task| (worker, task)).collect()))
+            .collect();
+        WorkStealing { deques, policy }
+    }
+
+    pub fn workers(&self) -> usize {
+        self.deques.len()
+    }
+
+    // A task only moves between deques whole, so a poisoned lock still
+    // guards a consistent deque
+    fn deque(&self, worker: usize) -> MutexGuard<'_, VecDeque<(usize, T)>> {
+        self.deques[worker].lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Tasks not yet taken, per worker
+    pub fn backlog(&self) -> Vec<usize> {
+        (0..self.workers()).map(|worker| self.deque(worker).len()).collect()
+    }
+
+    // `worker`'s next task: its own newest, or one stolen under the
+    // policy. None once there is nothing left to take. Tasks never add
+    // tasks, so an empty scan means the worker is done - though a thief
+    // still carrying half a backlog home may run it alone.
+    pub fn next(&self, worker: usize) -> Option<Taken<T>> {
+        if let Some((dealt_to, task)) = self.deque(worker).pop_back() {
+            return Some(Taken { task, dealt_to, steal: false });
+        }
+        if self.policy == StealPolicy::Never {
+            return None;
+        }
+        // Victims in turn, starting after ourselves, so thieves spread out
+        let workers = self.workers();
+        for victim in (1..workers).map(|offset| (worker + offset) % workers) {
+            let mut deque = self.deque(victim);
+            let Some((dealt_to, task)) = deque.pop_front() else { continue };
+            if self.policy == StealPolicy::StealHalf {
+                // Half of what is left comes along; never hold two locks at once
+                let half = deque.len() / 2;
+                let carried: Vec<(usize, T)> = deque.drain(..half).collect();
+                drop(deque);
+                self.deque(worker).extend(carried);
+            }
+            return Some(Taken { task, dealt_to, steal: true });
+        }
+        None
+    }
+}
+
+// What one worker did in a run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WorkerStats {
+    pub ran: usize,       // tasks it ran, wherever they came from
+    pub stolen: usize,    // of those, tasks dealt to another worker
+    pub steals: usize,    // trips to another deque that found work
+    pub busy_ticks: u64,  // how long its tasks took between them
+}
+
+impl WorkerStats {
+    pub fn record<T>(&mut self, worker: usize, taken: &Taken<T>, ticks: u64) {
+        self.ran += 1;
+        self.stolen += usize::from(taken.dealt_to != worker);
+        self.steals += usize::from(taken.steal);
+        self.busy_ticks += ticks;
+    }
+
+    // Tasks it was dealt and ran itself
+    pub fn own(&self) -> usize {
+        self.ran - self.stolen
+    }
+}